@@ -35,6 +35,10 @@ public class MainActivity extends AppCompatActivity {
     private static final int RESULT_SETTINGS = 1;
 
     private String appStatus = "initial";
+    // the action for content shared with the app, done when the page has loaded
+    private String pendingShare = null;
+    // the secrets for remotes, kept encrypted with a key in the Android Keystore
+    private SecretStore secrets;
     private final String APP_STATUS = "appState";
 
     @Override
@@ -53,7 +57,10 @@ public class MainActivity extends AppCompatActivity {
         Log.d(TAG, "making data...");
         final String optionsString = jsonOptions().toString();
         Log.d(TAG, "options as JSON: " + optionsString);
-        td = taiposwig.taiposwig.make_data(optionsString);
+        secrets = new SecretStore(getApplicationContext());
+        td = taiposwig.taiposwig.make_data_with_secrets(optionsString, secrets.allAsJson());
+        // tokens moved out of the Git configuration as the repository is opened
+        secrets.keepChanged(taiposwig.taiposwig.take_changed_secrets(td));
         Log.d(TAG, "data made.");
 
         mWebView = findViewById(R.id.webview);
@@ -62,7 +69,17 @@ public class MainActivity extends AppCompatActivity {
         webSettings.setJavaScriptEnabled(true);
         mWebView.addJavascriptInterface(new WebAppInterface(this), "taipo");
         //!!! add some code here to set platform-specific javascript (look at the PC version)
-        mWebView.setWebViewClient(new WebViewClient());
+        pendingShare = sharedAction(getIntent());
+        mWebView.setWebViewClient(new WebViewClient() {
+            @Override
+            public void onPageFinished(WebView view, String url) {
+                if (pendingShare != null) {
+                    Log.d(TAG, "capturing shared content...");
+                    view.evaluateJavascript("invoke(" + pendingShare + ");", null);
+                    pendingShare = null;
+                }
+            }
+        });
         Log.d(TAG, "web view set");
         // if (savedInstanceState != null) mWebView.restoreState(savedInstanceState);
         if (appStatus.equals("initial")) {
@@ -76,6 +93,29 @@ public class MainActivity extends AppCompatActivity {
         Log.d(TAG, "on create done");
     }
 
+    // the action for content shared with the app (eg a page shared from a browser), or null
+    String sharedAction(Intent intent) {
+        if (intent == null || !Intent.ACTION_SEND.equals(intent.getAction())) return null;
+        try {
+            JSONObject shared = new JSONObject();
+            String title = intent.getStringExtra(Intent.EXTRA_SUBJECT);
+            String text = intent.getStringExtra(Intent.EXTRA_TEXT);
+            shared.put("title", title == null ? "" : title);
+            shared.put("text", text == null ? "" : text);
+            shared.put("url", "");
+            JSONObject action = new JSONObject();
+            action.put("CaptureShared", shared);
+            JSONObject request = new JSONObject();
+            request.put("t", "");
+            request.put("i", "");
+            request.put("a", action);
+            return request.toString();
+        } catch (JSONException e) {
+            e.printStackTrace();
+            return null;
+        }
+    }
+
     void setInitialPreferencesIfRequired() {
         Log.d(TAG, "setting initial preferences...");
         SharedPreferences sp = PreferenceManager.getDefaultSharedPreferences(getBaseContext());
@@ -94,6 +134,13 @@ public class MainActivity extends AppCompatActivity {
             ed.putString("ssh_path", "id_rsa");
             ed.putBoolean("slurp_ssh", true);
             ed.putBoolean("auto_link", false);
+            ed.putBoolean("week_starts_sunday", false);
+            ed.putString("date_format", "%Y-%m-%d");
+            ed.putBoolean("twelve_hour", false);
+            ed.putString("decimal_separator", ".");
+            ed.putString("commit_template", "{verb} {ident}");
+            ed.putString("device_name", android.os.Build.MODEL);
+            ed.putBoolean("append_device", false);
             ed.apply();
             Log.d(TAG, "set initial preferences.");
         } else {
@@ -119,6 +166,13 @@ public class MainActivity extends AppCompatActivity {
             json.put("ssh_path", getApplicationContext().getFilesDir() + "/" + sp.getString("ssh_path", "??"));
             json.put("slurp_ssh", sp.getBoolean("slurp_ssh", true));
             json.put("auto_link", sp.getBoolean("auto_link", false));
+            json.put("week_starts_sunday", sp.getBoolean("week_starts_sunday", false));
+            json.put("date_format", sp.getString("date_format", "%Y-%m-%d"));
+            json.put("twelve_hour", sp.getBoolean("twelve_hour", false));
+            json.put("decimal_separator", sp.getString("decimal_separator", "."));
+            json.put("commit_template", sp.getString("commit_template", "{verb} {ident}"));
+            json.put("device_name", sp.getString("device_name", android.os.Build.MODEL));
+            json.put("append_device", sp.getBoolean("append_device", false));
             Log.d(TAG, "options set, prefix is " + sp.getString("unique_prefix", "??") + ", " + (
                     sp.getBoolean("git_have_url", false) ? "no url" : ("url is " + sp.getString("git_url", "??"))));
         } catch (JSONException e) {
@@ -150,6 +204,8 @@ public class MainActivity extends AppCompatActivity {
     protected void onPause() {
         Log.d(TAG, "pausing...");
         taiposwig.taiposwig.handle_event(td, CCycleEvent.Pause);
+        // the notifications for reminders are given while the app is not open
+        ReminderReceiver.schedule(getApplicationContext(), td);
         super.onPause();
     }
 
@@ -249,6 +305,7 @@ public class MainActivity extends AppCompatActivity {
         @JavascriptInterface
         public void execute(String body) {
             taiposwig.taiposwig.execute(td, body);
+            secrets.keepChanged(taiposwig.taiposwig.take_changed_secrets(td));
             Log.d(TAG, "execute done [from Java].");
             if (taiposwig.taiposwig.is_shutdown_required(td)) {
                 Log.d(TAG, "shutdown required! [from Java]");
@@ -20,7 +20,9 @@ public class PreferencesActivity extends PreferenceActivity {
     private final static String TAG = "fanling10:PreferencesActivity";
     private static final String[] keys = {
             "correct", "git_path", "git_branch", "git_has_url", "git_url", "git_name", "git_email",
-            "database_path", "unique_prefix", "ssh_path", "slurp_ssh", "auto_link"
+            "database_path", "unique_prefix", "ssh_path", "slurp_ssh", "auto_link",
+            "week_starts_sunday", "date_format", "twelve_hour", "decimal_separator",
+            "commit_template", "device_name", "append_device"
     };
     public final static int NOT_CHANGED_RESULT = RESULT_FIRST_USER;
     public final static int CHANGED_RESULT = RESULT_FIRST_USER + 1;
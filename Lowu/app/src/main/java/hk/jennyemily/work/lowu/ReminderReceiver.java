@@ -0,0 +1,79 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+package hk.jennyemily.work.lowu;
+
+import android.app.AlarmManager;
+import android.app.NotificationChannel;
+import android.app.NotificationManager;
+import android.app.PendingIntent;
+import android.content.BroadcastReceiver;
+import android.content.Context;
+import android.content.Intent;
+import android.content.SharedPreferences;
+import android.os.Build;
+import android.util.Log;
+
+import androidx.core.app.NotificationCompat;
+
+// gives the notifications for reminders, scheduled with the alarm manager
+// from those the engine says are next (see fetch_reminders)
+public class ReminderReceiver extends BroadcastReceiver {
+    private final static String TAG = "fanling10";
+    private static final String CHANNEL = "reminders";
+    private static final String EXTRA_IDENT = "ident";
+    private static final String EXTRA_TEXT = "text";
+    // the number of alarms scheduled, so that those no longer wanted can be cancelled
+    private static final String PREFERENCES = "reminders";
+    private static final String SCHEDULED = "scheduled";
+    // the most reminders scheduled at once
+    static final int MAX_REMINDERS = 20;
+
+    @Override
+    public void onReceive(Context context, Intent intent) {
+        String ident = intent.getStringExtra(EXTRA_IDENT);
+        String text = intent.getStringExtra(EXTRA_TEXT);
+        Log.d(TAG, "reminder for " + ident);
+        NotificationManager manager = (NotificationManager) context.getSystemService(Context.NOTIFICATION_SERVICE);
+        if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.O) {
+            manager.createNotificationChannel(new NotificationChannel(CHANNEL,
+                    context.getString(R.string.app_name), NotificationManager.IMPORTANCE_DEFAULT));
+        }
+        Intent open = new Intent(context, MainActivity.class);
+        open.setFlags(Intent.FLAG_ACTIVITY_NEW_TASK | Intent.FLAG_ACTIVITY_CLEAR_TOP);
+        NotificationCompat.Builder builder = new NotificationCompat.Builder(context, CHANNEL)
+                .setSmallIcon(R.mipmap.ic_launcher)
+                .setContentTitle(text)
+                .setContentText(ident)
+                .setAutoCancel(true)
+                .setContentIntent(PendingIntent.getActivity(context, 0, open, PendingIntent.FLAG_UPDATE_CURRENT));
+        manager.notify(ident == null ? 0 : ident.hashCode(), builder.build());
+    }
+
+    private static PendingIntent alarm(Context context, int n, String ident, String text) {
+        Intent intent = new Intent(context, ReminderReceiver.class);
+        if (ident != null) {
+            intent.putExtra(EXTRA_IDENT, ident);
+            intent.putExtra(EXTRA_TEXT, text);
+        }
+        return PendingIntent.getBroadcast(context, n, intent, PendingIntent.FLAG_UPDATE_CURRENT);
+    }
+
+    // schedule the next reminders from the engine, replacing those scheduled before
+    static void schedule(Context context, taiposwig.SWIGTYPE_p_LowuData td) {
+        AlarmManager alarms = (AlarmManager) context.getSystemService(Context.ALARM_SERVICE);
+        SharedPreferences prefs = context.getSharedPreferences(PREFERENCES, Context.MODE_PRIVATE);
+        int count = taiposwig.taiposwig.fetch_reminders(td, MAX_REMINDERS);
+        for (int n = 0; n < count; n++) {
+            long when = taiposwig.taiposwig.reminder_when(td, n) * 1000;
+            String ident = taiposwig.taiposwig.reminder_ident(td, n);
+            String text = taiposwig.taiposwig.reminder_text(td, n);
+            alarms.setAndAllowWhileIdle(AlarmManager.RTC_WAKEUP, when, alarm(context, n, ident, text));
+        }
+        for (int n = count; n < prefs.getInt(SCHEDULED, 0); n++) {
+            alarms.cancel(alarm(context, n, null, null));
+        }
+        prefs.edit().putInt(SCHEDULED, count).apply();
+        Log.d(TAG, "scheduled " + count + " reminders");
+    }
+}
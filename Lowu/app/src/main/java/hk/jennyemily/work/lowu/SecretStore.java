@@ -0,0 +1,113 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+package hk.jennyemily.work.lowu;
+
+import android.content.Context;
+import android.content.SharedPreferences;
+import android.security.keystore.KeyGenParameterSpec;
+import android.security.keystore.KeyProperties;
+import android.util.Base64;
+import android.util.Log;
+
+import org.json.JSONException;
+import org.json.JSONObject;
+
+import java.nio.charset.StandardCharsets;
+import java.security.GeneralSecurityException;
+import java.security.KeyStore;
+import java.util.Iterator;
+import java.util.Map;
+
+import javax.crypto.Cipher;
+import javax.crypto.KeyGenerator;
+import javax.crypto.SecretKey;
+import javax.crypto.spec.GCMParameterSpec;
+
+// keeps the secrets for remotes (such as tokens) in private preferences,
+// encrypted with a key kept in the Android Keystore
+class SecretStore {
+    private final static String TAG = "fanling10";
+    private static final String KEYSTORE = "AndroidKeyStore";
+    private static final String KEY_ALIAS = "fanling-secrets";
+    private static final String PREFERENCES = "secrets";
+    private static final String TRANSFORMATION = "AES/GCM/NoPadding";
+    private static final int TAG_BITS = 128;
+
+    private final SharedPreferences prefs;
+
+    SecretStore(Context context) {
+        prefs = context.getSharedPreferences(PREFERENCES, Context.MODE_PRIVATE);
+    }
+
+    // the key in the keystore, made the first time
+    private SecretKey key() throws GeneralSecurityException, java.io.IOException {
+        KeyStore keyStore = KeyStore.getInstance(KEYSTORE);
+        keyStore.load(null);
+        if (keyStore.containsAlias(KEY_ALIAS)) {
+            return ((KeyStore.SecretKeyEntry) keyStore.getEntry(KEY_ALIAS, null)).getSecretKey();
+        }
+        KeyGenerator generator = KeyGenerator.getInstance(KeyProperties.KEY_ALGORITHM_AES, KEYSTORE);
+        generator.init(new KeyGenParameterSpec.Builder(KEY_ALIAS,
+                KeyProperties.PURPOSE_ENCRYPT | KeyProperties.PURPOSE_DECRYPT)
+                .setBlockModes(KeyProperties.BLOCK_MODE_GCM)
+                .setEncryptionPaddings(KeyProperties.ENCRYPTION_PADDING_NONE)
+                .build());
+        return generator.generateKey();
+    }
+
+    // the secret encrypted, as the IV and the cipher text (both in base 64)
+    private String encrypt(String secret) throws Exception {
+        Cipher cipher = Cipher.getInstance(TRANSFORMATION);
+        cipher.init(Cipher.ENCRYPT_MODE, key());
+        byte[] sealed = cipher.doFinal(secret.getBytes(StandardCharsets.UTF_8));
+        return Base64.encodeToString(cipher.getIV(), Base64.NO_WRAP) + ":"
+                + Base64.encodeToString(sealed, Base64.NO_WRAP);
+    }
+
+    private String decrypt(String kept) throws Exception {
+        String[] parts = kept.split(":", 2);
+        Cipher cipher = Cipher.getInstance(TRANSFORMATION);
+        cipher.init(Cipher.DECRYPT_MODE, key(),
+                new GCMParameterSpec(TAG_BITS, Base64.decode(parts[0], Base64.NO_WRAP)));
+        return new String(cipher.doFinal(Base64.decode(parts[1], Base64.NO_WRAP)), StandardCharsets.UTF_8);
+    }
+
+    // all the secrets kept, as a JSON object from keys to secrets (for make_data_with_secrets)
+    String allAsJson() {
+        JSONObject json = new JSONObject();
+        for (Map.Entry<String, ?> entry : prefs.getAll().entrySet()) {
+            try {
+                json.put(entry.getKey(), decrypt((String) entry.getValue()));
+            } catch (Exception e) {
+                Log.e(TAG, "cannot read the secret for " + entry.getKey() + ": " + e);
+            }
+        }
+        return json.toString();
+    }
+
+    // keep the changes, a JSON object from keys to secrets, null for those to forget (from take_changed_secrets)
+    void keepChanged(String changedJson) {
+        try {
+            JSONObject changed = new JSONObject(changedJson);
+            if (changed.length() == 0) return;
+            SharedPreferences.Editor ed = prefs.edit();
+            for (Iterator<String> keys = changed.keys(); keys.hasNext(); ) {
+                String key = keys.next();
+                if (changed.isNull(key)) {
+                    ed.remove(key);
+                } else {
+                    try {
+                        ed.putString(key, encrypt(changed.getString(key)));
+                    } catch (Exception e) {
+                        Log.e(TAG, "cannot keep the secret for " + key + ": " + e);
+                    }
+                }
+            }
+            ed.apply();
+            Log.d(TAG, "kept " + changed.length() + " changed secrets");
+        } catch (JSONException e) {
+            e.printStackTrace();
+        }
+    }
+}
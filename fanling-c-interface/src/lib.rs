@@ -17,10 +17,23 @@ rust, which is converted by [`cbindgen`] into C.
 
 The life cycle of the interface is, in general:
 
-* `make_data`
+* `make_data` (or `make_data_with_secrets`, which is given the secrets
+for remotes that the app keeps safely, such as encrypted with a key in
+the Android Keystore; those changed are taken back with
+`take_changed_secrets` after each `execute`, so that tokens are not
+left in the Git configuration)
 * `execute` and `handle_event` (multiple calls, based on the user's
 gestures and the app's life cycle events) -- these return a response
-that needs to be followed by the main program
+that needs to be followed by the main program, and says how the
+repository stands against its remote (read with `response_sync_badge`,
+`response_sync_ahead`, `response_sync_behind`, `response_sync_conflicts`,
+`response_sync_failed_remotes` and `response_last_sync`)
+* `fetch_reminders` (such as when the app is paused) -- the
+notifications to schedule with the operating system, read with
+`reminder_when`, `reminder_ident` and `reminder_text`
+* `fetch_asset` (when the page asks for a URL that starts with
+[`fanling_interface::ASSET_BASE`], if the `serve_assets` option is set)
+-- read with `asset_content_type`, `asset_len` and `asset_bytes`
 * `delete_data` -- cleans up the data created in `make_data`
 
 */
@@ -28,14 +41,18 @@ that needs to be followed by the main program
 #![allow(non_snake_case)]
 extern crate libc;
 // use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::ffi::CStr;
 use std::ffi::CString;
 use std::ops::Deref;
 use std::os::raw::c_char;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
 //use taipo_git_control::RepoOptions;
 
-use fanling_engine::{taipo_git_control, EngineOptions, FanlingEngine, InterfaceType};
+use fanling_engine::{
+    taipo_git_control, CommitOptions, EngineOptions, FanlingEngine, FormatOptions, InterfaceType,
+};
 use fanling_interface::{CycleEvent, Engine};
 #[macro_use]
 extern crate log;
@@ -54,6 +71,12 @@ pub struct LowuData {
     last_response: fanling_interface::ResponseResult,
     last_key: CString,
     last_string: CString,
+    /** the notifications from the latest `fetch_reminders` */
+    reminders: Vec<fanling_interface::ScheduledReminder>,
+    /** the asset from the latest `fetch_asset` */
+    asset: Option<fanling_interface::Asset>,
+    /** the secrets for remotes kept for the app (if it gave them) */
+    secrets: Option<Arc<AppSecrets>>,
     //  canary: String, // for debug
 }
 
@@ -105,13 +128,173 @@ struct FanlingOptions {
     pub have_url: bool,
     pub branch: String,
     pub unique_prefix: String,
+    #[serde(default)]
+    pub ident_scheme: String,
     pub ssh_path: String,
     pub slurp_ssh: bool,
     pub auto_link: bool,
+    #[serde(default)]
+    pub week_starts_sunday: bool,
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    #[serde(default)]
+    pub twelve_hour: bool,
+    #[serde(default = "default_decimal_separator")]
+    pub decimal_separator: String,
+    #[serde(default = "default_locale")]
+    pub locale: String,
+    #[serde(default = "default_commit_template")]
+    pub commit_template: String,
+    #[serde(default)]
+    pub device_name: String,
+    #[serde(default)]
+    pub append_device: bool,
+    #[serde(default)]
+    pub generate_index: bool,
+    #[serde(default)]
+    pub signing_key: String,
+    #[serde(default)]
+    pub signing_format: String,
+    #[serde(default)]
+    pub allowed_signers: String,
+    #[serde(default)]
+    pub encrypt: bool,
+    #[serde(default)]
+    pub keep_history: bool,
+    #[serde(default)]
+    pub serve_assets: bool,
+    #[serde(default)]
+    pub sparse: Vec<String>,
+    #[serde(default)]
+    pub sparse_kinds: Vec<String>,
+    #[serde(default)]
+    pub read_only: bool,
+    #[serde(default)]
+    pub hooks: Vec<String>,
+    #[serde(default)]
+    pub caldav: String,
+    #[serde(default)]
+    pub carddav: String,
+    #[serde(default)]
+    pub dav_user: String,
+    #[serde(default)]
+    pub dav_password: String,
+    #[serde(default)]
+    pub dav_prefer: String,
+    #[serde(default)]
+    pub imap_server: String,
+    #[serde(default)]
+    pub imap_user: String,
+    #[serde(default)]
+    pub imap_password: String,
+    #[serde(default)]
+    pub imap_folder: String,
+    #[serde(default)]
+    pub math: String,
+}
+fn default_date_format() -> String {
+    "%Y-%m-%d".to_owned()
+}
+fn default_decimal_separator() -> String {
+    ".".to_owned()
+}
+fn default_locale() -> String {
+    "en".to_owned()
+}
+fn default_commit_template() -> String {
+    "{verb} {ident}".to_owned()
+}
+/** keeps the secrets for remotes for the app, which keeps them safely
+(such as encrypted with a key in the Android Keystore): it gives them
+to `make_data_with_secrets`, and takes those changed since with
+`take_changed_secrets` */
+#[derive(Default)]
+struct AppSecrets {
+    secrets: Mutex<BTreeMap<String, String>>,
+    /** the secrets changed since the app last took them (`None` if forgotten) */
+    changed: Mutex<BTreeMap<String, Option<String>>>,
+}
+impl AppSecrets {
+    /** the secrets changed since this was last called, as JSON (`null` for those forgotten) */
+    fn take_changed(&self) -> taipo_git_control::RepoResult<String> {
+        let changed = std::mem::take(&mut *locked(&self.changed)?);
+        Ok(serde_json::to_string(&changed).unwrap_or_else(|_| "{}".to_owned()))
+    }
+    /** keep the change for the app to take */
+    fn record(&self, key: &str, secret: Option<&str>) -> taipo_git_control::NullResult {
+        locked(&self.changed)?.insert(key.to_owned(), secret.map(str::to_owned));
+        Ok(())
+    }
+}
+/* the secrets are not shown in traces */
+impl std::fmt::Debug for AppSecrets {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AppSecrets")
+    }
+}
+/** the value inside the mutex */
+fn locked<T>(mutex: &Mutex<T>) -> taipo_git_control::RepoResult<MutexGuard<T>> {
+    mutex.lock().map_err(|_| "bad lock".to_owned().into())
+}
+impl taipo_git_control::CredentialProvider for AppSecrets {
+    fn name(&self) -> String {
+        "the keystore".to_owned()
+    }
+    fn get(&self, key: &str) -> taipo_git_control::RepoResult<Option<String>> {
+        Ok(locked(&self.secrets)?.get(key).cloned())
+    }
+    fn set(&self, key: &str, secret: &str) -> taipo_git_control::NullResult {
+        locked(&self.secrets)?.insert(key.to_owned(), secret.to_owned());
+        self.record(key, Some(secret))
+    }
+    fn delete(&self, key: &str) -> taipo_git_control::NullResult {
+        locked(&self.secrets)?.remove(key);
+        self.record(key, None)
+    }
+}
+
+#[no_mangle]
+/// creates the main data structure (see `make_data`), with the secrets for remotes that the app keeps, as a JSON object from keys to secrets. The app should call `take_changed_secrets` after this and after each `execute`, keeping the changes.
+pub unsafe extern "C" fn make_data_with_secrets(
+    fanling_options_json_c: *const c_char,
+    secrets_json_c: *const c_char,
+) -> *mut LowuData {
+    let secrets_json = string_from_c(secrets_json_c);
+    let secrets: BTreeMap<String, String> =
+        serde_json::from_str(&secrets_json).unwrap_or_else(|e| {
+            debug!("ignoring the secrets given, as they are not valid: {:?}", e);
+            BTreeMap::new()
+        });
+    let secrets = Arc::new(AppSecrets {
+        secrets: Mutex::new(secrets),
+        ..AppSecrets::default()
+    });
+    make_data_with(fanling_options_json_c, Some(secrets))
+}
+#[no_mangle]
+/// the secrets for remotes changed since `make_data_with_secrets` or the last call, as a JSON object from keys to secrets (`null` for those to forget), which the app should keep
+pub extern "C" fn take_changed_secrets(data: *mut LowuData) -> *const c_char {
+    let d = unsafe { data.as_mut().expect("bad pointer") };
+    let changed = match &d.secrets {
+        Some(secrets) => secrets.take_changed().unwrap_or_else(|e| {
+            debug!("could not take the changed secrets: {:?}", e);
+            "{}".to_owned()
+        }),
+        None => "{}".to_owned(),
+    };
+    d.last_string = string_to_cstring(changed);
+    d.last_string.as_ptr()
 }
 #[no_mangle]
 /// creates the main data structure. If you call this, you should call `delete_data` at the end of the program. Note that we initialise the android log; we can only do this once but this code is called more than once, and we have no easy way to check whether it has been called already, so we just ignore any error.
 pub unsafe extern "C" fn make_data(fanling_options_json_c: *const c_char) -> *mut LowuData {
+    make_data_with(fanling_options_json_c, None)
+}
+/** creates the main data structure, with the secrets for remotes kept for the app (if any) */
+unsafe fn make_data_with(
+    fanling_options_json_c: *const c_char,
+    secrets: Option<Arc<AppSecrets>>,
+) -> *mut LowuData {
     //  println!("making data...");
     #[cfg(target_os = "android")]
     match android_log::init("taipo") {
@@ -142,6 +325,26 @@ pub unsafe extern "C" fn make_data(fanling_options_json_c: *const c_char) -> *mu
             required_branch: Some(fanling_options.branch),
             ssh_path: PathBuf::from(fanling_options.ssh_path).into_boxed_path(),
             slurp_ssh: fanling_options.slurp_ssh,
+            signing: if fanling_options.signing_key.is_empty() {
+                None
+            } else {
+                Some(taipo_git_control::SigningKey {
+                    format: taipo_git_control::SigningFormat::parse(
+                        &fanling_options.signing_format,
+                    ),
+                    key: fanling_options.signing_key.clone(),
+                })
+            },
+            allowed_signers: if fanling_options.allowed_signers.is_empty() {
+                None
+            } else {
+                Some(PathBuf::from(&fanling_options.allowed_signers).into_boxed_path())
+            },
+            sparse: fanling_options.sparse.clone(),
+            credentials: secrets.clone().map(|s| {
+                let credentials: taipo_git_control::Credentials = s;
+                credentials
+            }),
             ..taipo_git_control::RepoOptions::default()
         },
         interface_type: InterfaceType::Android,
@@ -149,7 +352,62 @@ pub unsafe extern "C" fn make_data(fanling_options_json_c: *const c_char) -> *mu
             database_path: fanling_options.database_path,
         },
         uniq_pfx: fanling_options.unique_prefix,
+        ident_scheme: fanling_engine::IdentScheme::parse(&fanling_options.ident_scheme),
         auto_link: fanling_options.auto_link,
+        format_options: FormatOptions {
+            week_starts_monday: !fanling_options.week_starts_sunday,
+            date_format: fanling_options.date_format,
+            use_24_hour: !fanling_options.twelve_hour,
+            decimal_separator: fanling_options.decimal_separator.chars().next().unwrap_or('.'),
+            locale: fanling_options.locale,
+        },
+        commit_options: CommitOptions {
+            template: fanling_options.commit_template,
+            device_name: fanling_options.device_name,
+            append_device: fanling_options.append_device,
+            generate_index: fanling_options.generate_index,
+        },
+        repos: fanling_engine::ReposOptions::default(),
+        encryption: fanling_engine::EncryptionOptions {
+            enabled: fanling_options.encrypt,
+            passphrase: None,
+        },
+        keep_history: fanling_options.keep_history,
+        serve_assets: fanling_options.serve_assets,
+        sparse_kinds: fanling_options.sparse_kinds,
+        read_only: fanling_options.read_only,
+        plugins: vec![],
+        hooks: fanling_options
+            .hooks
+            .iter()
+            .filter_map(|spec| match fanling_engine::HookOptions::parse(spec) {
+                Ok(hook) => Some(hook),
+                Err(e) => {
+                    debug!("ignoring hook {}: {:?}", spec, e);
+                    None
+                }
+            })
+            .collect(),
+        dav: match fanling_engine::DavOptions::new(
+            &fanling_options.caldav,
+            &fanling_options.carddav,
+            &fanling_options.dav_user,
+            &fanling_options.dav_password,
+            &fanling_options.dav_prefer,
+        ) {
+            Ok(dav) => dav,
+            Err(e) => {
+                debug!("not syncing with CalDAV: {:?}", e);
+                None
+            }
+        },
+        mail: fanling_engine::MailOptions::new(
+            &fanling_options.imap_server,
+            &fanling_options.imap_user,
+            &fanling_options.imap_password,
+            &fanling_options.imap_folder,
+        ),
+        math: fanling_engine::MathRendering::parse(&fanling_options.math),
     };
     debug!("options as read {:#?}", engine_options);
     debug!("making data in rust...");
@@ -182,6 +440,9 @@ pub unsafe extern "C" fn make_data(fanling_options_json_c: *const c_char) -> *mu
         last_string: string_to_cstring(msg),
         last_key: string_to_cstring("".to_string()),
         last_response: fanling_interface::default_response_result(),
+        reminders: vec![],
+        asset: None,
+        secrets,
         // canary: "some lowu data".to_string(),
     }));
     debug!("data made in rust.");
@@ -203,7 +464,7 @@ fn string_from_c(s: *const c_char) -> String {
 pub extern "C" fn execute(data: *mut LowuData, body: *const c_char) {
     // let bs = CStr::from_ptr(body).to_string_lossy().into_owned();
     let bs = string_from_c(body);
-    debug!("executing {} [from rust-c]", bs);
+    debug!("executing {} [from rust-c]", fanling_engine::for_trace(&bs));
     let mut d = unsafe { data.as_mut().expect("bad pointer") };
     let result = panic::catch_unwind(AssertUnwindSafe(|| {
         match &mut d.engine {
@@ -227,6 +488,54 @@ pub extern "C" fn execute(data: *mut LowuData, body: *const c_char) {
     }
 }
 
+#[no_mangle]
+/// make an item from a single line of text, such as "buy milk #errands !friday"
+/// (for a quick-capture box or a share target)
+pub extern "C" fn quick_add(data: *mut LowuData, text: *const c_char) {
+    let ts = string_from_c(text);
+    debug!("quick add {} [from rust-c]", ts);
+    let d = unsafe { data.as_mut().expect("bad pointer") };
+    match &mut d.engine {
+        Some(e) => d.last_response = e.quick_add(&ts),
+        None => {
+            debug!("no engine!");
+        }
+    }
+}
+
+#[no_mangle]
+/// make an item from content shared with the app (any of which may be blank)
+pub extern "C" fn capture_shared(
+    data: *mut LowuData,
+    title: *const c_char,
+    text: *const c_char,
+    url: *const c_char,
+) {
+    let (title, text, url) = (string_from_c(title), string_from_c(text), string_from_c(url));
+    debug!("capturing shared {} [from rust-c]", url);
+    let d = unsafe { data.as_mut().expect("bad pointer") };
+    match &mut d.engine {
+        Some(e) => d.last_response = e.capture_shared(&title, &text, &url),
+        None => {
+            debug!("no engine!");
+        }
+    }
+}
+
+#[no_mangle]
+/// make an item from an e-mail message (the raw RFC 822 text)
+pub extern "C" fn capture_mail(data: *mut LowuData, message: *const c_char) {
+    let message = string_from_c(message);
+    debug!("capturing mail [from rust-c]");
+    let d = unsafe { data.as_mut().expect("bad pointer") };
+    match &mut d.engine {
+        Some(e) => d.last_response = e.capture_mail(&message),
+        None => {
+            debug!("no engine!");
+        }
+    }
+}
+
 // #[no_mangle]
 // pub extern "C" fn set_options(data: *mut LowuData, options_json: *const c_char) {
 //     let opts = string_from_c(options_json);
@@ -315,7 +624,8 @@ pub extern "C" fn response_num_items(data: *mut LowuData) -> c_int {
     }
 }
 #[no_mangle]
-/// a response item, selected by index
+/// a response item, selected by index (the key may start with an
+/// operation such as `append:`, as for `fanling_interface::Operation`)
 pub extern "C" fn response_item(data: *mut LowuData, n: c_int) -> CResponseItem {
     let mut d = unsafe { data.as_mut().expect("bad pointer") };
     let ss = match &d.last_response {
@@ -330,6 +640,218 @@ pub extern "C" fn response_item(data: *mut LowuData, n: c_int) -> CResponseItem
     }
 }
 #[no_mangle]
+/// the number of notifications in the response
+pub extern "C" fn response_num_notifications(data: *mut LowuData) -> c_int {
+    let d = unsafe { data.as_ref().expect("bad pointer") };
+    match &d.last_response {
+        Ok(r) => r.get_notifications().count() as c_int,
+        Err(_) => 0,
+    }
+}
+#[no_mangle]
+/// a notification in the response, selected by index
+pub extern "C" fn response_notification(data: *mut LowuData, n: c_int) -> *const c_char {
+    let mut d = unsafe { data.as_mut().expect("bad pointer") };
+    let text = match &d.last_response {
+        Ok(r) => r
+            .get_notifications()
+            .nth(n as usize)
+            .cloned()
+            .unwrap_or_default(),
+        Err(_e) => "".to_string(),
+    };
+    d.last_string = string_to_cstring(text);
+    d.last_string.as_ptr()
+}
+/** a file given with the last response, selected by index */
+fn response_file(d: &LowuData, n: c_int) -> Option<&fanling_interface::OutputFile> {
+    match &d.last_response {
+        Ok(r) => r.get_files().nth(n as usize),
+        Err(_) => None,
+    }
+}
+#[no_mangle]
+/// the number of files (such as exported items) in the response, to be shared, saved or copied
+pub extern "C" fn response_num_files(data: *mut LowuData) -> c_int {
+    let d = unsafe { data.as_ref().expect("bad pointer") };
+    match &d.last_response {
+        Ok(r) => r.get_files().count() as c_int,
+        Err(_) => 0,
+    }
+}
+#[no_mangle]
+/// the name of a file in the response, selected by index
+pub extern "C" fn response_file_name(data: *mut LowuData, n: c_int) -> *const c_char {
+    let d = unsafe { data.as_mut().expect("bad pointer") };
+    let name = response_file(d, n)
+        .map(|f| f.name.clone())
+        .unwrap_or_default();
+    d.last_string = string_to_cstring(name);
+    d.last_string.as_ptr()
+}
+#[no_mangle]
+/// the MIME type of a file in the response, selected by index
+pub extern "C" fn response_file_content_type(data: *mut LowuData, n: c_int) -> *const c_char {
+    let d = unsafe { data.as_mut().expect("bad pointer") };
+    let content_type = response_file(d, n)
+        .map(|f| f.content_type.clone())
+        .unwrap_or_default();
+    d.last_string = string_to_cstring(content_type);
+    d.last_string.as_ptr()
+}
+#[no_mangle]
+/// whether a file in the response is to be put on the clipboard rather than shared or saved
+pub extern "C" fn response_file_for_clipboard(data: *mut LowuData, n: c_int) -> bool {
+    let d = unsafe { data.as_ref().expect("bad pointer") };
+    response_file(d, n).map_or(false, |f| f.for_clipboard)
+}
+#[no_mangle]
+/// the number of bytes in a file in the response, selected by index
+pub extern "C" fn response_file_len(data: *mut LowuData, n: c_int) -> c_int {
+    let d = unsafe { data.as_ref().expect("bad pointer") };
+    response_file(d, n).map_or(0, |f| f.bytes.len() as c_int)
+}
+#[no_mangle]
+/// the bytes of a file in the response (`response_file_len` of them), valid until the next request
+pub extern "C" fn response_file_bytes(data: *mut LowuData, n: c_int) -> *const u8 {
+    let d = unsafe { data.as_ref().expect("bad pointer") };
+    response_file(d, n).map_or(std::ptr::null(), |f| f.bytes.as_ptr())
+}
+/** how the repository stands against its remote, if the last response gives it */
+fn sync_status(d: &LowuData) -> Option<&fanling_interface::SyncStatus> {
+    match &d.last_response {
+        Ok(r) => r.get_sync_status(),
+        Err(_) => None,
+    }
+}
+#[no_mangle]
+/// a short text for a sync badge (empty if there is nothing to show)
+pub extern "C" fn response_sync_badge(data: *mut LowuData) -> *const c_char {
+    let mut d = unsafe { data.as_mut().expect("bad pointer") };
+    let badge = sync_status(d).map(|s| s.badge()).unwrap_or_default();
+    d.last_string = string_to_cstring(badge);
+    d.last_string.as_ptr()
+}
+#[no_mangle]
+/// how many local commits have not been pushed (-1 if the response does not say)
+pub extern "C" fn response_sync_ahead(data: *mut LowuData) -> c_int {
+    let d = unsafe { data.as_ref().expect("bad pointer") };
+    sync_status(d).map_or(-1, |s| s.ahead as c_int)
+}
+#[no_mangle]
+/// how many commits on the remote have not been merged (-1 if the response does not say)
+pub extern "C" fn response_sync_behind(data: *mut LowuData) -> c_int {
+    let d = unsafe { data.as_ref().expect("bad pointer") };
+    sync_status(d).map_or(-1, |s| s.behind as c_int)
+}
+#[no_mangle]
+/// how many items had conflicting changes in the last merge (-1 if the response does not say)
+pub extern "C" fn response_sync_conflicts(data: *mut LowuData) -> c_int {
+    let d = unsafe { data.as_ref().expect("bad pointer") };
+    sync_status(d).map_or(-1, |s| s.conflicts as c_int)
+}
+#[no_mangle]
+/// how many remotes the last pull or push failed for (-1 if the response does not say)
+pub extern "C" fn response_sync_failed_remotes(data: *mut LowuData) -> c_int {
+    let d = unsafe { data.as_ref().expect("bad pointer") };
+    sync_status(d).map_or(-1, |s| s.failed_remotes.len() as c_int)
+}
+#[no_mangle]
+/// when the repository was last synced (seconds since the Unix epoch, 0 if never or not known)
+pub extern "C" fn response_last_sync(data: *mut LowuData) -> i64 {
+    let d = unsafe { data.as_ref().expect("bad pointer") };
+    sync_status(d).and_then(|s| s.last_sync).unwrap_or(0)
+}
+#[no_mangle]
+/// get the next `n` (or fewer) notifications to schedule, returning how many there are
+pub extern "C" fn fetch_reminders(data: *mut LowuData, n: c_int) -> c_int {
+    let d = unsafe { data.as_mut().expect("bad pointer") };
+    d.reminders = match &mut d.engine {
+        Some(e) => match e.next_reminders(n.max(0) as usize) {
+            Ok(reminders) => reminders,
+            Err(err) => {
+                debug!("could not get reminders: {:?}", err);
+                vec![]
+            }
+        },
+        None => vec![],
+    };
+    d.reminders.len() as c_int
+}
+#[no_mangle]
+/// when a fetched notification is to be given (seconds since the Unix epoch), selected by index
+pub extern "C" fn reminder_when(data: *mut LowuData, n: c_int) -> i64 {
+    let d = unsafe { data.as_ref().expect("bad pointer") };
+    d.reminders.get(n as usize).map_or(0, |r| r.when)
+}
+#[no_mangle]
+/// the ident of the item a fetched notification is for, selected by index
+pub extern "C" fn reminder_ident(data: *mut LowuData, n: c_int) -> *const c_char {
+    let d = unsafe { data.as_mut().expect("bad pointer") };
+    let ident = d
+        .reminders
+        .get(n as usize)
+        .map(|r| r.ident.clone())
+        .unwrap_or_default();
+    d.last_string = string_to_cstring(ident);
+    d.last_string.as_ptr()
+}
+#[no_mangle]
+/// the text of a fetched notification, selected by index
+pub extern "C" fn reminder_text(data: *mut LowuData, n: c_int) -> *const c_char {
+    let d = unsafe { data.as_mut().expect("bad pointer") };
+    let text = d
+        .reminders
+        .get(n as usize)
+        .map(|r| r.text.clone())
+        .unwrap_or_default();
+    d.last_string = string_to_cstring(text);
+    d.last_string.as_ptr()
+}
+#[no_mangle]
+/// get the asset for a URL asked for by the page, returning whether there is one
+pub extern "C" fn fetch_asset(data: *mut LowuData, url: *const c_char) -> bool {
+    let d = unsafe { data.as_mut().expect("bad pointer") };
+    let url = string_from_c(url);
+    d.asset = match &d.engine {
+        Some(e) => match e.asset(&url) {
+            Ok(asset) => asset,
+            Err(err) => {
+                debug!("could not get asset {}: {:?}", url, err);
+                None
+            }
+        },
+        None => None,
+    };
+    d.asset.is_some()
+}
+#[no_mangle]
+/// the MIME type of the fetched asset
+pub extern "C" fn asset_content_type(data: *mut LowuData) -> *const c_char {
+    let d = unsafe { data.as_mut().expect("bad pointer") };
+    let content_type = d
+        .asset
+        .as_ref()
+        .map(|a| a.content_type.clone())
+        .unwrap_or_default();
+    d.last_string = string_to_cstring(content_type);
+    d.last_string.as_ptr()
+}
+#[no_mangle]
+/// the number of bytes in the fetched asset
+pub extern "C" fn asset_len(data: *mut LowuData) -> c_int {
+    let d = unsafe { data.as_ref().expect("bad pointer") };
+    d.asset.as_ref().map_or(0, |a| a.bytes.len() as c_int)
+}
+#[no_mangle]
+/// the bytes of the fetched asset (`asset_len` of them), valid until the next `fetch_asset`
+pub extern "C" fn asset_bytes(data: *mut LowuData) -> *const u8 {
+    let d = unsafe { data.as_ref().expect("bad pointer") };
+    d.asset
+        .as_ref()
+        .map_or(std::ptr::null(), |a| a.bytes.as_ptr())
+}
+#[no_mangle]
 /// the error message, if the response is an error
 pub extern "C" fn response_error(data: *mut LowuData) -> *const c_char {
     let mut d = unsafe { data.as_mut().expect("bad pointer") };
@@ -349,6 +871,15 @@ pub extern "C" fn is_shutdown_required(data: *mut LowuData) -> bool {
     }
 }
 #[no_mangle]
+/// whether the response asks the user to confirm warnings before saving
+pub extern "C" fn is_confirm_required(data: *mut LowuData) -> bool {
+    let d = unsafe { data.as_ref().expect("bad pointer") };
+    match &d.last_response {
+        Ok(r) => r.is_confirm_required(),
+        Err(_e) => false,
+    }
+}
+#[no_mangle]
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 /// an item of a response
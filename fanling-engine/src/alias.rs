@@ -0,0 +1,223 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! renaming items: giving an item a new ident while keeping links to
+it working.
+
+The old ident is kept in the item as an alias, so that a wiki link to
+it (see [`crate::markdown`]), or a request to show it, goes to the item
+as it is now. The links in other items can also be changed to the new
+ident, in the same commit as the rename, so that they do not depend on
+the alias. An old ident that is later given to another item is that
+item's ident, and no longer an alias.
+
+Private items can not be renamed, as the links in them can not be
+read. */
+use crate::item::{split_data_parts, Ident, Item};
+use crate::shared::FLResult;
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use regex::{Captures, Regex};
+use std::collections::BTreeMap;
+
+/** the characters allowed in an ident, as in wiki links (with slashes for namespaces) */
+const IDENT_PATTERN: &str = r"^[\w./-]+$";
+
+/** template data for the page for renaming an item */
+#[derive(Template)]
+#[template(path = "rename.html", print = "none")]
+struct RenameTemplate<'a> {
+    ident: &'a str,
+    type_name: String,
+    descr: String,
+    /** the idents the item had before */
+    aliases: &'a [Ident],
+    message: String,
+}
+/** show the page for renaming an item */
+pub fn show_rename(
+    item: &Item,
+    message: &str,
+    world: &mut World,
+) -> fanling_interface::ResponseResult {
+    let ident = item.ident();
+    let t = RenameTemplate {
+        ident: &ident,
+        type_name: item.type_name(),
+        descr: item.description(),
+        aliases: item.aliases(),
+        message: world.i18n().tr(message),
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.clear_errors(vec!["new-ident-error".to_owned()]);
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    resp.set_test_data("aliases", &item.aliases().join(","));
+    Ok(resp)
+}
+
+/** the text with the wiki links to `from` changed to `to` (keeping any labels) */
+fn rewrite_links(text: &str, from: &str, to: &str) -> FLResult<String> {
+    let re = Regex::new(&format!(
+        r"\[\[(\s*){}(\s*(?:\|[^\[\]]*)?\]\])",
+        regex::escape(from)
+    ))?;
+    Ok(re
+        .replace_all(text, |caps: &Captures| {
+            format!("[[{}{}{}", &caps[1], to, &caps[2])
+        })
+        .into_owned())
+}
+/** what is wrong with the new ident for the item, if anything */
+pub(crate) fn check_new_ident(
+    world: &World,
+    ident: &Ident,
+    new_ident: &str,
+) -> FLResult<Option<&'static str>> {
+    Ok(if !Regex::new(IDENT_PATTERN)?.is_match(new_ident) {
+        Some("The new ident can only have letters, digits, dots, dashes and slashes.")
+    } else if let Some(problem) = crate::namespace::check_ident(new_ident) {
+        Some(problem)
+    } else if new_ident == ident {
+        Some("The new ident is the same as the old one.")
+    } else if world.has_item(new_ident)? {
+        Some("There is already an item with that ident.")
+    } else if world
+        .aliases()
+        .get(new_ident)
+        .map_or(false, |current| current != ident)
+    {
+        Some("That ident was used by another item, and links to it go there.")
+    } else {
+        None
+    })
+}
+/** give the item a new ident, keeping the old one as an alias, and if
+`rewrite` change the links to it in the other items, all in one
+commit; returns how many other items were changed */
+pub fn rename(world: &mut World, ident: &Ident, new_ident: &str, rewrite: bool) -> FLResult<usize> {
+    trace(&format!("renaming {} to {}", ident, new_ident));
+    let (mut base, values) = world.get_item_parts(ident)?;
+    let new_ident = new_ident.to_owned();
+    let values = if rewrite {
+        let yaml = serde_yaml::to_string(&values)?;
+        serde_yaml::from_str(&rewrite_links(&yaml, ident, &new_ident)?)?
+    } else {
+        values
+    };
+    base.aliases.retain(|a| *a != new_ident);
+    base.aliases.push(ident.clone());
+    base.ident = new_ident.clone();
+    let mut referring = vec![];
+    if rewrite {
+        for (other, yaml) in world.stored_items()? {
+            if other == *ident || !crate::graph::wiki_links(&yaml)?.contains(ident) {
+                continue;
+            }
+            referring.push((other, rewrite_links(&yaml, ident, &new_ident)?));
+        }
+    }
+    world.begin_batch();
+    let renamed = rename_in_batch(world, ident, &base, &values, &referring);
+    world.end_batch()?;
+    let changed = renamed?;
+    let mut aliases: BTreeMap<Ident, Ident> = world
+        .aliases()
+        .iter()
+        .map(|(old, current)| {
+            let current = if current == ident {
+                new_ident.clone()
+            } else {
+                current.clone()
+            };
+            (old.clone(), current)
+        })
+        .filter(|(old, _current)| *old != new_ident)
+        .collect();
+    aliases.insert(ident.clone(), new_ident);
+    world.set_aliases(aliases);
+    Ok(changed)
+}
+/** the changes for a rename, made while the changes are collected into a single commit */
+fn rename_in_batch(
+    world: &mut World,
+    ident: &Ident,
+    base: &crate::item::ItemBaseForSerde,
+    values: &serde_yaml::Value,
+    referring: &[(Ident, String)],
+) -> FLResult<usize> {
+    world.forget_item_file(ident)?;
+    world.import_item(base, values)?;
+    let mut changed = 0;
+    for (other, yaml) in referring {
+        let (other_base, other_values) = split_data_parts(yaml.as_bytes())?;
+        if crate::private::is_sealed(&other_values) {
+            continue;
+        }
+        let item_ref = world.get_item(other.clone(), "Simple".to_owned())?;
+        let mut item = item_ref.borrow_mut();
+        item.set_from_yaml(&other_values, world)?;
+        item.set_from_serde(&other_base)?;
+        world.persist_change_as(&mut item, "rename")?;
+        changed += 1;
+    }
+    Ok(changed)
+}
+/** rename an item (from the rename page), then show it */
+pub fn rename_and_show(
+    world: &mut World,
+    ident: &Ident,
+    new_ident: &str,
+    rewrite: bool,
+) -> fanling_interface::ResponseResult {
+    let new_ident = new_ident.trim();
+    let item_ref = world.get_item(ident.clone(), "Simple".to_owned())?;
+    let mut ar = crate::world::ActionResponse::new();
+    ar.assert(
+        !item_ref.borrow().is_private(),
+        "new-ident-error",
+        "A private item cannot be renamed.",
+    );
+    if let Some(problem) = check_new_ident(world, ident, new_ident)? {
+        ar.add_error("new-ident-error", problem);
+    }
+    if !ar.ok() {
+        return ar.translated(&world.i18n()).to_response();
+    }
+    let changed = rename(world, ident, new_ident, rewrite)?;
+    trace(&format!("renamed, changing {} other items", changed));
+    let item_ref = world.get_item(new_ident.to_owned(), "Simple".to_owned())?;
+    let mut resp = item_ref.borrow_mut().for_show(world)?;
+    resp.show_toast(&world.i18n().tr("The item has been renamed."));
+    #[cfg(test)]
+    resp.set_test_data("changed", &format!("{}", changed));
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("alias {}", Colour::Fixed(0).on(Colour::Fixed(186)).paint(m));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn rewriting() -> crate::shared::NullResult {
+        assert_eq!(
+            "see [[new-a2]], [[ new-a2 | the list]] and [[old-a23]]",
+            rewrite_links(
+                "see [[old-a2]], [[ old-a2 | the list]] and [[old-a23]]",
+                "old-a2",
+                "new-a2"
+            )?
+        );
+        assert_eq!(
+            "[[c]] [[axb]]",
+            rewrite_links("[[a.b]] [[axb]]", "a.b", "c")?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! articles saved to read later. Saving an article fetches the page
+and makes a bookmark for it, with the readable text of the page (as
+markdown) as its snapshot, a note of its source (the site, author and
+when it was published, as far as the page says) and the tag
+[`READ_LATER_TAG`]. The reading list is the bookmarks with that tag.
+
+Unlike content shared with the app (see [`crate::share`]), an article
+is only saved if its page can be fetched, as the point is to have its
+text. */
+use crate::item::{Item, ItemBaseForSerde, ItemListEntryList};
+use crate::shared::FLResult;
+use crate::world::World;
+use ansi_term::Colour;
+use std::collections::HashMap;
+
+/** the tag of the bookmarks in the reading list */
+pub const READ_LATER_TAG: &str = "read-later";
+
+/** a note of where the article came from */
+fn source_note(url: &str, page: &crate::fetch::Page) -> String {
+    let mut parts = vec![];
+    if !page.site.is_empty() {
+        parts.push(format!("from {}", page.site));
+    }
+    if !page.author.is_empty() {
+        parts.push(format!("by {}", page.author));
+    }
+    if !page.published.is_empty() {
+        parts.push(format!("published {}", page.published));
+    }
+    if parts.is_empty() {
+        format!("Saved from <{}>.", url)
+    } else {
+        format!("Saved from <{}> ({}).", url, parts.join(", "))
+    }
+}
+
+/** fetch the page at the URL and save it as an article to read later, and show it */
+pub fn save_article(world: &mut World, url: &str) -> fanling_interface::ResponseResult {
+    let url = url.trim();
+    let mut resp = fanling_interface::Response::new();
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        resp.show_toast("URL must start with http:// or https://.");
+        return Ok(resp);
+    }
+    let page = match crate::fetch::fetch_page(url) {
+        Ok(page) => page,
+        Err(e) => {
+            trace(&format!("could not fetch {}: {:?}", url, e));
+            resp.show_toast("The page could not be fetched.");
+            #[cfg(test)]
+            resp.set_test_data("fetched", "false");
+            return Ok(resp);
+        }
+    };
+    let mut vals = HashMap::new();
+    vals.insert("url".to_owned(), url.to_owned());
+    vals.insert("name".to_owned(), page.title.clone());
+    vals.insert("tags".to_owned(), READ_LATER_TAG.to_owned());
+    vals.insert("notes".to_owned(), source_note(url, &page));
+    vals.insert("snapshot".to_owned(), page.text);
+    let base = ItemBaseForSerde {
+        ident: "".to_owned(),
+        type_name: "Bookmark".to_owned(),
+        ..ItemBaseForSerde::default()
+    };
+    trace(&format!("saving article '{}'", page.title));
+    world.begin_batch();
+    let made = world.make_item("Bookmark", &base, &vals);
+    world.end_batch()?;
+    let item_ref = made?;
+    let mut resp = item_ref.borrow_mut().for_show(world)?;
+    resp.show_toast(&format!(
+        "Saved '{}' to read later",
+        item_ref.borrow().description()
+    ));
+    Ok(resp)
+}
+
+/** whether an item is in the reading list */
+fn is_to_read(item: &Item) -> bool {
+    item.type_name() == "Bookmark" && item.tags().iter().any(|t| t == READ_LATER_TAG)
+}
+/** the articles in the reading list */
+pub fn reading_list(world: &mut World) -> FLResult<ItemListEntryList> {
+    let mut all = world.search_all()?;
+    all.filter_on_item(|item, _world| Ok(is_to_read(item)), world)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "article {}",
+        Colour::Fixed(6).on(Colour::Fixed(230)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn notes() {
+        let mut page = crate::fetch::Page::default();
+        assert_eq!(
+            "Saved from <https://a.example/>.",
+            source_note("https://a.example/", &page)
+        );
+        page.site = "A".to_owned();
+        page.published = "2020-01-02".to_owned();
+        assert_eq!(
+            "Saved from <https://a.example/> (from A, published 2020-01-02).",
+            source_note("https://a.example/", &page)
+        );
+    }
+}
@@ -0,0 +1,140 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! assets for the page (style sheets, scripts, fonts and pictures),
+served from URLs that start with [`fanling_interface::ASSET_BASE`].
+
+If the main program can answer the requests of the page (see
+[`fanling_interface::Engine::asset`]), and the `serve_assets` option
+is set, the main page links to its style sheet and script rather than
+having them inlined, and pages can refer to other assets in the same
+way. The rest of the URL is either:
+
+* the name of a file embedded in the engine (from the `templates`
+folder, but only of the kinds listed in [`content_type`], so not the
+templates themselves), such as `fanling.css`;
+* `repo/` and the path of a file attached to an item (such as a
+thumbnail or an archived page), which is only served from the
+attachments folder, so that the items themselves (perhaps private)
+are not; one kept base64-encoded (see [`crate::mail::BASE64_SUFFIX`])
+is served decoded. */
+use crate::shared::FLResult;
+use crate::world::World;
+use ansi_term::Colour;
+use fanling_interface::{Asset, ASSET_BASE};
+use rust_embed::RustEmbed;
+
+/** the start of the path of an asset from the repository */
+const REPO_PREFIX: &str = "repo/";
+
+/** the files embedded in the engine */
+#[derive(RustEmbed)]
+#[folder = "templates/"]
+struct Embedded;
+
+/** the MIME type for the file name, if it is of a kind that can be served */
+fn content_type(name: &str) -> Option<&'static str> {
+    let extension = name.rsplit('.').next()?.to_lowercase();
+    Some(match extension.as_str() {
+        "css" => "text/css",
+        "js" => "application/javascript",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "txt" => "text/plain",
+        "pdf" => "application/pdf",
+        _ => return None,
+    })
+}
+/** the URL for an asset with the path */
+pub fn url(path: &str) -> String {
+    format!("{}{}", ASSET_BASE, path)
+}
+/** the asset for the URL (with any query or fragment ignored), if there is one */
+pub fn asset(url: &str, world: Option<&World>) -> FLResult<Option<Asset>> {
+    let path = match url.strip_prefix(ASSET_BASE) {
+        Some(path) => path.split(|c| c == '?' || c == '#').next().unwrap_or(""),
+        None => return Ok(None),
+    };
+    if path.split('/').any(|part| part == "..") {
+        trace(&format!("refusing {}", url));
+        return Ok(None);
+    }
+    let content_type = match content_type(
+        path.strip_suffix(crate::mail::BASE64_SUFFIX)
+            .unwrap_or(path),
+    ) {
+        Some(content_type) => content_type.to_owned(),
+        None => return Ok(None),
+    };
+    let bytes = if let Some(repo_path) = path.strip_prefix(REPO_PREFIX) {
+        match world {
+            Some(world)
+                if repo_path.starts_with(&format!("{}/", crate::bookmark::ATTACHMENTS_DIR)) =>
+            {
+                match world.attachment(repo_path) {
+                    Ok(contents) if repo_path.ends_with(crate::mail::BASE64_SUFFIX) => {
+                        match base64::decode(contents.trim()) {
+                            Ok(bytes) => bytes,
+                            Err(e) => {
+                                trace(&format!("bad attachment for {}: {:?}", url, e));
+                                return Ok(None);
+                            }
+                        }
+                    }
+                    Ok(contents) => contents.into_bytes(),
+                    Err(e) => {
+                        trace(&format!("no attachment for {}: {:?}", url, e));
+                        return Ok(None);
+                    }
+                }
+            }
+            _ => return Ok(None),
+        }
+    } else {
+        match Embedded::get(path) {
+            Some(contents) => contents.into_owned(),
+            None => return Ok(None),
+        }
+    };
+    Ok(Some(Asset {
+        content_type,
+        bytes,
+    }))
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "assets {}",
+        Colour::Fixed(15).on(Colour::Fixed(24)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn embedded() -> crate::shared::NullResult {
+        let css = asset(&url("fanling.css?v=2"), None)?.expect("no style sheet");
+        assert_eq!("text/css", css.content_type);
+        assert!(!css.bytes.is_empty());
+        assert_eq!(
+            "application/javascript",
+            asset(&url("code.js"), None)?
+                .expect("no script")
+                .content_type
+        );
+        assert!(asset(&url("main.html"), None)?.is_none());
+        assert!(asset(&url("../Cargo.toml"), None)?.is_none());
+        assert!(asset("fanling.css", None)?.is_none());
+        assert!(asset(&url("repo/attachments/a/b.png"), None)?.is_none());
+        Ok(())
+    }
+}
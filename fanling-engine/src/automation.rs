@@ -0,0 +1,332 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! automation rules: scripts (in [Rhai](https://rhai.rs)) that are run
+when something happens, such as an item being made or closed, or before
+a sync.
+
+A script is a snippet item whose language is `rhai`, so it is kept in
+the repository and syncs with the other items. Its first lines say
+when it is run, as in `// on: created, closed` (the events are
+`created`, `closed` and `before-sync`). An item being made or closed is
+given to the script as the map `item` (its fields as stored), which the
+script can change; `create(type, fields)` makes a new item (as if made
+from the form), `get_item(ident)` gives the fields of another item (or
+`()`), and `today()` the date. The items are given as they were before
+the scripts were run. Scripts are run in the order of their idents,
+each given the item as changed by those before it.
+
+The scripts cannot reach files or the network, and are stopped if they
+run for too long. A script that fails, or whose changes cannot be
+saved, is reported to the user, and the others are still run. Private
+items are not given to scripts, the items that scripts make do not run
+the scripts again, and no scripts are run in a read-only repository. */
+use crate::item::{split_data_parts, ItemBaseForSerde, ItemRef};
+use crate::shared::{FLResult, FanlingError, NullResult};
+use crate::world::World;
+use ansi_term::Colour;
+use rhai::{Dynamic, Engine, ImmutableString, Map, Scope};
+use serde_yaml::Value;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Deref;
+use std::rc::Rc;
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** the language of the snippets that are scripts */
+const LANGUAGE: &str = "rhai";
+/** how many operations a script can do before it is stopped */
+const MAX_OPERATIONS: u64 = 100_000;
+/** the longest string a script can make */
+const MAX_STRING: usize = 1_000_000;
+
+/** when a script is run */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    /** an item has been made */
+    Created,
+    /** an item has been closed (such as a task being done) */
+    Closed,
+    /** before pulling from the remotes */
+    BeforeSync,
+}
+impl Event {
+    /** the name of the event, as written in scripts */
+    pub fn name(self) -> &'static str {
+        match self {
+            Event::Created => "created",
+            Event::Closed => "closed",
+            Event::BeforeSync => "before-sync",
+        }
+    }
+    /** the event with the name, if there is one */
+    fn parse(name: &str) -> Option<Self> {
+        [Event::Created, Event::Closed, Event::BeforeSync]
+            .iter()
+            .copied()
+            .find(|e| e.name() == name)
+    }
+}
+/** a script, as found in a snippet */
+#[derive(Debug, Clone)]
+struct Script {
+    ident: String,
+    name: String,
+    code: String,
+}
+/** the events given in the `// on:` lines at the start of the code */
+fn events_of(code: &str) -> Vec<Event> {
+    code.lines()
+        .map(str::trim)
+        .take_while(|l| l.starts_with("//"))
+        .filter_map(|l| l.trim_start_matches('/').trim().strip_prefix("on:"))
+        .flat_map(|l| l.split(|c: char| c == ',' || c.is_whitespace()))
+        .filter_map(Event::parse)
+        .collect()
+}
+/** a string value from the YAML of an item, or blank */
+fn text(values: &Value, key: &str) -> String {
+    values
+        .get(key)
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_owned()
+}
+/** the scripts that are run on the event, in the order of their idents */
+fn scripts_for(world: &mut World, event: Event) -> FLResult<Vec<Script>> {
+    let mut scripts = vec![];
+    for entry in world.search_type("Snippet")?.entries {
+        let (_base, values) = world.get_item_parts(&entry.link.ident)?;
+        if crate::private::is_sealed(&values)
+            || !text(&values, "language").eq_ignore_ascii_case(LANGUAGE)
+        {
+            continue;
+        }
+        let code = text(&values, "code");
+        if events_of(&code).contains(&event) {
+            scripts.push(Script {
+                ident: entry.link.ident.clone(),
+                name: text(&values, "name"),
+                code,
+            });
+        }
+    }
+    scripts.sort_by(|a, b| a.ident.cmp(&b.ident));
+    Ok(scripts)
+}
+
+/** the YAML of an item as a value for scripts */
+fn to_dynamic(values: &Value) -> FLResult<Dynamic> {
+    rhai::serde::to_dynamic(values).map_err(|e| fanling_error!(&e.to_string()))
+}
+/** what running a script did */
+struct Outcome {
+    /** the item, as the script left it */
+    item: Option<Dynamic>,
+    /** the items to make, with their types and fields */
+    created: Vec<(String, Map)>,
+}
+/** an engine for running a script, in its sandbox */
+fn engine(
+    created: Rc<RefCell<Vec<(String, Map)>>>,
+    lookup: Rc<BTreeMap<String, Dynamic>>,
+) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(MAX_STRING);
+    engine.set_max_array_size(10_000);
+    engine.set_max_map_size(10_000);
+    engine.on_print(|m| trace(&format!("script says {}", m)));
+    engine.register_fn("create", move |type_name: ImmutableString, fields: Map| {
+        created.borrow_mut().push((type_name.to_string(), fields));
+    });
+    engine.register_fn("get_item", move |ident: ImmutableString| {
+        lookup.get(ident.as_str()).cloned().unwrap_or(Dynamic::UNIT)
+    });
+    engine.register_fn("today", || {
+        chrono::Local::today()
+            .naive_local()
+            .format(crate::format::ISO_DATE_FORMAT)
+            .to_string()
+    });
+    engine
+}
+/** run a script, with the item (if there is one) */
+fn run_one(
+    script: &Script,
+    event: Event,
+    item: Option<&Dynamic>,
+    lookup: &Rc<BTreeMap<String, Dynamic>>,
+) -> Result<Outcome, String> {
+    trace(&format!(
+        "running {} ({}) for {}",
+        script.ident,
+        script.name,
+        event.name()
+    ));
+    let created = Rc::new(RefCell::new(vec![]));
+    let engine = engine(created.clone(), lookup.clone());
+    let ast = engine.compile(&script.code).map_err(|e| e.to_string())?;
+    let mut scope = Scope::new();
+    scope.push("event", event.name().to_owned());
+    if let Some(item) = item {
+        scope.push_dynamic("item", item.clone());
+    }
+    engine
+        .consume_ast_with_scope(&mut scope, &ast)
+        .map_err(|e| e.to_string())?;
+    let item = scope.get_value::<Dynamic>("item");
+    let created = created.borrow().clone();
+    Ok(Outcome { item, created })
+}
+
+/** run the scripts for the event, with the item that it happened to
+(if any). The change has already been made, so anything that goes
+wrong is shown to the user (see [`report`]) rather than returned */
+pub fn run(world: &mut World, event: Event, item: Option<&ItemRef>) {
+    if let Err(e) = try_run(world, event, item) {
+        trace(&format!("running the scripts failed: {:?}", e));
+        world.note_script_failure(&format!("{}: {}", event.name(), e));
+    }
+}
+fn try_run(world: &mut World, event: Event, item: Option<&ItemRef>) -> NullResult {
+    if world.is_read_only() {
+        return Ok(());
+    }
+    let scripts = scripts_for(world, event)?;
+    if scripts.is_empty() {
+        return Ok(());
+    }
+    let before: Option<Value> = match item {
+        Some(item_ref) => {
+            let item = item_ref.deref().borrow();
+            if item.is_private() {
+                return Ok(());
+            }
+            Some(serde_yaml::from_slice(&item.to_yaml()?)?)
+        }
+        None => None,
+    };
+    let mut lookup = BTreeMap::new();
+    if scripts.iter().any(|s| s.code.contains("get_item")) {
+        for (ident, values) in world.stored_values()? {
+            if !crate::private::is_sealed(&values) {
+                lookup.insert(ident, to_dynamic(&values)?);
+            }
+        }
+    }
+    let lookup = Rc::new(lookup);
+    let mut current = match &before {
+        Some(values) => Some(to_dynamic(values)?),
+        None => None,
+    };
+    let mut created = vec![];
+    for script in &scripts {
+        match run_one(script, event, current.as_ref(), &lookup) {
+            Ok(outcome) => {
+                if outcome.item.is_some() {
+                    current = outcome.item;
+                }
+                created.extend(outcome.created);
+            }
+            Err(e) => {
+                trace(&format!("{} failed: {}", script.ident, e));
+                world.note_script_failure(&format!("{}: {}", script.name, e));
+            }
+        }
+    }
+    world.begin_batch();
+    let applied = apply(world, item, before, current, created);
+    world.end_batch()?;
+    applied
+}
+/** change the item as the scripts left it, and make the items that they asked for */
+fn apply(
+    world: &mut World,
+    item: Option<&ItemRef>,
+    before: Option<Value>,
+    after: Option<Dynamic>,
+    created: Vec<(String, Map)>,
+) -> NullResult {
+    if let (Some(item_ref), Some(before), Some(after)) = (item, before, after) {
+        let mut after: Value = match rhai::serde::from_dynamic(&after) {
+            Ok(after) => after,
+            Err(e) => {
+                world.note_script_failure(&format!("the item could not be read back: {}", e));
+                before.clone()
+            }
+        };
+        /* the ident and the type cannot be changed by scripts */
+        for key in &["ident", "type"] {
+            if let (Some(m), Some(v)) = (after.as_mapping_mut(), before.get(*key)) {
+                m.insert(Value::String((*key).to_owned()), v.clone());
+            }
+        }
+        if after != before {
+            let (base, values) = split_data_parts(&serde_yaml::to_vec(&after)?)?;
+            let mut item = item_ref.deref().borrow_mut();
+            trace(&format!("changing {}", item.ident()));
+            item.set_from_yaml(&values, world)?;
+            item.set_from_serde(&base)?;
+            world.persist_change_as(&mut item, "script")?;
+        }
+    }
+    for (type_name, fields) in created {
+        let base = ItemBaseForSerde {
+            type_name: type_name.clone(),
+            ..ItemBaseForSerde::default()
+        };
+        let vals: HashMap<String, String> = fields
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let item_type = world.get_item_type(type_name.clone())?;
+        if world
+            .check_item_valid(item_type, &base, &vals)?
+            .is_blocking()
+        {
+            world.note_script_failure(&format!("a {} item could not be made", type_name));
+            continue;
+        }
+        trace(&format!("making a {} item", type_name));
+        world.make_item(&type_name, &base, &vals)?;
+    }
+    Ok(())
+}
+/** show the user the scripts that have failed since the last action */
+pub fn report(world: &mut World, resp: &mut fanling_interface::Response) {
+    let failures = world.take_script_failures();
+    if !failures.is_empty() {
+        resp.show_toast(&format!(
+            "{} {}",
+            world.i18n().tr("A script failed:"),
+            failures.join("; ")
+        ));
+    }
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "automation {}",
+        Colour::Fixed(0).on(Colour::Fixed(193)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn events() {
+        assert_eq!(
+            vec![Event::Created, Event::BeforeSync],
+            events_of("// a rule\n// on: created, before-sync\nlet x = 1;\n// on: closed")
+        );
+        assert!(events_of("let x = 1;").is_empty());
+    }
+}
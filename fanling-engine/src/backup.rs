@@ -0,0 +1,197 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! backups of the whole working setup in a single compressed archive,
+so that it can be recreated on a new device without reaching the remote.
+
+A backup is a gzipped tar file holding a manifest, the repository
+(with its history and its Git configuration, so the settings and
+remotes kept there) under `repo/`, and the files kept locally beside
+the search database (the drafts and the recent items) under `local/`.
+The search database is not kept, as it is rebuilt from the repository
+when the restored setup is first opened. Secrets kept by the credential
+provider are not in the backup, so have to be given again.
+
+A backup is made from the settings page (into the export directory) or
+with the `--backup` option of the main program, and restored with its
+`--restore` option, which will only restore into a new repository. */
+use crate::shared::{FLResult, FanlingError};
+use crate::world::World;
+use ansi_term::Colour;
+use chrono::NaiveDateTime;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** the name of the manifest in the archive, which comes first */
+const MANIFEST_FILE: &str = "fanling-backup.json";
+/** the version of the format of the backup */
+const BACKUP_FORMAT: u32 = 1;
+/** the directory in the archive holding the repository */
+const REPO_DIR: &str = "repo";
+/** the directory in the archive holding the local files */
+const LOCAL_DIR: &str = "local";
+/** the files kept beside the search database that are backed up */
+const LOCAL_FILES: &[&str] = &[crate::draft::DRAFTS_FILE, crate::session::HISTORY_FILE];
+
+/** what is in a backup */
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Manifest {
+    pub format: u32,
+    /** when the backup was made */
+    pub created: NaiveDateTime,
+    /** the name of the repository */
+    pub repo_name: String,
+    /** whether the items are encrypted (so the restored setup has to be opened with the passphrase) */
+    pub encrypted: bool,
+    /** the local files in the backup */
+    pub local_files: Vec<String>,
+}
+
+/** write a backup of the repository and of the local files in `local_dir` to `path` */
+pub fn write_backup(
+    repo_path: &Path,
+    local_dir: &Path,
+    repo_name: &str,
+    encrypted: bool,
+    path: &Path,
+) -> FLResult<Manifest> {
+    let manifest = Manifest {
+        format: BACKUP_FORMAT,
+        created: chrono::Local::now().naive_local(),
+        repo_name: repo_name.to_owned(),
+        encrypted,
+        local_files: LOCAL_FILES
+            .iter()
+            .filter(|f| local_dir.join(f).is_file())
+            .map(|f| (*f).to_owned())
+            .collect(),
+    };
+    trace(&format!("backing up {:?} to {:?}", repo_path, path));
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let encoder = GzEncoder::new(fs::File::create(path)?, Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+    let text = serde_json::to_string_pretty(&manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(text.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(manifest.created.timestamp().max(0) as u64);
+    header.set_cksum();
+    builder.append_data(&mut header, MANIFEST_FILE, text.as_bytes())?;
+    builder.append_dir_all(REPO_DIR, repo_path)?;
+    for file in &manifest.local_files {
+        builder.append_path_with_name(local_dir.join(file), Path::new(LOCAL_DIR).join(file))?;
+    }
+    builder.into_inner()?.finish()?;
+    Ok(manifest)
+}
+
+/** where an entry of the archive is restored to (`None` if it is not restored) */
+fn destination(name: &Path, repo_path: &Path, local_dir: &Path) -> Option<PathBuf> {
+    if !name.components().all(|c| matches!(c, Component::Normal(_))) {
+        return None;
+    }
+    let mut components = name.components();
+    let top = components.next()?.as_os_str().to_str()?.to_owned();
+    let rest = components.as_path();
+    if top == REPO_DIR {
+        Some(repo_path.join(rest))
+    } else if top == LOCAL_DIR && LOCAL_FILES.iter().any(|f| Path::new(f) == rest) {
+        Some(local_dir.join(rest))
+    } else {
+        None
+    }
+}
+/** restore the backup in `archive`: the repository into `repo_path`
+(which must not already hold anything) and the local files into `local_dir` */
+pub fn restore_backup(archive: &Path, repo_path: &Path, local_dir: &Path) -> FLResult<Manifest> {
+    if repo_path.exists() && fs::read_dir(repo_path)?.next().is_some() {
+        return Err(fanling_error!(&format!(
+            "cannot restore into {}, as it is not empty",
+            repo_path.display()
+        )));
+    }
+    trace(&format!("restoring {:?} into {:?}", archive, repo_path));
+    let mut contents = tar::Archive::new(GzDecoder::new(fs::File::open(archive)?));
+    let mut manifest: Option<Manifest> = None;
+    for entry in contents.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_path_buf();
+        if manifest.is_none() {
+            if name != Path::new(MANIFEST_FILE) {
+                return Err(fanling_error!("not a fanling backup"));
+            }
+            let mut text = String::new();
+            entry.read_to_string(&mut text)?;
+            let read: Manifest = serde_json::from_str(&text)?;
+            if read.format > BACKUP_FORMAT {
+                return Err(fanling_error!(
+                    "the backup is from a newer version of fanling"
+                ));
+            }
+            manifest = Some(read);
+            continue;
+        }
+        /* only files and directories are restored: a link could point
+        outside the restore directories, and later entries be written through it */
+        let entry_type = entry.header().entry_type();
+        if !(entry_type.is_file() || entry_type.is_dir()) {
+            trace(&format!("not restoring {:?} ({:?})", name, entry_type));
+            continue;
+        }
+        match destination(&name, repo_path, local_dir) {
+            Some(dest) => {
+                if let Some(dir) = dest.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                entry.unpack(&dest)?;
+            }
+            None => trace(&format!("not restoring {:?}", name)),
+        }
+    }
+    let manifest = manifest.ok_or_else(|| fanling_error!("not a fanling backup"))?;
+    trace(&format!("restored {:?}", manifest));
+    Ok(manifest)
+}
+
+/** back up to the export directory, then show the settings page */
+pub fn backup_and_show(world: &mut World) -> fanling_interface::ResponseResult {
+    let path = world.export_dir().join(format!(
+        "fanling-{}.tar.gz",
+        chrono::Local::today()
+            .naive_local()
+            .format(crate::format::ISO_DATE_FORMAT)
+    ));
+    world.export_backup(&path)?;
+    let message = format!(
+        "{} {}",
+        world.i18n().tr("The backup has been written to"),
+        path.display()
+    );
+    let resp = crate::settings::show_settings(world, &message)?;
+    #[cfg(test)]
+    let resp = {
+        let mut resp = resp;
+        resp.set_test_data("path", &path.to_string_lossy());
+        resp
+    };
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "backup {}",
+        Colour::Fixed(0).on(Colour::Fixed(152)).paint(m)
+    );
+}
@@ -0,0 +1,370 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! batch operations on the items chosen in a list: adding or removing
+a tag, setting the parent, closing, soft deletion (archiving them, as
+a retention policy does, see [`crate::retention`]) and moving them into
+another namespace (see [`crate::namespace`]).
+
+All the changes are made in a single commit, and a summary is shown
+afterwards. Items that the operation cannot be done to (such as a tag
+for a type that has no tags, or closing an item that is already
+closed) are left as they are and listed in the summary with the
+reason. If an item fails to be changed, the changes already made are
+still committed, the rest of the items are left as they are, and the
+summary shows the error. */
+use crate::item::{Ident, ItemLink};
+use crate::shared::FLResult;
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use serde::{Deserialize, Serialize};
+
+/** how far up the parents are followed when looking for a cycle */
+const MAX_DEPTH: usize = 20;
+
+/** what is done to each of the items chosen */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BatchOperation {
+    AddTag(String),
+    RemoveTag(String),
+    /** make them children of the item with the ident (or top-level items if it is blank) */
+    SetParent(Ident),
+    Close,
+    /** archive them, keeping them in the repository */
+    SoftDelete,
+    /** move them into the namespace (or out of any if it is blank), keeping their old idents as aliases */
+    ChangeNamespace(String),
+}
+
+/** what happened to an item */
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Changed,
+    /** left as it was, for the reason given */
+    Skipped(&'static str),
+    /** the change failed, with the error */
+    Failed(String),
+}
+
+/** the item with the ident and its ancestors, nearest first */
+fn with_ancestors(world: &mut World, ident: &str) -> FLResult<Vec<Ident>> {
+    let mut idents = vec![ident.to_owned()];
+    for _ in 0..MAX_DEPTH {
+        let last = idents[idents.len() - 1].clone();
+        let parent = ItemLink::new(last)
+            .resolve_link(world)?
+            .borrow_mut()
+            .parent_ident();
+        match parent {
+            Some(parent) if !idents.contains(&parent) => idents.push(parent),
+            _ => break,
+        }
+    }
+    Ok(idents)
+}
+
+/** the tags after the operation (`None` if it does not change tags) */
+fn new_tags(tags: &[String], operation: &BatchOperation) -> Option<Vec<String>> {
+    match operation {
+        BatchOperation::AddTag(tag) => {
+            let mut tags = tags.to_vec();
+            if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                tags.push(tag.clone());
+            }
+            Some(tags)
+        }
+        BatchOperation::RemoveTag(tag) => Some(
+            tags.iter()
+                .filter(|t| !t.eq_ignore_ascii_case(tag))
+                .cloned()
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/** the ident of the item when moved into the namespace */
+fn in_namespace(ident: &str, namespace: &str) -> String {
+    let name = ident
+        .rsplit(crate::namespace::SEPARATOR)
+        .next()
+        .unwrap_or(ident);
+    let namespace = namespace.trim().trim_matches(crate::namespace::SEPARATOR);
+    if namespace.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}{}{}", namespace, crate::namespace::SEPARATOR, name)
+    }
+}
+
+/** move the item into the namespace (renaming it) */
+fn change_namespace(world: &mut World, ident: &Ident, namespace: &str) -> FLResult<Outcome> {
+    let new_ident = in_namespace(ident, namespace);
+    if new_ident == *ident {
+        return Ok(Outcome::Skipped("The item is already in the namespace."));
+    }
+    if world
+        .get_item(ident.clone(), "Simple".to_owned())?
+        .borrow()
+        .is_private()
+    {
+        return Ok(Outcome::Skipped("A private item cannot be renamed."));
+    }
+    if let Some(problem) = crate::alias::check_new_ident(world, ident, &new_ident)? {
+        return Ok(Outcome::Skipped(problem));
+    }
+    crate::alias::rename(world, ident, &new_ident, true)?;
+    Ok(Outcome::Changed)
+}
+
+/** do the operation to the item with the ident (`parents` being the
+new parent and its ancestors, for [`BatchOperation::SetParent`]) */
+fn apply_to(
+    world: &mut World,
+    ident: &Ident,
+    operation: &BatchOperation,
+    parents: &Option<Vec<Ident>>,
+) -> FLResult<Outcome> {
+    /* it may have gone since the list was shown */
+    if !world.has_item(ident)? {
+        return Ok(Outcome::Skipped("The item has been deleted."));
+    }
+    if let BatchOperation::ChangeNamespace(namespace) = operation {
+        return change_namespace(world, ident, namespace);
+    }
+    let item_ref = world.get_item(ident.clone(), "Simple".to_owned())?;
+    let mut item = item_ref.borrow_mut();
+    if item.is_locked() || item.is_read_only() {
+        return Ok(Outcome::Skipped("The item cannot be changed."));
+    }
+    match operation {
+        BatchOperation::AddTag(_) | BatchOperation::RemoveTag(_) => {
+            let tags = item.tags();
+            let changed = new_tags(&tags, operation).unwrap_or_default();
+            if changed == tags {
+                return Ok(Outcome::Skipped(
+                    if let BatchOperation::AddTag(_) = operation {
+                        "The item already has the tag."
+                    } else {
+                        "The item does not have the tag."
+                    },
+                ));
+            }
+            if !item.set_tags(&changed, world)? {
+                return Ok(Outcome::Skipped("This kind of item has no tags."));
+            }
+            world.persist_change(&mut item)?;
+        }
+        BatchOperation::SetParent(parent) => {
+            let parent = parent.trim();
+            if parent.is_empty() {
+                if item.parent_ident().is_none() {
+                    return Ok(Outcome::Skipped("The item has no parent."));
+                }
+                item.set_parent(None);
+            } else {
+                match parents {
+                    None => {
+                        return Ok(Outcome::Skipped("There is no item with that ident."));
+                    }
+                    Some(parents) if parents.contains(ident) => {
+                        return Ok(Outcome::Skipped(
+                            "An item cannot be a child of itself or of its children.",
+                        ));
+                    }
+                    _ => {}
+                }
+                if item.parent_ident().as_deref() == Some(parent) {
+                    return Ok(Outcome::Skipped("The item already has that parent."));
+                }
+                item.set_parent(Some(ItemLink::new(parent.to_owned())));
+            }
+            world.persist_change(&mut item)?;
+        }
+        BatchOperation::Close => {
+            let closable = item.available_actions().iter().any(|a| {
+                matches!(
+                    a.action,
+                    crate::Action::Close | crate::Action::CloseProject(_)
+                )
+            });
+            if !item.is_open() || !closable {
+                return Ok(Outcome::Skipped("The item cannot be closed."));
+            }
+            item.do_action(crate::Action::Close, world)?;
+        }
+        BatchOperation::SoftDelete => {
+            if item.classify() == "archived" {
+                return Ok(Outcome::Skipped("The item is already archived."));
+            }
+            item.set_classify("archived");
+            world.persist_change_as(&mut item, "archive")?;
+        }
+        /* done above, as the item is renamed */
+        BatchOperation::ChangeNamespace(_) => {}
+    }
+    Ok(Outcome::Changed)
+}
+
+/** do the operation to the items, collecting the changes into a single
+commit, and return what happened to each of them (if one fails, those
+after it are skipped, and those before it are still committed) */
+fn apply(
+    world: &mut World,
+    idents: &[Ident],
+    operation: &BatchOperation,
+) -> FLResult<Vec<(Ident, Outcome)>> {
+    let parents = match operation {
+        BatchOperation::SetParent(parent) if !parent.trim().is_empty() => {
+            if world.has_item(parent.trim())? {
+                Some(with_ancestors(world, parent.trim())?)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+    world.begin_batch();
+    let mut outcomes = vec![];
+    let mut failed = false;
+    for ident in idents {
+        let outcome = if failed {
+            Outcome::Skipped("Not done, as an earlier item could not be changed.")
+        } else {
+            apply_to(world, ident, operation, &parents).unwrap_or_else(|e| {
+                trace(&format!("could not change {}: {:?}", ident, e));
+                failed = true;
+                Outcome::Failed(format!("{}", e))
+            })
+        };
+        outcomes.push((ident.clone(), outcome));
+    }
+    world.end_batch()?;
+    Ok(outcomes)
+}
+
+/** an item in the summary */
+struct EntryForShow {
+    ident: Ident,
+    descr: String,
+    /** why it was skipped (blank if it was changed) */
+    reason: String,
+}
+/** template data for the summary of a batch operation */
+#[derive(Template)]
+#[template(path = "batch.html", print = "none")]
+struct BatchTemplate {
+    message: String,
+    changed: Vec<EntryForShow>,
+    skipped: Vec<EntryForShow>,
+}
+/** do the operation to the items chosen, in a single commit, then show a summary */
+pub fn do_batch(
+    world: &mut World,
+    idents: &[Ident],
+    operation: &BatchOperation,
+) -> fanling_interface::ResponseResult {
+    trace(&format!("{:?} for {} items", operation, idents.len()));
+    let outcomes = apply(world, idents, operation)?;
+    let closed: Vec<Ident> = if *operation == BatchOperation::Close {
+        outcomes
+            .iter()
+            .filter(|(_ident, o)| *o == Outcome::Changed)
+            .map(|(ident, _o)| ident.clone())
+            .collect()
+    } else {
+        vec![]
+    };
+    for ident in &closed {
+        let item_ref = world.get_item(ident.clone(), "Simple".to_owned())?;
+        crate::automation::run(world, crate::automation::Event::Closed, Some(&item_ref));
+        crate::hooks::fire(world, crate::hooks::HookEvent::Closed, &item_ref);
+    }
+    let i18n = world.i18n();
+    let mut changed = vec![];
+    let mut skipped = vec![];
+    for (ident, outcome) in &outcomes {
+        /* an item moved to another namespace is found by its old ident, now an alias */
+        let current = world.resolve_alias(ident)?;
+        let descr = if world.has_item(&current)? {
+            world
+                .get_item(current.clone(), "Simple".to_owned())?
+                .borrow()
+                .description_for_list()
+        } else {
+            current.clone()
+        };
+        match outcome {
+            Outcome::Changed => changed.push(EntryForShow {
+                ident: current,
+                descr,
+                reason: "".to_owned(),
+            }),
+            Outcome::Skipped(reason) => skipped.push(EntryForShow {
+                ident: current,
+                descr,
+                reason: i18n.tr(reason),
+            }),
+            Outcome::Failed(error) => skipped.push(EntryForShow {
+                ident: current,
+                descr,
+                reason: format!("{} {}", i18n.tr("The change failed:"), error),
+            }),
+        }
+    }
+    let message = format!(
+        "{} {}, {} {}",
+        i18n.tr("Items changed:"),
+        changed.len(),
+        i18n.tr("skipped:"),
+        skipped.len()
+    );
+    trace(&message);
+    #[cfg(test)]
+    let (changed_count, skipped_count) = (changed.len(), skipped.len());
+    let t = BatchTemplate {
+        message: message.clone(),
+        changed,
+        skipped,
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    resp.show_toast(&message);
+    for ident in &closed {
+        crate::blocking::notify_unblocked(world, ident, &mut resp)?;
+    }
+    #[cfg(test)]
+    {
+        resp.set_test_data("changed", &format!("{}", changed_count));
+        resp.set_test_data("skipped", &format!("{}", skipped_count));
+    }
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("batch {}", Colour::Fixed(0).on(Colour::Fixed(152)).paint(m));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn tags_and_namespaces() {
+        let tags = vec!["home".to_owned(), "Work".to_owned()];
+        assert_eq!(
+            Some(vec!["home".to_owned()]),
+            new_tags(&tags, &BatchOperation::RemoveTag("work".to_owned()))
+        );
+        assert_eq!(
+            Some(tags.clone()),
+            new_tags(&tags, &BatchOperation::AddTag("HOME".to_owned()))
+        );
+        assert_eq!(None, new_tags(&tags, &BatchOperation::Close));
+        assert_eq!("work/notes", in_namespace("home/notes", "/work/"));
+        assert_eq!("notes", in_namespace("home/notes", ""));
+        assert_eq!("a/b/notes", in_namespace("notes", "a/b"));
+    }
+}
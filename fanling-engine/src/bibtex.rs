@@ -0,0 +1,212 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! reads and writes BibTeX data, restricted to the fields that
+Fanling keeps for a reference. Values can be in braces, in quotes or
+bare (such as a year); `@string`, `@comment` and `@preamble` entries
+are skipped, and string macros are not expanded. */
+
+/** a single BibTeX entry */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BibEntry {
+    /** such as `article` or `book` (lower case) */
+    pub entry_type: String,
+    /** the citation key */
+    pub key: String,
+    /** `title` */
+    pub title: String,
+    /** `author`, with the authors separated by " and " */
+    pub authors: String,
+    /** `year` */
+    pub year: String,
+    /** `doi` */
+    pub doi: String,
+    /** `url` */
+    pub url: String,
+    /** where it was published (`journal`, `booktitle` or `publisher`) */
+    pub published_in: String,
+}
+impl BibEntry {
+    /** serialise as BibTeX text */
+    pub fn to_bibtex_string(&self) -> String {
+        let entry_type = if self.entry_type.is_empty() {
+            "misc"
+        } else {
+            &self.entry_type
+        };
+        let venue = match entry_type {
+            "article" => "journal",
+            "inproceedings" | "incollection" => "booktitle",
+            _ => "publisher",
+        };
+        let mut fields = vec![];
+        for (name, value) in &[
+            ("title", &self.title),
+            ("author", &self.authors),
+            ("year", &self.year),
+            (venue, &self.published_in),
+            ("doi", &self.doi),
+            ("url", &self.url),
+        ] {
+            if !value.is_empty() {
+                fields.push(format!("  {} = {{{}}}", name, value));
+            }
+        }
+        format!(
+            "@{}{{{},\n{}\n}}\n",
+            entry_type,
+            self.key,
+            fields.join(",\n")
+        )
+    }
+}
+
+/** parse BibTeX text, which may contain several entries */
+pub fn parse(text: &str) -> Vec<BibEntry> {
+    let mut entries = vec![];
+    let mut rest = text;
+    while let Some(at) = rest.find('@') {
+        rest = &rest[at + 1..];
+        let open = match rest.find(|c: char| c == '{' || c == '(') {
+            Some(open) => open,
+            None => break,
+        };
+        let entry_type = rest[..open].trim().to_lowercase();
+        if entry_type.is_empty() || !entry_type.chars().all(|c| c.is_ascii_alphabetic()) {
+            continue; /* an @ that does not start an entry, such as in an e-mail address */
+        }
+        let body_len = balanced_len(&rest[open..]);
+        let body = &rest[open + 1..open + body_len.saturating_sub(1).max(1)];
+        rest = &rest[open + body_len..];
+        if entry_type == "string" || entry_type == "comment" || entry_type == "preamble" {
+            continue;
+        }
+        if let Some(entry) = parse_body(&entry_type, body) {
+            entries.push(entry);
+        }
+    }
+    entries
+}
+
+/** serialise several entries into one BibTeX file */
+pub fn to_bibtex_text(entries: &[BibEntry]) -> String {
+    entries
+        .iter()
+        .map(|e| e.to_bibtex_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/** the length of the text up to and including the bracket that closes
+the one it starts with (or all of it, if that is not closed) */
+fn balanced_len(text: &str) -> usize {
+    let (open, close) = match text.chars().next() {
+        Some('(') => ('(', ')'),
+        _ => ('{', '}'),
+    };
+    let mut depth = 0;
+    for (i, ch) in text.char_indices() {
+        if ch == open {
+            depth += 1;
+        } else if ch == close {
+            depth -= 1;
+            if depth == 0 {
+                return i + 1;
+            }
+        }
+    }
+    text.len()
+}
+/** an entry from the text between its brackets (its key, then its fields) */
+fn parse_body(entry_type: &str, body: &str) -> Option<BibEntry> {
+    let comma = body.find(',').unwrap_or_else(|| body.len());
+    let key = body[..comma].trim();
+    if key.is_empty() {
+        return None;
+    }
+    let mut entry = BibEntry {
+        entry_type: entry_type.to_owned(),
+        key: key.to_owned(),
+        ..BibEntry::default()
+    };
+    let mut rest = body.get(comma + 1..).unwrap_or("");
+    while let Some(eq) = rest.find('=') {
+        let name = rest[..eq]
+            .trim()
+            .trim_start_matches(',')
+            .trim()
+            .to_lowercase();
+        let (value, len) = field_value(&rest[eq + 1..]);
+        rest = &rest[eq + 1 + len..];
+        let value = clean(&value);
+        match name.as_str() {
+            "title" => entry.title = value,
+            "author" => entry.authors = value,
+            "year" => entry.year = value,
+            "doi" => entry.doi = value,
+            "url" => entry.url = value,
+            "journal" | "booktitle" | "publisher" if entry.published_in.is_empty() => {
+                entry.published_in = value
+            }
+            _ => {}
+        }
+    }
+    Some(entry)
+}
+/** a field's value (in braces, in quotes or bare), and the length of the text it took */
+fn field_value(text: &str) -> (String, usize) {
+    let start = text.len() - text.trim_start().len();
+    let value = &text[start..];
+    if value.starts_with('{') {
+        let len = balanced_len(value);
+        (value[1..len.max(2) - 1].to_owned(), start + len)
+    } else if let Some(quoted) = value.strip_prefix('"') {
+        let len = quoted.find('"').unwrap_or_else(|| quoted.len());
+        (quoted[..len].to_owned(), (start + len + 2).min(text.len()))
+    } else {
+        let len = value.find(',').unwrap_or_else(|| value.len());
+        (value[..len].trim().to_owned(), start + len)
+    }
+}
+/** a value without the braces that protect its case, and with its white space collapsed */
+fn clean(value: &str) -> String {
+    value
+        .replace(|c: char| c == '{' || c == '}', "")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn round_trip() {
+        let entry = BibEntry {
+            entry_type: "article".to_owned(),
+            key: "turing1936".to_owned(),
+            title: "On Computable Numbers".to_owned(),
+            authors: "Turing, Alan".to_owned(),
+            year: "1936".to_owned(),
+            doi: "10.1112/plms/s2-42.1.230".to_owned(),
+            url: "".to_owned(),
+            published_in: "Proceedings of the London Mathematical Society".to_owned(),
+        };
+        let text = entry.to_bibtex_string();
+        assert!(text.contains("  journal = {Proceedings of the London Mathematical Society},"));
+        assert_eq!(vec![entry], parse(&text));
+    }
+    #[test]
+    fn values() {
+        let text = "Sent by someone@example.com\n@comment{ignored}\n@Book{knuth:taocp,\n  author = \"Knuth, Donald E.\",\n  title = {The Art of {Computer}\n    Programming},\n  year = 1968, publisher={Addison-Wesley}\n}";
+        let entries = parse(text);
+        assert_eq!(1, entries.len());
+        assert_eq!("book", entries[0].entry_type);
+        assert_eq!("knuth:taocp", entries[0].key);
+        assert_eq!("Knuth, Donald E.", entries[0].authors);
+        assert_eq!("The Art of Computer Programming", entries[0].title);
+        assert_eq!("1968", entries[0].year);
+        assert_eq!("Addison-Wesley", entries[0].published_in);
+    }
+}
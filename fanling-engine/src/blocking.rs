@@ -0,0 +1,72 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! tasks blocked by other items: a task is not ready while any item
+that it is blocked by is open (see [`crate::task`]).
+
+A task shows both the items blocking it and the tasks that it
+blocks. When an item is closed, the tasks that are ready now that it
+no longer blocks them are named in a notification, so that they can
+be picked up. */
+use crate::item::{Ident, ItemLink, ItemListEntryList};
+use crate::shared::{FLResult, NullResult};
+use crate::world::World;
+use ansi_term::Colour;
+
+/** the open tasks blocked by the item with the ident */
+pub fn blocked_tasks(world: &mut World, ident: &str) -> FLResult<ItemListEntryList> {
+    let mut blocked = vec![];
+    for entry in world.search_type("Task")?.entries {
+        /* the item itself may be in use (as when it is being shown) */
+        if entry.link.ident == ident {
+            continue;
+        }
+        let item_ref = ItemLink::from(entry.link.clone()).resolve_link(world)?;
+        let item = item_ref.borrow();
+        if item.is_open() && item.blocked_by().iter().any(|b| b == ident) {
+            blocked.push(entry);
+        }
+    }
+    Ok(ItemListEntryList::from_vec(blocked))
+}
+
+/** the descriptions of the tasks blocked by the item with the ident that are ready (as when it has just been closed) */
+pub fn unblocked_by(world: &mut World, ident: &Ident) -> FLResult<Vec<String>> {
+    let mut unblocked = vec![];
+    for entry in blocked_tasks(world, ident)?.entries {
+        let item_ref = ItemLink::from(entry.link.clone()).resolve_link(world)?;
+        if item_ref.borrow_mut().is_ready(world)? {
+            unblocked.push(entry.descr);
+        }
+    }
+    Ok(unblocked)
+}
+
+/** tell the user which tasks are ready now that the item with the ident has been closed */
+pub fn notify_unblocked(
+    world: &mut World,
+    ident: &Ident,
+    resp: &mut fanling_interface::Response,
+) -> NullResult {
+    let unblocked = unblocked_by(world, ident)?;
+    trace(&format!("{} unblocked {:?}", ident, &unblocked));
+    if !unblocked.is_empty() {
+        resp.show_toast(&format!(
+            "{} {}",
+            world.i18n().tr("Now unblocked:"),
+            unblocked.join(", ")
+        ));
+    }
+    #[cfg(test)]
+    resp.set_test_data("unblocked", &unblocked.join(","));
+    Ok(())
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "blocking {}",
+        Colour::Fixed(0).on(Colour::Fixed(217)).paint(m)
+    );
+}
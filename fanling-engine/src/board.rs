@@ -0,0 +1,191 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! a kanban board showing tasks in columns by status.
+
+The order of the cards within a column is kept in the board order field of each item's [`ItemBase`]. */
+use crate::item::{Ident, ItemBase};
+use crate::shared::{FLResult, NullResult};
+use crate::task::TaskStatus;
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use serde::Deserialize;
+use std::ops::Deref;
+
+/** the columns of the board, in order */
+const COLUMNS: [(TaskStatus, &str); 3] = [
+    (TaskStatus::Open, "To do"),
+    (TaskStatus::InProgress, "In progress"),
+    (TaskStatus::Closed, "Done"),
+];
+/** the most cards shown in the last ("done") column */
+const MAX_DONE_CARDS: usize = 20;
+/** the gap between the board orders of adjacent cards */
+const ORDER_STEP: i64 = 10;
+
+/** the parts of a task's data needed for the board */
+#[derive(Deserialize)]
+struct TaskStatusForSerde {
+    #[serde(default)]
+    #[serde(deserialize_with = "TaskStatus::deserialize")]
+    status: TaskStatus,
+    /** old field from legacy data */
+    #[serde(default)]
+    closed: bool,
+}
+/** a task on the board */
+#[derive(Debug, Clone)]
+struct Card {
+    ident: Ident,
+    descr: String,
+    order: i64,
+}
+/** a column of the board, for the template */
+struct Column {
+    title: String,
+    cards: Vec<Card>,
+    /** number of cards not shown */
+    more: usize,
+    /** status of the column to the left ("" if none) */
+    prev_status: String,
+    /** status of the column to the right ("" if none) */
+    next_status: String,
+}
+
+/** template data for the board */
+#[derive(Template)]
+#[template(path = "board.html", print = "none")]
+struct BoardTemplate {
+    columns: Vec<Column>,
+}
+
+/** the cards in a column, sorted by board order (cards that have not been placed come last) */
+fn column_cards(world: &mut World, status: TaskStatus) -> FLResult<Vec<Card>> {
+    let mut cards = vec![];
+    for entry in world.search_type("Task")?.entries {
+        let (base, values) = world.get_item_parts(&entry.link.ident)?;
+        if crate::private::is_sealed(&values) {
+            continue;
+        }
+        let ts: TaskStatusForSerde = serde_yaml::from_value(values)?;
+        let card_status = if ts.closed {
+            TaskStatus::Closed
+        } else {
+            ts.status
+        };
+        if card_status == status {
+            cards.push(Card {
+                ident: entry.link.ident.clone(),
+                descr: entry.descr.clone(),
+                order: base.board_order,
+            });
+        }
+    }
+    cards.sort_by(|a, b| {
+        (a.order == 0, a.order, &a.descr).cmp(&(b.order == 0, b.order, &b.descr))
+    });
+    Ok(cards)
+}
+
+/** show the board */
+pub fn show_board(world: &mut World) -> fanling_interface::ResponseResult {
+    let mut columns = vec![];
+    for (i, (status, title)) in COLUMNS.iter().enumerate() {
+        let mut cards = column_cards(world, *status)?;
+        let mut more = 0;
+        if *status == TaskStatus::Closed && cards.len() > MAX_DONE_CARDS {
+            more = cards.len() - MAX_DONE_CARDS;
+            cards.truncate(MAX_DONE_CARDS);
+        }
+        columns.push(Column {
+            title: title.to_string(),
+            cards,
+            more,
+            prev_status: if i == 0 {
+                "".to_owned()
+            } else {
+                format!("{:?}", COLUMNS[i - 1].0)
+            },
+            next_status: COLUMNS
+                .get(i + 1)
+                .map_or("".to_owned(), |c| format!("{:?}", c.0)),
+        });
+    }
+    #[cfg(test)]
+    let idents: Vec<String> = columns
+        .iter()
+        .map(|c| {
+            c.cards
+                .iter()
+                .map(|card| card.ident.clone())
+                .collect::<Vec<String>>()
+                .join(",")
+        })
+        .collect();
+    let t = BoardTemplate { columns };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    resp.set_test_data("columns", &idents.join("|"));
+    Ok(resp)
+}
+
+/** put the card for the item at the bottom of the column for the status */
+pub fn place_card(base: &mut ItemBase, status: TaskStatus, world: &mut World) -> NullResult {
+    let ident = base.get_ident();
+    let last = column_cards(world, status)?
+        .iter()
+        .filter(|c| c.ident != ident)
+        .map(|c| c.order)
+        .max()
+        .unwrap_or(0);
+    base.set_board_order(last + ORDER_STEP);
+    Ok(())
+}
+
+/** move the card for the item one place up (or down) its column. The
+other cards in the column are renumbered as required. */
+pub fn move_card(
+    base: &mut ItemBase,
+    status: TaskStatus,
+    up: bool,
+    world: &mut World,
+) -> NullResult {
+    let ident = base.get_ident();
+    let mut cards = column_cards(world, status)?;
+    trace(&format!("moving {} {}", &ident, if up { "up" } else { "down" }));
+    let pos = match cards.iter().position(|c| c.ident == ident) {
+        Some(p) => p,
+        None => return Ok(()),
+    };
+    let other = if up {
+        match pos.checked_sub(1) {
+            Some(o) => o,
+            None => return Ok(()),
+        }
+    } else if pos + 1 < cards.len() {
+        pos + 1
+    } else {
+        return Ok(());
+    };
+    cards.swap(pos, other);
+    for (i, card) in cards.iter().enumerate() {
+        let order = (i as i64 + 1) * ORDER_STEP;
+        if card.ident == ident {
+            base.set_board_order(order);
+        } else if card.order != order {
+            let item_ref = world.get_item(card.ident.clone(), "Task".to_owned())?;
+            let mut item = item_ref.deref().borrow_mut();
+            item.set_board_order(order);
+            world.persist_change(&mut item)?;
+        }
+    }
+    Ok(())
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("board {}", Colour::Fixed(14).on(Colour::Fixed(233)).paint(m));
+}
@@ -0,0 +1,633 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! implements [`Bookmark`] items, web pages to come back to, each
+optionally with a snapshot of its readable text (as markdown) so that
+it can be read offline from the synced repository. The snapshot is
+taken (or taken again) with the "Re-fetch" action.
+
+On the desktop, the "Archive" action also keeps a copy of the page (its
+readable text as a web page of its own) as an attachment in the
+repository, so that it can still be read if the site disappears. Each
+copy is kept, and they are listed with the bookmark.
+
+Parts of the snapshot can be highlighted, each with a note for the
+margin (see [`crate::highlight`]). */
+use crate::highlight::Highlight;
+use crate::item::{Item, ItemBase, ItemBaseForSerde, ItemData, NewBaseTemplate, ShowBaseTemplate};
+use crate::markdown;
+use crate::shared::{merge_strings, FLResult, FanlingError, NullResult};
+use crate::world::{ActionResponse, World};
+use ansi_term::Colour;
+use askama::Template;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::boxed::Box;
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** data for a bookmark item */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    /** the title of the page */
+    name: String,
+    /** the address of the page */
+    #[serde(default)]
+    url: String,
+    /** words for finding the bookmark, separated by commas */
+    #[serde(default)]
+    tags: String,
+    /** notes about the page (markdown) */
+    #[serde(default)]
+    notes: String,
+    /** the readable text of the page when it was last fetched (markdown) */
+    #[serde(default)]
+    snapshot: String,
+    /** when the snapshot was taken */
+    #[serde(default)]
+    fetched: Option<NaiveDateTime>,
+    /** the paths of the archived copies of the page, the latest last */
+    #[serde(default)]
+    archives: Vec<String>,
+    /** the highlights in the snapshot, in the order they were made */
+    #[serde(default)]
+    highlights: Vec<Highlight>,
+}
+impl Bookmark {
+    /** create a new [Bookmark]  */
+    pub fn new() -> Self {
+        Self {
+            name: "".to_owned(),
+            url: "".to_owned(),
+            tags: "".to_owned(),
+            notes: "".to_owned(),
+            snapshot: "".to_owned(),
+            fetched: None,
+            archives: vec![],
+            highlights: vec![],
+        }
+    }
+    fn set_from_yaml_basic(&mut self, yaml: &serde_yaml::Value) -> NullResult {
+        *self = serde_yaml::from_value(yaml.clone())?;
+        Ok(())
+    }
+    /** the tags, in the order given */
+    fn tag_list(&self) -> Vec<String> {
+        self.tags
+            .split(',')
+            .map(|t| t.trim().to_owned())
+            .filter(|t| !t.is_empty())
+            .collect()
+    }
+    /** the host name in the URL (eg "example.com"), if there is one */
+    fn host(&self) -> &str {
+        let rest = self.url.split("://").nth(1).unwrap_or("");
+        rest.split(|c| c == '/' || c == '?' || c == '#')
+            .next()
+            .unwrap_or("")
+    }
+    /** keep a snapshot of a page that has been fetched */
+    fn set_snapshot(&mut self, page: crate::fetch::Page) {
+        if self.name.is_empty() {
+            self.name = page.title;
+        }
+        self.snapshot = page.text;
+        self.fetched = Some(chrono::Local::now().naive_local());
+    }
+    /** fetch the page again, keeping its text as the snapshot */
+    fn refetch(
+        &mut self,
+        base: &mut ItemBase,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        match crate::fetch::fetch_page(&self.url) {
+            Ok(page) => {
+                self.set_snapshot(page);
+                self.for_show(base, world)
+            }
+            Err(e) => {
+                trace(&format!("could not fetch {}: {:?}", self.url, e));
+                let mut resp = self.for_show(base, world)?;
+                resp.add_tag(
+                    "message",
+                    &world.i18n().tr("The page could not be fetched."),
+                );
+                #[cfg(test)]
+                resp.set_test_data("fetched", "false");
+                Ok(resp)
+            }
+        }
+    }
+    /** fetch the page and keep a copy of it as an attachment (and its text as the snapshot) */
+    fn archive_page(
+        &mut self,
+        base: &mut ItemBase,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let refusal = if matches!(world.interface_type(), crate::InterfaceType::Android) {
+            Some("Pages can only be archived on the desktop.")
+        } else if base.is_private() {
+            Some("Private bookmarks cannot be archived.")
+        } else {
+            None
+        };
+        if let Some(refusal) = refusal {
+            let mut resp = self.for_show(base, world)?;
+            resp.add_tag("message", &world.i18n().tr(refusal));
+            return Ok(resp);
+        }
+        let page = match crate::fetch::fetch_page(&self.url) {
+            Ok(page) => page,
+            Err(e) => {
+                trace(&format!("could not fetch {}: {:?}", self.url, e));
+                let mut resp = self.for_show(base, world)?;
+                resp.add_tag(
+                    "message",
+                    &world.i18n().tr("The page could not be fetched."),
+                );
+                #[cfg(test)]
+                resp.set_test_data("fetched", "false");
+                return Ok(resp);
+            }
+        };
+        let now = chrono::Local::now().naive_local();
+        let archive = ArchiveTemplate {
+            title: page.title.clone(),
+            url: self.url.clone(),
+            when: world.format().format_date_time(now),
+            rendered_text: markdown::render(
+                &page.text.replace('<', "&lt;"),
+                &world.render_context(),
+            ),
+        }
+        .render()?;
+        let path = archive_path(&base.get_ident(), now);
+        world.add_attachment(&path, &archive)?;
+        self.archives.push(path);
+        self.set_snapshot(page);
+        self.for_show(base, world)
+    }
+    /** highlight some of the snapshot (the first place it is not already highlighted) */
+    fn add_highlight(
+        &mut self,
+        base: &mut ItemBase,
+        quote: &str,
+        note: &str,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let quote = quote.trim();
+        let refusal = if quote.is_empty() {
+            Some("Select some of the snapshot to highlight.")
+        } else if quote.contains("\n\n") {
+            Some("A highlight must be within a paragraph.")
+        } else {
+            match crate::highlight::find(&self.snapshot, quote, &self.highlights) {
+                Some(start) => {
+                    self.highlights.push(Highlight {
+                        quote: quote.to_owned(),
+                        start,
+                        note: note.trim().to_owned(),
+                        when: chrono::Local::now().naive_local(),
+                    });
+                    None
+                }
+                None => Some("That text is not in the snapshot, or is already highlighted."),
+            }
+        };
+        let mut resp = self.for_show(base, world)?;
+        if let Some(refusal) = refusal {
+            resp.add_tag("message", &world.i18n().tr(refusal));
+        }
+        Ok(resp)
+    }
+    /** remove a highlight */
+    fn remove_highlight(
+        &mut self,
+        base: &mut ItemBase,
+        index: usize,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        if index >= self.highlights.len() {
+            return Err(fanling_error!(&format!("no highlight {}", index)).into());
+        }
+        self.highlights.remove(index);
+        self.for_show(base, world)
+    }
+}
+
+/** the directory of the files attached to items */
+pub(crate) const ATTACHMENTS_DIR: &str = "attachments";
+/** the format of the time in the name of an archived copy */
+const ARCHIVE_TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+/** the path for a copy of a bookmark's page archived at a time */
+fn archive_path(ident: &str, when: NaiveDateTime) -> String {
+    format!(
+        "{}/{}/{}.html",
+        ATTACHMENTS_DIR,
+        ident,
+        when.format(ARCHIVE_TIME_FORMAT)
+    )
+}
+/** when a copy was archived, as shown to the user (or its path, if that has no time) */
+fn archive_label(path: &str, world: &World) -> String {
+    let stem = path
+        .rsplit('/')
+        .next()
+        .unwrap_or("")
+        .trim_end_matches(".html");
+    match NaiveDateTime::parse_from_str(stem, ARCHIVE_TIME_FORMAT) {
+        Ok(when) => world.format().format_date_time(when),
+        Err(_) => path.to_owned(),
+    }
+}
+/** show an archived copy of the page of a bookmark */
+pub fn show_archived_page(
+    item: &Item,
+    path: &str,
+    world: &mut World,
+) -> fanling_interface::ResponseResult {
+    let ident = item.ident();
+    if !path.starts_with(&format!("{}/{}/", ATTACHMENTS_DIR, ident)) {
+        return Err(fanling_error!(&format!("{} is not archived for {}", path, ident)).into());
+    }
+    let t = ShowArchiveTemplate {
+        ident,
+        type_name: item.type_name(),
+        descr: item.description(),
+        when: archive_label(path, world),
+        archive: world.attachment(path)?,
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    resp.set_test_data("archive", &t.archive);
+    Ok(resp)
+}
+
+impl crate::item::ItemData for Bookmark {
+    fn for_edit(
+        &mut self,
+        base: &mut ItemBase,
+        is_for_update: bool,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let nt = NewBookmarkTemplate {
+            data: &self,
+            base: NewBaseTemplate::from_base(base, is_for_update, world)?,
+            broken_notes: self.notes.replace("\n", "&#10;"),
+        };
+        let mut resp = fanling_interface::Response::new();
+        resp.clear_errors(vec!["url-error".to_owned()]);
+        resp.add_tag("content", &(nt.render()?));
+        #[cfg(test)]
+        {
+            resp.set_test_data("ident", &base.get_ident());
+        }
+        trace(&format!("for edit {:?}", &resp));
+        Ok(resp)
+    }
+    fn tags(&self) -> Vec<String> {
+        self.tag_list()
+    }
+    fn set_tags(&mut self, tags: &[String], _world: &mut World) -> FLResult<bool> {
+        self.tags = tags.join(", ");
+        Ok(true)
+    }
+    fn highlights(&self) -> Vec<Highlight> {
+        self.highlights.clone()
+    }
+    /** the snapshot can be taken again, and the page archived */
+    fn available_actions(&self, base: &ItemBase) -> Vec<crate::item::AvailableAction> {
+        vec![
+            crate::item::AvailableAction::new(base, crate::Action::Refetch, "Re-fetch", "🔄"),
+            crate::item::AvailableAction::new(
+                base,
+                crate::Action::ArchivePage,
+                "Archive page",
+                "🗄",
+            ),
+        ]
+    }
+    fn for_show(
+        &mut self,
+        base: &mut ItemBase,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let t = ShowBookmarkTemplate {
+            name: self.description(),
+            url: self.url.clone(),
+            tags: self.tag_list(),
+            rendered_notes: markdown::render(&self.notes, &world.render_context()),
+            rendered_snapshot: crate::highlight::render(
+                &self.snapshot,
+                &self.highlights,
+                &world.render_context(),
+            ),
+            fetched: self
+                .fetched
+                .map_or("".to_owned(), |f| world.format().format_date_time(f)),
+            archives: self
+                .archives
+                .iter()
+                .rev()
+                .map(|p| -> FLResult<(String, String)> {
+                    Ok((
+                        serde_json::to_string(&crate::Action::ShowArchivedPage(p.clone()))?,
+                        archive_label(p, world),
+                    ))
+                })
+                .collect::<FLResult<Vec<(String, String)>>>()?,
+            highlights: self
+                .highlights
+                .iter()
+                .enumerate()
+                .map(|(i, h)| -> FLResult<(String, String, String)> {
+                    Ok((
+                        serde_json::to_string(&crate::Action::RemoveHighlight(i))?,
+                        h.quote.clone(),
+                        h.note.clone(),
+                    ))
+                })
+                .collect::<FLResult<Vec<(String, String, String)>>>()?,
+            base: ShowBaseTemplate::from_base(base, world)?
+                .with_actions(base, self.available_actions(base))
+                .with_stats(self.stats(base, world)?),
+        };
+        let mut resp = fanling_interface::Response::new();
+        resp.add_tag("content", &(t.render()?));
+        #[cfg(test)]
+        {
+            resp.set_test_data("ident", &base.get_ident());
+            resp.set_test_data("tags", &self.tag_list().join(","));
+            resp.set_test_data("archives", &format!("{}", self.archives.len()));
+            resp.set_test_data("highlights", &format!("{}", self.highlights.len()));
+        }
+        trace(&format!("for show {:?}", &resp));
+        Ok(resp)
+    }
+    fn to_yaml(&self, base: &crate::item::ItemBase) -> Result<Vec<u8>, FanlingError> {
+        let for_serde = BookmarkForSerde {
+            base: crate::item::ItemBaseForSerde::from_base(base)?,
+            data: self.clone(),
+        };
+        let yaml = serde_yaml::to_vec(&for_serde)?;
+        trace(&format!("yaml is {}", String::from_utf8_lossy(&yaml)));
+        Ok(yaml)
+    }
+    fn is_open(&self) -> bool {
+        true
+    }
+    fn is_ready(&mut self, _world: &mut World) -> FLResult<bool> {
+        Ok(false)
+    }
+    /** can be turned into an ident */
+    fn descr_for_ident(&self) -> String {
+        self.description()
+    }
+    /** an English-language description */
+    fn description(&self) -> String {
+        if self.name.is_empty() {
+            self.url.clone()
+        } else {
+            self.name.clone()
+        }
+    }
+    /** a description that can be used in a list */
+    fn description_for_list(&self) -> String {
+        if self.name.is_empty() || self.host().is_empty() {
+            self.description()
+        } else {
+            format!("{} ({})", self.name, self.host())
+        }
+    }
+    fn set_data(&mut self, vals: &HashMap<String, String>, _world: &mut World) -> NullResult {
+        match vals.get("url") {
+            Some(s) => self.url = s.trim().to_owned(),
+            _ => return Err(fanling_error!("no url")),
+        }
+        self.name = vals
+            .get("name")
+            .map_or("".to_owned(), |s| s.trim().to_owned());
+        self.tags = vals
+            .get("tags")
+            .map_or("".to_owned(), |s| s.trim().to_owned());
+        self.notes = vals.get("notes").map_or("".to_owned(), |s| s.to_string());
+        /* a snapshot is only given when the page has just been fetched (eg when it is shared) */
+        if let Some(snapshot) = vals.get("snapshot") {
+            self.snapshot = snapshot.clone();
+            self.fetched = Some(chrono::Local::now().naive_local());
+        }
+        Ok(())
+    }
+    fn set_from_yaml(&mut self, yaml: &serde_yaml::Value, _world: &mut World) -> NullResult {
+        self.set_from_yaml_basic(yaml)
+    }
+    /** do action for bookmark */
+    fn do_action(
+        &mut self,
+        base: &mut ItemBase,
+        action: crate::Action,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        match action {
+            crate::Action::Refetch => self.refetch(base, world),
+            crate::Action::ArchivePage => self.archive_page(base, world),
+            crate::Action::Highlight { quote, note } => {
+                self.add_highlight(base, &quote, &note, world)
+            }
+            crate::Action::RemoveHighlight(index) => self.remove_highlight(base, index, world),
+            _ => Err(fanling_error!(&format!("bookmark cannot do {:?}", action)).into()),
+        }
+    }
+    /** copy from another item data */
+    fn fanling_clone(&self) -> FLResult<Box<dyn ItemData>> {
+        Ok(Box::new(self.clone()))
+    }
+    /** transitional to fix old data */
+    fn fix_data(
+        &self,
+        _yaml: &serde_yaml::Value,
+        _base: &mut ItemBase,
+        _world: &mut World,
+    ) -> NullResult {
+        Ok(())
+    }
+}
+impl Default for Bookmark {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+#[derive(Serialize, Deserialize)]
+struct BookmarkForSerde {
+    #[serde(flatten)]
+    base: crate::item::ItemBaseForSerde,
+    #[serde(flatten)]
+    data: Bookmark,
+}
+/** template data for creating a new bookmark */
+#[derive(Template)]
+#[template(path = "new-bookmark.html", print = "none")]
+struct NewBookmarkTemplate<'a> {
+    data: &'a Bookmark,
+    base: NewBaseTemplate,
+    broken_notes: String,
+}
+
+/** template data for showing a bookmark */
+#[derive(Template)]
+#[template(path = "show-bookmark.html", print = "none")]
+struct ShowBookmarkTemplate {
+    name: String,
+    url: String,
+    tags: Vec<String>,
+    rendered_notes: String,
+    rendered_snapshot: String,
+    fetched: String,
+    /** the archived copies, latest first, as the action to show each one (as JSON) and when it was archived */
+    archives: Vec<(String, String)>,
+    /** the highlights, as the action to remove each one (as JSON), the text highlighted and the note */
+    highlights: Vec<(String, String, String)>,
+    base: ShowBaseTemplate,
+}
+
+/** template data for an archived copy of a page (a web page of its own) */
+#[derive(Template)]
+#[template(path = "archive.html", print = "none")]
+struct ArchiveTemplate {
+    title: String,
+    url: String,
+    when: String,
+    rendered_text: String,
+}
+
+/** template data for showing an archived copy of a bookmark's page */
+#[derive(Template)]
+#[template(path = "show-archive.html", print = "none")]
+struct ShowArchiveTemplate {
+    ident: String,
+    type_name: String,
+    descr: String,
+    when: String,
+    archive: String,
+}
+
+/** policy for the bookmark item type*/
+#[derive(Debug)]
+pub struct BookmarkTypePolicy {}
+impl BookmarkTypePolicy {
+    pub fn new() -> Self {
+        Self {}
+    }
+    pub fn new_boxed() -> Box<Self> {
+        Box::new(Self::new())
+    }
+}
+impl crate::item::ItemTypePolicy for BookmarkTypePolicy {
+    fn kind(&self) -> crate::item::ItemKind {
+        crate::item::ItemKind::Bookmark
+    }
+    fn make_raw(&self, item_type: crate::item::ItemTypeRef) -> Item {
+        Item::new_with_data(item_type, Box::new(Bookmark::new()))
+    }
+    fn resolve_conflict_both(
+        &self,
+        _world: &mut World,
+        _ancestor: &Value,
+        ours: &Value,
+        theirs: &Value,
+    ) -> FLResult<Box<dyn ItemData>> {
+        let mut ob = Bookmark::new();
+        ob.set_from_yaml_basic(&ours)?;
+        let mut tb = Bookmark::new();
+        tb.set_from_yaml_basic(&theirs)?;
+        ob.name = merge_strings(&ob.name, &tb.name);
+        ob.url = merge_strings(&ob.url, &tb.url);
+        ob.tags = merge_strings(&ob.tags, &tb.tags);
+        ob.notes = merge_strings(&ob.notes, &tb.notes);
+        /* the copies archived on either side are all kept */
+        for archive in tb.archives {
+            if !ob.archives.contains(&archive) {
+                ob.archives.push(archive);
+            }
+        }
+        ob.archives.sort();
+        /* as are the highlights made on either side */
+        for highlight in tb.highlights {
+            if !ob.highlights.contains(&highlight) {
+                ob.highlights.push(highlight);
+            }
+        }
+        ob.highlights.sort_by_key(|h| h.when);
+        /* the later snapshot is kept */
+        if tb.fetched > ob.fetched {
+            ob.snapshot = tb.snapshot;
+            ob.fetched = tb.fetched;
+        }
+        Ok(Box::new(ob))
+    }
+    fn check_valid(
+        &mut self,
+        _base: &ItemBaseForSerde,
+        vals: &HashMap<String, String>,
+        _world: &mut World,
+    ) -> ActionResponse {
+        let mut ar = ActionResponse::new();
+        let url = vals.get("url").map_or("", |u| u.trim());
+        ar.assert(!url.is_empty(), "url-error", "URL must be non-blank.");
+        if !url.is_empty() {
+            ar.assert(
+                url.starts_with("http://") || url.starts_with("https://"),
+                "url-error",
+                "URL must start with http:// or https://.",
+            );
+        }
+        ar
+    }
+    /** get item data from serde value */
+    fn from_yaml(&self, values: &Value, world: &mut World) -> FLResult<Box<dyn ItemData>> {
+        let mut b = Bookmark::default();
+        b.set_from_yaml(&values, world)?;
+        Ok(Box::new(b))
+    }
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "bookmark {}",
+        Colour::Fixed(12).on(Colour::Fixed(230)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn parts() {
+        let mut b = Bookmark::new();
+        b.url = "https://www.example.com/a/b?c#d".to_owned();
+        b.tags = " rust, ,reading ".to_owned();
+        assert_eq!("www.example.com", b.host());
+        assert_eq!(vec!["rust", "reading"], b.tag_list());
+        assert_eq!("https://www.example.com/a/b?c#d", b.description());
+        b.set_snapshot(crate::fetch::Page {
+            title: "Example".to_owned(),
+            text: "Some text.".to_owned(),
+            ..crate::fetch::Page::default()
+        });
+        assert_eq!("Example (www.example.com)", b.description_for_list());
+        assert!(b.fetched.is_some());
+        let when =
+            NaiveDateTime::parse_from_str("2020-01-02 03:04:05", "%Y-%m-%d %H:%M:%S").unwrap();
+        assert_eq!(
+            "attachments/b1/20200102-030405.html",
+            archive_path("b1", when)
+        );
+    }
+}
@@ -0,0 +1,205 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! encrypted bundles of items, for backups and for exporting some
+types of item.
+
+A bundle holds the YAML for each item, encrypted in the same way as
+the items of an encrypted repository, with a key derived from a
+passphrase given when the bundle is exported. The passphrase is asked
+for again when the bundle is imported, so a bundle can be kept in
+somewhere like cloud storage without the items being readable there.
+Private items stay sealed with their own passphrases.
+
+Importing adds the items that are not already in the repository,
+keeping their idents so that links between them still work. */
+use crate::crypt::{self, Cipher};
+use crate::item::{split_data_parts, ItemListEntry, ItemListEntryList};
+use crate::shared::{FLResult, FanlingError};
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use std::fs;
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** the first line of a bundle */
+const HEADER: &str = "fanling-bundle-v1";
+/** starts the line of a bundle giving the salt */
+const SALT_PREFIX: &str = "salt: ";
+
+/** the text of a bundle holding the items (each given as its YAML) */
+pub fn seal_items(items: &[String], passphrase: &str) -> FLResult<String> {
+    if passphrase.is_empty() {
+        return Err(fanling_error!("the passphrase must not be blank"));
+    }
+    let (cipher, salt) = Cipher::for_item(passphrase, None)?;
+    let sealed = cipher.seal(&serde_json::to_string(items)?)?;
+    Ok(format!("{}\n{}{}\n{}", HEADER, SALT_PREFIX, salt, sealed))
+}
+/** the items (each given as its YAML) in the text of a bundle */
+pub fn open_items(text: &str, passphrase: &str) -> FLResult<Vec<String>> {
+    let mut lines = text.trim().lines();
+    if lines.next().map(str::trim) != Some(HEADER) {
+        return Err(fanling_error!("not a fanling bundle"));
+    }
+    let salt = match lines.next().map(str::trim) {
+        Some(line) if line.starts_with(SALT_PREFIX) => &line[SALT_PREFIX.len()..],
+        _ => return Err(fanling_error!("bundle has no salt")),
+    };
+    let sealed = lines.next().unwrap_or("").trim();
+    if !crypt::is_encrypted(sealed) {
+        return Err(fanling_error!("bundle is not encrypted"));
+    }
+    let (cipher, _salt) = Cipher::for_item(passphrase, Some(salt))?;
+    Ok(serde_json::from_str(&cipher.open(sealed)?)?)
+}
+
+/** a type of item, and whether it is chosen */
+struct TypeChoice {
+    name: &'static str,
+    chosen: bool,
+}
+/** template data for exporting and importing bundles */
+#[derive(Template)]
+#[template(path = "bundle.html", print = "none")]
+struct BundleTemplate {
+    message: String,
+    types: Vec<TypeChoice>,
+}
+/** show the form for exporting and importing bundles, with a
+message and the types chosen for exporting (all of them, if none are given) */
+pub fn show_bundle(message: &str, chosen: &[String]) -> fanling_interface::ResponseResult {
+    let t = BundleTemplate {
+        message: message.to_owned(),
+        types: crate::kinds::kinds()
+            .iter()
+            .map(|k| TypeChoice {
+                name: k.name,
+                chosen: chosen.is_empty() || chosen.iter().any(|c| c == k.name),
+            })
+            .collect(),
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    resp.set_prompt("bundle-passphrase");
+    #[cfg(test)]
+    resp.set_test_data("message", message);
+    Ok(resp)
+}
+
+/** write a bundle of the items of the types (or all the items, if
+none are given) to the export directory */
+pub fn export_bundle(
+    world: &mut World,
+    types: &[String],
+    passphrase: &str,
+) -> fanling_interface::ResponseResult {
+    if passphrase.is_empty() {
+        return show_bundle("Please give a passphrase for the bundle.", types);
+    }
+    let mut items = vec![];
+    for yaml in world.all_item_yaml()? {
+        let (base, _values) = split_data_parts(yaml.as_bytes())?;
+        if types.is_empty() || types.contains(&base.type_name) {
+            items.push(yaml);
+        }
+    }
+    let text = seal_items(&items, passphrase)?;
+    let dir = world.export_dir().to_path_buf();
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!(
+        "fanling-{}.bundle",
+        chrono::Local::today()
+            .naive_local()
+            .format(crate::format::ISO_DATE_FORMAT)
+    ));
+    fs::write(&path, &text)?;
+    trace(&format!("exported {} items to {:?}", items.len(), &path));
+    let mut resp = show_bundle(
+        &format!("Exported {} items to {}", items.len(), path.display()),
+        types,
+    )?;
+    #[cfg(test)]
+    {
+        resp.set_test_data("count", &format!("{}", items.len()));
+        resp.set_test_data("path", &path.to_string_lossy());
+    }
+    Ok(resp)
+}
+
+/** add the items that are not already in the repository to those
+imported, returning how many were already there */
+fn import_all(
+    world: &mut World,
+    items: &[String],
+    imported: &mut ItemListEntryList,
+) -> FLResult<usize> {
+    let mut skipped = 0;
+    for yaml in items {
+        let (base, values) = split_data_parts(yaml.as_bytes())?;
+        match world.import_item(&base, &values)? {
+            Some(item_ref) => imported.add(ItemListEntry::from_item(&item_ref.borrow())?),
+            None => skipped += 1,
+        }
+    }
+    Ok(skipped)
+}
+/** add the items in a bundle that are not already in the repository,
+then list them */
+pub fn import_bundle(
+    world: &mut World,
+    text: &str,
+    passphrase: &str,
+) -> fanling_interface::ResponseResult {
+    let items = match open_items(text, passphrase) {
+        Ok(items) => items,
+        Err(e) => {
+            trace(&format!("cannot open bundle: {:?}", e));
+            return show_bundle(&format!("Cannot import the bundle: {}", e), &[]);
+        }
+    };
+    let mut imported = ItemListEntryList::from_vec(vec![]);
+    /* the batch is ended even if an item cannot be imported, so that later changes are committed */
+    world.begin_batch();
+    let result = import_all(world, &items, &mut imported);
+    world.end_batch()?;
+    let skipped = result?;
+    trace(&format!(
+        "imported {} items ({} already present)",
+        imported.num_entries(),
+        skipped
+    ));
+    let resp = World::show_list(&mut imported, "imported")?;
+    #[cfg(test)]
+    let resp = {
+        let mut resp = resp;
+        resp.set_test_data("skipped", &format!("{}", skipped));
+        resp
+    };
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("bundle {}", Colour::Fixed(13).on(Colour::Fixed(233)).paint(m));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn seal_and_open() -> FLResult<()> {
+        let items = vec!["ident: a\ntype: Simple\nname: hello\n".to_owned()];
+        let text = seal_items(&items, "secret")?;
+        assert!(text.starts_with(HEADER));
+        assert!(!text.contains("hello"));
+        assert_eq!(items, open_items(&text, "secret")?);
+        assert!(open_items(&text, "wrong").is_err());
+        assert!(open_items("name: hello\n", "secret").is_err());
+        assert!(seal_items(&items, "").is_err());
+        Ok(())
+    }
+}
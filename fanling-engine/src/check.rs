@@ -0,0 +1,299 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! a maintenance check of the items in the repository, finding links
+to missing items (wiki links and parents), malformed YAML, items
+stored more than once, items whose idents collide (see
+[`crate::idents`]) and items that cannot be read. The problems are
+shown on a page, with a button to fix those that can be fixed (by
+making the missing item, by removing the missing parent, or by giving
+the item a new ident).
+
+Private items are only checked for being stored more than once and for
+malformed YAML, as the rest of them is encrypted.
+
+The page also says how many items are due to be archived or flagged
+under the retention policies (see [`crate::retention`]), and shows the
+report of a merge that was rolled back when Fanling started, if there
+was one (see [`crate::rollback`]). */
+use crate::item::{split_data_parts, Ident};
+use crate::shared::{FLResult, NullResult};
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::DerefMut;
+
+/** a way of fixing a problem */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Fix {
+    /** make a simple item with the ident */
+    CreateMissing(Ident),
+    /** remove the parent of the item */
+    RemoveParent(Ident),
+    /** give the item stored with the ident a new ident */
+    NewIdent(Ident),
+}
+/** something wrong with an item */
+#[derive(Debug, Clone)]
+pub struct Problem {
+    /** the item with the problem */
+    pub ident: Ident,
+    /** what is wrong */
+    pub descr: String,
+    /** how it can be fixed, if it can be */
+    pub fix: Option<Fix>,
+    /** whether the item can still be read (and so shown) */
+    pub readable: bool,
+}
+impl Problem {
+    /** a problem with an item that can still be read */
+    fn new(ident: &str, descr: &str, fix: Option<Fix>) -> Self {
+        Self {
+            ident: ident.to_owned(),
+            descr: descr.to_owned(),
+            fix,
+            readable: true,
+        }
+    }
+    /** a problem that stops the item from being read */
+    fn unreadable(ident: &str, descr: &str) -> Self {
+        Self {
+            readable: false,
+            ..Self::new(ident, descr, None)
+        }
+    }
+}
+
+/** the problems with the items stored at the paths (with the idents
+from the paths and the serialised data) */
+pub fn check(world: &mut World, stored: &[(String, Ident, String)]) -> FLResult<Vec<Problem>> {
+    let mut problems = vec![];
+    let mut paths: BTreeMap<&Ident, Vec<&String>> = BTreeMap::new();
+    for (path, ident, _data) in stored {
+        paths.entry(ident).or_default().push(path);
+    }
+    for (ident, paths) in &paths {
+        if paths.len() > 1 {
+            let paths: Vec<&str> = paths.iter().map(|p| p.as_str()).collect();
+            problems.push(Problem::new(
+                ident,
+                &format!("stored more than once ({})", paths.join(", ")),
+                None,
+            ));
+        }
+    }
+    let idents: BTreeSet<&Ident> = paths.keys().cloned().collect();
+    let current = world.repo_name();
+    let pairs: Vec<(Ident, Ident)> = stored
+        .iter()
+        .filter_map(|(_path, ident, data)| {
+            split_data_parts(data.as_bytes())
+                .ok()
+                .map(|(base, _values)| (ident.clone(), base.ident))
+        })
+        .collect();
+    let collisions = crate::idents::collisions(&pairs);
+    for collision in &collisions {
+        problems.push(Problem {
+            readable: pairs
+                .iter()
+                .any(|(path, data)| *path == collision.ident && path == data),
+            ..Problem::new(
+                &collision.ident,
+                &collision.descr,
+                Some(Fix::NewIdent(collision.ident.clone())),
+            )
+        });
+    }
+    for (_path, ident, data) in stored {
+        let (base, values) = match split_data_parts(data.as_bytes()) {
+            Ok(parts) => parts,
+            Err(e) => {
+                problems.push(Problem::unreadable(
+                    ident,
+                    &format!("malformed YAML: {}", e),
+                ));
+                continue;
+            }
+        };
+        if base.ident != *ident {
+            if !collisions.iter().any(|c| c.ident == *ident) {
+                problems.push(Problem {
+                    fix: Some(Fix::NewIdent(ident.clone())),
+                    ..Problem::unreadable(
+                        ident,
+                        &format!("has the ident '{}' in its data", base.ident),
+                    )
+                });
+            }
+            continue;
+        }
+        if world.kind_of(&base.type_name).is_none() {
+            problems.push(Problem::new(
+                ident,
+                &format!(
+                    "has a type ('{}') unknown to this version (or to its plugins), so is read-only",
+                    base.type_name
+                ),
+                None,
+            ));
+            continue;
+        }
+        if crate::private::is_sealed(&values) {
+            continue;
+        }
+        if world.get_item_if_known(ident).is_none() {
+            let item_type_rcrc = world.get_item_type(base.type_name.clone())?;
+            let item_type = item_type_rcrc.borrow();
+            if let Err(e) = world.make_and_populate_item(&item_type, &base, &values) {
+                problems.push(Problem::unreadable(
+                    ident,
+                    &format!("cannot be read: {}", e),
+                ));
+                continue;
+            }
+        }
+        if let Some(parent) = base.parent.as_ref().filter(|p| !p.is_empty()) {
+            if !idents.contains(parent) {
+                problems.push(Problem::new(
+                    ident,
+                    &format!("has a missing parent '{}'", parent),
+                    Some(Fix::RemoveParent(ident.clone())),
+                ));
+            }
+        }
+        /* links to items in other repositories are not checked */
+        let mut missing: Vec<Ident> = crate::graph::wiki_links(data)?
+            .iter()
+            .filter_map(|l| crate::repos::local_link(l, &current))
+            .map(|l| l.to_owned())
+            .filter(|l| !idents.contains(l) && !world.aliases().contains_key(l))
+            .collect();
+        missing.sort();
+        missing.dedup();
+        for link in missing {
+            problems.push(Problem::new(
+                ident,
+                &format!("links to a missing item '{}'", link),
+                Some(Fix::CreateMissing(link)),
+            ));
+        }
+    }
+    trace(&format!(
+        "{} items checked, {} problems",
+        stored.len(),
+        problems.len()
+    ));
+    Ok(problems)
+}
+
+/** fix a problem */
+pub fn fix(world: &mut World, fix: &Fix) -> NullResult {
+    trace(&format!("fixing: {:?}", fix));
+    match fix {
+        Fix::CreateMissing(ident) => {
+            if !world.has_item(ident)? {
+                world.ensure_item(ident.clone(), "Simple".to_owned())?;
+            }
+        }
+        Fix::RemoveParent(ident) => {
+            let item_ref = world.get_item(ident.clone(), "Simple".to_owned())?;
+            let mut item = item_ref.borrow_mut();
+            item.set_parent(None);
+            world.persist_change(item.deref_mut())?;
+        }
+        Fix::NewIdent(ident) => {
+            world.give_new_ident(ident)?;
+        }
+    }
+    Ok(())
+}
+
+/** a problem as shown on the page */
+struct ProblemForShow {
+    /** the item with the problem */
+    ident: Ident,
+    /** whether the item can be shown */
+    can_show: bool,
+    /** what is wrong */
+    descr: String,
+    /** the fix as JSON for the action (blank if it cannot be fixed) */
+    fix: String,
+}
+/** template data for the check page */
+#[derive(Template)]
+#[template(path = "check.html", print = "none")]
+struct CheckTemplate {
+    /** the problems found */
+    problems: Vec<ProblemForShow>,
+    /** the number of items checked */
+    item_count: usize,
+    /** the number of items due under the retention policies */
+    retention_due: usize,
+    /** the report of an operation that was rolled back, as HTML (blank if none) */
+    recovery: String,
+    /** a message, such as what was fixed */
+    message: String,
+}
+
+/** check the items and show the problems */
+pub fn show_check(world: &mut World, message: &str) -> fanling_interface::ResponseResult {
+    let (problems, item_count) = world.check()?;
+    let policies = world.retention_policies()?;
+    let retention_due = crate::retention::find_due(world, &policies)?.len();
+    let recovery = match world.take_recovery() {
+        Some(report) => crate::rollback::render(&report)?,
+        None => "".to_owned(),
+    };
+    let mut for_show = vec![];
+    for problem in &problems {
+        for_show.push(ProblemForShow {
+            ident: problem.ident.clone(),
+            can_show: problem.readable,
+            descr: problem.descr.clone(),
+            fix: match &problem.fix {
+                Some(fix) => serde_json::to_string(&crate::Action::FixProblem(fix.clone()))?,
+                None => "".to_owned(),
+            },
+        });
+    }
+    let t = CheckTemplate {
+        problems: for_show,
+        item_count,
+        retention_due,
+        recovery,
+        message: message.to_owned(),
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    {
+        resp.set_test_data("problems", &format!("{}", problems.len()));
+        resp.set_test_data(
+            "fixes",
+            &format!("{}", problems.iter().filter(|p| p.fix.is_some()).count()),
+        );
+        resp.set_test_data("retention", &format!("{}", retention_due));
+    }
+    Ok(resp)
+}
+/** fix a problem, then check again */
+pub fn fix_and_show(world: &mut World, problem_fix: &Fix) -> fanling_interface::ResponseResult {
+    fix(world, problem_fix)?;
+    show_check(
+        world,
+        match problem_fix {
+            Fix::CreateMissing(_) => "The missing item has been made.",
+            Fix::RemoveParent(_) => "The missing parent has been removed.",
+            Fix::NewIdent(_) => "The item has been given a new ident.",
+        },
+    )
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("check {}", Colour::Fixed(9).on(Colour::Fixed(236)).paint(m));
+}
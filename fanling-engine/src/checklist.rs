@@ -0,0 +1,625 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! implements [`Checklist`] items (an ordered list of entries that can be checked off, such as a shopping list).
+
+A checklist that is a template (such as a packing list) is used by
+making a fresh, dated copy of it with nothing checked. The copy is
+linked back to the template, and the template shows how often each of
+its entries has been skipped (left unchecked in a copy that has been
+started). */
+use crate::item::{
+    Ident, Item, ItemBase, ItemBaseForSerde, ItemData, NewBaseTemplate, ShowBaseTemplate,
+};
+use crate::shared::{merge_strings, FLResult, FanlingError, NullResult};
+use crate::world::{ActionResponse, World};
+use ansi_term::Colour;
+use askama::Template;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::boxed::Box;
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** prefix for a checked entry when the entries are edited as text */
+const CHECKED_PREFIX: &str = "[x] ";
+/** prefix for an unchecked entry when the entries are edited as text */
+const UNCHECKED_PREFIX: &str = "[ ] ";
+
+/** a single entry in a checklist */
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CheckEntry {
+    /** what is to be checked */
+    text: String,
+    /** whether it has been checked */
+    #[serde(default)]
+    checked: bool,
+}
+impl CheckEntry {
+    /** create a new unchecked [CheckEntry] */
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_owned(),
+            checked: false,
+        }
+    }
+    /** parse a line of text, as written by [CheckEntry::to_line] */
+    fn from_line(line: &str) -> Option<Self> {
+        let line = line.trim();
+        let (checked, text) = if line.starts_with(CHECKED_PREFIX) || line.starts_with("[X] ") {
+            (true, &line[CHECKED_PREFIX.len()..])
+        } else if line.starts_with(UNCHECKED_PREFIX) {
+            (false, &line[UNCHECKED_PREFIX.len()..])
+        } else {
+            (false, line)
+        };
+        let text = text.trim();
+        if text.is_empty() {
+            None
+        } else {
+            Some(Self {
+                text: text.to_owned(),
+                checked,
+            })
+        }
+    }
+    /** a line of text for editing */
+    fn to_line(&self) -> String {
+        format!(
+            "{}{}",
+            if self.checked {
+                CHECKED_PREFIX
+            } else {
+                UNCHECKED_PREFIX
+            },
+            self.text
+        )
+    }
+}
+
+/** data for a checklist item */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checklist {
+    /** the name of the list */
+    name: String,
+    /** the entries, in order */
+    #[serde(default)]
+    entries: Vec<CheckEntry>,
+    /** ident of the template this is a copy of (blank if none) */
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    template: Ident,
+}
+impl Checklist {
+    /** create a new [Checklist]  */
+    pub fn new() -> Self {
+        Self {
+            name: "".to_owned(),
+            entries: vec![],
+            template: "".to_owned(),
+        }
+    }
+    fn set_from_yaml_basic(&mut self, yaml: &serde_yaml::Value) -> NullResult {
+        *self = serde_yaml::from_value(yaml.clone())?;
+        Ok(())
+    }
+    /** number of entries that have been checked */
+    fn num_checked(&self) -> usize {
+        self.entries.iter().filter(|e| e.checked).count()
+    }
+    /** parse the entries as edited by the user (one per line) */
+    fn entries_from_text(text: &str) -> Vec<CheckEntry> {
+        text.lines().filter_map(CheckEntry::from_line).collect()
+    }
+    /** the entries as text for editing (one per line) */
+    fn entries_to_text(&self) -> String {
+        self.entries
+            .iter()
+            .map(|e| e.to_line())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+    /** ensure that there is an entry at `index` */
+    fn check_index(&self, index: usize) -> NullResult {
+        if index < self.entries.len() {
+            Ok(())
+        } else {
+            Err(fanling_error!(&format!(
+                "no entry {} in checklist (only {})",
+                index,
+                self.entries.len()
+            )))
+        }
+    }
+    /** check or uncheck an entry */
+    fn toggle(&mut self, index: usize) -> NullResult {
+        self.check_index(index)?;
+        self.entries[index].checked = !self.entries[index].checked;
+        Ok(())
+    }
+    /** the HTML for each entry, as a row of the table shown */
+    fn entry_rows(&self, ident: &str) -> FLResult<Vec<String>> {
+        let mut rows = vec![];
+        for (index, entry) in self.entries.iter().enumerate() {
+            rows.push(self.entry_row(ident, index, entry)?);
+        }
+        Ok(rows)
+    }
+    /** the HTML for an entry, as a row of the table shown */
+    fn entry_row(&self, ident: &str, index: usize, entry: &CheckEntry) -> FLResult<String> {
+        let t = CheckEntryTemplate {
+            ident: ident.to_owned(),
+            index,
+            entry: entry.clone(),
+        };
+        Ok(t.render()?)
+    }
+    /** a response that only replaces the entry that has been
+    toggled (and the count of checked entries), so that the rest of the
+    page (and where it is scrolled to) stays as it is */
+    fn for_toggle(&self, base: &ItemBase, index: usize) -> fanling_interface::ResponseResult {
+        let mut resp = fanling_interface::Response::new();
+        resp.replace_element(
+            &format!("entry-{}", index),
+            &self.entry_row(&base.get_ident(), index, &self.entries[index])?,
+        );
+        resp.add_tag(
+            "checked-count",
+            &format!("{}/{}", self.num_checked(), self.entries.len()),
+        );
+        #[cfg(test)]
+        {
+            resp.set_test_data("ident", &base.get_ident());
+            resp.set_test_data("entries", &self.entries_to_text());
+        }
+        Ok(resp)
+    }
+    /** add an unchecked entry at the end */
+    fn add(&mut self, text: &str) -> NullResult {
+        if text.trim().is_empty() {
+            return Err(fanling_error!("checklist entry must be non-blank"));
+        }
+        self.entries.push(CheckEntry::new(text.trim()));
+        Ok(())
+    }
+    /** delete an entry */
+    fn delete(&mut self, index: usize) -> NullResult {
+        self.check_index(index)?;
+        self.entries.remove(index);
+        Ok(())
+    }
+    /** move an entry one place towards the start (no effect if already first) */
+    fn move_up(&mut self, index: usize) -> NullResult {
+        self.check_index(index)?;
+        if index > 0 {
+            self.entries.swap(index - 1, index);
+        }
+        Ok(())
+    }
+    /** move an entry one place towards the end (no effect if already last) */
+    fn move_down(&mut self, index: usize) -> NullResult {
+        self.check_index(index)?;
+        if index + 1 < self.entries.len() {
+            self.entries.swap(index, index + 1);
+        }
+        Ok(())
+    }
+    /** how often the template `ident` (which this is) has been used
+    and how often each entry was skipped. Only copies that have been
+    started (have something checked) count. */
+    fn usage(&self, ident: &str, world: &mut World) -> FLResult<(usize, Vec<EntryUsage>)> {
+        let mut uses = 0;
+        let mut skipped = vec![0; self.entries.len()];
+        for entry in world.search_type("Checklist")?.entries {
+            if entry.link.ident == ident {
+                continue;
+            }
+            let (_base, values) = world.get_item_parts(&entry.link.ident)?;
+            if crate::private::is_sealed(&values) {
+                continue;
+            }
+            let copy: Checklist = serde_yaml::from_value(values)?;
+            if copy.template != ident || copy.num_checked() == 0 {
+                continue;
+            }
+            uses += 1;
+            for (i, e) in self.entries.iter().enumerate() {
+                if !copy.entries.iter().any(|c| c.checked && c.text == e.text) {
+                    skipped[i] += 1;
+                }
+            }
+        }
+        trace(&format!("template {} used {} times", ident, uses));
+        Ok((
+            uses,
+            self.entries
+                .iter()
+                .zip(skipped)
+                .map(|(e, skipped)| EntryUsage {
+                    text: e.text.clone(),
+                    skipped,
+                })
+                .collect(),
+        ))
+    }
+    /** merge concurrent changes to the entries.
+
+    Entries are matched by their text. The order is 'our' order, with
+    entries added only in 'their' version at the end. An entry that
+    was in the ancestor but has been deleted in either version is
+    deleted. If one version has checked or unchecked an entry, that
+    change wins; if both have changed it (necessarily in the same
+    way), the change is kept. */
+    fn merge_entries(
+        ancestor: &[CheckEntry],
+        ours: &[CheckEntry],
+        theirs: &[CheckEntry],
+    ) -> Vec<CheckEntry> {
+        let find = |entries: &[CheckEntry], text: &str| -> Option<CheckEntry> {
+            entries.iter().find(|e| e.text == text).cloned()
+        };
+        let mut merged = vec![];
+        for o in ours {
+            let a = find(ancestor, &o.text);
+            match (find(theirs, &o.text), a) {
+                (None, Some(_)) => {} // deleted by them
+                (None, None) => merged.push(o.clone()),
+                (Some(t), None) => merged.push(CheckEntry {
+                    text: o.text.clone(),
+                    checked: o.checked || t.checked,
+                }),
+                (Some(t), Some(a)) => merged.push(CheckEntry {
+                    text: o.text.clone(),
+                    checked: if o.checked != a.checked {
+                        o.checked
+                    } else {
+                        t.checked
+                    },
+                }),
+            }
+        }
+        for t in theirs {
+            let in_ours = merged.iter().any(|m| m.text == t.text)
+                || ours.iter().any(|o| o.text == t.text);
+            if !in_ours && find(ancestor, &t.text).is_none() {
+                merged.push(t.clone());
+            }
+        }
+        merged
+    }
+}
+impl crate::item::ItemData for Checklist {
+    fn for_edit(
+        &mut self,
+        base: &mut ItemBase,
+        is_for_update: bool,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let nt = NewChecklistTemplate {
+            data: &self,
+            base: NewBaseTemplate::from_base(base, is_for_update, world)?,
+            entries_text: self.entries_to_text(),
+        };
+        let mut resp = fanling_interface::Response::new();
+        resp.clear_errors(vec!["name-error".to_owned()]);
+        resp.add_tag("content", &(nt.render()?));
+        #[cfg(test)]
+        {
+            resp.set_test_data("ident", &base.get_ident());
+        }
+        trace(&format!("for edit {:?}", &resp));
+        Ok(resp)
+    }
+    fn for_show(
+        &mut self,
+        base: &mut ItemBase,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let (uses, usage) = if base.is_template() {
+            self.usage(&base.get_ident(), world)?
+        } else {
+            (0, vec![])
+        };
+        let template_descr = if self.template.is_empty() {
+            "".to_owned()
+        } else {
+            world
+                .get_item_parts(&self.template)
+                .ok()
+                .and_then(|(_base, values)| {
+                    values.get("name").and_then(|n| n.as_str()).map(str::to_owned)
+                })
+                .unwrap_or_else(|| self.template.clone())
+        };
+        #[cfg(test)]
+        let skipped = usage
+            .iter()
+            .map(|u| format!("{}:{}", u.text, u.skipped))
+            .collect::<Vec<String>>()
+            .join(",");
+        let t = ShowChecklistTemplate {
+            name: self.name.clone(),
+            rows: self.entry_rows(&base.get_ident())?,
+            num_checked: self.num_checked(),
+            template: self.template.clone(),
+            template_descr,
+            uses,
+            usage,
+            base: ShowBaseTemplate::from_base(base, world)?.with_stats(self.stats(base, world)?),
+        };
+        let mut resp = fanling_interface::Response::new();
+        resp.add_tag("content", &(t.render()?));
+        #[cfg(test)]
+        {
+            resp.set_test_data("ident", &base.get_ident());
+            resp.set_test_data("entries", &self.entries_to_text());
+            resp.set_test_data("template", &self.template);
+            resp.set_test_data("uses", &format!("{}", uses));
+            resp.set_test_data("skipped", &skipped);
+        }
+        trace(&format!("for show {:?}", &resp));
+        Ok(resp)
+    }
+    fn to_yaml(&self, base: &crate::item::ItemBase) -> Result<Vec<u8>, FanlingError> {
+        let for_serde = ChecklistForSerde {
+            base: crate::item::ItemBaseForSerde::from_base(base)?,
+            data: self.clone(),
+        };
+        let yaml = serde_yaml::to_vec(&for_serde)?;
+        trace(&format!("yaml is {}", String::from_utf8_lossy(&yaml)));
+        Ok(yaml)
+    }
+    /** open until every entry has been checked */
+    fn is_open(&self) -> bool {
+        self.entries.is_empty() || self.num_checked() < self.entries.len()
+    }
+    /** a checklist is ready if anything is left to check */
+    fn is_ready(&mut self, _world: &mut World) -> FLResult<bool> {
+        Ok(self.num_checked() < self.entries.len())
+    }
+    /** can be turned into an ident */
+    fn descr_for_ident(&self) -> String {
+        self.name.clone()
+    }
+    /** an English-language description */
+    fn description(&self) -> String {
+        format!(
+            "☑ {} ({}/{})",
+            self.name,
+            self.num_checked(),
+            self.entries.len()
+        )
+    }
+    /** a description that can be used in a list */
+    fn description_for_list(&self) -> String {
+        self.description()
+    }
+    fn set_data(&mut self, vals: &HashMap<String, String>, _world: &mut World) -> NullResult {
+        match vals.get("name") {
+            Some(s) => self.name = s.to_string(),
+            _ => return Err(fanling_error!("no name")),
+        }
+        if let Some(text) = vals.get("entries") {
+            self.entries = Self::entries_from_text(text);
+        }
+        Ok(())
+    }
+    fn set_from_yaml(&mut self, yaml: &serde_yaml::Value, _world: &mut World) -> NullResult {
+        self.set_from_yaml_basic(yaml)
+    }
+    /** do action for checklist */
+    fn do_action(
+        &mut self,
+        base: &mut ItemBase,
+        action: crate::Action,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        match &action {
+            crate::Action::ToggleEntry(index) => {
+                self.toggle(*index)?;
+                return self.for_toggle(base, *index);
+            }
+            crate::Action::AddEntry(text) => self.add(text)?,
+            crate::Action::DeleteEntry(index) => self.delete(*index)?,
+            crate::Action::MoveEntryUp(index) => self.move_up(*index)?,
+            crate::Action::MoveEntryDown(index) => self.move_down(*index)?,
+            _ => return Err(fanling_error!(&format!("invalid action {:?}", action)).into()),
+        }
+        self.for_show(base, world)
+    }
+    /** copy from another item data. But nothing is checked. */
+    fn fanling_clone(&self) -> FLResult<Box<dyn ItemData>> {
+        Ok(Box::new(Self {
+            name: self.name.clone(),
+            entries: self
+                .entries
+                .iter()
+                .map(|e| CheckEntry::new(&e.text))
+                .collect(),
+            template: self.template.clone(),
+        }))
+    }
+    /** a copy for today's use of the template: nothing is checked and the date is added to the name */
+    fn instantiated(&mut self, template: &Ident, world: &mut World) -> NullResult {
+        self.entries.iter_mut().for_each(|e| e.checked = false);
+        self.name = format!(
+            "{} {}",
+            self.name.trim(),
+            world
+                .format()
+                .format_date(chrono::Local::today().naive_local())
+        );
+        self.template = template.clone();
+        Ok(())
+    }
+    /** transitional to fix old data */
+    fn fix_data(
+        &self,
+        _yaml: &serde_yaml::Value,
+        _base: &mut ItemBase,
+        _world: &mut World,
+    ) -> NullResult {
+        Ok(())
+    }
+}
+impl Default for Checklist {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+#[derive(Serialize, Deserialize)]
+struct ChecklistForSerde {
+    #[serde(flatten)]
+    base: crate::item::ItemBaseForSerde,
+    #[serde(flatten)]
+    data: Checklist,
+}
+/** template data for creating a new checklist item */
+#[derive(Template)]
+#[template(path = "new-checklist.html", print = "none")]
+struct NewChecklistTemplate<'a> {
+    data: &'a Checklist,
+    base: NewBaseTemplate,
+    entries_text: String,
+}
+
+/** how often an entry of a template has been skipped */
+struct EntryUsage {
+    text: String,
+    skipped: usize,
+}
+/** template data for showing a checklist item */
+#[derive(Template)]
+#[template(path = "show-checklist.html", print = "none")]
+struct ShowChecklistTemplate {
+    name: String,
+    /** the HTML for each entry */
+    rows: Vec<String>,
+    num_checked: usize,
+    template: Ident,
+    template_descr: String,
+    /** number of times this template has been used */
+    uses: usize,
+    usage: Vec<EntryUsage>,
+    base: ShowBaseTemplate,
+}
+
+/** template data for a single entry of a checklist */
+#[derive(Template)]
+#[template(path = "checklist-entry.html", print = "none")]
+struct CheckEntryTemplate {
+    ident: Ident,
+    index: usize,
+    entry: CheckEntry,
+}
+
+/** policy for the checklist item type*/
+#[derive(Debug)]
+pub struct ChecklistTypePolicy {}
+impl ChecklistTypePolicy {
+    pub fn new() -> Self {
+        Self {}
+    }
+    pub fn new_boxed() -> Box<Self> {
+        Box::new(Self::new())
+    }
+}
+impl crate::item::ItemTypePolicy for ChecklistTypePolicy {
+    fn kind(&self) -> crate::item::ItemKind {
+        crate::item::ItemKind::Checklist
+    }
+    fn make_raw(&self, item_type: crate::item::ItemTypeRef) -> Item {
+        Item::new_with_data(item_type, Box::new(Checklist::new()))
+    }
+    fn resolve_conflict_both(
+        &self,
+        _world: &mut World,
+        ancestor: &Value,
+        ours: &Value,
+        theirs: &Value,
+    ) -> FLResult<Box<dyn ItemData>> {
+        let mut ac = Checklist::new();
+        ac.set_from_yaml_basic(&ancestor)?;
+        let mut oc = Checklist::new();
+        oc.set_from_yaml_basic(&ours)?;
+        let mut tc = Checklist::new();
+        tc.set_from_yaml_basic(&theirs)?;
+        oc.name = merge_strings(&oc.name, &tc.name);
+        oc.entries = Checklist::merge_entries(&ac.entries, &oc.entries, &tc.entries);
+        if oc.template.is_empty() {
+            oc.template = tc.template;
+        }
+        Ok(Box::new(oc))
+    }
+    fn check_valid(
+        &mut self,
+        _base: &ItemBaseForSerde,
+        vals: &HashMap<String, String>,
+        _world: &mut World,
+    ) -> ActionResponse {
+        let mut ar = ActionResponse::new();
+        ar.assert(
+            !vals.get("name").map_or(true, |n| n.is_empty()),
+            "name-error",
+            "Name must be non-blank.",
+        );
+        ar
+    }
+    /** get item data from serde value */
+    fn from_yaml(&self, values: &Value, world: &mut World) -> FLResult<Box<dyn ItemData>> {
+        let mut c = Checklist::default();
+        c.set_from_yaml(&values, world)?;
+        Ok(Box::new(c))
+    }
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "checklist {}",
+        Colour::Fixed(11).on(Colour::Fixed(233)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn entries(spec: &[(&str, bool)]) -> Vec<CheckEntry> {
+        spec.iter()
+            .map(|(text, checked)| CheckEntry {
+                text: text.to_string(),
+                checked: *checked,
+            })
+            .collect()
+    }
+    #[test]
+    fn edit_entries() -> NullResult {
+        let mut cl = Checklist::new();
+        cl.entries = Checklist::entries_from_text("[x] milk\n\n[ ] eggs\nbread");
+        assert_eq!(
+            entries(&[("milk", true), ("eggs", false), ("bread", false)]),
+            cl.entries
+        );
+        cl.toggle(1)?;
+        cl.move_up(2)?;
+        cl.move_down(2)?;
+        cl.add("jam")?;
+        cl.delete(0)?;
+        assert_eq!("[ ] bread\n[x] eggs\n[ ] jam", cl.entries_to_text());
+        assert!(cl.toggle(3).is_err());
+        Ok(())
+    }
+    #[test]
+    fn merge_checked() {
+        let ancestor = entries(&[("milk", false), ("eggs", false), ("bread", true)]);
+        let ours = entries(&[("eggs", true), ("milk", false), ("bread", true), ("jam", false)]);
+        let theirs = entries(&[("milk", true), ("bread", false), ("tea", false)]);
+        assert_eq!(
+            entries(&[("milk", true), ("bread", false), ("jam", false), ("tea", false)]),
+            Checklist::merge_entries(&ancestor, &ours, &theirs)
+        );
+    }
+}
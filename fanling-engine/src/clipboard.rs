@@ -0,0 +1,409 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! a clipboard for each view session (see [`crate::session`]), for
+moving content between items, such as cutting three entries from one
+checklist and pasting them into another.
+
+What is cut or copied is some blocks of a field of an item (the
+entries of a list, such as those of a checklist, or the lines of a
+text), or the whole item. The blocks are pasted into a field of
+another item (or the same one), being made to fit it: an entry pasted
+into a text is its text, and a line pasted into a list of entries is
+an entry with that text.
+
+Nothing is removed when the blocks are cut: they are removed from the
+item they came from when they are pasted, in the same commit as the
+item they are pasted into, and only if both items are still valid
+afterwards (for a whole item, the item itself is removed). If the item
+they came from has changed since, nothing is pasted. Private items
+that are sealed can not be cut or copied from, or pasted into. */
+use crate::item::{Ident, ItemBaseForSerde};
+use crate::shared::{FLResult, NullResult};
+use crate::world::World;
+use ansi_term::Colour;
+use serde_yaml::{Mapping, Value};
+use std::ops::Deref;
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** the fields that are pasted into when no field is given, the first that the item has */
+const MAIN_FIELDS: [&str; 2] = ["entries", "text"];
+/** the key of the text of an entry of a list */
+const TEXT_KEY: &str = "text";
+
+/** what is on the clipboard of a session */
+#[derive(Debug, Clone)]
+pub struct Clip {
+    /** the item it was cut or copied from */
+    source: Ident,
+    /** the field the blocks came from (empty for the whole item) */
+    field: String,
+    /** where the blocks were in the field */
+    positions: Vec<usize>,
+    /** the blocks, as they were in the field */
+    blocks: Vec<Value>,
+    /** the values of the whole item (if the whole item was taken) */
+    values: Option<Value>,
+    /** the description of the item it came from */
+    descr: String,
+    /** whether it is to be removed from the item it came from when pasted */
+    cut: bool,
+}
+impl Clip {
+    /** a short description, for the user */
+    fn describe(&self) -> String {
+        if self.field.is_empty() {
+            format!("'{}'", self.descr)
+        } else {
+            format!("{} blocks from '{}'", self.blocks.len(), self.descr)
+        }
+    }
+}
+
+/** the blocks of the values of a field: the entries of a list, or the lines of a text */
+fn blocks_of(value: Option<&Value>) -> FLResult<Vec<Value>> {
+    match value {
+        Some(Value::Sequence(entries)) => Ok(entries.clone()),
+        Some(Value::String(text)) => Ok(text
+            .lines()
+            .map(|line| Value::String(line.to_owned()))
+            .collect()),
+        Some(Value::Null) | None => Ok(vec![]),
+        Some(other) => Err(fanling_error!(&format!(
+            "cannot take blocks from {:?}",
+            other
+        ))),
+    }
+}
+/** the text of a block, to go in a text */
+fn block_text(block: &Value) -> String {
+    match block {
+        Value::String(text) => text.clone(),
+        Value::Mapping(map) => match map.get(&Value::String(TEXT_KEY.to_owned())) {
+            Some(Value::String(text)) => text.clone(),
+            _ => serde_yaml::to_string(block)
+                .unwrap_or_default()
+                .trim_start_matches("---")
+                .trim()
+                .to_owned(),
+        },
+        other => serde_yaml::to_string(other)
+            .unwrap_or_default()
+            .trim_start_matches("---")
+            .trim()
+            .to_owned(),
+    }
+}
+/** the block made to fit in a list, whose entries are like `like` (if it has any) */
+fn block_for_list(block: &Value, like: Option<&Value>) -> Value {
+    match (block, like) {
+        (Value::String(_), Some(Value::String(_))) | (Value::Mapping(_), _) => block.clone(),
+        (Value::String(text), _) => {
+            let mut entry = Mapping::new();
+            entry.insert(
+                Value::String(TEXT_KEY.to_owned()),
+                Value::String(text.clone()),
+            );
+            Value::Mapping(entry)
+        }
+        (other, _) => other.clone(),
+    }
+}
+/** the field with the blocks removed from the positions */
+fn without_blocks(value: Option<&Value>, positions: &[usize]) -> FLResult<Value> {
+    let kept = blocks_of(value)?
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _block)| !positions.contains(i))
+        .map(|(_i, block)| block);
+    Ok(match value {
+        Some(Value::String(_)) => {
+            Value::String(kept.map(|b| block_text(&b)).collect::<Vec<_>>().join("\n"))
+        }
+        _ => Value::Sequence(kept.collect()),
+    })
+}
+/** the field with the blocks inserted before the block at `at` (or at the end) */
+fn with_blocks(value: Option<&Value>, blocks: &[Value], at: Option<usize>) -> FLResult<Value> {
+    let mut all = blocks_of(value)?;
+    let at = at.unwrap_or_else(|| all.len()).min(all.len());
+    Ok(match value {
+        Some(Value::String(_)) => {
+            let mut lines: Vec<String> = all.iter().map(block_text).collect();
+            let _ = lines.splice(at..at, blocks.iter().map(block_text));
+            Value::String(lines.join("\n"))
+        }
+        _ => {
+            let like = all.first().cloned();
+            let _ = all.splice(
+                at..at,
+                blocks.iter().map(|b| block_for_list(b, like.as_ref())),
+            );
+            Value::Sequence(all)
+        }
+    })
+}
+/** the field pasted into when none is given */
+fn main_field(values: &Value) -> String {
+    MAIN_FIELDS
+        .iter()
+        .find(|f| values.get(**f).is_some())
+        .unwrap_or(&MAIN_FIELDS[1])
+        .to_string()
+}
+/** the parts of an item, if they can be read (so it is not sealed) */
+fn readable_parts(world: &World, ident: &Ident) -> FLResult<(ItemBaseForSerde, Value)> {
+    let (base, values) = world.get_item_parts(ident)?;
+    if crate::private::is_sealed(&values) {
+        return Err(fanling_error!(
+            "A private item cannot be cut, copied or pasted into."
+        ));
+    }
+    Ok((base, values))
+}
+/** what is wrong with the values for an item, if anything */
+fn check_values(
+    world: &mut World,
+    base: &ItemBaseForSerde,
+    values: &Value,
+) -> FLResult<Option<String>> {
+    let item_type = world.get_item_type(base.type_name.clone())?;
+    let result = item_type.deref().borrow().from_yaml(values, world);
+    Ok(result.err().map(|e| format!("{}", e)))
+}
+
+/** put blocks of a field of an item (or the whole item, if `field`
+is empty) on the clipboard of the session */
+pub fn clip(
+    world: &mut World,
+    session: &str,
+    ident: &Ident,
+    field: &str,
+    positions: &[usize],
+    cut: bool,
+) -> fanling_interface::ResponseResult {
+    let (_base, values) = readable_parts(world, ident)?;
+    let descr = world
+        .get_item(ident.clone(), "Simple".to_owned())?
+        .borrow()
+        .description();
+    let mut positions = positions.to_vec();
+    positions.sort_unstable();
+    positions.dedup();
+    let clip = if field.is_empty() {
+        Clip {
+            source: ident.clone(),
+            field: "".to_owned(),
+            positions: vec![],
+            blocks: vec![],
+            values: Some(values),
+            descr,
+            cut,
+        }
+    } else {
+        let all = blocks_of(values.get(field))?;
+        if positions.is_empty() || positions.iter().any(|p| *p >= all.len()) {
+            return Err(fanling_error!(&format!(
+                "blocks {:?} are not all in the {} of {}",
+                &positions, field, ident
+            ))
+            .into());
+        }
+        Clip {
+            source: ident.clone(),
+            field: field.to_owned(),
+            blocks: positions.iter().map(|p| all[*p].clone()).collect(),
+            positions,
+            values: None,
+            descr,
+            cut,
+        }
+    };
+    trace(&format!("clipped {:?} in session '{}'", &clip, session));
+    let message = format!(
+        "{} {}.",
+        if cut { "Cut" } else { "Copied" },
+        clip.describe()
+    );
+    #[cfg(test)]
+    let count = clip.blocks.len();
+    world.sessions().set_clip(session, Some(clip));
+    let mut resp = fanling_interface::Response::new();
+    resp.show_toast(&message);
+    #[cfg(test)]
+    resp.set_test_data("clipped", &format!("{}", count));
+    Ok(resp)
+}
+/** paste the clipboard of the session into a field of an item (or
+its main field if `field` is empty), before the block at `at` (or at
+the end), then show the item */
+pub fn paste(
+    world: &mut World,
+    session: &str,
+    ident: &Ident,
+    field: &str,
+    at: Option<usize>,
+) -> fanling_interface::ResponseResult {
+    let clip = match world.sessions().clip(session).cloned() {
+        Some(clip) => clip,
+        None => {
+            let mut resp = fanling_interface::Response::new();
+            resp.show_toast(&world.i18n().tr("There is nothing to paste."));
+            return Ok(resp);
+        }
+    };
+    let (base, values) = readable_parts(world, ident)?;
+    let field = if field.is_empty() {
+        main_field(&values)
+    } else {
+        field.to_owned()
+    };
+    let blocks = match &clip.values {
+        Some(source_values) => {
+            if clip.cut && clip.source == *ident {
+                return Err(fanling_error!("An item cannot be cut and pasted into itself.").into());
+            }
+            let from = source_values
+                .get(&field)
+                .or_else(|| source_values.get(TEXT_KEY));
+            match from {
+                Some(from) => blocks_of(Some(from))?,
+                None => vec![Value::String(clip.descr.clone())],
+            }
+        }
+        None => clip.blocks.clone(),
+    };
+    /* the item cut from must be as it was, and the blocks go from it first if it is the same item */
+    let mut source = None;
+    let mut at = at;
+    let mut target_values = values.clone();
+    if clip.cut && clip.values.is_none() {
+        let (source_base, source_values) = readable_parts(world, &clip.source)?;
+        let now = blocks_of(source_values.get(&clip.field))?;
+        if clip
+            .positions
+            .iter()
+            .zip(clip.blocks.iter())
+            .any(|(p, b)| now.get(*p) != Some(b))
+        {
+            return Err(fanling_error!(&format!(
+                "{} has changed since the blocks were cut from it",
+                &clip.source
+            ))
+            .into());
+        }
+        let mut source_values = source_values;
+        let remaining = without_blocks(source_values.get(&clip.field), &clip.positions)?;
+        source_values[clip.field.as_str()] = remaining;
+        if clip.source == *ident {
+            if clip.field == field {
+                at = at.map(|a| a - clip.positions.iter().filter(|p| **p < a).count());
+            }
+            target_values = source_values;
+        } else {
+            source = Some((source_base, source_values));
+        }
+    }
+    let pasted_into = with_blocks(target_values.get(&field), &blocks, at)?;
+    target_values[field.as_str()] = pasted_into;
+    let mut problems = vec![];
+    if let Some(problem) = check_values(world, &base, &target_values)? {
+        problems.push(problem);
+    }
+    if let Some((source_base, source_values)) = &source {
+        if let Some(problem) = check_values(world, source_base, source_values)? {
+            problems.push(problem);
+        }
+    }
+    if !problems.is_empty() {
+        trace(&format!("cannot paste: {:?}", &problems));
+        let mut resp = fanling_interface::Response::new();
+        resp.add_tag(
+            fanling_interface::region::ERROR,
+            &format!(
+                "{} {}",
+                world.i18n().tr("The clipboard cannot be pasted there:"),
+                problems.join(" ")
+            ),
+        );
+        resp.set_error();
+        return Ok(resp);
+    }
+    world.begin_batch();
+    let pasted = paste_in_batch(world, ident, &target_values, &clip, &source);
+    world.end_batch()?;
+    pasted?;
+    if clip.cut {
+        world.sessions().set_clip(session, None);
+    }
+    let item_ref = world.get_item(ident.clone(), "Simple".to_owned())?;
+    let mut resp = item_ref.borrow_mut().for_show(world)?;
+    resp.show_toast(&world.i18n().tr("The clipboard has been pasted."));
+    #[cfg(test)]
+    resp.set_test_data("pasted", &format!("{}", blocks.len()));
+    Ok(resp)
+}
+/** the changes for a paste, made while the changes are collected into a single commit */
+fn paste_in_batch(
+    world: &mut World,
+    ident: &Ident,
+    values: &Value,
+    clip: &Clip,
+    source: &Option<(ItemBaseForSerde, Value)>,
+) -> NullResult {
+    let item_ref = world.get_item(ident.clone(), "Simple".to_owned())?;
+    {
+        let mut item = item_ref.borrow_mut();
+        item.set_from_yaml(values, world)?;
+        world.persist_change_as(&mut item, "paste into")?;
+    }
+    if !clip.cut {
+        return Ok(());
+    }
+    match source {
+        Some((_base, source_values)) => {
+            let source_ref = world.get_item(clip.source.clone(), "Simple".to_owned())?;
+            let mut item = source_ref.borrow_mut();
+            item.set_from_yaml(source_values, world)?;
+            world.persist_change_as(&mut item, "cut from")?;
+        }
+        None if clip.values.is_some() => world.forget_item_file(&clip.source)?,
+        None => {}
+    }
+    Ok(())
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "clipboard {}",
+        Colour::Fixed(16).on(Colour::Fixed(153)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn fitting_blocks() -> crate::shared::NullResult {
+        let entries: Value = serde_yaml::from_str("[{text: a, checked: true}, {text: b}]")?;
+        let text = Value::String("one\ntwo".to_owned());
+        let into_text = with_blocks(Some(&text), &blocks_of(Some(&entries))?, Some(1))?;
+        assert_eq!(Value::String("one\na\nb\ntwo".to_owned()), into_text);
+        let into_list = with_blocks(Some(&entries), &blocks_of(Some(&text))?, None)?;
+        assert_eq!(4, blocks_of(Some(&into_list))?.len());
+        assert_eq!("two", block_text(&blocks_of(Some(&into_list))?[3]));
+        assert_eq!(
+            Value::String("two".to_owned()),
+            without_blocks(Some(&text), &[0])?
+        );
+        assert_eq!(
+            1,
+            blocks_of(Some(&without_blocks(Some(&entries), &[1])?))?.len()
+        );
+        Ok(())
+    }
+}
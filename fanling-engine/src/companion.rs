@@ -0,0 +1,136 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! generated files kept with the items in the repository, so that
+browsing the repository with other Git tools (eg on a Git hosting
+site) shows something readable without the app.
+
+If enabled in the settings, whenever a change to the items has been
+committed a `README.md` giving the number of items of each type, and
+an `index.md` linking to each of the top-level items, are updated
+beside the items (and committed if they have changed). They are
+never written for an encrypted repository, as they would give away
+the names of the items. A merge conflict in one of them is resolved
+by keeping our version until they are generated again. */
+use crate::shared::NullResult;
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use taipo_git_control::{Change, Conflict, ObjectOperation};
+
+/** name of the generated README */
+pub const README: &str = "README.md";
+/** name of the generated index of top-level items */
+pub const INDEX: &str = "index.md";
+
+/** the number of items of a type */
+struct TypeCount {
+    name: String,
+    count: usize,
+}
+/** template data for the README */
+#[derive(Template)]
+#[template(path = "readme.md", escape = "none")]
+struct ReadmeTemplate {
+    types: Vec<TypeCount>,
+    total: usize,
+}
+/** an entry in the index */
+struct IndexEntry {
+    descr: String,
+    path: String,
+}
+/** template data for the index of top-level items */
+#[derive(Template)]
+#[template(path = "index.md", escape = "none")]
+struct IndexTemplate {
+    items: Vec<IndexEntry>,
+}
+
+/** text for a Markdown link, with the characters that would end it escaped */
+fn link_text(descr: &str) -> String {
+    descr.replace('[', "\\[").replace(']', "\\]")
+}
+
+/** update the generated files (if they are enabled) */
+pub fn update_files(world: &mut World) -> NullResult {
+    if !world.commit_options().generate_index || world.is_encrypted() {
+        return Ok(());
+    }
+    let mut types = vec![];
+    for kind in crate::kinds::kinds() {
+        let count = world.search_type(kind.name)?.num_entries();
+        if count > 0 {
+            types.push(TypeCount {
+                name: kind.name.to_owned(),
+                count,
+            });
+        }
+    }
+    let total = types.iter().map(|t| t.count).sum();
+    let readme = ReadmeTemplate { types, total }.render()?;
+    let items = world
+        .search_all_hier()?
+        .entries
+        .into_iter()
+        .filter(|entry| entry.level == 0)
+        .map(|entry| IndexEntry {
+            descr: link_text(&entry.descr),
+            path: world.path_from_ident(&entry.link.ident),
+        })
+        .collect();
+    let index = IndexTemplate { items }.render()?;
+    trace("updating generated files");
+    world.write_other_files(&[(README.to_owned(), readme), (INDEX.to_owned(), index)])
+}
+
+/** a change resolving a merge conflict in a generated file (keeping
+our version, as the files are generated again after the merge), or
+`None` if the conflict is not in a generated file */
+pub fn resolve_conflict(conflict: &Conflict) -> Option<Change> {
+    let entry = conflict.our.as_ref().or_else(|| conflict.their.as_ref())?;
+    if entry.path != README && entry.path != INDEX {
+        return None;
+    }
+    Some(Change::new(
+        ObjectOperation::Modify(String::from_utf8_lossy(&entry.data).to_string()),
+        entry.path.clone(),
+        "resolve conflict".to_owned(),
+    ))
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "companion {}",
+        Colour::Fixed(15).on(Colour::Fixed(22)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn render() -> NullResult {
+        let readme = ReadmeTemplate {
+            types: vec![TypeCount {
+                name: "Task".to_owned(),
+                count: 2,
+            }],
+            total: 2,
+        }
+        .render()?;
+        assert!(readme.contains("| Task | 2 |"));
+        assert!(readme.contains("**2**"));
+        let index = IndexTemplate {
+            items: vec![IndexEntry {
+                descr: link_text("a [b] <c>"),
+                path: "a-b-c-a1.page".to_owned(),
+            }],
+        }
+        .render()?;
+        assert!(index.contains("- [a \\[b\\] <c>](a-b-c-a1.page)"));
+        Ok(())
+    }
+}
@@ -0,0 +1,653 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! implements [`Contact`] items (an address book entry).
+
+A contact's photo is kept as an attachment (base64-encoded, see
+[`crate::mail::BASE64_SUFFIX`]) named by a hash of the image, as it is
+given before the contact has an ident, and the contact only keeps the
+path of the attachment. */
+use crate::item::{Item, ItemBase, ItemBaseForSerde, ItemData, NewBaseTemplate, ShowBaseTemplate};
+use crate::markdown;
+use crate::shared::{merge_strings, FLResult, FanlingError, NullResult};
+use crate::vcard::{self, Photo, VCard};
+use crate::reminder::ReminderKind;
+use crate::world::{ActionResponse, World};
+use ansi_term::Colour;
+use askama::Template;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use sha2::{Digest, Sha256};
+use std::boxed::Box;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs;
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** the directory (in the attachments directory) of the photos of contacts */
+const PHOTOS_DIR: &str = "photos";
+/** the number of hex digits of the hash in the name of a photo */
+const HASH_LENGTH: usize = 16;
+
+/** data for a contact item */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Contact {
+    /** the name of the person or organisation */
+    name: String,
+    /** e-mail addresses */
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    emails: Vec<String>,
+    /** telephone numbers */
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    phones: Vec<String>,
+    /** postal address */
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::string::String::is_empty")]
+    address: String,
+    /** date of birth */
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    birthday: Option<NaiveDate>,
+    /** date of an anniversary (such as a wedding) */
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    anniversary: Option<NaiveDate>,
+    /** the path of the photo attached to the contact (blank if none) */
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::string::String::is_empty")]
+    photo: String,
+    /** notes in MarkDown format */
+    #[serde(default)]
+    notes: String,
+    /** calls, e-mails and so on, oldest first */
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    interactions: Vec<Interaction>,
+}
+/** how someone was in touch with a contact */
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum InteractionKind {
+    Call,
+    Email,
+    Message,
+    Meeting,
+    Other,
+}
+impl InteractionKind {
+    /** an English-language name */
+    fn name(self) -> &'static str {
+        match self {
+            InteractionKind::Call => "Call",
+            InteractionKind::Email => "E-mail",
+            InteractionKind::Message => "Message",
+            InteractionKind::Meeting => "Meeting",
+            InteractionKind::Other => "Other",
+        }
+    }
+}
+/** a record of being in touch with a contact */
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Interaction {
+    when: NaiveDateTime,
+    kind: InteractionKind,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::string::String::is_empty")]
+    note: String,
+}
+impl Contact {
+    /** create a new [Contact]  */
+    pub fn new() -> Self {
+        Self {
+            name: "".to_owned(),
+            emails: vec![],
+            phones: vec![],
+            address: "".to_owned(),
+            birthday: None,
+            anniversary: None,
+            photo: "".to_owned(),
+            notes: "".to_owned(),
+            interactions: vec![],
+        }
+    }
+    fn set_from_yaml_basic(&mut self, yaml: &serde_yaml::Value) -> NullResult {
+        *self = serde_yaml::from_value(yaml.clone())?;
+        Ok(())
+    }
+    /** the contact from the values of an item */
+    pub(crate) fn from_values(values: &Value) -> FLResult<Self> {
+        let mut contact = Self::new();
+        contact.set_from_yaml_basic(values)?;
+        Ok(contact)
+    }
+    /** the name of the person or organisation */
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+    /** the dates to be reminded of every year */
+    pub(crate) fn reminder_dates(&self) -> Vec<(ReminderKind, NaiveDate)> {
+        let mut dates = vec![];
+        if let Some(birthday) = self.birthday {
+            dates.push((ReminderKind::Birthday, birthday));
+        }
+        if let Some(anniversary) = self.anniversary {
+            dates.push((ReminderKind::Anniversary, anniversary));
+        }
+        dates
+    }
+    /** record being in touch with the contact now */
+    fn log_interaction(&mut self, kind: InteractionKind, note: &str) {
+        self.interactions.push(Interaction {
+            when: chrono::Local::now().naive_local(),
+            kind,
+            note: note.trim().to_owned(),
+        });
+    }
+    /** the attached photo (`None` if there is none, or it cannot be read) */
+    fn load_photo(&self, world: &World) -> Option<Photo> {
+        if self.photo.is_empty() {
+            return None;
+        }
+        match world.attachment(&self.photo) {
+            Ok(data) => Some(Photo {
+                media_type: photo_media_type(&self.photo),
+                data: data.trim().to_owned(),
+            }),
+            Err(e) => {
+                trace(&format!("cannot read the photo {}: {:?}", self.photo, e));
+                None
+            }
+        }
+    }
+    /** the attached photo as a `data:` URI (blank if none) */
+    fn photo_uri(&self, world: &World) -> String {
+        self.load_photo(world)
+            .map_or("".to_owned(), |p| p.to_data_uri())
+    }
+    /** convert to a [VCard] (with the attached photo) */
+    pub fn to_vcard(&self, world: &World) -> VCard {
+        VCard {
+            name: self.name.clone(),
+            emails: self.emails.clone(),
+            phones: self.phones.clone(),
+            address: self.address.clone(),
+            notes: self.notes.clone(),
+            birthday: self.birthday,
+            anniversary: self.anniversary,
+            photo: self.load_photo(world),
+            uid: "".to_owned(),
+        }
+    }
+    /** values as if entered by the user, suitable for creating an item */
+    pub fn vals_from_vcard(card: &VCard) -> HashMap<String, String> {
+        let mut vals = HashMap::new();
+        vals.insert("name".to_owned(), card.name.clone());
+        vals.insert("emails".to_owned(), card.emails.join("\n"));
+        vals.insert("phones".to_owned(), card.phones.join("\n"));
+        vals.insert("address".to_owned(), card.address.clone());
+        vals.insert("notes".to_owned(), card.notes.clone());
+        let iso = |d: NaiveDate| d.format(crate::format::ISO_DATE_FORMAT).to_string();
+        vals.insert("birthday".to_owned(), card.birthday.map_or("".to_owned(), iso));
+        vals.insert("anniversary".to_owned(), card.anniversary.map_or("".to_owned(), iso));
+        vals.insert(
+            "photo".to_owned(),
+            card.photo.as_ref().map_or("".to_owned(), |p| p.to_data_uri()),
+        );
+        vals
+    }
+}
+/** the path for attaching a photo (the same for the same image) */
+fn photo_path(photo: &Photo) -> String {
+    let hash = format!("{:x}", Sha256::digest(photo.data.as_bytes()));
+    format!(
+        "{}/{}/{}.{}{}",
+        crate::bookmark::ATTACHMENTS_DIR,
+        PHOTOS_DIR,
+        &hash[..HASH_LENGTH],
+        photo.media_type.trim_start_matches("image/"),
+        crate::mail::BASE64_SUFFIX
+    )
+}
+/** the media type of an attached photo, from its path */
+fn photo_media_type(path: &str) -> String {
+    let name = path
+        .strip_suffix(crate::mail::BASE64_SUFFIX)
+        .unwrap_or(path);
+    format!("image/{}", name.rsplit('.').next().unwrap_or("jpeg"))
+}
+/** the path of the photo as given by the user: a new photo (a `data:`
+URI) is attached, and the path of one already attached is kept */
+fn attach_photo(given: Option<&String>, world: &mut World) -> FLResult<String> {
+    let given = given.map_or("", |g| g.trim());
+    let photos = format!("{}/{}/", crate::bookmark::ATTACHMENTS_DIR, PHOTOS_DIR);
+    if let Some(photo) = Photo::from_data_uri(given) {
+        let path = photo_path(&photo);
+        if world.attachment(&path).is_err() {
+            world.add_attachment(&path, &photo.data)?;
+        }
+        Ok(path)
+    } else if given.starts_with(&photos) {
+        Ok(given.to_owned())
+    } else {
+        Ok("".to_owned())
+    }
+}
+/** split a user-entered list (one per line or comma-separated) */
+fn split_list(s: &str) -> Vec<String> {
+    s.split(|c| c == '\n' || c == ',')
+        .map(|e| e.trim().to_owned())
+        .filter(|e| !e.is_empty())
+        .collect()
+}
+/** a date entered by the user (`None` if blank) */
+fn parse_optional_date(s: Option<&String>, world: &World) -> FLResult<Option<NaiveDate>> {
+    match s {
+        Some(s) if !s.trim().is_empty() => Ok(Some(world.format().parse_date_time(s)?.date())),
+        _ => Ok(None),
+    }
+}
+/** a date for editing (blank if `None`) */
+fn format_optional_date(date: Option<NaiveDate>, world: &World) -> String {
+    date.map_or("".to_owned(), |d| world.format().format_date(d))
+}
+/** the address to use in a `tel:` link (without spaces and punctuation) */
+fn tel_href(phone: &str) -> String {
+    phone
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == '+')
+        .collect()
+}
+/** merge two lists, keeping the order of the first */
+fn merge_lists(ours: &[String], theirs: &[String]) -> Vec<String> {
+    let mut merged = ours.to_vec();
+    for t in theirs {
+        if !merged.contains(t) {
+            merged.push(t.clone());
+        }
+    }
+    merged
+}
+impl crate::item::ItemData for Contact {
+    fn for_edit(
+        &mut self,
+        base: &mut ItemBase,
+        is_for_update: bool,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let broken_notes = self.notes.replace("\n", "&#10;");
+        let nt = NewContactTemplate {
+            data: &self,
+            base: NewBaseTemplate::from_base(base, is_for_update, world)?,
+            emails: self.emails.join("\n"),
+            phones: self.phones.join("\n"),
+            birthday: format_optional_date(self.birthday, world),
+            anniversary: format_optional_date(self.anniversary, world),
+            photo: self.photo.clone(),
+            photo_uri: self.photo_uri(world),
+            broken_notes,
+        };
+        let mut resp = fanling_interface::Response::new();
+        resp.clear_errors(vec![
+            "name-error".to_owned(),
+            "emails-error".to_owned(),
+            "birthday-error".to_owned(),
+            "anniversary-error".to_owned(),
+        ]);
+        resp.add_tag("content", &(nt.render()?));
+        #[cfg(test)]
+        {
+            resp.set_test_data("ident", &base.get_ident());
+        }
+        trace(&format!("for edit {:?}", &resp));
+        Ok(resp)
+    }
+    fn for_show(
+        &mut self,
+        base: &mut ItemBase,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let t = ShowContactTemplate {
+            name: self.name.clone(),
+            emails: self
+                .emails
+                .iter()
+                .map(|e| ContactLink {
+                    text: e.clone(),
+                    href: format!("mailto:{}", e),
+                })
+                .collect(),
+            phones: self
+                .phones
+                .iter()
+                .map(|p| ContactLink {
+                    text: p.clone(),
+                    href: format!("tel:{}", tel_href(p)),
+                })
+                .collect(),
+            interactions: self
+                .interactions
+                .iter()
+                .rev()
+                .map(|i| InteractionEntry {
+                    when: world.format().format_date_time(i.when),
+                    kind: i.kind.name(),
+                    note: i.note.clone(),
+                })
+                .collect(),
+            address: self.address.clone(),
+            birthday: format_optional_date(self.birthday, world),
+            anniversary: format_optional_date(self.anniversary, world),
+            photo: self.photo_uri(world),
+            rendered_notes: markdown::render(&self.notes, &world.render_context()),
+            base: ShowBaseTemplate::from_base(base, world)?.with_stats(self.stats(base, world)?),
+        };
+        let mut resp = fanling_interface::Response::new();
+        resp.add_tag("content", &(t.render()?));
+        #[cfg(test)]
+        {
+            resp.set_test_data("ident", &base.get_ident());
+            resp.set_test_data("interactions", &format!("{}", self.interactions.len()));
+        }
+        trace(&format!("for show {:?}", &resp));
+        Ok(resp)
+    }
+    fn to_yaml(&self, base: &crate::item::ItemBase) -> Result<Vec<u8>, FanlingError> {
+        let for_serde = ContactForSerde {
+            base: crate::item::ItemBaseForSerde::from_base(base)?,
+            data: self.clone(),
+        };
+        let yaml = serde_yaml::to_vec(&for_serde)?;
+        trace(&format!("yaml is {}", String::from_utf8_lossy(&yaml)));
+        Ok(yaml)
+    }
+    fn is_open(&self) -> bool {
+        true
+    }
+    /** a contact is never something to be done, so it is never ready */
+    fn is_ready(&mut self, _world: &mut World) -> FLResult<bool> {
+        Ok(false)
+    }
+    /** can be turned into an ident */
+    fn descr_for_ident(&self) -> String {
+        self.name.clone()
+    }
+    /** an English-language description */
+    fn description(&self) -> String {
+        format!("☎ {}", self.name)
+    }
+    /** a description that can be used in a list */
+    fn description_for_list(&self) -> String {
+        self.name.clone()
+    }
+    fn set_data(&mut self, vals: &HashMap<String, String>, world: &mut World) -> NullResult {
+        match vals.get("name") {
+            Some(s) => self.name = s.to_string(),
+            _ => return Err(fanling_error!("no name")),
+        }
+        self.emails = vals.get("emails").map_or(vec![], |s| split_list(s));
+        self.phones = vals.get("phones").map_or(vec![], |s| split_list(s));
+        self.address = vals.get("address").cloned().unwrap_or_default();
+        self.birthday = parse_optional_date(vals.get("birthday"), world)?;
+        self.anniversary = parse_optional_date(vals.get("anniversary"), world)?;
+        self.photo = attach_photo(vals.get("photo"), world)?;
+        self.notes = vals.get("notes").cloned().unwrap_or_default();
+        Ok(())
+    }
+    fn set_from_yaml(&mut self, yaml: &serde_yaml::Value, _world: &mut World) -> NullResult {
+        self.set_from_yaml_basic(yaml)
+    }
+    /** do action for contact */
+    fn do_action(
+        &mut self,
+        base: &mut ItemBase,
+        action: crate::Action,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        match &action {
+            crate::Action::LogInteraction(kind, note) => self.log_interaction(*kind, note),
+            _ => return Err(fanling_error!(&format!("invalid action {:?}", action)).into()),
+        }
+        self.for_show(base, world)
+    }
+    /** copy from another item data, but not the interactions */
+    fn fanling_clone(&self) -> FLResult<Box<dyn ItemData>> {
+        Ok(Box::new(Self {
+            interactions: vec![],
+            ..self.clone()
+        }))
+    }
+    /** transitional to fix old data */
+    fn fix_data(
+        &self,
+        _yaml: &serde_yaml::Value,
+        _base: &mut ItemBase,
+        _world: &mut World,
+    ) -> NullResult {
+        Ok(())
+    }
+}
+impl Default for Contact {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+#[derive(Serialize, Deserialize)]
+struct ContactForSerde {
+    #[serde(flatten)]
+    base: crate::item::ItemBaseForSerde,
+    #[serde(flatten)]
+    data: Contact,
+}
+/** template data for creating a new contact item */
+#[derive(Template)]
+#[template(path = "new-contact.html", print = "none")]
+struct NewContactTemplate<'a> {
+    data: &'a Contact,
+    base: NewBaseTemplate,
+    emails: String,
+    phones: String,
+    birthday: String,
+    anniversary: String,
+    /** the path of the attached photo, or a new photo as a `data:` URI (blank if none) */
+    photo: String,
+    /** the photo as a `data:` URI, for the preview (blank if none) */
+    photo_uri: String,
+    broken_notes: String,
+}
+
+/** an e-mail address or telephone number, for the template */
+struct ContactLink {
+    text: String,
+    href: String,
+}
+/** an interaction, for the template */
+struct InteractionEntry {
+    when: String,
+    kind: &'static str,
+    note: String,
+}
+/** template data for showing a contact item */
+#[derive(Template)]
+#[template(path = "show-contact.html", print = "none")]
+struct ShowContactTemplate {
+    name: String,
+    emails: Vec<ContactLink>,
+    phones: Vec<ContactLink>,
+    /** newest first */
+    interactions: Vec<InteractionEntry>,
+    address: String,
+    birthday: String,
+    anniversary: String,
+    /** the photo as a `data:` URI (blank if none) */
+    photo: String,
+    rendered_notes: String,
+    base: ShowBaseTemplate,
+}
+
+/** template data for importing and exporting vCards */
+#[derive(Template)]
+#[template(path = "vcard.html", print = "none")]
+struct VCardTemplate {
+    exported: String,
+    count: usize,
+    message: String,
+}
+
+/** write all the contacts (with their photos) to a vCard file in the
+export directory, and also show them as vCard text with a form for
+importing more */
+pub fn export_vcards(world: &mut World) -> fanling_interface::ResponseResult {
+    let mut cards = vec![];
+    for entry in world.search_type("Contact")?.entries {
+        let (_base, values) = world.get_item_parts(&entry.link.ident)?;
+        if crate::private::is_sealed(&values) {
+            continue;
+        }
+        let mut contact = Contact::new();
+        contact.set_from_yaml_basic(&values)?;
+        cards.push(contact.to_vcard(world));
+    }
+    let exported = vcard::to_vcard_text(&cards);
+    let dir = world.export_dir().to_path_buf();
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!(
+        "contacts-{}.vcf",
+        chrono::Local::today()
+            .naive_local()
+            .format(crate::format::ISO_DATE_FORMAT)
+    ));
+    fs::write(&path, &exported)?;
+    trace(&format!("exported {} contacts to {:?}", cards.len(), &path));
+    let message = format!("Exported to {}", path.display());
+    let t = VCardTemplate {
+        exported,
+        count: cards.len(),
+        message: message.clone(),
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    {
+        resp.set_test_data("count", &format!("{}", cards.len()));
+        resp.set_test_data("message", &message);
+    }
+    Ok(resp)
+}
+/** create a contact for each card in the vCard text, then list the new contacts */
+pub fn import_vcards(world: &mut World, text: &str) -> fanling_interface::ResponseResult {
+    let base = ItemBaseForSerde {
+        ident: "".to_owned(),
+        type_name: "Contact".to_owned(),
+        ..ItemBaseForSerde::default()
+    };
+    let mut imported = crate::item::ItemListEntryList::from_vec(vec![]);
+    for card in vcard::parse(text) {
+        if card.name.trim().is_empty() {
+            trace(&format!("skipping vCard without a name: {:?}", &card));
+            continue;
+        }
+        let vals = Contact::vals_from_vcard(&card);
+        let item_ref = world.make_item("Contact", &base, &vals)?;
+        imported.add(crate::item::ItemListEntry::from_item(&item_ref.borrow())?);
+    }
+    trace(&format!("imported {} contacts", imported.num_entries()));
+    World::show_list(&mut imported, "imported")
+}
+
+/** policy for the contact item type*/
+#[derive(Debug)]
+pub struct ContactTypePolicy {}
+impl ContactTypePolicy {
+    pub fn new() -> Self {
+        Self {}
+    }
+    pub fn new_boxed() -> Box<Self> {
+        Box::new(Self::new())
+    }
+}
+impl crate::item::ItemTypePolicy for ContactTypePolicy {
+    fn kind(&self) -> crate::item::ItemKind {
+        crate::item::ItemKind::Contact
+    }
+    fn make_raw(&self, item_type: crate::item::ItemTypeRef) -> Item {
+        Item::new_with_data(item_type, Box::new(Contact::new()))
+    }
+    fn resolve_conflict_both(
+        &self,
+        _world: &mut World,
+        _ancestor: &Value,
+        ours: &Value,
+        theirs: &Value,
+    ) -> FLResult<Box<dyn ItemData>> {
+        let mut oc = Contact::new();
+        oc.set_from_yaml_basic(&ours)?;
+        let mut tc = Contact::new();
+        tc.set_from_yaml_basic(&theirs)?;
+        oc.name = merge_strings(&oc.name, &tc.name);
+        oc.emails = merge_lists(&oc.emails, &tc.emails);
+        oc.phones = merge_lists(&oc.phones, &tc.phones);
+        oc.address = merge_strings(&oc.address, &tc.address);
+        oc.birthday = oc.birthday.or(tc.birthday);
+        oc.anniversary = oc.anniversary.or(tc.anniversary);
+        if oc.photo.is_empty() {
+            oc.photo = tc.photo;
+        }
+        oc.notes = merge_strings(&oc.notes, &tc.notes);
+        for interaction in tc.interactions {
+            if !oc.interactions.contains(&interaction) {
+                oc.interactions.push(interaction);
+            }
+        }
+        oc.interactions.sort_by_key(|i| i.when);
+        Ok(Box::new(oc))
+    }
+    fn check_valid(
+        &mut self,
+        _base: &ItemBaseForSerde,
+        vals: &HashMap<String, String>,
+        world: &mut World,
+    ) -> ActionResponse {
+        let mut ar = ActionResponse::new();
+        ar.assert(
+            !vals.get("name").map_or(true, |n| n.is_empty()),
+            "name-error",
+            "Name must be non-blank.",
+        );
+        ar.assert(
+            vals.get("emails")
+                .map_or(true, |e| split_list(e).iter().all(|a| a.contains('@'))),
+            "emails-error",
+            "Each e-mail address must contain '@'.",
+        );
+        ar.assert(
+            parse_optional_date(vals.get("birthday"), world).is_ok(),
+            "birthday-error",
+            "Invalid birthday",
+        );
+        ar.assert(
+            parse_optional_date(vals.get("anniversary"), world).is_ok(),
+            "anniversary-error",
+            "Invalid anniversary",
+        );
+        ar
+    }
+    /** get item data from serde value */
+    fn from_yaml(&self, values: &Value, world: &mut World) -> FLResult<Box<dyn ItemData>> {
+        let mut c = Contact::default();
+        c.set_from_yaml(&values, world)?;
+        Ok(Box::new(c))
+    }
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "contact {}",
+        Colour::Fixed(13).on(Colour::Fixed(233)).paint(m)
+    );
+}
@@ -0,0 +1,110 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! the credentials for the remotes: the HTTPS token for each remote
+reached over HTTPS, and otherwise the passphrase of its ssh key.
+
+The secrets are kept by the credential provider given by the main
+program (see [`taipo_git_control::CredentialProvider`]): the keychain
+of the operating system on a desktop, or the Android Keystore on a
+phone. The credentials page (reached from the settings page) shows
+which remotes have a secret kept, lets the user set or forget one, and
+checks that a remote can be reached with it. The secrets themselves are
+never shown. */
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+
+/** the body of a request, as it can be traced (without any secret in it) */
+pub fn for_trace(body: &str) -> &str {
+    if body.contains("SaveCredential") {
+        "(saving a credential)"
+    } else {
+        body
+    }
+}
+/** a remote, for the template */
+struct Row {
+    name: String,
+    url: String,
+    /** whether its secret is an HTTPS token (or else an ssh key passphrase) */
+    token: bool,
+    kept: bool,
+}
+/** template data for the credentials page */
+#[derive(Template)]
+#[template(path = "credentials.html", print = "none")]
+struct CredentialsTemplate {
+    /** the name of the credential provider (empty if there is none) */
+    provider: String,
+    rows: Vec<Row>,
+    message: String,
+}
+/** show the remotes, with whether there is a secret kept for each */
+pub fn show_credentials(world: &mut World, message: &str) -> fanling_interface::ResponseResult {
+    let mut rows = vec![];
+    for remote in world.remotes()? {
+        rows.push(Row {
+            token: world.credential_key(&remote.name)?.starts_with("token:"),
+            kept: world.has_secret(&remote.name)?,
+            name: remote.name,
+            url: remote.url,
+        });
+    }
+    let t = CredentialsTemplate {
+        provider: world.credential_provider().unwrap_or_default(),
+        rows,
+        message: world.i18n().tr(message),
+    };
+    trace(&format!("showing credentials from '{}'", t.provider));
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    resp.set_test_data(
+        "credentials",
+        &t.rows
+            .iter()
+            .map(|r| format!("{}:{}", r.name, if r.kept { "kept" } else { "none" }))
+            .collect::<Vec<String>>()
+            .join(","),
+    );
+    Ok(resp)
+}
+/** keep the secret for the remote (from the credentials page), then show the credentials */
+pub fn save_credential(
+    world: &mut World,
+    remote: &str,
+    secret: &str,
+) -> fanling_interface::ResponseResult {
+    if secret.is_empty() {
+        return show_credentials(world, "Nothing was saved.");
+    }
+    trace(&format!("keeping the secret for {}", remote));
+    world.set_secret(remote, secret)?;
+    show_credentials(world, "The secret has been saved.")
+}
+/** forget the secret for the remote, then show the credentials */
+pub fn forget_credential(world: &mut World, remote: &str) -> fanling_interface::ResponseResult {
+    trace(&format!("forgetting the secret for {}", remote));
+    world.forget_secret(remote)?;
+    show_credentials(world, "The secret has been forgotten.")
+}
+/** check that the remote can be reached with its credentials, then show the credentials */
+pub fn test_and_show(world: &mut World, remote: &str) -> fanling_interface::ResponseResult {
+    match world.test_remote(remote) {
+        Ok(()) => show_credentials(world, "The remote can be reached."),
+        Err(e) => {
+            trace(&format!("could not reach {}: {}", remote, e));
+            show_credentials(world, "The remote could not be reached.")
+        }
+    }
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "credentials {}",
+        Colour::Fixed(0).on(Colour::Fixed(223)).paint(m)
+    );
+}
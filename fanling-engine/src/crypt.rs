@@ -0,0 +1,196 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! encryption of the items in a repository.
+
+When encryption is enabled, the YAML for each item is encrypted
+(ChaCha20-Poly1305, with a key derived from a passphrase) before it is
+given to the repository, and decrypted when it is loaded. The salt
+for the key, and a value for checking the passphrase, are kept in the
+repository's configuration. Items written before encryption was
+enabled are still read, and are encrypted when next changed.
+
+The passphrase is asked for once per session. The search database
+would hold the decrypted data, so for an encrypted repository it is
+kept in memory only and rebuilt when the repository is opened. */
+use crate::shared::{FLResult, FanlingError};
+use ansi_term::Colour;
+use askama::Template;
+use chacha20poly1305::aead::generic_array::GenericArray;
+use chacha20poly1305::aead::{Aead, NewAead};
+use chacha20poly1305::ChaCha20Poly1305;
+use hmac::Hmac;
+use rand::RngCore;
+use sha2::Sha256;
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** marks the encrypted form of an item */
+const PREFIX: &str = "fanling-encrypted-v1:";
+/** repository configuration key for the salt */
+const SALT_KEY: &str = "fanling.encryption-salt";
+/** repository configuration key for the value used to check the passphrase */
+const CHECK_KEY: &str = "fanling.encryption-check";
+/** the text that is encrypted for checking the passphrase */
+const CHECK_TEXT: &str = "fanling";
+/** iterations used when deriving the key */
+const ROUNDS: u32 = 100_000;
+const NONCE_LEN: usize = 12;
+/** search database path used for encrypted repositories (so nothing decrypted is written out) */
+pub const IN_MEMORY_DATABASE: &str = ":memory:";
+
+/** options for encrypting the repositories */
+#[derive(Debug, Clone, Default)]
+pub struct EncryptionOptions {
+    /** whether items are encrypted */
+    pub enabled: bool,
+    /** the passphrase (if `None`, it is asked for) */
+    pub passphrase: Option<String>,
+}
+impl EncryptionOptions {
+    /** whether the passphrase still has to be asked for */
+    pub fn is_locked(&self) -> bool {
+        self.enabled && self.passphrase.is_none()
+    }
+}
+
+/** encrypts and decrypts items */
+pub(crate) struct Cipher {
+    cipher: ChaCha20Poly1305,
+}
+impl Cipher {
+    /** the cipher for a passphrase and salt */
+    fn new(passphrase: &str, salt: &[u8]) -> Self {
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2::<Hmac<Sha256>>(passphrase.as_bytes(), salt, ROUNDS, &mut key);
+        Self {
+            cipher: ChaCha20Poly1305::new(GenericArray::from_slice(&key)),
+        }
+    }
+    /** the cipher for a repository, given its configuration values
+    for the salt and check value. If the repository does not have
+    them yet, the values to save are also returned. */
+    pub(crate) fn for_repo(
+        passphrase: &str,
+        salt: Option<String>,
+        check: Option<String>,
+    ) -> FLResult<(Self, Option<(String, String)>)> {
+        match (salt, check) {
+            (Some(salt), Some(check)) => {
+                let cipher = Self::new(passphrase, &decode(&salt)?);
+                if cipher.open(&check).ok().as_deref() != Some(CHECK_TEXT) {
+                    return Err(fanling_error!("wrong passphrase"));
+                }
+                Ok((cipher, None))
+            }
+            _ => {
+                trace("setting up encryption for repository");
+                let salt = new_salt();
+                let cipher = Self::new(passphrase, &salt);
+                let check = cipher.seal(CHECK_TEXT)?;
+                Ok((cipher, Some((base64::encode(&salt), check))))
+            }
+        }
+    }
+    /** the cipher for a private item, given its salt (if it has none,
+    a new one is made). The salt is also returned. */
+    pub(crate) fn for_item(passphrase: &str, salt: Option<&str>) -> FLResult<(Self, String)> {
+        let salt = match salt {
+            Some(salt) => decode(salt)?,
+            None => new_salt().to_vec(),
+        };
+        Ok((Self::new(passphrase, &salt), base64::encode(&salt)))
+    }
+    /** the encrypted form of some text */
+    pub(crate) fn seal(&self, plain: &str) -> FLResult<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let sealed = self
+            .cipher
+            .encrypt(GenericArray::from_slice(&nonce), plain.as_bytes())
+            .map_err(|_| fanling_error!("could not encrypt item"))?;
+        let mut data = nonce.to_vec();
+        data.extend(sealed);
+        Ok(format!("{}{}\n", PREFIX, base64::encode(&data)))
+    }
+    /** the text from its encrypted form (text that is not encrypted is returned as it is) */
+    pub(crate) fn open(&self, data: &str) -> FLResult<String> {
+        if !is_encrypted(data) {
+            return Ok(data.to_owned());
+        }
+        let data = decode(&data.trim()[PREFIX.len()..])?;
+        if data.len() < NONCE_LEN {
+            return Err(fanling_error!("encrypted item is too short"));
+        }
+        let plain = self
+            .cipher
+            .decrypt(GenericArray::from_slice(&data[..NONCE_LEN]), &data[NONCE_LEN..])
+            .map_err(|_| fanling_error!("could not decrypt item (wrong passphrase?)"))?;
+        String::from_utf8(plain).map_err(|_| fanling_error!("decrypted item is not text"))
+    }
+}
+
+/** whether some data is encrypted */
+pub fn is_encrypted(data: &str) -> bool {
+    data.starts_with(PREFIX)
+}
+/** the configuration keys for the salt and check value */
+pub(crate) fn config_keys() -> (&'static str, &'static str) {
+    (SALT_KEY, CHECK_KEY)
+}
+fn new_salt() -> [u8; 16] {
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    salt
+}
+fn decode(s: &str) -> FLResult<Vec<u8>> {
+    base64::decode(s.trim()).map_err(|e| fanling_error!(&format!("bad encrypted data: {}", e)))
+}
+
+/** template data for asking for the passphrase */
+#[derive(Template)]
+#[template(path = "unlock.html", print = "none")]
+struct UnlockTemplate {
+    message: String,
+}
+/** the form asking for the passphrase */
+pub fn unlock_html(message: &str) -> FLResult<String> {
+    Ok(UnlockTemplate {
+        message: message.to_owned(),
+    }
+    .render()?)
+}
+/** a response asking for the passphrase */
+pub fn ask_passphrase(message: &str) -> fanling_interface::ResponseResult {
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &unlock_html(message)?);
+    resp.set_prompt("passphrase");
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("crypt {}", Colour::Fixed(9).on(Colour::Fixed(233)).paint(m));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn seal_and_open() -> FLResult<()> {
+        let (cipher, new_values) = Cipher::for_repo("secret", None, None)?;
+        let (salt, check) = new_values.expect("no salt");
+        let sealed = cipher.seal("name: hello\n")?;
+        assert!(is_encrypted(&sealed));
+        assert!(!sealed.contains("hello"));
+        let (again, new_values) =
+            Cipher::for_repo("secret", Some(salt.clone()), Some(check.clone()))?;
+        assert!(new_values.is_none());
+        assert_eq!("name: hello\n", again.open(&sealed)?);
+        assert_eq!("plain: text\n", again.open("plain: text\n")?);
+        assert!(Cipher::for_repo("wrong", Some(salt), Some(check)).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! the dashboard, which updates several regions of the page with a
+single response: the focus view as the content, the events of the
+next few days in the sidebar, a status line for the repository, and
+the reminders as notifications.
+
+Also the home page, which is shown when the app starts: the items
+pinned to it, the favourites, the items modified most recently and
+counts of the open tasks. */
+use crate::item::ItemBaseForSerde;
+use crate::shared::FLResult;
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use chrono::Duration;
+use fanling_interface::region;
+
+/** how many days of events are shown in the sidebar */
+const UPCOMING_DAYS: i64 = 7;
+/** how many of the items modified most recently are shown on the home page */
+const RECENT_ITEMS: usize = 10;
+
+/** an occurrence of an event, for the sidebar */
+struct UpcomingEntry {
+    ident: String,
+    when: String,
+    name: String,
+}
+/** template data for the sidebar */
+#[derive(Template)]
+#[template(path = "sidebar.html", print = "none")]
+struct SidebarTemplate {
+    entries: Vec<UpcomingEntry>,
+}
+/** template data for the status line */
+#[derive(Template)]
+#[template(path = "status.html", print = "none")]
+struct StatusTemplate {
+    repo_name: String,
+    ready: usize,
+    open: usize,
+    needs_push: bool,
+}
+
+/** an item shown on the home page */
+struct HomeEntry {
+    ident: String,
+    type_name: String,
+    descr: String,
+    /** when it was last modified, as text */
+    when: String,
+}
+impl HomeEntry {
+    fn new(base: &ItemBaseForSerde, descr: &str, world: &World) -> Self {
+        Self {
+            ident: base.ident.clone(),
+            type_name: base.type_name.clone(),
+            descr: descr.to_owned(),
+            when: world.format().format_date_time(base.when_modified),
+        }
+    }
+}
+/** template data for the home page */
+#[derive(Template)]
+#[template(path = "home.html", print = "none")]
+struct HomeTemplate {
+    pinned: Vec<HomeEntry>,
+    favourites: Vec<HomeEntry>,
+    recent: Vec<HomeEntry>,
+    open_tasks: usize,
+    /** open tasks whose deadline has passed */
+    overdue: usize,
+    ready: usize,
+}
+
+/** the sidebar, showing the events in the next few days */
+fn sidebar(world: &mut World) -> FLResult<(String, usize)> {
+    let today = chrono::Local::today().naive_local();
+    let entries: Vec<UpcomingEntry> =
+        crate::event::upcoming(world, today, today + Duration::days(UPCOMING_DAYS))?
+            .into_iter()
+            .map(|(start, ident, name)| UpcomingEntry {
+                ident,
+                when: world.format().format_date_time(start),
+                name,
+            })
+            .collect();
+    let count = entries.len();
+    Ok((SidebarTemplate { entries }.render()?, count))
+}
+/** the status line, with the number of ready and open items */
+fn status(world: &mut World) -> FLResult<(String, usize)> {
+    let ready = world.list_entries("ready")?.num_entries();
+    let t = StatusTemplate {
+        repo_name: world.repo_name(),
+        ready,
+        open: world.list_entries("open")?.num_entries(),
+        needs_push: world.needs_push(),
+    };
+    Ok((t.render()?, ready))
+}
+
+/** show the dashboard */
+pub fn show_dashboard(world: &mut World) -> fanling_interface::ResponseResult {
+    let (sidebar, events) = sidebar(world)?;
+    let (status, ready) = status(world)?;
+    let mut resp = fanling_interface::Response::new_with_tags(&[
+        (region::SIDEBAR, sidebar.as_str()),
+        (region::STATUS, status.as_str()),
+    ]);
+    resp.merge(crate::focus::show_focus(world)?);
+    crate::reminder::add_notifications(world, &mut resp)?;
+    trace(&format!("dashboard with {} events, {} ready", events, ready));
+    #[cfg(test)]
+    {
+        resp.set_test_data("events", &format!("{}", events));
+        resp.set_test_data("ready", &format!("{}", ready));
+    }
+    Ok(resp)
+}
+
+/** what the home page shows */
+fn home(world: &mut World) -> FLResult<HomeTemplate> {
+    let now = chrono::Local::now().naive_local();
+    let mut t = HomeTemplate {
+        pinned: vec![],
+        favourites: vec![],
+        recent: vec![],
+        open_tasks: 0,
+        overdue: 0,
+        ready: world.list_entries("ready")?.num_entries(),
+    };
+    let mut recent = vec![];
+    for entry in world.search_all()?.entries {
+        let (base, values) = match world.get_item_parts(&entry.link.ident) {
+            Ok(parts) => parts,
+            Err(e) => {
+                trace(&format!("can not read {}: {:?}", &entry.link.ident, e));
+                continue;
+            }
+        };
+        if base.is_pinned {
+            t.pinned.push(HomeEntry::new(&base, &entry.descr, world));
+        }
+        if base.is_favourite {
+            t.favourites.push(HomeEntry::new(&base, &entry.descr, world));
+        }
+        if base.type_name == "Task" && !crate::private::is_sealed(&values) {
+            if let Some((_name, _remind, deadline)) = crate::task::reminder_times(&values)? {
+                t.open_tasks += 1;
+                if !crate::task::is_no_time(&deadline) && deadline < now {
+                    t.overdue += 1;
+                }
+            }
+        }
+        if base.classify != "archived" {
+            recent.push((base, entry.descr));
+        }
+    }
+    recent.sort_by(|(a, _), (b, _)| b.when_modified.cmp(&a.when_modified));
+    t.recent = recent
+        .iter()
+        .take(RECENT_ITEMS)
+        .map(|(base, descr)| HomeEntry::new(base, descr, world))
+        .collect();
+    Ok(t)
+}
+/** show the home page, with the reminders as notifications */
+pub fn show_home(world: &mut World) -> fanling_interface::ResponseResult {
+    let t = home(world)?;
+    trace(&format!(
+        "home with {} pinned, {} favourites, {} open tasks",
+        t.pinned.len(),
+        t.favourites.len(),
+        t.open_tasks
+    ));
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &t.render()?);
+    crate::reminder::add_notifications(world, &mut resp)?;
+    if !world.collisions().is_empty() {
+        resp.add_notification(&format!(
+            "{} items have idents that collide with those of others: see the check page.",
+            world.collisions().len()
+        ));
+    }
+    #[cfg(test)]
+    {
+        let idents = |entries: &[HomeEntry]| {
+            entries
+                .iter()
+                .map(|e| e.ident.clone())
+                .collect::<Vec<String>>()
+                .join(",")
+        };
+        resp.set_test_data("pinned", &idents(&t.pinned));
+        resp.set_test_data("favourites", &idents(&t.favourites));
+        resp.set_test_data("recent", &idents(&t.recent));
+        resp.set_test_data("open-tasks", &format!("{}", t.open_tasks));
+        resp.set_test_data("overdue", &format!("{}", t.overdue));
+    }
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "dashboard {}",
+        Colour::Fixed(10).on(Colour::Fixed(236)).paint(m)
+    );
+}
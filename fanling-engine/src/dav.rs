@@ -0,0 +1,991 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! two-way sync of event items with a CalDAV calendar, and of contact
+items with a CardDAV address book.
+
+The server is given in the engine options (see [`DavOptions`]), as it
+belongs to the device rather than to the repository. Syncing lists the
+resources in each collection with their etags, and compares them (and
+a hash of each item as it would be sent) with what they were at the
+last sync, which is kept in a local file beside the search database:
+
+* an item changed here is sent, and a resource changed there is fetched;
+* an item or resource that is new is fetched or sent;
+* something deleted on one side (and not changed on the other) is
+  deleted on the other side, but one that was changed is kept;
+* if both have changed, the preference in the options says which is
+  kept: the local item, the remote resource, or whichever was modified
+  most recently (from its `LAST-MODIFIED` or `REV`).
+
+Syncing is done with the other syncs, and from the calendar page.
+Private items are never sent. A read-only repository only sends its
+items, and nothing is changed locally. */
+use crate::contact::Contact;
+use crate::event::Event;
+use crate::item::ItemBaseForSerde;
+use crate::shared::{FLResult, FanlingError, NullResult};
+use crate::world::World;
+use crate::{ical, vcard};
+use ansi_term::Colour;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** the name of the file that the state of the last sync is kept in */
+pub const STATE_FILE: &str = "dav-state.json";
+/** how long to wait for the server (ms) */
+const TIMEOUT_MS: u64 = 20_000;
+/** the request for the etags of the resources in a collection */
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?><d:propfind xmlns:d="DAV:"><d:prop><d:getetag/></d:prop></d:propfind>"#;
+
+/** which is kept when both the item and the resource have changed */
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preference {
+    /** the local item */
+    Local,
+    /** the resource on the server */
+    Remote,
+    /** whichever was modified most recently */
+    Newer,
+}
+impl Preference {
+    /** the preference from its name (`local`, `remote` or `newer`) */
+    pub fn parse(name: &str) -> FLResult<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "local" => Ok(Preference::Local),
+            "remote" => Ok(Preference::Remote),
+            "newer" | "" => Ok(Preference::Newer),
+            other => Err(fanling_error!(&format!(
+                "unknown preference '{}' (local, remote or newer)",
+                other
+            ))),
+        }
+    }
+    /** whether the local item is kept, given when each was modified */
+    fn keeps_local(self, local: NaiveDateTime, remote: Option<NaiveDateTime>) -> bool {
+        match self {
+            Preference::Local => true,
+            Preference::Remote => false,
+            Preference::Newer => remote.map_or(true, |remote| local >= remote),
+        }
+    }
+}
+/** the server, as given in the engine options */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DavOptions {
+    /** the url of the CalDAV calendar (not synced if empty) */
+    pub calendar: String,
+    /** the url of the CardDAV address book (not synced if empty) */
+    pub addressbook: String,
+    pub user: String,
+    pub password: String,
+    pub prefer: Preference,
+}
+impl DavOptions {
+    /** the options, if either url is given */
+    pub fn new(
+        calendar: &str,
+        addressbook: &str,
+        user: &str,
+        password: &str,
+        prefer: &str,
+    ) -> FLResult<Option<Self>> {
+        if calendar.trim().is_empty() && addressbook.trim().is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            calendar: calendar.trim().to_owned(),
+            addressbook: addressbook.trim().to_owned(),
+            user: user.to_owned(),
+            password: password.to_owned(),
+            prefer: Preference::parse(prefer)?,
+        }))
+    }
+}
+
+/** the server, with where the state of the last sync is kept */
+#[derive(Debug, Clone)]
+pub struct Dav {
+    options: DavOptions,
+    path: PathBuf,
+}
+impl Dav {
+    /** the server (if there is one), keeping the state in the directory `dir` */
+    pub fn open(options: &Option<DavOptions>, dir: &Path) -> Option<Self> {
+        options.as_ref().map(|options| Self {
+            options: options.clone(),
+            path: dir.join(STATE_FILE),
+        })
+    }
+}
+
+/** a collection that is synced */
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Collection {
+    Calendar,
+    AddressBook,
+}
+impl Collection {
+    const ALL: [Collection; 2] = [Collection::Calendar, Collection::AddressBook];
+    /** the type of the items synced with the collection */
+    fn type_name(self) -> &'static str {
+        match self {
+            Collection::Calendar => "Event",
+            Collection::AddressBook => "Contact",
+        }
+    }
+    fn extension(self) -> &'static str {
+        match self {
+            Collection::Calendar => ".ics",
+            Collection::AddressBook => ".vcf",
+        }
+    }
+    fn content_type(self) -> &'static str {
+        match self {
+            Collection::Calendar => "text/calendar; charset=utf-8",
+            Collection::AddressBook => "text/vcard; charset=utf-8",
+        }
+    }
+    fn url(self, options: &DavOptions) -> &str {
+        match self {
+            Collection::Calendar => &options.calendar,
+            Collection::AddressBook => &options.addressbook,
+        }
+    }
+}
+
+/** an item and the resource it was synced with, as they were then */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Mapping {
+    ident: String,
+    href: String,
+    etag: String,
+    /** the `UID` of the resource */
+    uid: String,
+    /** the hash of the item as it was sent or fetched */
+    hash: String,
+}
+/** read the state file, if it is there */
+fn read_state(path: &Path) -> FLResult<Vec<Mapping>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+fn write_state(path: &Path, mappings: &[Mapping]) -> NullResult {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(mappings)?)?;
+    Ok(())
+}
+
+/** a local item that is synced */
+#[derive(Debug, Clone, PartialEq)]
+struct Local {
+    ident: String,
+    hash: String,
+    modified: NaiveDateTime,
+}
+/** a resource on the server */
+#[derive(Debug, Clone, PartialEq)]
+struct Remote {
+    href: String,
+    etag: String,
+}
+/** what is to be done to sync an item or resource */
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    /** send the item (as a new resource, if there is no etag) */
+    Upload {
+        ident: String,
+        href: String,
+        etag: Option<String>,
+    },
+    /** fetch the resource (as a new item, if there is no ident) */
+    Download {
+        href: String,
+        ident: Option<String>,
+    },
+    DeleteLocal {
+        ident: String,
+    },
+    DeleteRemote {
+        href: String,
+        etag: String,
+    },
+    /** both have changed, so the preference decides */
+    Conflict {
+        ident: String,
+        href: String,
+        etag: String,
+    },
+    /** gone from both sides */
+    Forget {
+        ident: String,
+    },
+}
+/** the href for a new resource for the item */
+fn new_href(collection_url: &str, ident: &str, extension: &str) -> String {
+    let slug: String = ident
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    format!(
+        "{}/{}{}",
+        collection_url.trim_end_matches('/'),
+        slug,
+        extension
+    )
+}
+/** what is to be done, comparing the items and resources with the last sync */
+fn plan(
+    collection_url: &str,
+    extension: &str,
+    locals: &[Local],
+    remotes: &[Remote],
+    mappings: &[Mapping],
+) -> Vec<Step> {
+    let mut steps = vec![];
+    for m in mappings {
+        let local = locals.iter().find(|l| l.ident == m.ident);
+        let remote = remotes.iter().find(|r| r.href == m.href);
+        steps.push(match (local, remote) {
+            (Some(l), Some(r)) => match (l.hash != m.hash, r.etag != m.etag) {
+                (false, false) => continue,
+                (true, false) => Step::Upload {
+                    ident: m.ident.clone(),
+                    href: m.href.clone(),
+                    etag: Some(r.etag.clone()),
+                },
+                (false, true) => Step::Download {
+                    href: m.href.clone(),
+                    ident: Some(m.ident.clone()),
+                },
+                (true, true) => Step::Conflict {
+                    ident: m.ident.clone(),
+                    href: m.href.clone(),
+                    etag: r.etag.clone(),
+                },
+            },
+            /* changed here and deleted there: sent again */
+            (Some(l), None) if l.hash != m.hash => Step::Upload {
+                ident: m.ident.clone(),
+                href: m.href.clone(),
+                etag: None,
+            },
+            (Some(_), None) => Step::DeleteLocal {
+                ident: m.ident.clone(),
+            },
+            /* deleted here and changed there: fetched again */
+            (None, Some(r)) if r.etag != m.etag => Step::Download {
+                href: m.href.clone(),
+                ident: None,
+            },
+            (None, Some(r)) => Step::DeleteRemote {
+                href: m.href.clone(),
+                etag: r.etag.clone(),
+            },
+            (None, None) => Step::Forget {
+                ident: m.ident.clone(),
+            },
+        });
+    }
+    for l in locals {
+        if !mappings.iter().any(|m| m.ident == l.ident) {
+            steps.push(Step::Upload {
+                ident: l.ident.clone(),
+                href: new_href(collection_url, &l.ident, extension),
+                etag: None,
+            });
+        }
+    }
+    for r in remotes {
+        if !mappings.iter().any(|m| m.href == r.href) {
+            steps.push(Step::Download {
+                href: r.href.clone(),
+                ident: None,
+            });
+        }
+    }
+    steps
+}
+
+/** the text of the XML elements with the (local) name, ignoring namespace prefixes */
+fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let local = |tag: &str| {
+        let tag = tag.trim_end_matches('/');
+        tag.rsplit(':').next().unwrap_or("").to_owned()
+    };
+    let mut found = vec![];
+    let mut rest = xml;
+    while let Some(open) = rest.find('<') {
+        rest = &rest[open + 1..];
+        let end = match rest.find('>') {
+            Some(end) => end,
+            None => break,
+        };
+        let tag = rest[..end].split_whitespace().next().unwrap_or("");
+        let self_closing = rest[..end].ends_with('/');
+        rest = &rest[end + 1..];
+        if tag.starts_with('/') || local(tag) != name {
+            continue;
+        }
+        if self_closing {
+            found.push("");
+            continue;
+        }
+        /* the closing tag, which has the same prefix */
+        let close = format!("</{}>", tag);
+        match rest.find(&close) {
+            Some(close_at) => {
+                found.push(&rest[..close_at]);
+                rest = &rest[close_at + close.len()..];
+            }
+            None => break,
+        }
+    }
+    found
+}
+fn unescape_xml(s: &str) -> String {
+    s.trim()
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+/** the hrefs and etags of the resources in a `multistatus` response
+(collections, which have no etag, are left out) */
+fn multistatus(xml: &str) -> Vec<(String, String)> {
+    elements(xml, "response")
+        .iter()
+        .filter_map(|response| {
+            let href = unescape_xml(elements(response, "href").first()?);
+            let etag = unescape_xml(elements(response, "getetag").first()?);
+            if href.is_empty() || href.ends_with('/') || etag.is_empty() {
+                None
+            } else {
+                Some((href, etag))
+            }
+        })
+        .collect()
+}
+/** the url of an href in a response about the collection */
+fn resolve(collection_url: &str, href: &str) -> String {
+    if href.contains("://") {
+        return href.to_owned();
+    }
+    let after_scheme = collection_url.find("://").map_or(0, |i| i + 3);
+    if href.starts_with('/') {
+        let origin_end = collection_url[after_scheme..]
+            .find('/')
+            .map_or(collection_url.len(), |i| after_scheme + i);
+        return format!("{}{}", &collection_url[..origin_end], href);
+    }
+    format!("{}/{}", collection_url.trim_end_matches('/'), href)
+}
+/** when the resource was modified, from its `LAST-MODIFIED` (iCalendar) or `REV` (vCard) */
+fn last_modified(text: &str) -> Option<NaiveDateTime> {
+    text.lines().find_map(|line| {
+        let colon = line.find(':')?;
+        let prop = line[..colon].split(';').next().unwrap_or("");
+        if prop.eq_ignore_ascii_case("LAST-MODIFIED") || prop.eq_ignore_ascii_case("REV") {
+            let value = line[colon + 1..].trim().replace('-', "").replace(':', "");
+            ical::parse_date_time(&value)
+        } else {
+            None
+        }
+    })
+}
+
+/** the requests made to the server */
+struct Server<'a> {
+    options: &'a DavOptions,
+}
+impl<'a> Server<'a> {
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        let mut request = ureq::request(method, url);
+        request.timeout_connect(TIMEOUT_MS).timeout_read(TIMEOUT_MS);
+        if !self.options.user.is_empty() {
+            request.auth(&self.options.user, &self.options.password);
+        }
+        request
+    }
+    fn check(&self, method: &str, url: &str, resp: &ureq::Response) -> NullResult {
+        if resp.ok() {
+            return Ok(());
+        }
+        Err(fanling_error!(&format!(
+            "{} {} failed: {}",
+            method,
+            url,
+            resp.status_line()
+        )))
+    }
+    /** the resources in the collection */
+    fn list(&self, collection_url: &str) -> FLResult<Vec<Remote>> {
+        let resp = self
+            .request("PROPFIND", collection_url)
+            .set("Depth", "1")
+            .set("Content-Type", "application/xml; charset=utf-8")
+            .send_string(PROPFIND_BODY);
+        self.check("PROPFIND", collection_url, &resp)?;
+        let collection = collection_url.trim_end_matches('/');
+        Ok(multistatus(&resp.into_string()?)
+            .into_iter()
+            .map(|(href, etag)| Remote {
+                href: resolve(collection_url, &href),
+                etag,
+            })
+            .filter(|r| r.href.trim_end_matches('/') != collection)
+            .collect())
+    }
+    /** the resource, with its etag */
+    fn get(&self, href: &str) -> FLResult<(String, String)> {
+        let resp = self.request("GET", href).call();
+        self.check("GET", href, &resp)?;
+        let etag = resp.header("ETag").unwrap_or("").to_owned();
+        Ok((resp.into_string()?, etag))
+    }
+    /** write the resource (only if it has the etag, or is new), giving its new etag */
+    fn put(
+        &self,
+        href: &str,
+        content_type: &str,
+        body: &str,
+        etag: Option<&str>,
+    ) -> FLResult<String> {
+        let mut request = self.request("PUT", href);
+        request.set("Content-Type", content_type);
+        match etag {
+            Some(etag) => request.set("If-Match", etag),
+            None => request.set("If-None-Match", "*"),
+        };
+        let resp = request.send_string(body);
+        self.check("PUT", href, &resp)?;
+        match resp.header("ETag") {
+            Some(etag) => Ok(etag.to_owned()),
+            /* some servers only give the etag when asked */
+            None => Ok(self.get(href)?.1),
+        }
+    }
+    fn delete(&self, href: &str, etag: &str) -> NullResult {
+        let resp = self.request("DELETE", href).set("If-Match", etag).call();
+        self.check("DELETE", href, &resp)
+    }
+}
+
+/** what syncing did */
+#[derive(Debug, Default, PartialEq)]
+pub struct Report {
+    pub sent: usize,
+    pub fetched: usize,
+    pub deleted: usize,
+    pub conflicts: usize,
+    /** what could not be synced, with why */
+    pub failed: Vec<String>,
+}
+impl Report {
+    /** a summary, for the user */
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "Synced calendar and contacts: {} sent, {} fetched, {} deleted, {} conflicts resolved.",
+            self.sent, self.fetched, self.deleted, self.conflicts
+        );
+        if !self.failed.is_empty() {
+            summary.push_str(&format!(
+                " Could not sync {}: {}.",
+                self.failed.len(),
+                self.failed.join("; ")
+            ));
+        }
+        summary
+    }
+}
+
+/** the uid for an item that has not yet been synced */
+fn default_uid(ident: &str) -> String {
+    format!("{}@fanling", ident)
+}
+/** the uid of the resource for the item */
+fn uid_for(mappings: &[Mapping], ident: &str) -> String {
+    mappings
+        .iter()
+        .find(|m| m.ident == ident)
+        .map_or_else(|| default_uid(ident), |m| m.uid.clone())
+}
+/** the item as it is sent, with a hash of it (which only changes when
+the fields that are synced change) */
+fn render(
+    world: &World,
+    collection: Collection,
+    uid: &str,
+    values: &serde_yaml::Value,
+) -> FLResult<(String, String)> {
+    match collection {
+        Collection::Calendar => {
+            let event = Event::from_values(values)?.to_ical(uid);
+            let hash = crate::integrity::hash(format!("{:?}", event).as_bytes());
+            Ok((ical::to_ical_text(&[event]), hash))
+        }
+        Collection::AddressBook => {
+            let mut card = Contact::from_values(values)?.to_vcard(world);
+            card.uid = uid.to_owned();
+            let text = card.to_vcard_string();
+            let hash = crate::integrity::hash(text.as_bytes());
+            Ok((text, hash))
+        }
+    }
+}
+/** the values of the resource, as if entered by the user, with its uid */
+fn vals_from(collection: Collection, text: &str) -> FLResult<(HashMap<String, String>, String)> {
+    match collection {
+        Collection::Calendar => ical::parse(text)
+            .first()
+            .map(|event| (Event::vals_from_ical(event), event.uid.clone())),
+        Collection::AddressBook => vcard::parse(text)
+            .first()
+            .filter(|card| !card.name.trim().is_empty())
+            .map(|card| (Contact::vals_from_vcard(card), card.uid.clone())),
+    }
+    .ok_or_else(|| fanling_error!("nothing that can be synced"))
+}
+/** the stored values of the item (current, as they were just written) */
+fn item_values(world: &mut World, ident: &str) -> FLResult<serde_yaml::Value> {
+    let item_ref = world.get_item(ident.to_owned(), "Simple".to_owned())?;
+    let yaml = item_ref.deref().borrow().to_yaml()?;
+    Ok(serde_yaml::from_slice(&yaml)?)
+}
+/** the items of the collection's type that can be synced, with their values */
+fn local_items(
+    world: &mut World,
+    collection: Collection,
+) -> FLResult<Vec<(String, NaiveDateTime, serde_yaml::Value)>> {
+    let mut items = vec![];
+    for entry in world.search_type(collection.type_name())?.entries {
+        let (base, values) = world.get_item_parts(&entry.link.ident)?;
+        if base.is_private || crate::private::is_sealed(&values) {
+            continue;
+        }
+        items.push((entry.link.ident.clone(), base.when_modified, values));
+    }
+    Ok(items)
+}
+
+/** sync one collection, updating the mappings */
+fn sync_collection(
+    world: &mut World,
+    server: &Server,
+    collection: Collection,
+    mappings: &mut Vec<Mapping>,
+    report: &mut Report,
+) -> NullResult {
+    let url = collection.url(server.options).to_owned();
+    let remotes = server.list(&url)?;
+    let items = local_items(world, collection)?;
+    let mut locals = vec![];
+    let mut rendered = HashMap::new();
+    for (ident, modified, values) in &items {
+        let (text, hash) = render(world, collection, &uid_for(mappings, ident), values)?;
+        locals.push(Local {
+            ident: ident.clone(),
+            hash: hash.clone(),
+            modified: *modified,
+        });
+        rendered.insert(ident.clone(), (text, hash));
+    }
+    let steps = plan(&url, collection.extension(), &locals, &remotes, mappings);
+    trace(&format!(
+        "{} {} items and {} resources: {:?}",
+        collection.type_name(),
+        locals.len(),
+        remotes.len(),
+        steps
+    ));
+    let read_only = world.is_read_only();
+    for step in steps {
+        let outcome = match step {
+            Step::Conflict { ident, href, etag } => {
+                report.conflicts += 1;
+                let local = locals
+                    .iter()
+                    .find(|l| l.ident == ident)
+                    .map(|l| l.modified)
+                    .unwrap_or_else(crate::timing::now);
+                let remote = match server.options.prefer {
+                    Preference::Newer => {
+                        server.get(&href).ok().and_then(|(t, _)| last_modified(&t))
+                    }
+                    _ => None,
+                };
+                let resolved = if read_only || server.options.prefer.keeps_local(local, remote) {
+                    upload(
+                        server,
+                        collection,
+                        &rendered,
+                        mappings,
+                        &ident,
+                        &href,
+                        Some(etag.as_str()),
+                    )
+                } else {
+                    download(
+                        world,
+                        server,
+                        collection,
+                        mappings,
+                        &href,
+                        Some(ident.as_str()),
+                    )
+                };
+                resolved.map_err(|e| format!("{}: {}", ident, e))
+            }
+            Step::Upload { ident, href, etag } => {
+                report.sent += 1;
+                upload(
+                    server,
+                    collection,
+                    &rendered,
+                    mappings,
+                    &ident,
+                    &href,
+                    etag.as_deref(),
+                )
+                .map_err(|e| format!("{}: {}", ident, e))
+            }
+            Step::Download { .. } | Step::DeleteLocal { .. } if read_only => Ok(()),
+            Step::Download { href, ident } => {
+                report.fetched += 1;
+                download(world, server, collection, mappings, &href, ident.as_deref())
+                    .map_err(|e| format!("{}: {}", href, e))
+            }
+            Step::DeleteLocal { ident } => {
+                report.deleted += 1;
+                let item_ref = world.get_item(ident.clone(), "Simple".to_owned());
+                item_ref
+                    .and_then(|item_ref| world.delete_item(item_ref))
+                    .map(|_| mappings.retain(|m| m.ident != ident))
+                    .map_err(|e| format!("{}: {}", ident, e))
+            }
+            Step::DeleteRemote { href, etag } => {
+                report.deleted += 1;
+                server
+                    .delete(&href, &etag)
+                    .map(|_| mappings.retain(|m| m.href != href))
+                    .map_err(|e| format!("{}: {}", href, e))
+            }
+            Step::Forget { ident } => {
+                mappings.retain(|m| m.ident != ident);
+                Ok(())
+            }
+        };
+        if let Err(e) = outcome {
+            trace(&format!("could not sync {}", e));
+            report.failed.push(e);
+        }
+    }
+    Ok(())
+}
+/** replace (or add) the mapping for the item */
+fn remember(mappings: &mut Vec<Mapping>, mapping: Mapping) {
+    mappings.retain(|m| m.ident != mapping.ident && m.href != mapping.href);
+    mappings.push(mapping);
+}
+/** send the item, as it was rendered */
+fn upload(
+    server: &Server,
+    collection: Collection,
+    rendered: &HashMap<String, (String, String)>,
+    mappings: &mut Vec<Mapping>,
+    ident: &str,
+    href: &str,
+    etag: Option<&str>,
+) -> NullResult {
+    let (text, hash) = rendered
+        .get(ident)
+        .ok_or_else(|| fanling_error!("item not rendered"))?;
+    let uid = uid_for(mappings, ident);
+    let etag = server.put(href, collection.content_type(), text, etag)?;
+    remember(
+        mappings,
+        Mapping {
+            ident: ident.to_owned(),
+            href: href.to_owned(),
+            etag,
+            uid,
+            hash: hash.clone(),
+        },
+    );
+    Ok(())
+}
+/** fetch the resource into the item (or a new item) */
+fn download(
+    world: &mut World,
+    server: &Server,
+    collection: Collection,
+    mappings: &mut Vec<Mapping>,
+    href: &str,
+    ident: Option<&str>,
+) -> NullResult {
+    let (text, etag) = server.get(href)?;
+    let (vals, uid) = vals_from(collection, &text)?;
+    let ident = match ident {
+        Some(ident) => {
+            let item_ref = world.get_item(ident.to_owned(), "Simple".to_owned())?;
+            let mut item = item_ref.deref().borrow_mut();
+            item.set_data(&vals, world)?;
+            world.persist_change_as(&mut item, "sync")?;
+            ident.to_owned()
+        }
+        None => {
+            let base = ItemBaseForSerde {
+                ident: "".to_owned(),
+                type_name: collection.type_name().to_owned(),
+                ..ItemBaseForSerde::default()
+            };
+            world
+                .make_item(collection.type_name(), &base, &vals)?
+                .deref()
+                .borrow()
+                .ident()
+        }
+    };
+    let uid = if uid.is_empty() {
+        default_uid(&ident)
+    } else {
+        uid
+    };
+    let values = item_values(world, &ident)?;
+    let (_text, hash) = render(world, collection, &uid, &values)?;
+    let etag = if etag.is_empty() {
+        server
+            .list(collection.url(server.options))?
+            .into_iter()
+            .find(|r| r.href == href)
+            .map(|r| r.etag)
+            .unwrap_or_default()
+    } else {
+        etag
+    };
+    remember(
+        mappings,
+        Mapping {
+            ident,
+            href: href.to_owned(),
+            etag,
+            uid,
+            hash,
+        },
+    );
+    Ok(())
+}
+
+/** sync the events and contacts with the server, if there is one */
+pub fn sync(world: &mut World) -> FLResult<Option<Report>> {
+    let dav = match world.dav() {
+        Some(dav) => dav.clone(),
+        None => return Ok(None),
+    };
+    let server = Server {
+        options: &dav.options,
+    };
+    let mut mappings = read_state(&dav.path).unwrap_or_else(|e| {
+        trace(&format!("ignoring state {:?}: {:?}", &dav.path, e));
+        vec![]
+    });
+    let mut report = Report::default();
+    world.begin_batch();
+    for collection in Collection::ALL.iter().copied() {
+        if collection.url(&dav.options).is_empty() {
+            continue;
+        }
+        let (mut ours, others): (Vec<Mapping>, Vec<Mapping>) =
+            mappings.into_iter().partition(|m| {
+                m.href
+                    .starts_with(collection.url(&dav.options).trim_end_matches('/'))
+            });
+        let synced = sync_collection(world, &server, collection, &mut ours, &mut report);
+        mappings = others.into_iter().chain(ours).collect();
+        if let Err(e) = synced {
+            trace(&format!("could not sync {:?}: {:?}", collection, e));
+            report.failed.push(format!("{:?}: {}", collection, e));
+        }
+    }
+    world.end_batch()?;
+    write_state(&dav.path, &mappings)?;
+    trace(&format!("synced: {:?}", report));
+    Ok(Some(report))
+}
+/** sync with the server, and say what was done */
+pub fn sync_dav(world: &mut World) -> fanling_interface::ResponseResult {
+    let mut resp = fanling_interface::Response::new();
+    match sync(world)? {
+        Some(report) => {
+            resp.show_toast(&report.summary());
+            #[cfg(test)]
+            resp.set_test_data("failed", &format!("{}", report.failed.len()));
+        }
+        None => resp.show_toast("No CalDAV or CardDAV server has been set up."),
+    }
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("dav {}", Colour::Fixed(0).on(Colour::Fixed(151)).paint(m));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn mapping(ident: &str, href: &str, etag: &str, hash: &str) -> Mapping {
+        Mapping {
+            ident: ident.to_owned(),
+            href: href.to_owned(),
+            etag: etag.to_owned(),
+            uid: default_uid(ident),
+            hash: hash.to_owned(),
+        }
+    }
+    fn local(ident: &str, hash: &str) -> Local {
+        Local {
+            ident: ident.to_owned(),
+            hash: hash.to_owned(),
+            modified: chrono::NaiveDate::from_ymd(2021, 1, 1).and_hms(0, 0, 0),
+        }
+    }
+    fn remote(href: &str, etag: &str) -> Remote {
+        Remote {
+            href: href.to_owned(),
+            etag: etag.to_owned(),
+        }
+    }
+    #[test]
+    fn planning() {
+        let url = "https://dav.example.com/cal/";
+        let mappings = vec![
+            mapping("same", "h/same", "e1", "x1"),
+            mapping("ours", "h/ours", "e2", "x2"),
+            mapping("theirs", "h/theirs", "e3", "x3"),
+            mapping("both", "h/both", "e4", "x4"),
+            mapping("gone-there", "h/gone-there", "e5", "x5"),
+            mapping("edited-gone-there", "h/edited", "e6", "x6"),
+            mapping("gone-here", "h/gone-here", "e7", "x7"),
+            mapping("gone", "h/gone", "e8", "x8"),
+        ];
+        let locals = vec![
+            local("same", "x1"),
+            local("ours", "changed"),
+            local("theirs", "x3"),
+            local("both", "changed"),
+            local("gone-there", "x5"),
+            local("edited-gone-there", "changed"),
+            local("new/here", "x9"),
+        ];
+        let remotes = vec![
+            remote("h/same", "e1"),
+            remote("h/ours", "e2"),
+            remote("h/theirs", "changed"),
+            remote("h/both", "changed"),
+            remote("h/gone-here", "e7"),
+            remote("h/new-there", "e10"),
+        ];
+        let steps = plan(url, ".ics", &locals, &remotes, &mappings);
+        assert_eq!(
+            vec![
+                Step::Upload {
+                    ident: "ours".to_owned(),
+                    href: "h/ours".to_owned(),
+                    etag: Some("e2".to_owned())
+                },
+                Step::Download {
+                    href: "h/theirs".to_owned(),
+                    ident: Some("theirs".to_owned())
+                },
+                Step::Conflict {
+                    ident: "both".to_owned(),
+                    href: "h/both".to_owned(),
+                    etag: "changed".to_owned()
+                },
+                Step::DeleteLocal {
+                    ident: "gone-there".to_owned()
+                },
+                Step::Upload {
+                    ident: "edited-gone-there".to_owned(),
+                    href: "h/edited".to_owned(),
+                    etag: None
+                },
+                Step::DeleteRemote {
+                    href: "h/gone-here".to_owned(),
+                    etag: "e7".to_owned()
+                },
+                Step::Forget {
+                    ident: "gone".to_owned()
+                },
+                Step::Upload {
+                    ident: "new/here".to_owned(),
+                    href: "https://dav.example.com/cal/new-here.ics".to_owned(),
+                    etag: None
+                },
+                Step::Download {
+                    href: "h/new-there".to_owned(),
+                    ident: None
+                },
+            ],
+            steps
+        );
+    }
+    #[test]
+    fn preferences() -> FLResult<()> {
+        let noon = chrono::NaiveDate::from_ymd(2021, 1, 1).and_hms(12, 0, 0);
+        let later = noon + chrono::Duration::hours(1);
+        assert!(Preference::parse("local")?.keeps_local(noon, Some(later)));
+        assert!(!Preference::parse("Remote")?.keeps_local(later, None));
+        assert!(!Preference::parse("newer")?.keeps_local(noon, Some(later)));
+        assert!(Preference::parse("")?.keeps_local(later, Some(noon)));
+        assert!(Preference::Newer.keeps_local(noon, None));
+        assert!(Preference::parse("mine").is_err());
+        Ok(())
+    }
+    #[test]
+    fn responses() {
+        let xml = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:">
+  <d:response><d:href>/cal/</d:href><d:propstat><d:prop><d:getetag/></d:prop></d:propstat></d:response>
+  <d:response><d:href>/cal/a.ics</d:href><d:propstat><d:prop><d:getetag>&quot;1-a&quot;</d:getetag></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>
+  <response xmlns="DAV:"><href>b%20c.ics</href><propstat><prop><getetag>"2"</getetag></prop></propstat></response>
+</d:multistatus>"#;
+        assert_eq!(
+            vec![
+                ("/cal/a.ics".to_owned(), "\"1-a\"".to_owned()),
+                ("b%20c.ics".to_owned(), "\"2\"".to_owned())
+            ],
+            multistatus(xml)
+        );
+        let url = "https://dav.example.com/cal/";
+        assert_eq!(
+            "https://dav.example.com/cal/a.ics",
+            resolve(url, "/cal/a.ics")
+        );
+        assert_eq!("https://dav.example.com/cal/b.ics", resolve(url, "b.ics"));
+        assert_eq!("http://other/x.ics", resolve(url, "http://other/x.ics"));
+    }
+    #[test]
+    fn modified() {
+        assert_eq!(
+            Some(chrono::NaiveDate::from_ymd(2021, 3, 4).and_hms(5, 6, 7)),
+            last_modified("BEGIN:VCARD\r\nREV:2021-03-04T05:06:07\r\nEND:VCARD\r\n")
+        );
+        assert_eq!(None, last_modified("BEGIN:VEVENT\nSUMMARY:x\nEND:VEVENT\n"));
+    }
+}
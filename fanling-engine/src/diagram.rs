@@ -0,0 +1,191 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! diagrams in markdown: fenced code blocks in the ```` ```dot ```` (or
+```` ```graphviz ````) and ```` ```mermaid ```` languages, shown as
+pictures (inline SVG) when an item is shown.
+
+With the `diagrams` feature (on the desktop) the pictures are made by
+the programs for the languages, which must be on the path: `dot` (from
+Graphviz) and `mmdc` (the Mermaid command line). Pictures are kept
+for the rest of the session, as making them is slow. Without the
+feature, or if the program is not there or cannot make the picture,
+the block is shown as code, as are blocks in other languages. */
+#[cfg(feature = "diagrams")]
+use crate::shared::FLResult;
+#[cfg(feature = "diagrams")]
+use ansi_term::Colour;
+#[cfg(feature = "diagrams")]
+use std::cell::RefCell;
+#[cfg(feature = "diagrams")]
+use std::collections::HashMap;
+#[cfg(feature = "diagrams")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+//#[macro_use]
+#[cfg(feature = "diagrams")]
+use crate::fanling_error;
+
+/** how many pictures are kept before they are forgotten */
+#[cfg(feature = "diagrams")]
+const CACHE_SIZE: usize = 64;
+/** a number for the names of the files given to `mmdc`, so that two pictures cannot be made in the same files at once */
+#[cfg(feature = "diagrams")]
+static NEXT_FILE: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(feature = "diagrams")]
+thread_local! {
+    /** the pictures made, by language and source (none if one could not be made) */
+    static PICTURES: RefCell<HashMap<(Language, String), Option<String>>> = RefCell::new(HashMap::new());
+}
+
+/** a language for diagrams */
+#[cfg(any(feature = "diagrams", test))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Language {
+    Dot,
+    Mermaid,
+}
+#[cfg(any(feature = "diagrams", test))]
+impl Language {
+    /** the language of a code block, if it is one for diagrams */
+    fn of_block(lang: &str) -> Option<Self> {
+        match lang.trim().to_lowercase().as_str() {
+            "dot" | "graphviz" => Some(Language::Dot),
+            "mermaid" => Some(Language::Mermaid),
+            _ => None,
+        }
+    }
+}
+
+/** the SVG element of a picture, without any XML declaration or doctype before it */
+#[cfg(any(feature = "diagrams", test))]
+fn svg_only(output: &str) -> Option<&str> {
+    let start = output.find("<svg")?;
+    let end = output.rfind("</svg>")? + "</svg>".len();
+    if end <= start {
+        return None;
+    }
+    Some(&output[start..end])
+}
+
+/** the picture for the source in the language, made by `dot` */
+#[cfg(feature = "diagrams")]
+fn dot(source: &str) -> FLResult<String> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+    let mut child = Command::new("dot")
+        .arg("-Tsvg")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    /* the standard input is closed (when dropped) before waiting */
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(source.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(fanling_error!(&format!(
+            "dot exited with {}",
+            output.status
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+/** the picture for the source in the language, made by `mmdc` (which only reads and writes files) */
+#[cfg(feature = "diagrams")]
+fn mermaid(source: &str) -> FLResult<String> {
+    use std::process::{Command, Stdio};
+    let stem = std::env::temp_dir().join(format!(
+        "fanling-diagram-{}-{}",
+        std::process::id(),
+        NEXT_FILE.fetch_add(1, Ordering::Relaxed)
+    ));
+    let input = stem.with_extension("mmd");
+    let output = stem.with_extension("svg");
+    std::fs::write(&input, source)?;
+    let status = Command::new("mmdc")
+        .arg("--quiet")
+        .arg("-i")
+        .arg(&input)
+        .arg("-o")
+        .arg(&output)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    let svg = std::fs::read_to_string(&output);
+    let _ = std::fs::remove_file(&input);
+    let _ = std::fs::remove_file(&output);
+    let status = status?;
+    if !status.success() {
+        return Err(fanling_error!(&format!("mmdc exited with {}", status)));
+    }
+    Ok(svg?)
+}
+/** the picture for the source in the language, if it can be made */
+#[cfg(feature = "diagrams")]
+fn picture(language: Language, source: &str) -> Option<String> {
+    let made = match language {
+        Language::Dot => dot(source),
+        Language::Mermaid => mermaid(source),
+    };
+    match made {
+        Ok(output) => svg_only(&output).map(|svg| svg.to_owned()),
+        Err(e) => {
+            trace(&format!("cannot make {:?} picture: {:?}", language, e));
+            None
+        }
+    }
+}
+
+/** the HTML for a code block in the language, if it is a diagram that can be shown as a picture */
+#[cfg(feature = "diagrams")]
+pub fn render(lang: &str, source: &str) -> Option<String> {
+    let language = Language::of_block(lang)?;
+    let key = (language, source.to_owned());
+    if let Some(known) = PICTURES.with(|p| p.borrow().get(&key).cloned()) {
+        return known;
+    }
+    let svg = picture(language, source).map(|svg| format!("<div class=\"diagram\">{}</div>", svg));
+    PICTURES.with(|p| {
+        let mut pictures = p.borrow_mut();
+        if pictures.len() >= CACHE_SIZE {
+            pictures.clear();
+        }
+        pictures.insert(key, svg.clone());
+    });
+    svg
+}
+/** diagrams are shown as code in this build */
+#[cfg(not(feature = "diagrams"))]
+pub fn render(_lang: &str, _source: &str) -> Option<String> {
+    None
+}
+
+/** convenience function for debug traces */
+#[cfg(feature = "diagrams")]
+fn trace(m: &str) {
+    println!(
+        "diagram {}",
+        Colour::Fixed(0).on(Colour::Fixed(151)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn languages() {
+        assert_eq!(Some(Language::Dot), Language::of_block("graphviz"));
+        assert_eq!(Some(Language::Mermaid), Language::of_block(" Mermaid"));
+        assert_eq!(None, Language::of_block("rust"));
+        assert_eq!(None, render("rust", "fn main() {}"));
+    }
+    #[test]
+    fn svg() {
+        let output = "<?xml version=\"1.0\"?>\n<!DOCTYPE svg>\n<svg width=\"8pt\"><g/></svg>\n";
+        assert_eq!(Some("<svg width=\"8pt\"><g/></svg>"), svg_only(output));
+        assert_eq!(None, svg_only("dot: syntax error"));
+    }
+}
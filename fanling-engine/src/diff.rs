@@ -0,0 +1,204 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! readable differences between two versions of an item (as in two
+commits in its history), shown word by word with the insertions and
+deletions highlighted.
+
+The text of the item is compared if both versions have one; otherwise
+the whole of the YAML is. Long texts are compared line by line, as
+comparing them word by word would be slow. */
+use crate::item::{split_data_parts, Item};
+use crate::shared::FLResult;
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use regex::Regex;
+
+/** the most tokens in each text for comparing word by word */
+const MAX_WORDS: usize = 3000;
+
+/** what happened to a piece of the text */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffKind {
+    Same,
+    Inserted,
+    Deleted,
+}
+
+/** the words (and the white space between them) of a text, or its
+lines if there are too many words */
+fn tokens(text: &str) -> FLResult<Vec<&str>> {
+    let words: Vec<&str> = Regex::new(r"\s+|\S+")?
+        .find_iter(text)
+        .map(|m| m.as_str())
+        .collect();
+    if words.len() <= MAX_WORDS {
+        return Ok(words);
+    }
+    Ok(Regex::new(r"[^\n]*\n|[^\n]+")?
+        .find_iter(text)
+        .map(|m| m.as_str())
+        .collect())
+}
+
+/** the differences between two texts, with runs of the same kind joined */
+pub fn diff(old: &str, new: &str) -> FLResult<Vec<(DiffKind, String)>> {
+    let (a, b) = (tokens(old)?, tokens(new)?);
+    /* lengths of the longest common subsequences of the tails */
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut parts: Vec<(DiffKind, String)> = vec![];
+    let mut push = |kind: DiffKind, token: &str| match parts.last_mut() {
+        Some((last, text)) if *last == kind => text.push_str(token),
+        _ => parts.push((kind, token.to_owned())),
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            push(DiffKind::Same, a[i]);
+            i += 1;
+            j += 1;
+        } else if j < b.len() && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            push(DiffKind::Inserted, b[j]);
+            j += 1;
+        } else {
+            push(DiffKind::Deleted, a[i]);
+            i += 1;
+        }
+    }
+    Ok(parts)
+}
+
+/** the differences as HTML */
+pub fn to_html(parts: &[(DiffKind, String)]) -> String {
+    parts
+        .iter()
+        .map(|(kind, text)| {
+            let text = crate::markdown::escape(text);
+            match kind {
+                DiffKind::Same => text,
+                DiffKind::Inserted => format!("<ins>{}</ins>", text),
+                DiffKind::Deleted => format!("<del>{}</del>", text),
+            }
+        })
+        .collect()
+}
+
+/** the text of a version of an item, if it has one */
+fn text_of(yaml: &str) -> FLResult<Option<String>> {
+    let (_base, values) = split_data_parts(yaml.as_bytes())?;
+    Ok(values
+        .get("text")
+        .and_then(|t| t.as_str())
+        .map(|t| t.to_owned()))
+}
+/** what to compare of two versions of an item (either of which may be
+missing): their texts if both have one, or else their YAML */
+pub(crate) fn comparable(old: Option<String>, new: Option<String>) -> FLResult<(String, String)> {
+    let old_text = match &old {
+        Some(yaml) => text_of(yaml)?,
+        None => Some("".to_owned()),
+    };
+    let new_text = match &new {
+        Some(yaml) => text_of(yaml)?,
+        None => Some("".to_owned()),
+    };
+    Ok(match (old_text, new_text) {
+        (Some(old_text), Some(new_text)) => (old_text, new_text),
+        _ => (old.unwrap_or_default(), new.unwrap_or_default()),
+    })
+}
+
+/** template data for the differences between two versions of an item */
+#[derive(Template)]
+#[template(path = "diff.html", print = "none")]
+struct DiffTemplate {
+    ident: String,
+    type_name: String,
+    descr: String,
+    /** the earlier commit (blank if the item did not exist) */
+    from: String,
+    /** the later commit */
+    to: String,
+    /** the differences as HTML */
+    rendered_diff: String,
+    /** a message, such as why they cannot be compared */
+    message: String,
+}
+
+/** show the differences in an item between two commits (the first
+blank to compare with nothing) */
+pub fn show_diff(
+    item: &Item,
+    from: &str,
+    to: &str,
+    world: &mut World,
+) -> fanling_interface::ResponseResult {
+    let ident = item.ident();
+    let mut t = DiffTemplate {
+        ident: ident.clone(),
+        type_name: item.type_name(),
+        descr: item.description(),
+        from: from.chars().take(7).collect(),
+        to: to.chars().take(7).collect(),
+        rendered_diff: "".to_owned(),
+        message: "".to_owned(),
+    };
+    let mut parts = vec![];
+    if item.is_private() {
+        t.message = "The versions of a private item cannot be compared.".to_owned();
+    } else {
+        let old = if from.is_empty() {
+            None
+        } else {
+            world.item_at_commit(&ident, from)?
+        };
+        let new = world.item_at_commit(&ident, to)?;
+        let (old, new) = comparable(old, new)?;
+        parts = diff(&old, &new)?;
+        if parts.iter().all(|(kind, _)| *kind == DiffKind::Same) {
+            t.message = "There are no differences.".to_owned();
+        }
+        t.rendered_diff = to_html(&parts);
+    }
+    trace(&format!("{} parts in diff of {}", parts.len(), &ident));
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    resp.set_test_data("diff", &t.rendered_diff);
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("diff {}", Colour::Fixed(2).on(Colour::Fixed(233)).paint(m));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn words() -> FLResult<()> {
+        let parts = diff("the quick brown fox", "the slow brown fox jumps")?;
+        assert_eq!(
+            "the <ins>slow</ins><del>quick</del> brown fox<ins> jumps</ins>",
+            to_html(&parts)
+        );
+        assert_eq!(
+            vec![(DiffKind::Inserted, "a <b>".to_owned())],
+            diff("", "a <b>")?
+        );
+        assert_eq!("a &lt;b&gt;", to_html(&diff("a <b>", "a <b>")?));
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! drafts of unsaved edits, so that what has been typed into a new or
+edit form is not lost if the app is killed.
+
+The user interface saves the values in the form every so often. The
+drafts are kept in a local file next to the search database, and are
+never committed to the repository; if the file has been corrupted,
+the drafts in it are ignored. A draft is discarded when the item
+is created or updated. Nothing is kept for private items or for an
+encrypted repository, as the file is not encrypted. */
+use crate::item::{Ident, ItemBaseForSerde};
+use crate::shared::{FLResult, FanlingError, NullResult};
+use ansi_term::Colour;
+use askama::Template;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** the name of the file that the drafts are kept in */
+pub const DRAFTS_FILE: &str = "drafts.json";
+
+/** the values of a form that has not been saved */
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Draft {
+    pub type_name: String,
+    pub vals: HashMap<String, String>,
+    /** when the draft was saved */
+    pub saved: NaiveDateTime,
+}
+
+/** the drafts, keyed by [key] */
+#[derive(Debug)]
+pub struct Drafts {
+    path: PathBuf,
+    /** false if drafts should not be kept (for an encrypted repository) */
+    enabled: bool,
+    drafts: HashMap<String, Draft>,
+}
+impl Drafts {
+    /** the drafts kept in the directory `dir` */
+    pub fn open(dir: &Path, enabled: bool) -> Self {
+        let path = dir.join(DRAFTS_FILE);
+        let drafts = if enabled {
+            read(&path).unwrap_or_else(|e| {
+                trace(&format!("ignoring drafts file {:?}: {:?}", &path, e));
+                HashMap::new()
+            })
+        } else {
+            HashMap::new()
+        };
+        trace(&format!("{} drafts from {:?}", drafts.len(), &path));
+        Self {
+            path,
+            enabled,
+            drafts,
+        }
+    }
+    /** the draft for the key, if any */
+    pub fn get(&self, key: &str) -> Option<&Draft> {
+        self.drafts.get(key)
+    }
+    /** keep the values of a form; returns false if they were not kept */
+    pub fn save(
+        &mut self,
+        base: &ItemBaseForSerde,
+        vals: &HashMap<String, String>,
+    ) -> FLResult<bool> {
+        if !self.enabled || base.is_private {
+            return Ok(false);
+        }
+        let draft = Draft {
+            type_name: base.type_name.clone(),
+            vals: vals.clone(),
+            saved: chrono::Local::now().naive_local(),
+        };
+        self.drafts.insert(key(&base.ident, &base.type_name), draft);
+        self.write()?;
+        Ok(true)
+    }
+    /** forget the draft for the key, if there is one */
+    pub fn discard(&mut self, key: &str) -> NullResult {
+        if self.drafts.remove(key).is_some() {
+            self.write()?;
+        }
+        Ok(())
+    }
+    fn write(&self) -> NullResult {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        crate::integrity::write(&self.path, serde_json::to_string(&self.drafts)?.as_bytes())?;
+        Ok(())
+    }
+}
+/** read the drafts file, if it is there and has not been corrupted */
+fn read(path: &Path) -> FLResult<HashMap<String, Draft>> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    if crate::integrity::check(path)? == crate::integrity::Check::Mismatch {
+        return Err(fanling_error!("drafts file does not match its hash"));
+    }
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+/** the key for the draft of an item (or of a new item of the type, if the ident is blank) */
+pub fn key(ident: &Ident, type_name: &str) -> String {
+    if ident.is_empty() {
+        format!("new {}", type_name)
+    } else {
+        ident.clone()
+    }
+}
+
+/** template data for the banner shown when a draft is restored */
+#[derive(Template)]
+#[template(path = "draft.html", print = "none")]
+struct DraftTemplate<'a> {
+    ident: &'a str,
+    type_name: &'a str,
+    saved: String,
+}
+/** add a banner saying that a draft has been restored to a response for editing */
+pub fn add_banner(
+    resp: &mut fanling_interface::Response,
+    ident: &Ident,
+    draft: &Draft,
+    world: &crate::world::World,
+) -> NullResult {
+    let t = DraftTemplate {
+        ident,
+        type_name: &draft.type_name,
+        saved: world.format().format_date_time(draft.saved),
+    };
+    resp.add_tag("message", &(t.render()?));
+    #[cfg(test)]
+    resp.set_test_data("draft", "restored");
+    Ok(())
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "draft {}",
+        Colour::Fixed(6).on(Colour::Fixed(230)).paint(m)
+    );
+}
@@ -0,0 +1,725 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! implements [`Event`] items (something in a calendar), the agenda
+showing them (and reminders of birthdays and anniversaries) by week or
+month, and exporting them as iCalendar */
+use crate::ical::{self, ICalEvent};
+use crate::item::{Item, ItemBase, ItemBaseForSerde, ItemData, NewBaseTemplate, ShowBaseTemplate};
+use crate::markdown;
+use crate::shared::{merge_strings, FLResult, FanlingError, NullResult};
+use crate::world::{ActionResponse, World};
+use ansi_term::Colour;
+use askama::Template;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::boxed::Box;
+use std::collections::HashMap;
+use std::fs;
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** how often an event happens again */
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Recurrence {
+    None,
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+impl Recurrence {
+    const ALL: [Recurrence; 5] = [
+        Recurrence::None,
+        Recurrence::Daily,
+        Recurrence::Weekly,
+        Recurrence::Monthly,
+        Recurrence::Yearly,
+    ];
+    /** the recurrence from its name (none if not known) */
+    fn parse(s: &str) -> Self {
+        match s.trim().to_lowercase().as_str() {
+            "daily" => Recurrence::Daily,
+            "weekly" => Recurrence::Weekly,
+            "monthly" => Recurrence::Monthly,
+            "yearly" => Recurrence::Yearly,
+            _ => Recurrence::None,
+        }
+    }
+    /** the name, as used in forms */
+    fn name(self) -> &'static str {
+        match self {
+            Recurrence::None => "none",
+            Recurrence::Daily => "daily",
+            Recurrence::Weekly => "weekly",
+            Recurrence::Monthly => "monthly",
+            Recurrence::Yearly => "yearly",
+        }
+    }
+}
+impl Default for Recurrence {
+    fn default() -> Self {
+        Recurrence::None
+    }
+}
+
+/** the same time a number of months later, on the same day of the
+month or the last day of the month if that is earlier */
+fn add_months(dt: NaiveDateTime, months: i32) -> NaiveDateTime {
+    let month0 = dt.year() * 12 + dt.month0() as i32 + months;
+    let (year, month) = (month0.div_euclid(12), month0.rem_euclid(12) as u32 + 1);
+    let mut day = dt.day();
+    loop {
+        if let Some(d) = NaiveDate::from_ymd_opt(year, month, day) {
+            return d.and_time(dt.time());
+        }
+        day -= 1;
+    }
+}
+/** the number of whole months from one date to another */
+fn months_between(from: NaiveDate, to: NaiveDate) -> i32 {
+    (to.year() - from.year()) * 12 + to.month0() as i32 - from.month0() as i32
+}
+
+/** data for an event item */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    /** what is happening */
+    name: String,
+    /** when it starts */
+    start: NaiveDateTime,
+    /** when it ends */
+    end: NaiveDateTime,
+    /** where it happens */
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::string::String::is_empty")]
+    location: String,
+    /** description in MarkDown format */
+    #[serde(default)]
+    description: String,
+    /** how often it happens again */
+    #[serde(default)]
+    recurrence: Recurrence,
+}
+impl Event {
+    /** create a new [Event]  */
+    pub fn new() -> Self {
+        Self {
+            name: "".to_owned(),
+            start: NaiveDateTime::from_timestamp(0, 0),
+            end: NaiveDateTime::from_timestamp(0, 0),
+            location: "".to_owned(),
+            description: "".to_owned(),
+            recurrence: Recurrence::None,
+        }
+    }
+    fn set_from_yaml_basic(&mut self, yaml: &serde_yaml::Value) -> NullResult {
+        *self = serde_yaml::from_value(yaml.clone())?;
+        Ok(())
+    }
+    /** the start of the `n`th occurrence (the first is 0) */
+    fn nth_start(&self, n: i32) -> NaiveDateTime {
+        match self.recurrence {
+            Recurrence::None => self.start,
+            Recurrence::Daily => self.start + Duration::days(n.into()),
+            Recurrence::Weekly => self.start + Duration::weeks(n.into()),
+            Recurrence::Monthly => add_months(self.start, n),
+            Recurrence::Yearly => add_months(self.start, n * 12),
+        }
+    }
+    /** the starts of the occurrences on or after `from` and before `to` */
+    pub fn occurrences(&self, from: NaiveDate, to: NaiveDate) -> Vec<NaiveDateTime> {
+        let first = self.start.date();
+        if self.recurrence == Recurrence::None {
+            return if first >= from && first < to {
+                vec![self.start]
+            } else {
+                vec![]
+            };
+        }
+        /* skip the occurrences that are certainly before `from` */
+        let mut n = match self.recurrence {
+            Recurrence::Daily => (from - first).num_days() as i32,
+            Recurrence::Weekly => (from - first).num_weeks() as i32,
+            Recurrence::Monthly => months_between(first, from) - 1,
+            _ => months_between(first, from) / 12 - 1,
+        }
+        .max(0);
+        let mut found = vec![];
+        loop {
+            let start = self.nth_start(n);
+            if start.date() >= to {
+                break;
+            }
+            if start.date() >= from {
+                found.push(start);
+            }
+            n += 1;
+        }
+        found
+    }
+    /** the event from the values of an item */
+    pub(crate) fn from_values(values: &serde_yaml::Value) -> FLResult<Self> {
+        let mut event = Self::new();
+        event.set_from_yaml_basic(values)?;
+        Ok(event)
+    }
+    /** in the form used for exporting */
+    pub(crate) fn to_ical(&self, ident: &str) -> ICalEvent {
+        ICalEvent {
+            uid: ident.to_owned(),
+            start: self.start,
+            end: self.end,
+            summary: self.name.clone(),
+            location: self.location.clone(),
+            description: self.description.clone(),
+            frequency: match self.recurrence {
+                Recurrence::None => None,
+                Recurrence::Daily => Some("DAILY"),
+                Recurrence::Weekly => Some("WEEKLY"),
+                Recurrence::Monthly => Some("MONTHLY"),
+                Recurrence::Yearly => Some("YEARLY"),
+            },
+        }
+    }
+    /** values as if entered by the user, suitable for creating an item */
+    pub(crate) fn vals_from_ical(event: &ICalEvent) -> HashMap<String, String> {
+        let mut vals = HashMap::new();
+        let iso = |dt: NaiveDateTime| {
+            dt.format(&format!("{} %H:%M:%S", crate::format::ISO_DATE_FORMAT))
+                .to_string()
+        };
+        vals.insert("name".to_owned(), event.summary.clone());
+        vals.insert("start".to_owned(), iso(event.start));
+        vals.insert("end".to_owned(), iso(event.end));
+        vals.insert("location".to_owned(), event.location.clone());
+        vals.insert("description".to_owned(), event.description.clone());
+        vals.insert(
+            "recurrence".to_owned(),
+            event.frequency.unwrap_or("none").to_lowercase(),
+        );
+        vals
+    }
+}
+impl crate::item::ItemData for Event {
+    fn for_edit(
+        &mut self,
+        base: &mut ItemBase,
+        is_for_update: bool,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        /* a new event has no times yet */
+        let (start, end) = if self.start != NaiveDateTime::from_timestamp(0, 0) {
+            (
+                world.format().format_date_time(self.start),
+                world.format().format_date_time(self.end),
+            )
+        } else {
+            ("".to_owned(), "".to_owned())
+        };
+        let nt = NewEventTemplate {
+            data: &self,
+            base: NewBaseTemplate::from_base(base, is_for_update, world)?,
+            start,
+            end,
+            recurrences: Recurrence::ALL
+                .iter()
+                .map(|r| RecurrenceOption {
+                    name: r.name(),
+                    selected: *r == self.recurrence,
+                })
+                .collect(),
+            broken_description: self.description.replace("\n", "&#10;"),
+        };
+        let mut resp = fanling_interface::Response::new();
+        resp.clear_errors(vec![
+            "name-error".to_owned(),
+            "start-error".to_owned(),
+            "end-error".to_owned(),
+        ]);
+        resp.add_tag("content", &(nt.render()?));
+        #[cfg(test)]
+        {
+            resp.set_test_data("ident", &base.get_ident());
+        }
+        trace(&format!("for edit {:?}", &resp));
+        Ok(resp)
+    }
+    fn for_show(
+        &mut self,
+        base: &mut ItemBase,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let t = ShowEventTemplate {
+            name: self.name.clone(),
+            start: world.format().format_date_time(self.start),
+            end: world.format().format_date_time(self.end),
+            location: self.location.clone(),
+            recurrence: self.recurrence.name(),
+            rendered_description: markdown::render(&self.description, &world.render_context()),
+            base: ShowBaseTemplate::from_base(base, world)?.with_stats(self.stats(base, world)?),
+        };
+        let mut resp = fanling_interface::Response::new();
+        resp.add_tag("content", &(t.render()?));
+        #[cfg(test)]
+        {
+            resp.set_test_data("ident", &base.get_ident());
+            resp.set_test_data("recurrence", self.recurrence.name());
+        }
+        trace(&format!("for show {:?}", &resp));
+        Ok(resp)
+    }
+    fn to_yaml(&self, base: &crate::item::ItemBase) -> Result<Vec<u8>, FanlingError> {
+        let for_serde = EventForSerde {
+            base: crate::item::ItemBaseForSerde::from_base(base)?,
+            data: self.clone(),
+        };
+        let yaml = serde_yaml::to_vec(&for_serde)?;
+        trace(&format!("yaml is {}", String::from_utf8_lossy(&yaml)));
+        Ok(yaml)
+    }
+    fn is_open(&self) -> bool {
+        true
+    }
+    /** an event is not something to be done, so it is never ready */
+    fn is_ready(&mut self, _world: &mut World) -> FLResult<bool> {
+        Ok(false)
+    }
+    /** can be turned into an ident */
+    fn descr_for_ident(&self) -> String {
+        self.name.clone()
+    }
+    /** an English-language description */
+    fn description(&self) -> String {
+        format!("📅 {}", self.name)
+    }
+    /** a description that can be used in a list */
+    fn description_for_list(&self) -> String {
+        format!(
+            "{} {}",
+            self.start.format(crate::format::ISO_DATE_FORMAT),
+            self.name
+        )
+    }
+    fn set_data(&mut self, vals: &HashMap<String, String>, world: &mut World) -> NullResult {
+        match vals.get("name") {
+            Some(s) => self.name = s.to_string(),
+            _ => return Err(fanling_error!("no name")),
+        }
+        self.start = match vals.get("start") {
+            Some(s) => world.format().parse_date_time(s)?,
+            _ => return Err(fanling_error!("no start")),
+        };
+        self.end = match vals.get("end") {
+            Some(e) if !e.trim().is_empty() => world.format().parse_date_time(e)?,
+            _ => self.start,
+        };
+        self.location = vals.get("location").cloned().unwrap_or_default();
+        self.description = vals.get("description").cloned().unwrap_or_default();
+        self.recurrence = vals
+            .get("recurrence")
+            .map_or(Recurrence::None, |r| Recurrence::parse(r));
+        Ok(())
+    }
+    fn set_from_yaml(&mut self, yaml: &serde_yaml::Value, _world: &mut World) -> NullResult {
+        self.set_from_yaml_basic(yaml)
+    }
+    /** do action for event -- should never get called */
+    fn do_action(
+        &mut self,
+        _base: &mut ItemBase,
+        _action: crate::Action,
+        _world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        Err(fanling_error!("event do action called, should never happen").into())
+    }
+    /** copy from another item data */
+    fn fanling_clone(&self) -> FLResult<Box<dyn ItemData>> {
+        Ok(Box::new(self.clone()))
+    }
+    /** transitional to fix old data */
+    fn fix_data(
+        &self,
+        _yaml: &serde_yaml::Value,
+        _base: &mut ItemBase,
+        _world: &mut World,
+    ) -> NullResult {
+        Ok(())
+    }
+}
+impl Default for Event {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+#[derive(Serialize, Deserialize)]
+struct EventForSerde {
+    #[serde(flatten)]
+    base: crate::item::ItemBaseForSerde,
+    #[serde(flatten)]
+    data: Event,
+}
+/** a choice of recurrence, for the template */
+struct RecurrenceOption {
+    name: &'static str,
+    selected: bool,
+}
+/** template data for creating a new event item */
+#[derive(Template)]
+#[template(path = "new-event.html", print = "none")]
+struct NewEventTemplate<'a> {
+    data: &'a Event,
+    base: NewBaseTemplate,
+    start: String,
+    end: String,
+    recurrences: Vec<RecurrenceOption>,
+    broken_description: String,
+}
+
+/** template data for showing an event item */
+#[derive(Template)]
+#[template(path = "show-event.html", print = "none")]
+struct ShowEventTemplate {
+    name: String,
+    start: String,
+    end: String,
+    location: String,
+    recurrence: &'static str,
+    rendered_description: String,
+    base: ShowBaseTemplate,
+}
+
+/** all the events, with their idents */
+fn all_events(world: &mut World) -> FLResult<Vec<(String, Event)>> {
+    let mut events = vec![];
+    for entry in world.search_type("Event")?.entries {
+        let (_base, values) = world.get_item_parts(&entry.link.ident)?;
+        if crate::private::is_sealed(&values) {
+            continue;
+        }
+        events.push((entry.link.ident.clone(), Event::from_values(&values)?));
+    }
+    Ok(events)
+}
+
+/** the occurrences of events starting between two dates (`to` is
+not included), in order, with the ident and name of each event */
+pub fn upcoming(
+    world: &mut World,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> FLResult<Vec<(NaiveDateTime, String, String)>> {
+    let mut occurrences = vec![];
+    for (ident, event) in all_events(world)? {
+        for start in event.occurrences(from, to) {
+            occurrences.push((start, ident.clone(), event.name.clone()));
+        }
+    }
+    occurrences.sort();
+    Ok(occurrences)
+}
+
+/** the occurrences of events taking place on any of the days from
+`from` to before `to`, in order, each with its end and the ident and
+name of the event */
+pub fn spans(
+    world: &mut World,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> FLResult<Vec<(NaiveDateTime, NaiveDateTime, String, String)>> {
+    let mut spans = vec![];
+    for (ident, event) in all_events(world)? {
+        let length = (event.end - event.start).max(Duration::zero());
+        /* an occurrence that started before `from` may still be going on */
+        let earliest = from - Duration::days(length.num_days() + 1);
+        for start in event.occurrences(earliest, to) {
+            if (start + length).date() >= from {
+                spans.push((start, start + length, ident.clone(), event.name.clone()));
+            }
+        }
+    }
+    spans.sort();
+    Ok(spans)
+}
+
+/** an occurrence of an event, for the agenda */
+struct AgendaEntry {
+    ident: String,
+    time: String,
+    name: String,
+}
+/** a day in the agenda */
+struct AgendaDay {
+    day: u32,
+    /** whether the day is in the month shown (always true for a week) */
+    in_range: bool,
+    is_today: bool,
+    entries: Vec<AgendaEntry>,
+}
+/** a week in the agenda */
+struct AgendaWeek {
+    days: Vec<AgendaDay>,
+}
+/** template data for the agenda */
+#[derive(Template)]
+#[template(path = "agenda.html", print = "none")]
+struct AgendaTemplate {
+    title: String,
+    month: bool,
+    date: String,
+    previous: String,
+    next: String,
+    day_names: Vec<String>,
+    weeks: Vec<AgendaWeek>,
+    message: String,
+}
+
+/** show the events in the week or month containing a date (today if
+the date is blank) */
+pub fn agenda(
+    world: &mut World,
+    month: bool,
+    date: &str,
+    message: &str,
+) -> fanling_interface::ResponseResult {
+    let today = chrono::Local::today().naive_local();
+    let date = if date.trim().is_empty() {
+        today
+    } else {
+        world.format().parse_date_time(date)?.date()
+    };
+    let (range_start, range_end, title, previous, next) = if month {
+        let first = NaiveDate::from_ymd(date.year(), date.month(), 1);
+        let first_dt = first.and_hms(0, 0, 0);
+        (
+            first,
+            add_months(first_dt, 1).date(),
+            date.format("%B %Y").to_string(),
+            add_months(first_dt, -1).date(),
+            add_months(first_dt, 1).date(),
+        )
+    } else {
+        let first = date - Duration::days(world.format().day_of_week(date).into());
+        (
+            first,
+            first + Duration::days(7),
+            format!("Week of {}", world.format().format_date(first)),
+            first - Duration::days(7),
+            first + Duration::days(7),
+        )
+    };
+    /* the grid is whole weeks */
+    let grid_start =
+        range_start - Duration::days(world.format().day_of_week(range_start).into());
+    let mut grid_end = range_end;
+    while world.format().day_of_week(grid_end) != 0 {
+        grid_end = grid_end.succ();
+    }
+    let mut occurrences: Vec<(NaiveDateTime, AgendaEntry)> = vec![];
+    for (ident, event) in all_events(world)? {
+        for start in event.occurrences(grid_start, grid_end) {
+            occurrences.push((
+                start,
+                AgendaEntry {
+                    ident: ident.clone(),
+                    time: start.format("%H:%M").to_string(),
+                    name: event.name.clone(),
+                },
+            ));
+        }
+    }
+    for reminder in crate::reminder::reminders(world, grid_start, grid_end)? {
+        occurrences.push((
+            reminder.date.and_hms(0, 0, 0),
+            AgendaEntry {
+                ident: reminder.ident.clone(),
+                time: "".to_owned(),
+                name: reminder.description(),
+            },
+        ));
+    }
+    occurrences.sort_by_key(|(start, _entry)| *start);
+    let count = occurrences
+        .iter()
+        .filter(|(start, _entry)| start.date() >= range_start && start.date() < range_end)
+        .count();
+    let mut weeks = vec![];
+    let mut day = grid_start;
+    while day < grid_end {
+        let mut days = vec![];
+        for _ in 0..7 {
+            let mut entries = vec![];
+            while occurrences.first().map_or(false, |(s, _e)| s.date() == day) {
+                entries.push(occurrences.remove(0).1);
+            }
+            days.push(AgendaDay {
+                day: day.day(),
+                in_range: day >= range_start && day < range_end,
+                is_today: day == today,
+                entries,
+            });
+            day = day.succ();
+        }
+        weeks.push(AgendaWeek { days });
+    }
+    let t = AgendaTemplate {
+        title,
+        month,
+        date: world.format().format_date(date),
+        previous: world.format().format_date(previous),
+        next: world.format().format_date(next),
+        day_names: (0..7)
+            .map(|i| (grid_start + Duration::days(i)).format("%a").to_string())
+            .collect(),
+        weeks,
+        message: message.to_owned(),
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    {
+        resp.set_test_data("count", &format!("{}", count));
+        resp.set_test_data("message", message);
+    }
+    trace(&format!("agenda has {} occurrences", count));
+    Ok(resp)
+}
+
+/** write all the events to an iCalendar file in the export directory */
+pub fn export_ical(world: &mut World) -> fanling_interface::ResponseResult {
+    let events: Vec<ICalEvent> = all_events(world)?
+        .iter()
+        .map(|(ident, event)| event.to_ical(ident))
+        .collect();
+    let dir = world.export_dir().to_path_buf();
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!(
+        "events-{}.ics",
+        chrono::Local::today()
+            .naive_local()
+            .format(crate::format::ISO_DATE_FORMAT)
+    ));
+    fs::write(&path, ical::to_ical_text(&events))?;
+    trace(&format!("exported {} events to {:?}", events.len(), &path));
+    agenda(
+        world,
+        true,
+        "",
+        &format!("Exported to {}", path.display()),
+    )
+}
+
+/** policy for the event item type*/
+#[derive(Debug)]
+pub struct EventTypePolicy {}
+impl EventTypePolicy {
+    pub fn new() -> Self {
+        Self {}
+    }
+    pub fn new_boxed() -> Box<Self> {
+        Box::new(Self::new())
+    }
+}
+impl crate::item::ItemTypePolicy for EventTypePolicy {
+    fn kind(&self) -> crate::item::ItemKind {
+        crate::item::ItemKind::Event
+    }
+    fn make_raw(&self, item_type: crate::item::ItemTypeRef) -> Item {
+        Item::new_with_data(item_type, Box::new(Event::new()))
+    }
+    /** the times and recurrence can not be merged, so ours are kept */
+    fn resolve_conflict_both(
+        &self,
+        _world: &mut World,
+        _ancestor: &Value,
+        ours: &Value,
+        theirs: &Value,
+    ) -> FLResult<Box<dyn ItemData>> {
+        let mut oe = Event::new();
+        oe.set_from_yaml_basic(&ours)?;
+        let mut te = Event::new();
+        te.set_from_yaml_basic(&theirs)?;
+        oe.name = merge_strings(&oe.name, &te.name);
+        oe.location = merge_strings(&oe.location, &te.location);
+        oe.description = merge_strings(&oe.description, &te.description);
+        Ok(Box::new(oe))
+    }
+    fn check_valid(
+        &mut self,
+        _base: &ItemBaseForSerde,
+        vals: &HashMap<String, String>,
+        world: &mut World,
+    ) -> ActionResponse {
+        let mut ar = ActionResponse::new();
+        ar.assert(
+            !vals.get("name").map_or(true, |n| n.is_empty()),
+            "name-error",
+            "Name must be non-blank.",
+        );
+        let start = vals
+            .get("start")
+            .and_then(|s| world.format().parse_date_time(s).ok());
+        ar.assert(start.is_some(), "start-error", "Invalid start date");
+        if let (Some(start), Some(end)) = (start, vals.get("end")) {
+            if !end.trim().is_empty() {
+                let end = world.format().parse_date_time(end).ok();
+                ar.assert(end.is_some(), "end-error", "Invalid end date");
+                ar.assert(
+                    end.map_or(true, |e| e >= start),
+                    "end-error",
+                    "The end must not be before the start.",
+                );
+            }
+        }
+        ar
+    }
+    /** get item data from serde value */
+    fn from_yaml(&self, values: &Value, world: &mut World) -> FLResult<Box<dyn ItemData>> {
+        let mut e = Event::default();
+        e.set_from_yaml(&values, world)?;
+        Ok(Box::new(e))
+    }
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "event {}",
+        Colour::Fixed(11).on(Colour::Fixed(233)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn occurrences() {
+        let mut event = Event::new();
+        event.start = NaiveDate::from_ymd(2020, 1, 31).and_hms(9, 0, 0);
+        event.recurrence = Recurrence::Monthly;
+        let from = NaiveDate::from_ymd(2020, 2, 1);
+        let to = NaiveDate::from_ymd(2020, 5, 1);
+        let days: Vec<NaiveDate> = event
+            .occurrences(from, to)
+            .iter()
+            .map(|s| s.date())
+            .collect();
+        assert_eq!(
+            vec![
+                NaiveDate::from_ymd(2020, 2, 29),
+                NaiveDate::from_ymd(2020, 3, 31),
+                NaiveDate::from_ymd(2020, 4, 30)
+            ],
+            days
+        );
+        event.recurrence = Recurrence::Weekly;
+        assert_eq!(12, event.occurrences(from, to).len());
+        event.recurrence = Recurrence::None;
+        assert!(event.occurrences(from, to).is_empty());
+        assert_eq!(Recurrence::Yearly, Recurrence::parse(" Yearly"));
+    }
+}
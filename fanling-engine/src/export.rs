@@ -0,0 +1,308 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! an item exported for sending or sharing elsewhere: as markdown (to
+be copied), as a standalone HTML page or, on the desktop (with the
+`pdf` feature), as a PDF.
+
+The markdown is the description of the item as a heading, then its
+text fields and any entries (as a task list). The HTML page is that
+rendered, with the style sheet inlined, links to other items made
+plain (labelled with the names of the items) and pictures from
+attachments embedded, so that it can be read anywhere. The file is
+given to the main program with the response (see
+[`fanling_interface::OutputFile`]), which shares or saves it, or puts
+it on the clipboard. Private items are not exported. */
+use crate::item::Item;
+use crate::shared::{FLResult, FanlingError};
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use fanling_interface::{OutputFile, ASSET_BASE};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+//#[macro_use]
+use crate::fanling_error;
+
+/** the fields of an item that are markdown, in the order they are exported */
+const TEXT_FIELDS: &[&str] = &["description", "text", "notes"];
+/** the size of a PDF page (A4), in millimetres */
+#[cfg(feature = "pdf")]
+const PAGE_SIZE: (f64, f64) = (210.0, 297.0);
+/** the margin around the text of a PDF page, in millimetres */
+#[cfg(feature = "pdf")]
+const MARGIN: f64 = 20.0;
+/** the size of the text of a PDF, in points */
+#[cfg(feature = "pdf")]
+const FONT_SIZE: f64 = 11.0;
+/** the distance between the lines of a PDF, in millimetres */
+#[cfg(feature = "pdf")]
+const LINE_HEIGHT: f64 = 5.5;
+/** how many characters fit on a line of a PDF */
+#[cfg(feature = "pdf")]
+const LINE_CHARS: usize = 90;
+
+/** what an item can be exported as */
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub enum Format {
+    /** markdown, to be put on the clipboard */
+    Markdown,
+    /** a standalone HTML page */
+    Html,
+    /** a PDF (only with the `pdf` feature) */
+    Pdf,
+}
+impl Format {
+    /** whether items can be exported in this format by this build */
+    pub fn is_available(self) -> bool {
+        self != Format::Pdf || cfg!(feature = "pdf")
+    }
+    /** the extension of the file name */
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Markdown => "md",
+            Format::Html => "html",
+            Format::Pdf => "pdf",
+        }
+    }
+    /** the MIME type of the file */
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Markdown => "text/markdown",
+            Format::Html => "text/html",
+            Format::Pdf => "application/pdf",
+        }
+    }
+}
+
+/** the markdown for an item with the description and values */
+pub(crate) fn values_markdown(description: &str, values: &serde_yaml::Value) -> String {
+    let body = body_markdown(values);
+    if body.is_empty() {
+        format!("# {}\n", description)
+    } else {
+        format!("# {}\n\n{}\n", description, body)
+    }
+}
+/** the markdown for the values of an item: its URL, text fields and any entries */
+pub(crate) fn body_markdown(values: &serde_yaml::Value) -> String {
+    let mut parts = vec![];
+    if let Some(url) = values.get("url").and_then(|v| v.as_str()) {
+        if !url.trim().is_empty() {
+            parts.push(format!("<{}>", url.trim()));
+        }
+    }
+    for field in TEXT_FIELDS {
+        if let Some(text) = values.get(*field).and_then(|v| v.as_str()) {
+            if !text.trim().is_empty() {
+                parts.push(text.trim().to_owned());
+            }
+        }
+    }
+    if let Some(serde_yaml::Value::Sequence(entries)) = values.get("entries") {
+        let list: Vec<String> = entries
+            .iter()
+            .filter_map(|entry| {
+                let checked = entry.get("checked").and_then(|c| c.as_bool());
+                Some(format!(
+                    "- [{}] {}",
+                    if checked == Some(true) { "x" } else { " " },
+                    entry.get("text")?.as_str()?
+                ))
+            })
+            .collect();
+        if !list.is_empty() {
+            parts.push(list.join("\n"));
+        }
+    }
+    parts.join("\n\n")
+}
+/** the item as markdown */
+fn markdown(world: &World, item: &Item) -> FLResult<String> {
+    let (_base, values) = world.get_item_parts(&item.ident())?;
+    if item.is_private() || crate::private::is_sealed(&values) {
+        return Err(fanling_error!("A private item cannot be exported."));
+    }
+    Ok(values_markdown(&item.description(), &values))
+}
+
+/** template data for a standalone HTML page */
+#[derive(Template)]
+#[template(path = "export.html", print = "none")]
+struct ExportTemplate {
+    title: String,
+    body: String,
+}
+/** the name of the item linked to by ident, if it has one */
+fn linked_name(world: &World, ident: &str) -> Option<String> {
+    if !world.has_item(ident).unwrap_or(false) {
+        return None;
+    }
+    let (_base, values) = world.get_item_parts(&ident.to_owned()).ok()?;
+    values
+        .get("name")
+        .and_then(|n| n.as_str())
+        .map(|n| n.to_owned())
+}
+/** rendered HTML that does not depend on the app: links to items are
+plain text and assets are embedded as `data:` URLs (or dropped, if
+they cannot be found) */
+pub(crate) fn standalone(world: &World, html: &str) -> FLResult<String> {
+    let item_link =
+        Regex::new(r#"<span class="itemlink" id="([^"]*)" onclick='[^']*'>(.*?)</span>"#)?;
+    let html = item_link.replace_all(html, |caps: &Captures| {
+        let label = match linked_name(world, &caps[1]) {
+            Some(name) if caps[2] == caps[1] => crate::markdown::escape(&name),
+            _ => caps[2].to_owned(),
+        };
+        format!(
+            "<span class=\"itemlink\" title=\"{}\">{}</span>",
+            &caps[1], label
+        )
+    });
+    let citation = Regex::new(r#"<span class="itemlink citation" onclick='[^']*'>(.*?)</span>"#)?;
+    let html = citation.replace_all(&html, "<cite>$1</cite>");
+    let asset = Regex::new(&format!(
+        r#"(src|href)="({}[^"]*)""#,
+        regex::escape(ASSET_BASE)
+    ))?;
+    Ok(asset
+        .replace_all(&html, |caps: &Captures| {
+            match crate::assets::asset(&caps[2], Some(world)) {
+                Ok(Some(asset)) => format!(
+                    "{}=\"data:{};base64,{}\"",
+                    &caps[1],
+                    asset.content_type,
+                    base64::encode(&asset.bytes)
+                ),
+                _ => {
+                    trace(&format!("cannot embed {}", &caps[2]));
+                    format!("{}=\"\"", &caps[1])
+                }
+            }
+        })
+        .into_owned())
+}
+/** the item as a standalone HTML page */
+fn html(world: &World, item: &Item) -> FLResult<String> {
+    let body = crate::markdown::render(&markdown(world, item)?, &world.render_context());
+    let t = ExportTemplate {
+        title: item.description(),
+        body: standalone(world, &body)?,
+    };
+    Ok(t.render()?)
+}
+
+/** the text broken into lines of no more than `width` characters (where there are spaces) */
+#[cfg(feature = "pdf")]
+fn wrapped(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && line.chars().count() + word.chars().count() >= width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+/** the error for a PDF that cannot be made */
+#[cfg(feature = "pdf")]
+fn pdf_error<E: std::fmt::Debug>(e: E) -> FanlingError {
+    fanling_error!(&format!("cannot make the PDF: {:?}", e))
+}
+/** the item as a PDF of its markdown, on as many pages as it needs */
+#[cfg(feature = "pdf")]
+fn pdf(world: &World, item: &Item) -> FLResult<Vec<u8>> {
+    use printpdf::{BuiltinFont, Mm, PdfDocument};
+    let (width, height) = PAGE_SIZE;
+    let title = item.description();
+    let (doc, page, layer) = PdfDocument::new(title.as_str(), Mm(width), Mm(height), "text");
+    let font = doc
+        .add_builtin_font(BuiltinFont::Helvetica)
+        .map_err(pdf_error)?;
+    let mut layer = doc.get_page(page).get_layer(layer);
+    let mut y = height - MARGIN;
+    for line in wrapped(&markdown(world, item)?, LINE_CHARS) {
+        if y < MARGIN {
+            let (page, next) = doc.add_page(Mm(width), Mm(height), "text");
+            layer = doc.get_page(page).get_layer(next);
+            y = height - MARGIN;
+        }
+        layer.use_text(line, FONT_SIZE, Mm(MARGIN), Mm(y), &font);
+        y -= LINE_HEIGHT;
+    }
+    doc.save_to_bytes().map_err(pdf_error)
+}
+#[cfg(not(feature = "pdf"))]
+fn pdf(_world: &World, _item: &Item) -> FLResult<Vec<u8>> {
+    Err(fanling_error!("PDF export is not available in this build."))
+}
+
+/** export the item, giving the file with the response */
+pub fn export(item: &Item, format: Format, world: &World) -> fanling_interface::ResponseResult {
+    let mut resp = fanling_interface::Response::new();
+    if !format.is_available() {
+        resp.show_toast("PDF export is not available in this build.");
+        return Ok(resp);
+    }
+    let bytes = match format {
+        Format::Markdown => markdown(world, item)?.into_bytes(),
+        Format::Html => html(world, item)?.into_bytes(),
+        Format::Pdf => pdf(world, item)?,
+    };
+    let name = format!("{}.{}", item.ident().replace('/', "-"), format.extension());
+    trace(&format!("exported {} ({} bytes)", name, bytes.len()));
+    resp.show_toast(&if format == Format::Markdown {
+        format!("Copied '{}' as markdown", item.description())
+    } else {
+        format!("Exported '{}' as {}", item.description(), name)
+    });
+    #[cfg(test)]
+    {
+        resp.set_test_data("name", &name);
+        resp.set_test_data("exported", &String::from_utf8_lossy(&bytes));
+    }
+    resp.add_file(OutputFile {
+        name,
+        content_type: format.content_type().to_owned(),
+        bytes,
+        for_clipboard: format == Format::Markdown,
+    });
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "export {}",
+        Colour::Fixed(15).on(Colour::Fixed(96)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn formats() {
+        assert!(Format::Markdown.is_available());
+        assert!(Format::Html.is_available());
+        assert_eq!(cfg!(feature = "pdf"), Format::Pdf.is_available());
+        assert_eq!("application/pdf", Format::Pdf.content_type());
+    }
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn wrapping() {
+        assert_eq!(
+            vec!["one two", "three", "", "four"],
+            wrapped("one two three\n\nfour", 9)
+        );
+    }
+}
@@ -0,0 +1,179 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! fetches web pages, getting their title and their readable text
+(the headings, paragraphs and lists of the article or body, without
+scripts or navigation) as markdown, and what the page says about its
+source (the site, the author and when it was published). */
+use crate::shared::{FLResult, FanlingError};
+use ansi_term::Colour;
+use regex::Regex;
+//#[macro_use]
+use crate::fanling_error;
+
+/** how long to wait for a page, in milliseconds */
+const TIMEOUT_MS: u64 = 10_000;
+/** the elements that are never part of the readable text */
+const SKIPPED: &[&str] = &[
+    "script", "style", "noscript", "nav", "header", "footer", "aside", "form",
+];
+
+/** the parts of a web page that are kept */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Page {
+    /** the title of the page */
+    pub title: String,
+    /** the readable text of the page, as markdown */
+    pub text: String,
+    /** the name of the site (blank if not given) */
+    pub site: String,
+    /** the author (blank if not given) */
+    pub author: String,
+    /** when it was published, as given (blank if not given) */
+    pub published: String,
+}
+impl Page {
+    /** the title and readable text of some HTML */
+    pub fn from_html(html: &str) -> FLResult<Self> {
+        let title = Regex::new(r"(?is)<title[^>]*>(.*?)</title>")?
+            .captures(html)
+            .map(|c| collapse(&decode(&c[1])))
+            .unwrap_or_default();
+        Ok(Self {
+            title,
+            text: readable_text(html)?,
+            site: meta(html, &["og:site_name", "application-name"])?,
+            author: meta(html, &["author", "article:author"])?,
+            published: meta(html, &["article:published_time", "date"])?,
+        })
+    }
+}
+
+/** fetch a web page */
+pub fn fetch_page(url: &str) -> FLResult<Page> {
+    trace(&format!("fetching {}", url));
+    let resp = ureq::get(url)
+        .timeout_connect(TIMEOUT_MS)
+        .timeout_read(TIMEOUT_MS)
+        .call();
+    if !resp.ok() {
+        return Err(fanling_error!(&format!(
+            "could not fetch {}: {}",
+            url,
+            resp.status_line()
+        )));
+    }
+    Page::from_html(&resp.into_string()?)
+}
+
+/** the content of the first of the meta tags (by name or property) that the page has, or blank */
+fn meta(html: &str, names: &[&str]) -> FLResult<String> {
+    for name in names {
+        let name = regex::escape(name);
+        let name_first = Regex::new(&format!(
+            r#"(?is)<meta\b[^>]*\b(?:name|property)\s*=\s*["']{}["'][^>]*\bcontent\s*=\s*["']([^"']*)["']"#,
+            name
+        ))?;
+        let content_first = Regex::new(&format!(
+            r#"(?is)<meta\b[^>]*\bcontent\s*=\s*["']([^"']*)["'][^>]*\b(?:name|property)\s*=\s*["']{}["']"#,
+            name
+        ))?;
+        if let Some(c) = name_first
+            .captures(html)
+            .or_else(|| content_first.captures(html))
+        {
+            return Ok(collapse(&decode(&c[1])));
+        }
+    }
+    Ok("".to_owned())
+}
+/** the contents of the first element with the tag, if there is one */
+fn inner<'a>(html: &'a str, tag: &str) -> FLResult<Option<&'a str>> {
+    Ok(Regex::new(&format!(r"(?is)<{0}\b[^>]*>(.*)</{0}>", tag))?
+        .captures(html)
+        .and_then(|c| c.get(1))
+        .map(|m| m.as_str()))
+}
+/** the headings, paragraphs and list items of the article (or body) as markdown */
+fn readable_text(html: &str) -> FLResult<String> {
+    let mut body = match inner(html, "article")? {
+        Some(article) => article,
+        None => inner(html, "body")?.unwrap_or(html),
+    }
+    .to_owned();
+    for tag in SKIPPED {
+        body = Regex::new(&format!(r"(?is)<{0}\b.*?</{0}>", tag))?
+            .replace_all(&body, "")
+            .into_owned();
+    }
+    let link = Regex::new(r#"(?is)<a\b[^>]*href\s*=\s*["']([^"']*)["'][^>]*>(.*?)</a>"#)?;
+    let body = link.replace_all(&body, "[$2]($1)");
+    let tags = Regex::new(r"(?s)<[^>]*>")?;
+    let block = Regex::new(
+        r"(?is)<(h[1-6]|p|li|pre|blockquote)\b[^>]*>(.*?)</(?:h[1-6]|p|li|pre|blockquote)>",
+    )?;
+    let paragraphs: Vec<String> = block
+        .captures_iter(&body)
+        .filter_map(|c| {
+            let text = collapse(&decode(&tags.replace_all(&c[2], "")));
+            if text.is_empty() {
+                return None;
+            }
+            let kind = c[1].to_lowercase();
+            Some(match kind.as_str() {
+                "li" => format!("* {}", text),
+                "blockquote" => format!("> {}", text),
+                "pre" | "p" => text,
+                heading => format!("{} {}", "#".repeat(heading[1..].parse().unwrap_or(1)), text),
+            })
+        })
+        .collect();
+    Ok(paragraphs.join("\n\n"))
+}
+/** the text with the common HTML entities replaced by their characters */
+fn decode(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+/** the text with each run of white space replaced by a single space */
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("fetch {}", Colour::Fixed(4).on(Colour::Fixed(253)).paint(m));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn readable() -> FLResult<()> {
+        let page = Page::from_html(
+            r#"<html><head><title> Milk &amp; eggs
+            </title><meta property="og:site_name" content="Shop &amp; Co">
+            <meta content="A. Grocer" name="author"><script>var x = "<p>no</p>";</script></head>
+            <body><nav><p>Home</p></nav><article><h2>Buying</h2>
+            <p>Get them <a href="https://example.com/shop">here</a>.</p>
+            <ul><li>milk</li><li></li><li>eggs</li></ul></article>
+            <footer><p>(c) 2020</p></footer></body></html>"#,
+        )?;
+        assert_eq!("Milk & eggs", page.title);
+        assert_eq!("Shop & Co", page.site);
+        assert_eq!("A. Grocer", page.author);
+        assert_eq!("", page.published);
+        assert_eq!(
+            "## Buying\n\nGet them [here](https://example.com/shop).\n\n* milk\n\n* eggs",
+            page.text
+        );
+        assert_eq!(Page::default(), Page::from_html("")?);
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! focus mode: a single next action for each project.
+
+A project is an open task with ready tasks as children. Its next
+action is the child chosen for it (see [`crate::Action::NextAction`]),
+or if none has been chosen (or the chosen one is no longer ready), the
+ready child with the best priority. */
+use crate::item::{Ident, ItemListEntry};
+use crate::shared::FLResult;
+use crate::world::World;
+use askama::Template;
+use std::ops::Deref;
+
+/** a project and its next action, for the template */
+struct FocusEntry {
+    project: ItemListEntry,
+    action: ItemListEntry,
+}
+/** template data for the focus view */
+#[derive(Template)]
+#[template(path = "focus.html", print = "none")]
+struct FocusTemplate {
+    entries: Vec<FocusEntry>,
+}
+
+/** the ready tasks that are children of the project, best first */
+fn candidates(world: &mut World, project: &str) -> FLResult<Vec<ItemListEntry>> {
+    let mut ready = world
+        .search_open_children(project)?
+        .filter_on_item(|i, w| Ok(i.type_name() == "Task" && i.is_ready(w)?), world)?
+        .entries;
+    let mut keyed = vec![];
+    for entry in ready.drain(..) {
+        let (base, values) = world.get_item_parts(&entry.link.ident)?;
+        let priority = values
+            .get("priority")
+            .and_then(|p| p.as_i64())
+            .unwrap_or(0);
+        keyed.push(((priority, base.sort.clone(), entry.descr.clone()), entry));
+    }
+    keyed.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(keyed.into_iter().map(|(_k, e)| e).collect())
+}
+
+/** the next action for the project, given the one chosen for it (if any) */
+pub fn next_action(
+    world: &mut World,
+    project: &str,
+    chosen: &str,
+) -> FLResult<Option<ItemListEntry>> {
+    let candidates = candidates(world, project)?;
+    Ok(candidates
+        .iter()
+        .find(|c| c.link.ident == chosen)
+        .or_else(|| candidates.first())
+        .cloned())
+}
+
+/** the action to choose when the current next action for the project is skipped */
+pub fn skip(world: &mut World, project: &str, chosen: &str) -> FLResult<Ident> {
+    let candidates = candidates(world, project)?;
+    let current = next_action(world, project, chosen)?.map(|c| c.link.ident);
+    let pos = candidates
+        .iter()
+        .position(|c| Some(&c.link.ident) == current.as_ref())
+        .unwrap_or(0);
+    Ok(candidates
+        .get((pos + 1) % candidates.len().max(1))
+        .map_or("".to_owned(), |c| c.link.ident.clone()))
+}
+
+/** show the focus view, with one next action for each project */
+pub fn show_focus(world: &mut World) -> fanling_interface::ResponseResult {
+    let mut entries = vec![];
+    for project in world.search_type("Task")?.entries {
+        let ident = project.link.ident.clone();
+        let is_open = {
+            let item_ref = world.get_item(ident.clone(), "Task".to_owned())?;
+            let item = item_ref.deref().borrow();
+            item.is_open()
+        };
+        if !is_open {
+            continue;
+        }
+        let (_base, values) = world.get_item_parts(&ident)?;
+        let chosen = values
+            .get("next_action")
+            .and_then(|n| n.as_str())
+            .unwrap_or("")
+            .to_owned();
+        if let Some(action) = next_action(world, &ident, &chosen)? {
+            entries.push(FocusEntry { project, action });
+        }
+    }
+    entries.sort_by(|a, b| a.project.descr.cmp(&b.project.descr));
+    trace(&format!("{} projects in focus", entries.len()));
+    #[cfg(test)]
+    let focus: Vec<String> = entries
+        .iter()
+        .map(|e| format!("{}:{}", e.project.link.ident, e.action.link.ident))
+        .collect();
+    let t = FocusTemplate { entries };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    resp.set_test_data("focus", &focus.join(","));
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "focus {}",
+        ansi_term::Colour::Fixed(12).on(ansi_term::Colour::Fixed(233)).paint(m)
+    );
+}
@@ -0,0 +1,227 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! implements [`Foreign`] items, which are items of a type that this
+version does not know (as when another device with a newer version has
+pushed them, or when they are of a kind added by a plugin that is not
+loaded, see [`crate::plugin`]).
+
+Rather than failing to load, such an item is shown as it is stored
+(its YAML) and cannot be changed. It is kept exactly as it was stored,
+including its type, so that the newer version can still use it. If it
+has been changed both locally and remotely, the remote version is kept
+unless only the local one has changed, as the two can not be combined. */
+use crate::item::{AvailableAction, Item, ItemBase, ItemBaseForSerde, ItemData, ShowBaseTemplate};
+use crate::shared::{FLResult, FanlingError, NullResult};
+use crate::world::{ActionResponse, World};
+use ansi_term::Colour;
+use askama::Template;
+use serde_yaml::Value;
+use std::collections::HashMap;
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** an item of a type that is not known, kept as it was stored */
+#[derive(Debug, Clone, Default)]
+pub struct Foreign {
+    /** the stored values, including the base fields */
+    stored: Value,
+}
+impl Foreign {
+    /** a string value, or blank */
+    fn value(&self, key: &str) -> String {
+        self.stored
+            .get(key)
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_owned()
+    }
+    /** the type that the item has in the repository */
+    fn stored_type(&self) -> String {
+        self.value("type")
+    }
+    /** the response for an item that cannot be changed */
+    fn read_only(&self) -> fanling_interface::ResponseResult {
+        let mut resp = fanling_interface::Response::new();
+        resp.show_toast(&format!(
+            "Items of type '{}' cannot be changed by this version.",
+            self.stored_type()
+        ));
+        Ok(resp)
+    }
+}
+impl ItemData for Foreign {
+    fn for_edit(
+        &mut self,
+        _base: &mut ItemBase,
+        _is_for_update: bool,
+        _world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        self.read_only()
+    }
+    fn for_show(
+        &mut self,
+        base: &mut ItemBase,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let mut show_base = ShowBaseTemplate::from_base(base, world)?;
+        show_base.actions = vec![AvailableAction::new(
+            base,
+            crate::Action::History,
+            "History",
+            "🕓",
+        )];
+        show_base.stats = self.stats(base, world)?;
+        let t = ShowForeignTemplate {
+            name: self.description(),
+            type_name: self.stored_type(),
+            yaml: serde_yaml::to_string(&self.stored)?,
+            base: show_base,
+        };
+        let mut resp = fanling_interface::Response::new();
+        resp.add_tag("content", &(t.render()?));
+        #[cfg(test)]
+        {
+            resp.set_test_data("ident", &base.get_ident());
+            resp.set_test_data("type", &self.stored_type());
+        }
+        trace(&format!("for show {:?}", &resp));
+        Ok(resp)
+    }
+    /** the item exactly as it was stored */
+    fn to_yaml(&self, _base: &ItemBase) -> Result<Vec<u8>, FanlingError> {
+        Ok(serde_yaml::to_vec(&self.stored)?)
+    }
+    fn is_open(&self) -> bool {
+        true
+    }
+    fn is_ready(&mut self, _world: &mut World) -> FLResult<bool> {
+        Ok(false)
+    }
+    fn is_read_only(&self) -> bool {
+        true
+    }
+    fn descr_for_ident(&self) -> String {
+        self.description()
+    }
+    /** the name of the item (if it has one) */
+    fn description(&self) -> String {
+        match self.value("name") {
+            name if name.is_empty() => self.value("ident"),
+            name => name,
+        }
+    }
+    fn description_for_list(&self) -> String {
+        format!("{} ({})", self.description(), self.stored_type())
+    }
+    fn set_data(&mut self, _vals: &HashMap<String, String>, _world: &mut World) -> NullResult {
+        Err(fanling_error!("item of an unknown type cannot be changed"))
+    }
+    fn set_from_yaml(&mut self, yaml: &Value, _world: &mut World) -> NullResult {
+        self.stored = yaml.clone();
+        Ok(())
+    }
+    fn do_action(
+        &mut self,
+        _base: &mut ItemBase,
+        _action: crate::Action,
+        _world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        self.read_only()
+    }
+    fn fanling_clone(&self) -> FLResult<Box<dyn ItemData>> {
+        Ok(Box::new(self.clone()))
+    }
+    fn fix_data(&self, _yaml: &Value, _base: &mut ItemBase, _world: &mut World) -> NullResult {
+        Ok(())
+    }
+}
+
+/** template data for showing an item of an unknown type */
+#[derive(Template)]
+#[template(path = "show-foreign.html", print = "none")]
+struct ShowForeignTemplate {
+    name: String,
+    type_name: String,
+    yaml: String,
+    base: ShowBaseTemplate,
+}
+
+/** policy for items of unknown types */
+#[derive(Debug)]
+pub struct ForeignTypePolicy {}
+impl ForeignTypePolicy {
+    pub fn new() -> Self {
+        Self {}
+    }
+    pub fn new_boxed() -> Box<Self> {
+        Box::new(Self::new())
+    }
+}
+impl crate::item::ItemTypePolicy for ForeignTypePolicy {
+    fn kind(&self) -> crate::item::ItemKind {
+        crate::item::ItemKind::Foreign
+    }
+    fn make_raw(&self, item_type: crate::item::ItemTypeRef) -> Item {
+        Item::new_with_data(item_type, Box::new(Foreign::default()))
+    }
+    /** keep the remote version, unless only the local one has changed */
+    fn resolve_conflict_both(
+        &self,
+        _world: &mut World,
+        ancestor: &Value,
+        ours: &Value,
+        theirs: &Value,
+    ) -> FLResult<Box<dyn ItemData>> {
+        Ok(Box::new(Foreign {
+            stored: if theirs == ancestor { ours } else { theirs }.clone(),
+        }))
+    }
+    fn check_valid(
+        &mut self,
+        _base: &ItemBaseForSerde,
+        _vals: &HashMap<String, String>,
+        _world: &mut World,
+    ) -> ActionResponse {
+        let mut ar = ActionResponse::new();
+        ar.assert(
+            false,
+            "name-error",
+            "Items of this type cannot be changed by this version.",
+        );
+        ar
+    }
+    /** get item data from serde value */
+    fn from_yaml(&self, values: &Value, world: &mut World) -> FLResult<Box<dyn ItemData>> {
+        let mut f = Foreign::default();
+        f.set_from_yaml(&values, world)?;
+        Ok(Box::new(f))
+    }
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "foreign {}",
+        Colour::Fixed(5).on(Colour::Fixed(255)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn stored_untouched() -> FLResult<()> {
+        let yaml = "ident: f1\ntype: Recipe\nname: Soup\nservings: 4\n";
+        let foreign = Foreign {
+            stored: serde_yaml::from_str(yaml)?,
+        };
+        assert_eq!("Recipe", foreign.stored_type());
+        assert_eq!("Soup (Recipe)", foreign.description_for_list());
+        let again: Value = serde_yaml::from_slice(&serde_yaml::to_vec(&foreign.stored)?)?;
+        assert_eq!(foreign.stored, again);
+        Ok(())
+    }
+}
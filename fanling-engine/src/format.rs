@@ -0,0 +1,171 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! formats (and parses) dates, times and numbers according to the user's preferences */
+use crate::shared::{FLResult, NullResult};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Weekday};
+use std::fmt::Write;
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** the ISO 8601 date format, which is always accepted when parsing */
+pub const ISO_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/** how dates, times and numbers are shown to (and entered by) the user */
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatOptions {
+    /** weeks start on Monday (otherwise Sunday) */
+    pub week_starts_monday: bool,
+    /** format for dates, using `strftime` codes (eg `%d/%m/%Y`) */
+    pub date_format: String,
+    /** use a 24 hour clock (otherwise 12 hour with am/pm) */
+    pub use_24_hour: bool,
+    /** character separating the integer and fractional parts of a number */
+    pub decimal_separator: char,
+    /** the locale for the text shown to the user (eg "fr"; see [`crate::i18n`]) */
+    pub locale: String,
+}
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            week_starts_monday: true,
+            date_format: ISO_DATE_FORMAT.to_owned(),
+            use_24_hour: true,
+            decimal_separator: '.',
+            locale: crate::i18n::DEFAULT_LOCALE.to_owned(),
+        }
+    }
+}
+impl FormatOptions {
+    /** the format for times of day */
+    fn time_format(&self) -> &'static str {
+        if self.use_24_hour {
+            "%H:%M:%S"
+        } else {
+            "%I:%M:%S %p"
+        }
+    }
+    /** the first day of the week */
+    pub fn week_start(&self) -> Weekday {
+        if self.week_starts_monday {
+            Weekday::Mon
+        } else {
+            Weekday::Sun
+        }
+    }
+    /** the position of a day within the week (0 for the first day of the week) */
+    pub fn day_of_week(&self, day: NaiveDate) -> u32 {
+        if self.week_starts_monday {
+            day.weekday().num_days_from_monday()
+        } else {
+            day.weekday().num_days_from_sunday()
+        }
+    }
+    /** whether the date format can be used (a `strftime` format
+    that is not valid fails when a date is formatted with it) */
+    pub fn check(&self) -> NullResult {
+        let mut s = String::new();
+        write!(
+            s,
+            "{}",
+            NaiveDate::from_ymd(2020, 3, 1).format(&self.date_format)
+        )
+        .map_err(|_e| fanling_error!(&format!("invalid date format '{}'", self.date_format)))
+    }
+    /** the date format, or ISO 8601 if it is not valid */
+    fn usable_date_format(&self) -> &str {
+        if self.check().is_ok() {
+            &self.date_format
+        } else {
+            ISO_DATE_FORMAT
+        }
+    }
+    /** format a date */
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        date.format(self.usable_date_format()).to_string()
+    }
+    /** format a date and time */
+    pub fn format_date_time(&self, date_time: NaiveDateTime) -> String {
+        date_time
+            .format(&format!(
+                "{} {}",
+                self.usable_date_format(),
+                self.time_format()
+            ))
+            .to_string()
+    }
+    /** parse a date and time as entered by the user.
+
+    Accepts the configured date format or ISO 8601, optionally
+    followed by a time in either the 12 or 24 hour clock (midnight if
+    there is no time). */
+    pub fn parse_date_time(&self, text: &str) -> FLResult<NaiveDateTime> {
+        let text = text.trim();
+        for date_format in &[self.date_format.as_str(), ISO_DATE_FORMAT] {
+            for time_format in &["%H:%M:%S", "%H:%M", "%I:%M:%S %p", "%I:%M %p"] {
+                let format = format!("{} {}", date_format, time_format);
+                if let Ok(dt) = NaiveDateTime::parse_from_str(text, &format) {
+                    return Ok(dt);
+                }
+            }
+            if let Ok(d) = NaiveDate::parse_from_str(text, date_format) {
+                return Ok(d.and_hms(0, 0, 0));
+            }
+        }
+        Err(fanling_error!(&format!("invalid date '{}'", text)))
+    }
+    /** format a percentage (a whole number) */
+    pub fn format_percent(&self, percent: u32) -> String {
+        format!("{}%", self.format_number(f64::from(percent), 0))
+    }
+    /** format a number with a fixed number of decimal places */
+    pub fn format_number(&self, n: f64, decimals: usize) -> String {
+        let s = format!("{:.*}", decimals, n);
+        if self.decimal_separator == '.' {
+            s
+        } else {
+            s.replace('.', &self.decimal_separator.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn formats() -> crate::shared::NullResult {
+        let fo = FormatOptions {
+            week_starts_monday: false,
+            date_format: "%d/%m/%Y".to_owned(),
+            use_24_hour: false,
+            decimal_separator: ',',
+            ..FormatOptions::default()
+        };
+        let dt = NaiveDate::from_ymd(2020, 3, 1).and_hms(14, 30, 0);
+        assert_eq!("01/03/2020 02:30:00 PM", fo.format_date_time(dt));
+        assert_eq!(dt, fo.parse_date_time("01/03/2020 02:30:00 PM")?);
+        assert_eq!(dt, fo.parse_date_time("2020-03-01 14:30")?);
+        assert_eq!(
+            NaiveDate::from_ymd(2020, 3, 1).and_hms(0, 0, 0),
+            fo.parse_date_time("01/03/2020")?
+        );
+        assert!(fo.parse_date_time("1st March").is_err());
+        /* 1 March 2020 was a Sunday */
+        assert_eq!(0, fo.day_of_week(dt.date()));
+        assert_eq!(6, FormatOptions::default().day_of_week(dt.date()));
+        assert_eq!("2,50", fo.format_number(2.5, 2));
+        assert_eq!("25%", fo.format_percent(25));
+        /* a date format that is not valid is checked for, and not used */
+        let bad = FormatOptions {
+            date_format: "%Q %".to_owned(),
+            ..FormatOptions::default()
+        };
+        assert!(bad.check().is_err());
+        assert!(fo.check().is_ok());
+        assert_eq!("2020-03-01", bad.format_date(dt.date()));
+        assert_eq!("2020-03-01 14:30:00", bad.format_date_time(dt));
+        Ok(())
+    }
+}
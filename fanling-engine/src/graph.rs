@@ -0,0 +1,283 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! the graph of the links between items, which can be exported (as
+GraphViz DOT and as JSON) to see the structure of a large wiki, and
+the orphan items, which have no links at all.
+
+The nodes are the items, and the edges are the wiki links (`[[ident]]`)
+in their text and the links from children to their parents. Links to
+missing items are left out, as are the links in private items (whose
+text is encrypted). */
+use crate::item::{Ident, ItemListEntry, ItemListEntryList};
+use crate::shared::FLResult;
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+
+/** an item in the graph */
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Node {
+    /** the ident of the item */
+    pub ident: Ident,
+    /** the type of the item */
+    pub kind: String,
+    /** the description of the item */
+    pub name: String,
+}
+/** a link from one item to another */
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Edge {
+    /** the item with the link */
+    pub from: Ident,
+    /** the item linked to */
+    pub to: Ident,
+    /** `link` for a wiki link or `parent` for a link to the parent */
+    pub kind: String,
+}
+/** the items and the links between them */
+#[derive(Debug, Default, Serialize)]
+pub struct Graph {
+    /** the items */
+    pub nodes: Vec<Node>,
+    /** the links */
+    pub edges: Vec<Edge>,
+}
+impl Graph {
+    /** the graph as GraphViz DOT */
+    pub fn to_dot(&self) -> String {
+        let mut dot = "digraph fanling {\n".to_owned();
+        for node in &self.nodes {
+            dot.push_str(&format!(
+                "  {} [label={}, kind={}];\n",
+                quote(&node.ident),
+                quote(&node.name),
+                quote(&node.kind)
+            ));
+        }
+        for edge in &self.edges {
+            dot.push_str(&format!(
+                "  {} -> {}{};\n",
+                quote(&edge.from),
+                quote(&edge.to),
+                if edge.kind == "parent" {
+                    " [style=dashed]"
+                } else {
+                    ""
+                }
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+    /** the graph as JSON */
+    pub fn to_json(&self) -> FLResult<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+    /** the idents of the items that have no links to or from them */
+    pub fn orphans(&self) -> Vec<Ident> {
+        let linked: BTreeSet<&Ident> = self
+            .edges
+            .iter()
+            .flat_map(|e| vec![&e.from, &e.to])
+            .collect();
+        self.nodes
+            .iter()
+            .filter(|n| !linked.contains(&n.ident))
+            .map(|n| n.ident.clone())
+            .collect()
+    }
+}
+
+/** the wiki links in some text */
+pub fn wiki_links(text: &str) -> FLResult<Vec<Ident>> {
+    let re = Regex::new(r"\[\[\s*((?:[\w.-]+:)?[\w./-]+)\s*(?:\|[^\[\]]*)?\]\]")?;
+    Ok(re.captures_iter(text).map(|c| c[1].to_owned()).collect())
+}
+
+/** the graph of all the items in the current repository, with the
+items in other repositories that they link to */
+pub fn graph(world: &mut World) -> FLResult<Graph> {
+    let entries = world.search_all()?.entries;
+    let idents: BTreeSet<Ident> = entries.iter().map(|e| e.link.ident.clone()).collect();
+    let current = world.repo_name();
+    let mut graph = Graph::default();
+    let mut edges = BTreeSet::new();
+    /* the items in other repositories that are linked to, with their types */
+    let mut others = BTreeMap::new();
+    for entry in entries {
+        let ident = entry.link.ident.clone();
+        let item_ref = world.get_item(ident.clone(), "Simple".to_owned())?;
+        let mut item = item_ref.borrow_mut();
+        graph.nodes.push(Node {
+            ident: ident.clone(),
+            kind: item.type_name(),
+            name: entry.descr.clone(),
+        });
+        let mut links = vec![];
+        if !item.is_private() {
+            for link in wiki_links(&String::from_utf8_lossy(&item.to_yaml()?))? {
+                links.push(match crate::repos::local_link(&link, &current) {
+                    /* a link to the old ident of a renamed item is to the item */
+                    Some(to) => world
+                        .aliases()
+                        .get(to)
+                        .cloned()
+                        .unwrap_or_else(|| to.to_owned()),
+                    None => {
+                        if !others.contains_key(&link) {
+                            if let Some(kind) = world.other_repo_item_type(&link)? {
+                                others.insert(link.clone(), kind);
+                            }
+                        }
+                        link
+                    }
+                });
+            }
+        }
+        let mut add = |to: Ident, kind: &str| {
+            if to != ident && (idents.contains(&to) || others.contains_key(&to)) {
+                edges.insert(Edge {
+                    from: ident.clone(),
+                    to,
+                    kind: kind.to_owned(),
+                });
+            }
+        };
+        if let Some(parent) = item.parent_ident() {
+            add(parent, "parent");
+        }
+        for to in links {
+            add(to, "link");
+        }
+    }
+    for (ident, kind) in others {
+        graph.nodes.push(Node {
+            name: ident.clone(),
+            ident,
+            kind,
+        });
+    }
+    graph.edges = edges.into_iter().collect();
+    trace(&format!(
+        "{} nodes, {} edges",
+        graph.nodes.len(),
+        graph.edges.len()
+    ));
+    Ok(graph)
+}
+
+/** template data for the orphans page */
+#[derive(Template)]
+#[template(path = "orphans.html", print = "none")]
+struct OrphansTemplate {
+    /** the items with no links */
+    entries: ItemListEntryList,
+    /** the number of items */
+    item_count: usize,
+    /** the number of links */
+    link_count: usize,
+    /** a message, such as where the graph was exported to */
+    message: String,
+}
+
+/** show the items that have no links to or from them */
+pub fn show_orphans(world: &mut World, message: &str) -> fanling_interface::ResponseResult {
+    let graph = graph(world)?;
+    let orphans = graph.orphans();
+    let entries = graph
+        .nodes
+        .iter()
+        .filter(|n| orphans.contains(&n.ident))
+        .map(|n| {
+            let mut entry = ItemListEntry::default();
+            entry.link.ident = n.ident.clone();
+            entry.descr = n.name.clone();
+            entry
+        })
+        .collect();
+    let t = OrphansTemplate {
+        entries: ItemListEntryList::from_vec(entries),
+        item_count: graph.nodes.len(),
+        link_count: graph.edges.len(),
+        message: message.to_owned(),
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    {
+        resp.set_test_data("orphans", &orphans.join(","));
+        resp.set_test_data("links", &format!("{}", graph.edges.len()));
+    }
+    Ok(resp)
+}
+
+/** write the graph to DOT and JSON files in the export directory, then show the orphans page */
+pub fn export_graph(world: &mut World) -> fanling_interface::ResponseResult {
+    let graph = graph(world)?;
+    let dir = world.export_dir().to_path_buf();
+    fs::create_dir_all(&dir)?;
+    let stem = format!(
+        "graph-{}",
+        chrono::Local::today()
+            .naive_local()
+            .format(crate::format::ISO_DATE_FORMAT)
+    );
+    let dot_path = dir.join(format!("{}.dot", stem));
+    fs::write(&dot_path, graph.to_dot())?;
+    fs::write(dir.join(format!("{}.json", stem)), graph.to_json()?)?;
+    trace(&format!("exported graph to {:?}", &dot_path));
+    show_orphans(
+        world,
+        &format!("Exported to {} (and .json)", dot_path.display()),
+    )
+}
+
+/** a string quoted for DOT */
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "graph {}",
+        Colour::Fixed(13).on(Colour::Fixed(236)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn dot_and_orphans() -> FLResult<()> {
+        assert_eq!(
+            vec!["a.b", "c", "work:d"],
+            wiki_links("see [[a.b]] and [[ c |the c]], not [x](y), and [[work:d]]")?
+        );
+        let node = |ident: &str| Node {
+            ident: ident.to_owned(),
+            kind: "Simple".to_owned(),
+            name: format!("say \"{}\"", ident),
+        };
+        let graph = Graph {
+            nodes: vec![node("a"), node("b"), node("c")],
+            edges: vec![Edge {
+                from: "a".to_owned(),
+                to: "b".to_owned(),
+                kind: "parent".to_owned(),
+            }],
+        };
+        assert_eq!(vec!["c"], graph.orphans());
+        let dot = graph.to_dot();
+        assert!(dot.contains("\"a\" [label=\"say \\\"a\\\"\", kind=\"Simple\"];"));
+        assert!(dot.contains("\"a\" -> \"b\" [style=dashed];"));
+        assert!(graph.to_json()?.contains("\"kind\": \"parent\""));
+        Ok(())
+    }
+}
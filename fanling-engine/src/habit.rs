@@ -0,0 +1,472 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! implements [`Habit`] items, things to be done regularly (such as
+exercise), with the days they were done.
+
+A habit has a target cadence: every day, or a number of days a week.
+Its streak is the number of days (or, for a weekly target, weeks) in a
+row that the target was met, not counting today (or this week) until it
+has been met. Its completion rate is the proportion of the target that
+was met in the last four weeks (or since it was first done, if that is
+more recent).
+
+As the days done only ever accumulate, changes made on two devices are
+merged by keeping the days from both. */
+use crate::format::FormatOptions;
+use crate::item::{Item, ItemBase, ItemBaseForSerde, ItemData, NewBaseTemplate, ShowBaseTemplate};
+use crate::shared::{merge_strings, FLResult, FanlingError, NullResult};
+use crate::world::{ActionResponse, World};
+use ansi_term::Colour;
+use askama::Template;
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::boxed::Box;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Debug;
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** the number of weeks shown in the calendar of a habit */
+const CALENDAR_WEEKS: i64 = 13;
+/** the number of weeks over which the completion rate is found */
+const RATE_WEEKS: i64 = 4;
+
+/** the default cadence */
+fn every_day() -> u32 {
+    7
+}
+
+/** data for a habit item */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Habit {
+    /** what is to be done */
+    name: String,
+    /** the target cadence: how many days a week (7 for every day) */
+    #[serde(default = "every_day")]
+    per_week: u32,
+    /** the (local) days when it was done */
+    #[serde(default)]
+    done: BTreeSet<NaiveDate>,
+}
+impl Habit {
+    /** create a new [Habit]  */
+    pub fn new() -> Self {
+        Self {
+            name: "".to_owned(),
+            per_week: every_day(),
+            done: BTreeSet::new(),
+        }
+    }
+    fn set_from_yaml_basic(&mut self, yaml: &serde_yaml::Value) -> NullResult {
+        *self = serde_yaml::from_value(yaml.clone())?;
+        Ok(())
+    }
+    /** whether the target is to do it every day */
+    fn is_daily(&self) -> bool {
+        self.per_week >= 7
+    }
+    /** the target cadence in words */
+    fn cadence(&self) -> String {
+        match self.per_week {
+            n if n >= 7 => "every day".to_owned(),
+            1 => "once a week".to_owned(),
+            n => format!("{} days a week", n),
+        }
+    }
+    /** the first day of the week containing the day */
+    fn week_of(day: NaiveDate, format: &FormatOptions) -> NaiveDate {
+        day - Duration::days(format.day_of_week(day).into())
+    }
+    /** how many days it was done in the week starting on the day */
+    fn done_in_week(&self, week: NaiveDate) -> u32 {
+        self.done.range(week..week + Duration::days(7)).count() as u32
+    }
+    /** mark it as done on the day (no change if it already was) */
+    fn mark_done(&mut self, day: NaiveDate) {
+        self.done.insert(day);
+    }
+    /** the number of days (or weeks, for a weekly target) in a row up
+    to the day that the target was met. The day (or its week) only
+    counts once the target has been met, so the streak is not broken
+    until it is over. */
+    fn streak(&self, today: NaiveDate, format: &FormatOptions) -> u32 {
+        let mut streak = 0;
+        if self.is_daily() {
+            let mut day = if self.done.contains(&today) {
+                today
+            } else {
+                today.pred()
+            };
+            while self.done.contains(&day) {
+                streak += 1;
+                day = day.pred();
+            }
+        } else {
+            let mut week = Self::week_of(today, format);
+            if self.done_in_week(week) < self.per_week {
+                week = week - Duration::weeks(1);
+            }
+            let first = self.done.iter().next().cloned().unwrap_or(today);
+            while week + Duration::days(6) >= first && self.done_in_week(week) >= self.per_week {
+                streak += 1;
+                week = week - Duration::weeks(1);
+            }
+        }
+        streak
+    }
+    /** the percentage of the target met in the last few weeks up to
+    the day (or since it was first done, if that is later) */
+    fn completion_rate(&self, today: NaiveDate) -> u32 {
+        let first = match self.done.iter().next() {
+            Some(first) => (*first).max(today - Duration::weeks(RATE_WEEKS) + Duration::days(1)),
+            None => return 0,
+        };
+        let days = (today - first).num_days() + 1;
+        if days <= 0 {
+            return 0;
+        }
+        let expected = ((days * i64::from(self.per_week.min(7))) as f64 / 7.0).ceil();
+        let done = self.done.range(first..=today).count() as f64;
+        ((done * 100.0 / expected.max(1.0)).round() as u32).min(100)
+    }
+    /** the streak in words */
+    fn streak_descr(&self, streak: u32) -> String {
+        let unit = if self.is_daily() { "day" } else { "week" };
+        format!("{} {}{}", streak, unit, if streak == 1 { "" } else { "s" })
+    }
+    /** the days done, as a heatmap like the one of activity (see [`crate::stats`]) */
+    fn calendar(&self, today: NaiveDate, format: &FormatOptions) -> String {
+        let activity: crate::stats::Activity = self
+            .done
+            .iter()
+            .map(|day| {
+                (
+                    *day,
+                    crate::stats::DayActivity {
+                        completed: 1,
+                        ..crate::stats::DayActivity::default()
+                    },
+                )
+            })
+            .collect();
+        crate::stats::heatmap_svg(&activity, today, CALENDAR_WEEKS, format)
+    }
+}
+impl crate::item::ItemData for Habit {
+    fn for_edit(
+        &mut self,
+        base: &mut ItemBase,
+        is_for_update: bool,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let nt = NewHabitTemplate {
+            data: &self,
+            base: NewBaseTemplate::from_base(base, is_for_update, world)?,
+            cadences: (1..=7)
+                .rev()
+                .map(|per_week| CadenceChoice {
+                    per_week,
+                    selected: per_week == self.per_week,
+                    label: Habit {
+                        per_week,
+                        ..Habit::new()
+                    }
+                    .cadence(),
+                })
+                .collect(),
+        };
+        let mut resp = fanling_interface::Response::new();
+        resp.clear_errors(vec!["name-error".to_owned(), "cadence-error".to_owned()]);
+        resp.add_tag("content", &(nt.render()?));
+        #[cfg(test)]
+        {
+            resp.set_test_data("ident", &base.get_ident());
+        }
+        trace(&format!("for edit {:?}", &resp));
+        Ok(resp)
+    }
+    /** it can be marked as done today, unless it already has been */
+    fn available_actions(&self, base: &ItemBase) -> Vec<crate::item::AvailableAction> {
+        if self.done.contains(&chrono::Local::today().naive_local()) {
+            vec![]
+        } else {
+            vec![crate::item::AvailableAction::new(
+                base,
+                crate::Action::DoneToday,
+                "Done today",
+                "✔",
+            )]
+        }
+    }
+    fn for_show(
+        &mut self,
+        base: &mut ItemBase,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let today = chrono::Local::today().naive_local();
+        let streak = self.streak(today, world.format());
+        let rate = self.completion_rate(today);
+        let t = ShowHabitTemplate {
+            name: self.name.clone(),
+            cadence: self.cadence(),
+            done_today: self.done.contains(&today),
+            streak: self.streak_descr(streak),
+            rate: world.format().format_percent(rate),
+            this_week: self.done_in_week(Habit::week_of(today, world.format())),
+            per_week: self.per_week,
+            calendar: self.calendar(today, world.format()),
+            base: ShowBaseTemplate::from_base(base, world)?
+                .with_actions(base, self.available_actions(base))
+                .with_stats(self.stats(base, world)?),
+        };
+        let mut resp = fanling_interface::Response::new();
+        resp.add_tag("content", &(t.render()?));
+        #[cfg(test)]
+        {
+            resp.set_test_data("ident", &base.get_ident());
+            resp.set_test_data("streak", &format!("{}", streak));
+            resp.set_test_data("rate", &format!("{}", rate));
+            resp.set_test_data("done", &format!("{}", self.done.len()));
+        }
+        trace(&format!("for show {:?}", &resp));
+        Ok(resp)
+    }
+    fn to_yaml(&self, base: &crate::item::ItemBase) -> Result<Vec<u8>, FanlingError> {
+        let for_serde = HabitForSerde {
+            base: crate::item::ItemBaseForSerde::from_base(base)?,
+            data: self.clone(),
+        };
+        let yaml = serde_yaml::to_vec(&for_serde)?;
+        trace(&format!("yaml is {}", String::from_utf8_lossy(&yaml)));
+        Ok(yaml)
+    }
+    fn is_open(&self) -> bool {
+        true
+    }
+    /** ready if it has not been done today and the target for this week has not been met */
+    fn is_ready(&mut self, world: &mut World) -> FLResult<bool> {
+        let today = chrono::Local::today().naive_local();
+        Ok(!self.done.contains(&today)
+            && (self.is_daily()
+                || self.done_in_week(Habit::week_of(today, world.format())) < self.per_week))
+    }
+    /** can be turned into an ident */
+    fn descr_for_ident(&self) -> String {
+        self.name.clone()
+    }
+    /** an English-language description */
+    fn description(&self) -> String {
+        self.name.clone()
+    }
+    /** a description that can be used in a list */
+    fn description_for_list(&self) -> String {
+        format!("🔁 {} ({})", self.name, self.cadence())
+    }
+    fn set_data(&mut self, vals: &HashMap<String, String>, _world: &mut World) -> NullResult {
+        match vals.get("name") {
+            Some(s) => self.name = s.trim().to_owned(),
+            _ => return Err(fanling_error!("no name")),
+        }
+        if let Some(per_week) = vals.get("per_week") {
+            self.per_week = per_week.trim().parse::<u32>()?.max(1).min(7);
+        }
+        Ok(())
+    }
+    fn set_from_yaml(&mut self, yaml: &serde_yaml::Value, _world: &mut World) -> NullResult {
+        self.set_from_yaml_basic(yaml)
+    }
+    /** do action for habit */
+    fn do_action(
+        &mut self,
+        base: &mut ItemBase,
+        action: crate::Action,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        match &action {
+            crate::Action::DoneToday => self.mark_done(chrono::Local::today().naive_local()),
+            _ => return Err(fanling_error!(&format!("invalid action {:?}", action)).into()),
+        }
+        self.for_show(base, world)
+    }
+    /** copy from another item data. But it has not been done. */
+    fn fanling_clone(&self) -> FLResult<Box<dyn ItemData>> {
+        Ok(Box::new(Self {
+            name: self.name.clone(),
+            per_week: self.per_week,
+            done: BTreeSet::new(),
+        }))
+    }
+    /** transitional to fix old data */
+    fn fix_data(
+        &self,
+        _yaml: &serde_yaml::Value,
+        _base: &mut ItemBase,
+        _world: &mut World,
+    ) -> NullResult {
+        Ok(())
+    }
+}
+impl Default for Habit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+#[derive(Serialize, Deserialize)]
+struct HabitForSerde {
+    #[serde(flatten)]
+    base: crate::item::ItemBaseForSerde,
+    #[serde(flatten)]
+    data: Habit,
+}
+/** template data for creating a new habit */
+#[derive(Template)]
+#[template(path = "new-habit.html", print = "none")]
+struct NewHabitTemplate<'a> {
+    data: &'a Habit,
+    base: NewBaseTemplate,
+    cadences: Vec<CadenceChoice>,
+}
+/** a choice of how many days a week */
+struct CadenceChoice {
+    per_week: u32,
+    selected: bool,
+    label: String,
+}
+
+/** template data for showing a habit */
+#[derive(Template)]
+#[template(path = "show-habit.html", print = "none")]
+struct ShowHabitTemplate {
+    name: String,
+    cadence: String,
+    done_today: bool,
+    streak: String,
+    /** the completion rate, formatted as a percentage */
+    rate: String,
+    /** days done this week */
+    this_week: u32,
+    per_week: u32,
+    /** the days done, as SVG */
+    calendar: String,
+    base: ShowBaseTemplate,
+}
+
+/** policy for the habit item type*/
+#[derive(Debug)]
+pub struct HabitTypePolicy {}
+impl HabitTypePolicy {
+    pub fn new() -> Self {
+        Self {}
+    }
+    pub fn new_boxed() -> Box<Self> {
+        Box::new(Self::new())
+    }
+}
+impl crate::item::ItemTypePolicy for HabitTypePolicy {
+    fn kind(&self) -> crate::item::ItemKind {
+        crate::item::ItemKind::Habit
+    }
+    fn make_raw(&self, item_type: crate::item::ItemTypeRef) -> Item {
+        Item::new_with_data(item_type, Box::new(Habit::new()))
+    }
+    /** the days done in both versions are kept */
+    fn resolve_conflict_both(
+        &self,
+        _world: &mut World,
+        ancestor: &Value,
+        ours: &Value,
+        theirs: &Value,
+    ) -> FLResult<Box<dyn ItemData>> {
+        let mut ah = Habit::new();
+        ah.set_from_yaml_basic(&ancestor)?;
+        let mut oh = Habit::new();
+        oh.set_from_yaml_basic(&ours)?;
+        let mut th = Habit::new();
+        th.set_from_yaml_basic(&theirs)?;
+        oh.name = merge_strings(&oh.name, &th.name);
+        if oh.per_week == ah.per_week {
+            oh.per_week = th.per_week;
+        }
+        oh.done = oh.done.union(&th.done).cloned().collect();
+        Ok(Box::new(oh))
+    }
+    fn check_valid(
+        &mut self,
+        _base: &ItemBaseForSerde,
+        vals: &HashMap<String, String>,
+        _world: &mut World,
+    ) -> ActionResponse {
+        let mut ar = ActionResponse::new();
+        ar.assert(
+            !vals.get("name").map_or(true, |n| n.trim().is_empty()),
+            "name-error",
+            "Name must be non-blank.",
+        );
+        ar.assert(
+            vals.get("per_week").map_or(true, |n| {
+                n.trim()
+                    .parse::<u32>()
+                    .map_or(false, |n| (1..=7).contains(&n))
+            }),
+            "cadence-error",
+            "The number of days a week must be a number from 1 to 7.",
+        );
+        ar
+    }
+    /** get item data from serde value */
+    fn from_yaml(&self, values: &Value, world: &mut World) -> FLResult<Box<dyn ItemData>> {
+        let mut h = Habit::default();
+        h.set_from_yaml(&values, world)?;
+        Ok(Box::new(h))
+    }
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("habit {}", Colour::Fixed(2).on(Colour::Fixed(230)).paint(m));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    /** a day in January 2020 (the 1st was a Wednesday) */
+    fn jan(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(2020, 1, day)
+    }
+    fn habit(per_week: u32, days: &[u32]) -> Habit {
+        Habit {
+            name: "exercise".to_owned(),
+            per_week,
+            done: days.iter().map(|d| jan(*d)).collect(),
+        }
+    }
+    #[test]
+    fn daily_streaks() {
+        let format = FormatOptions::default();
+        let h = habit(7, &[3, 5, 6, 7]);
+        assert_eq!(3, h.streak(jan(7), &format));
+        /* not broken until today is over */
+        assert_eq!(3, h.streak(jan(8), &format));
+        assert_eq!(0, h.streak(jan(9), &format));
+        /* 4 of the 7 days since it was first done */
+        assert_eq!(57, h.completion_rate(jan(9)));
+        assert_eq!(0, habit(7, &[]).completion_rate(jan(9)));
+    }
+    #[test]
+    fn weekly_streaks() {
+        let format = FormatOptions::default();
+        /* the weeks start on Mondays: 30 December, and 6, 13, 20 and 27 January */
+        let h = habit(2, &[1, 3, 6, 8, 13, 15, 20, 22]);
+        assert_eq!(2, h.done_in_week(Habit::week_of(jan(8), &format)));
+        assert_eq!(3, h.streak(jan(19), &format));
+        assert_eq!(4, h.streak(jan(23), &format));
+        /* not broken until the week is over */
+        assert_eq!(4, h.streak(jan(28), &format));
+        assert_eq!(0, h.streak(NaiveDate::from_ymd(2020, 2, 4), &format));
+        assert_eq!(100, h.completion_rate(jan(15)));
+    }
+}
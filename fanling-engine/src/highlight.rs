@@ -0,0 +1,200 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! highlights in the snapshots of saved articles (see
+[`crate::article`]), each with an optional note for the margin.
+
+The highlights are kept with the bookmark, apart from its snapshot:
+each has the text highlighted and where it starts in the snapshot. If
+the snapshot is taken again, a highlight is found wherever its text now
+is, and is shown only in the list of highlights if its text is no
+longer there. All the highlights are listed in the "my highlights"
+view. */
+use crate::item::{Ident, ItemLink};
+use crate::markdown;
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/** some highlighted text in a snapshot */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Highlight {
+    /** the text highlighted (markdown, as in the snapshot) */
+    pub quote: String,
+    /** where the text starts in the snapshot (in bytes) */
+    pub start: usize,
+    /** a note about the text, for the margin (may be blank) */
+    #[serde(default)]
+    pub note: String,
+    /** when the highlight was made */
+    pub when: NaiveDateTime,
+}
+impl Highlight {
+    /** the part of the snapshot highlighted, if it is still there */
+    fn locate(&self, snapshot: &str) -> Option<usize> {
+        match snapshot.get(self.start..) {
+            Some(rest) if rest.starts_with(&self.quote) => Some(self.start),
+            _ => snapshot.find(&self.quote),
+        }
+    }
+}
+
+/** where some text to be highlighted starts in the snapshot (the first
+place that is not already highlighted), if it is there */
+pub fn find(snapshot: &str, quote: &str, highlights: &[Highlight]) -> Option<usize> {
+    let taken: Vec<(usize, usize)> = highlights
+        .iter()
+        .filter_map(|h| h.locate(snapshot).map(|s| (s, s + h.quote.len())))
+        .collect();
+    snapshot
+        .match_indices(quote)
+        .map(|(start, _)| start)
+        .find(|start| {
+            let end = start + quote.len();
+            taken.iter().all(|(s, e)| end <= *s || *start >= *e)
+        })
+}
+
+/** any HTML in a snapshot is shown as text, as it is from another site */
+fn plain(text: &str) -> String {
+    text.replace('<', "&lt;")
+}
+/** render a snapshot as HTML, with the highlights marked and their notes in the margin */
+pub fn render(
+    snapshot: &str,
+    highlights: &[Highlight],
+    context: &markdown::RenderContext,
+) -> String {
+    let mut placed: Vec<(usize, &Highlight)> = highlights
+        .iter()
+        .filter_map(|h| h.locate(snapshot).map(|s| (s, h)))
+        .collect();
+    placed.sort_by_key(|(start, _)| *start);
+    let mut marked = String::new();
+    let mut pos = 0;
+    for (start, highlight) in placed {
+        if start < pos {
+            continue; /* overlaps the one before */
+        }
+        marked.push_str(&plain(&snapshot[pos..start]));
+        marked.push_str(&format!(
+            "<mark class=\"highlight\">{}</mark>",
+            plain(&highlight.quote)
+        ));
+        if !highlight.note.is_empty() {
+            marked.push_str(&format!(
+                "<span class=\"margin-note\">{}</span>",
+                markdown::escape(&highlight.note)
+            ));
+        }
+        pos = start + highlight.quote.len();
+    }
+    marked.push_str(&plain(&snapshot[pos..]));
+    markdown::render(&marked, context)
+}
+
+/** a highlight as shown in the "my highlights" view */
+struct HighlightForShow {
+    /** the bookmark */
+    ident: Ident,
+    /** the description of the bookmark */
+    descr: String,
+    quote: String,
+    note: String,
+    when: String,
+}
+/** template data for the "my highlights" view */
+#[derive(Template)]
+#[template(path = "highlights.html", print = "none")]
+struct HighlightsTemplate {
+    /** the highlights, latest first */
+    highlights: Vec<HighlightForShow>,
+}
+
+/** show all the highlights in the bookmarks, latest first */
+pub fn show_highlights(world: &mut World) -> fanling_interface::ResponseResult {
+    let bookmarks = world.search_type("Bookmark")?;
+    let mut all = vec![];
+    for entry in &bookmarks.entries {
+        let item_ref = ItemLink::from(entry.link.clone()).resolve_link(world)?;
+        let item = item_ref.borrow();
+        for highlight in item.highlights() {
+            all.push((highlight, item.ident(), item.description()));
+        }
+    }
+    all.sort_by(|(a, _, _), (b, _, _)| b.when.cmp(&a.when));
+    trace(&format!(
+        "{} highlights in {} bookmarks",
+        all.len(),
+        bookmarks.entries.len()
+    ));
+    let highlights = all
+        .into_iter()
+        .map(|(highlight, ident, descr)| HighlightForShow {
+            ident,
+            descr,
+            quote: highlight.quote,
+            note: highlight.note,
+            when: world.format().format_date_time(highlight.when),
+        })
+        .collect::<Vec<HighlightForShow>>();
+    #[cfg(test)]
+    let count = highlights.len();
+    let t = HighlightsTemplate { highlights };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    resp.set_test_data("count", &format!("{}", count));
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "highlight {}",
+        Colour::Fixed(11).on(Colour::Fixed(230)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn highlight(quote: &str, start: usize, note: &str) -> Highlight {
+        Highlight {
+            quote: quote.to_owned(),
+            start,
+            note: note.to_owned(),
+            when: chrono::Local::now().naive_local(),
+        }
+    }
+    #[test]
+    fn finding() {
+        let snapshot = "a cat and a cat";
+        assert_eq!(Some(2), find(snapshot, "cat", &[]));
+        assert_eq!(Some(12), find(snapshot, "cat", &[highlight("cat", 2, "")]));
+        assert_eq!(None, find(snapshot, "a dog", &[]));
+        /* found again where it now is */
+        assert_eq!(Some(4), highlight("cat", 2, "").locate("the cat"));
+    }
+    #[test]
+    fn rendering() {
+        let aliases = std::collections::BTreeMap::new();
+        let context = markdown::RenderContext {
+            aliases: &aliases,
+            repo: "main",
+            math: crate::math::MathRendering::default(),
+            typography: crate::typography::Typography::default(),
+        };
+        let html = render(
+            "Some <b>text</b> here.",
+            &[highlight("text", 8, "a <note>")],
+            &context,
+        );
+        assert!(html.contains("Some &lt;b&gt;<mark class=\"highlight\">text</mark>"));
+        assert!(html.contains("<span class=\"margin-note\">a &lt;note&gt;</span>&lt;/b&gt; here."));
+        assert!(!render("no match", &[highlight("text", 0, "")], &context).contains("<mark"));
+    }
+}
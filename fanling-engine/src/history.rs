@@ -0,0 +1,87 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! the history of changes to an item, taken from the commits that changed it.
+
+Each commit is checked to see whether it is signed and if so whether
+the signature is good. Each change can be compared with the one before
+it (see [`crate::diff`]), and the item can be restored to how it was
+after any change (see [`crate::restore`]). */
+use crate::item::Item;
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use chrono::NaiveDateTime;
+
+/** the most changes shown */
+const MAX_CHANGES: usize = 50;
+
+/** a change to the item, for the template */
+struct Change {
+    /** the action to compare the item after the change with before it, as JSON */
+    diff_action: String,
+    /** the action to restore the item to how it was after the change, as JSON */
+    restore_action: String,
+    when: String,
+    author: String,
+    message: String,
+    verification: String,
+}
+/** template data for the history of an item */
+#[derive(Template)]
+#[template(path = "history.html", print = "none")]
+struct HistoryTemplate {
+    ident: String,
+    type_name: String,
+    descr: String,
+    changes: Vec<Change>,
+}
+
+/** show the history of changes to an item */
+pub fn show_history(item: &Item, world: &mut World) -> fanling_interface::ResponseResult {
+    let ident = item.ident();
+    let mut changes = vec![];
+    let commits = world.item_history(&ident, MAX_CHANGES)?;
+    for (n, commit) in commits.iter().enumerate() {
+        let local = NaiveDateTime::from_timestamp(
+            commit.when + i64::from(commit.offset_minutes) * 60,
+            0,
+        );
+        /* the oldest change shown is compared with nothing if it made the item */
+        let from = commits.get(n + 1).map_or("".to_owned(), |c| c.id.clone());
+        changes.push(Change {
+            diff_action: serde_json::to_string(&crate::Action::Diff {
+                from,
+                to: commit.id.clone(),
+            })?,
+            restore_action: serde_json::to_string(&crate::Action::Restore {
+                id: commit.id.clone(),
+                confirmed: false,
+            })?,
+            when: world.format().format_date_time(local),
+            author: commit.author.clone(),
+            message: commit.message.trim().to_owned(),
+            verification: world.verify_commit(&commit.id)?.to_string(),
+        });
+    }
+    trace(&format!("{} changes to {}", changes.len(), &ident));
+    #[cfg(test)]
+    let verifications: Vec<String> = changes.iter().map(|c| c.verification.clone()).collect();
+    let t = HistoryTemplate {
+        ident,
+        type_name: item.type_name(),
+        descr: item.description(),
+        changes,
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    resp.set_test_data("history", &verifications.join(","));
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("history {}", Colour::Fixed(10).on(Colour::Fixed(233)).paint(m));
+}
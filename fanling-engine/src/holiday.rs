@@ -0,0 +1,195 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! knows which days are holidays or weekends, so that dates can be moved to working days.
+
+The holidays are listed in an ordinary (simple) item with the ident
+[`HOLIDAYS_IDENT`], one per line, for example:
+
+```text
+* 2020-12-25 Christmas Day
+* 01-01 New Year's Day (every year)
+* weekend: Fri Sat
+```
+
+Lines that do not start with a date are ignored, so the page can also contain notes. */
+use crate::format::ISO_DATE_FORMAT;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use std::collections::BTreeMap;
+
+/** ident of the item listing the holidays */
+pub const HOLIDAYS_IDENT: &str = "holidays";
+/** limit on the search for a working day, in case every day is a holiday */
+const MAX_SEARCH_DAYS: i64 = 366;
+
+/** the holidays and weekend days */
+#[derive(Debug, Clone)]
+pub struct HolidayCalendar {
+    /** holidays on a specific date */
+    dated: BTreeMap<NaiveDate, String>,
+    /** holidays on the same day every year, keyed by (month, day) */
+    annual: BTreeMap<(u32, u32), String>,
+    /** days of the week that are not working days */
+    weekend: Vec<Weekday>,
+}
+impl HolidayCalendar {
+    /** a calendar with no holidays and a Saturday and Sunday weekend */
+    pub fn new() -> Self {
+        Self {
+            dated: BTreeMap::new(),
+            annual: BTreeMap::new(),
+            weekend: vec![Weekday::Sat, Weekday::Sun],
+        }
+    }
+    /** read a calendar from the text of the holidays item */
+    pub fn parse(text: &str) -> Self {
+        let mut cal = Self::new();
+        for line in text.lines() {
+            let line = line.trim().trim_start_matches(|c| c == '*' || c == '-').trim();
+            let (first, rest) = match line.find(char::is_whitespace) {
+                Some(i) => (&line[..i], line[i..].trim()),
+                None => (line, ""),
+            };
+            if first.eq_ignore_ascii_case("weekend:") {
+                cal.weekend = rest
+                    .split(|c: char| c.is_whitespace() || c == ',')
+                    .filter_map(|d| d.parse::<Weekday>().ok())
+                    .collect();
+            } else if let Ok(date) = NaiveDate::parse_from_str(first, ISO_DATE_FORMAT) {
+                cal.dated.insert(date, rest.to_owned());
+            } else if let Some((month, day)) = parse_month_day(first) {
+                cal.annual.insert((month, day), rest.to_owned());
+            }
+        }
+        cal
+    }
+    /** the description of the holiday on the date, if any */
+    pub fn holiday(&self, date: NaiveDate) -> Option<&str> {
+        self.dated
+            .get(&date)
+            .or_else(|| self.annual.get(&(date.month(), date.day())))
+            .map(|s| s.as_str())
+    }
+    /** is the date at a weekend? */
+    pub fn is_weekend(&self, date: NaiveDate) -> bool {
+        self.weekend.contains(&date.weekday())
+    }
+    /** is the date neither a holiday nor at a weekend? */
+    pub fn is_working_day(&self, date: NaiveDate) -> bool {
+        !self.is_weekend(date) && self.holiday(date).is_none()
+    }
+    /** the first working day after the date */
+    pub fn next_working_day(&self, date: NaiveDate) -> NaiveDate {
+        self.find_working_day(date, 1)
+    }
+    /** the last working day before the date */
+    pub fn previous_working_day(&self, date: NaiveDate) -> NaiveDate {
+        self.find_working_day(date, -1)
+    }
+    /** the date itself if it is a working day, otherwise the next working day */
+    pub fn this_or_next_working_day(&self, date: NaiveDate) -> NaiveDate {
+        if self.is_working_day(date) {
+            date
+        } else {
+            self.next_working_day(date)
+        }
+    }
+    fn find_working_day(&self, date: NaiveDate, step: i64) -> NaiveDate {
+        let mut d = date;
+        for _i in 0..MAX_SEARCH_DAYS {
+            d += Duration::days(step);
+            if self.is_working_day(d) {
+                return d;
+            }
+        }
+        date + Duration::days(step)
+    }
+    /** the number of working days after `from` up to and including `to` (negative if `to` is earlier) */
+    pub fn working_days_between(&self, from: NaiveDate, to: NaiveDate) -> i64 {
+        let (start, end, sign) = if to >= from {
+            (from, to, 1)
+        } else {
+            (to, from, -1)
+        };
+        let mut count = 0;
+        let mut d = start.succ();
+        while d <= end {
+            if self.is_working_day(d) {
+                count += 1;
+            }
+            d = d.succ();
+        }
+        count * sign
+    }
+    /** a warning about a deadline, if it is not on a working day or is close */
+    pub fn deadline_warning(&self, deadline: NaiveDate, today: NaiveDate) -> Option<String> {
+        if !self.is_working_day(deadline) {
+            let why = match self.holiday(deadline) {
+                Some(h) if !h.is_empty() => h.to_owned(),
+                Some(_) => "a holiday".to_owned(),
+                None => "a weekend".to_owned(),
+            };
+            return Some(format!(
+                "The deadline is on {}; the last working day before it is {}.",
+                why,
+                self.previous_working_day(deadline).format(ISO_DATE_FORMAT)
+            ));
+        }
+        match self.working_days_between(today, deadline) {
+            n if deadline < today => Some(format!("Overdue by {} working days.", -n)),
+            0 => Some("Due today.".to_owned()),
+            1 => Some("Due on the next working day.".to_owned()),
+            n if n <= 3 => Some(format!("Due in {} working days.", n)),
+            _ => None,
+        }
+    }
+}
+impl Default for HolidayCalendar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+/** parse a `MM-DD` date */
+fn parse_month_day(s: &str) -> Option<(u32, u32)> {
+    let mut parts = s.split('-');
+    let month = parts.next()?.parse::<u32>().ok()?;
+    let day = parts.next()?.parse::<u32>().ok()?;
+    if parts.next().is_some() || NaiveDate::from_ymd_opt(2000, month, day).is_none() {
+        return None;
+    }
+    Some((month, day))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn working_days() {
+        let cal = HolidayCalendar::parse(
+            "Holidays for the office:\n* 2020-12-25 Christmas Day\n* 12-26 Boxing Day\n- 2021-01-01\n",
+        );
+        let christmas_eve = NaiveDate::from_ymd(2020, 12, 24);
+        assert_eq!(Some("Christmas Day"), cal.holiday(christmas_eve.succ()));
+        assert_eq!(
+            Some("Boxing Day"),
+            cal.holiday(NaiveDate::from_ymd(2031, 12, 26))
+        );
+        /* 25 December 2020 was a Friday, then the weekend, then 1 January is a holiday too */
+        assert_eq!(
+            NaiveDate::from_ymd(2020, 12, 28),
+            cal.next_working_day(christmas_eve)
+        );
+        assert_eq!(
+            4,
+            cal.working_days_between(christmas_eve, NaiveDate::from_ymd(2021, 1, 1))
+        );
+        assert!(cal
+            .deadline_warning(christmas_eve.succ(), christmas_eve)
+            .unwrap()
+            .contains("2020-12-24"));
+        let friday_weekend = HolidayCalendar::parse("weekend: Fri, Sat");
+        assert!(friday_weekend.is_working_day(NaiveDate::from_ymd(2020, 12, 27)));
+        assert!(!friday_weekend.is_working_day(NaiveDate::from_ymd(2020, 12, 25)));
+    }
+}
@@ -0,0 +1,370 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! outbound hooks, for connecting fanling to other tools: when an item
+is made, updated or closed, its data is posted to a url or given to a
+local command.
+
+The hooks are given in the engine options (see [`HookOptions`]), as
+they belong to the device rather than to the repository. A url is sent
+a POST of the item as JSON (with the event and its ident) or as it is
+stored (YAML). A command is run with the shell, given the item on its
+standard input and the event and ident in `FANLING_EVENT` and
+`FANLING_IDENT`; commands are only run on a desktop.
+
+Posts are queued in a local file beside the search database rather
+than sent as the item is saved, so that the action is not held up by
+the network (as when offline). They are sent, in order, when the page
+next asks for a [`crate::Action::DeliverHooks`] and at each pull or
+sync; what could not be posted is sent again next time. A failing
+command is not run again. Hooks are called once the change has been saved, so nothing
+that goes wrong with them fails the action. Private items are never
+sent, and nothing is queued on disk for an encrypted repository. */
+use crate::item::ItemRef;
+use crate::shared::{FLResult, FanlingError, NullResult};
+use crate::world::World;
+use ansi_term::Colour;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** the name of the file that undelivered posts are queued in */
+pub const QUEUE_FILE: &str = "hooks-queue.json";
+/** the most posts that are queued (the oldest are dropped) */
+const MAX_QUEUED: usize = 500;
+/** how long to wait for a url (ms) */
+const TIMEOUT_MS: u64 = 10_000;
+
+/** when a hook is called */
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookEvent {
+    Created,
+    Updated,
+    Closed,
+}
+impl HookEvent {
+    /** the name of the event, as given in the options */
+    pub fn name(self) -> &'static str {
+        match self {
+            HookEvent::Created => "created",
+            HookEvent::Updated => "updated",
+            HookEvent::Closed => "closed",
+        }
+    }
+    fn parse(name: &str) -> Option<Self> {
+        [HookEvent::Created, HookEvent::Updated, HookEvent::Closed]
+            .iter()
+            .copied()
+            .find(|e| e.name() == name)
+    }
+}
+/** where the item is sent */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HookTarget {
+    /** posted to the url */
+    Url(String),
+    /** given to the command (desktop only) */
+    Command(String),
+}
+/** a hook, as given in the engine options */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HookOptions {
+    /** the events that the hook is called for */
+    pub events: Vec<HookEvent>,
+    pub target: HookTarget,
+    /** send the item as JSON (otherwise as it is stored) */
+    pub json: bool,
+}
+impl HookOptions {
+    /** read a hook given as `events=target`, such as
+    `created,closed=https://example.com/hook` or `closed=!notify-send done`
+    (a command starts with `!`); `yaml:` before the target sends the item as it is stored */
+    pub fn parse(spec: &str) -> FLResult<Self> {
+        let (events, target) = match spec.find('=') {
+            Some(i) => (&spec[..i], spec[i + 1..].trim()),
+            None => return Err(fanling_error!(&format!("no events in hook {}", spec))),
+        };
+        let events = events
+            .split(',')
+            .map(|e| {
+                HookEvent::parse(e.trim())
+                    .ok_or_else(|| fanling_error!(&format!("unknown event '{}' in hook", e)))
+            })
+            .collect::<FLResult<Vec<HookEvent>>>()?;
+        let (json, target) = match target.strip_prefix("yaml:") {
+            Some(target) => (false, target.trim()),
+            None => (true, target),
+        };
+        let target = match target.strip_prefix('!') {
+            Some(command) => HookTarget::Command(command.trim().to_owned()),
+            None => HookTarget::Url(target.to_owned()),
+        };
+        Ok(Self {
+            events,
+            target,
+            json,
+        })
+    }
+}
+
+/** a post that has not yet been delivered */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Queued {
+    url: String,
+    content_type: String,
+    body: String,
+    /** when it was first tried */
+    queued: NaiveDateTime,
+}
+/** the hooks, with the posts waiting to be delivered */
+#[derive(Debug)]
+pub struct Hooks {
+    hooks: Vec<HookOptions>,
+    /** the queue file, unless the queue is kept in memory only */
+    path: Option<PathBuf>,
+    /** whether commands can be run (not on a phone) */
+    commands: bool,
+    queue: Vec<Queued>,
+}
+impl Hooks {
+    /** the hooks, with any posts queued in the directory `dir` (if `keep`) */
+    pub fn open(hooks: &[HookOptions], dir: &Path, keep: bool, commands: bool) -> Self {
+        let path = if keep {
+            Some(dir.join(QUEUE_FILE))
+        } else {
+            None
+        };
+        let queue = match &path {
+            Some(path) if !hooks.is_empty() => read(path).unwrap_or_else(|e| {
+                trace(&format!("ignoring queue {:?}: {:?}", path, e));
+                vec![]
+            }),
+            _ => vec![],
+        };
+        Self {
+            hooks: hooks.to_vec(),
+            path,
+            commands,
+            queue,
+        }
+    }
+    /** whether a hook is called for the event */
+    fn wanted(&self, event: HookEvent) -> bool {
+        self.hooks.iter().any(|h| h.events.contains(&event))
+    }
+    /** how many posts are waiting to be delivered */
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+    /** call the hooks for the event, queueing the posts (see [`Hooks::retry`]) */
+    fn fire(&mut self, event: HookEvent, ident: &str, yaml: &[u8]) -> NullResult {
+        let mut json = None;
+        for hook in &self.hooks {
+            if !hook.events.contains(&event) {
+                continue;
+            }
+            let (content_type, body) = if hook.json {
+                if json.is_none() {
+                    json = Some(as_json(event, ident, yaml)?);
+                }
+                ("application/json", json.clone().unwrap_or_default())
+            } else {
+                (
+                    "application/yaml",
+                    String::from_utf8_lossy(yaml).to_string(),
+                )
+            };
+            match &hook.target {
+                HookTarget::Url(url) => {
+                    let post = Queued {
+                        url: url.clone(),
+                        content_type: content_type.to_owned(),
+                        body,
+                        queued: chrono::Local::now().naive_local(),
+                    };
+                    self.queue.push(post);
+                }
+                HookTarget::Command(command) if self.commands => {
+                    if let Err(e) = run_command(command, event, ident, &body) {
+                        trace(&format!("command '{}' failed: {}", command, e));
+                    }
+                }
+                HookTarget::Command(command) => {
+                    trace(&format!("not running '{}' here", command));
+                }
+            }
+        }
+        if self.queue.len() > MAX_QUEUED {
+            let dropped = self.queue.len() - MAX_QUEUED;
+            trace(&format!("dropping the {} oldest posts", dropped));
+            self.queue.drain(..dropped);
+        }
+        self.write()
+    }
+    /** post what is queued, in order, until one fails (as when still offline) */
+    pub fn retry(&mut self) -> NullResult {
+        if self.queue.is_empty() {
+            return Ok(());
+        }
+        let sent = self
+            .queue
+            .iter()
+            .take_while(|post| post_to(post).is_ok())
+            .count();
+        trace(&format!(
+            "{} of {} queued posts sent",
+            sent,
+            self.queue.len()
+        ));
+        self.queue.drain(..sent);
+        self.write()
+    }
+    fn write(&self) -> NullResult {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if self.queue.is_empty() {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+            return Ok(());
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(&self.queue)?)?;
+        Ok(())
+    }
+}
+/** read the queue file, if it is there */
+fn read(path: &Path) -> FLResult<Vec<Queued>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+/** the item as JSON, with the event and its ident */
+fn as_json(event: HookEvent, ident: &str, yaml: &[u8]) -> FLResult<String> {
+    let item: serde_json::Value = serde_yaml::from_slice(yaml)?;
+    Ok(serde_json::json!({
+        "event": event.name(),
+        "ident": ident,
+        "item": item,
+    })
+    .to_string())
+}
+/** post to the url */
+fn post_to(post: &Queued) -> NullResult {
+    let resp = ureq::post(&post.url)
+        .set("Content-Type", &post.content_type)
+        .timeout_connect(TIMEOUT_MS)
+        .timeout_read(TIMEOUT_MS)
+        .send_string(&post.body);
+    if !resp.ok() {
+        trace(&format!(
+            "could not post to {}: {}",
+            post.url,
+            resp.status_line()
+        ));
+        return Err(fanling_error!(&format!(
+            "could not post to {}: {}",
+            post.url,
+            resp.status_line()
+        )));
+    }
+    Ok(())
+}
+/** run the command with the shell, giving it the body on its standard input */
+fn run_command(command: &str, event: HookEvent, ident: &str, body: &str) -> NullResult {
+    trace(&format!("running '{}'", command));
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("FANLING_EVENT", event.name())
+        .env("FANLING_IDENT", ident)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    /* the standard input is closed (when dropped) before waiting */
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(body.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(fanling_error!(&format!("exited with {}", status)));
+    }
+    Ok(())
+}
+
+/** post what the hooks have queued (see [`Hooks::retry`]) */
+pub fn deliver(world: &mut World) -> fanling_interface::ResponseResult {
+    if let Err(e) = world.hooks_mut().retry() {
+        trace(&format!("could not deliver the queued posts: {:?}", e));
+    }
+    #[allow(unused_mut)]
+    let mut resp = fanling_interface::Response::new();
+    #[cfg(test)]
+    resp.set_test_data("queued", &format!("{}", world.hooks().queued()));
+    Ok(resp)
+}
+
+/** call the hooks for the event on the item (unless it is private),
+noting (but not returning) anything that goes wrong */
+pub fn fire(world: &mut World, event: HookEvent, item_ref: &ItemRef) {
+    if let Err(e) = try_fire(world, event, item_ref) {
+        trace(&format!("could not call the hooks: {:?}", e));
+    }
+}
+fn try_fire(world: &mut World, event: HookEvent, item_ref: &ItemRef) -> NullResult {
+    if !world.hooks().wanted(event) {
+        return Ok(());
+    }
+    let (ident, yaml) = {
+        let item = item_ref.deref().borrow();
+        if item.is_private() {
+            return Ok(());
+        }
+        (item.ident(), item.to_yaml()?)
+    };
+    trace(&format!("{} {}", event.name(), ident));
+    world.hooks_mut().fire(event, &ident, &yaml)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("hooks {}", Colour::Fixed(0).on(Colour::Fixed(216)).paint(m));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn parse() -> FLResult<()> {
+        let hook = HookOptions::parse("created,closed=https://example.com/hook")?;
+        assert_eq!(vec![HookEvent::Created, HookEvent::Closed], hook.events);
+        assert_eq!(
+            HookTarget::Url("https://example.com/hook".to_owned()),
+            hook.target
+        );
+        assert!(hook.json);
+        let hook = HookOptions::parse("closed=yaml:!notify-send done")?;
+        assert_eq!(
+            HookTarget::Command("notify-send done".to_owned()),
+            hook.target
+        );
+        assert!(!hook.json);
+        assert!(HookOptions::parse("opened=https://example.com").is_err());
+        assert!(HookOptions::parse("https://example.com").is_err());
+        Ok(())
+    }
+}
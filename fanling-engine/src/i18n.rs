@@ -0,0 +1,89 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! translations of the text shown to the user, in the style of gettext.
+
+The English text (as written in the code and templates) is looked up
+in a catalog for the locale chosen in the engine options, and is used
+as it is if there is no translation. Each catalog is a YAML file in
+the `locales` directory, mapping English text to its translation, and
+is built into the engine. Templates use the translator held in their
+base data (eg `{{base.i18n.tr("Parent:")}}`). */
+use crate::shared::FLResult;
+use ansi_term::Colour;
+use std::collections::HashMap;
+
+/** the locale used when none is chosen (the text in the code) */
+pub const DEFAULT_LOCALE: &str = "en";
+/** the catalog for each language that has translations */
+const CATALOGS: &[(&str, &str)] = &[("fr", include_str!("../locales/fr.yaml"))];
+
+/** translates text into the language of a locale */
+#[derive(Debug, Clone, Default)]
+pub struct Translator {
+    /** the language of the translations (eg "fr") */
+    language: String,
+    /** the translation of each English text */
+    messages: HashMap<String, String>,
+}
+impl Translator {
+    /** a translator for a locale such as "fr" or "fr_FR.UTF-8"
+    (English, if there are no translations for its language) */
+    pub fn new(locale: &str) -> FLResult<Self> {
+        let language = locale
+            .split(|c| c == '_' || c == '-' || c == '.')
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+        match CATALOGS.iter().find(|(lang, _catalog)| *lang == language) {
+            Some((_lang, catalog)) => Ok(Self {
+                language,
+                messages: serde_yaml::from_str(catalog)?,
+            }),
+            None => {
+                if !language.is_empty() && language != DEFAULT_LOCALE {
+                    trace(&format!("no translations for {}, using English", locale));
+                }
+                Ok(Self {
+                    language: DEFAULT_LOCALE.to_owned(),
+                    messages: HashMap::new(),
+                })
+            }
+        }
+    }
+    /** the language that text is translated into */
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+    /** the translation of some English text (or the text itself, if it has no translation) */
+    pub fn tr(&self, text: &str) -> String {
+        self.messages
+            .get(text)
+            .cloned()
+            .unwrap_or_else(|| text.to_owned())
+    }
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("i18n {}", Colour::Fixed(14).on(Colour::Fixed(236)).paint(m));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn translate() -> FLResult<()> {
+        let fr = Translator::new("fr_FR.UTF-8")?;
+        assert_eq!("fr", fr.language());
+        assert_eq!("Modifier", fr.tr("Edit"));
+        assert_eq!("Le nom ne doit pas être vide.", fr.tr("Name must be non-blank."));
+        assert_eq!("not translated", fr.tr("not translated"));
+        let en = Translator::new("")?;
+        assert_eq!(DEFAULT_LOCALE, en.language());
+        assert_eq!("Edit", en.tr("Edit"));
+        assert_eq!(DEFAULT_LOCALE, Translator::new("xx")?.language());
+        Ok(())
+    }
+}
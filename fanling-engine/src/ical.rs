@@ -0,0 +1,211 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! reads and writes iCalendar data (RFC 5545), so that events can be
+imported into other calendars and synced with them */
+use crate::vcard::{escape, fold, unescape, unfold};
+use chrono::{NaiveDate, NaiveDateTime, TimeZone};
+
+/** format for local ("floating") date-times */
+const DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+/** a single event, restricted to the properties that Fanling uses */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ICalEvent {
+    /** unique identifier (`UID`), made from the ident of the item */
+    pub uid: String,
+    /** start (`DTSTART`) */
+    pub start: NaiveDateTime,
+    /** end (`DTEND`) */
+    pub end: NaiveDateTime,
+    /** what is happening (`SUMMARY`) */
+    pub summary: String,
+    /** where it happens (`LOCATION`) */
+    pub location: String,
+    /** description (`DESCRIPTION`) */
+    pub description: String,
+    /** how often it happens again (`FREQ` in the `RRULE`), if it does */
+    pub frequency: Option<&'static str>,
+}
+impl ICalEvent {
+    /** the lines for the event */
+    fn to_lines(&self, stamp: &str) -> Vec<String> {
+        let mut lines = vec!["BEGIN:VEVENT".to_owned()];
+        /* an event that came from another calendar keeps its own UID */
+        if self.uid.contains('@') {
+            lines.push(format!("UID:{}", escape(&self.uid)));
+        } else {
+            lines.push(format!("UID:{}@fanling", escape(&self.uid)));
+        }
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("DTSTART:{}", self.start.format(DATE_TIME_FORMAT)));
+        lines.push(format!("DTEND:{}", self.end.format(DATE_TIME_FORMAT)));
+        lines.push(format!("SUMMARY:{}", escape(&self.summary)));
+        if !self.location.is_empty() {
+            lines.push(format!("LOCATION:{}", escape(&self.location)));
+        }
+        if !self.description.is_empty() {
+            lines.push(format!("DESCRIPTION:{}", escape(&self.description)));
+        }
+        if let Some(frequency) = self.frequency {
+            lines.push(format!("RRULE:FREQ={}", frequency));
+        }
+        lines.push("END:VEVENT".to_owned());
+        lines
+    }
+}
+
+/** serialise events as an iCalendar file (lines end with CRLF) */
+pub fn to_ical_text(events: &[ICalEvent]) -> String {
+    let stamp = chrono::Utc::now()
+        .naive_utc()
+        .format(&format!("{}Z", DATE_TIME_FORMAT))
+        .to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_owned(),
+        "VERSION:2.0".to_owned(),
+        "PRODID:-//Fanling//Fanling//EN".to_owned(),
+    ];
+    for event in events {
+        lines.extend(event.to_lines(&stamp));
+    }
+    lines.push("END:VCALENDAR".to_owned());
+    let folded: Vec<String> = lines.iter().map(|l| fold(l)).collect();
+    folded.join("\r\n") + "\r\n"
+}
+
+/** the frequency in an `RRULE`, if it is one that Fanling knows */
+fn parse_frequency(rule: &str) -> Option<&'static str> {
+    let frequency = rule
+        .split(';')
+        .find_map(|part| part.trim().strip_prefix("FREQ="))?;
+    ["DAILY", "WEEKLY", "MONTHLY", "YEARLY"]
+        .iter()
+        .copied()
+        .find(|f| frequency.eq_ignore_ascii_case(f))
+}
+/** a date-time (in UTC if it ends with `Z`, given as local time), or a
+date (as its start), ignoring any time zone */
+pub(crate) fn parse_date_time(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    if let Some(utc) = value.strip_suffix('Z') {
+        let dt = NaiveDateTime::parse_from_str(utc, DATE_TIME_FORMAT).ok()?;
+        return Some(chrono::Local.from_utc_datetime(&dt).naive_local());
+    }
+    NaiveDateTime::parse_from_str(value, DATE_TIME_FORMAT)
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y%m%d")
+                .ok()
+                .map(|d| d.and_hms(0, 0, 0))
+        })
+}
+
+/** parse iCalendar text, giving its events (those without a start are skipped) */
+pub fn parse(text: &str) -> Vec<ICalEvent> {
+    let mut events = vec![];
+    let mut current: Option<(Option<NaiveDateTime>, Option<NaiveDateTime>, ICalEvent)> = None;
+    for line in unfold(text).lines() {
+        let line = line.trim_end_matches('\r');
+        let colon = match line.find(':') {
+            Some(c) => c,
+            None => continue,
+        };
+        let (params, value) = (&line[..colon], &line[colon + 1..]);
+        let prop = params.split(';').next().unwrap_or("").to_uppercase();
+        match prop.as_str() {
+            "BEGIN" if value.eq_ignore_ascii_case("VEVENT") => {
+                current = Some((
+                    None,
+                    None,
+                    ICalEvent {
+                        uid: "".to_owned(),
+                        start: NaiveDateTime::from_timestamp(0, 0),
+                        end: NaiveDateTime::from_timestamp(0, 0),
+                        summary: "".to_owned(),
+                        location: "".to_owned(),
+                        description: "".to_owned(),
+                        frequency: None,
+                    },
+                ));
+            }
+            "END" if value.eq_ignore_ascii_case("VEVENT") => {
+                if let Some((Some(start), end, mut event)) = current.take() {
+                    event.start = start;
+                    event.end = end.unwrap_or(start);
+                    events.push(event);
+                }
+            }
+            _ => {
+                if let Some((start, end, event)) = current.as_mut() {
+                    match prop.as_str() {
+                        "UID" => event.uid = unescape(value),
+                        "DTSTART" => *start = parse_date_time(value),
+                        "DTEND" => *end = parse_date_time(value),
+                        "SUMMARY" => event.summary = unescape(value),
+                        "LOCATION" => event.location = unescape(value),
+                        "DESCRIPTION" => event.description = unescape(value),
+                        "RRULE" => event.frequency = parse_frequency(value),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn calendar() {
+        let event = ICalEvent {
+            uid: "party-a1".to_owned(),
+            start: chrono::NaiveDate::from_ymd(2020, 3, 1).and_hms(19, 30, 0),
+            end: chrono::NaiveDate::from_ymd(2020, 3, 1).and_hms(23, 0, 0),
+            summary: "Party, with cake".to_owned(),
+            location: "".to_owned(),
+            description: "Bring a bottle.\nOr two. ".repeat(5),
+            frequency: Some("YEARLY"),
+        };
+        let text = to_ical_text(&[event]);
+        assert!(text.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(text.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+        assert!(text.contains("\r\nDTSTART:20200301T193000\r\n"));
+        assert!(text.contains("\r\nSUMMARY:Party\\, with cake\r\n"));
+        assert!(text.contains("\r\nRRULE:FREQ=YEARLY\r\n"));
+        assert!(!text.contains("LOCATION"));
+        assert!(text.lines().all(|l| l.len() <= 76));
+    }
+    #[test]
+    fn round_trip() {
+        let event = ICalEvent {
+            uid: "meeting-b2@fanling".to_owned(),
+            start: chrono::NaiveDate::from_ymd(2021, 5, 4).and_hms(9, 0, 0),
+            end: chrono::NaiveDate::from_ymd(2021, 5, 4).and_hms(10, 30, 0),
+            summary: "Planning; budget".to_owned(),
+            location: "Room 2".to_owned(),
+            description: "Agenda:\n1. numbers, more numbers ".repeat(4),
+            frequency: Some("WEEKLY"),
+        };
+        let text = to_ical_text(&[event.clone()]);
+        assert!(!text.contains("@fanling@fanling"));
+        assert_eq!(vec![event], parse(&text));
+    }
+    #[test]
+    fn other_calendars() {
+        let text = "BEGIN:VCALENDAR\nBEGIN:VTIMEZONE\nTZID:Europe/Paris\nEND:VTIMEZONE\nBEGIN:VEVENT\nUID:abc123@example.com\nDTSTART;VALUE=DATE:20210704\nSUMMARY:Holiday\nRRULE:FREQ=YEARLY;BYMONTH=7\nEND:VEVENT\nBEGIN:VEVENT\nDTSTART;TZID=Europe/Paris:20210705T140000\nDTEND;TZID=Europe/Paris:20210705T150000\nSUMMARY:Call\nRRULE:FREQ=HOURLY\nEND:VEVENT\nBEGIN:VEVENT\nSUMMARY:No start\nEND:VEVENT\nEND:VCALENDAR\n";
+        let events = parse(text);
+        assert_eq!(2, events.len());
+        assert_eq!("abc123@example.com", events[0].uid);
+        assert_eq!(events[0].start, events[0].end);
+        assert_eq!(Some("YEARLY"), events[0].frequency);
+        assert_eq!(
+            chrono::NaiveDate::from_ymd(2021, 7, 5).and_hms(15, 0, 0),
+            events[1].end
+        );
+        assert_eq!(None, events[1].frequency);
+    }
+}
@@ -0,0 +1,235 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! how the idents of new items are made, and finding idents that collide.
+
+The scheme is chosen in the options:
+
+* `slug` (the default): the start of the name, the prefix of the device and a number, such as `buy-milk-a12`;
+* `sequential`: the prefix of the device and a number only, such as `item-a12`;
+* `uuid7`: a UUID (version 7), which starts with the time, so that the idents sort in the order they were made;
+* `ulid`: a ULID, also ordered by time, but shorter.
+
+The numbers are kept for each device, continuing from the largest in
+the repository, and a new ident is never one that an item already has.
+Even so, when repositories from several sources are merged, two items
+can collide: the file of one can claim the ident of another, or two
+idents can differ only in case (and so be the same file on a
+filesystem that ignores case, as on Windows, macOS and often Android).
+These are found when the items are loaded and by the check page (see
+[`crate::check`]), which can give one of the items a new ident. */
+use crate::item::Ident;
+use crate::shared::FLResult;
+use rand::Rng;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/** the longest part of a name used in a slug */
+const MAX_SLUG_LEN: usize = 20;
+/** the start of the ident of an item whose description gives no slug */
+const FALLBACK_SLUG: &str = "item";
+/** the letters of Crockford's base 32, used in ULIDs */
+const CROCKFORD: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/** how the idents of new items are made */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IdentScheme {
+    Slug,
+    Sequential,
+    UuidV7,
+    Ulid,
+}
+impl IdentScheme {
+    /** the scheme with the name (as in the options), or a slug if it is not known */
+    pub fn parse(name: &str) -> Self {
+        match name.trim().to_lowercase().as_str() {
+            "sequential" => IdentScheme::Sequential,
+            "uuid7" | "uuidv7" | "uuid" => IdentScheme::UuidV7,
+            "ulid" => IdentScheme::Ulid,
+            _ => IdentScheme::Slug,
+        }
+    }
+}
+impl Default for IdentScheme {
+    fn default() -> Self {
+        IdentScheme::Slug
+    }
+}
+
+/** makes the idents of new items */
+pub trait IdentGenerator: std::fmt::Debug {
+    /** an ident for an item with the description, `number` being the
+    next number of this device (which some schemes do not use) */
+    fn generate(&mut self, descr: &str, number: u64) -> Ident;
+}
+/** the generator for a scheme, for the device with the prefix */
+pub fn generator(scheme: IdentScheme, prefix: &str) -> FLResult<Box<dyn IdentGenerator>> {
+    assert!(!prefix.is_empty(), "prefix is empty");
+    Ok(match scheme {
+        IdentScheme::Slug => Box::new(SlugGenerator {
+            prefix: prefix.to_owned(),
+            unallowed_chars: Regex::new("[^0-9a-zA-Z]+")?,
+        }),
+        IdentScheme::Sequential => Box::new(SequentialGenerator {
+            prefix: prefix.to_owned(),
+        }),
+        IdentScheme::UuidV7 => Box::new(UuidV7Generator {}),
+        IdentScheme::Ulid => Box::new(UlidGenerator {}),
+    })
+}
+
+/** the start of the name, the prefix and a number */
+#[derive(Debug)]
+struct SlugGenerator {
+    prefix: String,
+    unallowed_chars: Regex,
+}
+impl IdentGenerator for SlugGenerator {
+    fn generate(&mut self, descr: &str, number: u64) -> Ident {
+        let tidy_name = self.unallowed_chars.replace_all(descr, "-");
+        /* a name with no letters or digits that can be used (such as "会议" or "!!!") is an item */
+        let tidy_name = match tidy_name.trim_start_matches('-') {
+            "" => FALLBACK_SLUG,
+            tidy_name => tidy_name,
+        };
+        let short_tidy_name: String = tidy_name.chars().take(MAX_SLUG_LEN).collect();
+        format!("{}-{}{}", short_tidy_name, self.prefix, number)
+    }
+}
+/** the prefix and a number */
+#[derive(Debug)]
+struct SequentialGenerator {
+    prefix: String,
+}
+impl IdentGenerator for SequentialGenerator {
+    fn generate(&mut self, _descr: &str, number: u64) -> Ident {
+        format!("item-{}{}", self.prefix, number)
+    }
+}
+/** milliseconds since the epoch */
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+/** a version 7 UUID from the time and random bytes */
+fn uuid_v7(millis: u64, random: [u8; 10]) -> String {
+    let mut bytes = [0u8; 16];
+    bytes[..6].copy_from_slice(&millis.to_be_bytes()[2..]);
+    bytes[6..].copy_from_slice(&random);
+    bytes[6] = (bytes[6] & 0x0f) | 0x70;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+/** a ULID from the time and random bytes */
+fn ulid(millis: u64, random: [u8; 10]) -> String {
+    let value = random
+        .iter()
+        .fold(u128::from(millis & 0xffff_ffff_ffff), |v, b| {
+            (v << 8) | u128::from(*b)
+        });
+    (0..26)
+        .rev()
+        .map(|i| CROCKFORD[((value >> (i * 5)) & 0x1f) as usize] as char)
+        .collect()
+}
+#[derive(Debug)]
+struct UuidV7Generator {}
+impl IdentGenerator for UuidV7Generator {
+    fn generate(&mut self, _descr: &str, _number: u64) -> Ident {
+        uuid_v7(now_millis(), rand::thread_rng().gen())
+    }
+}
+#[derive(Debug)]
+struct UlidGenerator {}
+impl IdentGenerator for UlidGenerator {
+    fn generate(&mut self, _descr: &str, _number: u64) -> Ident {
+        ulid(now_millis(), rand::thread_rng().gen())
+    }
+}
+
+/** an item whose ident collides with that of another */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collision {
+    /** the ident (from the path) of the item that should be given a new ident */
+    pub ident: Ident,
+    /** what it collides with */
+    pub descr: String,
+}
+/** the collisions among the items stored, given as the ident from the
+path of each and the ident in its data */
+pub fn collisions(stored: &[(Ident, Ident)]) -> Vec<Collision> {
+    let paths: BTreeSet<&Ident> = stored.iter().map(|(path, _data)| path).collect();
+    let mut collisions = vec![];
+    let mut by_case: BTreeMap<String, Vec<&Ident>> = BTreeMap::new();
+    for (path, data) in stored {
+        if data != path && paths.contains(data) {
+            collisions.push(Collision {
+                ident: path.clone(),
+                descr: format!("claims the ident of '{}'", data),
+            });
+        }
+        by_case.entry(path.to_lowercase()).or_default().push(path);
+    }
+    for idents in by_case.values_mut().filter(|i| i.len() > 1) {
+        idents.sort();
+        idents.dedup();
+        for other in &idents[1..] {
+            collisions.push(Collision {
+                ident: (*other).clone(),
+                descr: format!("differs only in case from '{}'", idents[0]),
+            });
+        }
+    }
+    collisions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schemes() -> crate::shared::NullResult {
+        let mut slug = generator(IdentScheme::Slug, "a")?;
+        assert_eq!("buy-milk-a3", slug.generate("buy milk", 3));
+        assert_eq!("x-a4", slug.generate("--x", 4));
+        assert_eq!("item-a5", slug.generate("会议", 5));
+        assert_eq!("item-a6", slug.generate("!!!", 6));
+        assert_eq!("item-a7", slug.generate("", 7));
+        let mut seq = generator(IdentScheme::parse("sequential"), "b")?;
+        assert_eq!("item-b5", seq.generate("buy milk", 5));
+        let uuid = uuid_v7(0x0123_4567_89ab, [0xff; 10]);
+        assert_eq!("01234567-89ab-7fff-bfff-ffffffffffff", uuid);
+        assert_eq!("00000000000000000000000000", ulid(0, [0; 10]));
+        assert_eq!(26, ulid(now_millis(), [0xff; 10]).len());
+        assert!(ulid(1, [0; 10]) < ulid(2, [0; 10]));
+        assert_eq!(IdentScheme::Slug, IdentScheme::parse(""));
+        Ok(())
+    }
+    #[test]
+    fn find_collisions() {
+        let stored = |pairs: &[(&str, &str)]| -> Vec<(Ident, Ident)> {
+            pairs
+                .iter()
+                .map(|(p, d)| (p.to_string(), d.to_string()))
+                .collect()
+        };
+        assert!(collisions(&stored(&[("a", "a"), ("b", "b")])).is_empty());
+        let found = collisions(&stored(&[("a", "a"), ("b", "a")]));
+        assert_eq!(1, found.len());
+        assert_eq!("b", found[0].ident);
+        let found = collisions(&stored(&[("Note", "Note"), ("note", "note")]));
+        assert_eq!(1, found.len());
+        assert_eq!("note", found[0].ident);
+    }
+}
@@ -0,0 +1,332 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! uses template items to create new items.
+
+The name, text and other fields of a template can contain variables
+such as `{{who:person}}`. When the template is used the values of its
+variables are asked for, and replaced in a new copy of the item. The
+kind of a variable (`text`, `date` or `person`) follows the colon, and
+is `text` if there is no colon.
+
+Two placeholders are not asked for: `{{date}}` becomes today's date,
+and `{{cursor}}` is removed, and the new item is edited with the cursor
+where it was. */
+use crate::item::{Ident, ItemListEntryList};
+use crate::shared::FLResult;
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use regex::{Captures, Regex};
+use serde_yaml::Value;
+use std::collections::HashMap;
+
+/** the kinds of value a variable can have */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VariableKind {
+    Text,
+    Date,
+    /** the name of a contact */
+    Person,
+}
+impl VariableKind {
+    /** the kind from its name (text if not known) */
+    fn parse(s: &str) -> Self {
+        match s.trim().to_lowercase().as_str() {
+            "date" => VariableKind::Date,
+            "person" => VariableKind::Person,
+            _ => VariableKind::Text,
+        }
+    }
+}
+/** a variable in a template */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variable {
+    pub name: String,
+    pub kind: VariableKind,
+}
+
+/** the placeholder for today's date */
+const DATE_PLACEHOLDER: &str = "date";
+/** the placeholder for where the cursor should be when editing the new item */
+const CURSOR_PLACEHOLDER: &str = "cursor";
+/** stands for the cursor until its position has been found */
+const CURSOR_MARK: char = '\u{1}';
+
+/** matches a variable, capturing its name and kind */
+fn variable_re() -> Regex {
+    Regex::new(r"\{\{\s*([^{}:]+?)\s*(?::\s*(\w+)\s*)?\}\}").expect("bad variable regex")
+}
+
+/** all the strings in some values */
+fn strings<'a>(value: &'a Value, found: &mut Vec<&'a str>) {
+    match value {
+        Value::String(s) => found.push(s),
+        Value::Sequence(seq) => seq.iter().for_each(|v| strings(v, found)),
+        Value::Mapping(map) => map.iter().for_each(|(_k, v)| strings(v, found)),
+        _ => {}
+    }
+}
+
+/** the variables in the values of a template, in the order they are first found */
+pub fn variables(values: &Value) -> Vec<Variable> {
+    let re = variable_re();
+    let mut found = vec![];
+    strings(values, &mut found);
+    let mut vars: Vec<Variable> = vec![];
+    for s in found {
+        for caps in re.captures_iter(s) {
+            let name = caps[1].to_owned();
+            if caps.get(2).is_none() && is_placeholder(&name) {
+                continue;
+            }
+            if !vars.iter().any(|v| v.name == name) {
+                vars.push(Variable {
+                    name,
+                    kind: VariableKind::parse(caps.get(2).map_or("", |k| k.as_str())),
+                });
+            }
+        }
+    }
+    vars
+}
+
+/** whether a variable without a kind is one of the placeholders that are not asked for */
+fn is_placeholder(name: &str) -> bool {
+    name == DATE_PLACEHOLDER || name == CURSOR_PLACEHOLDER
+}
+
+/** the field that the cursor mark is in, and its position in the
+field (in UTF-16 code units, as used by browsers) */
+fn find_cursor(values: &Value) -> Option<(String, usize)> {
+    match values {
+        Value::Mapping(map) => map.iter().find_map(|(key, value)| {
+            let s = value.as_str()?;
+            let pos = s.find(CURSOR_MARK)?;
+            Some((
+                key.as_str().unwrap_or("").to_owned(),
+                s[..pos].encode_utf16().count(),
+            ))
+        }),
+        _ => None,
+    }
+}
+
+/** the values with each variable replaced by its value (or removed if it has none) */
+pub fn substitute(values: &Value, vals: &HashMap<String, String>) -> Value {
+    fn subst(value: &Value, vals: &HashMap<String, String>, re: &Regex) -> Value {
+        match value {
+            Value::String(s) => Value::String(
+                re.replace_all(s, |caps: &Captures| {
+                    vals.get(&caps[1]).cloned().unwrap_or_default()
+                })
+                .into_owned(),
+            ),
+            Value::Sequence(seq) => Value::Sequence(seq.iter().map(|v| subst(v, vals, re)).collect()),
+            Value::Mapping(map) => Value::Mapping(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), subst(v, vals, re)))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+    subst(values, vals, &variable_re())
+}
+
+/** a variable, for the template */
+struct Field {
+    name: String,
+    is_person: bool,
+    /** the initial value */
+    value: String,
+}
+/** template data for the form asking for the values of the variables */
+#[derive(Template)]
+#[template(path = "instantiate.html", print = "none")]
+struct InstantiateTemplate {
+    ident: String,
+    type_name: String,
+    descr: String,
+    fields: Vec<Field>,
+    people: ItemListEntryList,
+}
+
+/** start using a template: ask for the values of its variables, or
+if there are none, create the new item straight away */
+pub fn start(world: &mut World, ident: &Ident) -> fanling_interface::ResponseResult {
+    let (base, values) = world.get_item_parts(ident)?;
+    let vars = variables(&values);
+    trace(&format!("template {} has variables {:?}", ident, &vars));
+    if vars.is_empty() {
+        return instantiate(world, ident, &HashMap::new());
+    }
+    let today = world.format().format_date(chrono::Local::today().naive_local());
+    let fields = vars
+        .iter()
+        .map(|v| Field {
+            name: v.name.clone(),
+            is_person: v.kind == VariableKind::Person,
+            value: if v.kind == VariableKind::Date {
+                today.clone()
+            } else {
+                "".to_owned()
+            },
+        })
+        .collect();
+    let t = InstantiateTemplate {
+        ident: ident.clone(),
+        type_name: base.type_name.clone(),
+        descr: values
+            .get("name")
+            .and_then(|n| n.as_str())
+            .unwrap_or(ident)
+            .to_owned(),
+        fields,
+        people: world.search_type("Contact")?,
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    resp.set_test_data(
+        "variables",
+        &vars
+            .iter()
+            .map(|v| v.name.clone())
+            .collect::<Vec<String>>()
+            .join(","),
+    );
+    Ok(resp)
+}
+
+/** create a new item from a template, using the values given for its variables */
+pub fn instantiate(
+    world: &mut World,
+    ident: &Ident,
+    vals: &HashMap<String, String>,
+) -> fanling_interface::ResponseResult {
+    let (_base, values) = world.get_item_parts(ident)?;
+    let mut vals = trimmed(vals);
+    let today = world.format().format_date(chrono::Local::today().naive_local());
+    vals.entry(DATE_PLACEHOLDER.to_owned()).or_insert(today);
+    let cursor = if vals.contains_key(CURSOR_PLACEHOLDER) {
+        None
+    } else {
+        /* find where the cursor goes (it is then left out, as it has no value) */
+        let mut marked = vals.clone();
+        marked.insert(CURSOR_PLACEHOLDER.to_owned(), CURSOR_MARK.to_string());
+        find_cursor(&substitute(&values, &marked))
+    };
+    let values = substitute(&values, &vals);
+    match cursor {
+        None => world.instantiate_item(ident, &values, false),
+        Some((field, offset)) => {
+            let mut resp = world.instantiate_item(ident, &values, true)?;
+            let t = CursorTemplate { field, offset };
+            resp.add_tag("message", &(t.render()?));
+            #[cfg(test)]
+            resp.set_test_data("cursor", &format!("{}:{}", &t.field, t.offset));
+            Ok(resp)
+        }
+    }
+}
+
+/** template data for placing the cursor in the form for editing a new item */
+#[derive(Template)]
+#[template(path = "cursor.html", print = "none")]
+struct CursorTemplate {
+    field: String,
+    offset: usize,
+}
+
+/** a template, for the template */
+struct TemplateEntry {
+    ident: String,
+    type_name: String,
+    descr: String,
+}
+/** template data for choosing a template */
+#[derive(Template)]
+#[template(path = "templates.html", print = "none")]
+struct TemplatesTemplate {
+    templates: Vec<TemplateEntry>,
+}
+/** show the templates, to choose one to make a new item from */
+pub fn show_templates(world: &mut World) -> fanling_interface::ResponseResult {
+    let mut templates = vec![];
+    for entry in world.search_templates()?.entries {
+        let (base, values) = world.get_item_parts(&entry.link.ident)?;
+        if crate::private::is_sealed(&values) {
+            continue;
+        }
+        templates.push(TemplateEntry {
+            ident: entry.link.ident.clone(),
+            type_name: base.type_name,
+            descr: entry.descr.clone(),
+        });
+    }
+    templates.sort_by(|a, b| (&a.type_name, &a.descr).cmp(&(&b.type_name, &b.descr)));
+    let count = templates.len();
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(TemplatesTemplate { templates }.render()?));
+    #[cfg(test)]
+    resp.set_test_data("count", &format!("{}", count));
+    trace(&format!("{} templates", count));
+    Ok(resp)
+}
+
+/** the values with surrounding white space removed */
+fn trimmed(vals: &HashMap<String, String>) -> HashMap<String, String> {
+    vals.iter()
+        .map(|(k, v)| (k.trim().to_owned(), v.trim().to_owned()))
+        .collect()
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "instantiate {}",
+        Colour::Fixed(13).on(Colour::Fixed(233)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn variables_and_substitution() -> FLResult<()> {
+        let values: Value = serde_yaml::from_str(
+            "name: Meet {{ who : person }}\ntext: On {{when:date}} with {{who}} about {{topic}}\n",
+        )?;
+        let vars = variables(&values);
+        assert_eq!(3, vars.len());
+        assert_eq!("who", vars[0].name);
+        assert_eq!(VariableKind::Person, vars[0].kind);
+        assert_eq!(VariableKind::Date, vars[1].kind);
+        assert_eq!(VariableKind::Text, vars[2].kind);
+        let mut vals = HashMap::new();
+        vals.insert("who".to_owned(), "Jo".to_owned());
+        vals.insert("when".to_owned(), "Friday".to_owned());
+        let result = substitute(&values, &vals);
+        assert_eq!(Some("Meet Jo"), result.get("name").and_then(|n| n.as_str()));
+        assert_eq!(
+            Some("On Friday with Jo about "),
+            result.get("text").and_then(|n| n.as_str())
+        );
+        Ok(())
+    }
+    #[test]
+    fn placeholders() -> FLResult<()> {
+        let values: Value =
+            serde_yaml::from_str("name: Diary {{date}}\ntext: \"Café: {{cursor}} ({{mood}})\"\n")?;
+        let vars = variables(&values);
+        assert_eq!(1, vars.len());
+        assert_eq!("mood", vars[0].name);
+        let mut vals = HashMap::new();
+        vals.insert(CURSOR_PLACEHOLDER.to_owned(), CURSOR_MARK.to_string());
+        let marked = substitute(&values, &vals);
+        assert_eq!(Some(("text".to_owned(), 6)), find_cursor(&marked));
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! content hashes for the local files that are kept beside the
+repository (the search database and the drafts), so that a file that
+has been corrupted (as can happen with storage on Android) is noticed
+when it is loaded, and is rebuilt instead of being believed.
+
+The hash of a file is kept in a file next to it, with `.sha256`
+added to its name. The drafts are hashed whenever they are written.
+The search database changes with every action, so its hash is
+recorded when the engine shuts down and forgotten when it is opened
+again: if the app is killed, there is no hash, and the database is
+used as it is. */
+use crate::shared::{FLResult, NullResult};
+use ansi_term::Colour;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/** what was found when checking a file against its hash */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Check {
+    /** the file matches its hash */
+    Matches,
+    /** there is no hash (or no file) to check */
+    Missing,
+    /** the file has changed since its hash was recorded */
+    Mismatch,
+}
+
+/** the path of the file holding the hash for a file */
+fn hash_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".sha256");
+    path.with_file_name(name)
+}
+/** the hash of some data, as text */
+pub(crate) fn hash(data: &[u8]) -> String {
+    base64::encode(&Sha256::digest(data))
+}
+
+/** write a file, with its hash */
+pub fn write(path: &Path, contents: &[u8]) -> NullResult {
+    fs::write(path, contents)?;
+    fs::write(hash_path(path), hash(contents))?;
+    Ok(())
+}
+/** record the hash of a file as it is now */
+pub fn record(path: &Path) -> NullResult {
+    let contents = fs::read(path)?;
+    fs::write(hash_path(path), hash(&contents))?;
+    trace(&format!("recorded hash for {:?}", path));
+    Ok(())
+}
+/** check a file against its hash */
+pub fn check(path: &Path) -> FLResult<Check> {
+    let expected = match fs::read_to_string(hash_path(path)) {
+        Ok(expected) => expected,
+        Err(_) => return Ok(Check::Missing),
+    };
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(_) => return Ok(Check::Missing),
+    };
+    if hash(&contents) == expected.trim() {
+        Ok(Check::Matches)
+    } else {
+        trace(&format!("{:?} does not match its hash", path));
+        Ok(Check::Mismatch)
+    }
+}
+/** forget the hash of a file (as it is about to be changed) */
+pub fn forget(path: &Path) -> NullResult {
+    let hash_path = hash_path(path);
+    if hash_path.exists() {
+        fs::remove_file(hash_path)?;
+    }
+    Ok(())
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "integrity {}",
+        Colour::Fixed(11).on(Colour::Fixed(236)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn check_hashes() -> NullResult {
+        let dir = Path::new("testfiles-integrity");
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir)?;
+        let path = dir.join("cache.json");
+        assert_eq!(Check::Missing, check(&path)?);
+        write(&path, b"{}")?;
+        assert_eq!(Check::Matches, check(&path)?);
+        fs::write(&path, b"{\"x\"")?;
+        assert_eq!(Check::Mismatch, check(&path)?);
+        record(&path)?;
+        assert_eq!(Check::Matches, check(&path)?);
+        forget(&path)?;
+        assert_eq!(Check::Missing, check(&path)?);
+        Ok(())
+    }
+}
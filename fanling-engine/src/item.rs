@@ -4,6 +4,7 @@ file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 /*! implements `Items`. */
 
+use crate::private::Passphrase;
 use crate::shared::{FLResult, FanlingError, NullResult, Tracer};
 use crate::world::{ActionResponse, World};
 use crate::Action;
@@ -40,6 +41,8 @@ pub enum SpecialKind {
     Parent,
     /// it can be the 'context' of an item
     Context,
+    /// it is a template for new items
+    Template,
 }
 impl SpecialKind {
     pub fn as_bitmap(self) -> SpecialKinds {
@@ -73,6 +76,9 @@ impl SpecialKinds {
     pub fn add(&mut self, sk: SpecialKind) {
         self.set_bit(sk as usize, true);
     }
+    pub fn remove(&mut self, sk: SpecialKind) {
+        self.set_bit(sk as usize, false);
+    }
 }
 
 /** data is organised into [`Item`]s */
@@ -103,6 +109,10 @@ impl Item {
     pub fn classify(&self) -> String {
         self.base.get_classify()
     }
+    /** set the classification (eg "archived", see [`crate::retention`]) */
+    pub fn set_classify(&mut self, classify: &str) {
+        self.base.classify = classify.to_owned();
+    }
     /** SpecialKinds */
     pub fn specials(&self) -> SpecialKinds {
         self.base.get_specials()
@@ -117,6 +127,20 @@ impl Item {
         self.base.can_be_parent()
     }
 
+    /** the actions that can be done to the item now, in the order
+    they should appear in a menu. Only a few are possible until a
+    locked private item has been unlocked, or for a read-only item. */
+    pub fn available_actions(&self) -> Vec<AvailableAction> {
+        if self.is_locked() || self.is_read_only() {
+            vec![
+                AvailableAction::new(&self.base, Action::History, "History", "🕓"),
+                AvailableAction::new(&self.base, Action::Delete, "Delete", "🗑"),
+            ]
+        } else {
+            self.base
+                .available_actions(self.data.available_actions(&self.base))
+        }
+    }
     /** do an item action (from the user interface) */
     pub fn do_action(
         &mut self,
@@ -125,12 +149,74 @@ impl Item {
         world: &mut World,
     ) -> fanling_interface::ResponseResult {
         match &action {
-            Action::Show => self.for_show(world),
+            Action::Show | Action::ShowHeading(_) => self.for_show(world),
             Action::Edit => self.for_edit(true, world),
+            Action::History => crate::history::show_history(self, world),
+            Action::Diff { from, to } => crate::diff::show_diff(self, from, to, world),
+            Action::ShowArchivedPage(path) => {
+                crate::bookmark::show_archived_page(self, path, world)
+            }
+            Action::AvailableActions => {
+                let actions = self.available_actions();
+                let mut resp = fanling_interface::Response::new();
+                resp.add_tag("actions", &serde_json::to_string(&actions)?);
+                #[cfg(test)]
+                resp.set_test_data(
+                    "actions",
+                    &actions
+                        .iter()
+                        .map(|a| a.label.clone())
+                        .collect::<Vec<String>>()
+                        .join(","),
+                );
+                Ok(resp)
+            }
+            Action::UnlockItem(passphrase) => {
+                let passphrase = Passphrase::new(passphrase);
+                match self.unlock(&passphrase, world) {
+                    Ok(()) => self.for_show(world),
+                    Err(e) => {
+                        trace(&format!("could not unlock: {:?}", e));
+                        let mut resp = crate::private::ask_item_passphrase(
+                            &self.ident(),
+                            &self.type_name(),
+                            &self.description(),
+                            "Wrong passphrase, please try again.",
+                        )?;
+                        resp.set_error();
+                        Ok(resp)
+                    }
+                }
+            }
+            Action::ExportItem(format) if !self.is_locked() => {
+                crate::export::export(self, *format, world)
+            }
+            _ if self.is_locked() || self.is_read_only() => self.for_show(world),
+            Action::Restore { id, confirmed } => {
+                crate::restore::restore(self, id, *confirmed, world)
+            }
+            Action::TogglePin => {
+                self.base.set_pinned(!self.base.is_pinned());
+                world.persist_change(self)?;
+                self.for_show(world)
+            }
+            Action::ToggleFavourite => {
+                self.base.set_favourite(!self.base.is_favourite());
+                world.persist_change(self)?;
+                self.for_show(world)
+            }
+            Action::ShowRename => crate::alias::show_rename(self, "", world),
+            Action::CloseProject(children) => crate::project::close_project(self, *children, world),
+            Action::Print => crate::print::show_print(self, world),
             _ => {
+                let verb = action.verb();
+                /* any items made by the action are in the same commit */
+                world.begin_batch();
                 let res = self.data.do_action(&mut self.base, action, world);
                 trace("persisting change for edit action");
-                world.persist_change(self)?;
+                let persisted = world.persist_change_as(self, verb);
+                world.end_batch()?;
+                persisted?;
                 res
             }
         }
@@ -158,12 +244,27 @@ impl Item {
         //   self.data.fix_data(&yaml, &mut self.base, world)?;
         Ok(())
     }
-    /** display for editing */
+    /** display for editing (with the values from a draft of unsaved edits, if there is one) */
     pub fn for_edit(
         &mut self,
         is_for_update: bool,
         world: &mut World,
     ) -> fanling_interface::ResponseResult {
+        let key = crate::draft::key(&self.base.ident, &self.type_name());
+        if let Some(draft) = world.drafts().get(&key).cloned() {
+            /* edit a copy, so that the item itself is unchanged until it is saved */
+            let mut base = self.base.clone();
+            let mut data = self.data.fanling_clone()?;
+            match data.set_data(&draft.vals, world) {
+                Ok(()) => {
+                    let mut resp = data.for_edit(&mut base, is_for_update, world)?;
+                    crate::draft::add_banner(&mut resp, &self.base.ident, &draft, world)?;
+                    fanling_trace!("for edit from draft");
+                    return Ok(resp);
+                }
+                Err(e) => trace(&format!("could not restore draft for {}: {:?}", &key, e)),
+            }
+        }
         let rr = self.data.for_edit(&mut self.base, is_for_update, world);
         fanling_trace!("for edit");
         rr
@@ -174,10 +275,18 @@ impl Item {
         fanling_trace!("for show");
         rr
     }
+    /** the HTML for printing (see [`crate::print`]) */
+    pub fn for_print(&mut self, world: &mut World) -> FLResult<String> {
+        self.data.for_print(&mut self.base, world)
+    }
     /** serialise the Item to YAML */
     pub fn to_yaml(&self) -> Result<Vec<u8>, FanlingError> {
         self.data.to_yaml(&self.base)
     }
+    /** serialise the Item to JSON (see [`crate::json`]) */
+    pub fn to_json(&self) -> FLResult<serde_json::Value> {
+        self.data.to_json(&self.base)
+    }
     /** can be turned into an ident */
     pub fn descr_for_ident(&self) -> String {
         self.data.descr_for_ident()
@@ -227,6 +336,99 @@ impl Item {
     pub fn get_sort(&self) -> String {
         self.base.sort.clone()
     }
+    /** set the key the item is sorted by among its siblings (see [`crate::ordering`]) */
+    pub fn set_sort(&mut self, sort: &str) {
+        self.base.sort = sort.to_owned();
+    }
+    /** set the position within a board column */
+    pub fn set_board_order(&mut self, board_order: i64) {
+        self.base.set_board_order(board_order)
+    }
+    /** whether the item is a template for new items */
+    pub fn is_template(&self) -> bool {
+        self.base.is_template()
+    }
+    /** set whether the item is a template for new items */
+    pub fn set_template(&mut self, is_template: bool) {
+        self.base.set_template(is_template)
+    }
+    /** adjust a new item made from the template `template` */
+    pub fn instantiated(&mut self, template: &Ident, world: &mut World) -> NullResult {
+        self.data.instantiated(template, world)
+    }
+    /** whether the item is private (protected by its own passphrase) */
+    pub fn is_private(&self) -> bool {
+        self.base.private
+    }
+    /** the idents the item had before it was renamed */
+    pub fn aliases(&self) -> &[Ident] {
+        self.base.aliases()
+    }
+    /** the passphrase of a private item, if it has been given */
+    pub fn passphrase(&self) -> Option<&Passphrase> {
+        self.base.passphrase.as_ref()
+    }
+    /** whether the item is private and its passphrase has not been given */
+    pub fn is_locked(&self) -> bool {
+        self.data.is_locked()
+    }
+    /** whether the item cannot be changed (as for a type that is not known) */
+    pub fn is_read_only(&self) -> bool {
+        self.data.is_read_only()
+    }
+    /** the tags of the item */
+    pub fn tags(&self) -> Vec<String> {
+        self.data.tags()
+    }
+    /** set the tags of the item, returning false if its type has none */
+    pub fn set_tags(&mut self, tags: &[String], world: &mut World) -> FLResult<bool> {
+        self.data.set_tags(tags, world)
+    }
+    /** the idents of the items blocking this one (see [`crate::blocking`]) */
+    pub fn blocked_by(&self) -> Vec<Ident> {
+        self.data.blocked_by()
+    }
+    /** when the item is due, if it has a due date */
+    pub fn due(&self) -> Option<chrono::NaiveDateTime> {
+        self.data.due()
+    }
+    /** when work on the item can start, if it is scheduled */
+    pub fn starts(&self) -> Option<chrono::NaiveDateTime> {
+        self.data.starts()
+    }
+    /** the periods when the item's timer was running (see [`crate::timing`]) */
+    pub fn time_records(&self) -> Vec<crate::timing::TimeRecord> {
+        self.data.time_records()
+    }
+    /** stop the item's timer, returning whether it was running */
+    pub fn stop_timer(&mut self, now: chrono::NaiveDateTime) -> bool {
+        self.data.stop_timer(now)
+    }
+    /** the highlights in the item (see [`crate::highlight`]) */
+    pub fn highlights(&self) -> Vec<crate::highlight::Highlight> {
+        self.data.highlights()
+    }
+    /** make the item a locked private item, from its stored values */
+    pub fn lock(&mut self, values: &serde_yaml::Value) -> NullResult {
+        self.data = Box::new(crate::private::Locked::from_values(values)?);
+        Ok(())
+    }
+    /** decrypt a locked private item using its passphrase */
+    pub fn unlock(&mut self, passphrase: &Passphrase, world: &mut World) -> NullResult {
+        if !self.is_locked() {
+            return Ok(());
+        }
+        let (_base, stored) = world.get_item_parts(&self.ident())?;
+        let (_base, values) = crate::private::open_item(&stored, passphrase)?;
+        let mut unlocked = self.base.item_type.deref().borrow().make_raw();
+        unlocked.set_from_yaml(&values, world)?;
+        unlocked.fix_data(&values, world)?;
+        self.data = unlocked.data;
+        self.base.passphrase = Some(passphrase.clone());
+        world.remember_passphrase(&self.ident(), passphrase);
+        trace(&format!("unlocked {}", self.ident()));
+        Ok(())
+    }
     /** clone an item */
     pub fn clone_from(&mut self, other: &Item) -> NullResult {
         self.base.clone_from(&other.base);
@@ -307,6 +509,18 @@ pub struct ItemBase {
     when_created: NaiveDateTime,
     /** when the Item was last modified */
     when_modified: NaiveDateTime,
+    /** position of the Item within its column on a board (0 if it has not been placed) */
+    board_order: i64,
+    /** whether the Item is private (encrypted with its own passphrase) */
+    private: bool,
+    /** the passphrase of a private Item, once given -- do not save this to the repo */
+    passphrase: Option<Passphrase>,
+    /** whether the Item is pinned to the home dashboard */
+    pinned: bool,
+    /** whether the Item is a favourite */
+    favourite: bool,
+    /** the idents the Item had before it was renamed (see [`crate::alias`]) */
+    aliases: Vec<Ident>,
 }
 impl ItemBase {
     fn new(item_type: ItemTypeRef) -> Self {
@@ -321,11 +535,114 @@ impl ItemBase {
             targeted: false,
             when_created: naive_date_time,
             when_modified: naive_date_time,
+            board_order: 0,
+            private: false,
+            passphrase: None,
+            pinned: false,
+            favourite: false,
+            aliases: vec![],
         }
     }
     pub fn get_ident(&self) -> Ident {
         self.ident.clone()
     }
+    /** the actions that can be done to any unlocked item now, with
+    those for its type (`for_type`) after editing */
+    pub fn available_actions(&self, for_type: Vec<AvailableAction>) -> Vec<AvailableAction> {
+        let mut actions = vec![AvailableAction::new(self, Action::Edit, "Edit", "✎")];
+        actions.extend(for_type);
+        actions.push(AvailableAction::new(self, Action::Delete, "Delete", "🗑"));
+        actions.push(AvailableAction::new(self, Action::History, "History", "🕓"));
+        if self.is_template() {
+            actions.push(AvailableAction::new(
+                self,
+                Action::Instantiate,
+                "Use template",
+                "📋",
+            ));
+        }
+        actions.push(AvailableAction::new(self, Action::Clone, "Clone", "⧉"));
+        actions.push(AvailableAction::new(self, Action::Print, "Print", "🖨"));
+        if !self.private {
+            actions.push(AvailableAction::new(
+                self,
+                Action::ShowRename,
+                "Rename",
+                "🏷",
+            ));
+            actions.push(AvailableAction::new(
+                self,
+                Action::Clip {
+                    ident: self.get_ident(),
+                    field: "".to_owned(),
+                    blocks: vec![],
+                    cut: false,
+                },
+                "Copy",
+                "⎘",
+            ));
+            actions.push(AvailableAction::new(
+                self,
+                Action::Clip {
+                    ident: self.get_ident(),
+                    field: "".to_owned(),
+                    blocks: vec![],
+                    cut: true,
+                },
+                "Cut",
+                "✂",
+            ));
+            actions.push(AvailableAction::new(
+                self,
+                Action::Paste {
+                    ident: self.get_ident(),
+                    field: "".to_owned(),
+                    at: None,
+                },
+                "Paste",
+                "📥",
+            ));
+            actions.push(AvailableAction::new(
+                self,
+                Action::ExportItem(crate::export::Format::Markdown),
+                "Copy markdown",
+                "📝",
+            ));
+            actions.push(AvailableAction::new(
+                self,
+                Action::ExportItem(crate::export::Format::Html),
+                "Export HTML",
+                "🌐",
+            ));
+            if crate::export::Format::Pdf.is_available() {
+                actions.push(AvailableAction::new(
+                    self,
+                    Action::ExportItem(crate::export::Format::Pdf),
+                    "Export PDF",
+                    "📄",
+                ));
+            }
+        }
+        actions.push(if self.pinned {
+            AvailableAction::new(self, Action::TogglePin, "Unpin", "📌")
+        } else {
+            AvailableAction::new(self, Action::TogglePin, "Pin", "📌")
+        });
+        actions.push(if self.favourite {
+            AvailableAction::new(self, Action::ToggleFavourite, "Unfavourite", "★")
+        } else {
+            AvailableAction::new(self, Action::ToggleFavourite, "Favourite", "☆")
+        });
+        actions
+    }
+    /** the name of the item type */
+    pub fn type_name(&self) -> String {
+        self.item_type.deref().borrow().ident()
+    }
+    /** whether the item is private */
+    pub fn is_private(&self) -> bool {
+        self.private
+    }
     pub fn resolve_parent(&mut self, world: &mut World) -> FLResult<Option<ItemRef>> {
         Ok(match &mut self.parent {
             Some(p) => Some(world.resolve_link(p)?),
@@ -348,6 +665,18 @@ impl ItemBase {
     pub fn can_be_context(&self) -> bool {
         self.special.bit(SpecialKind::Context as usize)
     }
+    /** whether the item is a template for new items */
+    pub fn is_template(&self) -> bool {
+        self.special.bit(SpecialKind::Template as usize)
+    }
+    /** set whether the item is a template for new items */
+    pub fn set_template(&mut self, is_template: bool) {
+        if is_template {
+            self.special.add(SpecialKind::Template);
+        } else {
+            self.special.remove(SpecialKind::Template);
+        }
+    }
     /** whether the item is targeted */
     pub fn get_targeted(&self) -> bool {
         self.targeted
@@ -384,10 +713,36 @@ impl ItemBase {
         if base.can_be_context {
             self.special.add(SpecialKind::Context);
         }
+        if base.is_template {
+            self.special.add(SpecialKind::Template);
+        }
         // do not copy targeted
         self.when_created = base.when_created;
         let naive_date_time = Utc::now().naive_utc();
         self.when_modified = naive_date_time;
+        /* the edit forms do not include the board order, so keep any existing order */
+        if base.board_order != 0 {
+            self.board_order = base.board_order;
+        }
+        self.private = base.is_private;
+        /* the passphrase only needs to be given when the item is made private */
+        if !base.passphrase.is_empty() {
+            self.passphrase = Some(base.passphrase.clone());
+        }
+        if !self.private {
+            self.passphrase = None;
+        }
+        /* nor do they include the pin and favourite flags, which are toggled by actions */
+        if base.is_pinned {
+            self.pinned = true;
+        }
+        if base.is_favourite {
+            self.favourite = true;
+        }
+        /* or the aliases, which are added by renaming */
+        if !base.aliases.is_empty() {
+            self.aliases = base.aliases.clone();
+        }
         trace("set base from serde.");
         Ok(())
     }
@@ -429,6 +784,34 @@ impl ItemBase {
     pub fn get_sort(&self) -> String {
         self.sort.clone()
     }
+    /** position within a board column */
+    pub fn get_board_order(&self) -> i64 {
+        self.board_order
+    }
+    /** set the position within a board column */
+    pub fn set_board_order(&mut self, board_order: i64) {
+        self.board_order = board_order;
+    }
+    /** whether the item is pinned to the home dashboard */
+    pub fn is_pinned(&self) -> bool {
+        self.pinned
+    }
+    /** pin the item to the home dashboard, or unpin it */
+    pub fn set_pinned(&mut self, pinned: bool) {
+        self.pinned = pinned;
+    }
+    /** whether the item is a favourite */
+    pub fn is_favourite(&self) -> bool {
+        self.favourite
+    }
+    /** make the item a favourite, or not */
+    pub fn set_favourite(&mut self, favourite: bool) {
+        self.favourite = favourite;
+    }
+    /** the idents the item had before it was renamed */
+    pub fn aliases(&self) -> &[Ident] {
+        &self.aliases
+    }
     /** get all children with open status */
     pub fn get_open_children(&self, world: &World) -> FLResult<ItemListEntryList> {
         world.search_open_children(&self.ident)
@@ -443,12 +826,15 @@ impl ItemBase {
         self.targeted = other.targeted;
         self.when_created = other.when_created;
         self.when_modified = other.when_modified;
+        self.private = other.private;
+        self.passphrase = other.passphrase.clone();
     }
 }
 
 /** interpret the serialised data as YAML and set the [ItemBase]  */
 pub fn split_data_parts(data: &[u8]) -> FLResult<(ItemBaseForSerde, serde_yaml::Value)> {
-    let serde_value: serde_yaml::Value = dump_fanling_error!(serde_yaml::from_slice(data));
+    let mut serde_value: serde_yaml::Value = dump_fanling_error!(serde_yaml::from_slice(data));
+    crate::migrate::upgrade(&mut serde_value);
     let base: ItemBaseForSerde = dump_fanling_error!(serde_yaml::from_value(serde_value.clone()));
     Ok((base, serde_value))
 }
@@ -472,6 +858,10 @@ pub struct ItemBaseForSerde {
     #[serde(skip_serializing_if = "Not::not")]
     #[serde(default)]
     pub can_be_context: bool,
+    /**  represent the ItemBase template field */
+    #[serde(skip_serializing_if = "Not::not")]
+    #[serde(default)]
+    pub is_template: bool,
     /** sort key */
     #[serde(skip_serializing_if = "std::string::String::is_empty")]
     #[serde(default)]
@@ -493,9 +883,40 @@ pub struct ItemBaseForSerde {
     #[serde(default = "ItemBaseForSerde::now")]
     #[serde(deserialize_with = "ItemBaseForSerde::deserialize")]
     pub when_modified: NaiveDateTime,
+    /** position within a board column (0 if not placed) */
+    #[serde(skip_serializing_if = "ItemBaseForSerde::is_zero")]
+    #[serde(default)]
+    pub board_order: i64,
+    /** whether the item is private */
+    #[serde(skip_serializing_if = "Not::not")]
+    #[serde(default)]
+    pub is_private: bool,
+    /** passphrase given when making the item private -- not serialised to the repo */
+    #[serde(skip_serializing)]
+    #[serde(default)]
+    pub passphrase: Passphrase,
+    /** the user has confirmed saving despite any warnings -- not serialised to the repo */
+    #[serde(skip_serializing)]
+    #[serde(default)]
+    pub confirmed: bool,
     /** do not use */
     #[serde(skip)]
     pub closed: bool,
+    /** the version of the format of the YAML (see [`crate::migrate`]) */
+    #[serde(default)]
+    pub schema_version: u32,
+    /** whether the item is pinned to the home dashboard */
+    #[serde(skip_serializing_if = "Not::not")]
+    #[serde(default)]
+    pub is_pinned: bool,
+    /** whether the item is a favourite */
+    #[serde(skip_serializing_if = "Not::not")]
+    #[serde(default)]
+    pub is_favourite: bool,
+    /** the idents the item had before it was renamed */
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub aliases: Vec<Ident>,
 }
 impl ItemBaseForSerde {
     /** default classification */
@@ -514,6 +935,13 @@ impl ItemBaseForSerde {
     pub fn now() -> NaiveDateTime {
         Utc::now().naive_utc()
     }
+    /** is zero (for serialisation) */
+    pub fn is_zero_u32(n: &u32) -> bool {
+        *n == 0
+    }
+    pub fn is_zero(n: &i64) -> bool {
+        *n == 0
+    }
     /** false value */
     pub fn say_false() -> bool {
         false
@@ -529,13 +957,22 @@ impl ItemBaseForSerde {
             parent,
             can_be_parent: (ib.special).clone().has(SpecialKind::Parent),
             can_be_context: (ib.special).clone().has(SpecialKind::Context),
+            is_template: (ib.special).clone().has(SpecialKind::Template),
             sort: ib.sort.clone(),
             classify: ib.classify.clone(),
             //    special: ib.special.clone().val(),
             targeted: ib.targeted,
             when_created: ib.when_created,
             when_modified: naive_date_time,
+            board_order: ib.board_order,
+            is_private: ib.private,
+            passphrase: Passphrase::default(),
+            confirmed: false,
             closed: false,
+            schema_version: crate::migrate::SCHEMA_VERSION,
+            is_pinned: ib.pinned,
+            is_favourite: ib.favourite,
+            aliases: ib.aliases.clone(),
         })
     }
     /** deserialise date/time from various formats (tries different formats until it finds one that works) */
@@ -588,13 +1025,22 @@ impl Default for ItemBaseForSerde {
             parent: None,
             can_be_parent: false,
             can_be_context: false,
+            is_template: false,
             sort: "".to_owned(),
             classify: "normal".to_owned(),
             // special: 0,
             targeted: false,
             when_created: naive_date_time,
             when_modified: naive_date_time,
+            board_order: 0,
+            is_private: false,
+            passphrase: Passphrase::default(),
+            confirmed: false,
             closed: false,
+            schema_version: crate::migrate::SCHEMA_VERSION,
+            is_pinned: false,
+            is_favourite: false,
+            aliases: vec![],
         }
     }
 }
@@ -615,6 +1061,27 @@ pub trait ItemData: Debug {
     ) -> fanling_interface::ResponseResult;
     /** convert the Item to YAML */
     fn to_yaml(&self, base: &ItemBase) -> Result<Vec<u8>, FanlingError>;
+    /** the HTML of the Item laid out for printing, without the
+    controls of the app (see [`crate::print`]). By default it is
+    the description, then the text fields and any entries. */
+    fn for_print(&mut self, base: &mut ItemBase, world: &mut World) -> FLResult<String> {
+        let values: serde_yaml::Value = serde_yaml::from_slice(&self.to_yaml(base)?)?;
+        Ok(crate::transclude::render(
+            world,
+            &base.get_ident(),
+            &crate::export::values_markdown(&self.description(), &values),
+        ))
+    }
+    /** statistics about the Item: the length of its text (by default
+    the text fields and any entries) and when it was last changed */
+    fn stats(&self, base: &ItemBase, world: &World) -> FLResult<crate::stats::ItemStats> {
+        let values: serde_yaml::Value = serde_yaml::from_slice(&self.to_yaml(base)?)?;
+        crate::stats::item_stats(
+            world,
+            &base.get_ident(),
+            &crate::export::body_markdown(&values),
+        )
+    }
     /** is the  [`Item`] open? */
     fn is_open(&self) -> bool;
     /**  is the  [`Item`] ready? */
@@ -646,6 +1113,88 @@ pub trait ItemData: Debug {
         //    _json_value: serde_json::value::Value,
         world: &mut World,
     ) -> fanling_interface::ResponseResult;
+    /** adjust the data of a new item made from the template `template` (nothing by default) */
+    fn instantiated(&mut self, _template: &Ident, _world: &mut World) -> NullResult {
+        Ok(())
+    }
+    /** whether this is a private item whose passphrase has not been given */
+    fn is_locked(&self) -> bool {
+        false
+    }
+    /** whether the item cannot be changed (as for a type that is not known) */
+    fn is_read_only(&self) -> bool {
+        false
+    }
+    /** the tags of the item, for types that have them (none by default) */
+    fn tags(&self) -> Vec<String> {
+        vec![]
+    }
+    /** set the tags, for types that have them, returning whether the type has them (it does not by default) */
+    fn set_tags(&mut self, _tags: &[String], _world: &mut World) -> FLResult<bool> {
+        Ok(false)
+    }
+    /** the idents of the items blocking this one, for types that can be blocked (none by default) */
+    fn blocked_by(&self) -> Vec<Ident> {
+        vec![]
+    }
+    /** when it is due, for types that have due dates (none by default) */
+    fn due(&self) -> Option<chrono::NaiveDateTime> {
+        None
+    }
+    /** when work on it can start, for types that are scheduled (none by default) */
+    fn starts(&self) -> Option<chrono::NaiveDateTime> {
+        None
+    }
+    /** the periods when the timer was running, for types that can be timed (none by default) */
+    fn time_records(&self) -> Vec<crate::timing::TimeRecord> {
+        vec![]
+    }
+    /** stop the timer, returning whether it was running (it never is by default) */
+    fn stop_timer(&mut self, _now: chrono::NaiveDateTime) -> bool {
+        false
+    }
+    /** the highlights in the item, for types that have them (none by default) */
+    fn highlights(&self) -> Vec<crate::highlight::Highlight> {
+        vec![]
+    }
+    /** the actions for this type of item that can be done now (none by default) */
+    fn available_actions(&self, _base: &ItemBase) -> Vec<AvailableAction> {
+        vec![]
+    }
+    /** convert the Item to JSON, with the same fields as its YAML */
+    fn to_json(&self, base: &ItemBase) -> FLResult<serde_json::Value> {
+        Ok(serde_yaml::from_slice(&self.to_yaml(base)?)?)
+    }
+}
+/** an action that can be done to an item now, with what to show for it in a menu */
+#[derive(Debug, Clone, Serialize)]
+pub struct AvailableAction {
+    /** the action, as sent by the user interface */
+    pub action: Action,
+    /** the item that the action is to be done to */
+    pub ident: Ident,
+    /** the type of that item */
+    pub type_name: String,
+    /** what the user is shown */
+    pub label: String,
+    /** a symbol to show with the label (or instead of it) */
+    pub icon: String,
+}
+impl AvailableAction {
+    /** an action on the item with the base */
+    pub fn new(base: &ItemBase, action: Action, label: &str, icon: &str) -> Self {
+        Self {
+            action,
+            ident: base.get_ident(),
+            type_name: base.item_type.deref().borrow().ident(),
+            label: label.to_owned(),
+            icon: icon.to_owned(),
+        }
+    }
+    /** the action as JSON, for the `invoke` function of the page */
+    pub fn action_json(&self) -> String {
+        serde_json::to_string(&self.action).unwrap_or_default()
+    }
 }
 /** each  [`Item`] has an `ItemType`.
 
@@ -742,6 +1291,17 @@ impl ItemType {
                     None => {
                         // item has been added in remote
                         let (tib, tv) = split_data_parts(&t.data)?;
+                        if crate::private::is_sealed(&tv) {
+                            /* a private item can not be read, so add it as it is */
+                            changes.push(taipo_git_control::Change::new(
+                                taipo_git_control::ObjectOperation::Modify(
+                                    String::from_utf8_lossy(&t.data).to_string(),
+                                ),
+                                t.path.clone(),
+                                "resolve conflict".to_owned(),
+                            ));
+                            return Ok(());
+                        }
                         let type_name = tib.type_name.clone();
                         let item_type_rcrc = world.get_item_type(type_name.to_string())?;
                         let item_type = item_type_rcrc.deref().borrow();
@@ -767,6 +1327,12 @@ impl ItemType {
                         let (_tib, tv) = split_data_parts(&t.data)?;
                         match &conflict.ancestor {
                             None => Ok(()), // we have created it, keep
+                            Some(_a)
+                                if crate::private::is_sealed(&ov)
+                                    || crate::private::is_sealed(&tv) =>
+                            {
+                                Ok(()) // private items can not be merged, keep ours
+                            }
                             Some(a) => {
                                 // item modified locally or in remote
                                 let (_aib, av) = split_data_parts(&a.data)?;
@@ -827,17 +1393,41 @@ pub trait ItemTypePolicy: Debug {
     ) -> ActionResponse;
     /** get item data from serde value */
     fn from_yaml(&self, values: &Value, world: &mut World) -> FLResult<Box<dyn ItemData>>;
+    /** get item data from JSON, with the same fields as the YAML */
+    fn from_json(
+        &self,
+        json: &serde_json::Value,
+        world: &mut World,
+    ) -> FLResult<Box<dyn ItemData>> {
+        self.from_yaml(&serde_yaml::to_value(json)?, world)
+    }
 }
 /** simple enum, each [`ItemKind`] has an [`ItemType`]*/
 #[derive(PartialEq, Eq, Hash, Debug, Copy, Clone)]
 pub enum ItemKind {
     Simple,
     Task,
+    Contact,
+    Checklist,
+    Event,
+    Snippet,
+    Meeting,
+    Bookmark,
+    Reference,
+    Habit,
+    Project,
+    /** an item of a type that is not known (see [`crate::foreign`]) */
+    Foreign,
+    /** an item of a type added by a plugin, with its type name (see [`crate::plugin`]) */
+    Plugin(&'static str),
 }
 impl fmt::Display for ItemKind {
-    /** display an ItemType for debugging */
+    /** display an ItemType for debugging (and as its type name) */
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            ItemKind::Plugin(name) => write!(f, "{}", name),
+            _ => write!(f, "{:?}", self),
+        }
     }
 }
 /** a collection of [`ItemType`]s */
@@ -1004,6 +1594,19 @@ impl ItemListEntryList {
             self.final_adjust_level = "</ul>".repeat(ile.level.try_into().expect("bad???"));
         }
     }
+    /** the entries from `offset`: at least `limit` of them (if there
+    are that many), but not stopping part way through the children of
+    an entry, so that the page can be shown on its own. The offset of
+    the next page is also returned, if there is one. */
+    pub fn page(&self, offset: usize, limit: usize) -> (Self, Option<usize>) {
+        let len = self.entries.len();
+        let mut end = (offset + limit.max(1)).min(len);
+        while end < len && self.entries[end].level > 0 {
+            end += 1;
+        }
+        let entries = self.entries.get(offset..end).unwrap_or(&[]).to_vec();
+        (Self::from_vec(entries), if end < len { Some(end) } else { None })
+    }
     /** whether list has any entries */
     pub fn has_entries(&self) -> bool {
         !self.entries.is_empty()
@@ -1202,6 +1805,10 @@ pub struct NewBaseTemplate {
     pub can_be_parent: bool,
     pub sort: String,
     pub can_be_context: bool,
+    pub is_template: bool,
+    pub is_private: bool,
+    /** translates the text of the form */
+    pub i18n: Rc<crate::i18n::Translator>,
 }
 impl NewBaseTemplate {
     pub fn from_base(
@@ -1211,6 +1818,7 @@ impl NewBaseTemplate {
     ) -> FLResult<Self> {
         let parents = base.get_parents(world)?;
         let op_name = if is_for_update { "Update" } else { "Create" };
+        let i18n = world.i18n();
         let ident = if is_for_update {
             base.get_ident()
         } else {
@@ -1218,13 +1826,16 @@ impl NewBaseTemplate {
         };
         Ok(NewBaseTemplate {
             next_op: op_name.to_string(),
-            next_op_name: op_name.to_string(),
+            next_op_name: i18n.tr(op_name),
             has_ident: is_for_update,
             ident,
             parent: parents,
             can_be_parent: base.can_be_parent(),
             sort: base.get_sort(),
             can_be_context: base.can_be_context(),
+            is_template: base.is_template(),
+            is_private: base.is_private(),
+            i18n,
         })
     }
 }
@@ -1237,6 +1848,17 @@ pub struct ShowBaseTemplate {
     pub children: ItemListEntryList,
     pub has_children: bool,
     pub can_be_context: bool,
+    pub is_template: bool,
+    /** the actions to show buttons for */
+    pub actions: Vec<AvailableAction>,
+    /** the classification, such as "archived" (see [`crate::retention`]) */
+    pub classify: String,
+    /** whether the buttons for changing the item are hidden (see [`crate::read_only`]) */
+    pub read_only: bool,
+    /** the length of the text and the latest changes (see [`ItemData::stats`]) */
+    pub stats: crate::stats::ItemStats,
+    /** translates the text of the page */
+    pub i18n: Rc<crate::i18n::Translator>,
 }
 impl ShowBaseTemplate {
     /** fill in fields */
@@ -1252,7 +1874,30 @@ impl ShowBaseTemplate {
             children,
             has_children,
             can_be_context: base.can_be_context(),
-        })
+            is_template: base.is_template(),
+            actions: vec![],
+            classify: base.get_classify(),
+            read_only: world.is_read_only(),
+            stats: crate::stats::ItemStats::default(),
+            i18n: world.i18n(),
+        }
+        .with_actions(base, vec![]))
+    }
+    /** include the actions for the type of item (see [`ItemData::available_actions`]),
+    leaving out those that change it if it is read-only */
+    pub fn with_actions(mut self, base: &ItemBase, for_type: Vec<AvailableAction>) -> Self {
+        let read_only = self.read_only;
+        self.actions = base
+            .available_actions(for_type)
+            .into_iter()
+            .filter(|a| !read_only || crate::read_only::allows(&a.action))
+            .collect();
+        self
+    }
+    /** include the statistics about the item */
+    pub fn with_stats(mut self, stats: crate::stats::ItemStats) -> Self {
+        self.stats = stats;
+        self
     }
 }
 
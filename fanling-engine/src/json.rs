@@ -0,0 +1,236 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! exporting and importing items as JSON, for other tools that would
+rather not read YAML.
+
+Each item is given as a JSON object with the same fields as its YAML
+(see [`crate::item::ItemData::to_json`]). An export is a JSON array of
+some items (given by their idents), of the items of some types, or of
+all of them; it is written to the export directory and shown.
+
+Importing takes such an array (or a single object) and adds the items
+that are not already in the repository, keeping their idents. An item
+whose ident is already used is skipped: as already present if it is the
+same, or as a conflict if it differs. Items without an ident or a type
+are skipped as invalid. */
+use crate::item::{split_data_parts, ItemListEntry, ItemListEntryList};
+use crate::shared::{FLResult, FanlingError, NullResult};
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use std::fs;
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** the items given by their idents (or else those of the types, or
+else all of them), each as a JSON object */
+pub fn items_json(
+    world: &mut World,
+    idents: &[String],
+    types: &[String],
+) -> FLResult<Vec<serde_json::Value>> {
+    let mut items = vec![];
+    if !idents.is_empty() {
+        for ident in idents {
+            let item_ref = world.get_item(ident.clone(), "Simple".to_owned())?;
+            items.push(item_ref.borrow().to_json()?);
+        }
+        return Ok(items);
+    }
+    for (_ident, values) in world.stored_values()? {
+        let type_name = values.get("type").and_then(|t| t.as_str()).unwrap_or("");
+        if types.is_empty() || types.iter().any(|t| t == type_name) {
+            items.push(serde_json::to_value(&values)?);
+        }
+    }
+    Ok(items)
+}
+
+/** a type of item, and whether it is chosen */
+struct TypeChoice {
+    name: &'static str,
+    chosen: bool,
+}
+/** template data for exporting and importing JSON */
+#[derive(Template)]
+#[template(path = "json.html", print = "none")]
+struct JsonTemplate {
+    message: String,
+    types: Vec<TypeChoice>,
+    exported: String,
+    count: usize,
+}
+/** show the form for exporting and importing JSON, with what was exported (if anything) */
+fn show_json(
+    message: &str,
+    types: &[String],
+    exported: &str,
+    count: usize,
+) -> fanling_interface::ResponseResult {
+    let t = JsonTemplate {
+        message: message.to_owned(),
+        types: crate::kinds::kinds()
+            .iter()
+            .map(|k| TypeChoice {
+                name: k.name,
+                chosen: types.iter().any(|c| c == k.name),
+            })
+            .collect(),
+        exported: exported.to_owned(),
+        count,
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    {
+        resp.set_test_data("message", message);
+        resp.set_test_data("count", &format!("{}", count));
+    }
+    Ok(resp)
+}
+/** write the items (see [`items_json`]) as a JSON array to the export directory, and show it */
+pub fn export_json(
+    world: &mut World,
+    idents: &[String],
+    types: &[String],
+) -> fanling_interface::ResponseResult {
+    let items = items_json(world, idents, types)?;
+    let exported = serde_json::to_string_pretty(&items)?;
+    let dir = world.export_dir().to_path_buf();
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!(
+        "fanling-{}.json",
+        chrono::Local::today()
+            .naive_local()
+            .format(crate::format::ISO_DATE_FORMAT)
+    ));
+    fs::write(&path, &exported)?;
+    trace(&format!("exported {} items to {:?}", items.len(), &path));
+    let resp = show_json(
+        &format!("Exported to {}", path.display()),
+        types,
+        &exported,
+        items.len(),
+    )?;
+    #[cfg(test)]
+    let resp = {
+        let mut resp = resp;
+        resp.set_test_data("path", &path.to_string_lossy());
+        resp
+    };
+    Ok(resp)
+}
+
+/** what importing did */
+#[derive(Debug, Default, PartialEq)]
+struct Skipped {
+    /** already in the repository, the same */
+    present: usize,
+    /** the ident is used by a different item */
+    conflicts: Vec<String>,
+    /** without an ident or a type */
+    invalid: usize,
+}
+/** the items in the text: a JSON array of objects, or a single object */
+fn parse(text: &str) -> FLResult<Vec<serde_json::Value>> {
+    match serde_json::from_str(text)? {
+        serde_json::Value::Array(items) => Ok(items),
+        item @ serde_json::Value::Object(_) => Ok(vec![item]),
+        _ => Err(fanling_error!("not a JSON array of items")),
+    }
+}
+/** add the items that are not already in the repository to those
+imported, noting those skipped */
+fn import_all(
+    world: &mut World,
+    items: &[serde_json::Value],
+    imported: &mut ItemListEntryList,
+    skipped: &mut Skipped,
+) -> NullResult {
+    for item in items {
+        let (base, values) = match split_data_parts(&serde_yaml::to_vec(item)?) {
+            Ok(parts) if !parts.0.ident.is_empty() && !parts.0.type_name.is_empty() => parts,
+            _ => {
+                skipped.invalid += 1;
+                continue;
+            }
+        };
+        if world.has_item(&base.ident)? {
+            let (_base, stored) = world.get_item_parts(&base.ident)?;
+            if stored == values {
+                skipped.present += 1;
+            } else {
+                skipped.conflicts.push(base.ident.clone());
+            }
+            continue;
+        }
+        if let Some(item_ref) = world.import_item(&base, &values)? {
+            imported.add(ItemListEntry::from_item(&item_ref.borrow())?);
+        }
+    }
+    Ok(())
+}
+/** add the items in the JSON that are not already in the repository, then list them */
+pub fn import_json(world: &mut World, text: &str) -> fanling_interface::ResponseResult {
+    let items = match parse(text) {
+        Ok(items) => items,
+        Err(e) => {
+            trace(&format!("cannot read JSON: {:?}", e));
+            return show_json(&format!("Cannot import the JSON: {}", e), &[], "", 0);
+        }
+    };
+    let mut imported = ItemListEntryList::from_vec(vec![]);
+    let mut skipped = Skipped::default();
+    /* the batch is ended even if an item cannot be imported, so that later changes are committed */
+    world.begin_batch();
+    let result = import_all(world, &items, &mut imported, &mut skipped);
+    world.end_batch()?;
+    result?;
+    trace(&format!(
+        "imported {} items (skipped {:?})",
+        imported.num_entries(),
+        skipped
+    ));
+    let mut resp = World::show_list(&mut imported, "imported")?;
+    let mut summary = format!(
+        "Imported {} items; {} already present, {} invalid.",
+        imported.num_entries(),
+        skipped.present,
+        skipped.invalid
+    );
+    if !skipped.conflicts.is_empty() {
+        summary.push_str(&format!(
+            " Not imported, as their idents are used by other items: {}.",
+            skipped.conflicts.join(", ")
+        ));
+    }
+    resp.show_toast(&summary);
+    #[cfg(test)]
+    {
+        resp.set_test_data("present", &format!("{}", skipped.present));
+        resp.set_test_data("conflicts", &skipped.conflicts.join(","));
+        resp.set_test_data("invalid", &format!("{}", skipped.invalid));
+    }
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("json {}", Colour::Fixed(0).on(Colour::Fixed(195)).paint(m));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn parse_items() -> FLResult<()> {
+        assert_eq!(2, parse(r#"[{"ident":"a"},{"ident":"b"}]"#)?.len());
+        assert_eq!(1, parse(r#"{"ident":"a","type":"Simple"}"#)?.len());
+        assert!(parse("42").is_err());
+        assert!(parse("ident: a").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,183 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! the registry of the kinds of item, with what the user interface
+needs to know about each of them (such as its icon and the fields that
+must be given when one is made). Lists of kinds (such as the "New"
+buttons, or the types that can be exported) are made from this rather
+than being written out, and it can be given as JSON (see
+[`schema_json`]) for other interfaces. The kinds added by plugins are
+kept by the world (see [`crate::plugin`]). */
+use crate::item::ItemKind;
+use crate::shared::FLResult;
+use serde::Serialize;
+
+/** what is known about a kind of item */
+#[derive(Debug, Clone, Serialize)]
+pub struct KindInfo {
+    /** the kind */
+    #[serde(skip)]
+    pub kind: ItemKind,
+    /** the type name, as used in requests and in the repository */
+    pub name: &'static str,
+    /** other type names accepted for it (eg from older versions) */
+    #[serde(skip)]
+    pub aliases: &'static [&'static str],
+    /** the name as shown to the user (eg "New simple") */
+    pub label: &'static str,
+    /** an icon for it */
+    pub icon: &'static str,
+    /** what it is for */
+    pub description: &'static str,
+    /** whether the user can make one directly */
+    pub creatable: bool,
+    /** the fields that must be given when one is made */
+    pub required_fields: &'static [&'static str],
+}
+
+/** the kinds of item, in the order they are offered to the user */
+const KINDS: &[KindInfo] = &[
+    KindInfo {
+        kind: ItemKind::Simple,
+        name: "Simple",
+        aliases: &["simple"],
+        label: "simple",
+        icon: "📄",
+        description: "a wiki page, which can be a context or a parent",
+        creatable: true,
+        required_fields: &["name"],
+    },
+    KindInfo {
+        kind: ItemKind::Task,
+        name: "Task",
+        aliases: &["task", "todo"],
+        label: "task",
+        icon: "☑",
+        description: "something to do, with a context, priority and deadline",
+        creatable: true,
+        required_fields: &["name", "context"],
+    },
+    KindInfo {
+        kind: ItemKind::Contact,
+        name: "Contact",
+        aliases: &["contact"],
+        label: "contact",
+        icon: "👤",
+        description: "a person, with their email addresses and birthday",
+        creatable: true,
+        required_fields: &["name"],
+    },
+    KindInfo {
+        kind: ItemKind::Checklist,
+        name: "Checklist",
+        aliases: &["checklist"],
+        label: "checklist",
+        icon: "📋",
+        description: "a list of entries to tick off (eg a shopping list)",
+        creatable: true,
+        required_fields: &["name"],
+    },
+    KindInfo {
+        kind: ItemKind::Event,
+        name: "Event",
+        aliases: &["event"],
+        label: "event",
+        icon: "📅",
+        description: "something in the calendar, possibly recurring",
+        creatable: true,
+        required_fields: &["name", "start"],
+    },
+    KindInfo {
+        kind: ItemKind::Snippet,
+        name: "Snippet",
+        aliases: &["snippet"],
+        label: "snippet",
+        icon: "⌨",
+        description: "a piece of code, highlighted for its language",
+        creatable: true,
+        required_fields: &["name"],
+    },
+    KindInfo {
+        kind: ItemKind::Meeting,
+        name: "Meeting",
+        aliases: &["meeting"],
+        label: "meeting",
+        icon: "🗣",
+        description: "notes of a meeting, with its attendees and action items",
+        creatable: true,
+        required_fields: &["name", "date"],
+    },
+    KindInfo {
+        kind: ItemKind::Bookmark,
+        name: "Bookmark",
+        aliases: &["bookmark"],
+        label: "bookmark",
+        icon: "🔖",
+        description: "a web page, with tags and a snapshot of its text",
+        creatable: true,
+        required_fields: &["url"],
+    },
+    KindInfo {
+        kind: ItemKind::Reference,
+        name: "Reference",
+        aliases: &["reference"],
+        label: "reference",
+        icon: "📚",
+        description: "a book, article or other work, with its citation key",
+        creatable: true,
+        required_fields: &["name"],
+    },
+    KindInfo {
+        kind: ItemKind::Habit,
+        name: "Habit",
+        aliases: &["habit"],
+        label: "habit",
+        icon: "🔁",
+        description: "something to do regularly, with its streak of days done",
+        creatable: true,
+        required_fields: &["name"],
+    },
+    KindInfo {
+        kind: ItemKind::Project,
+        name: "Project",
+        aliases: &["project"],
+        label: "project",
+        icon: "📁",
+        description: "tasks gathered together, with how far they have got",
+        creatable: true,
+        required_fields: &["name"],
+    },
+];
+
+/** all the kinds of item */
+pub fn kinds() -> &'static [KindInfo] {
+    KINDS
+}
+/** the kind of item with the type name (or one of its aliases), if there is one */
+pub fn find(type_name: &str) -> Option<&'static KindInfo> {
+    KINDS
+        .iter()
+        .find(|k| k.name == type_name || k.aliases.contains(&type_name))
+}
+/** the kinds of item as JSON, for other interfaces (such as a command line or a REST API) */
+pub fn schema_json() -> FLResult<String> {
+    Ok(serde_json::to_string_pretty(KINDS)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn registry() -> FLResult<()> {
+        assert_eq!(Some(ItemKind::Task), find("todo").map(|k| k.kind));
+        assert!(find("Unknown").is_none());
+        for kind in kinds() {
+            assert_eq!(Some(kind.kind), find(kind.name).map(|k| k.kind));
+        }
+        let json = schema_json()?;
+        assert!(json.contains("\"name\": \"Bookmark\""));
+        assert!(json.contains("\"required_fields\": [\n      \"url\"\n    ]"));
+        Ok(())
+    }
+}
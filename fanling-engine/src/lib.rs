@@ -29,13 +29,95 @@ which is used by the platform-specific implementations.
 
 The engine contains the following modules:
 
+* [`alias`] -- renaming items, keeping their old idents as aliases so that links to them still work
+* [`assets`] -- style sheets, scripts, fonts and pictures served to the page from asset URLs, rather than inlined
+* [`article`] -- articles saved to read later, with their readable text and source
+* [`automation`] -- automation rules: scripts kept as snippets, run when items are made or closed and before syncing
+* [`backup`] -- backups of the repository and the local files in a single archive, restored on a new device without the remote
+* [`batch`] -- batch operations on the items chosen in a list (tagging, reparenting, closing, soft deletion and moving to another namespace), in a single commit
+* [`bibtex`] -- reads and writes BibTeX data
+* [`board`] -- a kanban board showing tasks in columns by status
+* [`blocking`] -- tasks blocked by other items, the tasks each item blocks and a notification of those ready when it is closed
+* [`bookmark`] -- implements the 'bookmark' item type (a web page, with a snapshot for reading offline)
+* [`bundle`] -- encrypted bundles of items, for backups
+* [`check`] -- a maintenance check of the items, for broken links and items that cannot be read
+* [`checklist`] -- implements the 'checklist' item type (eg a shopping list)
+* [`clipboard`] -- the clipboard of each view session, for cutting or copying blocks (or whole items) and pasting them into other items
+* [`companion`] -- a generated README and index kept with the items, for other Git tools
+* [`contact`] -- implements the 'contact' item type (an address book entry)
+* [`credentials`] -- the credentials for the remotes (HTTPS tokens and ssh key passphrases), kept by a credential provider such as the keychain, with a page for setting and testing them
+* [`crypt`] -- encrypts the items in a repository, with a passphrase asked for once per session
+* [`dashboard`] -- several regions of the page updated at once: focus, upcoming events and status; and the home page of pinned, favourite and recent items
+* [`dav`] -- two-way sync of events with a CalDAV calendar and of contacts with a CardDAV address book, with a preference for conflicts
+* [`diagram`] -- diagrams in markdown (```` ```dot ```` and ```` ```mermaid ````) shown as pictures, with the `diagrams` feature
+* [`diff`] -- word-by-word differences between two versions of an item
+* [`draft`] -- drafts of unsaved edits, kept locally until the item is saved
+* [`event`] -- implements the 'event' item type (something in a calendar) and the agenda
+* [`export`] -- an item exported as markdown, a standalone HTML page or a PDF, for sending or sharing
+* [`fetch`] -- fetches web pages, with their title and readable text
+* [`focus`] -- focus mode, showing a single next action for each project
+* [`foreign`] -- items of types not known to this version, kept as they are and shown read-only
+* [`format`] -- formats dates, times and numbers according to the user's preferences
+* [`graph`] -- the graph of the links between items, and the orphan items with no links
+* [`habit`] -- implements the 'habit' item type (something done regularly, with its streak)
+* [`highlight`] -- highlights in the snapshots of saved articles, with notes in the margin
+* [`history`] -- the history of changes to an item, including whether each commit is signed
+* [`hooks`] -- outbound hooks posting items to a url (or giving them to a local command) when they are made, updated or closed, queued while offline
+* [`holiday`] -- knows which days are holidays or weekends
+* [`i18n`] -- translations of the text shown to the user
+* [`ical`] -- reads and writes iCalendar files
+* [`idents`] -- how the idents of new items are made (slugs, sequential numbers, UUIDs or ULIDs), and finding idents that collide
+* [`instantiate`] -- uses template items, prompting for the values of their variables
+* [`integrity`] -- content hashes for the local files, so that corrupted ones are rebuilt
 * [`item`] -- implements a single item (page, node)
-* [`markdown`] -- supports markdown formatting
+* [`json`] -- exports items as JSON (one, some types or all of them), and imports them again, skipping those already present
+* [`kinds`] -- the registry of the kinds of item, with their icons and required fields
+* [`mail`] -- items made from e-mail messages (given as they are, or the unread ones in an IMAP folder), with their attachments
+* [`maintenance`] -- the size of the repository, with packing it and collecting its garbage
+* [`markdown`] -- supports markdown formatting, with ids for headings (for tables of contents and links to headings)
+* [`math`] -- mathematics in markdown (`$x^2$`), as MathML or for KaTeX in the page
+* [`meeting`] -- implements the 'meeting' item type (notes with attendees and action items)
+* [`namespace`] -- namespaces (folders) for items, given by the start of their idents, with a page for browsing them and lists scoped to them
+* [`migrate`] -- versions of the format of the YAML of items, and migrations from old versions
+* [`ordering`] -- the order of the list views (by name, dates or due date, or a manual order)
+* [`org`] -- imports and exports org-mode files, with headings as tasks (by their TODO keywords) and simple items
+* [`plugin`] -- plugins adding kinds of item without the engine being changed, whose items are kept read-only when the plugin is not loaded
+* [`prefetch`] -- renders the items likely to be shown next, so that moving between items is quick
+* [`print`] -- an item laid out for printing, without the controls of the app and with its links as footnotes
+* [`private`] -- private items, each encrypted with a passphrase of its own
+* [`project`] -- implements the 'project' item type (tasks gathered together, with their progress)
+* [`quick`] -- quick capture: a task (or note) made from a single line such as "buy milk #errands !fri"
+* [`read_only`] -- read-only repositories, such as a reference wiki shared with the family, which can be read and pulled but not changed
+* [`reference`] -- implements the 'reference' item type (a book or article to cite)
+* [`reminder`] -- reminders of the birthdays and anniversaries of contacts, and the notifications for the main program to schedule
+* [`remotes`] -- several remotes for a repository, each with its own ssh key and policy for pushing, reporting how the sync with each went
+* [`report`] -- a report for weekly reviews: the activity heatmap, tasks completed each week and the open items untouched the longest
+* [`repos`] -- several repositories open at once, one of them current
+* [`restore`] -- restores an item to a version from its history, once confirmed
+* [`retention`] -- retention policies, archiving or flagging old items of a kind or with a tag
+* [`rollback`] -- rolls back a merge or migration that leaves items that are not valid, with a report
 * [`search`] -- searches for items (uses sqlite)
+* [`secrets`] -- warns before saving items that seem to contain secrets (eg API keys)
+* [`session`] -- view sessions (eg windows), each with its own current item and history for going back and forward; and the recent items
+* [`settings`] -- settings kept with each repository, such as how commits are described
+* [`share`] -- items made from content shared with the app, such as a web page from a phone browser
 * [`shared`] -- some shared code used in multiple modules
 * [`simple`] -- implements the 'simple' item type (in effect, a wiki page)
+* [`snippet`] -- implements the 'snippet' item type (a piece of code)
+* [`soak`] -- a hidden soak test mode, making random activity against a test repository and checking that all is well
+* [`sparse`] -- selective sync: only some namespaces or kinds are checked out and loaded, and the other items are stubs in the search until needed
+* [`stats`] -- statistics about the items, such as an activity heatmap
 * [`store`] -- stores items (using Git)
+* [`suggest`] -- suggestions of what to do now, given the time, energy and context
+* [`sync`] -- how the repository stands against its remote (commits waiting to be pushed or merged, the last sync and conflicts), for a badge and a page of the changes waiting to push
+* [`taskimport`] -- imports tasks from Todoist and Google Tasks, with their projects as namespaces, in one commit
 * [`task`] --  implements the 'task' item type (a to-do item)
+* [`timeline`] -- a timeline (SVG) of the tasks with deadlines and the events, grouped by project or tag, with collisions shaded
+* [`timing`] -- time tracking on tasks, with the running timer and a weekly report
+* [`transclude`] -- the text of one item included in another (`{{include:ident}}`)
+* [`typography`] -- emoji shortcodes and smart punctuation in rendered text, set for each repository
+* [`vcard`] -- reads and writes vCard files
+* [`warm`] -- warm starts, loading the items in use at the last shutdown from a snapshot
 * [`world`] -- the collection of all items
 
 */
@@ -54,19 +136,118 @@ extern crate regex;
 extern crate rust_embed;
 pub extern crate taipo_git_control;
 //use std::panic::catch_unwind;
+mod alias;
+mod article;
+mod assets;
+mod automation;
+mod backup;
+mod batch;
+mod bibtex;
+mod blocking;
+mod board;
+mod bookmark;
+mod bundle;
+mod check;
+mod checklist;
+mod clipboard;
+mod companion;
+mod contact;
+mod credentials;
+mod crypt;
+mod dashboard;
+mod dav;
+mod diagram;
+mod diff;
+mod draft;
+mod event;
+mod export;
+mod fetch;
+mod focus;
+mod foreign;
+mod format;
+mod graph;
+mod habit;
+mod highlight;
+mod history;
+mod holiday;
+mod hooks;
+mod i18n;
+mod ical;
+mod idents;
+mod instantiate;
+mod integrity;
 mod item;
+mod json;
+mod kinds;
+mod mail;
+mod maintenance;
 mod markdown;
+mod math;
+mod meeting;
+mod migrate;
+mod namespace;
+mod ordering;
+mod org;
+mod plugin;
+mod prefetch;
+mod print;
+mod private;
+mod project;
+mod quick;
+mod read_only;
+mod reference;
+mod reminder;
+mod remotes;
+mod report;
+mod repos;
+mod restore;
+mod retention;
+mod rollback;
 mod search;
+mod secrets;
+mod session;
+mod settings;
+mod share;
 mod shared;
 mod simple;
+mod soak;
+mod snippet;
+mod sparse;
+mod stats;
 mod store;
+mod suggest;
+mod sync;
 mod task;
+mod timeline;
+mod timing;
+mod transclude;
+mod typography;
+mod vcard;
+mod warm;
 mod world;
-use crate::item::ItemBaseForSerde;
 pub use crate::shared::{FLResult, FanlingError, NullResult, Tracer};
 use fanling_interface::error_response_result;
 use log::trace;
+pub use backup::{restore_backup, Manifest as BackupManifest};
+pub use credentials::for_trace;
+pub use crypt::EncryptionOptions;
+pub use dav::{DavOptions, Preference as DavPreference};
+pub use format::FormatOptions;
+pub use hooks::{HookEvent, HookOptions, HookTarget};
+pub use idents::IdentScheme;
+pub use item::{
+    AvailableAction, Item, ItemBase, ItemBaseForSerde, ItemData, ItemKind, ItemTypePolicy,
+    ItemTypeRef, ShowBaseTemplate,
+};
+pub use kinds::{kinds, KindInfo};
+pub use mail::MailOptions;
+pub use math::MathRendering;
+pub use plugin::{ItemTypePlugin, Plugin};
 pub use search::SearchOptions;
+pub use repos::{OtherRepoOptions, ReposOptions};
+pub use settings::CommitOptions;
+pub use world::{ActionResponse, World};
+pub use soak::{run_soak, SoakOptions, SoakReport};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::panic;
@@ -81,12 +262,19 @@ use std::time::SystemTime;
 pub enum Action {
     Start,
     Shutdown,
+    /** give the passphrase for an encrypted repository */
+    Unlock(String),
     // SetOptions(Options),
     Pull,
     PushAndQuit { force: bool },
     Push { force: bool },
     Show,
+    /** show the item at a heading, given the heading's id without the prefix (see [`markdown`]) */
+    ShowHeading(String),
     Edit,
+    History,
+    /** the actions that can be done to an item now, as JSON (for building menus) */
+    AvailableActions,
     Update(ItemBaseForSerde, HashMap<String, String>),
     Delete,
     Archive,
@@ -94,27 +282,277 @@ pub enum Action {
     ListReady,
     ListOpen,
     ListAll,
+    /** list the articles saved to read later (see [`article`]) */
+    ListReading,
+    /** list the highlights in all the bookmarks (see [`highlight`]) */
+    ListHighlights,
+    /** show the next page of a list ("ready", "open", "all" or "reading"), to go after those already shown */
+    ListMore {
+        list: String,
+        offset: usize,
+        limit: usize,
+    },
+    /** choose the order of the list views and show the list again (see [`ordering`]) */
+    SortList {
+        list: String,
+        order: String,
+    },
+    /** move an item up or down in the manual order and show the list again */
+    MoveInList {
+        list: String,
+        ident: item::Ident,
+        up: bool,
+    },
     New,
     NewChild(item::Ident),
     Create(ItemBaseForSerde, HashMap<String, String>),
     Clone,
+    /** choose a template to make a new item from */
+    Templates,
+    Instantiate,
+    InstantiateWith(HashMap<String, String>),
     Unknown, /* error */
     /* actions for specific item types */
     Close,
     Reopen,
+    /** close a project, doing as chosen with its open tasks (see [`project`]) */
+    CloseProject(project::ProjectChildren),
     GetAll,
     CheckData,
+    /** the kinds of item (see [`kinds`]) as JSON, in the `kinds` tag */
+    Kinds,
+    /** check the items for problems such as links to missing items, and show them */
+    Check,
+    /** fix a problem found by [Action::Check] */
+    FixProblem(check::Fix),
+    /** preview what the retention policies would do (see [`retention`]) */
+    Retention,
+    /** archive or flag the items previewed by [Action::Retention] that are still due */
+    ApplyRetention(Vec<item::Ident>),
+    /** do an operation to the items chosen in a list, in a single commit, and show a summary (see [`batch`]) */
+    Batch {
+        idents: Vec<item::Ident>,
+        operation: batch::BatchOperation,
+    },
+    /** save all the items written in an old format (see [`migrate`]), in a single commit */
+    MigrateAll,
     BlockBy(item::Ident),
     UnblockBy(item::Ident),
+    Snooze(task::Snooze),
+    NextAction(item::Ident),
+    FocusDone,
+    FocusSkip,
+    SetStatus(task::TaskStatus),
+    MoveCardUp,
+    MoveCardDown,
+    /** mark a habit as done today */
+    DoneToday,
+    /** start timing a task (stopping any other task's timer) */
+    StartTimer,
+    StopTimer,
+    /** show the time spent on tasks in the week containing the date (this week if blank) */
+    TimeReport(String),
+    ToggleEntry(usize),
+    AddEntry(String),
+    DeleteEntry(usize),
+    MoveEntryUp(usize),
+    MoveEntryDown(usize),
+    /** make tasks from the action items in a meeting's notes */
+    ExtractActions,
+    /** fetch a bookmark's page again, keeping a snapshot of its text */
+    Refetch,
+    /** show the differences in an item between two commits (`from` blank to compare with nothing) */
+    Diff { from: String, to: String },
+    /** restore an item to the version in a commit (showing what would change until `confirmed`) */
+    Restore { id: String, confirmed: bool },
+    /** keep a copy of a bookmark's page as an attachment (on the desktop) */
+    ArchivePage,
+    /** show a copy of a bookmark's page archived at the path */
+    ShowArchivedPage(String),
+    /** highlight some of a bookmark's snapshot, with a note (which may be blank) */
+    Highlight { quote: String, note: String },
+    /** remove one of a bookmark's highlights */
+    RemoveHighlight(usize),
+    /** give the passphrase for a private item */
+    UnlockItem(String),
+    /** pin the item to the home dashboard, or unpin it */
+    TogglePin,
+    /** make the item a favourite, or stop it being one */
+    ToggleFavourite,
+    /** show the page for renaming the item */
+    ShowRename,
+    /** export the item, giving the file to the user interface (see [`export`]) */
+    ExportItem(export::Format),
+    /** show the item laid out for printing (see [`print`]) */
+    Print,
+    /** record a call, e-mail and so on with a contact, with a note */
+    LogInteraction(contact::InteractionKind, String),
+    ExportVCard,
+    ImportVCard(String),
+    ExportBibTeX,
+    ImportBibTeX(String),
+    /** show the reference with the citation key */
+    Cite(String),
+    /** show the form for exporting and importing encrypted bundles */
+    Backup,
+    /** write an encrypted bundle of the items of the types (all of them, if none are given) */
+    ExportBundle { types: Vec<String>, passphrase: String },
+    /** add the items in an encrypted bundle */
+    ImportBundle { text: String, passphrase: String },
+    /** write the items with the idents (or else those of the types, or else all of them) as a JSON array */
+    ExportJson { idents: Vec<String>, types: Vec<String> },
+    /** add the items in a JSON array that are not already in the repository */
+    ImportJson(String),
+    /** write the tasks and simple items as an org-mode file */
+    ExportOrg,
+    /** make items from the headings of an org-mode file */
+    ImportOrg(String),
+    /** make tasks from an export of Todoist or Google Tasks, whose project is given for a CSV file (shows the form if the text is blank) */
+    ImportTasks { text: String, project: String },
+    /** sync the events and contacts with the CalDAV and CardDAV server */
+    SyncDav,
+    Stats,
+    /** show the report for a weekly review: activity, tasks completed each week and the items untouched the longest */
+    Report,
+    /** list the projects with their progress (see [`project`]) */
+    ListProjects,
+    Holidays,
+    Board,
+    Focus,
+    /** show the focus view with upcoming events, a status line and reminders, all at once */
+    Dashboard,
+    /** render the items likely to be shown after the one just shown */
+    Prefetch,
+    /** push to the remotes pushed to on save, if there have been commits since (asked for by the page every so often, see [`remotes`]) */
+    PushPending,
+    /** post what the hooks have queued (asked for by the page every so often, see [`hooks`]) */
+    DeliverHooks,
+    /** make an item from a single line, such as "buy milk #errands !friday" */
+    QuickAdd(String),
+    /** fetch the page at the URL and save it as an article to read later */
+    SaveArticle(String),
+    /** make an item from content shared with the app (any of which may be blank) */
+    CaptureShared {
+        title: String,
+        text: String,
+        url: String,
+    },
+    /** make an item from an e-mail message, given as it is (RFC 822) */
+    CaptureMail(String),
+    /** make items from the unread messages in the mailbox */
+    PollMail,
+    Repos,
+    SelectRepo(String),
+    Suggest(suggest::SuggestionRequest),
+    /** export the chart (one of [`stats::CHARTS`]) as a file or an item with it attached */
+    ExportChart {
+        chart: String,
+        format: stats::ChartFormat,
+    },
+    /** show the item shown before the current one in the session */
+    Back,
+    /** show the item gone back from in the session */
+    Forward,
+    /** give an item a new ident, keeping the old one as an alias (and
+    changing the links to it in other items, if `rewrite_links`) */
+    Rename {
+        ident: String,
+        new_ident: String,
+        rewrite_links: bool,
+    },
+    /** show the items shown recently, the latest first */
+    Recent,
+    /** show the namespaces and items in a namespace (the top level if empty) */
+    Namespace(String),
+    /** put some blocks of a field of an item (such as entries of a
+    checklist), or the whole item if `field` is empty, on the clipboard
+    of the session, to be removed from the item when pasted if `cut` */
+    Clip {
+        ident: String,
+        #[serde(default)]
+        field: String,
+        #[serde(default)]
+        blocks: Vec<usize>,
+        #[serde(default)]
+        cut: bool,
+    },
+    /** paste the clipboard of the session into a field of an item (its
+    main field if empty), before the block at `at` (or at the end) */
+    Paste {
+        ident: String,
+        #[serde(default)]
+        field: String,
+        #[serde(default)]
+        at: Option<usize>,
+    },
+    /** show the local commits waiting to be pushed, with how the repository stands against its remote */
+    Unpushed,
+    /** show the remotes, with how the last pull from and push to each went */
+    Remotes,
+    /** add a remote, or change its ssh key and policies (see [`remotes`]) */
+    SaveRemote {
+        name: String,
+        #[serde(default)]
+        url: String,
+        #[serde(default)]
+        ssh_key: String,
+        on_save: bool,
+        pull: bool,
+    },
+    /** remove a remote other than the main one */
+    RemoveRemote(String),
+    /** pull from just one remote */
+    PullFrom(String),
+    /** push to just one remote */
+    PushTo { name: String, force: bool },
+    /** show the remotes, with whether a secret is kept for each (see [`credentials`]) */
+    Credentials,
+    /** keep the secret (HTTPS token or ssh key passphrase) for a remote */
+    SaveCredential { remote: String, secret: String },
+    /** forget the secret for a remote */
+    ForgetCredential(String),
+    /** check that a remote can be reached with its credentials */
+    TestRemote(String),
+    /** show how big the repository is (see [`maintenance`]) */
+    Maintenance,
+    /** pack the loose objects of the repository */
+    Repack,
+    /** collect the garbage in the repository (with the `git` program) */
+    Gc,
+    /** write a backup of the repository and the local files to the export directory (see [`backup`]) */
+    ExportBackup,
+    /** show the current item of the session (or the start page if it has none) */
+    ShowCurrent,
+    /** forget the session (as when its window is closed) */
+    CloseSession,
+    /** show the items that have no links to or from them */
+    Orphans,
+    /** write the graph of the links between items as DOT and JSON */
+    ExportGraph,
+    /** show the events in the month (or week) containing the date (today if blank) */
+    Agenda { month: bool, date: String },
+    /** show the timeline of the weeks around the date (today if blank), grouped by tag or by project (see [`timeline`]) */
+    Timeline { by_tag: bool, date: String },
+    ExportICal,
+    /** render some Markdown text for previewing it while editing (nothing is saved) */
+    Preview(String),
+    /** keep the values in a new or edit form, in case the app is killed before they are saved */
+    SaveDraft(ItemBaseForSerde, HashMap<String, String>),
+    /** forget the draft for an item, and edit it as it was saved */
+    DiscardDraft,
+    Settings,
+    SaveSettings(HashMap<String, String>),
     TestError1,
     TestError2,
 }
 impl Action {
     fn kind(&self) -> ActionKind {
         match self {
-            Action::Shutdown | Action::PushAndQuit { force: _ } | Action::TestError1 => {
-                ActionKind::Engine
-            }
+            Action::Shutdown
+            | Action::Unlock(_)
+            | Action::PushAndQuit { force: _ }
+            | Action::Kinds
+            | Action::TestError1 => ActionKind::Engine,
             Action::Start
             | Action::Pull
             | Action::Create(_, _)
@@ -122,24 +560,239 @@ impl Action {
             | Action::ListReady
             | Action::ListOpen
             | Action::ListAll
+            | Action::ListMore {
+                list: _,
+                offset: _,
+                limit: _,
+            }
+            | Action::SortList { .. }
+            | Action::MoveInList { .. }
             | Action::Delete
             | Action::GetAll
             | Action::CheckData
+            | Action::Check
+            | Action::FixProblem(_)
+            | Action::Retention
+            | Action::ApplyRetention(_)
+            | Action::Batch {
+                idents: _,
+                operation: _,
+            }
+            | Action::MigrateAll
             | Action::Push { force: _ }
             | Action::New
             | Action::Clone
+            | Action::Templates
+            | Action::Instantiate
+            | Action::InstantiateWith(_)
             | Action::NewChild(_)
+            | Action::ExportVCard
+            | Action::ImportVCard(_)
+            | Action::ExportBibTeX
+            | Action::ImportBibTeX(_)
+            | Action::Cite(_)
+            | Action::Backup
+            | Action::ExportBundle {
+                types: _,
+                passphrase: _,
+            }
+            | Action::ImportBundle {
+                text: _,
+                passphrase: _,
+            }
+            | Action::ExportJson {
+                idents: _,
+                types: _,
+            }
+            | Action::ImportJson(_)
+            | Action::ExportOrg
+            | Action::ImportOrg(_)
+            | Action::ImportTasks {
+                text: _,
+                project: _,
+            }
+            | Action::SyncDav
+            | Action::Stats
+            | Action::Report
+            | Action::ListProjects
+            | Action::Holidays
+            | Action::Board
+            | Action::Focus
+            | Action::Dashboard
+            | Action::Prefetch
+            | Action::PushPending
+            | Action::DeliverHooks
+            | Action::QuickAdd(_)
+            | Action::SaveArticle(_)
+            | Action::ListReading
+            | Action::ListHighlights
+            | Action::TimeReport(_)
+            | Action::CaptureShared {
+                title: _,
+                text: _,
+                url: _,
+            }
+            | Action::CaptureMail(_)
+            | Action::PollMail
+            | Action::Repos
+            | Action::SelectRepo(_)
+            | Action::Suggest(_)
+            | Action::ExportChart { .. }
+            | Action::Back
+            | Action::Forward
+            | Action::Rename { .. }
+            | Action::Recent
+            | Action::Namespace(_)
+            | Action::Clip { .. }
+            | Action::Paste { .. }
+            | Action::Unpushed
+            | Action::Remotes
+            | Action::SaveRemote { .. }
+            | Action::RemoveRemote(_)
+            | Action::PullFrom(_)
+            | Action::PushTo { .. }
+            | Action::Credentials
+            | Action::SaveCredential { .. }
+            | Action::ForgetCredential(_)
+            | Action::TestRemote(_)
+            | Action::Maintenance
+            | Action::Repack
+            | Action::Gc
+            | Action::ExportBackup
+            | Action::ShowCurrent
+            | Action::CloseSession
+            | Action::Orphans
+            | Action::ExportGraph
+            | Action::Agenda { month: _, date: _ }
+            | Action::Timeline { by_tag: _, date: _ }
+            | Action::ExportICal
+            | Action::Preview(_)
+            | Action::SaveDraft(_, _)
+            | Action::DiscardDraft
+            | Action::Settings
+            | Action::SaveSettings(_)
             | Action::TestError2 => ActionKind::World,
             Action::Show
+            | Action::ShowHeading(_)
             | Action::Edit
+            | Action::History
+            | Action::Diff { .. }
+            | Action::Restore { .. }
+            | Action::AvailableActions
             | Action::Archive
             | Action::Close
             | Action::Reopen
+            | Action::CloseProject(_)
             | Action::BlockBy(_)
-            | Action::UnblockBy(_) => ActionKind::Item,
+            | Action::UnblockBy(_)
+            | Action::Snooze(_)
+            | Action::NextAction(_)
+            | Action::FocusDone
+            | Action::FocusSkip
+            | Action::SetStatus(_)
+            | Action::MoveCardUp
+            | Action::MoveCardDown
+            | Action::StartTimer
+            | Action::StopTimer
+            | Action::DoneToday
+            | Action::ToggleEntry(_)
+            | Action::AddEntry(_)
+            | Action::DeleteEntry(_)
+            | Action::MoveEntryUp(_)
+            | Action::MoveEntryDown(_)
+            | Action::ExtractActions
+            | Action::Refetch
+            | Action::ArchivePage
+            | Action::ShowArchivedPage(_)
+            | Action::Highlight { .. }
+            | Action::RemoveHighlight(_)
+            | Action::LogInteraction(_, _)
+            | Action::UnlockItem(_)
+            | Action::TogglePin
+            | Action::ToggleFavourite
+            | Action::ShowRename
+            | Action::ExportItem(_)
+            | Action::Print => ActionKind::Item,
             Action::Unknown => panic!("unknown action"),
         }
     }
+    /** the verb used in the commit message when an item action changes the item */
+    pub fn verb(&self) -> &'static str {
+        match self {
+            Action::Close
+            | Action::SetStatus(task::TaskStatus::Closed)
+            | Action::FocusDone => "close",
+            Action::Reopen => "reopen",
+            Action::Archive => "archive",
+            Action::Snooze(_) => "snooze",
+            _ => "modify",
+        }
+    }
+    /** whether the focus view is shown after the action */
+    fn shows_focus(&self) -> bool {
+        matches!(self, Action::FocusDone | Action::FocusSkip)
+    }
+    /** whether the items prefetched before the action can still be shown
+    after it (as it changes nothing that they show) */
+    fn keeps_prefetched(&self) -> bool {
+        matches!(
+            self,
+            Action::Show
+                | Action::ShowHeading(_)
+                | Action::Prefetch
+                | Action::PushPending
+                | Action::DeliverHooks
+                | Action::Edit
+                | Action::History
+                | Action::Diff { .. }
+                | Action::AvailableActions
+                | Action::ListReady
+                | Action::ListOpen
+                | Action::ListAll
+                | Action::ListReading
+                | Action::ListHighlights
+                | Action::TimeReport(_)
+                | Action::Cite(_)
+                | Action::Retention
+                | Action::ListMore { .. }
+                | Action::SortList { .. }
+                | Action::Focus
+                | Action::Board
+                | Action::Dashboard
+                | Action::Agenda { .. }
+                | Action::Timeline { .. }
+                | Action::Stats
+                | Action::Report
+                | Action::ListProjects
+                | Action::Orphans
+                | Action::Check
+                | Action::Back
+                | Action::Forward
+                | Action::Recent
+                | Action::Namespace(_)
+                | Action::Clip { .. }
+                | Action::Unpushed
+                | Action::Remotes
+                | Action::Credentials
+                | Action::TestRemote(_)
+                | Action::Maintenance
+                | Action::ShowRename
+                | Action::ExportItem(_)
+                | Action::Print
+                | Action::ShowCurrent
+                | Action::CloseSession
+                | Action::Templates
+                | Action::Preview(_)
+                | Action::SaveDraft(_, _)
+        )
+    }
+    /** whether the board is shown after the action */
+    fn shows_board(&self) -> bool {
+        matches!(
+            self,
+            Action::SetStatus(_) | Action::MoveCardUp | Action::MoveCardDown
+        )
+    }
 }
 
 impl Default for Action {
@@ -164,7 +817,7 @@ pub struct Options {
 }
 
 /** contains common fields across requests. The input request is deserialised into a `BasicRequest`. */
-#[derive(Debug, Serialize, Deserialize, PartialEq, Default)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Default, Clone)]
 pub struct BasicRequest {
     //  #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "a")]
@@ -175,6 +828,10 @@ pub struct BasicRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "i")]
     ident: Option<String>,
+    /** the view session that the request is from (see [`session`]) */
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "s")]
+    session: Option<String>,
 }
 impl BasicRequest {
     pub fn ensure_type_name(&self) -> FLResult<String> {
@@ -191,12 +848,16 @@ impl BasicRequest {
             .ok_or_else(|| fanling_error!("no ident"))?
             .clone())
     }
+    /** the id of the view session (blank for the default one) */
+    pub fn session(&self) -> &str {
+        self.session.as_deref().unwrap_or("")
+    }
 }
 // impl Default for BasicRequest {
 //     fn default() -> Self {}
 // }
 /** options for an [Engine]. Some fields are passed down to components. */
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct EngineOptions {
     /** options have been specified */
     pub correct: bool,
@@ -208,8 +869,38 @@ pub struct EngineOptions {
     pub search_options: search::SearchOptions,
     /**  unique prefix for identifiers (must be different for each ser instance) */
     pub uniq_pfx: String,
+    /** how the idents of new items are made */
+    pub ident_scheme: IdentScheme,
     /** automatically generate items for missing items in links */
     pub auto_link: bool,
+    /** how dates, times and numbers are formatted */
+    pub format_options: FormatOptions,
+    /** how commits are described (unless overridden by the repository's settings) */
+    pub commit_options: CommitOptions,
+    /** the name of the main repository, and the other repositories to open */
+    pub repos: ReposOptions,
+    /** whether the items are encrypted */
+    pub encryption: EncryptionOptions,
+    /** keep the items shown recently when shutting down, for the recent page next time */
+    pub keep_history: bool,
+    /** the main program serves the assets of the page (see
+    [`fanling_interface::Engine::asset`]), so they need not be inlined */
+    pub serve_assets: bool,
+    /** the kinds of item loaded when all the items are loaded (all of
+    them if empty); the rest are stubs until needed (see [`sparse`]) */
+    pub sparse_kinds: Vec<String>,
+    /** the items in the main repository can only be read, and syncing only pulls (see [`read_only`]) */
+    pub read_only: bool,
+    /** plugins adding kinds of item (see [`plugin`]) */
+    pub plugins: Vec<plugin::Plugin>,
+    /** urls posted to, and commands run, when items change (see [`hooks`]) */
+    pub hooks: Vec<hooks::HookOptions>,
+    /** the CalDAV and CardDAV server that events and contacts are synced with (see [`dav`]) */
+    pub dav: Option<dav::DavOptions>,
+    /** the IMAP mailbox whose unread messages are made into items (see [`mail`]) */
+    pub mail: Option<mail::MailOptions>,
+    /** how mathematics in markdown is shown (see [`math`]) */
+    pub math: math::MathRendering,
 }
 /** type of user interface that drives this engine. Can be used to elicit different behaviour depending on the interface type. */
 #[derive(Copy, Clone, Debug)]
@@ -222,6 +913,12 @@ pub enum InterfaceType {
 pub struct FanlingEngine {
     /** the model */
     world: Option<world::World>,
+    /** the options for opening the model, kept until the passphrase is given (if encrypted) */
+    locked: Option<EngineOptions>,
+    /** type of user interface (PC or phone) */
+    interface_type: InterfaceType,
+    /** whether the main program serves the assets of the page */
+    serve_assets: bool,
     // interface_callback: Option<fn(js: &str)>,
 }
 impl FanlingEngine {
@@ -234,14 +931,57 @@ impl FanlingEngine {
             // env::var("HOST").unwrap_or("no host".to_string()),
             opts.interface_type
         ));
+        if let Err(e) = opts.format_options.check() {
+            trace(&format!("{}, so dates are shown as ISO 8601", e));
+        }
+        if opts.correct && opts.encryption.is_locked() {
+            trace("repository is encrypted, waiting for passphrase");
+            return Ok(Self {
+                world: None,
+                locked: Some(opts.clone()),
+                interface_type: opts.interface_type,
+                serve_assets: opts.serve_assets,
+            });
+        }
         Ok(Self {
             world: if opts.correct {
                 Some(world::World::new_and_open(opts)?)
             } else {
                 None
             },
+            locked: None,
+            interface_type: opts.interface_type,
+            serve_assets: opts.serve_assets,
         })
     }
+    /** open the model using the passphrase given, or ask again if it is wrong */
+    fn unlock(&mut self, passphrase: &str) -> fanling_interface::ResponseResult {
+        let mut opts = match &self.locked {
+            Some(opts) => opts.clone(),
+            None => return Ok(fanling_interface::Response::new()),
+        };
+        opts.encryption.passphrase = Some(passphrase.to_owned());
+        match world::World::new_and_open(&opts) {
+            Ok(world) => {
+                trace("unlocked");
+                self.locked = None;
+                let world = self.world.get_or_insert(world);
+                world.do_action(
+                    &BasicRequest {
+                        action: Action::Start,
+                        ..BasicRequest::default()
+                    },
+                    serde_json::Value::Null,
+                )
+            }
+            Err(e) => {
+                trace(&format!("could not unlock: {:?}", e));
+                let mut resp = crypt::ask_passphrase("Wrong passphrase, please try again.")?;
+                resp.set_error();
+                Ok(resp)
+            }
+        }
+    }
     fn do_engine_action(
         &mut self,
         basic_request: &BasicRequest,
@@ -249,7 +989,12 @@ impl FanlingEngine {
         fanling_trace!("doing engine action");
         match basic_request.action {
             Action::Shutdown => self.shutdown(),
+            Action::Unlock(ref passphrase) => self.unlock(passphrase),
             Action::PushAndQuit { force } => self.push_and_shutdown(force),
+            Action::Kinds => Ok(fanling_interface::Response::new_with_tags(&[(
+                "kinds",
+                &kinds::schema_json()?,
+            )])),
             Action::TestError1 => {
                 if let Some(world) = &self.world {
                     trace("making world test error 1");
@@ -275,6 +1020,11 @@ impl FanlingEngine {
     }
     fn shutdown(&mut self) -> fanling_interface::ResponseResult {
         trace("should shut down now");
+        if let Some(world) = &self.world {
+            world.save_warm_start()?;
+            world.save_history()?;
+            world.record_hashes()?;
+        }
         let mut resp = fanling_interface::Response::new();
         resp.set_shutdown_required();
         Ok(resp)
@@ -282,10 +1032,53 @@ impl FanlingEngine {
     pub fn touch(&self) {
         trace("touched");
     }
+    /** write a backup of the repository and the local files to `path` (see [`backup`]) */
+    pub fn export_backup(&self, path: &std::path::Path) -> FLResult<BackupManifest> {
+        match &self.world {
+            Some(world) => world.export_backup(path),
+            None => Err(fanling_error!("the repository is locked")),
+        }
+    }
+    /** make an item from a single line of text (eg from a quick-capture
+    box or a share target), as [Action::QuickAdd] */
+    pub fn quick_add(&mut self, text: &str) -> fanling_interface::ResponseResult {
+        let body = serde_json::to_string(&BasicRequest {
+            action: Action::QuickAdd(text.to_owned()),
+            ..BasicRequest::default()
+        })?;
+        fanling_interface::Engine::execute(self, &body)
+    }
+    /** make an item from content shared with the app (eg a page shared
+    from a phone browser), as [Action::CaptureShared] */
+    pub fn capture_shared(
+        &mut self,
+        title: &str,
+        text: &str,
+        url: &str,
+    ) -> fanling_interface::ResponseResult {
+        let body = serde_json::to_string(&BasicRequest {
+            action: Action::CaptureShared {
+                title: title.to_owned(),
+                text: text.to_owned(),
+                url: url.to_owned(),
+            },
+            ..BasicRequest::default()
+        })?;
+        fanling_interface::Engine::execute(self, &body)
+    }
+    /** make an item from an e-mail message (eg piped from the shell),
+    as [Action::CaptureMail] */
+    pub fn capture_mail(&mut self, message: &str) -> fanling_interface::ResponseResult {
+        let body = serde_json::to_string(&BasicRequest {
+            action: Action::CaptureMail(message.to_owned()),
+            ..BasicRequest::default()
+        })?;
+        fanling_interface::Engine::execute(self, &body)
+    }
 }
 impl fanling_interface::Engine for FanlingEngine {
     fn execute(&mut self, body: &str) -> fanling_interface::ResponseResult {
-        fanling_trace!(&format!("executing action «{}»", &body));
+        fanling_trace!(&format!("executing action «{}»", for_trace(&body)));
         let result = panic::catch_unwind(AssertUnwindSafe(move || {
             let now = SystemTime::now();
             let json_body = serde_json::from_str(&body);
@@ -315,6 +1108,8 @@ impl fanling_interface::Engine for FanlingEngine {
                 ActionKind::World | ActionKind::Item => {
                     if let Some(world) = &mut self.world {
                         world.do_action(&basic_request, json_value)
+                    } else if self.locked.is_some() {
+                        crypt::ask_passphrase("")
                     } else {
                         Ok(fanling_interface::Response::new())
                     }
@@ -359,6 +1154,13 @@ impl fanling_interface::Engine for FanlingEngine {
         let now = SystemTime::now();
         let html = if let Some(world) = &self.world {
             world.initial_html()?
+        } else if let Some(opts) = &self.locked {
+            world::main_html(
+                self.interface_type,
+                self.serve_assets,
+                opts.math,
+                kinds::kinds().to_vec(),
+            )?
         } else {
             "please set the SSH keys and the preferences".to_owned()
         };
@@ -368,6 +1170,15 @@ impl fanling_interface::Engine for FanlingEngine {
         ));
         Ok(html)
     }
+    fn next_reminders(
+        &mut self,
+        n: usize,
+    ) -> fanling_interface::TPResult<Vec<fanling_interface::ScheduledReminder>> {
+        match &mut self.world {
+            Some(world) => Ok(reminder::next_reminders(world, n)?),
+            None => Ok(vec![]),
+        }
+    }
     // fn get_value(&self, key: &str) -> String {
     //     shared::embedded_asset(key)
     // }
@@ -383,6 +1194,13 @@ impl fanling_interface::Engine for FanlingEngine {
             None => "no world".to_owned(),
         }
     }
+    /** an asset embedded in the engine, or attached to an item (see [`assets`]) */
+    fn asset(
+        &self,
+        url: &str,
+    ) -> fanling_interface::TPResult<Option<fanling_interface::Asset>> {
+        Ok(assets::asset(url, self.world.as_ref())?)
+    }
 }
 impl Drop for FanlingEngine {
     fn drop(&mut self) {
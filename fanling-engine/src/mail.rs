@@ -0,0 +1,684 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! items made from e-mail messages: the subject becomes the name, the
+body becomes the text, and the attachments are attached to the item.
+
+A message can be given as it is (RFC 822, as saved from a mail
+program or piped from the shell), or the unread messages in a folder
+of an IMAP mailbox can be fetched, which is done with the other syncs
+and from the toolbar. The mailbox is given in the engine options (see
+[`MailOptions`]), as it belongs to the device rather than to the
+repository; for Gmail, a label is a folder. Fetched messages are
+marked as read, so each is only captured once.
+
+The body is the plain text of the message if it has one, and otherwise
+the readable text of its HTML. Attachments are kept in the attachments
+folder, and listed (with links) at the end of the text; those that are
+not text are kept base64-encoded, with [`BASE64_SUFFIX`] after their
+names, as the repository holds text. */
+use crate::item::{ItemBaseForSerde, ItemRef};
+use crate::shared::{FLResult, FanlingError};
+use crate::world::World;
+use ansi_term::Colour;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::time::Duration;
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** after the name of an attachment kept base64-encoded */
+pub const BASE64_SUFFIX: &str = ".base64";
+/** the port for IMAP over TLS, if none is given */
+const IMAPS_PORT: u16 = 993;
+/** how long to wait for the server (ms) */
+const TIMEOUT_MS: u64 = 20_000;
+/** the most messages captured at a time */
+const MAX_MESSAGES: usize = 50;
+
+/** the mailbox, as given in the engine options */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MailOptions {
+    /** the IMAP server, as `host` or `host:port` (TLS is always used) */
+    pub server: String,
+    pub user: String,
+    pub password: String,
+    /** the folder (or label) whose unread messages are captured */
+    pub folder: String,
+}
+impl MailOptions {
+    /** the options, if a server is given (the folder is the inbox if none is given) */
+    pub fn new(server: &str, user: &str, password: &str, folder: &str) -> Option<Self> {
+        if server.trim().is_empty() {
+            return None;
+        }
+        Some(Self {
+            server: server.trim().to_owned(),
+            user: user.to_owned(),
+            password: password.to_owned(),
+            folder: if folder.trim().is_empty() {
+                "INBOX".to_owned()
+            } else {
+                folder.trim().to_owned()
+            },
+        })
+    }
+    fn host_and_port(&self) -> FLResult<(String, u16)> {
+        match self.server.rfind(':') {
+            Some(i) => Ok((self.server[..i].to_owned(), self.server[i + 1..].parse()?)),
+            None => Ok((self.server.clone(), IMAPS_PORT)),
+        }
+    }
+}
+
+/** a file attached to a message */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment {
+    pub name: String,
+    /** such as `image/png` */
+    pub media_type: String,
+    pub data: Vec<u8>,
+}
+/** the parts of a message that are kept */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Message {
+    pub subject: String,
+    pub from: String,
+    /** the body, as text */
+    pub text: String,
+    pub attachments: Vec<Attachment>,
+}
+
+/** the head and the body of a message or part */
+fn split_head(raw: &[u8]) -> (&[u8], &[u8]) {
+    for (i, window) in raw.windows(2).enumerate() {
+        if window == b"\n\n" {
+            return (&raw[..i + 1], &raw[i + 2..]);
+        }
+        if window == b"\n\r" && raw.get(i + 2) == Some(&b'\n') {
+            return (&raw[..i + 1], &raw[i + 3..]);
+        }
+    }
+    (raw, &[])
+}
+/** the header fields, unfolded, with their names in lower case */
+fn headers(head: &[u8]) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = vec![];
+    for line in String::from_utf8_lossy(head).lines() {
+        if line.starts_with(' ') || line.starts_with('\t') {
+            if let Some((_, value)) = fields.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some(colon) = line.find(':') {
+            fields.push((
+                line[..colon].trim().to_lowercase(),
+                line[colon + 1..].trim().to_owned(),
+            ));
+        }
+    }
+    fields
+}
+fn field<'a>(fields: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    fields
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, v)| v.as_str())
+}
+/** the value of a field such as `Content-Type` (in lower case), with its parameters */
+fn with_params(value: &str) -> (String, HashMap<String, String>) {
+    let mut parts = value.split(';');
+    let main = parts.next().unwrap_or("").trim().to_lowercase();
+    let mut params = HashMap::new();
+    for part in parts {
+        if let Some(eq) = part.find('=') {
+            let name = part[..eq].trim().to_lowercase();
+            let value = part[eq + 1..].trim().trim_matches('"');
+            /* RFC 2231, as in `filename*=utf-8''caf%C3%A9.txt` */
+            match name.strip_suffix('*') {
+                Some(name) => {
+                    let encoded = value.splitn(3, '\'').last().unwrap_or("");
+                    params.insert(name.to_owned(), percent_decode(encoded));
+                }
+                None => {
+                    params.insert(name, value.to_owned());
+                }
+            }
+        }
+    }
+    (main, params)
+}
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], s.get(i + 1..i + 3)) {
+            (b'%', Some(hex)) if u8::from_str_radix(hex, 16).is_ok() => {
+                out.push(u8::from_str_radix(hex, 16).unwrap_or(0));
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+/** text in the character set (those that are not known are taken as UTF-8) */
+fn decode_charset(bytes: &[u8], charset: &str) -> String {
+    match charset.to_lowercase().as_str() {
+        "iso-8859-1" | "iso-8859-15" | "latin1" | "windows-1252" | "us-ascii" => {
+            bytes.iter().map(|b| *b as char).collect()
+        }
+        _ => String::from_utf8_lossy(bytes).to_string(),
+    }
+}
+fn quoted_printable(bytes: &[u8], in_header: bool) -> Vec<u8> {
+    let mut out = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'=' if bytes.get(i + 1) == Some(&b'\r') && bytes.get(i + 2) == Some(&b'\n') => i += 3,
+            b'=' if bytes.get(i + 1) == Some(&b'\n') => i += 2,
+            b'=' if i + 2 < bytes.len() => {
+                match u8::from_str_radix(&String::from_utf8_lossy(&bytes[i + 1..i + 3]), 16) {
+                    Ok(b) => {
+                        out.push(b);
+                        i += 3;
+                    }
+                    Err(_) => {
+                        out.push(b'=');
+                        i += 1;
+                    }
+                }
+            }
+            b'_' if in_header => {
+                out.push(b' ');
+                i += 1;
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+/** decode the encoded words (RFC 2047) in a header field, such as `=?utf-8?Q?caf=C3=A9?=` */
+fn decode_words(value: &str) -> String {
+    let mut out = String::new();
+    let mut rest = value;
+    /* the space between two encoded words is not kept */
+    let mut after_word = false;
+    while let Some(start) = rest.find("=?") {
+        let word = rest[start + 2..].splitn(3, '?').collect::<Vec<&str>>();
+        let decoded = match word.as_slice() {
+            [charset, encoding, text] => text.find("?=").and_then(|end| {
+                let bytes = match encoding.to_uppercase().as_str() {
+                    "B" => base64::decode(&text[..end]).ok()?,
+                    "Q" => quoted_printable(text[..end].as_bytes(), true),
+                    _ => return None,
+                };
+                let length = charset.len() + encoding.len() + end + 6;
+                Some((decode_charset(&bytes, charset), length))
+            }),
+            _ => None,
+        };
+        match decoded {
+            Some((text, length)) => {
+                let between = &rest[..start];
+                if !(after_word && between.trim().is_empty()) {
+                    out.push_str(between);
+                }
+                out.push_str(&text);
+                rest = &rest[start + length..];
+                after_word = true;
+            }
+            None => {
+                out.push_str(&rest[..start + 2]);
+                rest = &rest[start + 2..];
+                after_word = false;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+/** the body of a part, decoded */
+fn decode_body(body: &[u8], encoding: &str) -> Vec<u8> {
+    match encoding.trim().to_lowercase().as_str() {
+        "base64" => {
+            let compact: Vec<u8> = body
+                .iter()
+                .copied()
+                .filter(|b| !b.is_ascii_whitespace())
+                .collect();
+            base64::decode(&compact).unwrap_or_else(|e| {
+                trace(&format!("bad base64: {:?}", e));
+                vec![]
+            })
+        }
+        "quoted-printable" => quoted_printable(body, false),
+        _ => body.to_vec(),
+    }
+}
+/** the parts of a multipart body */
+fn parts<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{}", boundary);
+    let mut found = vec![];
+    let mut start: Option<usize> = None;
+    let mut pos = 0;
+    for line in body.split(|b| *b == b'\n') {
+        let next = (pos + line.len() + 1).min(body.len());
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.starts_with(delimiter.as_bytes()) {
+            if let Some(start) = start {
+                /* the line break before the delimiter belongs to it */
+                let mut end = pos.saturating_sub(1).max(start);
+                if end > start && body[end - 1] == b'\r' {
+                    end -= 1;
+                }
+                found.push(&body[start..end]);
+            }
+            if line[delimiter.len()..].starts_with(b"--") {
+                break;
+            }
+            start = Some(next);
+        }
+        pos = next;
+    }
+    found
+}
+/** what is found in the parts of a message */
+#[derive(Default)]
+struct Found {
+    plain: Option<String>,
+    html: Option<String>,
+    attachments: Vec<Attachment>,
+}
+fn walk(raw: &[u8], found: &mut Found) {
+    let (head, body) = split_head(raw);
+    let fields = headers(head);
+    let (media_type, params) = with_params(field(&fields, "content-type").unwrap_or("text/plain"));
+    if media_type.starts_with("multipart/") {
+        if let Some(boundary) = params.get("boundary") {
+            for part in parts(body, boundary) {
+                walk(part, found);
+            }
+            return;
+        }
+    }
+    let (disposition, disposition_params) =
+        with_params(field(&fields, "content-disposition").unwrap_or(""));
+    let data = decode_body(
+        body,
+        field(&fields, "content-transfer-encoding").unwrap_or(""),
+    );
+    let name = disposition_params
+        .get("filename")
+        .or_else(|| params.get("name"))
+        .map(|n| decode_words(n));
+    let charset = params.get("charset").map_or("utf-8", |c| c.as_str());
+    let is_body = disposition != "attachment" && name.is_none();
+    match media_type.as_str() {
+        "text/plain" if is_body && found.plain.is_none() => {
+            found.plain = Some(decode_charset(&data, charset))
+        }
+        "text/html" if is_body && found.html.is_none() => {
+            found.html = Some(decode_charset(&data, charset))
+        }
+        _ => {
+            let name = name.unwrap_or_else(|| {
+                let subtype = media_type.rsplit('/').next().unwrap_or("bin");
+                let extension = if media_type == "message/rfc822" {
+                    "eml"
+                } else {
+                    subtype
+                };
+                format!("part{}.{}", found.attachments.len() + 1, extension)
+            });
+            found.attachments.push(Attachment {
+                name,
+                media_type,
+                data,
+            });
+        }
+    }
+}
+/** read a message (RFC 822, with MIME parts) */
+pub fn parse(raw: &[u8]) -> FLResult<Message> {
+    let (head, _body) = split_head(raw);
+    let fields = headers(head);
+    let mut found = Found::default();
+    walk(raw, &mut found);
+    let text = match (found.plain, found.html) {
+        (Some(plain), _) => plain,
+        (None, Some(html)) => crate::fetch::Page::from_html(&html)?.text,
+        (None, None) => "".to_owned(),
+    };
+    Ok(Message {
+        subject: decode_words(field(&fields, "subject").unwrap_or("")),
+        from: decode_words(field(&fields, "from").unwrap_or("")),
+        text: text.replace("\r\n", "\n").trim().to_owned(),
+        attachments: found.attachments,
+    })
+}
+
+/** a name for an attachment that is safe as part of a path */
+fn safe_name(name: &str) -> String {
+    let name = name.rsplit(|c| c == '/' || c == '\\').next().unwrap_or("");
+    let safe: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '.' || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    match safe.trim_start_matches('.') {
+        "" => "attachment".to_owned(),
+        safe => safe.to_owned(),
+    }
+}
+/** whether an attachment can be kept as it is */
+fn is_text(attachment: &Attachment) -> bool {
+    let media_type = attachment.media_type.as_str();
+    (media_type.starts_with("text/")
+        || media_type == "application/json"
+        || media_type == "message/rfc822")
+        && std::str::from_utf8(&attachment.data).is_ok()
+}
+/** make a simple item from a message, with its attachments */
+fn capture(world: &mut World, raw: &[u8]) -> FLResult<ItemRef> {
+    let message = parse(raw)?;
+    let name = match message.subject.trim() {
+        "" => match message.text.lines().next() {
+            Some(line) if !line.trim().is_empty() => line.trim().to_owned(),
+            _ => format!("Message from {}", message.from),
+        },
+        subject => subject.to_owned(),
+    };
+    trace(&format!(
+        "capturing '{}' with {} attachments",
+        name,
+        message.attachments.len()
+    ));
+    let base = ItemBaseForSerde {
+        ident: "".to_owned(),
+        type_name: "Simple".to_owned(),
+        ..ItemBaseForSerde::default()
+    };
+    let mut vals = HashMap::new();
+    vals.insert("name".to_owned(), name);
+    vals.insert("text".to_owned(), message.text.clone());
+    let item_ref = world.make_item("Simple", &base, &vals)?;
+    if message.attachments.is_empty() {
+        return Ok(item_ref);
+    }
+    let ident = item_ref.deref().borrow().ident();
+    let mut links = vec![];
+    for attachment in &message.attachments {
+        let path = format!(
+            "{}/{}/{}",
+            crate::bookmark::ATTACHMENTS_DIR,
+            ident,
+            safe_name(&attachment.name)
+        );
+        let path = if is_text(attachment) {
+            world.add_attachment(&path, &String::from_utf8_lossy(&attachment.data))?;
+            path
+        } else {
+            let path = format!("{}{}", path, BASE64_SUFFIX);
+            world.add_attachment(&path, &base64::encode(&attachment.data))?;
+            path
+        };
+        links.push(format!(
+            "* [{}]({})",
+            attachment.name.replace('[', "(").replace(']', ")"),
+            crate::assets::url(&format!("repo/{}", path))
+        ));
+    }
+    vals.insert(
+        "text".to_owned(),
+        format!("{}\n\nAttachments:\n\n{}\n", message.text, links.join("\n"))
+            .trim_start()
+            .to_owned(),
+    );
+    {
+        let mut item = item_ref.deref().borrow_mut();
+        item.set_data(&vals, world)?;
+        world.persist_change_as(&mut item, "attach to")?;
+    }
+    Ok(item_ref)
+}
+/** make an item from a message given as it is, and show it */
+pub fn capture_mail(world: &mut World, raw: &str) -> fanling_interface::ResponseResult {
+    world.begin_batch();
+    let made = capture(world, raw.as_bytes());
+    world.end_batch()?;
+    let item_ref = made?;
+    let mut resp = item_ref.borrow_mut().for_show(world)?;
+    resp.show_toast(&format!("Saved '{}'", item_ref.borrow().description()));
+    #[cfg(test)]
+    {
+        resp.set_test_data("ident", &item_ref.borrow().ident());
+        resp.set_test_data(
+            "attachments",
+            &format!("{}", parse(raw.as_bytes())?.attachments.len()),
+        );
+    }
+    Ok(resp)
+}
+
+/** a response from the IMAP server, with any literals in it */
+struct Response {
+    /** the text of the response (without the literals) */
+    line: String,
+    literals: Vec<Vec<u8>>,
+}
+/** the length of the literal at the end of the line (as `{123}`), if there is one */
+fn literal_length(line: &[u8]) -> Option<usize> {
+    let line = String::from_utf8_lossy(line);
+    let line = line.trim_end();
+    let open = line.rfind('{')?;
+    line.strip_suffix('}')?[open + 1..].parse().ok()
+}
+/** a string quoted for an IMAP command */
+fn quoted(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+/** a connection to an IMAP server */
+struct Imap {
+    reader: BufReader<rustls::StreamOwned<rustls::ClientSession, TcpStream>>,
+    tag: usize,
+}
+impl Imap {
+    /** connect to the server (with TLS), and log in */
+    fn login(options: &MailOptions) -> FLResult<Self> {
+        let (host, port) = options.host_and_port()?;
+        let mut config = rustls::ClientConfig::new();
+        config
+            .root_store
+            .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+        let name = webpki::DNSNameRef::try_from_ascii_str(&host)
+            .map_err(|_| fanling_error!(&format!("bad host name {}", host)))?;
+        let session = rustls::ClientSession::new(&Arc::new(config), name);
+        let tcp = TcpStream::connect((host.as_str(), port))?;
+        tcp.set_read_timeout(Some(Duration::from_millis(TIMEOUT_MS)))?;
+        tcp.set_write_timeout(Some(Duration::from_millis(TIMEOUT_MS)))?;
+        let mut imap = Self {
+            reader: BufReader::new(rustls::StreamOwned::new(session, tcp)),
+            tag: 0,
+        };
+        let greeting = imap.read_line()?;
+        trace(&format!(
+            "connected: {}",
+            String::from_utf8_lossy(&greeting).trim()
+        ));
+        imap.command(&format!(
+            "LOGIN {} {}",
+            quoted(&options.user),
+            quoted(&options.password)
+        ))?;
+        Ok(imap)
+    }
+    fn read_line(&mut self) -> FLResult<Vec<u8>> {
+        let mut line = vec![];
+        if self.reader.read_until(b'\n', &mut line)? == 0 {
+            return Err(fanling_error!("the IMAP server closed the connection"));
+        }
+        Ok(line)
+    }
+    /** send a command, giving the untagged responses if it succeeds */
+    fn command(&mut self, command: &str) -> FLResult<Vec<Response>> {
+        self.tag += 1;
+        let tag = format!("f{} ", self.tag);
+        let verb = command.split(' ').take(2).collect::<Vec<&str>>().join(" ");
+        let stream = self.reader.get_mut();
+        stream.write_all(format!("{}{}\r\n", tag, command).as_bytes())?;
+        stream.flush()?;
+        let mut responses = vec![];
+        loop {
+            let mut line = self.read_line()?;
+            let mut literals = vec![];
+            while let Some(length) = literal_length(&line) {
+                let mut literal = vec![0; length];
+                self.reader.read_exact(&mut literal)?;
+                literals.push(literal);
+                line.extend(self.read_line()?);
+            }
+            let line = String::from_utf8_lossy(&line).trim_end().to_owned();
+            if let Some(status) = line.strip_prefix(&tag) {
+                if status.starts_with("OK") {
+                    return Ok(responses);
+                }
+                return Err(fanling_error!(&format!("IMAP {} failed: {}", verb, status)));
+            }
+            responses.push(Response { line, literals });
+        }
+    }
+    /** the uids of the unread messages in the folder */
+    fn unread(&mut self, folder: &str) -> FLResult<Vec<String>> {
+        self.command(&format!("SELECT {}", quoted(folder)))?;
+        Ok(self
+            .command("UID SEARCH UNSEEN")?
+            .iter()
+            .filter_map(|r| r.line.strip_prefix("* SEARCH"))
+            .flat_map(|uids| uids.split_whitespace().map(|u| u.to_owned()))
+            .take(MAX_MESSAGES)
+            .collect())
+    }
+    /** the message, without marking it as read */
+    fn fetch(&mut self, uid: &str) -> FLResult<Vec<u8>> {
+        self.command(&format!("UID FETCH {} BODY.PEEK[]", uid))?
+            .into_iter()
+            .find_map(|r| r.literals.into_iter().next())
+            .ok_or_else(|| fanling_error!(&format!("no message {}", uid)))
+    }
+    fn mark_read(&mut self, uid: &str) -> FLResult<Vec<Response>> {
+        self.command(&format!("UID STORE {} +FLAGS (\\Seen)", uid))
+    }
+}
+/** capture the unread messages in the mailbox (if there is one), giving how many were captured */
+pub fn poll(world: &mut World) -> FLResult<Option<usize>> {
+    let options = match world.mail() {
+        Some(options) => options.clone(),
+        None => return Ok(None),
+    };
+    let mut imap = Imap::login(&options)?;
+    let uids = imap.unread(&options.folder)?;
+    trace(&format!("{} unread in {}", uids.len(), options.folder));
+    let mut captured = 0;
+    world.begin_batch();
+    for uid in &uids {
+        let outcome = imap
+            .fetch(uid)
+            .and_then(|raw| capture(world, &raw))
+            .and_then(|_| imap.mark_read(uid));
+        match outcome {
+            Ok(_) => captured += 1,
+            Err(e) => trace(&format!("could not capture message {}: {:?}", uid, e)),
+        }
+    }
+    world.end_batch()?;
+    if let Err(e) = imap.command("LOGOUT") {
+        trace(&format!("could not log out: {:?}", e));
+    }
+    Ok(Some(captured))
+}
+/** capture the unread messages, and say how many were captured */
+pub fn poll_mail(world: &mut World) -> fanling_interface::ResponseResult {
+    let mut resp = fanling_interface::Response::new();
+    match poll(world) {
+        Ok(Some(captured)) => resp.show_toast(&format!("Captured {} messages.", captured)),
+        Ok(None) => resp.show_toast("No mailbox has been set up."),
+        Err(e) => {
+            trace(&format!("could not fetch mail: {:?}", e));
+            resp.show_toast(&format!("Could not fetch mail: {}", e))
+        }
+    }
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("mail {}", Colour::Fixed(0).on(Colour::Fixed(223)).paint(m));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn plain_message() -> FLResult<()> {
+        let raw = b"From: =?iso-8859-1?Q?Ren=E9?= <rene@example.com>\r\nSubject: =?utf-8?B?Q2Fmw6k=?=\r\n =?utf-8?Q?_menu?=\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: quoted-printable\r\n\r\nSoup of the day: leek=\r\n and potato.\r\nCo=C3=BBt: 5=E2=82=AC\r\n";
+        let message = parse(raw)?;
+        assert_eq!("Café menu", message.subject);
+        assert_eq!("René <rene@example.com>", message.from);
+        assert_eq!("Soup of the day: leek and potato.\nCoût: 5€", message.text);
+        assert!(message.attachments.is_empty());
+        Ok(())
+    }
+    #[test]
+    fn multipart_message() -> FLResult<()> {
+        let raw = "Subject: Report\nContent-Type: multipart/mixed; boundary=\"outer\"\n\nPreamble.\n--outer\nContent-Type: multipart/alternative; boundary=inner\n\n--inner\nContent-Type: text/plain\n\nSee attached.\n--inner\nContent-Type: text/html\n\n<p>See <b>attached</b>.</p>\n--inner--\n--outer\nContent-Type: image/png; name=\"dot.png\"\nContent-Disposition: attachment; filename*=utf-8''d%C3%B6t.png\nContent-Transfer-Encoding: base64\n\niVBORw0K\nGgo=\n--outer\nContent-Type: text/csv\nContent-Disposition: attachment; filename=\"figures.csv\"\n\na,b\n1,2\n--outer--\nEpilogue.\n";
+        let message = parse(raw.as_bytes())?;
+        assert_eq!("Report", message.subject);
+        assert_eq!("See attached.", message.text);
+        assert_eq!(2, message.attachments.len());
+        assert_eq!("döt.png", message.attachments[0].name);
+        assert_eq!("image/png", message.attachments[0].media_type);
+        assert_eq!(b"\x89PNG\r\n\x1a\n".to_vec(), message.attachments[0].data);
+        assert!(!is_text(&message.attachments[0]));
+        assert_eq!(b"a,b\n1,2".to_vec(), message.attachments[1].data);
+        assert!(is_text(&message.attachments[1]));
+        Ok(())
+    }
+    #[test]
+    fn html_only() -> FLResult<()> {
+        let raw = b"Subject: News\nContent-Type: text/html\n\n<html><body><p>Hello <i>there</i></p></body></html>\n";
+        assert!(parse(raw)?.text.contains("Hello"));
+        Ok(())
+    }
+    #[test]
+    fn imap_details() {
+        assert_eq!(
+            Some(1234),
+            literal_length(b"* 3 FETCH (UID 7 BODY[] {1234}\r\n")
+        );
+        assert_eq!(None, literal_length(b"* 3 FETCH (FLAGS (\\Seen))\r\n"));
+        assert_eq!("\"a \\\"b\\\" \\\\c\"", quoted("a \"b\" \\c"));
+        assert_eq!("-passwd", safe_name("../..-passwd"));
+        assert_eq!("report-1.pdf", safe_name("C:\\docs\\report 1.pdf"));
+        assert_eq!("attachment", safe_name(".."));
+    }
+}
@@ -0,0 +1,124 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! maintenance of the repository, which grows with each commit: the
+maintenance page (reached from the settings page) shows how big the
+repository is, how many objects of each type it has and its largest
+files, and can pack its loose objects or run `git gc`.
+
+Packing is done with libgit2, so can be done anywhere; `git gc` needs
+the `git` program, so if that fails the message is shown rather than
+being an error. */
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+
+/** how many of the largest files are shown */
+const LARGEST: usize = 10;
+
+/** a file, for the template */
+struct Largest {
+    path: String,
+    size: String,
+}
+/** template data for the maintenance page */
+#[derive(Template)]
+#[template(path = "maintenance.html", print = "none")]
+struct MaintenanceTemplate {
+    total: String,
+    packs: usize,
+    pack_size: String,
+    loose_objects: usize,
+    loose_size: String,
+    commits: usize,
+    trees: usize,
+    blobs: usize,
+    tags: usize,
+    largest: Vec<Largest>,
+    message: String,
+}
+/** a size in bytes, in the units that suit it */
+fn size(world: &World, bytes: u64) -> String {
+    const KB: f64 = 1024.0;
+    let bytes = bytes as f64;
+    if bytes < KB {
+        format!("{} B", bytes)
+    } else if bytes < KB * KB {
+        format!("{} KB", world.format().format_number(bytes / KB, 1))
+    } else {
+        format!("{} MB", world.format().format_number(bytes / KB / KB, 1))
+    }
+}
+/** show how big the repository is */
+pub fn show_maintenance(world: &mut World, message: &str) -> fanling_interface::ResponseResult {
+    let report = world.size_report(LARGEST)?;
+    trace(&format!("showing maintenance {:?}", report));
+    let t = MaintenanceTemplate {
+        total: size(world, report.total_bytes),
+        packs: report.packs,
+        pack_size: size(world, report.pack_bytes),
+        loose_objects: report.loose_objects,
+        loose_size: size(world, report.loose_bytes),
+        commits: report.commits,
+        trees: report.trees,
+        blobs: report.blobs,
+        tags: report.tags,
+        largest: report
+            .largest
+            .iter()
+            .map(|(path, bytes)| Largest {
+                path: path.clone(),
+                size: size(world, *bytes),
+            })
+            .collect(),
+        message: message.to_owned(),
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    resp.set_test_data(
+        "maintenance",
+        &format!(
+            "blobs:{},loose:{},packs:{}",
+            t.blobs, t.loose_objects, t.packs
+        ),
+    );
+    Ok(resp)
+}
+/** pack the loose objects, then show the maintenance page */
+pub fn repack_and_show(world: &mut World) -> fanling_interface::ResponseResult {
+    let report = world.repack()?;
+    trace(&format!("repacked {:?}", report));
+    let message = format!(
+        "{} {}, {} {}",
+        world.i18n().tr("Objects packed:"),
+        report.packed,
+        world.i18n().tr("loose objects removed:"),
+        report.removed
+    );
+    show_maintenance(world, &message)
+}
+/** run `git gc`, then show the maintenance page (with the error if it failed) */
+pub fn gc_and_show(world: &mut World) -> fanling_interface::ResponseResult {
+    let message = match world.gc() {
+        Ok(()) => world.i18n().tr("The garbage has been collected."),
+        Err(e) => {
+            trace(&format!("gc failed: {}", e));
+            format!(
+                "{} {}",
+                world.i18n().tr("Could not collect the garbage:"),
+                e
+            )
+        }
+    };
+    show_maintenance(world, &message)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "maintenance {}",
+        Colour::Fixed(15).on(Colour::Fixed(58)).paint(m)
+    );
+}
@@ -2,17 +2,416 @@
 License, v. 2.0. If a copy of the MPL was not distributed with this
 file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-/*! implementation of Markdown rendering */
-use pulldown_cmark::{html, Options, Parser};
-/** render some Markdown text to HTML */
-pub fn render(markdown_input: &str) -> String {
+/*! implementation of Markdown rendering, including syntax highlighting of code
+and wiki links to other items (`[[some-ident]]` or `[[some-ident|label]]`),
+citations of references (`[@some-key]`, see [`crate::reference`]) and
+mathematics (`$x^2$`, see [`crate::math`]).
+A wiki link to the old ident of an item that has been renamed is a
+link to the item (see [`crate::alias`]).
+
+Each heading has an id made from its text (`## Getting started` is
+`heading-getting-started`), so that a page can have a table of contents
+(see [`contents`]) and a wiki link can go to a heading of an item
+(`[[some-ident#getting-started]]`). */
+use pulldown_cmark::{html, CodeBlockKind, CowStr, Event, Options, Parser, Tag};
+use regex::{Captures, Regex};
+use std::collections::BTreeMap;
+use syntect::highlighting::ThemeSet;
+use syntect::html::highlighted_html_for_string;
+use syntect::parsing::SyntaxSet;
+
+/** the theme used for highlighting code */
+const THEME: &str = "InspiredGitHub";
+/** the start of the id of a heading, so that it cannot be the same as the id of anything else on the page */
+pub(crate) const HEADING_PREFIX: &str = "heading-";
+
+thread_local! {
+    /** the syntax definitions, which are slow to load so are only loaded once */
+    static SYNTAXES: SyntaxSet = SyntaxSet::load_defaults_newlines();
+    /** the highlighting themes */
+    static THEMES: ThemeSet = ThemeSet::load_defaults();
+    /** wiki links and citations, compiled once as they are looked for in each text of every render */
+    static WIKI_LINK: Regex = Regex::new(&format!(
+        r"\[\[\s*((?:[\w.-]+:)?[\w./-]+)(?:#([\w-]+))?\s*(?:\|([^\[\]]+))?\]\]|\[@({})\]",
+        crate::reference::KEY_PATTERN
+    ))
+    .expect("bad wiki link regex");
+    /** the headings with ids in rendered HTML, for tables of contents */
+    static HEADING: Regex = Regex::new(&format!(
+        r#"(?s)<h([1-6]) id="{}([^"]*)">(.*?)</h[1-6]>"#,
+        HEADING_PREFIX
+    ))
+    .expect("bad heading regex");
+    /** an HTML tag */
+    static TAG: Regex = Regex::new(r"<[^>]*>").expect("bad tag regex");
+    /** the placeholders for formulas while the markdown is rendered (see [`crate::math`]) */
+    pub(crate) static MATH_PLACEHOLDER: Regex =
+        Regex::new(&crate::math::placeholder_pattern()).expect("bad placeholder regex");
+}
+
+/** what rendering depends on besides the text, which is that of the
+repository shown (see [`crate::world::World::render_context`]) */
+#[derive(Debug, Clone, Copy)]
+pub struct RenderContext<'a> {
+    /** the current idents of the items that have been renamed, by their old idents, for wiki links */
+    pub aliases: &'a BTreeMap<String, String>,
+    /** the name of the current repository, as links may be qualified with it (see [`crate::repos`]) */
+    pub repo: &'a str,
+    /** how formulas are shown (from the engine's options) */
+    pub math: crate::math::MathRendering,
+    /** the emoji and smart punctuation used (from the settings of the repository) */
+    pub typography: crate::typography::Typography,
+}
+impl<'a> RenderContext<'a> {
+    /** the current ident of an item, given it or an old ident of it
+    (an item in another repository is left with its qualified ident, so
+    that showing it makes that repository current) */
+    fn current_ident(&self, ident: &str) -> String {
+        match crate::repos::local_link(ident, self.repo) {
+            Some(local) => self
+                .aliases
+                .get(local)
+                .cloned()
+                .unwrap_or_else(|| local.to_owned()),
+            None => ident.to_owned(),
+        }
+    }
+}
+
+/** a heading in some rendered HTML */
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Heading {
+    /** 1 for the biggest */
+    pub level: u32,
+    /** the part of the id after [`HEADING_PREFIX`] */
+    pub id: String,
+    /** the text, as HTML */
+    pub text: String,
+}
+impl Heading {
+    /** the id of the heading in the page */
+    pub fn anchor(&self) -> String {
+        format!("{}{}", HEADING_PREFIX, self.id)
+    }
+}
+
+/** the part of a heading's id made from its text: lower case, with
+dashes instead of spaces and no punctuation */
+fn slug(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.trim().chars() {
+        if c.is_alphanumeric() || c == '_' {
+            slug.extend(c.to_lowercase());
+        } else if (c.is_whitespace() || c == '-') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        "section".to_owned()
+    } else {
+        slug.to_owned()
+    }
+}
+
+/** the extensions to markdown that are used */
+pub(crate) fn options() -> Options {
     let mut options = Options::empty();
     options.insert(Options::ENABLE_STRIKETHROUGH);
     options.insert(Options::ENABLE_TABLES);
     options.insert(Options::ENABLE_FOOTNOTES);
     options.insert(Options::ENABLE_TASKLISTS);
-    let parser = Parser::new_ext(markdown_input, options);
+    options
+}
+
+/** render some Markdown text to HTML. Fenced code blocks that name a
+language (eg ```` ```rust ````) are syntax highlighted (or are
+pictures, for diagrams, see [`crate::diagram`]), wiki links
+and citations outside code become links to the items and headings
+have ids (with a number added to any that would be the same as an
+earlier one). */
+pub fn render(markdown_input: &str, context: &RenderContext) -> String {
+    let (markdown_input, maths) = crate::math::extract(markdown_input, context.math);
+    let parser = Parser::new_ext(&markdown_input, options());
+    let mut events = vec![];
+    let mut code: Option<(String, String)> = None;
+    /* text is collected, as the parser can split a wiki link into several pieces */
+    let mut text = String::new();
+    let mut in_code_block = false;
+    /* where the start of the heading is in the events, and its text so far */
+    let mut heading: Option<(usize, String)> = None;
+    let mut slugs: BTreeMap<String, usize> = BTreeMap::new();
+    /* the last character of the text before, for smart quotes (see [`crate::typography`]) */
+    let mut before: Option<char> = None;
+    for event in parser {
+        if let Some((_, heading_text)) = &mut heading {
+            match &event {
+                Event::Text(t) | Event::Code(t) => heading_text.push_str(t),
+                _ => {}
+            }
+        }
+        if let Event::Text(t) = &event {
+            if code.is_none() && !in_code_block {
+                text.push_str(t);
+                continue;
+            }
+        }
+        if !text.is_empty() {
+            let linked = with_wiki_links(&text, before, context);
+            events.push(Event::Html(CowStr::from(linked)));
+            before = text.chars().last();
+            text.clear();
+        }
+        match &event {
+            Event::Code(c) => before = c.chars().last(),
+            Event::SoftBreak | Event::HardBreak => before = Some(' '),
+            Event::Start(Tag::Emphasis)
+            | Event::Start(Tag::Strong)
+            | Event::Start(Tag::Strikethrough)
+            | Event::Start(Tag::Link(..)) => {}
+            Event::Start(_) => before = None,
+            _ => {}
+        }
+        if let Some((lang, body)) = &mut code {
+            match event {
+                Event::Text(text) => body.push_str(&text),
+                Event::End(Tag::CodeBlock(_)) => {
+                    let html =
+                        crate::diagram::render(lang, body).unwrap_or_else(|| highlight(body, lang));
+                    events.push(Event::Html(CowStr::from(html)));
+                    code = None;
+                }
+                _ => {}
+            }
+            continue;
+        }
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(ref lang)))
+                if !lang.trim().is_empty() =>
+            {
+                code = Some((lang.to_string(), String::new()));
+            }
+            Event::Start(Tag::CodeBlock(_)) => {
+                in_code_block = true;
+                events.push(event);
+            }
+            Event::End(Tag::CodeBlock(_)) => {
+                in_code_block = false;
+                events.push(event);
+            }
+            Event::Start(Tag::Heading(_)) => {
+                heading = Some((events.len(), String::new()));
+                events.push(event);
+            }
+            Event::End(Tag::Heading(level)) => {
+                if let Some((start, heading_text)) = heading.take() {
+                    let mut id = slug(&heading_text);
+                    let count = slugs.entry(id.clone()).or_insert(0);
+                    if *count > 0 {
+                        id = format!("{}-{}", id, count);
+                    }
+                    *count += 1;
+                    events[start] = Event::Html(CowStr::from(format!(
+                        "<h{} id=\"{}{}\">",
+                        level, HEADING_PREFIX, id
+                    )));
+                }
+                events.push(event);
+            }
+            _ => events.push(event),
+        }
+    }
+    if !text.is_empty() {
+        let linked = with_wiki_links(&text, before, context);
+        events.push(Event::Html(CowStr::from(linked)));
+    }
     let mut html_output = String::new();
-    html::push_html(&mut html_output, parser);
-    html_output
+    html::push_html(&mut html_output, events.into_iter());
+    crate::math::restore(&html_output, &maths)
+}
+
+/** the headings in some rendered HTML, so that the page can have a
+table of contents (the text has no links or other tags) */
+pub(crate) fn contents(html: &str) -> Vec<Heading> {
+    HEADING.with(|heading| {
+        TAG.with(|tag| {
+            heading
+                .captures_iter(html)
+                .map(|caps| Heading {
+                    level: caps[1].parse().unwrap_or(1),
+                    id: caps[2].to_owned(),
+                    text: tag.replace_all(&caps[3], "").trim().to_owned(),
+                })
+                .collect()
+        })
+    })
+}
+/** the HTML that, added to a page, makes the user interface show the heading with the id (after [`HEADING_PREFIX`]) */
+pub(crate) fn scroll_to(id: &str) -> String {
+    format!(
+        "<span class=\"scrollto\" data-target=\"{}{}\"></span>",
+        HEADING_PREFIX,
+        escape(id)
+    )
+}
+
+/** some text as HTML, with each wiki link (or citation) made into a
+link to the item and the typography of the repository, `before` being
+the character before the text (if any) */
+fn with_wiki_links(text: &str, before: Option<char>, context: &RenderContext) -> String {
+    let mut html = String::new();
+    let mut last = 0;
+    WIKI_LINK.with(|re| {
+        for caps in re.captures_iter(text) {
+            let whole = caps.get(0).expect("no match");
+            let previous = text[..last].chars().last().or(before);
+            html.push_str(&escape(&crate::typography::apply(
+                &text[last..whole.start()],
+                previous,
+                context.typography,
+            )));
+            html.push_str(&wiki_link(&caps, context));
+            last = whole.end();
+        }
+    });
+    let previous = text[..last].chars().last().or(before);
+    html.push_str(&escape(&crate::typography::apply(
+        &text[last..],
+        previous,
+        context.typography,
+    )));
+    html
+}
+/** the HTML for a wiki link (the ident only has word characters, dots, dashes and the slashes of namespaces,
+and may be qualified with the name of a repository), which may be to a heading of the item */
+fn wiki_link(caps: &Captures, context: &RenderContext) -> String {
+    if let Some(key) = caps.get(4) {
+        return citation(key.as_str());
+    }
+    let ident = &caps[1];
+    let unlabelled = match caps.get(2) {
+        Some(heading) => format!("{}#{}", ident, heading.as_str()),
+        None => ident.to_owned(),
+    };
+    let label = caps
+        .get(3)
+        .map_or(unlabelled.as_str(), |l| l.as_str().trim());
+    let target = context.current_ident(ident);
+    let action = match caps.get(2) {
+        Some(heading) => format!("{{\"ShowHeading\": \"{}\"}}", heading.as_str()),
+        None => "\"Show\"".to_owned(),
+    };
+    format!(
+        "<span class=\"itemlink\" id=\"{}\" onclick='invoke({{ t:\"\",  i: \"{}\", a: {}}})'>{}</span>",
+        target,
+        target,
+        action,
+        escape(label)
+    )
+}
+/** the HTML for a citation, which shows the reference with the key when clicked */
+fn citation(key: &str) -> String {
+    format!(
+        "<span class=\"itemlink citation\" onclick='invoke({{ t:\"\",  i: \"\", a: {{\"Cite\": \"{}\"}}}})'>[{}]</span>",
+        key, key
+    )
+}
+/** escape text for HTML */
+pub(crate) fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/** highlight some code as HTML. The language can be a name (eg `Rust`)
+or a file extension (eg `rs`); unknown languages are shown as plain
+text. */
+pub fn highlight(code: &str, language: &str) -> String {
+    SYNTAXES.with(|syntaxes| {
+        let syntax = syntaxes
+            .find_syntax_by_token(language.trim())
+            .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+        THEMES.with(|themes| {
+            highlighted_html_for_string(code, syntaxes, syntax, &themes.themes[THEME])
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    /** render with no aliases */
+    fn render(markdown_input: &str) -> String {
+        super::render(
+            markdown_input,
+            &RenderContext {
+                aliases: &BTreeMap::new(),
+                repo: "main",
+                math: crate::math::MathRendering::default(),
+                typography: crate::typography::Typography::default(),
+            },
+        )
+    }
+    #[test]
+    fn highlighting() {
+        let html = render("Some code:\n\n```rust\nfn main() {}\n```\n\n```\nplain\n```\n");
+        assert!(html.contains("<pre style="));
+        assert!(html.contains("<span style="));
+        assert!(html.contains("main"));
+        assert!(html.contains("<pre><code>plain"));
+        let plain = highlight("<b>", "no such language");
+        assert!(plain.contains("&lt;b&gt;"));
+    }
+    #[test]
+    fn wiki_links() {
+        let html = render("See [[shopping-a1]] and [[todo-a2 | my list]], not `[[code]]` if a < b.");
+        assert!(html.contains(r#"i: "shopping-a1", a: "Show"})'>shopping-a1</span>"#));
+        assert!(html.contains(">my list</span>, not <code>[[code]]</code> if a &lt; b."));
+        let mut aliases = BTreeMap::new();
+        aliases.insert("shopping-a1".to_owned(), "groceries-a1".to_owned());
+        let context = RenderContext {
+            aliases: &aliases,
+            repo: "main",
+            math: crate::math::MathRendering::default(),
+            typography: crate::typography::Typography::default(),
+        };
+        let html = super::render("See [[shopping-a1]].", &context);
+        assert!(html.contains(r#"i: "groceries-a1", a: "Show"})'>shopping-a1</span>"#));
+        /* qualified with the current repository, or with another one */
+        let html = super::render(
+            "See [[main:shopping-a1]] and [[work:plan-a3|the plan]].",
+            &context,
+        );
+        assert!(html.contains(r#"i: "groceries-a1", a: "Show"})'>main:shopping-a1</span>"#));
+        assert!(html.contains(r#"i: "work:plan-a3", a: "Show"})'>the plan</span>"#));
+    }
+    #[test]
+    fn headings() {
+        let html = render("# Getting started\n\n## The `main` fn!\n\ntext\n\n## Getting Started\n");
+        assert!(html.contains(r#"<h1 id="heading-getting-started">Getting started</h1>"#));
+        assert!(html.contains(r#"<h2 id="heading-the-main-fn">The <code>main</code> fn!</h2>"#));
+        assert!(html.contains(r#"<h2 id="heading-getting-started-1">"#));
+        let contents = contents(&html);
+        assert_eq!(3, contents.len());
+        assert_eq!(
+            Heading {
+                level: 2,
+                id: "the-main-fn".to_owned(),
+                text: "The main fn!".to_owned()
+            },
+            contents[1]
+        );
+        let html = render("See [[notes-a1#getting-started]].");
+        assert!(html.contains(
+            r#"i: "notes-a1", a: {"ShowHeading": "getting-started"}})'>notes-a1#getting-started</span>"#
+        ));
+    }
+    #[test]
+    fn citations() {
+        let html = render("As shown [@turing1936], but not [@not a key].");
+        assert!(html.contains(
+            r#"a: {"Cite": "turing1936"}})'>[turing1936]</span>, but not [@not a key]."#
+        ));
+    }
 }
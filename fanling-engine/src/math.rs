@@ -0,0 +1,276 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! mathematics in markdown, written in TeX between dollar signs:
+`$e^{i\pi} + 1 = 0$` within a line, or `$$...$$` for a formula on
+its own (which can be over several lines).
+
+The formulas are taken out of the text before it is parsed as markdown
+(so that markdown does not treat backslashes, underscores or stars in
+them as its own), then put back in the HTML. Depending on the engine's
+option (see [`MathRendering`]), each is either made into MathML, which
+the web view shows with no help, or left as TeX marked up for a
+renderer in the page (KaTeX, which the main page loads). A dollar sign
+followed by a space or a digit after the closing one (such as in
+`$5 and $10`) is not mathematics, and nor is one escaped with a
+backslash or in code (a code span, or a code block, fenced or
+indented, as the markdown parser finds them). */
+use ansi_term::Colour;
+use pulldown_cmark::{Event, Parser, Tag};
+use regex::Captures;
+use std::ops::Range;
+
+/** the start of the placeholder for a formula while the markdown is rendered */
+const START: char = '\u{E000}';
+/** the end of the placeholder for a formula */
+const END: char = '\u{E001}';
+
+/** how formulas are shown */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MathRendering {
+    /** made into MathML by the engine */
+    MathMl,
+    /** left as TeX for KaTeX in the page */
+    Client,
+}
+impl MathRendering {
+    /** the rendering with the name (as in the options), or MathML if it is not known */
+    pub fn parse(name: &str) -> Self {
+        match name.trim().to_lowercase().as_str() {
+            "client" | "katex" => MathRendering::Client,
+            _ => MathRendering::MathMl,
+        }
+    }
+}
+impl Default for MathRendering {
+    fn default() -> Self {
+        MathRendering::MathMl
+    }
+}
+
+/** the HTML for a formula, shown as the rendering says */
+fn rendered(tex: &str, display: bool, rendering: MathRendering) -> String {
+    let tex = tex.trim();
+    match rendering {
+        MathRendering::MathMl => {
+            let style = if display {
+                latex2mathml::DisplayStyle::Block
+            } else {
+                latex2mathml::DisplayStyle::Inline
+            };
+            match latex2mathml::latex_to_mathml(tex, style) {
+                Ok(mathml) => mathml,
+                Err(e) => {
+                    trace(&format!("cannot render {}: {:?}", tex, e));
+                    format!(
+                        "<code class=\"math-error\">{}</code>",
+                        crate::markdown::escape(tex)
+                    )
+                }
+            }
+        }
+        MathRendering::Client if display => format!(
+            "<span class=\"math display\">\\[{}\\]</span>",
+            crate::markdown::escape(tex)
+        ),
+        MathRendering::Client => format!(
+            "<span class=\"math inline\">\\({}\\)</span>",
+            crate::markdown::escape(tex)
+        ),
+    }
+}
+/** the placeholder for the nth formula */
+fn placeholder(n: usize) -> String {
+    format!("{}{}{}", START, n, END)
+}
+/** the pattern for a placeholder, with the number of the formula as the first group */
+pub(crate) fn placeholder_pattern() -> String {
+    format!("{}([0-9]+){}", START, END)
+}
+
+/** where the inline formula starting at `start` (a dollar sign) ends,
+if it is one (it cannot go on to another line) */
+fn inline_end(chars: &[(usize, char)], start: usize) -> Option<usize> {
+    match chars.get(start + 1) {
+        Some((_at, c)) if !c.is_whitespace() && *c != '$' => {}
+        _ => return None,
+    }
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i].1 {
+            '\n' => return None,
+            '\\' => i += 1,
+            '$' if !chars[i - 1].1.is_whitespace() => {
+                return match chars.get(i + 1) {
+                    Some((_at, c)) if c.is_ascii_digit() => None,
+                    _ => Some(i),
+                };
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/** the byte ranges of the code in the markdown: code spans, and code
+blocks whether fenced or indented */
+fn code_ranges(text: &str) -> Vec<Range<usize>> {
+    Parser::new_ext(text, crate::markdown::options())
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::CodeBlock(_)) | Event::Code(_) => Some(range),
+            _ => None,
+        })
+        .collect()
+}
+
+/** the markdown with each formula (outside code) replaced by a
+placeholder, and the HTML for each formula */
+pub(crate) fn extract(text: &str, rendering: MathRendering) -> (String, Vec<String>) {
+    let code = code_ranges(text);
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut out = String::new();
+    let mut maths = vec![];
+    /* the TeX of a display formula that has been started */
+    let mut display: Option<String> = None;
+    let mut i = 0;
+    while i < chars.len() {
+        let (at, c) = chars[i];
+        let next = chars.get(i + 1).map(|(_at, c)| *c);
+        if let Some(tex) = &mut display {
+            if c == '$' && next == Some('$') {
+                maths.push(rendered(tex, true, rendering));
+                out.push_str(&placeholder(maths.len() - 1));
+                display = None;
+                i += 2;
+            } else {
+                tex.push(c);
+                i += 1;
+            }
+            continue;
+        }
+        /* code is left as it is */
+        if let Some(range) = code.iter().find(|r| r.contains(&at)) {
+            while i < chars.len() && chars[i].0 < range.end {
+                out.push(chars[i].1);
+                i += 1;
+            }
+            continue;
+        }
+        match c {
+            '\\' => {
+                out.extend(chars[i..(i + 2).min(chars.len())].iter().map(|(_at, c)| c));
+                i += 2;
+            }
+            '$' if next == Some('$') => {
+                display = Some(String::new());
+                i += 2;
+            }
+            '$' => match inline_end(&chars, i) {
+                Some(end) => {
+                    let tex: String = chars[i + 1..end].iter().map(|(_at, c)| c).collect();
+                    maths.push(rendered(&tex, false, rendering));
+                    out.push_str(&placeholder(maths.len() - 1));
+                    i = end + 1;
+                }
+                None => {
+                    out.push('$');
+                    i += 1;
+                }
+            },
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    /* a display formula with no end is not one */
+    if let Some(tex) = display {
+        out.push_str("$$");
+        out.push_str(&tex);
+    }
+    (out, maths)
+}
+
+/** the rendered HTML with the formulas put back in place of their placeholders */
+pub(crate) fn restore(html: &str, maths: &[String]) -> String {
+    if maths.is_empty() {
+        return html.to_owned();
+    }
+    crate::markdown::MATH_PLACEHOLDER.with(|placeholder| {
+        placeholder
+            .replace_all(html, |caps: &Captures| {
+                caps[1]
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| maths.get(n))
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .into_owned()
+    })
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("math {}", Colour::Fixed(0).on(Colour::Fixed(153)).paint(m));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn extracting() {
+        let (text, maths) = extract(
+            "If $a_1 = b$ then `$x$` costs $5 and $10, not \\$3.\n\n$$\n\\sum_i x_i\n$$\n",
+            MathRendering::Client,
+        );
+        assert_eq!(
+            format!(
+                "If {}0{} then `$x$` costs $5 and $10, not \\$3.\n\n{}1{}\n",
+                START, END, START, END
+            ),
+            text
+        );
+        assert_eq!(
+            vec![
+                r#"<span class="math inline">\(a_1 = b\)</span>"#,
+                r#"<span class="math display">\[\sum_i x_i\]</span>"#
+            ],
+            maths
+        );
+    }
+    #[test]
+    fn indented_code() {
+        let (text, maths) = extract(
+            "Costs:\n\n    $x$ and $y$\n\nbut $z$, and\n\n* a list\n\n      $in code$\n",
+            MathRendering::Client,
+        );
+        assert_eq!(
+            format!(
+                "Costs:\n\n    $x$ and $y$\n\nbut {}0{}, and\n\n* a list\n\n      $in code$\n",
+                START, END
+            ),
+            text
+        );
+        assert_eq!(1, maths.len());
+    }
+    #[test]
+    fn mathml() {
+        let aliases = std::collections::BTreeMap::new();
+        let html = crate::markdown::render(
+            "so $x^2$ and\n\n```\n$not$\n```\n",
+            &crate::markdown::RenderContext {
+                aliases: &aliases,
+                repo: "main",
+                math: MathRendering::MathMl,
+                typography: crate::typography::Typography::default(),
+            },
+        );
+        assert!(html.contains("<math"));
+        assert!(html.contains("<msup>"));
+        assert!(html.contains("$not$"));
+    }
+}
@@ -0,0 +1,453 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! implements [`Meeting`] items (notes of a meeting, with its date and attendees).
+
+Action items are written in the notes as `- [ ] something to do`.
+Extracting them creates a task for each one, as a child of the
+meeting, assigned to the attendee it mentions (as `@name`, or by their
+full name). All the tasks are made in a single commit. */
+use crate::item::{
+    Ident, Item, ItemBase, ItemBaseForSerde, ItemData, ItemListEntryList, NewBaseTemplate,
+    ShowBaseTemplate,
+};
+use crate::markdown;
+use crate::shared::{merge_strings, FLResult, FanlingError, NullResult};
+use crate::world::{ActionResponse, World};
+use ansi_term::Colour;
+use askama::Template;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::boxed::Box;
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** starts an action item in the notes */
+const ACTION_PREFIX: &str = "- [ ]";
+
+/** data for a meeting item */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Meeting {
+    /** what the meeting was about */
+    name: String,
+    /** when the meeting was held */
+    date: NaiveDate,
+    /** idents of the contacts who attended */
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attendees: Vec<Ident>,
+    /** the notes in MarkDown format */
+    #[serde(default)]
+    notes: String,
+    /** the action items that have already been made into tasks */
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    extracted: Vec<String>,
+}
+impl Meeting {
+    /** create a new [Meeting]  */
+    pub fn new() -> Self {
+        Self {
+            name: "".to_owned(),
+            date: chrono::Local::today().naive_local(),
+            attendees: vec![],
+            notes: "".to_owned(),
+            extracted: vec![],
+        }
+    }
+    fn set_from_yaml_basic(&mut self, yaml: &serde_yaml::Value) -> NullResult {
+        *self = serde_yaml::from_value(yaml.clone())?;
+        Ok(())
+    }
+    /** the attendees with their names */
+    fn attendee_names(&self, world: &World) -> Vec<(Ident, String)> {
+        self.attendees
+            .iter()
+            .map(|ident| {
+                let name = world
+                    .get_item_parts(ident)
+                    .ok()
+                    .and_then(|(_base, values)| {
+                        values.get("name").and_then(|n| n.as_str()).map(str::to_owned)
+                    })
+                    .unwrap_or_else(|| ident.clone());
+                (ident.clone(), name)
+            })
+            .collect()
+    }
+    /** the contacts, with the attendees selected, for the edit form */
+    fn contacts(&self, world: &mut World) -> FLResult<ItemListEntryList> {
+        let mut contacts = world.search_type("Contact")?;
+        for entry in contacts.entries.iter_mut() {
+            entry.selected = self.attendees.contains(&entry.link.ident);
+        }
+        Ok(contacts)
+    }
+    /** the number of action items that tasks have not been made for */
+    fn pending(&self) -> usize {
+        action_items(&self.notes)
+            .iter()
+            .filter(|a| !self.extracted.contains(a))
+            .count()
+    }
+    /** make tasks for the action items not yet extracted, returning their idents */
+    fn extract_actions(&mut self, base: &ItemBase, world: &mut World) -> FLResult<Vec<Ident>> {
+        let attendees = self.attendee_names(world);
+        let mut made = vec![];
+        for text in action_items(&self.notes) {
+            if self.extracted.contains(&text) {
+                continue;
+            }
+            let task_base = ItemBaseForSerde {
+                ident: "".to_owned(),
+                type_name: "Task".to_owned(),
+                parent: Some(base.get_ident()),
+                ..ItemBaseForSerde::default()
+            };
+            let mut vals = HashMap::new();
+            vals.insert("name".to_owned(), text.clone());
+            vals.insert(
+                "text".to_owned(),
+                format!("From the meeting '{}'.", self.name),
+            );
+            vals.insert("context".to_owned(), "default_context".to_owned());
+            vals.insert(
+                "assignee".to_owned(),
+                mentioned(&text, &attendees).unwrap_or_default(),
+            );
+            let task = world.make_item("Task", &task_base, &vals)?;
+            made.push(task.borrow().ident());
+            self.extracted.push(text);
+        }
+        trace(&format!("made tasks {:?}", &made));
+        Ok(made)
+    }
+}
+
+/** the text of each action item in the notes */
+fn action_items(notes: &str) -> Vec<String> {
+    notes
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with(ACTION_PREFIX))
+        .map(|line| line[ACTION_PREFIX.len()..].trim().to_owned())
+        .filter(|text| !text.is_empty())
+        .collect()
+}
+/** the ident of the attendee mentioned in the text, as `@name` (their
+first name, or their name without spaces) or by their full name */
+fn mentioned(text: &str, attendees: &[(Ident, String)]) -> Option<Ident> {
+    let lower = text.to_lowercase();
+    let handles: Vec<String> = lower
+        .split_whitespace()
+        .filter(|w| w.starts_with('@'))
+        .map(|w| {
+            w[1..]
+                .trim_end_matches(|c: char| !c.is_alphanumeric())
+                .to_owned()
+        })
+        .collect();
+    attendees
+        .iter()
+        .find(|(_ident, name)| {
+            let name = name.to_lowercase();
+            let first = name.split_whitespace().next().unwrap_or("").to_owned();
+            let joined: String = name.split_whitespace().collect();
+            handles.iter().any(|h| *h == first || *h == joined) || lower.contains(&name)
+        })
+        .map(|(ident, _name)| ident.clone())
+}
+
+impl crate::item::ItemData for Meeting {
+    fn for_edit(
+        &mut self,
+        base: &mut ItemBase,
+        is_for_update: bool,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let nt = NewMeetingTemplate {
+            data: &self,
+            base: NewBaseTemplate::from_base(base, is_for_update, world)?,
+            date: world.format().format_date(self.date),
+            contacts: self.contacts(world)?,
+            broken_notes: self.notes.replace("\n", "&#10;"),
+        };
+        let mut resp = fanling_interface::Response::new();
+        resp.clear_errors(vec!["name-error".to_owned(), "date-error".to_owned()]);
+        resp.add_tag("content", &(nt.render()?));
+        #[cfg(test)]
+        {
+            resp.set_test_data("ident", &base.get_ident());
+        }
+        trace(&format!("for edit {:?}", &resp));
+        Ok(resp)
+    }
+    /** tasks can be made from any action items that have not had them made yet */
+    fn available_actions(&self, base: &ItemBase) -> Vec<crate::item::AvailableAction> {
+        let pending = self.pending();
+        if pending == 0 {
+            return vec![];
+        }
+        vec![crate::item::AvailableAction::new(
+            base,
+            crate::Action::ExtractActions,
+            &format!("Make {} task(s) from action items", pending),
+            "☑",
+        )]
+    }
+    fn for_show(
+        &mut self,
+        base: &mut ItemBase,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let t = ShowMeetingTemplate {
+            name: self.name.clone(),
+            date: world.format().format_date(self.date),
+            attendees: self
+                .attendee_names(world)
+                .into_iter()
+                .map(|(ident, name)| Attendee { ident, name })
+                .collect(),
+            rendered_notes: markdown::render(&self.notes, &world.render_context()),
+            base: ShowBaseTemplate::from_base(base, world)?
+                .with_actions(base, self.available_actions(base))
+                .with_stats(self.stats(base, world)?),
+        };
+        let mut resp = fanling_interface::Response::new();
+        resp.add_tag("content", &(t.render()?));
+        #[cfg(test)]
+        {
+            resp.set_test_data("ident", &base.get_ident());
+            resp.set_test_data("attendees", &self.attendees.join(","));
+        }
+        trace(&format!("for show {:?}", &resp));
+        Ok(resp)
+    }
+    fn to_yaml(&self, base: &crate::item::ItemBase) -> Result<Vec<u8>, FanlingError> {
+        let for_serde = MeetingForSerde {
+            base: crate::item::ItemBaseForSerde::from_base(base)?,
+            data: self.clone(),
+        };
+        let yaml = serde_yaml::to_vec(&for_serde)?;
+        trace(&format!("yaml is {}", String::from_utf8_lossy(&yaml)));
+        Ok(yaml)
+    }
+    fn is_open(&self) -> bool {
+        true
+    }
+    fn is_ready(&mut self, _world: &mut World) -> FLResult<bool> {
+        Ok(false)
+    }
+    /** can be turned into an ident */
+    fn descr_for_ident(&self) -> String {
+        self.name.clone()
+    }
+    /** an English-language description */
+    fn description(&self) -> String {
+        format!("{} ({})", self.name, self.date)
+    }
+    /** a description that can be used in a list */
+    fn description_for_list(&self) -> String {
+        self.description()
+    }
+    fn set_data(&mut self, vals: &HashMap<String, String>, world: &mut World) -> NullResult {
+        match vals.get("name") {
+            Some(s) => self.name = s.to_string(),
+            _ => return Err(fanling_error!("no name")),
+        }
+        if let Some(date) = vals.get("date") {
+            self.date = world.format().parse_date_time(date)?.date();
+        }
+        if let Some(attendees) = vals.get("attendees") {
+            self.attendees = attendees
+                .split(',')
+                .map(str::trim)
+                .filter(|a| !a.is_empty())
+                .map(str::to_owned)
+                .collect();
+        }
+        self.notes = vals.get("notes").cloned().unwrap_or_default();
+        Ok(())
+    }
+    fn set_from_yaml(&mut self, yaml: &serde_yaml::Value, _world: &mut World) -> NullResult {
+        self.set_from_yaml_basic(yaml)
+    }
+    /** do action for meeting */
+    fn do_action(
+        &mut self,
+        base: &mut ItemBase,
+        action: crate::Action,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        match &action {
+            crate::Action::ExtractActions => {
+                let made = self.extract_actions(base, world)?;
+                let mut resp = self.for_show(base, world)?;
+                #[cfg(test)]
+                resp.set_test_data("tasks", &made.join(","));
+                resp.add_tag(
+                    "message",
+                    &format!("{} task(s) made from the action items.", made.len()),
+                );
+                Ok(resp)
+            }
+            _ => Err(fanling_error!(&format!("invalid action {:?}", action)).into()),
+        }
+    }
+    /** copy from another item data (the action items have not been extracted from the copy) */
+    fn fanling_clone(&self) -> FLResult<Box<dyn ItemData>> {
+        Ok(Box::new(Self {
+            extracted: vec![],
+            ..self.clone()
+        }))
+    }
+    /** transitional to fix old data */
+    fn fix_data(
+        &self,
+        _yaml: &serde_yaml::Value,
+        _base: &mut ItemBase,
+        _world: &mut World,
+    ) -> NullResult {
+        Ok(())
+    }
+}
+impl Default for Meeting {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+#[derive(Serialize, Deserialize)]
+struct MeetingForSerde {
+    #[serde(flatten)]
+    base: crate::item::ItemBaseForSerde,
+    #[serde(flatten)]
+    data: Meeting,
+}
+/** template data for creating a new meeting item */
+#[derive(Template)]
+#[template(path = "new-meeting.html", print = "none")]
+struct NewMeetingTemplate<'a> {
+    data: &'a Meeting,
+    base: NewBaseTemplate,
+    date: String,
+    contacts: ItemListEntryList,
+    broken_notes: String,
+}
+
+/** an attendee, for the template */
+struct Attendee {
+    ident: Ident,
+    name: String,
+}
+/** template data for showing a meeting item */
+#[derive(Template)]
+#[template(path = "show-meeting.html", print = "none")]
+struct ShowMeetingTemplate {
+    name: String,
+    date: String,
+    attendees: Vec<Attendee>,
+    rendered_notes: String,
+    base: ShowBaseTemplate,
+}
+
+/** policy for the meeting item type*/
+#[derive(Debug)]
+pub struct MeetingTypePolicy {}
+impl MeetingTypePolicy {
+    pub fn new() -> Self {
+        Self {}
+    }
+    pub fn new_boxed() -> Box<Self> {
+        Box::new(Self::new())
+    }
+}
+impl crate::item::ItemTypePolicy for MeetingTypePolicy {
+    fn kind(&self) -> crate::item::ItemKind {
+        crate::item::ItemKind::Meeting
+    }
+    fn make_raw(&self, item_type: crate::item::ItemTypeRef) -> Item {
+        Item::new_with_data(item_type, Box::new(Meeting::new()))
+    }
+    fn resolve_conflict_both(
+        &self,
+        _world: &mut World,
+        _ancestor: &Value,
+        ours: &Value,
+        theirs: &Value,
+    ) -> FLResult<Box<dyn ItemData>> {
+        let mut om = Meeting::new();
+        om.set_from_yaml_basic(&ours)?;
+        let mut tm = Meeting::new();
+        tm.set_from_yaml_basic(&theirs)?;
+        om.name = merge_strings(&om.name, &tm.name);
+        om.notes = merge_strings(&om.notes, &tm.notes);
+        for a in tm.attendees {
+            if !om.attendees.contains(&a) {
+                om.attendees.push(a);
+            }
+        }
+        for e in tm.extracted {
+            if !om.extracted.contains(&e) {
+                om.extracted.push(e);
+            }
+        }
+        Ok(Box::new(om))
+    }
+    fn check_valid(
+        &mut self,
+        _base: &ItemBaseForSerde,
+        vals: &HashMap<String, String>,
+        world: &mut World,
+    ) -> ActionResponse {
+        let mut ar = ActionResponse::new();
+        ar.assert(
+            !vals.get("name").map_or(true, |n| n.is_empty()),
+            "name-error",
+            "Name must be non-blank.",
+        );
+        ar.assert(
+            vals.get("date")
+                .map_or(true, |d| world.format().parse_date_time(d).is_ok()),
+            "date-error",
+            "Date is not valid.",
+        );
+        ar
+    }
+    /** get item data from serde value */
+    fn from_yaml(&self, values: &Value, world: &mut World) -> FLResult<Box<dyn ItemData>> {
+        let mut m = Meeting::default();
+        m.set_from_yaml(&values, world)?;
+        Ok(Box::new(m))
+    }
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "meeting {}",
+        Colour::Fixed(11).on(Colour::Fixed(233)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn action_items_and_mentions() {
+        let notes = "Discussed budget.\n- [ ] send figures @jo\n  - [ ] book room for Sam Smith\n- [x] done already\n- [ ] ";
+        let items = action_items(notes);
+        assert_eq!(vec!["send figures @jo", "book room for Sam Smith"], items);
+        let attendees = vec![
+            ("jo-a1".to_owned(), "Jo Bloggs".to_owned()),
+            ("sam-a2".to_owned(), "Sam Smith".to_owned()),
+        ];
+        assert_eq!(Some("jo-a1".to_owned()), mentioned(&items[0], &attendees));
+        assert_eq!(Some("sam-a2".to_owned()), mentioned(&items[1], &attendees));
+        assert_eq!(None, mentioned("nobody", &attendees));
+    }
+}
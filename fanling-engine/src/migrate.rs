@@ -0,0 +1,238 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! versions of the format of the YAML of items, and the migrations
+that upgrade old YAML to the current version.
+
+Each item records the version of the format it was written in (as
+`schema_version`; items without one are version 0). When an item is
+read, the migrations for the versions after its own are applied to its
+YAML (see [`upgrade`]), and it is written in the current version the
+next time it is saved. [`migrate_all`] saves all the items that are in
+an old version, in a single commit (which is rolled back if any of the
+items saved is not valid, see [`crate::rollback`]).
+
+Items of types not known to this version, and items written by a newer
+version, are left as they are. Private items are only upgraded when
+they are saved, as their data is encrypted. */
+use crate::shared::{FLResult, NullResult};
+use crate::world::World;
+use ansi_term::Colour;
+use serde_yaml::{Mapping, Value};
+use std::ops::DerefMut;
+
+/** the version of the format of the YAML written by this version */
+pub const SCHEMA_VERSION: u32 = 1;
+
+/** a change to the format of the YAML of items */
+struct Migration {
+    /** the version that it upgrades to */
+    version: u32,
+    /** the type of item that it applies to (or all types if `None`) */
+    type_name: Option<&'static str>,
+    /** what it does */
+    descr: &'static str,
+    /** change the YAML */
+    upgrade: fn(&mut Mapping),
+}
+
+/** the migrations, in order of version */
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        type_name: None,
+        descr: "rename whencreated to when_created",
+        upgrade: rename_when_created,
+    },
+    Migration {
+        version: 1,
+        type_name: Some("Task"),
+        descr: "rename whenclosed to when_closed",
+        upgrade: rename_when_closed,
+    },
+    Migration {
+        version: 1,
+        type_name: Some("Task"),
+        descr: "replace the closed flag by the status",
+        upgrade: closed_to_status,
+    },
+];
+
+/** a YAML string */
+fn key(k: &str) -> Value {
+    Value::String(k.to_owned())
+}
+/** rename a field (if it is there, and there is no field with the new name) */
+fn rename(m: &mut Mapping, from: &str, to: &str) {
+    if m.contains_key(&key(to)) {
+        return;
+    }
+    if let Some(v) = m.remove(&key(from)) {
+        m.insert(key(to), v);
+    }
+}
+
+/** version 1: the old name of when an item was created */
+fn rename_when_created(m: &mut Mapping) {
+    rename(m, "whencreated", "when_created")
+}
+/** version 1: the old name of when a task was closed */
+fn rename_when_closed(m: &mut Mapping) {
+    rename(m, "whenclosed", "when_closed")
+}
+/** version 1: the old flag for a closed task */
+fn closed_to_status(m: &mut Mapping) {
+    if let Some(Value::Bool(true)) = m.remove(&key("closed")) {
+        m.insert(key("status"), key("Closed"));
+    }
+}
+
+/** the version of the format of the YAML of an item */
+pub fn schema_version(values: &Value) -> u32 {
+    values
+        .get("schema_version")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0) as u32
+}
+/** whether the YAML of an item is in an old version that can be upgraded */
+pub fn needs_upgrade(values: &Value) -> bool {
+    let known = values
+        .get("type")
+        .and_then(|t| t.as_str())
+        .and_then(crate::kinds::find)
+        .is_some();
+    known && schema_version(values) < SCHEMA_VERSION
+}
+/** upgrade the YAML of an item to the current version, returning whether it has changed */
+pub fn upgrade(values: &mut Value) -> bool {
+    if !needs_upgrade(values) {
+        return false;
+    }
+    let from = schema_version(values);
+    let type_name = values
+        .get("type")
+        .and_then(|t| t.as_str())
+        .and_then(crate::kinds::find)
+        .map(|k| k.name);
+    let mapping = match values.as_mapping_mut() {
+        Some(mapping) => mapping,
+        None => return false,
+    };
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|m| m.version > from)
+        .filter(|m| m.type_name.is_none() || m.type_name == type_name)
+    {
+        trace(&format!(
+            "{} (version {})",
+            migration.descr, migration.version
+        ));
+        (migration.upgrade)(mapping);
+    }
+    mapping.insert(key("schema_version"), Value::Number(SCHEMA_VERSION.into()));
+    true
+}
+
+/** save an item in the current version */
+fn save(world: &mut World, ident: &str) -> NullResult {
+    let item_ref = world.get_item(ident.to_owned(), "Simple".to_owned())?;
+    let mut item = item_ref.borrow_mut();
+    world.persist_change_as(item.deref_mut(), "migrate")
+}
+/** save all the items that are in an old version (in the current
+version), in a single commit, returning how many were saved and how
+many private items were passed over (none are saved if the commit is
+rolled back, and the report is kept in the world) */
+pub fn migrate_all(world: &mut World) -> FLResult<(usize, usize)> {
+    let mut old = vec![];
+    let mut private = 0;
+    for (ident, values) in world.stored_values()? {
+        if !needs_upgrade(&values) {
+            continue;
+        }
+        if crate::private::is_sealed(&values) {
+            private += 1;
+        } else {
+            old.push(ident);
+        }
+    }
+    let before = world.head_id()?;
+    world.begin_batch();
+    let mut result = Ok(());
+    for ident in &old {
+        result = save(world, ident);
+        if result.is_err() {
+            break;
+        }
+    }
+    /* the batch is ended before any error is returned, so that later changes are committed */
+    world.end_batch()?;
+    result?;
+    if let Some(report) = crate::rollback::check_since(world, "migration", &before)? {
+        world.set_recovery(report);
+        return Ok((0, private));
+    }
+    trace(&format!(
+        "migrated {} items ({} private items passed over)",
+        old.len(),
+        private
+    ));
+    Ok((old.len(), private))
+}
+/** save all the items that are in an old version, and say so in a toast */
+pub fn migrate_and_show(world: &mut World) -> fanling_interface::ResponseResult {
+    let (migrated, private) = migrate_all(world)?;
+    if let Some(report) = world.take_recovery() {
+        return crate::rollback::show(&report);
+    }
+    let mut resp = fanling_interface::Response::new();
+    resp.show_toast(&match (migrated, private) {
+        (0, 0) => "All the items are up to date.".to_owned(),
+        (_, 0) => format!("Upgraded {} items.", migrated),
+        _ => format!(
+            "Upgraded {} items ({} private items will be upgraded when next saved).",
+            migrated, private
+        ),
+    });
+    #[cfg(test)]
+    {
+        resp.set_test_data("migrated", &format!("{}", migrated));
+        resp.set_test_data("private", &format!("{}", private));
+    }
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "migrate {}",
+        Colour::Fixed(11).on(Colour::Fixed(236)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn upgrades() -> FLResult<()> {
+        let mut values: Value =
+            serde_yaml::from_str("ident: t1\ntype: todo\nclosed: true\nwhenclosed: x\n")?;
+        assert!(upgrade(&mut values));
+        assert_eq!(SCHEMA_VERSION, schema_version(&values));
+        assert_eq!(
+            Some("Closed"),
+            values.get("status").and_then(|v| v.as_str())
+        );
+        assert_eq!(
+            Some("x"),
+            values.get("when_closed").and_then(|v| v.as_str())
+        );
+        assert!(values.get("closed").is_none());
+        assert!(!upgrade(&mut values));
+        let mut foreign: Value = serde_yaml::from_str("ident: r1\ntype: Recipe\n")?;
+        assert!(!upgrade(&mut foreign));
+        assert!(foreign.get("schema_version").is_none());
+        Ok(())
+    }
+}
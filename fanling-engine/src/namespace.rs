@@ -0,0 +1,209 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! namespaces: folders for items, given by the start of their idents.
+
+An ident such as `projects/fanling/roadmap` is in the namespace
+`projects/fanling`, which is itself in `projects`. The item is stored
+in the matching directory of the repository
+(`projects/fanling/roadmap.page`), so the items can be browsed in the
+same way with other Git tools. An item is put into a namespace (or
+moved out of one) by renaming it (see [`crate::alias`]).
+
+This module has the namespace page, which shows the namespaces and
+items in a namespace, and the lists scoped to a namespace: the name of
+a list followed by `@` and the namespace (such as
+`open@projects/fanling`) has only the items in that namespace (and
+those within it). */
+use crate::item::Ident;
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use std::collections::BTreeMap;
+
+/** separates the parts of a namespace */
+pub const SEPARATOR: char = '/';
+/** separates the list from the namespace in a scoped list */
+const SCOPE_SEPARATOR: char = '@';
+
+/** the namespace of the ident (empty if it is not in one) */
+pub fn namespace_of(ident: &str) -> &str {
+    ident.rfind(SEPARATOR).map_or("", |i| &ident[..i])
+}
+/** whether the ident is in the namespace or in one within it (all idents are in the empty namespace) */
+pub fn is_in(ident: &str, namespace: &str) -> bool {
+    namespace.is_empty()
+        || (ident.starts_with(namespace) && ident[namespace.len()..].starts_with(SEPARATOR))
+}
+/** what is wrong with the namespaces of the ident, if anything */
+pub fn check_ident(ident: &str) -> Option<&'static str> {
+    let parts: Vec<&str> = ident.split(SEPARATOR).collect();
+    if parts
+        .iter()
+        .any(|p| p.is_empty() || *p == "." || *p == "..")
+    {
+        Some("Each part of a namespace must have a name.")
+    } else if parts.len() > 1 && parts[0] == crate::bookmark::ATTACHMENTS_DIR {
+        Some("That namespace is kept for the files attached to items.")
+    } else {
+        None
+    }
+}
+/** the list and the namespace it is scoped to (if it is) */
+pub fn scoped_list(list: &str) -> (&str, Option<&str>) {
+    match list.find(SCOPE_SEPARATOR) {
+        Some(i) => (&list[..i], Some(&list[i + 1..])),
+        None => (list, None),
+    }
+}
+/** the name of the list scoped to the namespace */
+fn scoped_list_name(list: &str, namespace: &str) -> String {
+    if namespace.is_empty() {
+        list.to_owned()
+    } else {
+        format!("{}{}{}", list, SCOPE_SEPARATOR, namespace)
+    }
+}
+
+/** a namespace within the one shown */
+struct Child {
+    namespace: String,
+    /** the last part of its name */
+    label: String,
+    /** how many items it has (including those in namespaces within it) */
+    count: usize,
+}
+/** an item directly in the namespace shown */
+struct Entry {
+    ident: Ident,
+    descr: String,
+}
+/** template data for the namespace page */
+#[derive(Template)]
+#[template(path = "namespace.html", print = "none")]
+struct NamespaceTemplate<'a> {
+    namespace: &'a str,
+    /** the namespaces that this one is in, outermost first, with the last part of each name */
+    parents: Vec<(String, String)>,
+    children: Vec<Child>,
+    entries: Vec<Entry>,
+    /** the lists scoped to this namespace, with their labels */
+    lists: Vec<(String, &'static str)>,
+    /** how many items each list shows at first */
+    limit: usize,
+}
+/** show the namespaces and items directly in a namespace (the top level if empty) */
+pub fn show_namespace(world: &mut World, namespace: &str) -> fanling_interface::ResponseResult {
+    let namespace = namespace.trim_matches(SEPARATOR);
+    trace(&format!("showing namespace '{}'", namespace));
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    let mut entries = vec![];
+    for entry in world.search_all()?.entries {
+        let ident = entry.link.ident;
+        if !is_in(&ident, namespace) {
+            continue;
+        }
+        let rest = if namespace.is_empty() {
+            &ident[..]
+        } else {
+            &ident[namespace.len() + 1..]
+        };
+        match rest.find(SEPARATOR) {
+            Some(i) => *counts.entry(rest[..i].to_owned()).or_default() += 1,
+            None => entries.push(Entry {
+                ident,
+                descr: entry.descr,
+            }),
+        }
+    }
+    entries.sort_by(|a, b| a.descr.to_lowercase().cmp(&b.descr.to_lowercase()));
+    let children: Vec<Child> = counts
+        .into_iter()
+        .map(|(label, count)| Child {
+            namespace: scoped_ident(namespace, &label),
+            label,
+            count,
+        })
+        .collect();
+    let mut parents = vec![];
+    let mut ancestor = namespace;
+    while !ancestor.is_empty() {
+        ancestor = namespace_of(ancestor);
+        let label = ancestor.rsplit(SEPARATOR).next().unwrap_or("").to_owned();
+        parents.insert(0, (ancestor.to_owned(), label));
+    }
+    let t = NamespaceTemplate {
+        namespace,
+        parents,
+        lists: vec![
+            (scoped_list_name("open", namespace), "Open items"),
+            (scoped_list_name("all", namespace), "All items"),
+        ],
+        children,
+        entries,
+        limit: crate::world::PAGE_SIZE,
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    {
+        resp.set_test_data(
+            "namespaces",
+            &t.children
+                .iter()
+                .map(|c| format!("{}:{}", c.namespace, c.count))
+                .collect::<Vec<String>>()
+                .join(","),
+        );
+        resp.set_test_data(
+            "items",
+            &t.entries
+                .iter()
+                .map(|e| e.ident.clone())
+                .collect::<Vec<String>>()
+                .join(","),
+        );
+    }
+    Ok(resp)
+}
+/** the ident (or namespace) with the name in the namespace */
+fn scoped_ident(namespace: &str, name: &str) -> String {
+    if namespace.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}{}{}", namespace, SEPARATOR, name)
+    }
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "namespace {}",
+        Colour::Fixed(15).on(Colour::Fixed(30)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn namespaces() {
+        assert_eq!("projects/fanling", namespace_of("projects/fanling/roadmap"));
+        assert_eq!("", namespace_of("roadmap"));
+        assert!(is_in("projects/fanling/roadmap", "projects"));
+        assert!(!is_in("projectsx/roadmap", "projects"));
+        assert!(!is_in("projects", "projects"));
+        assert!(is_in("roadmap", ""));
+        assert_eq!(
+            ("open", Some("projects/fanling")),
+            scoped_list("open@projects/fanling")
+        );
+        assert_eq!(("open", None), scoped_list("open"));
+        assert_eq!("all", scoped_list_name("all", ""));
+        assert!(check_ident("projects/fanling/roadmap").is_none());
+        assert!(check_ident("projects//roadmap").is_some());
+        assert!(check_ident("../roadmap").is_some());
+        assert!(check_ident("attachments/roadmap").is_some());
+    }
+}
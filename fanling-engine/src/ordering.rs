@@ -0,0 +1,365 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! the order of the entries in the list views. A list can be sorted
+by name, by when the items were created or modified (newest first), by
+due date (the deadline of a task, soonest first, with the items that
+have none last), or in the manual order, which is the default.
+Whatever the order, children stay under their parents: only siblings
+are sorted.
+
+The manual order is that of the sort key of each item, with the ident
+breaking ties. Moving an item up or down gives it a new key between
+those of its new neighbours, so that only the item moved is changed,
+and when two devices reorder different items at the same time, the
+merged order is the same on both. Only when there is no key between
+the neighbours (as when neither has ever been moved, so both have the
+blank key) are the siblings all given new, evenly spaced keys. */
+use crate::item::{Ident, ItemListEntry, ItemListEntryList};
+use crate::shared::{FLResult, NullResult};
+use crate::world::World;
+use ansi_term::Colour;
+use chrono::NaiveDateTime;
+use std::cmp::Ordering;
+use std::ops::Deref;
+
+/** the configuration key for the order of the list views */
+pub const LIST_ORDER_KEY: &str = "fanling.listorder";
+
+/** an order for the list views */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortOrder {
+    Manual,
+    Name,
+    Created,
+    Modified,
+    Due,
+}
+impl SortOrder {
+    /** all the orders, as offered to the user */
+    pub const ALL: [SortOrder; 5] = [
+        SortOrder::Manual,
+        SortOrder::Name,
+        SortOrder::Created,
+        SortOrder::Modified,
+        SortOrder::Due,
+    ];
+    /** the order with the name, or the manual order if it is not known */
+    pub fn parse(name: &str) -> Self {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|o| o.name() == name)
+            .unwrap_or(SortOrder::Manual)
+    }
+    /** the name, as stored in the configuration */
+    pub fn name(self) -> &'static str {
+        match self {
+            SortOrder::Manual => "manual",
+            SortOrder::Name => "name",
+            SortOrder::Created => "created",
+            SortOrder::Modified => "modified",
+            SortOrder::Due => "due",
+        }
+    }
+    /** the label, for the user */
+    pub fn label(self) -> &'static str {
+        match self {
+            SortOrder::Manual => "Manual order",
+            SortOrder::Name => "Name",
+            SortOrder::Created => "Newest first",
+            SortOrder::Modified => "Recently modified",
+            SortOrder::Due => "Due date",
+        }
+    }
+}
+
+/** the order chosen for the list views */
+pub fn list_order(world: &World) -> FLResult<SortOrder> {
+    Ok(SortOrder::parse(
+        &world.config_value(LIST_ORDER_KEY)?.unwrap_or_default(),
+    ))
+}
+/** choose the order for the list views */
+pub fn set_list_order(world: &mut World, order: SortOrder) -> NullResult {
+    trace(&format!("list order now {}", order.name()));
+    world.set_config_value(LIST_ORDER_KEY, order.name())
+}
+
+/** an entry with what it is sorted by and its children */
+struct Node {
+    entry: ItemListEntry,
+    text: String,
+    time: Option<NaiveDateTime>,
+    children: Vec<Node>,
+}
+impl Node {
+    fn new(world: &World, entry: ItemListEntry, order: SortOrder) -> Self {
+        let parts = world.get_item_parts(&entry.link.ident).ok();
+        let time = parts.and_then(|(base, values)| match order {
+            SortOrder::Created => Some(base.when_created),
+            SortOrder::Modified => Some(base.when_modified),
+            SortOrder::Due => values
+                .get("deadline")
+                .cloned()
+                .and_then(|d| serde_yaml::from_value::<NaiveDateTime>(d).ok())
+                .filter(|d| !crate::task::is_no_time(d)),
+            _ => None,
+        });
+        Self {
+            text: entry.descr.to_lowercase(),
+            entry,
+            time,
+            children: vec![],
+        }
+    }
+    /** compare for the order (other than the manual order) */
+    fn compare(&self, other: &Self, order: SortOrder) -> Ordering {
+        let by_key = match order {
+            SortOrder::Manual => Ordering::Equal,
+            SortOrder::Name => self.text.cmp(&other.text),
+            SortOrder::Created | SortOrder::Modified => other.time.cmp(&self.time),
+            SortOrder::Due => match (self.time, other.time) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            },
+        };
+        by_key.then_with(|| self.entry.link.ident.cmp(&other.entry.link.ident))
+    }
+}
+/** add a node at `depth` below the last of the nodes */
+fn insert(nodes: &mut Vec<Node>, node: Node, depth: i8) {
+    if depth > 0 {
+        if let Some(last) = nodes.last_mut() {
+            return insert(&mut last.children, node, depth - 1);
+        }
+    }
+    nodes.push(node);
+}
+fn sort_nodes(nodes: &mut Vec<Node>, order: SortOrder) {
+    nodes.sort_by(|a, b| a.compare(b, order));
+    for node in nodes.iter_mut() {
+        sort_nodes(&mut node.children, order);
+    }
+}
+fn flatten(nodes: Vec<Node>, entries: &mut Vec<ItemListEntry>) {
+    for node in nodes {
+        entries.push(node.entry);
+        flatten(node.children, entries);
+    }
+}
+/** the list (in the manual order) sorted into the order */
+pub fn sort_list(world: &World, list: ItemListEntryList, order: SortOrder) -> ItemListEntryList {
+    if order == SortOrder::Manual {
+        return list;
+    }
+    let mut roots = vec![];
+    for entry in list.entries {
+        let level = entry.level;
+        insert(&mut roots, Node::new(world, entry, order), level);
+    }
+    sort_nodes(&mut roots, order);
+    let mut entries = vec![];
+    flatten(roots, &mut entries);
+    ItemListEntryList::from_vec(entries)
+}
+
+/** the idents of the item and its siblings (those with the same parent)
+in the list, in order, leaving out items from other repositories (which
+can not be moved here) */
+fn siblings(list: &ItemListEntryList, ident: &str) -> Vec<Ident> {
+    let mut ancestors: Vec<&Ident> = vec![];
+    let mut parents = vec![];
+    for entry in &list.entries {
+        ancestors.truncate(entry.level.max(0) as usize);
+        parents.push((ancestors.last().copied(), &entry.link.ident));
+        ancestors.push(&entry.link.ident);
+    }
+    let parent = match parents.iter().find(|(_, i)| *i == ident) {
+        Some((parent, _)) => *parent,
+        None => return vec![],
+    };
+    parents
+        .into_iter()
+        .filter(|(p, i)| *p == parent && crate::repos::split_qualified(i).is_none())
+        .map(|(_, i)| i.clone())
+        .collect()
+}
+
+/** the letters used in sort keys, so that they compare as they look */
+const FIRST: u8 = b'a';
+const LETTERS: u32 = 26;
+/** a key that sorts after `low` and before `high` (if there is one), if
+there is such a key and both are made of lower case letters */
+pub fn key_between(low: &str, high: Option<&str>) -> Option<String> {
+    let is_key = |s: &str| s.bytes().all(|b| b.is_ascii_lowercase());
+    if !is_key(low) || !high.map_or(true, is_key) || high.map_or(false, |h| low >= h) {
+        return None;
+    }
+    let low = low.as_bytes();
+    let high = high.map_or(&[][..], str::as_bytes);
+    let mut bounded = !high.is_empty();
+    let mut key = vec![];
+    for i in 0.. {
+        if bounded && i >= high.len() {
+            return None;
+        }
+        let lo = low.get(i).map_or(0, |b| u32::from(b - FIRST));
+        let hi = if bounded {
+            u32::from(high[i] - FIRST)
+        } else {
+            LETTERS
+        };
+        if hi > lo + 1 {
+            key.push(FIRST + ((lo + hi) / 2) as u8);
+            break;
+        }
+        key.push(FIRST + lo as u8);
+        if hi == lo + 1 {
+            bounded = false;
+        }
+    }
+    String::from_utf8(key).ok()
+}
+/** `n` keys that are evenly spaced, leaving room before, after and between them */
+pub fn spaced_keys(n: usize) -> Vec<String> {
+    let mut width = 1;
+    let mut range = u64::from(LETTERS);
+    while range < 2 * (n as u64 + 1) {
+        width += 1;
+        range *= u64::from(LETTERS);
+    }
+    let step = range / (n as u64 + 1);
+    (1..=n as u64)
+        .map(|i| {
+            let mut value = i * step;
+            let mut key = vec![FIRST; width];
+            for pos in (0..width).rev() {
+                key[pos] = FIRST + (value % u64::from(LETTERS)) as u8;
+                value /= u64::from(LETTERS);
+            }
+            String::from_utf8(key).unwrap_or_default()
+        })
+        .collect()
+}
+/** give the item the sort key, if it does not already have it */
+fn set_key(world: &mut World, ident: &Ident, key: &str) -> NullResult {
+    let item_ref = world.get_item(ident.clone(), "Simple".to_owned())?;
+    let mut item = item_ref.deref().borrow_mut();
+    if item.get_sort() != key {
+        item.set_sort(key);
+        world.persist_change_as(&mut item, "move")?;
+    }
+    Ok(())
+}
+/** move the item up or down among its siblings in the manual order of the list */
+pub fn move_in_list(world: &mut World, list: &str, ident: &Ident, up: bool) -> NullResult {
+    let entries = world.list_entries(list)?;
+    let mut sibs = siblings(&entries, ident);
+    trace(&format!(
+        "moving {} {} in {}",
+        ident,
+        if up { "up" } else { "down" },
+        list
+    ));
+    let pos = match sibs.iter().position(|i| i == ident) {
+        Some(p) => p,
+        None => return Ok(()),
+    };
+    let other = if up {
+        match pos.checked_sub(1) {
+            Some(o) => o,
+            None => return Ok(()),
+        }
+    } else if pos + 1 < sibs.len() {
+        pos + 1
+    } else {
+        return Ok(());
+    };
+    let keys: Vec<String> = sibs
+        .iter()
+        .map(|i| {
+            world
+                .get_item_parts(i)
+                .map_or(String::new(), |(b, _)| b.sort)
+        })
+        .collect();
+    let (low, high) = if up {
+        (
+            other.checked_sub(1).map_or("", |i| keys[i].as_str()),
+            Some(keys[other].as_str()),
+        )
+    } else {
+        (
+            keys[other].as_str(),
+            keys.get(other + 1).map(String::as_str),
+        )
+    };
+    if let Some(key) = key_between(low, high) {
+        return set_key(world, ident, &key);
+    }
+    trace("no room between the neighbours, so renumbering");
+    sibs.swap(pos, other);
+    world.begin_batch();
+    let mut result = Ok(());
+    for (sib, key) in sibs.iter().zip(spaced_keys(sibs.len())) {
+        result = set_key(world, sib, &key);
+        if result.is_err() {
+            break;
+        }
+    }
+    /* the batch is ended before any error is returned, so that later changes are committed */
+    world.end_batch()?;
+    result
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "ordering {}",
+        Colour::Fixed(232).on(Colour::Fixed(117)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_between() {
+        assert_eq!(key_between("", None), Some("n".to_owned()));
+        assert_eq!(key_between("n", None), Some("t".to_owned()));
+        assert_eq!(key_between("", Some("b")), Some("an".to_owned()));
+        assert_eq!(key_between("a", Some("b")), Some("an".to_owned()));
+        assert_eq!(key_between("az", Some("b")), Some("azn".to_owned()));
+        assert_eq!(key_between("", Some("a")), None);
+        assert_eq!(key_between("b", Some("b")), None);
+        assert_eq!(key_between("c", Some("b")), None);
+        assert_eq!(key_between("10", None), None);
+        for (low, high) in &[("a", "c"), ("m", "mb"), ("yz", "z"), ("abc", "abd")] {
+            let key = key_between(low, Some(high)).unwrap();
+            assert!(*low < key.as_str() && key.as_str() < *high, "{}", key);
+        }
+    }
+    #[test]
+    fn keys_spaced() {
+        let keys = spaced_keys(3);
+        assert_eq!(keys.len(), 3);
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+        assert!(key_between("", Some(&keys[0])).is_some());
+        assert!(key_between(&keys[2], None).is_some());
+        let many = spaced_keys(100);
+        assert!(many.windows(2).all(|w| w[0] < w[1]));
+        assert!(many.iter().all(|k| k.len() == many[0].len()));
+    }
+    #[test]
+    fn orders() {
+        for order in SortOrder::ALL.iter() {
+            assert_eq!(SortOrder::parse(order.name()), *order);
+        }
+        assert_eq!(SortOrder::parse(""), SortOrder::Manual);
+    }
+}
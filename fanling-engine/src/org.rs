@@ -0,0 +1,602 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! importing and exporting [org-mode](https://orgmode.org) files, so
+that org users can move their notes and tasks to fanling (and back).
+
+Each heading becomes an item, and the text under it (but not the
+headings within it) its text. A heading with a TODO keyword becomes a
+task: `TODO`, `NEXT` and `WAITING` are open, `STARTED` and `DOING` in
+progress, and `DONE` and `CANCELLED` closed (`CLOSED:` giving when).
+The first tag of a task is its context (made if there is none with that
+name), `SCHEDULED:` is when it is shown as ready and `DEADLINE:` its
+deadline. A heading without a keyword becomes a simple item. A heading
+within another becomes a child of its item.
+
+Exporting does the reverse for the tasks and simple items (but not the
+contexts, which are the tags, nor private items). A heading whose name
+is already that of an item of the same type is skipped when importing,
+so exporting and importing again changes nothing. Priorities, property
+drawers and other tags are not kept. */
+use crate::item::{split_data_parts, Ident, ItemBaseForSerde, ItemListEntry, ItemListEntryList};
+use crate::shared::FLResult;
+use crate::task::TaskStatus;
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+/** the keywords of open tasks */
+const OPEN: &[&str] = &["TODO", "NEXT", "WAITING"];
+/** the keywords of tasks in progress */
+const IN_PROGRESS: &[&str] = &["STARTED", "DOING"];
+/** the keywords of closed tasks */
+const CLOSED: &[&str] = &["DONE", "CANCELLED", "CANCELED"];
+/** how times are written in timestamps */
+const TIME_FORMAT: &str = "%H:%M";
+/** how dates and times are given to tasks */
+const DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/** a heading in an org-mode file, with what is under it (but not the headings within it) */
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Heading {
+    /** how many stars it has */
+    pub level: usize,
+    /** the TODO keyword, if it has one */
+    pub keyword: Option<String>,
+    pub title: String,
+    pub tags: Vec<String>,
+    pub scheduled: Option<NaiveDateTime>,
+    pub deadline: Option<NaiveDateTime>,
+    pub closed: Option<NaiveDateTime>,
+    pub body: String,
+}
+impl Heading {
+    /** the status of the task for the keyword (none if it is not a task) */
+    pub fn status(&self) -> Option<TaskStatus> {
+        let keyword = self.keyword.as_deref()?;
+        if IN_PROGRESS.contains(&keyword) {
+            Some(TaskStatus::InProgress)
+        } else if CLOSED.contains(&keyword) {
+            Some(TaskStatus::Closed)
+        } else {
+            Some(TaskStatus::Open)
+        }
+    }
+}
+/** the keyword for the status of a task */
+fn keyword_for(status: TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::Open => OPEN[0],
+        TaskStatus::InProgress => IN_PROGRESS[0],
+        TaskStatus::Closed => CLOSED[0],
+    }
+}
+/** whether the word is a TODO keyword */
+fn is_keyword(word: &str) -> bool {
+    OPEN.contains(&word) || IN_PROGRESS.contains(&word) || CLOSED.contains(&word)
+}
+/** the level of the heading (0 if the line is not one) */
+fn heading_level(line: &str) -> usize {
+    let stars = line.chars().take_while(|c| *c == '*').count();
+    if stars > 0 && line[stars..].starts_with(' ') {
+        stars
+    } else {
+        0
+    }
+}
+/** whether the word is a group of tags, such as `:home:phone:` */
+fn is_tags(word: &str) -> bool {
+    word.len() > 2
+        && word.starts_with(':')
+        && word.ends_with(':')
+        && word[1..word.len() - 1]
+            .split(':')
+            .all(|t| !t.is_empty() && t.chars().all(|c| c.is_alphanumeric() || "_@#%".contains(c)))
+}
+/** the heading from its line (of the level) */
+fn parse_heading(line: &str, level: usize) -> Heading {
+    let mut words: Vec<&str> = line[level..].split_whitespace().collect();
+    let mut heading = Heading {
+        level,
+        ..Heading::default()
+    };
+    if let Some(last) = words.last() {
+        if is_tags(last) {
+            heading.tags = last
+                .trim_matches(':')
+                .split(':')
+                .map(str::to_owned)
+                .collect();
+            words.pop();
+        }
+    }
+    if let Some(first) = words.first() {
+        if is_keyword(first) {
+            heading.keyword = Some((*first).to_owned());
+            words.remove(0);
+        }
+    }
+    /* a priority such as [#A] */
+    if let Some(first) = words.first() {
+        if first.starts_with("[#") && first.ends_with(']') {
+            words.remove(0);
+        }
+    }
+    heading.title = words.join(" ");
+    heading
+}
+/** the time in a timestamp, such as `<2020-03-14 Sat 10:00 +1w>` (midnight if no time is given) */
+fn parse_timestamp(stamp: &str) -> Option<NaiveDateTime> {
+    let mut parts = stamp
+        .trim_matches(|c| c == '<' || c == '>' || c == '[' || c == ']')
+        .split_whitespace();
+    let date = NaiveDate::parse_from_str(parts.next()?, crate::format::ISO_DATE_FORMAT).ok()?;
+    let time = parts
+        .filter_map(|p| NaiveTime::parse_from_str(p.get(..5)?, TIME_FORMAT).ok())
+        .next()
+        .unwrap_or_else(|| NaiveTime::from_hms(0, 0, 0));
+    Some(date.and_time(time))
+}
+/** the timestamp after the word (such as `DEADLINE:`) in the line, if there is one */
+fn timestamp_after(line: &str, word: &str) -> Option<NaiveDateTime> {
+    let rest = &line[line.find(word)? + word.len()..];
+    let start = rest.find(|c| c == '<' || c == '[')?;
+    let end = rest[start..].find(|c| c == '>' || c == ']')?;
+    parse_timestamp(&rest[start..=start + end])
+}
+/** whether the line is a planning line (with `SCHEDULED:`, `DEADLINE:` or `CLOSED:`) */
+fn is_planning(line: &str) -> bool {
+    let line = line.trim_start();
+    ["SCHEDULED:", "DEADLINE:", "CLOSED:"]
+        .iter()
+        .any(|w| line.starts_with(w))
+}
+/** the text under a heading, without its common indentation */
+fn body_text(lines: &[&str]) -> String {
+    let indent = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|l| l.get(indent..).unwrap_or("").trim_end())
+        .collect::<Vec<&str>>()
+        .join("\n")
+        .trim_matches('\n')
+        .to_owned()
+}
+/** the headings in the org-mode text, in order (what is before the first heading is ignored) */
+pub fn parse(text: &str) -> Vec<Heading> {
+    let mut headings = vec![];
+    let mut current: Option<Heading> = None;
+    let mut body: Vec<&str> = vec![];
+    let mut in_drawer = false;
+    for line in text.lines() {
+        let level = heading_level(line);
+        if level > 0 {
+            if let Some(mut heading) = current.take() {
+                heading.body = body_text(&body);
+                headings.push(heading);
+            }
+            body.clear();
+            in_drawer = false;
+            current = Some(parse_heading(line, level));
+            continue;
+        }
+        let heading = match current.as_mut() {
+            Some(heading) => heading,
+            None => continue,
+        };
+        let trimmed = line.trim();
+        if in_drawer {
+            in_drawer = !trimmed.eq_ignore_ascii_case(":END:");
+        } else if body.is_empty() && is_planning(line) {
+            heading.scheduled = timestamp_after(line, "SCHEDULED:").or(heading.scheduled);
+            heading.deadline = timestamp_after(line, "DEADLINE:").or(heading.deadline);
+            heading.closed = timestamp_after(line, "CLOSED:").or(heading.closed);
+        } else if body.is_empty() && trimmed.len() > 2 && is_drawer(trimmed) {
+            in_drawer = true;
+        } else {
+            body.push(line);
+        }
+    }
+    if let Some(mut heading) = current {
+        heading.body = body_text(&body);
+        headings.push(heading);
+    }
+    headings
+}
+/** whether the line starts a drawer, such as `:PROPERTIES:` */
+fn is_drawer(line: &str) -> bool {
+    line.starts_with(':')
+        && line.ends_with(':')
+        && line[1..line.len() - 1]
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+/** a timestamp for the time (active or not), without the time if it is `no_time_of_day` */
+fn timestamp(time: NaiveDateTime, active: bool, no_time_of_day: Option<NaiveTime>) -> String {
+    let (open, close) = if active { ('<', '>') } else { ('[', ']') };
+    let mut stamp = time.format("%Y-%m-%d %a").to_string();
+    if Some(time.time()) != no_time_of_day {
+        stamp.push(' ');
+        stamp.push_str(&time.format(TIME_FORMAT).to_string());
+    }
+    format!("{}{}{}", open, stamp, close)
+}
+/** the org-mode text for the headings */
+pub fn to_org_text(headings: &[Heading]) -> String {
+    let mut text = String::new();
+    for heading in headings {
+        text.push_str(&"*".repeat(heading.level.max(1)));
+        if let Some(keyword) = &heading.keyword {
+            text.push(' ');
+            text.push_str(keyword);
+        }
+        text.push(' ');
+        text.push_str(&heading.title);
+        if !heading.tags.is_empty() {
+            text.push_str(&format!(" :{}:", heading.tags.join(":")));
+        }
+        text.push('\n');
+        let indent = " ".repeat(heading.level.max(1) + 1);
+        let mut planning = vec![];
+        if let Some(closed) = heading.closed {
+            planning.push(format!("CLOSED: {}", timestamp(closed, false, None)));
+        }
+        if let Some(scheduled) = heading.scheduled {
+            planning.push(format!(
+                "SCHEDULED: {}",
+                timestamp(scheduled, true, Some(NaiveTime::from_hms(0, 0, 0)))
+            ));
+        }
+        if let Some(deadline) = heading.deadline {
+            planning.push(format!(
+                "DEADLINE: {}",
+                timestamp(deadline, true, Some(end_of_day()))
+            ));
+        }
+        if !planning.is_empty() {
+            text.push_str(&format!("{}{}\n", indent, planning.join(" ")));
+        }
+        /* indented, so that a line of the text starting with a star is not taken as a heading */
+        for line in heading.body.lines() {
+            if !line.is_empty() {
+                text.push_str(&indent);
+            }
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    text
+}
+/** the time given to deadlines without a time (as in quick capture) */
+fn end_of_day() -> NaiveTime {
+    NaiveTime::from_hms(23, 59, 0)
+}
+
+/** an item to be exported, with the idents of its children */
+struct Node {
+    heading: Heading,
+    parent: Option<Ident>,
+    children: Vec<Ident>,
+}
+/** the name of a context as a tag (with its spaces as underscores) */
+fn context_tag(world: &World, context: &str) -> Option<String> {
+    if context.is_empty() || context == "default_context" {
+        return None;
+    }
+    let name = match world.get_item_parts(&context.to_owned()) {
+        Ok((_base, values)) => values
+            .get("name")
+            .and_then(|n| n.as_str())
+            .unwrap_or(context)
+            .to_owned(),
+        Err(_) => context.to_owned(),
+    };
+    Some(name.split_whitespace().collect::<Vec<&str>>().join("_"))
+}
+/** the headings for the tasks and simple items, each followed by those within it */
+pub fn headings(world: &mut World) -> FLResult<Vec<Heading>> {
+    let mut nodes: HashMap<Ident, Node> = HashMap::new();
+    let mut order = vec![];
+    for (ident, values) in world.stored_values()? {
+        if crate::private::is_sealed(&values) {
+            continue;
+        }
+        let (base, values) = split_data_parts(&serde_yaml::to_vec(&values)?)?;
+        let text = |key: &str| {
+            values
+                .get(key)
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_owned()
+        };
+        let heading = match base.type_name.as_str() {
+            "Task" => {
+                let task = crate::task::summary(&values)?;
+                let time = |t: NaiveDateTime| {
+                    if crate::task::is_no_time(&t) {
+                        None
+                    } else {
+                        Some(t)
+                    }
+                };
+                Heading {
+                    keyword: Some(keyword_for(task.status).to_owned()),
+                    title: task.name,
+                    tags: context_tag(world, &task.context).into_iter().collect(),
+                    scheduled: time(task.show_after_date),
+                    deadline: time(task.deadline),
+                    closed: if task.status == TaskStatus::Closed {
+                        time(task.when_closed)
+                    } else {
+                        None
+                    },
+                    body: task.text,
+                    ..Heading::default()
+                }
+            }
+            "Simple" if !base.can_be_context && !base.is_template => Heading {
+                title: text("name"),
+                body: text("text"),
+                ..Heading::default()
+            },
+            _ => continue,
+        };
+        order.push((base.when_created, ident.clone()));
+        nodes.insert(
+            ident,
+            Node {
+                heading,
+                parent: base.parent,
+                children: vec![],
+            },
+        );
+    }
+    order.sort();
+    let mut roots = vec![];
+    for (_when, ident) in &order {
+        match nodes[ident].parent.clone() {
+            Some(parent) if nodes.contains_key(&parent) => {
+                if let Some(p) = nodes.get_mut(&parent) {
+                    p.children.push(ident.clone());
+                }
+            }
+            _ => roots.push(ident.clone()),
+        }
+    }
+    let mut headings = vec![];
+    let mut stack: Vec<(Ident, usize)> = roots.into_iter().rev().map(|i| (i, 1)).collect();
+    let mut seen = HashSet::new();
+    while let Some((ident, level)) = stack.pop() {
+        if !seen.insert(ident.clone()) {
+            continue;
+        }
+        let node = &nodes[&ident];
+        headings.push(Heading {
+            level,
+            ..node.heading.clone()
+        });
+        stack.extend(node.children.iter().rev().map(|c| (c.clone(), level + 1)));
+    }
+    Ok(headings)
+}
+
+/** template data for importing and exporting org-mode files */
+#[derive(Template)]
+#[template(path = "org.html", print = "none")]
+struct OrgTemplate {
+    exported: String,
+    count: usize,
+    message: String,
+}
+/** write the tasks and simple items to an org-mode file in the export
+directory, and also show them with a form for importing more */
+pub fn export_org(world: &mut World) -> fanling_interface::ResponseResult {
+    let headings = headings(world)?;
+    let exported = to_org_text(&headings);
+    let dir = world.export_dir().to_path_buf();
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!(
+        "fanling-{}.org",
+        chrono::Local::today()
+            .naive_local()
+            .format(crate::format::ISO_DATE_FORMAT)
+    ));
+    fs::write(&path, &exported)?;
+    trace(&format!(
+        "exported {} headings to {:?}",
+        headings.len(),
+        &path
+    ));
+    let t = OrgTemplate {
+        exported,
+        count: headings.len(),
+        message: format!("Exported to {}", path.display()),
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    {
+        resp.set_test_data("count", &format!("{}", headings.len()));
+        resp.set_test_data("path", &path.to_string_lossy());
+    }
+    Ok(resp)
+}
+/** the values for making the item for a heading */
+fn vals_for(world: &mut World, heading: &Heading) -> FLResult<HashMap<String, String>> {
+    let mut vals = HashMap::new();
+    vals.insert("name".to_owned(), heading.title.clone());
+    vals.insert("text".to_owned(), heading.body.clone());
+    let status = match heading.status() {
+        Some(status) => status,
+        None => return Ok(vals),
+    };
+    let context = match heading.tags.first() {
+        Some(tag) => crate::quick::context_ident(world, &tag.replace('_', " "))?,
+        None => "default_context".to_owned(),
+    };
+    vals.insert("context".to_owned(), context);
+    if let Some(deadline) = heading.deadline {
+        let deadline = if deadline.time() == NaiveTime::from_hms(0, 0, 0) {
+            deadline.date().and_time(end_of_day())
+        } else {
+            deadline
+        };
+        vals.insert(
+            "deadline".to_owned(),
+            deadline.format(DATE_TIME_FORMAT).to_string(),
+        );
+    }
+    if let Some(scheduled) = heading.scheduled {
+        vals.insert(
+            "show_after_date".to_owned(),
+            scheduled.format(DATE_TIME_FORMAT).to_string(),
+        );
+    }
+    vals.insert("status".to_owned(), format!("{:?}", status));
+    if let Some(closed) = heading.closed {
+        vals.insert(
+            "when_closed".to_owned(),
+            closed.format(DATE_TIME_FORMAT).to_string(),
+        );
+    }
+    Ok(vals)
+}
+/** the types and names of the items (in lower case), for finding those already present */
+fn known_names(world: &World) -> FLResult<HashSet<(String, String)>> {
+    Ok(world
+        .stored_values()?
+        .iter()
+        .map(|(_ident, values)| {
+            let text = |key: &str| values.get(key).and_then(|v| v.as_str()).unwrap_or("");
+            (text("type").to_owned(), text("name").trim().to_lowercase())
+        })
+        .collect())
+}
+/** make an item for each heading in the org-mode text (except those
+already present), then list the new items */
+pub fn import_org(world: &mut World, text: &str) -> fanling_interface::ResponseResult {
+    let headings = parse(text);
+    let known = known_names(world)?;
+    let mut imported = ItemListEntryList::from_vec(vec![]);
+    let mut skipped = 0;
+    /* the idents of the items of the headings that the current one is within */
+    let mut parents: Vec<(usize, Option<Ident>)> = vec![];
+    world.begin_batch();
+    let mut made = Ok(());
+    for (i, heading) in headings.iter().enumerate() {
+        while parents.last().map_or(false, |(l, _)| *l >= heading.level) {
+            parents.pop();
+        }
+        let type_name = if heading.keyword.is_some() {
+            "Task"
+        } else {
+            "Simple"
+        };
+        let name = heading.title.trim().to_lowercase();
+        if name.is_empty() || known.contains(&(type_name.to_owned(), name)) {
+            trace(&format!("skipping heading '{}'", heading.title));
+            skipped += 1;
+            parents.push((heading.level, None));
+            continue;
+        }
+        let base = ItemBaseForSerde {
+            ident: "".to_owned(),
+            type_name: type_name.to_owned(),
+            parent: parents.last().and_then(|(_, p)| p.clone()),
+            can_be_parent: headings
+                .get(i + 1)
+                .map_or(false, |h| h.level > heading.level),
+            ..ItemBaseForSerde::default()
+        };
+        let item_ref = match vals_for(world, heading)
+            .and_then(|vals| world.make_item(type_name, &base, &vals))
+        {
+            Ok(item_ref) => item_ref,
+            Err(e) => {
+                made = Err(e);
+                break;
+            }
+        };
+        let ident = item_ref.borrow().ident();
+        imported.add(ItemListEntry::from_item(&item_ref.borrow())?);
+        parents.push((heading.level, Some(ident)));
+    }
+    world.end_batch()?;
+    made?;
+    trace(&format!(
+        "imported {} headings ({} skipped)",
+        imported.num_entries(),
+        skipped
+    ));
+    let mut resp = World::show_list(&mut imported, "imported")?;
+    resp.show_toast(&format!(
+        "Imported {} headings; {} skipped (already present or without a title).",
+        imported.num_entries(),
+        skipped
+    ));
+    #[cfg(test)]
+    resp.set_test_data("skipped", &format!("{}", skipped));
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("org {}", Colour::Fixed(15).on(Colour::Fixed(65)).paint(m));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    const TEXT: &str = "#+TITLE: plans
+* TODO [#A] Write report :work:urgent:
+  SCHEDULED: <2020-03-10 Tue> DEADLINE: <2020-03-14 Sat 17:00>
+  :PROPERTIES:
+  :ID: 1234
+  :END:
+  Some notes
+  * a list
+** DONE Gather figures
+   CLOSED: [2020-03-09 Mon 10:30]
+* Ideas
+";
+    #[test]
+    fn parse_headings() {
+        let headings = parse(TEXT);
+        assert_eq!(3, headings.len());
+        let report = &headings[0];
+        assert_eq!(Some("TODO".to_owned()), report.keyword);
+        assert_eq!("Write report", report.title);
+        assert_eq!(vec!["work", "urgent"], report.tags);
+        assert_eq!(
+            Some(NaiveDate::from_ymd(2020, 3, 10).and_hms(0, 0, 0)),
+            report.scheduled
+        );
+        assert_eq!(
+            Some(NaiveDate::from_ymd(2020, 3, 14).and_hms(17, 0, 0)),
+            report.deadline
+        );
+        assert_eq!("Some notes\n* a list", report.body);
+        assert_eq!(Some(TaskStatus::Closed), headings[1].status());
+        assert_eq!(2, headings[1].level);
+        assert_eq!(
+            Some(NaiveDate::from_ymd(2020, 3, 9).and_hms(10, 30, 0)),
+            headings[1].closed
+        );
+        assert_eq!(None, headings[2].status());
+        assert_eq!("", headings[2].body);
+    }
+    #[test]
+    fn round_trip() {
+        let headings = parse(TEXT);
+        assert_eq!(headings, parse(&to_org_text(&headings)));
+    }
+}
@@ -0,0 +1,84 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! plugins, which add kinds of item to the engine without it being
+changed (from another crate, or from a scripting layer).
+
+A plugin gives what the user interface needs to know about its kind (a
+[`KindInfo`] whose kind is [`ItemKind::Plugin`] with its type name) and
+makes the [`ItemTypePolicy`] for it, much as the kinds built into the
+engine do. The plugins are given in the
+[`crate::EngineOptions::plugins`], and are registered when the world
+is made, before any item is loaded.
+
+An item of a kind whose plugin is not loaded is kept and shown as a
+[`crate::foreign`] item: as it is stored, and read-only, so it is not
+changed before a version with the plugin reads it again. */
+use crate::item::{ItemKind, ItemType, ItemTypePolicy, ItemTypeRegistry};
+use crate::kinds::KindInfo;
+use crate::shared::{FanlingError, NullResult};
+use ansi_term::Colour;
+use std::fmt;
+use std::sync::Arc;
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** adds a kind of item to the engine */
+pub trait ItemTypePlugin: fmt::Debug + Send + Sync {
+    /** what is known about the kind */
+    fn info(&self) -> KindInfo;
+    /** make the policy for the kind (once for each world) */
+    fn policy(&self) -> Box<dyn ItemTypePolicy>;
+}
+/** a plugin, as given in the engine options */
+pub type Plugin = Arc<dyn ItemTypePlugin>;
+
+/** register the kind of the plugin, unless it is not valid or its name is already taken */
+pub(crate) fn register(
+    registry: &mut ItemTypeRegistry,
+    kinds: &mut Vec<KindInfo>,
+    plugin: &dyn ItemTypePlugin,
+) -> NullResult {
+    let info = plugin.info();
+    trace(&format!("registering plugin for {}", info.name));
+    if info.kind != ItemKind::Plugin(info.name) {
+        return Err(fanling_error!(&format!(
+            "the plugin for {} gives the wrong kind ({:?})",
+            info.name, info.kind
+        )));
+    }
+    let taken = |name: &str| crate::kinds::find(name).is_some() || find(kinds, name).is_some();
+    if taken(info.name) || info.aliases.iter().any(|a| taken(a)) {
+        return Err(fanling_error!(&format!(
+            "there is already a kind of item called {}",
+            info.name
+        )));
+    }
+    let policy = plugin.policy();
+    if policy.kind() != info.kind {
+        return Err(fanling_error!(&format!(
+            "the policy of the plugin for {} is for {}",
+            info.name,
+            policy.kind()
+        )));
+    }
+    registry.register(ItemType::new(policy));
+    kinds.push(info);
+    Ok(())
+}
+/** the kind of item of the plugins for a type name, if there is one */
+pub(crate) fn find<'a>(kinds: &'a [KindInfo], type_name: &str) -> Option<&'a KindInfo> {
+    kinds
+        .iter()
+        .find(|k| k.name == type_name || k.aliases.contains(&type_name))
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "plugin {}",
+        Colour::Fixed(0).on(Colour::Fixed(187)).paint(m)
+    );
+}
@@ -0,0 +1,172 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! prefetching of the items most likely to be shown next, so that
+moving between items is quick on slow devices.
+
+After an item is shown, the page asks for a [`crate::Action::Prefetch`]
+while the user reads it, which renders its next and previous siblings
+and the items it links to (including its parent and children), ready to
+be shown. What has been rendered is forgotten after any action that
+might change what is shown, after any commit (including a pull), and
+after a few minutes (as what is shown can depend on the time). */
+use crate::item::{Ident, ItemRef};
+use crate::shared::{FLResult, NullResult};
+use crate::world::World;
+use ansi_term::Colour;
+use regex::Regex;
+use std::collections::HashMap;
+use std::ops::DerefMut;
+use std::time::{Duration, Instant};
+
+/** the most items rendered by a single prefetch */
+const MAX_PREFETCH: usize = 6;
+/** how long something rendered can be shown for */
+const MAX_AGE: Duration = Duration::from_secs(300);
+
+/** the show response for an item, and when (and at which commit) it was rendered */
+#[derive(Debug)]
+struct Entry {
+    resp: fanling_interface::Response,
+    head: Option<String>,
+    when: Instant,
+}
+/** the items that have been rendered in advance, and those to render next */
+#[derive(Debug, Default)]
+pub struct Prefetched {
+    entries: HashMap<Ident, Entry>,
+    queue: Vec<Ident>,
+}
+impl Prefetched {
+    /** forget everything rendered so far */
+    pub fn clear(&mut self) {
+        if !self.entries.is_empty() {
+            trace(&format!("forgetting {} items", self.entries.len()));
+        }
+        self.entries.clear();
+        self.queue.clear();
+    }
+    /** the show response rendered for the item, if it can still be used */
+    fn get(&self, ident: &str, head: &Option<String>) -> Option<fanling_interface::Response> {
+        self.entries
+            .get(ident)
+            .filter(|e| e.head == *head && e.when.elapsed() < MAX_AGE)
+            .map(|e| e.resp.clone())
+    }
+}
+
+/** show an item, using what was prefetched for it if possible, and
+note the items likely to be shown next */
+pub fn show(world: &mut World, item_ref: &ItemRef) -> fanling_interface::ResponseResult {
+    let ident = item_ref.borrow().ident();
+    let head = world.head_id()?;
+    let cached = world.prefetched().get(&ident, &head);
+    let found = cached.is_some();
+    let mut resp = match cached {
+        Some(resp) => {
+            trace(&format!("showing prefetched {}", ident));
+            resp
+        }
+        None => item_ref
+            .borrow_mut()
+            .deref_mut()
+            .do_action(crate::Action::Show, world)?,
+    };
+    let likely = likely_next(world, item_ref, &resp)?;
+    world.prefetched().queue = likely;
+    #[cfg(test)]
+    resp.set_test_data("prefetched", if found { "true" } else { "false" });
+    #[cfg(not(test))]
+    let _ = found;
+    Ok(resp)
+}
+/** render the items likely to be shown next (after an item has been shown) */
+pub fn prefetch(world: &mut World) -> fanling_interface::ResponseResult {
+    let queue = std::mem::take(&mut world.prefetched().queue);
+    let head = world.head_id()?;
+    let mut count = 0;
+    for ident in queue {
+        /* a link to a missing item is not followed, so that no item is made for it */
+        if world.prefetched().get(&ident, &head).is_some() || !world.has_item(&ident)? {
+            continue;
+        }
+        if let Err(e) = render(world, &ident, &head) {
+            trace(&format!("could not prefetch {}: {:?}", ident, e));
+            continue;
+        }
+        count += 1;
+    }
+    trace(&format!("prefetched {} items", count));
+    #[allow(unused_mut)]
+    let mut resp = fanling_interface::Response::new();
+    #[cfg(test)]
+    resp.set_test_data("count", &format!("{}", count));
+    Ok(resp)
+}
+/** render an item and keep the response */
+fn render(world: &mut World, ident: &str, head: &Option<String>) -> NullResult {
+    let item_ref = world.get_item(ident.to_owned(), "Simple".to_owned())?;
+    let resp = item_ref
+        .borrow_mut()
+        .deref_mut()
+        .do_action(crate::Action::Show, world)?;
+    world.prefetched().entries.insert(
+        ident.to_owned(),
+        Entry {
+            resp,
+            head: head.clone(),
+            when: Instant::now(),
+        },
+    );
+    Ok(())
+}
+
+/** the items likely to be shown after this one: its next and previous
+siblings, then the items shown by the links in its page */
+fn likely_next(
+    world: &mut World,
+    item_ref: &ItemRef,
+    resp: &fanling_interface::Response,
+) -> FLResult<Vec<Ident>> {
+    let ident = item_ref.borrow().ident();
+    let mut likely = vec![];
+    let parent = item_ref.borrow_mut().parent_ident();
+    if let Some(parent) = parent {
+        let siblings: Vec<Ident> = world
+            .search_open_children(&parent)?
+            .entries
+            .iter()
+            .map(|e| e.link.ident.clone())
+            .collect();
+        if let Some(pos) = siblings.iter().position(|s| *s == ident) {
+            likely.extend(siblings.get(pos + 1).cloned());
+            if pos > 0 {
+                likely.push(siblings[pos - 1].clone());
+            }
+        }
+    }
+    let link = Regex::new(r#"(?s)i:\s*"([^"]+)",\s*a:\s*"Show""#)?;
+    if let Some(content) = resp.region(fanling_interface::region::CONTENT) {
+        likely.extend(link.captures_iter(content).map(|c| c[1].to_owned()));
+    }
+    let mut seen = vec![ident];
+    likely.retain(|i| {
+        if seen.contains(i) {
+            false
+        } else {
+            seen.push(i.clone());
+            true
+        }
+    });
+    likely.truncate(MAX_PREFETCH);
+    Ok(likely)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "prefetch {}",
+        Colour::Fixed(6).on(Colour::Fixed(236)).paint(m)
+    );
+}
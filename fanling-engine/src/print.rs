@@ -0,0 +1,105 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! an item laid out for printing, reached from its page.
+
+The page (see [`crate::item::ItemData::for_print`]) has none of the
+buttons and other controls of the app (the style sheet hides the rest
+of the main page when printing). Links to other items are plain text,
+labelled with the names of the items, and each link to a URL is
+followed by the number of a footnote giving the URL, so that the
+printed page still says where the links go. */
+use crate::item::Item;
+use crate::shared::FLResult;
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use regex::{Captures, Regex};
+use std::rc::Rc;
+
+/** template data for an item laid out for printing */
+#[derive(Template)]
+#[template(path = "print.html", print = "none")]
+struct PrintTemplate {
+    ident: String,
+    type_name: String,
+    body: String,
+    footnotes: Vec<String>,
+    i18n: Rc<crate::i18n::Translator>,
+}
+
+/** the HTML with each link to a URL replaced by its label and the
+number of a footnote, and the URLs for the footnotes in order (each
+only once). Links within the page are just their labels. */
+fn with_footnotes(html: &str) -> FLResult<(String, Vec<String>)> {
+    let link = Regex::new(r#"(?is)<a\b[^>]*\bhref="([^"]*)"[^>]*>(.*?)</a>"#)?;
+    let mut urls: Vec<String> = vec![];
+    let html = link
+        .replace_all(html, |caps: &Captures| {
+            let url = &caps[1];
+            if url.is_empty() || url.starts_with('#') || url.starts_with("data:") {
+                return caps[2].to_owned();
+            }
+            let n = match urls.iter().position(|u| u == url) {
+                Some(i) => i + 1,
+                None => {
+                    urls.push(url.to_owned());
+                    urls.len()
+                }
+            };
+            format!("{}<sup class=\"footnote-ref\">[{}]</sup>", &caps[2], n)
+        })
+        .into_owned();
+    Ok((html, urls))
+}
+
+/** show the item laid out for printing */
+pub fn show_print(item: &mut Item, world: &mut World) -> fanling_interface::ResponseResult {
+    let body = item.for_print(world)?;
+    let (body, footnotes) = with_footnotes(&crate::export::standalone(world, &body)?)?;
+    trace(&format!(
+        "printing {} with {} footnotes",
+        item.ident(),
+        footnotes.len()
+    ));
+    #[cfg(test)]
+    let count = footnotes.len();
+    let t = PrintTemplate {
+        ident: item.ident(),
+        type_name: item.type_name(),
+        body,
+        footnotes,
+        i18n: world.i18n(),
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    resp.set_test_data("footnotes", &format!("{}", count));
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("print {}", Colour::Fixed(0).on(Colour::Fixed(254)).paint(m));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn footnotes() -> crate::shared::NullResult {
+        let (html, urls) = with_footnotes(
+            r##"<p><a href="https://a.example/">A</a>, <a href="#top">top</a> and <a href="https://a.example/">again</a> <a title="b" href="https://b.example/?x=1&amp;y=2">B</a></p>"##,
+        )?;
+        assert_eq!(
+            r#"<p>A<sup class="footnote-ref">[1]</sup>, top and again<sup class="footnote-ref">[1]</sup> B<sup class="footnote-ref">[2]</sup></p>"#,
+            html
+        );
+        assert_eq!(
+            vec!["https://a.example/", "https://b.example/?x=1&amp;y=2"],
+            urls
+        );
+        Ok(())
+    }
+}
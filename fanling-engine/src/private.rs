@@ -0,0 +1,239 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! private items, protected by a passphrase of their own.
+
+This is separate from encrypting the whole repository. The YAML for a
+private item is encrypted with a key derived from the item's
+passphrase, and stored along with the base fields and the name of the
+item, so that it can still be listed. Until the passphrase is given,
+the item is [`Locked`]: showing it asks for the passphrase. Once given,
+the passphrase is kept (in memory only) for the rest of the session.
+
+A private item that has been changed both locally and remotely keeps
+the local version when merging, as the two can not be combined. */
+use crate::crypt::Cipher;
+use crate::item::{split_data_parts, ItemBase, ItemBaseForSerde, ItemData};
+use crate::shared::{FLResult, FanlingError, NullResult};
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** key for the encrypted YAML of a private item */
+const SEALED_KEY: &str = "sealed";
+/** what the user interface is asked to prompt for */
+const PROMPT: &str = "item-passphrase";
+
+/** the passphrase for a private item (not shown in traces) */
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Passphrase(String);
+impl Passphrase {
+    pub fn new(passphrase: &str) -> Self {
+        Self(passphrase.to_owned())
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+impl fmt::Debug for Passphrase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Passphrase(..)")
+    }
+}
+
+/** whether the values of an item are those of a private item that is still encrypted */
+pub fn is_sealed(values: &Value) -> bool {
+    values.get(SEALED_KEY).is_some()
+}
+
+/** the stored form of a private item, given its YAML */
+pub(crate) fn seal_item(yaml: &[u8], passphrase: &Passphrase) -> FLResult<Vec<u8>> {
+    if passphrase.is_empty() {
+        return Err(fanling_error!("a private item needs a passphrase"));
+    }
+    let (base, values) = split_data_parts(yaml)?;
+    let (cipher, salt) = Cipher::for_item(passphrase.as_str(), None)?;
+    let locked = Locked {
+        name: values
+            .get("name")
+            .and_then(|n| n.as_str())
+            .unwrap_or(&base.ident)
+            .to_owned(),
+        salt,
+        sealed: cipher.seal(&String::from_utf8_lossy(yaml))?,
+    };
+    locked.to_yaml_using(&base)
+}
+
+/** the base and values of a private item, decrypted using its passphrase */
+pub(crate) fn open_item(
+    values: &Value,
+    passphrase: &Passphrase,
+) -> FLResult<(ItemBaseForSerde, Value)> {
+    let locked: Locked = serde_yaml::from_value(values.clone())?;
+    let (cipher, _salt) = Cipher::for_item(passphrase.as_str(), Some(locked.salt.as_str()))?;
+    let plain = cipher.open(&locked.sealed)?;
+    split_data_parts(plain.as_bytes())
+}
+
+/** a private item whose passphrase has not been given yet */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Locked {
+    /** the name of the item (not encrypted, so it can be listed) */
+    name: String,
+    /** the salt for deriving the key from the passphrase */
+    salt: String,
+    /** the encrypted YAML of the item */
+    sealed: String,
+}
+impl Locked {
+    /** the locked item from its stored values */
+    pub(crate) fn from_values(values: &Value) -> FLResult<Self> {
+        Ok(serde_yaml::from_value(values.clone())?)
+    }
+    fn to_yaml_using(&self, base: &ItemBaseForSerde) -> FLResult<Vec<u8>> {
+        let mut stored = serde_yaml::to_value(base)?;
+        if let (Value::Mapping(map), Value::Mapping(locked)) =
+            (&mut stored, serde_yaml::to_value(self)?)
+        {
+            map.extend(locked);
+        }
+        Ok(serde_yaml::to_vec(&stored)?)
+    }
+    fn ask(&self, base: &ItemBase) -> fanling_interface::ResponseResult {
+        ask_item_passphrase(&base.get_ident(), &base.type_name(), &self.name, "")
+    }
+}
+impl ItemData for Locked {
+    fn for_edit(
+        &mut self,
+        base: &mut ItemBase,
+        _is_for_update: bool,
+        _world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        self.ask(base)
+    }
+    fn for_show(
+        &mut self,
+        base: &mut ItemBase,
+        _world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        self.ask(base)
+    }
+    fn to_yaml(&self, base: &ItemBase) -> Result<Vec<u8>, FanlingError> {
+        self.to_yaml_using(&ItemBaseForSerde::from_base(base)?)
+    }
+    fn is_open(&self) -> bool {
+        true
+    }
+    fn is_ready(&mut self, _world: &mut World) -> FLResult<bool> {
+        Ok(false)
+    }
+    fn is_locked(&self) -> bool {
+        true
+    }
+    fn descr_for_ident(&self) -> String {
+        self.name.clone()
+    }
+    fn description(&self) -> String {
+        self.name.clone()
+    }
+    fn description_for_list(&self) -> String {
+        self.name.clone()
+    }
+    fn set_data(&mut self, _vals: &HashMap<String, String>, _world: &mut World) -> NullResult {
+        Err(fanling_error!("private item is locked"))
+    }
+    fn set_from_yaml(&mut self, yaml: &Value, _world: &mut World) -> NullResult {
+        *self = Self::from_values(yaml)?;
+        Ok(())
+    }
+    fn do_action(
+        &mut self,
+        base: &mut ItemBase,
+        _action: crate::Action,
+        _world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        self.ask(base)
+    }
+    fn fanling_clone(&self) -> FLResult<Box<dyn ItemData>> {
+        Err(fanling_error!("private item is locked"))
+    }
+    fn fix_data(&self, _yaml: &Value, _base: &mut ItemBase, _world: &mut World) -> NullResult {
+        Ok(())
+    }
+}
+
+/** template data for asking for the passphrase of a private item */
+#[derive(Template)]
+#[template(path = "unlock-item.html", print = "none")]
+struct UnlockItemTemplate {
+    ident: String,
+    type_name: String,
+    name: String,
+    message: String,
+}
+/** a response asking for the passphrase of a private item */
+pub fn ask_item_passphrase(
+    ident: &str,
+    type_name: &str,
+    name: &str,
+    message: &str,
+) -> fanling_interface::ResponseResult {
+    trace(&format!("asking for passphrase for {}", ident));
+    let t = UnlockItemTemplate {
+        ident: ident.to_owned(),
+        type_name: type_name.to_owned(),
+        name: name.to_owned(),
+        message: message.to_owned(),
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    resp.set_prompt(PROMPT);
+    #[cfg(test)]
+    {
+        resp.set_test_data("ident", ident);
+        resp.set_test_data("locked", "true");
+    }
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("private {}", Colour::Fixed(9).on(Colour::Fixed(236)).paint(m));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn seal_and_open() -> FLResult<()> {
+        let yaml = b"ident: diary-a1\ntype: Simple\nname: diary\ntext: dear diary\n";
+        let passphrase = Passphrase::new("pw");
+        let stored = seal_item(yaml, &passphrase)?;
+        let (base, values) = split_data_parts(&stored)?;
+        assert_eq!("diary-a1", base.ident);
+        assert!(is_sealed(&values));
+        assert_eq!(Some("diary"), values.get("name").and_then(|n| n.as_str()));
+        assert!(!String::from_utf8_lossy(&stored).contains("dear diary"));
+        let (_base, opened) = open_item(&values, &passphrase)?;
+        assert_eq!(
+            Some("dear diary"),
+            opened.get("text").and_then(|n| n.as_str())
+        );
+        assert!(open_item(&values, &Passphrase::new("wrong")).is_err());
+        Ok(())
+    }
+}
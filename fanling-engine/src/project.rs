@@ -0,0 +1,547 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! implements [`Project`] items, which gather tasks: the tasks whose
+parent is the project.
+
+The progress of a project is rolled up from its tasks each time it is
+shown (nothing about them is kept with the project): how many are open
+and closed, the percentage closed and the nearest deadline of those
+still open. The list of projects shows the same for each of them.
+
+Closing a project that still has open tasks asks whether they should be
+closed too, or left open outside the project (orphaned). Either way,
+the tasks and the project are changed in a single commit. */
+use crate::item::{
+    AvailableAction, Ident, Item, ItemBase, ItemBaseForSerde, ItemData, ItemLink, ItemListEntry,
+    NewBaseTemplate, ShowBaseTemplate,
+};
+use crate::shared::{merge_strings, FLResult, FanlingError, NullResult};
+use crate::world::{ActionResponse, World};
+use ansi_term::Colour;
+use askama::Template;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::boxed::Box;
+use std::collections::HashMap;
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** what is done with the open tasks of a project when it is closed */
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ProjectChildren {
+    /** ask the user, if there are any */
+    Ask,
+    /** close them too */
+    Close,
+    /** leave them open, but no longer in the project */
+    Orphan,
+}
+
+/** data for a project item */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Project {
+    /** what the project is */
+    name: String,
+    /** notes about the project in MarkDown format */
+    #[serde(default)]
+    text: String,
+    /** whether it has been closed */
+    #[serde(default)]
+    closed: bool,
+}
+impl Project {
+    /** create a new [Project]  */
+    pub fn new() -> Self {
+        Self {
+            name: "".to_owned(),
+            text: "".to_owned(),
+            closed: false,
+        }
+    }
+    fn set_from_yaml_basic(&mut self, yaml: &serde_yaml::Value) -> NullResult {
+        *self = serde_yaml::from_value(yaml.clone())?;
+        Ok(())
+    }
+}
+
+/** a task of a project, as needed for its progress */
+struct ProjectTask {
+    entry: ItemListEntry,
+    open: bool,
+    due: Option<NaiveDateTime>,
+}
+/** the tasks of the project with the ident (its children that are tasks) */
+fn tasks(world: &mut World, ident: &str) -> FLResult<Vec<ProjectTask>> {
+    let mut tasks = vec![];
+    for entry in world.search_children(ident)?.entries {
+        let item_ref = ItemLink::from(entry.link.clone()).resolve_link(world)?;
+        let item = item_ref.borrow();
+        if item.type_name() == "Task" {
+            tasks.push(ProjectTask {
+                open: item.is_open(),
+                due: item.due(),
+                entry,
+            });
+        }
+    }
+    Ok(tasks)
+}
+
+/** the progress of a project, rolled up from its tasks */
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Progress {
+    pub open: usize,
+    pub closed: usize,
+    /** the nearest deadline of the open tasks (if any of them have one) */
+    pub next_due: Option<NaiveDateTime>,
+}
+impl Progress {
+    /** the progress given whether each task is open and when it is due */
+    fn of(tasks: &[(bool, Option<NaiveDateTime>)]) -> Self {
+        let open = tasks.iter().filter(|(open, _due)| *open).count();
+        Self {
+            open,
+            closed: tasks.len() - open,
+            next_due: tasks
+                .iter()
+                .filter(|(open, _due)| *open)
+                .filter_map(|(_open, due)| *due)
+                .min(),
+        }
+    }
+    /** the percentage of the tasks that are closed (none if there are no tasks) */
+    pub fn percent(&self) -> u32 {
+        let total = self.open + self.closed;
+        if total == 0 {
+            0
+        } else {
+            (self.closed * 100 / total) as u32
+        }
+    }
+}
+/** the progress of the tasks */
+fn progress_of(tasks: &[ProjectTask]) -> Progress {
+    Progress::of(
+        &tasks
+            .iter()
+            .map(|t| (t.open, t.due))
+            .collect::<Vec<(bool, Option<NaiveDateTime>)>>(),
+    )
+}
+/** the progress of the project with the ident */
+pub fn progress(world: &mut World, ident: &str) -> FLResult<Progress> {
+    Ok(progress_of(&tasks(world, ident)?))
+}
+
+impl crate::item::ItemData for Project {
+    fn for_edit(
+        &mut self,
+        base: &mut ItemBase,
+        is_for_update: bool,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let nt = NewProjectTemplate {
+            data: &self,
+            broken_text: self.text.replace("\n", "&#10;"),
+            base: NewBaseTemplate::from_base(base, is_for_update, world)?,
+        };
+        let mut resp = fanling_interface::Response::new();
+        resp.clear_errors(vec!["name-error".to_owned()]);
+        resp.add_tag("content", &(nt.render()?));
+        #[cfg(test)]
+        {
+            resp.set_test_data("ident", &base.get_ident());
+        }
+        trace(&format!("for edit {:?}", &resp));
+        Ok(resp)
+    }
+    /** closing (asking about any open tasks) or reopening, and adding a task */
+    fn available_actions(&self, base: &ItemBase) -> Vec<AvailableAction> {
+        let status = if self.closed {
+            AvailableAction::new(base, crate::Action::Reopen, "Re-open", "↺")
+        } else {
+            AvailableAction::new(
+                base,
+                crate::Action::CloseProject(ProjectChildren::Ask),
+                "Close",
+                "✔",
+            )
+        };
+        /* the new child is a task, not another project */
+        let add_task = AvailableAction {
+            type_name: "Task".to_owned(),
+            ..AvailableAction::new(
+                base,
+                crate::Action::NewChild(base.get_ident()),
+                "Add task",
+                "➕",
+            )
+        };
+        vec![status, add_task]
+    }
+    fn for_show(
+        &mut self,
+        base: &mut ItemBase,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let tasks = tasks(world, &base.get_ident())?;
+        let progress = progress_of(&tasks);
+        let t = ShowProjectTemplate {
+            name: self.name.clone(),
+            closed: self.closed,
+            rendered_text: crate::markdown::render(&self.text, &world.render_context()),
+            percent: progress.percent(),
+            percent_shown: world.format().format_percent(progress.percent()),
+            open: progress.open,
+            closed_tasks: progress.closed,
+            next_due: progress
+                .next_due
+                .map_or_else(String::new, |d| world.format().format_date_time(d)),
+            base: ShowBaseTemplate::from_base(base, world)?
+                .with_actions(base, self.available_actions(base))
+                .with_stats(self.stats(base, world)?),
+        };
+        let mut resp = fanling_interface::Response::new();
+        resp.add_tag("content", &(t.render()?));
+        #[cfg(test)]
+        {
+            resp.set_test_data("ident", &base.get_ident());
+            resp.set_test_data("open", &format!("{}", progress.open));
+            resp.set_test_data("closed", &format!("{}", progress.closed));
+            resp.set_test_data("percent", &format!("{}", progress.percent()));
+        }
+        trace(&format!("for show {:?}", &resp));
+        Ok(resp)
+    }
+    fn to_yaml(&self, base: &crate::item::ItemBase) -> Result<Vec<u8>, FanlingError> {
+        let for_serde = ProjectForSerde {
+            base: crate::item::ItemBaseForSerde::from_base(base)?,
+            data: self.clone(),
+        };
+        let yaml = serde_yaml::to_vec(&for_serde)?;
+        trace(&format!("yaml is {}", String::from_utf8_lossy(&yaml)));
+        Ok(yaml)
+    }
+    fn is_open(&self) -> bool {
+        !self.closed
+    }
+    /** a project is not itself done, but its tasks are */
+    fn is_ready(&mut self, _world: &mut World) -> FLResult<bool> {
+        Ok(false)
+    }
+    /** can be turned into an ident */
+    fn descr_for_ident(&self) -> String {
+        self.name.clone()
+    }
+    /** an English-language description */
+    fn description(&self) -> String {
+        self.name.clone()
+    }
+    /** a description that can be used in a list */
+    fn description_for_list(&self) -> String {
+        format!(
+            "📁 {}{}",
+            self.name,
+            if self.closed { " (closed)" } else { "" }
+        )
+    }
+    fn set_data(&mut self, vals: &HashMap<String, String>, _world: &mut World) -> NullResult {
+        match vals.get("name") {
+            Some(s) => self.name = s.trim().to_owned(),
+            _ => return Err(fanling_error!("no name")),
+        }
+        self.text = vals.get("text").cloned().unwrap_or_default();
+        Ok(())
+    }
+    fn set_from_yaml(&mut self, yaml: &serde_yaml::Value, _world: &mut World) -> NullResult {
+        self.set_from_yaml_basic(yaml)
+    }
+    /** do action for project (closing it leaves its tasks as they are: see [`close_project`]) */
+    fn do_action(
+        &mut self,
+        base: &mut ItemBase,
+        action: crate::Action,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        match &action {
+            crate::Action::Close => self.closed = true,
+            crate::Action::Reopen => self.closed = false,
+            _ => return Err(fanling_error!(&format!("invalid action {:?}", action)).into()),
+        }
+        self.for_show(base, world)
+    }
+    /** copy from another item data. But it is open. */
+    fn fanling_clone(&self) -> FLResult<Box<dyn ItemData>> {
+        Ok(Box::new(Self {
+            name: self.name.clone(),
+            text: self.text.clone(),
+            closed: false,
+        }))
+    }
+    /** transitional to fix old data */
+    fn fix_data(
+        &self,
+        _yaml: &serde_yaml::Value,
+        _base: &mut ItemBase,
+        _world: &mut World,
+    ) -> NullResult {
+        Ok(())
+    }
+}
+impl Default for Project {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+#[derive(Serialize, Deserialize)]
+struct ProjectForSerde {
+    #[serde(flatten)]
+    base: crate::item::ItemBaseForSerde,
+    #[serde(flatten)]
+    data: Project,
+}
+/** template data for creating a new project */
+#[derive(Template)]
+#[template(path = "new-project.html", print = "none")]
+struct NewProjectTemplate<'a> {
+    data: &'a Project,
+    broken_text: String,
+    base: NewBaseTemplate,
+}
+/** template data for showing a project */
+#[derive(Template)]
+#[template(path = "show-project.html", print = "none")]
+struct ShowProjectTemplate {
+    name: String,
+    closed: bool,
+    rendered_text: String,
+    /** the percentage of its tasks closed */
+    percent: u32,
+    /** the percentage, formatted */
+    percent_shown: String,
+    open: usize,
+    closed_tasks: usize,
+    /** the nearest deadline of the open tasks, formatted (blank if none) */
+    next_due: String,
+    base: ShowBaseTemplate,
+}
+
+/** close the project, first asking what to do with its open tasks
+(if it has any and that has not been chosen). The tasks and the
+project are changed in a single commit. */
+pub fn close_project(
+    item: &mut Item,
+    children: ProjectChildren,
+    world: &mut World,
+) -> fanling_interface::ResponseResult {
+    let ident = item.ident();
+    let open: Vec<Ident> = tasks(world, &ident)?
+        .into_iter()
+        .filter(|t| t.open)
+        .map(|t| t.entry.link.ident)
+        .collect();
+    trace(&format!(
+        "closing {} ({:?}) with {} open tasks",
+        &ident,
+        children,
+        open.len()
+    ));
+    if children == ProjectChildren::Ask && !open.is_empty() {
+        let mut ar = ActionResponse::new();
+        ar.add_warning("message", "The project still has open tasks.");
+        let choice = |action, label: &str, icon: &str| AvailableAction {
+            action,
+            ident: ident.clone(),
+            type_name: item.type_name(),
+            label: world.i18n().tr(label),
+            icon: icon.to_owned(),
+        };
+        let choices = vec![
+            choice(
+                crate::Action::CloseProject(ProjectChildren::Close),
+                "Close them too",
+                "✔",
+            ),
+            choice(
+                crate::Action::CloseProject(ProjectChildren::Orphan),
+                "Leave them open outside the project",
+                "↗",
+            ),
+            choice(crate::Action::Show, "Cancel", "✖"),
+        ];
+        return ar
+            .translated(&world.i18n())
+            .to_response_with_choices(choices);
+    }
+    world.begin_batch();
+    let closed = close_tasks(&open, children, world);
+    let res = match closed {
+        Ok(()) => item.do_action(crate::Action::Close, world),
+        Err(e) => Err(e.into()),
+    };
+    world.end_batch()?;
+    res
+}
+/** close (or orphan) the tasks with the idents */
+fn close_tasks(idents: &[Ident], children: ProjectChildren, world: &mut World) -> NullResult {
+    for ident in idents {
+        let item_ref = ItemLink::new(ident.clone()).resolve_link(world)?;
+        let mut task = item_ref.borrow_mut();
+        if children == ProjectChildren::Orphan {
+            task.set_parent(None);
+            world.persist_change(&mut task)?;
+        } else {
+            task.do_action(crate::Action::Close, world)?;
+        }
+    }
+    Ok(())
+}
+
+/** a project in the list of projects */
+struct ProjectEntry {
+    ident: String,
+    descr: String,
+    percent: u32,
+    /** the percentage, formatted */
+    percent_shown: String,
+    open: usize,
+    closed: usize,
+    next_due: String,
+}
+/** template data for the list of projects */
+#[derive(Template)]
+#[template(path = "projects.html", print = "none")]
+struct ProjectsTemplate {
+    projects: Vec<ProjectEntry>,
+}
+/** show the projects with their progress, the open ones first */
+pub fn list_projects(world: &mut World) -> fanling_interface::ResponseResult {
+    let mut projects = vec![];
+    for entry in world.search_type("Project")?.entries {
+        let is_open = ItemLink::from(entry.link.clone())
+            .resolve_link(world)?
+            .borrow()
+            .is_open();
+        let progress = progress(world, &entry.link.ident)?;
+        projects.push((is_open, entry, progress));
+    }
+    projects.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.descr.cmp(&b.1.descr)));
+    let t = ProjectsTemplate {
+        projects: projects
+            .iter()
+            .map(|(_is_open, entry, progress)| ProjectEntry {
+                ident: entry.link.ident.clone(),
+                descr: entry.descr.clone(),
+                percent: progress.percent(),
+                percent_shown: world.format().format_percent(progress.percent()),
+                open: progress.open,
+                closed: progress.closed,
+                next_due: progress
+                    .next_due
+                    .map_or_else(String::new, |d| world.format().format_date_time(d)),
+            })
+            .collect(),
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    resp.set_test_data(
+        "projects",
+        &t.projects
+            .iter()
+            .map(|p| format!("{}:{}", p.ident, p.percent))
+            .collect::<Vec<String>>()
+            .join(","),
+    );
+    Ok(resp)
+}
+
+/** policy for the project item type*/
+#[derive(Debug)]
+pub struct ProjectTypePolicy {}
+impl ProjectTypePolicy {
+    pub fn new() -> Self {
+        Self {}
+    }
+    pub fn new_boxed() -> Box<Self> {
+        Box::new(Self::new())
+    }
+}
+impl crate::item::ItemTypePolicy for ProjectTypePolicy {
+    fn kind(&self) -> crate::item::ItemKind {
+        crate::item::ItemKind::Project
+    }
+    fn make_raw(&self, item_type: crate::item::ItemTypeRef) -> Item {
+        Item::new_with_data(item_type, Box::new(Project::new()))
+    }
+    /** it stays closed if it was closed in either version */
+    fn resolve_conflict_both(
+        &self,
+        _world: &mut World,
+        _ancestor: &Value,
+        ours: &Value,
+        theirs: &Value,
+    ) -> FLResult<Box<dyn ItemData>> {
+        let mut op = Project::new();
+        op.set_from_yaml_basic(&ours)?;
+        let mut tp = Project::new();
+        tp.set_from_yaml_basic(&theirs)?;
+        op.name = merge_strings(&op.name, &tp.name);
+        op.text = merge_strings(&op.text, &tp.text);
+        op.closed = op.closed || tp.closed;
+        Ok(Box::new(op))
+    }
+    fn check_valid(
+        &mut self,
+        _base: &ItemBaseForSerde,
+        vals: &HashMap<String, String>,
+        _world: &mut World,
+    ) -> ActionResponse {
+        let mut ar = ActionResponse::new();
+        ar.assert(
+            !vals.get("name").map_or(true, |n| n.trim().is_empty()),
+            "name-error",
+            "Name must be non-blank.",
+        );
+        ar
+    }
+    /** get item data from serde value */
+    fn from_yaml(&self, values: &Value, world: &mut World) -> FLResult<Box<dyn ItemData>> {
+        let mut p = Project::default();
+        p.set_from_yaml(&values, world)?;
+        Ok(Box::new(p))
+    }
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "project {}",
+        Colour::Fixed(0).on(Colour::Fixed(180)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn rolled_up() {
+        let day = |d| chrono::NaiveDate::from_ymd(2020, 6, d).and_hms(9, 0, 0);
+        let p = Progress::of(&[
+            (true, Some(day(20))),
+            (false, Some(day(1))),
+            (true, None),
+            (true, Some(day(12))),
+        ]);
+        assert_eq!(3, p.open);
+        assert_eq!(1, p.closed);
+        assert_eq!(25, p.percent());
+        /* the closed task's deadline does not count */
+        assert_eq!(Some(day(12)), p.next_due);
+        assert_eq!(0, Progress::default().percent());
+    }
+}
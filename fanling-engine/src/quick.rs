@@ -0,0 +1,217 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! quick capture: an item made from a single line of text, such as
+"buy milk #errands !friday", for a quick-capture box or a share target.
+
+In the line,
+
+* `#word` gives the context of a task, which is made if there is no
+  context with that name yet;
+* `!when` gives the deadline of a task: `!today`, `!tomorrow`, a day
+  of the week (eg `!fri`, the next one, or today) or a date;
+* a leading `note:` makes a simple item instead of a task (and a
+  leading `task:` is ignored).
+
+The rest of the line is the name of the item. */
+use crate::item::{Ident, ItemBaseForSerde};
+use crate::shared::FLResult;
+use crate::world::World;
+use ansi_term::Colour;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use std::collections::HashMap;
+
+/** what was found in a line of quick capture */
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Capture {
+    /** the type of item to make ("Task" or "Simple") */
+    pub type_name: String,
+    /** the name of the item */
+    pub name: String,
+    /** the name of the context, if one is given */
+    pub context: Option<String>,
+    /** the deadline, if one is given */
+    pub deadline: Option<NaiveDate>,
+}
+
+/** the day given after a `!` (relative to `today`), if it is one */
+fn parse_day(word: &str, today: NaiveDate, date_format: &str) -> Option<NaiveDate> {
+    let lower = word.to_lowercase();
+    match lower.as_str() {
+        "today" => return Some(today),
+        "tomorrow" => return Some(today.succ()),
+        _ => {}
+    }
+    if lower.len() >= 3 {
+        if let Ok(weekday) = lower.parse::<Weekday>() {
+            let ahead =
+                (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+            return Some(today + Duration::days(ahead.into()));
+        }
+    }
+    [date_format, crate::format::ISO_DATE_FORMAT]
+        .iter()
+        .find_map(|f| NaiveDate::parse_from_str(word, f).ok())
+}
+/** parse a line of quick capture */
+pub fn parse(line: &str, today: NaiveDate, date_format: &str) -> Capture {
+    let mut capture = Capture {
+        type_name: "Task".to_owned(),
+        ..Capture::default()
+    };
+    let mut words = vec![];
+    for (i, word) in line.split_whitespace().enumerate() {
+        match word.to_lowercase().as_str() {
+            "note:" if i == 0 => {
+                capture.type_name = "Simple".to_owned();
+                continue;
+            }
+            "task:" if i == 0 => continue,
+            _ => {}
+        }
+        if word.len() > 1 && word.starts_with('#') {
+            capture.context = Some(word[1..].to_owned());
+        } else if let Some(day) = word
+            .strip_prefix('!')
+            .and_then(|w| parse_day(w, today, date_format))
+        {
+            capture.deadline = Some(day);
+        } else {
+            words.push(word);
+        }
+    }
+    capture.name = words.join(" ");
+    capture
+}
+
+/** the ident of the context with the name, making one if there is none */
+pub(crate) fn context_ident(world: &mut World, name: &str) -> FLResult<Ident> {
+    if let Some(entry) = world
+        .search_contexts()?
+        .entries
+        .iter()
+        .find(|e| e.descr.trim().eq_ignore_ascii_case(name))
+    {
+        return Ok(entry.link.ident.clone());
+    }
+    trace(&format!("making context {}", name));
+    let base = ItemBaseForSerde {
+        ident: "".to_owned(),
+        type_name: "Simple".to_owned(),
+        can_be_context: true,
+        ..ItemBaseForSerde::default()
+    };
+    let mut vals = HashMap::new();
+    vals.insert("name".to_owned(), name.to_owned());
+    vals.insert("text".to_owned(), "".to_owned());
+    let context = world.make_item("Simple", &base, &vals)?;
+    let ident = context.borrow().ident();
+    Ok(ident)
+}
+
+/** make (and commit) an item from a line of quick capture, and say so in a toast */
+pub fn quick_add(world: &mut World, line: &str) -> fanling_interface::ResponseResult {
+    let today = chrono::Local::today().naive_local();
+    let capture = parse(line, today, &world.format().date_format);
+    let mut resp = fanling_interface::Response::new();
+    if capture.name.is_empty() {
+        resp.show_toast("Nothing to add.");
+        return Ok(resp);
+    }
+    trace(&format!("quick add {:?}", &capture));
+    world.begin_batch();
+    let made = make(world, &capture);
+    world.end_batch()?;
+    let ident = made?;
+    let mut details = vec![];
+    if let Some(context) = &capture.context {
+        details.push(context.clone());
+    }
+    if let Some(deadline) = capture.deadline {
+        details.push(format!("due {}", world.format().format_date(deadline)));
+    }
+    resp.show_toast(&format!(
+        "Added {} '{}'{}",
+        if capture.type_name == "Task" {
+            "task"
+        } else {
+            "note"
+        },
+        capture.name,
+        if details.is_empty() {
+            "".to_owned()
+        } else {
+            format!(" ({})", details.join(", "))
+        }
+    ));
+    #[cfg(test)]
+    {
+        resp.set_test_data("ident", &ident);
+        resp.set_test_data("type", &capture.type_name);
+    }
+    #[cfg(not(test))]
+    let _ = ident;
+    Ok(resp)
+}
+/** make the item for a capture, returning its ident */
+fn make(world: &mut World, capture: &Capture) -> FLResult<Ident> {
+    let base = ItemBaseForSerde {
+        ident: "".to_owned(),
+        type_name: capture.type_name.clone(),
+        ..ItemBaseForSerde::default()
+    };
+    let mut vals = HashMap::new();
+    vals.insert("name".to_owned(), capture.name.clone());
+    vals.insert("text".to_owned(), "".to_owned());
+    if capture.type_name == "Task" {
+        let context = match &capture.context {
+            Some(name) => context_ident(world, name)?,
+            None => "default_context".to_owned(),
+        };
+        vals.insert("context".to_owned(), context);
+        if let Some(deadline) = capture.deadline {
+            vals.insert(
+                "deadline".to_owned(),
+                deadline
+                    .and_hms(23, 59, 0)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string(),
+            );
+        }
+    }
+    let item = world.make_item(&capture.type_name, &base, &vals)?;
+    let ident = item.borrow().ident();
+    Ok(ident)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("quick {}", Colour::Fixed(3).on(Colour::Fixed(236)).paint(m));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn parse_lines() {
+        /* a Wednesday */
+        let today = NaiveDate::from_ymd(2020, 6, 3);
+        let capture = parse("buy milk #errands !friday", today, "%d/%m/%Y");
+        assert_eq!("Task", capture.type_name);
+        assert_eq!("buy milk", capture.name);
+        assert_eq!(Some("errands".to_owned()), capture.context);
+        assert_eq!(Some(NaiveDate::from_ymd(2020, 6, 5)), capture.deadline);
+        assert_eq!(Some(today), parse("x !wed", today, "").deadline);
+        assert_eq!(Some(today.succ()), parse("x !Tomorrow", today, "").deadline);
+        assert_eq!(
+            Some(NaiveDate::from_ymd(2020, 7, 1)),
+            parse("x !01/07/2020", today, "%d/%m/%Y").deadline
+        );
+        let capture = parse("note: see #1 !soon", today, "");
+        assert_eq!("Simple", capture.type_name);
+        assert_eq!(Some("1".to_owned()), capture.context);
+        assert_eq!("see !soon", capture.name);
+        assert_eq!(None, capture.deadline);
+    }
+}
@@ -0,0 +1,122 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! read-only repositories, such as a reference wiki published to the
+phones of the family, whose items can be read but not changed here.
+
+A repository is made read-only in the engine options (see
+[`crate::EngineOptions::read_only`] and
+[`crate::OtherRepoOptions::read_only`]). While it is current, only the
+actions that change nothing in it are done; the others are answered
+with a message. The buttons for editing are not shown with its items,
+syncing only pulls from its remotes, and the store refuses to commit to
+it in case anything else tries. */
+use crate::world::{ActionResponse, World};
+use crate::Action;
+use ansi_term::Colour;
+
+/** whether the action can be done in a read-only repository */
+pub fn allows(action: &Action) -> bool {
+    match action {
+        /* these show forms for making changes */
+        Action::Edit | Action::ShowRename | Action::Templates | Action::SaveDraft(_, _) => false,
+        Action::Clip { cut, .. } => !cut,
+        Action::ExportChart { format, .. } => *format != crate::stats::ChartFormat::Attachment,
+        Action::Start
+        | Action::Pull
+        | Action::PullFrom(_)
+        | Action::GetAll
+        | Action::CheckData
+        | Action::Cite(_)
+        | Action::Holidays
+        | Action::Repos
+        | Action::SelectRepo(_)
+        | Action::Suggest(_)
+        | Action::ShowArchivedPage(_)
+        | Action::UnlockItem(_)
+        | Action::ExportVCard
+        | Action::ExportBibTeX
+        | Action::ExportICal
+        | Action::ExportGraph
+        | Action::Backup
+        | Action::ExportBundle { .. }
+        | Action::ExportJson { .. }
+        | Action::ExportOrg
+        | Action::SyncDav
+        | Action::ExportBackup
+        | Action::SaveRemote { .. }
+        | Action::RemoveRemote(_)
+        | Action::SaveCredential { .. }
+        | Action::ForgetCredential(_)
+        | Action::Repack
+        | Action::Gc
+        | Action::Settings
+        /* these only show or render things */
+        | Action::Show
+        | Action::ShowHeading(_)
+        | Action::Prefetch
+        | Action::PushPending
+        | Action::DeliverHooks
+        | Action::History
+        | Action::Diff { .. }
+        | Action::AvailableActions
+        | Action::ListReady
+        | Action::ListOpen
+        | Action::ListAll
+        | Action::ListReading
+        | Action::ListHighlights
+        | Action::TimeReport(_)
+        | Action::Retention
+        | Action::ListMore { .. }
+        | Action::SortList { .. }
+        | Action::Focus
+        | Action::Board
+        | Action::Dashboard
+        | Action::Agenda { .. }
+        | Action::Timeline { .. }
+        | Action::Stats
+        | Action::Report
+        | Action::ListProjects
+        | Action::Orphans
+        | Action::Check
+        | Action::Back
+        | Action::Forward
+        | Action::Recent
+        | Action::Namespace(_)
+        | Action::Unpushed
+        | Action::Remotes
+        | Action::Credentials
+        | Action::TestRemote(_)
+        | Action::Maintenance
+        | Action::ExportItem(_)
+        | Action::Print
+        | Action::ShowCurrent
+        | Action::CloseSession
+        | Action::Preview(_) => true,
+        /* anything else (including any action added later) might change the repository */
+        _ => false,
+    }
+}
+/** the answer to an action that cannot be done as the repository is read-only */
+pub fn refuse(world: &World, action: &Action) -> fanling_interface::ResponseResult {
+    trace(&format!(
+        "refusing {:?} in read-only {}",
+        action,
+        world.repo_name()
+    ));
+    let mut ar = ActionResponse::new();
+    ar.add_error(
+        "error",
+        "This repository is read-only, so its items cannot be changed here.",
+    );
+    ar.translated(&world.i18n()).to_response()
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "read only {}",
+        Colour::Fixed(15).on(Colour::Fixed(60)).paint(m)
+    );
+}
@@ -0,0 +1,521 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! implements [`Reference`] items, books, articles and other works
+that can be cited, with their bibliographic fields and a citation key.
+
+A citation such as `[@turing1936]` in the Markdown of any item is a
+link to the reference with that key (see [`cite`]). The references can
+be exported to and imported from BibTeX (see [`crate::bibtex`]). */
+use crate::bibtex::{self, BibEntry};
+use crate::item::{
+    Ident, Item, ItemBase, ItemBaseForSerde, ItemData, NewBaseTemplate, ShowBaseTemplate,
+};
+use crate::markdown;
+use crate::shared::{merge_strings, FLResult, FanlingError, NullResult};
+use crate::world::{ActionResponse, World};
+use ansi_term::Colour;
+use askama::Template;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::boxed::Box;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs;
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** the characters allowed in a citation key */
+pub const KEY_PATTERN: &str = r"[\w:./-]+";
+
+/** data for a reference item */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reference {
+    /** the title of the work */
+    name: String,
+    /** the key for citing it (eg `turing1936`) */
+    #[serde(default)]
+    key: String,
+    /** the BibTeX entry type (eg `article` or `book`) */
+    #[serde(default)]
+    entry_type: String,
+    /** the authors, separated by " and " (as in BibTeX) */
+    #[serde(default)]
+    authors: String,
+    /** the year it was published */
+    #[serde(default)]
+    year: String,
+    /** where it was published (a journal, proceedings or publisher) */
+    #[serde(default)]
+    published_in: String,
+    #[serde(default)]
+    doi: String,
+    #[serde(default)]
+    url: String,
+    /** notes about the work (markdown) */
+    #[serde(default)]
+    notes: String,
+}
+impl Reference {
+    /** create a new [Reference]  */
+    pub fn new() -> Self {
+        Self {
+            name: "".to_owned(),
+            key: "".to_owned(),
+            entry_type: "".to_owned(),
+            authors: "".to_owned(),
+            year: "".to_owned(),
+            published_in: "".to_owned(),
+            doi: "".to_owned(),
+            url: "".to_owned(),
+            notes: "".to_owned(),
+        }
+    }
+    fn set_from_yaml_basic(&mut self, yaml: &serde_yaml::Value) -> NullResult {
+        *self = serde_yaml::from_value(yaml.clone())?;
+        Ok(())
+    }
+    /** the family names of the authors */
+    fn family_names(&self) -> Vec<String> {
+        self.authors
+            .split(" and ")
+            .map(|a| a.trim())
+            .filter(|a| !a.is_empty())
+            .map(|a| match a.find(',') {
+                Some(comma) => a[..comma].trim().to_owned(),
+                None => a.rsplit(' ').next().unwrap_or(a).to_owned(),
+            })
+            .collect()
+    }
+    /** the authors and year, as in a citation (eg "Turing 1936") */
+    fn short_citation(&self) -> String {
+        let names = self.family_names();
+        let who = match names.len() {
+            0 => "".to_owned(),
+            1 => names[0].clone(),
+            2 => format!("{} and {}", names[0], names[1]),
+            _ => format!("{} et al.", names[0]),
+        };
+        vec![who, self.year.clone()]
+            .into_iter()
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+    /** a key made from the first author's family name and the year (eg `turing1936`) */
+    fn default_key(&self) -> String {
+        let first = self.family_names().into_iter().next().unwrap_or_default();
+        let key: String = format!("{}{}", first, self.year)
+            .to_lowercase()
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect();
+        if key.is_empty() {
+            "ref".to_owned()
+        } else {
+            key
+        }
+    }
+    /** as a BibTeX entry */
+    pub fn to_entry(&self) -> BibEntry {
+        BibEntry {
+            entry_type: self.entry_type.clone(),
+            key: self.key.clone(),
+            title: self.name.clone(),
+            authors: self.authors.clone(),
+            year: self.year.clone(),
+            doi: self.doi.clone(),
+            url: self.url.clone(),
+            published_in: self.published_in.clone(),
+        }
+    }
+    /** the values for making a reference from a BibTeX entry */
+    pub fn vals_from_entry(entry: &BibEntry) -> HashMap<String, String> {
+        let mut vals = HashMap::new();
+        vals.insert("name".to_owned(), entry.title.clone());
+        vals.insert("key".to_owned(), entry.key.clone());
+        vals.insert("entry_type".to_owned(), entry.entry_type.clone());
+        vals.insert("authors".to_owned(), entry.authors.clone());
+        vals.insert("year".to_owned(), entry.year.clone());
+        vals.insert("published_in".to_owned(), entry.published_in.clone());
+        vals.insert("doi".to_owned(), entry.doi.clone());
+        vals.insert("url".to_owned(), entry.url.clone());
+        vals
+    }
+}
+impl crate::item::ItemData for Reference {
+    fn for_edit(
+        &mut self,
+        base: &mut ItemBase,
+        is_for_update: bool,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let nt = NewReferenceTemplate {
+            data: &self,
+            base: NewBaseTemplate::from_base(base, is_for_update, world)?,
+            broken_notes: self.notes.replace("\n", "&#10;"),
+        };
+        let mut resp = fanling_interface::Response::new();
+        resp.clear_errors(vec!["name-error".to_owned(), "key-error".to_owned()]);
+        resp.add_tag("content", &(nt.render()?));
+        #[cfg(test)]
+        {
+            resp.set_test_data("ident", &base.get_ident());
+        }
+        trace(&format!("for edit {:?}", &resp));
+        Ok(resp)
+    }
+    fn for_show(
+        &mut self,
+        base: &mut ItemBase,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let t = ShowReferenceTemplate {
+            data: &self,
+            citation: self.short_citation(),
+            rendered_notes: markdown::render(&self.notes, &world.render_context()),
+            base: ShowBaseTemplate::from_base(base, world)?
+                .with_actions(base, self.available_actions(base))
+                .with_stats(self.stats(base, world)?),
+        };
+        let mut resp = fanling_interface::Response::new();
+        resp.add_tag("content", &(t.render()?));
+        #[cfg(test)]
+        {
+            resp.set_test_data("ident", &base.get_ident());
+            resp.set_test_data("key", &self.key);
+        }
+        trace(&format!("for show {:?}", &resp));
+        Ok(resp)
+    }
+    fn to_yaml(&self, base: &crate::item::ItemBase) -> Result<Vec<u8>, FanlingError> {
+        let for_serde = ReferenceForSerde {
+            base: crate::item::ItemBaseForSerde::from_base(base)?,
+            data: self.clone(),
+        };
+        let yaml = serde_yaml::to_vec(&for_serde)?;
+        trace(&format!("yaml is {}", String::from_utf8_lossy(&yaml)));
+        Ok(yaml)
+    }
+    fn is_open(&self) -> bool {
+        true
+    }
+    fn is_ready(&mut self, _world: &mut World) -> FLResult<bool> {
+        Ok(false)
+    }
+    /** can be turned into an ident */
+    fn descr_for_ident(&self) -> String {
+        self.key.clone()
+    }
+    /** an English-language description */
+    fn description(&self) -> String {
+        self.name.clone()
+    }
+    /** a description that can be used in a list */
+    fn description_for_list(&self) -> String {
+        let citation = self.short_citation();
+        if citation.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{} ({})", self.name, citation)
+        }
+    }
+    fn set_data(&mut self, vals: &HashMap<String, String>, _world: &mut World) -> NullResult {
+        match vals.get("name") {
+            Some(s) => self.name = s.trim().to_owned(),
+            _ => return Err(fanling_error!("no name")),
+        }
+        let get = |field: &str| {
+            vals.get(field)
+                .map_or("".to_owned(), |s| s.trim().to_owned())
+        };
+        self.entry_type = get("entry_type").to_lowercase();
+        self.authors = get("authors");
+        self.year = get("year");
+        self.published_in = get("published_in");
+        self.doi = get("doi");
+        self.url = get("url");
+        self.notes = vals.get("notes").map_or("".to_owned(), |s| s.to_string());
+        self.key = get("key");
+        if self.key.is_empty() {
+            self.key = self.default_key();
+        }
+        Ok(())
+    }
+    fn set_from_yaml(&mut self, yaml: &serde_yaml::Value, _world: &mut World) -> NullResult {
+        self.set_from_yaml_basic(yaml)
+    }
+    /** do action for reference -- should never get called */
+    fn do_action(
+        &mut self,
+        _base: &mut ItemBase,
+        _action: crate::Action,
+        _world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        Err(fanling_error!("reference do action called, should never happen").into())
+    }
+    /** copy from another item data */
+    fn fanling_clone(&self) -> FLResult<Box<dyn ItemData>> {
+        Ok(Box::new(self.clone()))
+    }
+    /** transitional to fix old data */
+    fn fix_data(
+        &self,
+        _yaml: &serde_yaml::Value,
+        _base: &mut ItemBase,
+        _world: &mut World,
+    ) -> NullResult {
+        Ok(())
+    }
+}
+impl Default for Reference {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+#[derive(Serialize, Deserialize)]
+struct ReferenceForSerde {
+    #[serde(flatten)]
+    base: crate::item::ItemBaseForSerde,
+    #[serde(flatten)]
+    data: Reference,
+}
+/** template data for creating a new reference */
+#[derive(Template)]
+#[template(path = "new-reference.html", print = "none")]
+struct NewReferenceTemplate<'a> {
+    data: &'a Reference,
+    base: NewBaseTemplate,
+    broken_notes: String,
+}
+
+/** template data for showing a reference */
+#[derive(Template)]
+#[template(path = "show-reference.html", print = "none")]
+struct ShowReferenceTemplate<'a> {
+    data: &'a Reference,
+    /** the authors and year */
+    citation: String,
+    rendered_notes: String,
+    base: ShowBaseTemplate,
+}
+
+/** the references, with their idents (private ones are left out, as their keys are encrypted) */
+fn all_references(world: &mut World) -> FLResult<Vec<(Ident, Reference)>> {
+    let mut references = vec![];
+    for entry in world.search_type("Reference")?.entries {
+        let (_base, values) = world.get_item_parts(&entry.link.ident)?;
+        if crate::private::is_sealed(&values) {
+            continue;
+        }
+        let mut reference = Reference::new();
+        reference.set_from_yaml_basic(&values)?;
+        references.push((entry.link.ident.clone(), reference));
+    }
+    Ok(references)
+}
+/** the ident of the reference with the citation key, if there is one */
+pub fn find_by_key(world: &mut World, key: &str) -> FLResult<Option<Ident>> {
+    Ok(all_references(world)?
+        .into_iter()
+        .find(|(_ident, reference)| reference.key == key)
+        .map(|(ident, _reference)| ident))
+}
+/** show the reference cited with the key */
+pub fn cite(world: &mut World, key: &str) -> fanling_interface::ResponseResult {
+    match find_by_key(world, key)? {
+        Some(ident) => {
+            let item_ref = world.get_item(ident, "Reference".to_owned())?;
+            let resp = item_ref.borrow_mut().for_show(world)?;
+            Ok(resp)
+        }
+        None => {
+            let mut resp = fanling_interface::Response::new();
+            resp.show_toast(&format!("No reference has the citation key '{}'.", key));
+            Ok(resp)
+        }
+    }
+}
+
+/** template data for importing and exporting BibTeX */
+#[derive(Template)]
+#[template(path = "bibtex.html", print = "none")]
+struct BibTeXTemplate {
+    exported: String,
+    count: usize,
+    message: String,
+}
+/** write all the references to a BibTeX file in the export directory,
+and also show them as BibTeX text with a form for importing more */
+pub fn export_bibtex(world: &mut World) -> fanling_interface::ResponseResult {
+    let entries: Vec<BibEntry> = all_references(world)?
+        .iter()
+        .map(|(_ident, reference)| reference.to_entry())
+        .collect();
+    let exported = bibtex::to_bibtex_text(&entries);
+    let dir = world.export_dir().to_path_buf();
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!(
+        "references-{}.bib",
+        chrono::Local::today()
+            .naive_local()
+            .format(crate::format::ISO_DATE_FORMAT)
+    ));
+    fs::write(&path, &exported)?;
+    trace(&format!(
+        "exported {} references to {:?}",
+        entries.len(),
+        &path
+    ));
+    let message = format!("Exported to {}", path.display());
+    let t = BibTeXTemplate {
+        exported,
+        count: entries.len(),
+        message,
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    resp.set_test_data("count", &format!("{}", entries.len()));
+    Ok(resp)
+}
+/** create a reference for each entry in the BibTeX text (except those
+whose keys are already used), then list the new references */
+pub fn import_bibtex(world: &mut World, text: &str) -> fanling_interface::ResponseResult {
+    let base = ItemBaseForSerde {
+        ident: "".to_owned(),
+        type_name: "Reference".to_owned(),
+        ..ItemBaseForSerde::default()
+    };
+    let known: Vec<String> = all_references(world)?
+        .into_iter()
+        .map(|(_ident, reference)| reference.key)
+        .collect();
+    let mut imported = crate::item::ItemListEntryList::from_vec(vec![]);
+    for entry in bibtex::parse(text) {
+        if known.contains(&entry.key) || entry.title.is_empty() {
+            trace(&format!("skipping BibTeX entry {}", &entry.key));
+            continue;
+        }
+        let vals = Reference::vals_from_entry(&entry);
+        let item_ref = world.make_item("Reference", &base, &vals)?;
+        imported.add(crate::item::ItemListEntry::from_item(&item_ref.borrow())?);
+    }
+    trace(&format!("imported {} references", imported.num_entries()));
+    World::show_list(&mut imported, "imported")
+}
+
+/** policy for the reference item type*/
+#[derive(Debug)]
+pub struct ReferenceTypePolicy {}
+impl ReferenceTypePolicy {
+    pub fn new() -> Self {
+        Self {}
+    }
+    pub fn new_boxed() -> Box<Self> {
+        Box::new(Self::new())
+    }
+}
+impl crate::item::ItemTypePolicy for ReferenceTypePolicy {
+    fn kind(&self) -> crate::item::ItemKind {
+        crate::item::ItemKind::Reference
+    }
+    fn make_raw(&self, item_type: crate::item::ItemTypeRef) -> Item {
+        Item::new_with_data(item_type, Box::new(Reference::new()))
+    }
+    fn resolve_conflict_both(
+        &self,
+        _world: &mut World,
+        _ancestor: &Value,
+        ours: &Value,
+        theirs: &Value,
+    ) -> FLResult<Box<dyn ItemData>> {
+        let mut or = Reference::new();
+        or.set_from_yaml_basic(&ours)?;
+        let mut tr = Reference::new();
+        tr.set_from_yaml_basic(&theirs)?;
+        or.name = merge_strings(&or.name, &tr.name);
+        or.authors = merge_strings(&or.authors, &tr.authors);
+        or.published_in = merge_strings(&or.published_in, &tr.published_in);
+        or.notes = merge_strings(&or.notes, &tr.notes);
+        /* the other fields are single values, so are kept unless blank */
+        for (o, t) in vec![
+            (&mut or.key, tr.key),
+            (&mut or.entry_type, tr.entry_type),
+            (&mut or.year, tr.year),
+            (&mut or.doi, tr.doi),
+            (&mut or.url, tr.url),
+        ] {
+            if o.is_empty() {
+                *o = t;
+            }
+        }
+        Ok(Box::new(or))
+    }
+    fn check_valid(
+        &mut self,
+        base: &ItemBaseForSerde,
+        vals: &HashMap<String, String>,
+        world: &mut World,
+    ) -> ActionResponse {
+        let mut ar = ActionResponse::new();
+        ar.assert(
+            !vals["name"].trim().is_empty(),
+            "name-error",
+            "Title must be non-blank.",
+        );
+        let key = vals.get("key").map_or("", |k| k.trim());
+        let valid = Regex::new(&format!("^{}$", KEY_PATTERN))
+            .map(|re| key.is_empty() || re.is_match(key))
+            .unwrap_or(false);
+        ar.assert(
+            valid,
+            "key-error",
+            "A citation key can only have letters, digits and : . / - _",
+        );
+        if valid && !key.is_empty() {
+            let used = match find_by_key(world, key) {
+                Ok(found) => found.map_or(false, |ident| ident != base.ident),
+                Err(_) => false,
+            };
+            ar.warn(
+                !used,
+                "key-error",
+                "Another reference has this citation key.",
+            );
+        }
+        ar
+    }
+    /** get item data from serde value */
+    fn from_yaml(&self, values: &Value, world: &mut World) -> FLResult<Box<dyn ItemData>> {
+        let mut r = Reference::default();
+        r.set_from_yaml(&values, world)?;
+        Ok(Box::new(r))
+    }
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "reference {}",
+        Colour::Fixed(4).on(Colour::Fixed(230)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn keys() {
+        let mut r = Reference::new();
+        r.authors = "Turing, Alan and Alonzo Church".to_owned();
+        r.year = "1936".to_owned();
+        assert_eq!(vec!["Turing", "Church"], r.family_names());
+        assert_eq!("Turing and Church 1936", r.short_citation());
+        assert_eq!("turing1936", r.default_key());
+        assert_eq!("ref", Reference::new().default_key());
+    }
+}
@@ -0,0 +1,236 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! reminders of birthdays and anniversaries, from the dates of
+[`Contact`](crate::contact::Contact) items, and the times of the
+notifications that the main program is to schedule.
+
+The reminders are worked out when needed, so there is no need to
+create an event for each of them. They are shown in the agenda, and
+those for the next few days are given as notifications when Fanling
+starts. Someone born on 29 February is remembered on 28 February in
+other years.
+
+Notifications are scheduled (see [`next_reminders`]) for the
+reminder time and deadline of each open task, a little before each
+event starts, and in the morning of each birthday and anniversary,
+so that they are given even when Fanling is not running. */
+use crate::contact::Contact;
+use crate::shared::{FLResult, NullResult};
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone};
+use fanling_interface::ScheduledReminder;
+
+/** how many days ahead notifications are given for */
+const NOTICE_DAYS: i64 = 7;
+/** how many days ahead notifications are scheduled for */
+const SCHEDULE_DAYS: i64 = 31;
+/** how many minutes before an event starts its notification is given */
+const EVENT_NOTICE_MINUTES: i64 = 15;
+/** the hour of the day that notifications for whole days (such as
+birthdays or deadlines without a time) are given */
+const MORNING_HOUR: u32 = 9;
+
+/** what a reminder is for */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReminderKind {
+    Birthday,
+    Anniversary,
+}
+/** a reminder of a birthday or anniversary of a contact */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reminder {
+    /** the ident of the contact */
+    pub ident: String,
+    pub name: String,
+    pub kind: ReminderKind,
+    /** the day of the reminder */
+    pub date: NaiveDate,
+    /** how many years it has been */
+    pub years: i32,
+}
+impl Reminder {
+    /** an English-language description */
+    pub fn description(&self) -> String {
+        let what = match self.kind {
+            ReminderKind::Birthday => "birthday",
+            ReminderKind::Anniversary => "anniversary",
+        };
+        if self.years > 0 {
+            format!("{}'s {} ({})", self.name, what, self.years)
+        } else {
+            format!("{}'s {}", self.name, what)
+        }
+    }
+}
+
+/** the day in a year that something that happened on a date is remembered */
+pub fn in_year(date: NaiveDate, year: i32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, date.month(), date.day())
+        .unwrap_or_else(|| NaiveDate::from_ymd(year, 2, 28))
+}
+
+/** the reminders on or after `from` and before `to`, in date order */
+pub fn reminders(world: &mut World, from: NaiveDate, to: NaiveDate) -> FLResult<Vec<Reminder>> {
+    let mut found = vec![];
+    for entry in world.search_type("Contact")?.entries {
+        let (_base, values) = world.get_item_parts(&entry.link.ident)?;
+        if crate::private::is_sealed(&values) {
+            continue;
+        }
+        let contact = Contact::from_values(&values)?;
+        for (kind, date) in contact.reminder_dates() {
+            for year in from.year()..=to.year() {
+                let day = in_year(date, year);
+                if day >= from && day < to && day >= date {
+                    found.push(Reminder {
+                        ident: entry.link.ident.clone(),
+                        name: contact.name().to_owned(),
+                        kind,
+                        date: day,
+                        years: year - date.year(),
+                    });
+                }
+            }
+        }
+    }
+    found.sort_by_key(|r| r.date);
+    Ok(found)
+}
+
+/** a reminder, for the template */
+struct Notice {
+    ident: String,
+    when: String,
+    descr: String,
+}
+/** template data for the notifications */
+#[derive(Template)]
+#[template(path = "reminders.html", print = "none")]
+struct RemindersTemplate {
+    notices: Vec<Notice>,
+}
+/** add notifications for the reminders in the next few days */
+pub fn add_notifications(world: &mut World, resp: &mut fanling_interface::Response) -> NullResult {
+    let today = chrono::Local::today().naive_local();
+    let mut notices = vec![];
+    for reminder in reminders(world, today, today + Duration::days(NOTICE_DAYS))? {
+        let when = if reminder.date == today {
+            "Today".to_owned()
+        } else {
+            world.format().format_date(reminder.date)
+        };
+        resp.add_notification(&format!("{}: {}", &when, reminder.description()));
+        notices.push(Notice {
+            ident: reminder.ident.clone(),
+            when,
+            descr: reminder.description(),
+        });
+    }
+    trace(&format!("{} reminders", notices.len()));
+    resp.add_tag("notifications", &(RemindersTemplate { notices }.render()?));
+    Ok(())
+}
+
+/** when to notify of something at a time (in the morning if it is at midnight, as for a date without a time) */
+fn notice_time(time: NaiveDateTime) -> NaiveDateTime {
+    if time.time() == chrono::NaiveTime::from_hms(0, 0, 0) {
+        time.date().and_hms(MORNING_HOUR, 0, 0)
+    } else {
+        time
+    }
+}
+/** the notifications to be given at or after `now`, within the next
+few weeks, in time order: as (local time, ident, text) */
+pub fn scheduled(
+    world: &mut World,
+    now: NaiveDateTime,
+) -> FLResult<Vec<(NaiveDateTime, String, String)>> {
+    let (today, to) = (now.date(), now.date() + Duration::days(SCHEDULE_DAYS));
+    let mut found = vec![];
+    for entry in world.search_type("Task")?.entries {
+        let (_base, values) = world.get_item_parts(&entry.link.ident)?;
+        if crate::private::is_sealed(&values) {
+            continue;
+        }
+        if let Some((name, remind, deadline)) = crate::task::reminder_times(&values)? {
+            if !crate::task::is_no_time(&remind) {
+                found.push((remind, entry.link.ident.clone(), name.clone()));
+            }
+            if !crate::task::is_no_time(&deadline) {
+                found.push((
+                    notice_time(deadline),
+                    entry.link.ident.clone(),
+                    format!("Deadline: {}", name),
+                ));
+            }
+        }
+    }
+    for (start, ident, name) in crate::event::upcoming(world, today, to)? {
+        let text = format!("{}: {}", world.format().format_date_time(start), name);
+        found.push((start - Duration::minutes(EVENT_NOTICE_MINUTES), ident, text));
+    }
+    for reminder in reminders(world, today, to)? {
+        found.push((
+            reminder.date.and_hms(MORNING_HOUR, 0, 0),
+            reminder.ident.clone(),
+            reminder.description(),
+        ));
+    }
+    found.retain(|(when, _ident, _text)| *when >= now && when.date() < to);
+    found.sort();
+    Ok(found)
+}
+/** the next `n` (or fewer) notifications to be scheduled by the main program */
+pub fn next_reminders(world: &mut World, n: usize) -> FLResult<Vec<ScheduledReminder>> {
+    let mut found = scheduled(world, chrono::Local::now().naive_local())?;
+    found.truncate(n);
+    trace(&format!("{} notifications to schedule", found.len()));
+    Ok(found
+        .into_iter()
+        .map(|(when, ident, text)| ScheduledReminder {
+            when: chrono::Local
+                .from_local_datetime(&when)
+                .earliest()
+                .map_or_else(|| when.timestamp(), |t| t.timestamp()),
+            ident,
+            text,
+        })
+        .collect())
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "reminder {}",
+        Colour::Fixed(11).on(Colour::Fixed(236)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn leap_day() {
+        let born = NaiveDate::from_ymd(2000, 2, 29);
+        assert_eq!(NaiveDate::from_ymd(2021, 2, 28), in_year(born, 2021));
+        assert_eq!(NaiveDate::from_ymd(2024, 2, 29), in_year(born, 2024));
+        let reminder = Reminder {
+            ident: "ada-lovelace".to_owned(),
+            name: "Ada Lovelace".to_owned(),
+            kind: ReminderKind::Birthday,
+            date: in_year(born, 2021),
+            years: 21,
+        };
+        assert_eq!("Ada Lovelace's birthday (21)", reminder.description());
+    }
+    #[test]
+    fn notice_times() {
+        let day = NaiveDate::from_ymd(2021, 3, 4);
+        assert_eq!(day.and_hms(9, 0, 0), notice_time(day.and_hms(0, 0, 0)));
+        assert_eq!(day.and_hms(14, 30, 0), notice_time(day.and_hms(14, 30, 0)));
+    }
+}
@@ -0,0 +1,310 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! several remotes for a repository (such as a mirror on a server of
+your own as well as one on GitHub), each with its own ssh key and its
+own policy for when it is pushed to.
+
+The remote given in the options of the repository is the main one. The
+others are added on the remotes page, and are kept in the Git
+configuration of the repository (so on this device only), as are the
+policies and ssh keys of all of them. A remote that is pushed to on save
+is pushed to soon after each action that makes a commit, when the page
+next asks for a [`crate::Action::PushPending`] (so that the action is
+not held up by the push, as when offline); the others are pushed to
+when a push is asked for (including when quitting). A sync pulls from
+each remote that is pulled from, in turn.
+
+How the last pull from and push to each remote went is kept (until the
+app is closed) and shown on the remotes page, so that a failure with
+one remote (such as a server that is down) does not stop the sync with
+the others. A failure with the main remote is still an error. */
+use crate::shared::{FLResult, NullResult};
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use chrono::TimeZone;
+use regex::Regex;
+use std::convert::TryInto;
+use std::time::{SystemTime, UNIX_EPOCH};
+use taipo_git_control::{PushPolicy, RemoteDescr};
+
+/** the characters allowed in the name of a remote */
+const NAME_PATTERN: &str = r"^[\w.-]+$";
+
+/** how a pull or push went */
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    /** when it was done (seconds since the Unix epoch) */
+    pub when: i64,
+    /** what went wrong, if it failed */
+    pub error: Option<String>,
+}
+/** how the last pull from and push to a remote went */
+#[derive(Debug, Clone, Default)]
+pub struct Results {
+    pub pulled: Option<Outcome>,
+    pub pushed: Option<Outcome>,
+}
+impl Results {
+    /** whether the last pull or push failed */
+    pub fn failed(&self) -> bool {
+        [&self.pulled, &self.pushed]
+            .iter()
+            .any(|o| o.as_ref().map_or(false, |o| o.error.is_some()))
+    }
+}
+
+/** the remotes that the last pull or push failed for */
+pub fn failed(world: &World) -> Vec<String> {
+    world
+        .remote_results()
+        .iter()
+        .filter(|(_name, results)| results.failed())
+        .map(|(name, _results)| name.clone())
+        .collect()
+}
+/** warn of the remotes that the last pull or push failed for */
+pub fn warn_failed(world: &World, resp: &mut fanling_interface::Response) {
+    let failed = failed(world);
+    if !failed.is_empty() {
+        resp.show_toast(&format!(
+            "{} {}",
+            world.i18n().tr("Could not sync with:"),
+            failed.join(", ")
+        ));
+    }
+}
+/** keep how a pull or push went */
+fn note(world: &mut World, remote: &str, pulled: bool, result: &NullResult) -> NullResult {
+    let outcome = Outcome {
+        when: SystemTime::now()
+            .duration_since(UNIX_EPOCH)?
+            .as_secs()
+            .try_into()?,
+        error: result.as_ref().err().map(|e| format!("{}", e)),
+    };
+    trace(&format!(
+        "{} {}: {:?}",
+        if pulled { "pulled from" } else { "pushed to" },
+        remote,
+        outcome.error
+    ));
+    let results = world
+        .remote_results_mut()
+        .entry(remote.to_owned())
+        .or_default();
+    if pulled {
+        results.pulled = Some(outcome);
+    } else {
+        results.pushed = Some(outcome);
+    }
+    Ok(())
+}
+/** pull from a remote, keeping how it went */
+fn pull_one(world: &mut World, remote: &str) -> NullResult {
+    let result = world.pull_from(remote);
+    note(world, remote, true, &result)?;
+    result
+}
+/** push to a remote, keeping how it went */
+fn push_one(world: &mut World, remote: &str, force: bool) -> NullResult {
+    let result = if world.main_remote().as_deref() == Some(remote) {
+        world.push_main(force)
+    } else {
+        world.push_to(remote, force)
+    };
+    note(world, remote, false, &result)?;
+    result
+}
+/** pull from each remote that is pulled from, returning the error
+from the main remote (after trying the others) if it failed */
+pub fn pull_all(world: &mut World) -> NullResult {
+    let main = world.main_remote();
+    let mut main_result = Ok(());
+    for remote in world.remotes()? {
+        if !remote.pull {
+            continue;
+        }
+        let result = pull_one(world, &remote.name);
+        if main.as_ref() == Some(&remote.name) {
+            main_result = result;
+        }
+    }
+    main_result
+}
+/** push to each remote, returning the error from the main remote
+(after trying the others) if it failed */
+pub fn push_all(world: &mut World, force: bool) -> NullResult {
+    let main = world.main_remote();
+    let mut main_result = Ok(());
+    for remote in world.remotes()? {
+        let result = push_one(world, &remote.name, force);
+        if main.as_ref() == Some(&remote.name) {
+            main_result = result;
+        }
+    }
+    world.set_push_pending(false);
+    main_result
+}
+/** push to the remotes that are pushed to on save, returning whether
+they all were; failures are kept rather than returned */
+fn push_on_save(world: &mut World) -> FLResult<bool> {
+    let mut pushed = true;
+    for remote in world.remotes()? {
+        if remote.push == PushPolicy::OnSave && push_one(world, &remote.name, false).is_err() {
+            pushed = false;
+        }
+    }
+    Ok(pushed)
+}
+/** push to the remotes that are pushed to on save, if there have been
+commits since they were pushed to (trying again next time if any of
+them failed) */
+pub fn push_pending(world: &mut World) -> fanling_interface::ResponseResult {
+    if world.push_pending() {
+        let pushed = push_on_save(world)?;
+        world.set_push_pending(!pushed);
+    }
+    #[allow(unused_mut)]
+    let mut resp = fanling_interface::Response::new();
+    #[cfg(test)]
+    resp.set_test_data("pending", &format!("{}", world.push_pending()));
+    Ok(resp)
+}
+
+/** a remote, for the template */
+struct Row {
+    name: String,
+    url: String,
+    ssh_key: String,
+    on_save: bool,
+    pull: bool,
+    /** whether it is the remote given in the options */
+    main: bool,
+    pulled: String,
+    pushed: String,
+    failed: bool,
+}
+/** template data for the remotes page */
+#[derive(Template)]
+#[template(path = "remotes.html", print = "none")]
+struct RemotesTemplate {
+    rows: Vec<Row>,
+    message: String,
+}
+/** describe how a pull or push went */
+fn describe(world: &World, outcome: &Option<Outcome>) -> String {
+    match outcome {
+        None => world.i18n().tr("never"),
+        Some(outcome) => {
+            let when = world
+                .format()
+                .format_date_time(chrono::Local.timestamp(outcome.when, 0).naive_local());
+            match &outcome.error {
+                Some(error) => format!("{} ({})", when, error),
+                None => when,
+            }
+        }
+    }
+}
+/** show the remotes, with how the last pull from and push to each went */
+pub fn show_remotes(world: &mut World, message: &str) -> fanling_interface::ResponseResult {
+    let main = world.main_remote();
+    let mut rows = vec![];
+    for remote in world.remotes()? {
+        let results = world
+            .remote_results()
+            .get(&remote.name)
+            .cloned()
+            .unwrap_or_default();
+        rows.push(Row {
+            main: main.as_ref() == Some(&remote.name),
+            pulled: describe(world, &results.pulled),
+            pushed: describe(world, &results.pushed),
+            failed: results.failed(),
+            name: remote.name,
+            url: remote.url,
+            ssh_key: remote.ssh_key,
+            on_save: remote.push == PushPolicy::OnSave,
+            pull: remote.pull,
+        });
+    }
+    trace(&format!("showing {} remotes", rows.len()));
+    let t = RemotesTemplate {
+        rows,
+        message: world.i18n().tr(message),
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.clear_errors(vec!["remote-error".to_owned()]);
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    resp.set_test_data(
+        "remotes",
+        &t.rows
+            .iter()
+            .map(|r| format!("{}:{}", r.name, if r.failed { "failed" } else { "ok" }))
+            .collect::<Vec<String>>()
+            .join(","),
+    );
+    Ok(resp)
+}
+/** what is wrong with the remote, if anything */
+fn check_remote(world: &World, remote: &RemoteDescr) -> FLResult<Option<&'static str>> {
+    let main = world.main_remote().as_ref() == Some(&remote.name);
+    Ok(if !Regex::new(NAME_PATTERN)?.is_match(&remote.name) {
+        Some("The name of a remote can only have letters, digits, dots and dashes.")
+    } else if !main && remote.url.trim().is_empty() {
+        Some("A remote needs a url.")
+    } else {
+        None
+    })
+}
+/** add or change a remote (from the remotes page), then show the remotes */
+pub fn save_remote(world: &mut World, remote: RemoteDescr) -> fanling_interface::ResponseResult {
+    let remote = RemoteDescr {
+        name: remote.name.trim().to_owned(),
+        url: remote.url.trim().to_owned(),
+        ..remote
+    };
+    if let Some(problem) = check_remote(world, &remote)? {
+        let mut ar = crate::world::ActionResponse::new();
+        ar.add_error("remote-error", problem);
+        return ar.translated(&world.i18n()).to_response();
+    }
+    trace(&format!("saving remote {}", remote.name));
+    world.set_remote(&remote)?;
+    show_remotes(world, "The remote has been saved.")
+}
+/** remove a remote (other than the main one), then show the remotes */
+pub fn remove_remote(world: &mut World, name: &str) -> fanling_interface::ResponseResult {
+    if world.main_remote().as_deref() == Some(name) {
+        return show_remotes(world, "The main remote is given in the options.");
+    }
+    trace(&format!("removing remote {}", name));
+    world.delete_remote(name)?;
+    show_remotes(world, "The remote has been removed.")
+}
+/** pull from one remote (from the remotes page); how it went is shown with the remotes */
+pub fn pull_from(world: &mut World, name: &str) -> NullResult {
+    let _kept = pull_one(world, name);
+    Ok(())
+}
+/** push to one remote (from the remotes page), then show the remotes */
+pub fn push_to_and_show(
+    world: &mut World,
+    name: &str,
+    force: bool,
+) -> fanling_interface::ResponseResult {
+    let _kept = push_one(world, name, force);
+    show_remotes(world, "")
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "remotes {}",
+        Colour::Fixed(15).on(Colour::Fixed(94)).paint(m)
+    );
+}
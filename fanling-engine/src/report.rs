@@ -0,0 +1,243 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! a report for weekly reviews: the activity heatmap for the last
+year, the tasks completed in each of the last weeks and the open items
+that have been left untouched the longest.
+
+All of it comes from the git log (see [`crate::stats`] for how
+commit messages are counted), so nothing needs to be recorded as the
+items are changed. An item that has not been changed in the commits
+examined ([`crate::stats::MAX_COMMITS`]) is shown as untouched since
+before them. */
+use crate::item::ItemLink;
+use crate::shared::FLResult;
+use crate::stats::Activity;
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use chrono::{Duration, Local, NaiveDate, TimeZone};
+
+/** the number of weeks of completed tasks shown */
+const REPORT_WEEKS: usize = 12;
+/** the number of untouched items shown */
+const MAX_UNTOUCHED: usize = 10;
+/** the width of the bar for the week with the most tasks completed (pixels) */
+const BAR_WIDTH: u32 = 200;
+/** the height of a row in the chart of completed tasks (pixels) */
+const CHART_ROW_HEIGHT: u32 = 18;
+/** space for the dates in the chart of completed tasks (pixels) */
+const CHART_LABEL_WIDTH: u32 = 90;
+/** space for the numbers after the bars in the chart of completed tasks (pixels) */
+const CHART_COUNT_WIDTH: u32 = 40;
+
+/** the tasks completed in a week */
+struct Week {
+    /** the first day of the week, formatted */
+    start: String,
+    completed: u32,
+    /** the width of the bar (pixels) */
+    width: u32,
+}
+/** an open item that has not been changed for a while */
+struct Untouched {
+    ident: String,
+    descr: String,
+    /** when it was last changed, formatted (blank if before the commits examined) */
+    when: String,
+    /** the number of days since then */
+    days: i64,
+}
+
+/** the number of tasks completed in each of the `weeks` weeks up to
+the one containing `last_day`, given by their first days (oldest first) */
+fn completed_by_week(
+    activity: &Activity,
+    last_day: NaiveDate,
+    weeks: usize,
+    format: &crate::FormatOptions,
+) -> Vec<(NaiveDate, u32)> {
+    let last_week_start = last_day - Duration::days(i64::from(format.day_of_week(last_day)));
+    (0..weeks)
+        .rev()
+        .map(|n| {
+            let start = last_week_start - Duration::weeks(n as i64);
+            let completed = activity
+                .range(start..start + Duration::weeks(1))
+                .map(|(_d, a)| a.completed)
+                .sum::<u32>();
+            (start, completed)
+        })
+        .collect()
+}
+
+/** the tasks completed each week as an SVG bar chart, a row for each
+week: its first day, the bar and the number */
+fn completed_svg(by_week: &[(NaiveDate, u32)], format: &crate::FormatOptions) -> String {
+    let most = by_week.iter().map(|(_s, c)| *c).max().unwrap_or(0);
+    let width = CHART_LABEL_WIDTH + BAR_WIDTH + CHART_COUNT_WIDTH;
+    let height = CHART_ROW_HEIGHT * by_week.len().max(1) as u32;
+    let mut parts = vec![format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="sans-serif" font-size="11">"#,
+        width, height
+    )];
+    for (row, (start, completed)) in by_week.iter().enumerate() {
+        let y = CHART_ROW_HEIGHT * row as u32;
+        let bar = if most == 0 {
+            0
+        } else {
+            completed * BAR_WIDTH / most
+        };
+        parts.push(format!(
+            r#"<text x="0" y="{}">{}</text>"#,
+            y + CHART_ROW_HEIGHT - 5,
+            crate::markdown::escape(&format.format_date(*start))
+        ));
+        parts.push(format!(
+            r##"<rect x="{}" y="{}" width="{}" height="{}" fill="#40c463"/>"##,
+            CHART_LABEL_WIDTH,
+            y + 2,
+            bar,
+            CHART_ROW_HEIGHT - 4
+        ));
+        parts.push(format!(
+            r#"<text x="{}" y="{}">{}</text>"#,
+            CHART_LABEL_WIDTH + bar + 4,
+            y + CHART_ROW_HEIGHT - 5,
+            completed
+        ));
+    }
+    parts.push("</svg>".to_owned());
+    parts.join("")
+}
+/** the tasks completed each week, as in the report, as SVG for
+exporting (see [`crate::stats::export_chart`]) */
+pub fn completed_chart(world: &mut World) -> FLResult<String> {
+    let activity = crate::stats::activity_from_history(&world.history(crate::stats::MAX_COMMITS)?);
+    let by_week = completed_by_week(
+        &activity,
+        Local::today().naive_local(),
+        REPORT_WEEKS,
+        world.format(),
+    );
+    Ok(completed_svg(&by_week, world.format()))
+}
+
+/** the open items changed least recently (with when they were changed, if known), oldest first */
+fn untouched(world: &mut World, max: usize) -> FLResult<Vec<(String, String, Option<i64>)>> {
+    let last_changed = world.last_changed(crate::stats::MAX_COMMITS)?;
+    let mut items = vec![];
+    for entry in &world.search_all()?.entries {
+        let item_ref = ItemLink::from(entry.link.clone()).resolve_link(world)?;
+        let item = item_ref.borrow();
+        if item.is_open() {
+            let ident = item.ident();
+            let when = last_changed.get(&ident).copied();
+            items.push((ident, item.description_for_list(), when));
+        }
+    }
+    /* those not changed in the commits examined (none) come first */
+    items.sort_by(|a, b| a.2.cmp(&b.2).then_with(|| a.0.cmp(&b.0)));
+    items.truncate(max);
+    Ok(items)
+}
+
+/** template data for the review report */
+#[derive(Template)]
+#[template(path = "report.html", print = "none")]
+struct ReportTemplate {
+    heatmap: String,
+    weeks: Vec<Week>,
+    untouched: Vec<Untouched>,
+}
+/** show the report for a weekly review */
+pub fn show_report(world: &mut World) -> fanling_interface::ResponseResult {
+    let today = Local::today().naive_local();
+    let activity = crate::stats::activity_from_history(&world.history(crate::stats::MAX_COMMITS)?);
+    let by_week = completed_by_week(&activity, today, REPORT_WEEKS, world.format());
+    let most = by_week.iter().map(|(_s, c)| *c).max().unwrap_or(0);
+    let weeks = by_week
+        .iter()
+        .map(|(start, completed)| Week {
+            start: world.format().format_date(*start),
+            completed: *completed,
+            width: if most == 0 {
+                0
+            } else {
+                completed * BAR_WIDTH / most
+            },
+        })
+        .collect();
+    let now = Local::now().naive_local();
+    let untouched: Vec<Untouched> = untouched(world, MAX_UNTOUCHED)?
+        .into_iter()
+        .map(|(ident, descr, when)| {
+            let local = when.map(|w| Local.timestamp(w, 0).naive_local());
+            Untouched {
+                ident,
+                descr,
+                when: local.map_or_else(String::new, |l| world.format().format_date_time(l)),
+                days: local.map_or(0, |l| (now - l).num_days()),
+            }
+        })
+        .collect();
+    trace(&format!("{} untouched items", untouched.len()));
+    #[cfg(test)]
+    let untouched_idents: Vec<String> = untouched.iter().map(|u| u.ident.clone()).collect();
+    let t = ReportTemplate {
+        heatmap: crate::stats::heatmap_svg(
+            &activity,
+            today,
+            crate::stats::HEATMAP_WEEKS,
+            world.format(),
+        ),
+        weeks,
+        untouched,
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    {
+        let this_week = by_week.last().map_or(0, |(_s, c)| *c);
+        resp.set_test_data("completed", &format!("{}", this_week));
+        resp.set_test_data("untouched", &untouched_idents.join(","));
+    }
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "report {}",
+        Colour::Fixed(0).on(Colour::Fixed(186)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::DayActivity;
+    #[test]
+    fn weeks() {
+        let mut activity = Activity::new();
+        let completed = |n| DayActivity {
+            completed: n,
+            ..DayActivity::default()
+        };
+        /* a Wednesday, the Monday before it and the Sunday before that */
+        activity.insert(NaiveDate::from_ymd(2020, 6, 3), completed(2));
+        activity.insert(NaiveDate::from_ymd(2020, 6, 1), completed(1));
+        activity.insert(NaiveDate::from_ymd(2020, 5, 31), completed(4));
+        let format = crate::FormatOptions::default();
+        let weeks = completed_by_week(&activity, NaiveDate::from_ymd(2020, 6, 4), 3, &format);
+        assert_eq!(
+            vec![
+                (NaiveDate::from_ymd(2020, 5, 18), 0),
+                (NaiveDate::from_ymd(2020, 5, 25), 4),
+                (NaiveDate::from_ymd(2020, 6, 1), 3)
+            ],
+            weeks
+        );
+    }
+}
@@ -0,0 +1,129 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! several repositories open at once (eg "work" and "personal").
+
+One repository is current and actions apply to it. An item in another
+repository is referred to by qualifying its ident with the name of the
+repository, as in `work:some-item` (also in wiki links, such as
+`[[work:some-item]]`); acting on such an item first makes its
+repository the current one. Lists only include the current
+repository unless cross-repository search is enabled. */
+use crate::search::{Search, SearchOptions};
+use crate::store::Store;
+use crate::world::World;
+use askama::Template;
+use taipo_git_control::{RepoActionRequired, RepoOptions};
+
+/** separates the repository name from the ident in a qualified ident */
+const SEPARATOR: char = ':';
+
+/** options for the repositories */
+#[derive(Debug, Clone)]
+pub struct ReposOptions {
+    /** name of the main repository (given by the engine's repository options) */
+    pub name: String,
+    /** the other repositories to open */
+    pub others: Vec<OtherRepoOptions>,
+    /** whether lists include items from the other repositories */
+    pub cross_repo_search: bool,
+}
+impl Default for ReposOptions {
+    fn default() -> Self {
+        Self {
+            name: "main".to_owned(),
+            others: vec![],
+            cross_repo_search: false,
+        }
+    }
+}
+/** options for a repository that is opened as well as the main one */
+#[derive(Debug, Clone)]
+pub struct OtherRepoOptions {
+    pub name: String,
+    pub repo_options: RepoOptions,
+    pub search_options: SearchOptions,
+    /** whether its items can only be read (see [`crate::read_only`]) */
+    pub read_only: bool,
+}
+
+/** a repository that is open but is not the current one */
+pub(crate) struct OpenRepo {
+    pub(crate) name: String,
+    pub(crate) store: Store,
+    pub(crate) search: Search,
+    /** what needs to be done with changes fetched when it was opened (done when it is first made current) */
+    pub(crate) action_required: Option<RepoActionRequired>,
+}
+
+/** qualify an ident with the name of its repository */
+pub fn qualify(repo: &str, ident: &str) -> String {
+    format!("{}{}{}", repo, SEPARATOR, ident)
+}
+/** split a qualified ident into the repository name and the ident (`None` if not qualified) */
+pub fn split_qualified(ident: &str) -> Option<(&str, &str)> {
+    let mut parts = ident.splitn(2, SEPARATOR);
+    match (parts.next(), parts.next()) {
+        (Some(repo), Some(ident)) if !repo.is_empty() => Some((repo, ident)),
+        _ => None,
+    }
+}
+
+/** the ident in the current repository (named `current`) that a wiki
+link is to, which may be qualified with the name of the current
+repository (`None` if it is to an item in another repository) */
+pub fn local_link<'a>(link: &'a str, current: &str) -> Option<&'a str> {
+    match split_qualified(link) {
+        Some((repo, ident)) if repo == current => Some(ident),
+        Some(_) => None,
+        None => Some(link),
+    }
+}
+
+/** a repository, for the template */
+struct RepoEntry {
+    name: String,
+    is_current: bool,
+}
+/** template data for choosing the current repository */
+#[derive(Template)]
+#[template(path = "repos.html", print = "none")]
+struct ReposTemplate {
+    repos: Vec<RepoEntry>,
+}
+/** show the repositories, so one can be made current */
+pub fn show_repos(world: &mut World) -> fanling_interface::ResponseResult {
+    let current = world.repo_name();
+    let mut names = world.repo_names();
+    names.sort();
+    let t = ReposTemplate {
+        repos: names
+            .into_iter()
+            .map(|name| RepoEntry {
+                is_current: name == current,
+                name,
+            })
+            .collect(),
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    resp.set_test_data("repo", &current);
+    Ok(resp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn qualified() {
+        assert_eq!("work:abc", qualify("work", "abc"));
+        assert_eq!(Some(("work", "abc")), split_qualified("work:abc"));
+        assert_eq!(None, split_qualified("abc"));
+        assert_eq!(None, split_qualified(":abc"));
+        assert_eq!(Some("abc"), local_link("main:abc", "main"));
+        assert_eq!(Some("abc"), local_link("abc", "main"));
+        assert_eq!(None, local_link("work:abc", "main"));
+    }
+}
@@ -0,0 +1,118 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! restoring an item to a version from its history. The version is
+first shown compared with the current one (see [`crate::diff`]), and
+the user is asked to confirm; once confirmed, the item is given the
+values of that version and committed as a new change, so that the
+history stays linear (and the restore can itself be undone).
+
+The versions of private items cannot be restored, as they are
+encrypted. */
+use crate::item::{split_data_parts, Item};
+use crate::world::{ActionResponse, World};
+use ansi_term::Colour;
+use askama::Template;
+
+/** template data for confirming that an item should be restored to a version */
+#[derive(Template)]
+#[template(path = "restore.html", print = "none")]
+struct RestoreTemplate {
+    ident: String,
+    type_name: String,
+    descr: String,
+    /** the version to restore */
+    id: String,
+    /** the action to do the restore, as JSON */
+    confirm_action: String,
+    /** the warnings to confirm */
+    warnings: Vec<String>,
+    /** the changes that restoring would make, as HTML */
+    rendered_diff: String,
+}
+
+/** restore the item to the version in a commit (given as hex), once
+the user has confirmed it; until then, show what would change */
+pub fn restore(
+    item: &mut Item,
+    id: &str,
+    confirmed: bool,
+    world: &mut World,
+) -> fanling_interface::ResponseResult {
+    let ident = item.ident();
+    let old = if item.is_private() {
+        None
+    } else {
+        world.item_at_commit(&ident, id)?
+    };
+    let mut ar = ActionResponse::new();
+    ar.assert(
+        !item.is_private(),
+        "restore",
+        "The versions of a private item cannot be restored.",
+    );
+    ar.assert(
+        item.is_private() || old.is_some(),
+        "restore",
+        "The item did not exist in that version.",
+    );
+    let old = match old {
+        Some(old) if !ar.is_blocking() => old,
+        _ => return ar.translated(&world.i18n()).to_response(),
+    };
+    let (base, values) = split_data_parts(old.as_bytes())?;
+    ar.assert(
+        base.type_name == item.type_name(),
+        "restore",
+        "The item had another type in that version.",
+    );
+    ar.add_warning(
+        "restore",
+        "The item will be changed back to this version (as a new change).",
+    );
+    if confirmed {
+        ar = ar.confirmed();
+    }
+    if let ActionResponse::Unconfirmed { messages, .. } = &ar {
+        let current = String::from_utf8_lossy(&item.to_yaml()?).to_string();
+        let (current, old) = crate::diff::comparable(Some(current), Some(old))?;
+        let t = RestoreTemplate {
+            ident,
+            type_name: item.type_name(),
+            descr: item.description(),
+            id: id.chars().take(7).collect(),
+            confirm_action: serde_json::to_string(&crate::Action::Restore {
+                id: id.to_owned(),
+                confirmed: true,
+            })?,
+            warnings: messages.iter().map(|m| world.i18n().tr(m)).collect(),
+            rendered_diff: crate::diff::to_html(&crate::diff::diff(&current, &old)?),
+        };
+        let mut resp = fanling_interface::Response::new();
+        resp.add_tag("content", &(t.render()?));
+        resp.set_confirm_required();
+        return Ok(resp);
+    }
+    if ar.is_blocking() {
+        return ar.translated(&world.i18n()).to_response();
+    }
+    trace(&format!("restoring {} to {}", &ident, id));
+    item.set_from_yaml(&values, world)?;
+    item.set_from_serde(&base)?;
+    world.begin_batch();
+    let persisted = world.persist_change_as(item, "restore");
+    world.end_batch()?;
+    persisted?;
+    let mut resp = item.for_show(world)?;
+    resp.show_toast(&world.i18n().tr("The earlier version has been restored."));
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "restore {}",
+        Colour::Fixed(3).on(Colour::Fixed(233)).paint(m)
+    );
+}
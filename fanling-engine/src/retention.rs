@@ -0,0 +1,385 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! retention policies, which keep a long-lived repository tidy by
+archiving or flagging (for review) items of a kind or with a tag once
+they are old.
+
+The policies are kept in the settings, one per line, such as
+
+```text
+#journal older than 2 years: archive
+bookmarks untagged for 90 days: flag
+```
+
+Each line gives the kind of item and/or a `#tag` (either may be left
+out), a condition (`older than`, `unchanged for` or `untagged for` a
+number of days, weeks, months or years) and what to do. An item is
+dealt with by the first policy that applies to it. The items due are
+listed when the items are checked (see [`crate::check`]), and are only
+changed once the list has been previewed and confirmed.
+
+Private items are left alone, as their tags cannot be read. */
+use crate::item::{Ident, ItemLink};
+use crate::shared::{FLResult, NullResult};
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use chrono::{Duration, NaiveDateTime};
+use regex::Regex;
+use std::ops::DerefMut;
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** what a policy does to the items it applies to */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RetentionAction {
+    /** classify the item as archived */
+    Archive,
+    /** classify the item as flagged for review */
+    Flag,
+}
+impl RetentionAction {
+    /** the classification given to the item */
+    pub fn classify(self) -> &'static str {
+        match self {
+            RetentionAction::Archive => "archived",
+            RetentionAction::Flag => "flagged",
+        }
+    }
+    /** the verb for the commit message */
+    fn verb(self) -> &'static str {
+        match self {
+            RetentionAction::Archive => "archive",
+            RetentionAction::Flag => "flag",
+        }
+    }
+}
+/** when a policy applies to an item */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Condition {
+    /** made long enough ago */
+    OlderThan,
+    /** not changed for long enough */
+    UnchangedFor,
+    /** without tags, and not changed for long enough */
+    UntaggedFor,
+}
+
+/** a single retention policy */
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetentionPolicy {
+    /** the type name of the items (any kind if none) */
+    pub kind: Option<String>,
+    /** the tag the items must have (any items if none) */
+    pub tag: Option<String>,
+    pub condition: Condition,
+    /** how old (in days) the items must be */
+    pub days: i64,
+    pub action: RetentionAction,
+    /** the policy as written */
+    pub text: String,
+}
+impl RetentionPolicy {
+    /** parse a policy such as "bookmarks untagged for 90 days: flag" */
+    pub fn parse(line: &str) -> FLResult<Self> {
+        let re = Regex::new(
+            r"(?i)^\s*(.*?)\s*\b(older than|unchanged for|untagged for)\s+(\d+)\s+(day|week|month|year)s?\s*:\s*(\w+)\s*$",
+        )?;
+        let caps = re.captures(line).ok_or_else(|| {
+            fanling_error!(&format!(
+                "'{}' should be like \"#journal older than 2 years: archive\"",
+                line.trim()
+            ))
+        })?;
+        let mut kind = None;
+        let mut tag = None;
+        for word in caps[1].split_whitespace() {
+            if let Some(t) = word.strip_prefix('#') {
+                tag = Some(t.to_owned());
+            } else if !Self::is_any(word) {
+                kind = Some(Self::kind_named(word).ok_or_else(|| {
+                    fanling_error!(&format!(
+                        "unknown kind of item '{}' in '{}'",
+                        word,
+                        line.trim()
+                    ))
+                })?);
+            }
+        }
+        let condition = match caps[2].to_lowercase().as_str() {
+            "older than" => Condition::OlderThan,
+            "unchanged for" => Condition::UnchangedFor,
+            _ => Condition::UntaggedFor,
+        };
+        let unit = match caps[4].to_lowercase().as_str() {
+            "day" => 1,
+            "week" => 7,
+            "month" => 30,
+            _ => 365,
+        };
+        let action = match caps[5].to_lowercase().as_str() {
+            "archive" => RetentionAction::Archive,
+            "flag" => RetentionAction::Flag,
+            other => {
+                return Err(fanling_error!(&format!(
+                    "unknown action '{}' in '{}' (should be archive or flag)",
+                    other,
+                    line.trim()
+                )))
+            }
+        };
+        Ok(Self {
+            kind,
+            tag,
+            condition,
+            days: caps[3].parse::<i64>()? * unit,
+            action,
+            text: line.trim().to_owned(),
+        })
+    }
+    /** whether the word means items of any kind */
+    fn is_any(word: &str) -> bool {
+        matches!(
+            word.to_lowercase().as_str(),
+            "item" | "items" | "entry" | "entries" | "anything"
+        )
+    }
+    /** the type name for a word such as "bookmarks" */
+    fn kind_named(word: &str) -> Option<String> {
+        let word = word.to_lowercase();
+        let singular = word.trim_end_matches('s');
+        crate::kinds::kinds()
+            .iter()
+            .find(|k| {
+                [word.as_str(), singular]
+                    .iter()
+                    .any(|w| k.name.to_lowercase() == *w || k.label == *w || k.aliases.contains(w))
+            })
+            .map(|k| k.name.to_owned())
+    }
+    /** whether the policy applies to an item that has not yet been dealt with */
+    pub fn applies(
+        &self,
+        type_name: &str,
+        tags: &[String],
+        created: NaiveDateTime,
+        modified: NaiveDateTime,
+        now: NaiveDateTime,
+    ) -> bool {
+        if self.kind.as_ref().map_or(false, |k| k != type_name)
+            || self.tag.as_ref().map_or(false, |t| !tags.contains(t))
+        {
+            return false;
+        }
+        let age = Duration::days(self.days);
+        match self.condition {
+            Condition::OlderThan => now - created >= age,
+            Condition::UnchangedFor => now - modified >= age,
+            Condition::UntaggedFor => tags.is_empty() && now - modified >= age,
+        }
+    }
+    /** whether the tags of the items are needed to see if it applies */
+    fn needs_tags(&self) -> bool {
+        self.tag.is_some() || self.condition == Condition::UntaggedFor
+    }
+}
+
+/** parse the policies, one per line (blank lines and lines starting with `//` are passed over) */
+pub fn parse_policies(text: &str) -> FLResult<Vec<RetentionPolicy>> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("//"))
+        .map(RetentionPolicy::parse)
+        .collect()
+}
+
+/** an item that a policy applies to */
+#[derive(Debug, Clone)]
+pub struct Due {
+    pub ident: Ident,
+    pub type_name: String,
+    pub descr: String,
+    pub action: RetentionAction,
+    /** the policy, as written */
+    pub policy: String,
+}
+/** the items that the policies apply to (that have not already been dealt with) */
+pub fn find_due(world: &mut World, policies: &[RetentionPolicy]) -> FLResult<Vec<Due>> {
+    let mut due = vec![];
+    if policies.is_empty() {
+        return Ok(due);
+    }
+    let now = chrono::Utc::now().naive_utc();
+    for entry in world.search_all()?.entries {
+        let (base, values) = world.get_item_parts(&entry.link.ident)?;
+        if crate::private::is_sealed(&values) || base.classify == "archived" {
+            continue;
+        }
+        let mut tags = None;
+        for policy in policies {
+            if base.classify == policy.action.classify() {
+                continue;
+            }
+            if tags.is_none() && policy.needs_tags() {
+                let item_ref = ItemLink::from(entry.link.clone()).resolve_link(world)?;
+                let item_tags = item_ref.borrow().tags();
+                tags = Some(item_tags);
+            }
+            if policy.applies(
+                &base.type_name,
+                tags.as_deref().unwrap_or(&[]),
+                base.when_created,
+                base.when_modified,
+                now,
+            ) {
+                due.push(Due {
+                    ident: base.ident.clone(),
+                    type_name: base.type_name.clone(),
+                    descr: entry.descr.clone(),
+                    action: policy.action,
+                    policy: policy.text.clone(),
+                });
+                break;
+            }
+        }
+    }
+    trace(&format!(
+        "{} items due under {} policies",
+        due.len(),
+        policies.len()
+    ));
+    Ok(due)
+}
+
+/** deal with the items due that were previewed (those with the idents), in a single commit */
+fn apply(world: &mut World, idents: &[Ident]) -> FLResult<usize> {
+    let policies = world.retention_policies()?;
+    let due: Vec<Due> = find_due(world, &policies)?
+        .into_iter()
+        .filter(|d| idents.contains(&d.ident))
+        .collect();
+    world.begin_batch();
+    let res = apply_due(world, &due);
+    world.end_batch()?;
+    res?;
+    Ok(due.len())
+}
+fn apply_due(world: &mut World, due: &[Due]) -> NullResult {
+    for d in due {
+        trace(&format!("{} {} ({})", d.action.verb(), &d.ident, &d.policy));
+        let item_ref = world.get_item(d.ident.clone(), d.type_name.clone())?;
+        let mut item = item_ref.borrow_mut();
+        item.set_classify(d.action.classify());
+        world.persist_change_as(item.deref_mut(), d.action.verb())?;
+    }
+    Ok(())
+}
+
+/** an item due, as shown in the preview */
+struct DueForShow {
+    ident: Ident,
+    descr: String,
+    action: String,
+    policy: String,
+}
+/** template data for the preview of what the policies would do */
+#[derive(Template)]
+#[template(path = "retention.html", print = "none")]
+struct RetentionTemplate {
+    policies: Vec<String>,
+    due: Vec<DueForShow>,
+    /** the action for confirming, as JSON */
+    apply_action: String,
+    message: String,
+}
+/** show what the policies would do, with a button to do it */
+pub fn show_preview(world: &mut World, message: &str) -> fanling_interface::ResponseResult {
+    let policies = world.retention_policies()?;
+    let due = find_due(world, &policies)?;
+    let apply_action = serde_json::to_string(&crate::Action::ApplyRetention(
+        due.iter().map(|d| d.ident.clone()).collect(),
+    ))?;
+    let i18n = world.i18n();
+    let t = RetentionTemplate {
+        policies: policies.iter().map(|p| p.text.clone()).collect(),
+        due: due
+            .iter()
+            .map(|d| DueForShow {
+                ident: d.ident.clone(),
+                descr: d.descr.clone(),
+                action: i18n.tr(d.action.verb()),
+                policy: d.policy.clone(),
+            })
+            .collect(),
+        apply_action,
+        message: i18n.tr(message),
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    {
+        resp.set_test_data("due", &format!("{}", due.len()));
+        resp.set_test_data("message", message);
+    }
+    Ok(resp)
+}
+/** deal with the items previewed, then show what is still due */
+pub fn apply_and_show(world: &mut World, idents: &[Ident]) -> fanling_interface::ResponseResult {
+    let done = apply(world, idents)?;
+    show_preview(
+        world,
+        if done == 1 {
+            "1 item has been archived or flagged."
+        } else if done == 0 {
+            "Nothing has been changed."
+        } else {
+            "The items have been archived or flagged."
+        },
+    )
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "retention {}",
+        Colour::Fixed(3).on(Colour::Fixed(236)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn parsing() -> NullResult {
+        let policies = parse_policies(
+            "#journal older than 2 years: archive\n\n// old links\nBookmarks untagged for 90 days: Flag",
+        )?;
+        assert_eq!(2, policies.len());
+        assert_eq!(None, policies[0].kind);
+        assert_eq!(Some("journal".to_owned()), policies[0].tag);
+        assert_eq!(730, policies[0].days);
+        assert_eq!(Some("Bookmark".to_owned()), policies[1].kind);
+        assert_eq!(Condition::UntaggedFor, policies[1].condition);
+        assert_eq!(RetentionAction::Flag, policies[1].action);
+        assert!(RetentionPolicy::parse("bookmarks older than 2 years: delete").is_err());
+        assert!(RetentionPolicy::parse("widgets older than 2 years: archive").is_err());
+        assert!(RetentionPolicy::parse("archive old things").is_err());
+        Ok(())
+    }
+    #[test]
+    fn applying() -> NullResult {
+        let day = |d: u32| chrono::NaiveDate::from_ymd(2020, 1, d).and_hms(12, 0, 0);
+        let untagged = RetentionPolicy::parse("bookmarks untagged for 1 week: flag")?;
+        assert!(untagged.applies("Bookmark", &[], day(1), day(2), day(9)));
+        assert!(!untagged.applies("Bookmark", &[], day(1), day(3), day(9)));
+        assert!(!untagged.applies("Bookmark", &["rust".to_owned()], day(1), day(2), day(9)));
+        assert!(!untagged.applies("Task", &[], day(1), day(2), day(9)));
+        let journal = RetentionPolicy::parse("entries #journal older than 5 days: archive")?;
+        assert!(journal.applies("Simple", &["journal".to_owned()], day(1), day(8), day(6)));
+        assert!(!journal.applies("Simple", &[], day(1), day(8), day(6)));
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! checks the result of an operation that changes items without the
+user looking at each of them: merging the changes fetched from the
+remote (with conflicts resolved automatically), or migrating the items
+to the current version.
+
+Each item that the operation added or changed must still be readable
+as an item of its kind: if any is not (say it can not be parsed, or a
+field that is needed is missing), the repository is rolled back to the
+commit from before the operation, rather than being left half merged,
+and a report says which items were wrong and why. Private items that
+are sealed can not be checked, so they are passed over. */
+use crate::item::Ident;
+use crate::shared::FLResult;
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use std::ops::Deref;
+
+/** an item that was not valid after an operation */
+#[derive(Debug, Clone)]
+pub struct Invalid {
+    pub ident: Ident,
+    /** what was wrong with it */
+    pub reason: String,
+}
+/** what was wrong with the result of an operation that has been rolled back */
+#[derive(Debug, Clone)]
+pub struct Report {
+    /** what the operation was, such as "merge" */
+    pub operation: String,
+    /** the commit the repository was rolled back to */
+    pub commit: String,
+    pub invalid: Vec<Invalid>,
+}
+
+/** what is wrong with the item as stored, if anything */
+fn check_item(world: &mut World, ident: &Ident) -> Option<String> {
+    let (base, values) = match world.get_item_parts(ident) {
+        Ok(parts) => parts,
+        Err(e) => return Some(format!("It can not be read: {}", e)),
+    };
+    if base.ident != *ident {
+        return Some(format!("It is stored with the ident '{}'.", base.ident));
+    }
+    if crate::private::is_sealed(&values) {
+        return None;
+    }
+    let item_type = match world.get_item_type(base.type_name.clone()) {
+        Ok(item_type) => item_type,
+        Err(e) => return Some(format!("Its type can not be found: {}", e)),
+    };
+    let result = item_type.deref().borrow().from_yaml(&values, world);
+    result
+        .err()
+        .map(|e| format!("It is not a valid {}: {}", &base.type_name, e))
+}
+
+/** check the items changed since the commit from before an operation,
+and if any is not valid, roll back to that commit, returning what was
+wrong (`before` is `None` if there were no commits before it, so there
+is nothing to roll back to) */
+pub fn check_since(
+    world: &mut World,
+    operation: &str,
+    before: &Option<String>,
+) -> FLResult<Option<Report>> {
+    let before = match before {
+        Some(before) => before,
+        None => return Ok(None),
+    };
+    if world.head_id()?.as_ref() == Some(before) {
+        return Ok(None);
+    }
+    let mut invalid = vec![];
+    for ident in world.changed_items(before)? {
+        if let Some(reason) = check_item(world, &ident) {
+            trace(&format!(
+                "after {}, {} is not valid: {}",
+                operation, &ident, &reason
+            ));
+            invalid.push(Invalid { ident, reason });
+        }
+    }
+    if invalid.is_empty() {
+        return Ok(None);
+    }
+    world.roll_back_to(before)?;
+    trace(&format!(
+        "{} rolled back to {} ({} items not valid)",
+        operation,
+        before,
+        invalid.len()
+    ));
+    Ok(Some(Report {
+        operation: operation.to_owned(),
+        commit: before.clone(),
+        invalid,
+    }))
+}
+
+/** template data for a report of an operation that was rolled back */
+#[derive(Template)]
+#[template(path = "rollback.html", print = "none")]
+struct RollbackTemplate<'a> {
+    report: &'a Report,
+    /** whether the operation was a merge (so it will be tried again at the next pull) */
+    is_merge: bool,
+}
+/** a report of an operation that was rolled back, as HTML */
+pub fn render(report: &Report) -> FLResult<String> {
+    let t = RollbackTemplate {
+        report,
+        is_merge: report.operation == "merge",
+    };
+    Ok(t.render()?)
+}
+/** show a report of an operation that was rolled back */
+pub fn show(report: &Report) -> fanling_interface::ResponseResult {
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &render(report)?);
+    resp.add_notification(&format!(
+        "The {} was rolled back, as {} items were not valid.",
+        &report.operation,
+        report.invalid.len()
+    ));
+    #[cfg(test)]
+    {
+        resp.set_test_data("invalid", &format!("{}", report.invalid.len()));
+        resp.set_test_data("commit", &report.commit);
+    }
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "rollback {}",
+        Colour::Fixed(15).on(Colour::Fixed(88)).paint(m)
+    );
+}
@@ -2,7 +2,13 @@
 License, v. 2.0. If a copy of the MPL was not distributed with this
 file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-/*! define searches for [`Item`]s */
+/*! define searches for [`Item`]s.
+
+The search database is kept between runs as an index of the items,
+so that lists can be shown without reading every item from the
+repository (an item is only read when it is needed). The database
+notes the commit that it is up to date with, and is rebuilt when it
+is opened if the repository has been changed without it. */
 use crate::fanling_trace;
 use crate::item::{Item, ItemListEntryList, ItemRef, SpecialKind};
 mod models;
@@ -20,7 +26,7 @@ embed_migrations!("../migrations");
 /** a  search for [`Item`]s */
 pub struct Search {
     connect: SqliteConnection,
-    //    database_path: String,
+    database_path: String,
 }
 impl Search {
     /** create a new [Search]  */
@@ -38,6 +44,7 @@ impl Search {
         ));
         let conn = Self {
             connect: SqliteConnection::establish(&path)?,
+            database_path: path.clone(),
         };
         trace(&"have sqlite connection, running migrations...".to_string());
         embedded_migrations::run_with_output(&conn.connect, &mut std::io::stdout())?;
@@ -49,6 +56,13 @@ impl Search {
         ));
         Ok((conn, !exists))
     }
+    /** record the hash of the database file, to check it when it is next opened */
+    pub fn record_hash(&self) -> NullResult {
+        if self.database_path == crate::crypt::IN_MEMORY_DATABASE {
+            return Ok(());
+        }
+        crate::integrity::record(Path::new(&self.database_path))
+    }
     /** delete all items from the search database */
     pub fn clear(&mut self) -> NullResult {
         trace("clearing database...");
@@ -88,6 +102,29 @@ impl Search {
         )?)
         // TODO: create in other tables
     }
+    /** add an item that has not been loaded (see [`crate::sparse`]) as findable by search */
+    pub fn add_stub(&mut self, ident: &str, type_name: &str, name: &str) -> NullResult {
+        trace(&format!("adding stub '{}' to search", ident));
+        Ok(models::create_item(
+            &self.connect,
+            &models::NewItem {
+                ident,
+                type_name,
+                name,
+                open: false,
+                parent: None,
+                sort: name,
+                classify: crate::sparse::STUB_CLASSIFY.to_owned(),
+                special: 0,
+                targeted: false,
+            },
+        )?)
+    }
+    /** whether the item is in the search only as a stub, not having been loaded */
+    pub fn is_stub(&self, ident: &str) -> FLResult<bool> {
+        Ok(models::classify_of(&self.connect, ident)?.as_deref()
+            == Some(crate::sparse::STUB_CLASSIFY))
+    }
     /** remove an [`Item`] from the set of searchable values */
     pub fn delete_item(&mut self, item: ItemRef) -> NullResult {
         let itemx = item.borrow();
@@ -156,6 +193,24 @@ impl Search {
         };
         Ok(iter)
     }
+    /** search for all items of a type */
+    pub fn search_type(&self, type_name: &str) -> FLResult<ItemListEntryList> {
+        let results = models::search_type(&self.connect, type_name)?;
+        let iter = ItemListEntryList {
+            entries: results.entries,
+            final_adjust_level: "".to_owned(),
+        };
+        Ok(iter)
+    }
+    /** search for children, open or not */
+    pub fn search_children(&self, parent_ident: &str) -> FLResult<ItemListEntryList> {
+        let results = models::search_children(&self.connect, parent_ident)?;
+        let iter = ItemListEntryList {
+            entries: results.entries,
+            final_adjust_level: "".to_owned(),
+        };
+        Ok(iter)
+    }
     /** search for children with open status */
     pub fn search_open_children(&self, parent_ident: &str) -> FLResult<ItemListEntryList> {
         let results = models::search_open_children(&self.connect, parent_ident)?;
@@ -183,6 +238,14 @@ impl Search {
     pub fn update_last_ident(&mut self, last_ident: i32) -> NullResult {
         models::global_row::update_last_ident(&mut self.connect, last_ident)
     }
+    /** the id of the commit that the database was last up to date with ("" if not known) */
+    pub fn head(&self) -> FLResult<String> {
+        models::global_row::read_head(&self.connect)
+    }
+    /** note the commit that the database is up to date with */
+    pub fn set_head(&mut self, head: &str) -> NullResult {
+        models::global_row::update_head(&mut self.connect, head)
+    }
     // /** search relative to a specific [`Item`] */
     // pub fn search_relative(&self, _item: ItemRef) -> ItemListEntryList {
     //     unimplemented!(); //
@@ -195,7 +258,7 @@ impl Drop for Search {
 }
 
 /** options for a [Search]. */
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SearchOptions {
     pub database_path: String,
 }
@@ -58,6 +58,14 @@ pub fn create_item<'a>(conn: &SqliteConnection, new_item: &NewItem) -> NullResul
         .execute(conn)
         .map(|_n| ())?)
 }
+/** the classification of an item in the database, if it is there */
+pub fn classify_of(conn: &SqliteConnection, ident: &str) -> FLResult<Option<String>> {
+    Ok(item::dsl::item
+        .find(ident)
+        .select(item::columns::classify)
+        .first::<String>(conn)
+        .optional()?)
+}
 /** delete all items in the database */
 pub fn delete_all(conn: &SqliteConnection) -> NullResult {
     let num_deleted = diesel::delete(item::table).execute(conn)?;
@@ -107,6 +115,26 @@ pub fn search_special(conn: &SqliteConnection, sk: SpecialKind) -> FLResult<Item
         results.into_iter().map(DslItem::into).collect(),
     ))
 }
+/** find all items of a type */
+pub fn search_type(conn: &SqliteConnection, type_name: &str) -> FLResult<ItemListEntryList> {
+    let results = item::dsl::item
+        .filter(item::columns::type_name.eq(type_name))
+        .order(item::columns::name)
+        .load::<DslItem>(conn)?;
+    Ok(ItemListEntryList::from_vec(
+        results.into_iter().map(DslItem::into).collect(),
+    ))
+}
+/** the children of an item, open or not */
+pub fn search_children(conn: &SqliteConnection, parent_ident: &str) -> FLResult<ItemListEntryList> {
+    let results = item::dsl::item
+        .filter(item::columns::parent.eq(parent_ident))
+        .order(item::columns::name)
+        .load::<DslItem>(conn)?;
+    Ok(ItemListEntryList::from_vec(
+        results.into_iter().map(DslItem::into).collect(),
+    ))
+}
 /** */
 pub fn search_open_children(
     conn: &SqliteConnection,
@@ -175,6 +203,7 @@ pub mod global_row {
         _id: i32,
         ident_prefix: String,
         last_ident: i32,
+        head: String,
     }
     /** find the global row */
     pub fn read_global(conn: &SqliteConnection) -> FLResult<(i32, String)> {
@@ -188,6 +217,17 @@ pub mod global_row {
             .execute(conn)?;
         Ok(())
     }
+    /** the id of the commit that the database was last up to date with */
+    pub fn read_head(conn: &SqliteConnection) -> FLResult<String> {
+        Ok(global::dsl::global.get_result::<DslGlobal>(conn)?.head)
+    }
+    /** note the commit that the database is up to date with */
+    pub fn update_head(conn: &mut SqliteConnection, head: &str) -> NullResult {
+        diesel::update(global::dsl::global)
+            .set(global::dsl::head.eq(head))
+            .execute(conn)?;
+        Ok(())
+    }
 }
 // helper
 
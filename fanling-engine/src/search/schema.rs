@@ -3,6 +3,7 @@ table! {
         id -> Integer,
         ident_prefix -> Text,
         last_ident -> Integer,
+        head -> Text,
     }
 }
 
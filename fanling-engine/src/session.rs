@@ -0,0 +1,266 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! view sessions, so that several views (such as windows on a PC, or
+the two halves of a split screen on Android) can be used at once, each
+with its own current item, its own history of the items shown in it
+(for going back and forward, as in a web browser) and its own
+clipboard.
+
+Each request can give the id of its session (as `s`); requests without
+one are in the default session. A session starts when a request first
+uses it, and is forgotten with [`crate::Action::CloseSession`].
+
+The items shown recently in any session are also kept, the latest
+first, for the recent page. If the options ask for it, they are kept
+in a file beside the search database when the engine shuts down, and
+read again when it starts (but not if the repository is encrypted). */
+use crate::item::Ident;
+use crate::shared::{FLResult, NullResult};
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/** the most items kept in the stack of a view */
+const MAX_DEPTH: usize = 50;
+/** the most items kept as recently shown */
+const MAX_RECENT: usize = 30;
+/** the name of the file of the recent items, beside the search database */
+pub const HISTORY_FILE: &str = "history.json";
+
+/** the items shown in a view, the current one last, with those gone back from */
+#[derive(Debug, Default)]
+struct View {
+    stack: Vec<Ident>,
+    /** the items that can be gone forward to, the next one last */
+    forward: Vec<Ident>,
+    /** what has been cut or copied in the view (see [`crate::clipboard`]) */
+    clip: Option<crate::clipboard::Clip>,
+}
+/** the views, by session id */
+#[derive(Debug, Default)]
+pub struct Sessions {
+    views: HashMap<String, View>,
+    /** the items shown recently in any session, the latest first */
+    recent: Vec<Ident>,
+}
+impl Sessions {
+    /** note that an item has been shown in the session (going
+    forward if it is the next item, otherwise forgetting the items that
+    could be gone forward to) */
+    pub fn shown(&mut self, session: &str, ident: &str) {
+        self.recent.retain(|i| i != ident);
+        self.recent.insert(0, ident.to_owned());
+        self.recent.truncate(MAX_RECENT);
+        let view = self.views.entry(session.to_owned()).or_default();
+        if view.stack.last().map(|i| i.as_str()) == Some(ident) {
+            return;
+        }
+        if view.forward.last().map(|i| i.as_str()) == Some(ident) {
+            let _ = view.forward.pop();
+        } else {
+            view.forward.clear();
+        }
+        view.stack.push(ident.to_owned());
+        if view.stack.len() > MAX_DEPTH {
+            let _ = view.stack.remove(0);
+        }
+    }
+    /** the items shown recently in any session, the latest first */
+    pub fn recent(&self) -> &[Ident] {
+        &self.recent
+    }
+    /** the item currently shown in the session, if any */
+    pub fn current(&self, session: &str) -> Option<&Ident> {
+        self.views.get(session).and_then(|v| v.stack.last())
+    }
+    /** go back from the current item of the session (so that it can
+    be gone forward to), returning the one shown before it */
+    fn pop(&mut self, session: &str) -> Option<Ident> {
+        let view = self.views.get_mut(session)?;
+        if let Some(ident) = view.stack.pop() {
+            view.forward.push(ident);
+        }
+        view.stack.last().cloned()
+    }
+    /** the item to go forward to in the session, if any (it is
+    forgotten if it has gone) */
+    fn next(&mut self, session: &str) -> Option<Ident> {
+        self.views.get(session)?.forward.last().cloned()
+    }
+    /** forget the item to go forward to in the session */
+    fn forget_next(&mut self, session: &str) {
+        if let Some(view) = self.views.get_mut(session) {
+            let _ = view.forward.pop();
+        }
+    }
+    /** what is on the clipboard of the session, if anything */
+    pub fn clip(&self, session: &str) -> Option<&crate::clipboard::Clip> {
+        self.views.get(session).and_then(|v| v.clip.as_ref())
+    }
+    /** set (or clear) the clipboard of the session */
+    pub fn set_clip(&mut self, session: &str, clip: Option<crate::clipboard::Clip>) {
+        self.views.entry(session.to_owned()).or_default().clip = clip;
+    }
+    /** forget the session (and its clipboard) */
+    pub fn close(&mut self, session: &str) {
+        trace(&format!("closing session '{}'", session));
+        let _ = self.views.remove(session);
+    }
+}
+
+/** show the item that was shown in the session before the current one */
+pub fn back(world: &mut World, session: &str) -> fanling_interface::ResponseResult {
+    /* items that have since gone (as after a pull) are passed over */
+    while let Some(ident) = world.sessions().pop(session) {
+        if !world.has_item(&ident)? {
+            continue;
+        }
+        trace(&format!("back to {} in session '{}'", ident, session));
+        let item_ref = world.get_item(ident, "Simple".to_owned())?;
+        return crate::prefetch::show(world, &item_ref);
+    }
+    let mut resp = fanling_interface::Response::new();
+    resp.show_toast("There is nothing to go back to.");
+    Ok(resp)
+}
+/** show the item that was gone back from in the session */
+pub fn forward(world: &mut World, session: &str) -> fanling_interface::ResponseResult {
+    while let Some(ident) = world.sessions().next(session) {
+        if !world.has_item(&ident)? {
+            world.sessions().forget_next(session);
+            continue;
+        }
+        trace(&format!("forward to {} in session '{}'", ident, session));
+        let item_ref = world.get_item(ident.clone(), "Simple".to_owned())?;
+        let resp = crate::prefetch::show(world, &item_ref)?;
+        world.sessions().shown(session, &ident);
+        return Ok(resp);
+    }
+    let mut resp = fanling_interface::Response::new();
+    resp.show_toast("There is nothing to go forward to.");
+    Ok(resp)
+}
+/** an item on the recent page */
+struct RecentEntry {
+    ident: Ident,
+    descr: String,
+}
+/** template data for the recent page */
+#[derive(Template)]
+#[template(path = "recent.html", print = "none")]
+struct RecentTemplate {
+    entries: Vec<RecentEntry>,
+}
+/** show the items shown recently, the latest first */
+pub fn show_recent(world: &mut World) -> fanling_interface::ResponseResult {
+    let mut entries = vec![];
+    for ident in world.sessions().recent().to_vec() {
+        if !world.has_item(&ident)? {
+            continue;
+        }
+        let item_ref = world.get_item(ident.clone(), "Simple".to_owned())?;
+        let descr = item_ref.borrow().description_for_list();
+        entries.push(RecentEntry { ident, descr });
+    }
+    let t = RecentTemplate { entries };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    resp.set_test_data(
+        "recent",
+        &t.entries
+            .iter()
+            .map(|e| e.ident.clone())
+            .collect::<Vec<String>>()
+            .join(","),
+    );
+    Ok(resp)
+}
+
+/** the path of the file of the recent items for the search database at `database_path` */
+pub fn history_path(database_path: &str) -> PathBuf {
+    Path::new(database_path)
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(HISTORY_FILE)
+}
+/** keep the recent items in the file */
+pub fn save_history(sessions: &Sessions, path: &Path) -> NullResult {
+    std::fs::write(path, serde_json::to_vec(&sessions.recent)?)?;
+    trace(&format!("saved {} recent items", sessions.recent.len()));
+    Ok(())
+}
+/** the recent items kept in the file (none if there is no file) */
+pub fn load_history(path: &Path) -> FLResult<Sessions> {
+    let mut sessions = Sessions::default();
+    if path.exists() {
+        sessions.recent = serde_json::from_slice(&std::fs::read(path)?)?;
+        sessions.recent.truncate(MAX_RECENT);
+    }
+    Ok(sessions)
+}
+
+/** show the item currently shown in the session (as when its page is
+reloaded), or the start page if there is none */
+pub fn show_current(world: &mut World, session: &str) -> fanling_interface::ResponseResult {
+    let current = world.sessions().current(session).cloned();
+    match current {
+        Some(ident) if world.has_item(&ident)? => {
+            let item_ref = world.get_item(ident, "Simple".to_owned())?;
+            crate::prefetch::show(world, &item_ref)
+        }
+        _ => world.do_world_action(&crate::BasicRequest {
+            action: crate::Action::Start,
+            ..crate::BasicRequest::default()
+        }),
+    }
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "session {}",
+        Colour::Fixed(3).on(Colour::Fixed(236)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn stacks() {
+        let mut sessions = Sessions::default();
+        sessions.shown("", "a");
+        sessions.shown("", "b");
+        sessions.shown("", "b");
+        sessions.shown("w2", "c");
+        assert_eq!(Some(&"b".to_owned()), sessions.current(""));
+        assert_eq!(Some(&"c".to_owned()), sessions.current("w2"));
+        assert_eq!(Some("a".to_owned()), sessions.pop(""));
+        assert_eq!(Some("b".to_owned()), sessions.next(""));
+        sessions.shown("", "b");
+        assert_eq!(None, sessions.next(""));
+        assert_eq!(Some("a".to_owned()), sessions.pop(""));
+        /* showing another item forgets the one gone back from */
+        sessions.shown("", "d");
+        assert_eq!(None, sessions.next(""));
+        assert_eq!(Some("a".to_owned()), sessions.pop(""));
+        assert_eq!(None, sessions.pop(""));
+        assert_eq!(None, sessions.pop("w3"));
+        sessions.close("w2");
+        assert_eq!(None, sessions.current("w2"));
+        assert_eq!(
+            vec![
+                "d".to_owned(),
+                "b".to_owned(),
+                "c".to_owned(),
+                "a".to_owned()
+            ],
+            sessions.recent()
+        );
+    }
+}
@@ -0,0 +1,211 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! settings kept with each repository, such as how commits are described.
+
+The settings are saved in the repository's own Git configuration, so
+they override the values given in the engine options. */
+use crate::item::Item;
+use crate::shared::NullResult;
+use crate::world::World;
+use askama::Template;
+use std::collections::HashMap;
+
+/** configuration key for the commit message template */
+pub const TEMPLATE_KEY: &str = "fanling.committemplate";
+/** configuration key for whether to append the device name to commit messages */
+pub const APPEND_DEVICE_KEY: &str = "fanling.appenddevice";
+/** configuration key for whether to keep a generated README and index with the items */
+pub const GENERATE_INDEX_KEY: &str = "fanling.generateindex";
+/** configuration key for whether to warn before saving items that seem to contain secrets */
+pub const CHECK_SECRETS_KEY: &str = "fanling.checksecrets";
+/** configuration key for more patterns of secrets to warn about, one per line */
+pub const SECRET_PATTERNS_KEY: &str = "fanling.secretpatterns";
+/** configuration key for the retention policies, one per line (see [`crate::retention`]) */
+pub const RETENTION_KEY: &str = "fanling.retention";
+/** configuration key for whether emoji shortcodes are expanded in rendered text (see [`crate::typography`]) */
+pub const EMOJI_KEY: &str = "fanling.emoji";
+/** configuration key for whether rendered text has smart punctuation */
+pub const SMART_PUNCTUATION_KEY: &str = "fanling.smartpunctuation";
+/** the default commit message, which is what the stats page expects */
+pub const DEFAULT_TEMPLATE: &str = "{verb} {ident}";
+
+/** how the commits for changes to items are described */
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommitOptions {
+    /** template for the message for each change. `{verb}`, `{ident}`,
+    `{type}` and `{name}` are replaced by the values for the item. */
+    pub template: String,
+    /** name of this device */
+    pub device_name: String,
+    /** append the device name to each message (useful when debugging with several devices) */
+    pub append_device: bool,
+    /** keep a generated README and index with the items (see [`crate::companion`]) */
+    pub generate_index: bool,
+}
+impl Default for CommitOptions {
+    fn default() -> Self {
+        Self {
+            template: DEFAULT_TEMPLATE.to_owned(),
+            device_name: "".to_owned(),
+            append_device: false,
+            generate_index: false,
+        }
+    }
+}
+impl CommitOptions {
+    /** the commit message for a change to an item */
+    pub fn message(&self, verb: &str, item: &Item) -> String {
+        let template = if self.template.trim().is_empty() {
+            DEFAULT_TEMPLATE
+        } else {
+            &self.template
+        };
+        let message = template
+            .replace("{verb}", verb)
+            .replace("{ident}", &item.ident())
+            .replace("{type}", &item.type_name())
+            .replace("{name}", &item.descr_for_ident());
+        if self.append_device && !self.device_name.is_empty() {
+            format!("{} [{}]", message, self.device_name)
+        } else {
+            message
+        }
+    }
+}
+
+/** template data for the settings page */
+#[derive(Template)]
+#[template(path = "settings.html", print = "none")]
+struct SettingsTemplate {
+    name: String,
+    email: String,
+    commit: CommitOptions,
+    secrets: crate::secrets::SecretPolicy,
+    /** the retention policies, one per line */
+    retention: String,
+    typography: crate::typography::Typography,
+    message: String,
+}
+/** show the settings page, with a message (eg after saving) */
+pub fn show_settings(world: &mut World, message: &str) -> fanling_interface::ResponseResult {
+    let (name, email) = world.identity();
+    let t = SettingsTemplate {
+        name,
+        email,
+        commit: world.commit_options().clone(),
+        secrets: world.secret_policy()?,
+        retention: world.config_value(RETENTION_KEY)?.unwrap_or_default(),
+        typography: world.typography(),
+        message: world.i18n().tr(message),
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.clear_errors(vec![
+        "name-error".to_owned(),
+        "template-error".to_owned(),
+        "secret-patterns-error".to_owned(),
+        "retention-error".to_owned(),
+    ]);
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    resp.set_test_data("message", message);
+    Ok(resp)
+}
+/** save the settings from the settings page */
+pub fn save_settings(
+    world: &mut World,
+    vals: &HashMap<String, String>,
+) -> fanling_interface::ResponseResult {
+    let value = |key: &str| vals.get(key).map_or("", |v| v.trim());
+    let mut ar = crate::world::ActionResponse::new();
+    ar.assert(
+        !value("name").is_empty() && !value("email").is_empty(),
+        "name-error",
+        "Name and email must be non-blank.",
+    );
+    ar.assert(
+        !value("template").is_empty(),
+        "template-error",
+        "The commit message template must be non-blank.",
+    );
+    let secret_patterns = vals.get("secret_patterns").map_or("", |v| v.trim());
+    if let Err(e) = crate::secrets::SecretPolicy::new(true, secret_patterns) {
+        ar.add_error(
+            "secret-patterns-error",
+            &format!("Invalid pattern for secrets: {}", e),
+        );
+    }
+    let retention = vals.get("retention").map_or("", |v| v.trim());
+    if let Err(e) = crate::retention::parse_policies(retention) {
+        ar.add_error(
+            "retention-error",
+            &format!("Invalid retention policy: {}", e),
+        );
+    }
+    if !ar.ok() {
+        return ar.translated(&world.i18n()).to_response();
+    }
+    world.set_identity(value("name"), value("email"))?;
+    world.set_config_value(
+        CHECK_SECRETS_KEY,
+        if value("check_secrets") == "true" {
+            "true"
+        } else {
+            "false"
+        },
+    )?;
+    world.set_config_value(SECRET_PATTERNS_KEY, secret_patterns)?;
+    world.set_config_value(RETENTION_KEY, retention)?;
+    save_typography(
+        world,
+        crate::typography::Typography {
+            emoji: value("emoji") == "true",
+            smart_punctuation: value("smart_punctuation") == "true",
+        },
+    )?;
+    let commit = CommitOptions {
+        template: value("template").to_owned(),
+        append_device: value("append_device") == "true",
+        generate_index: value("generate_index") == "true",
+        ..world.commit_options().clone()
+    };
+    save_commit_options(world, commit)?;
+    show_settings(world, "Settings saved.")
+}
+/** use and save new typography for rendered text */
+fn save_typography(world: &mut World, typography: crate::typography::Typography) -> NullResult {
+    world.set_config_value(EMOJI_KEY, if typography.emoji { "true" } else { "false" })?;
+    world.set_config_value(
+        SMART_PUNCTUATION_KEY,
+        if typography.smart_punctuation {
+            "true"
+        } else {
+            "false"
+        },
+    )?;
+    world.set_typography(typography);
+    Ok(())
+}
+/** use and save new commit options */
+fn save_commit_options(world: &mut World, commit: CommitOptions) -> NullResult {
+    world.set_config_value(TEMPLATE_KEY, &commit.template)?;
+    world.set_config_value(
+        APPEND_DEVICE_KEY,
+        if commit.append_device {
+            "true"
+        } else {
+            "false"
+        },
+    )?;
+    world.set_config_value(
+        GENERATE_INDEX_KEY,
+        if commit.generate_index {
+            "true"
+        } else {
+            "false"
+        },
+    )?;
+    world.set_commit_options(commit);
+    Ok(())
+}
@@ -0,0 +1,120 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! items made from content shared with the app (as with "Share to
+Fanling" in a phone browser), which is a title, some text and a URL,
+any of which may be blank.
+
+Browsers often share the URL as the text, so a URL in the text is used
+if none is given. Content with a URL becomes a bookmark, with the page
+fetched for its title (if none is given) and a snapshot of its text
+(and the bookmark is still made if it cannot be fetched). Other
+content becomes a simple item. */
+use crate::item::ItemBaseForSerde;
+use crate::shared::FLResult;
+use crate::world::World;
+use ansi_term::Colour;
+use regex::Regex;
+use std::collections::HashMap;
+
+/** the URL to use and the text without it (if the text was just the URL) */
+fn split_url(text: &str, url: &str) -> FLResult<(String, String)> {
+    let text = text.trim();
+    if !url.trim().is_empty() {
+        return Ok((text.to_owned(), url.trim().to_owned()));
+    }
+    match Regex::new(r"https?://\S+")?.find(text) {
+        Some(m) if m.as_str() == text => Ok(("".to_owned(), text.to_owned())),
+        Some(m) => Ok((text.to_owned(), m.as_str().to_owned())),
+        None => Ok((text.to_owned(), "".to_owned())),
+    }
+}
+
+/** make an item from shared content, and show it: a bookmark if
+there is a URL, otherwise a simple item */
+pub fn capture_shared(
+    world: &mut World,
+    title: &str,
+    text: &str,
+    url: &str,
+) -> fanling_interface::ResponseResult {
+    let (text, url) = split_url(text, url)?;
+    let mut title = title.trim().to_owned();
+    let mut vals = HashMap::new();
+    let type_name = if url.is_empty() {
+        if title.is_empty() {
+            title = text.lines().next().unwrap_or("").trim().to_owned();
+        }
+        vals.insert("text".to_owned(), text);
+        "Simple"
+    } else {
+        /* the page is fetched for its title and a snapshot of its text */
+        match crate::fetch::fetch_page(&url) {
+            Ok(page) => {
+                if title.is_empty() {
+                    title = page.title;
+                }
+                vals.insert("snapshot".to_owned(), page.text);
+            }
+            Err(e) => trace(&format!("could not fetch {}: {:?}", url, e)),
+        }
+        vals.insert("url".to_owned(), url.clone());
+        vals.insert("notes".to_owned(), text);
+        "Bookmark"
+    };
+    if title.is_empty() && url.is_empty() {
+        let mut resp = fanling_interface::Response::new();
+        resp.show_toast("Nothing was shared.");
+        return Ok(resp);
+    }
+    vals.insert("name".to_owned(), title.clone());
+    trace(&format!("capturing {} '{}'", type_name, title));
+    let base = ItemBaseForSerde {
+        ident: "".to_owned(),
+        type_name: type_name.to_owned(),
+        ..ItemBaseForSerde::default()
+    };
+    world.begin_batch();
+    let made = world.make_item(type_name, &base, &vals);
+    world.end_batch()?;
+    let item_ref = made?;
+    let mut resp = item_ref.borrow_mut().for_show(world)?;
+    resp.show_toast(&format!("Saved '{}'", item_ref.borrow().description()));
+    #[cfg(test)]
+    {
+        resp.set_test_data("ident", &item_ref.borrow().ident());
+        resp.set_test_data("type", type_name);
+    }
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("share {}", Colour::Fixed(4).on(Colour::Fixed(229)).paint(m));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn urls() -> FLResult<()> {
+        assert_eq!(
+            ("".to_owned(), "https://example.com/a".to_owned()),
+            split_url(" https://example.com/a\n", "")?
+        );
+        assert_eq!(
+            (
+                "see https://example.com/a now".to_owned(),
+                "https://example.com/a".to_owned()
+            ),
+            split_url("see https://example.com/a now", "")?
+        );
+        assert_eq!(
+            ("text".to_owned(), "http://x.org".to_owned()),
+            split_url("text", " http://x.org")?
+        );
+        assert_eq!(("text".to_owned(), "".to_owned()), split_url("text", "")?);
+        Ok(())
+    }
+}
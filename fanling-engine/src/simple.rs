@@ -5,8 +5,9 @@ file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 /*! implements [`Simple`] items */
 /** a simple item, like a wiki page */
 use crate::item::{Item, ItemBase, ItemBaseForSerde, ItemData, NewBaseTemplate, ShowBaseTemplate};
-use crate::markdown;
+use crate::markdown::{self, Heading};
 use crate::shared::{merge_strings, FLResult, FanlingError, NullResult};
+use crate::transclude;
 use crate::world::{ActionResponse, World};
 use ansi_term::Colour;
 use askama::Template;
@@ -15,10 +16,14 @@ use serde_yaml::Value;
 use std::boxed::Box;
 use std::collections::HashMap;
 use std::fmt::Debug;
+use std::rc::Rc;
 
 //#[macro_use]
 use crate::fanling_error;
 
+/** how many headings a page needs for a table of contents to be shown */
+const MIN_CONTENTS: usize = 3;
+
 /** data for a simple item */
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Simple {
@@ -54,6 +59,7 @@ impl crate::item::ItemData for Simple {
             data: &self,
             base: NewBaseTemplate::from_base(base, is_for_update, world)?,
             broken_text,
+            rendered_text: transclude::render(world, &base.get_ident(), &self.text),
         };
         let mut resp = fanling_interface::Response::new();
         resp.clear_errors(vec!["name-error".to_owned()]);
@@ -65,21 +71,53 @@ impl crate::item::ItemData for Simple {
         trace(&format!("for edit {:?}", &resp));
         Ok(resp)
     }
+    /** a child can be added */
+    fn available_actions(&self, base: &ItemBase) -> Vec<crate::item::AvailableAction> {
+        vec![crate::item::AvailableAction::new(
+            base,
+            crate::Action::NewChild(base.get_ident()),
+            "Add simple child",
+            "➕",
+        )]
+    }
     fn for_show(
         &mut self,
         base: &mut ItemBase,
         world: &mut World,
     ) -> fanling_interface::ResponseResult {
+        let rendered_text = transclude::render(world, &base.get_ident(), &self.text);
+        let mut contents = markdown::contents(&rendered_text);
+        if contents.len() < MIN_CONTENTS {
+            contents.clear();
+        }
         let t = ShowSimpleTemplate {
             name: self.name.clone(),
-            rendered_text: markdown::render(&self.text),
-            base: ShowBaseTemplate::from_base(base, world)?,
+            rendered_text,
+            contents,
+            base: ShowBaseTemplate::from_base(base, world)?
+                .with_actions(base, self.available_actions(base))
+                .with_stats(self.stats(base, world)?),
         };
         let mut resp = fanling_interface::Response::new();
         resp.add_tag("content", &(t.render()?));
         trace(&format!("for show {:?}", &resp));
         Ok(resp)
     }
+    /** the page, with the names of its children */
+    fn for_print(&mut self, base: &mut ItemBase, world: &mut World) -> FLResult<String> {
+        let t = PrintSimpleTemplate {
+            name: self.name.clone(),
+            rendered_text: transclude::render(world, &base.get_ident(), &self.text),
+            children: base
+                .get_open_children(world)?
+                .entries
+                .into_iter()
+                .map(|child| child.descr)
+                .collect(),
+            i18n: world.i18n(),
+        };
+        Ok(t.render()?)
+    }
     fn to_yaml(&self, base: &crate::item::ItemBase) -> Result<Vec<u8>, FanlingError> {
         let for_serde = SimpleForSerde {
             base: crate::item::ItemBaseForSerde::from_base(base)?,
@@ -172,6 +210,8 @@ struct NewSimpleTemplate<'a> {
     data: &'a Simple,
     base: NewBaseTemplate,
     broken_text: String,
+    /** the initial preview */
+    rendered_text: String,
 }
 
 /** template data for showing a simple item */
@@ -180,8 +220,20 @@ struct NewSimpleTemplate<'a> {
 struct ShowSimpleTemplate {
     name: String,
     rendered_text: String,
+    /** the headings for the table of contents (none for a short page) */
+    contents: Vec<Heading>,
     base: ShowBaseTemplate,
 }
+/** template data for a simple item laid out for printing */
+#[derive(Template)]
+#[template(path = "print-simple.html", print = "none")]
+struct PrintSimpleTemplate {
+    name: String,
+    rendered_text: String,
+    /** the descriptions of the children */
+    children: Vec<String>,
+    i18n: Rc<crate::i18n::Translator>,
+}
 
 /** policy for the simple item type*/
 #[derive(Debug)]
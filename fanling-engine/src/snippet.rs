@@ -0,0 +1,255 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! implements [`Snippet`] items, pieces of code shown with syntax highlighting */
+use crate::item::{Item, ItemBase, ItemBaseForSerde, ItemData, NewBaseTemplate, ShowBaseTemplate};
+use crate::markdown;
+use crate::shared::{merge_strings, FLResult, FanlingError, NullResult};
+use crate::world::{ActionResponse, World};
+use ansi_term::Colour;
+use askama::Template;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::boxed::Box;
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** data for a snippet item */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snippet {
+    /** the name of the snippet */
+    name: String,
+    /** the language of the code, as a name (eg `Rust`) or file extension (eg `rs`) */
+    #[serde(default)]
+    language: String,
+    /** the code itself */
+    #[serde(default)]
+    code: String,
+}
+impl Snippet {
+    /** create a new [Snippet]  */
+    pub fn new() -> Self {
+        Self {
+            name: "".to_owned(),
+            language: "".to_owned(),
+            code: "".to_owned(),
+        }
+    }
+    fn set_from_yaml_basic(&mut self, yaml: &serde_yaml::Value) -> NullResult {
+        *self = serde_yaml::from_value(yaml.clone())?;
+        Ok(())
+    }
+}
+impl crate::item::ItemData for Snippet {
+    fn for_edit(
+        &mut self,
+        base: &mut ItemBase,
+        is_for_update: bool,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let nt = NewSnippetTemplate {
+            data: &self,
+            base: NewBaseTemplate::from_base(base, is_for_update, world)?,
+            broken_code: self.code.replace("\n", "&#10;"),
+        };
+        let mut resp = fanling_interface::Response::new();
+        resp.clear_errors(vec!["name-error".to_owned()]);
+        resp.add_tag("content", &(nt.render()?));
+        #[cfg(test)]
+        {
+            resp.set_test_data("ident", &base.get_ident());
+        }
+        trace(&format!("for edit {:?}", &resp));
+        Ok(resp)
+    }
+    /** a child can be added */
+    fn available_actions(&self, base: &ItemBase) -> Vec<crate::item::AvailableAction> {
+        vec![crate::item::AvailableAction::new(
+            base,
+            crate::Action::NewChild(base.get_ident()),
+            "Add snippet child",
+            "➕",
+        )]
+    }
+    fn for_show(
+        &mut self,
+        base: &mut ItemBase,
+        world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let t = ShowSnippetTemplate {
+            name: self.name.clone(),
+            language: self.language.clone(),
+            highlighted: markdown::highlight(&self.code, &self.language),
+            base: ShowBaseTemplate::from_base(base, world)?
+                .with_actions(base, self.available_actions(base))
+                .with_stats(self.stats(base, world)?),
+        };
+        let mut resp = fanling_interface::Response::new();
+        resp.add_tag("content", &(t.render()?));
+        trace(&format!("for show {:?}", &resp));
+        Ok(resp)
+    }
+    fn to_yaml(&self, base: &crate::item::ItemBase) -> Result<Vec<u8>, FanlingError> {
+        let for_serde = SnippetForSerde {
+            base: crate::item::ItemBaseForSerde::from_base(base)?,
+            data: self.clone(),
+        };
+        let yaml = serde_yaml::to_vec(&for_serde)?;
+        trace(&format!("yaml is {}", String::from_utf8_lossy(&yaml)));
+        Ok(yaml)
+    }
+    fn is_open(&self) -> bool {
+        true
+    }
+    fn is_ready(&mut self, _world: &mut World) -> FLResult<bool> {
+        Ok(false)
+    }
+    /** can be turned into an ident */
+    fn descr_for_ident(&self) -> String {
+        self.name.clone()
+    }
+    /** an English-language description */
+    fn description(&self) -> String {
+        if self.language.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{} ({})", self.name, self.language)
+        }
+    }
+    /** a description that can be used in a list */
+    fn description_for_list(&self) -> String {
+        self.description()
+    }
+    fn set_data(&mut self, vals: &HashMap<String, String>, _world: &mut World) -> NullResult {
+        match vals.get("name") {
+            Some(s) => self.name = s.to_string(),
+            _ => return Err(fanling_error!("no name")),
+        }
+        self.language = vals.get("language").map_or("".to_owned(), |s| s.trim().to_owned());
+        self.code = vals.get("code").map_or("".to_owned(), |s| s.to_string());
+        Ok(())
+    }
+    fn set_from_yaml(&mut self, yaml: &serde_yaml::Value, _world: &mut World) -> NullResult {
+        self.set_from_yaml_basic(yaml)
+    }
+    /** do action for snippet -- should never get called */
+    fn do_action(
+        &mut self,
+        _base: &mut ItemBase,
+        _action: crate::Action,
+        _world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        Err(fanling_error!("snippet do action called, should never happen").into())
+    }
+    /** copy from another item data */
+    fn fanling_clone(&self) -> FLResult<Box<dyn ItemData>> {
+        Ok(Box::new(self.clone()))
+    }
+    /** transitional to fix old data */
+    fn fix_data(
+        &self,
+        _yaml: &serde_yaml::Value,
+        _base: &mut ItemBase,
+        _world: &mut World,
+    ) -> NullResult {
+        Ok(())
+    }
+}
+impl Default for Snippet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+#[derive(Serialize, Deserialize)]
+struct SnippetForSerde {
+    #[serde(flatten)]
+    base: crate::item::ItemBaseForSerde,
+    #[serde(flatten)]
+    data: Snippet,
+}
+/** template data for creating a new snippet */
+#[derive(Template)]
+#[template(path = "new-snippet.html", print = "none")]
+struct NewSnippetTemplate<'a> {
+    data: &'a Snippet,
+    base: NewBaseTemplate,
+    broken_code: String,
+}
+
+/** template data for showing a snippet */
+#[derive(Template)]
+#[template(path = "show-snippet.html", print = "none")]
+struct ShowSnippetTemplate {
+    name: String,
+    language: String,
+    highlighted: String,
+    base: ShowBaseTemplate,
+}
+
+/** policy for the snippet item type*/
+#[derive(Debug)]
+pub struct SnippetTypePolicy {}
+impl SnippetTypePolicy {
+    pub fn new() -> Self {
+        Self {}
+    }
+    pub fn new_boxed() -> Box<Self> {
+        Box::new(Self::new())
+    }
+}
+impl crate::item::ItemTypePolicy for SnippetTypePolicy {
+    fn kind(&self) -> crate::item::ItemKind {
+        crate::item::ItemKind::Snippet
+    }
+    fn make_raw(&self, item_type: crate::item::ItemTypeRef) -> Item {
+        Item::new_with_data(item_type, Box::new(Snippet::new()))
+    }
+    fn resolve_conflict_both(
+        &self,
+        _world: &mut World,
+        _ancestor: &Value,
+        ours: &Value,
+        theirs: &Value,
+    ) -> FLResult<Box<dyn ItemData>> {
+        let mut os = Snippet::new();
+        os.set_from_yaml_basic(&ours)?;
+        let mut ts = Snippet::new();
+        ts.set_from_yaml_basic(&theirs)?;
+        os.name = merge_strings(&os.name, &ts.name);
+        os.language = merge_strings(&os.language, &ts.language);
+        os.code = merge_strings(&os.code, &ts.code);
+        Ok(Box::new(os))
+    }
+    fn check_valid(
+        &mut self,
+        _base: &ItemBaseForSerde,
+        vals: &HashMap<String, String>,
+        _world: &mut World,
+    ) -> ActionResponse {
+        let mut ar = ActionResponse::new();
+        ar.assert(
+            !vals["name"].is_empty(),
+            "name-error",
+            "Name must be non-blank.",
+        );
+        ar
+    }
+    /** get item data from serde value */
+    fn from_yaml(&self, values: &Value, world: &mut World) -> FLResult<Box<dyn ItemData>> {
+        let mut s = Snippet::default();
+        s.set_from_yaml(&values, world)?;
+        Ok(Box::new(s))
+    }
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "snippet {}",
+        Colour::Fixed(13).on(Colour::Fixed(233)).paint(m)
+    );
+}
@@ -0,0 +1,251 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! a soak test: a hidden mode of the engine that makes random
+activity against a test repository (creating, editing, closing and
+showing items, and syncing with the remote) for as long as it is asked
+to, such as several hours before a release.
+
+Every few actions, and at the end, it checks that:
+
+* no action has failed (an action that panics is reported as an error by [`crate::FanlingEngine`]);
+* every item in the repository can still be parsed;
+* the search database has exactly the items of the repository.
+
+It stops at the first failure, so that the repository can be looked
+at as it was. The random choices come from a seed, which is reported,
+so that a failing run can be repeated. Never point it at a repository
+with real items in it. */
+use crate::item::split_data_parts;
+use crate::shared::{FLResult, NullResult};
+//#[macro_use]
+use crate::fanling_error;
+use crate::FanlingEngine;
+use ansi_term::Colour;
+use fanling_interface::Engine;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+/** how many actions there are between checks */
+const CHECK_EVERY: usize = 25;
+/** words that the names and text of the items are made from */
+const WORDS: [&str; 12] = [
+    "apple",
+    "river",
+    "[[link]]",
+    "meeting",
+    "#tag",
+    "quick",
+    "brown",
+    "fox",
+    "ünïcode",
+    "draft",
+    "notes",
+    "*bold*",
+];
+
+/** what the soak test does */
+#[derive(Debug, Clone)]
+pub struct SoakOptions {
+    /** how long it runs for */
+    pub duration: Duration,
+    /** the most actions it makes (0 for no limit) */
+    pub max_actions: usize,
+    /** how many actions there are between syncs with the remote (0 for no syncing) */
+    pub sync_every: usize,
+    /** the seed for the random choices */
+    pub seed: u64,
+}
+/** what happened in a soak test */
+#[derive(Debug, Clone, Default)]
+pub struct SoakReport {
+    pub seed: u64,
+    pub actions: usize,
+    pub syncs: usize,
+    pub checks: usize,
+    /** what went wrong, if anything (the test stops at the first failure) */
+    pub failure: Option<String>,
+}
+
+/** the items made by the test, by type */
+#[derive(Default)]
+struct Made {
+    simple: Vec<String>,
+    tasks: Vec<String>,
+}
+
+/** some random words */
+fn words(rng: &mut StdRng, n: usize) -> String {
+    (0..n)
+        .map(|_| *WORDS.choose(rng).unwrap_or(&"word"))
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+/** a random action, as JSON, and whether it makes an item */
+fn random_action(rng: &mut StdRng, made: &Made) -> (serde_json::Value, bool) {
+    let simple = made.simple.choose(rng).cloned();
+    let task = made.tasks.choose(rng).cloned();
+    match (rng.gen_range(0, 10), simple, task) {
+        (0..=2, _, _) | (3..=4, None, _) => (
+            serde_json::json!({"t":"Simple","i":"","a":{"Create":[{"ident":"","type":"Simple"},
+                {"name":words(rng, 2),"text":words(rng, 8)}]}}),
+            true,
+        ),
+        (3..=4, Some(ident), _) => (
+            serde_json::json!({"t":"Simple","i":&ident,"a":{"Update":[{"ident":&ident,"type":"Simple"},
+                {"name":words(rng, 2),"text":words(rng, 8)}]}}),
+            false,
+        ),
+        (5, _, _) | (6, _, None) => (
+            serde_json::json!({"t":"Task","i":"","a":{"Create":[{"ident":"","type":"Task"},
+                {"name":words(rng, 3),"text":words(rng, 5),"priority":"10","context":"default_context",
+                 "deadline":"1970-01-01 00:00:00","show_after_date":"1970-01-01 00:00:00"}]}}),
+            true,
+        ),
+        (6, _, Some(ident)) => {
+            let action = if rng.gen() { "Close" } else { "Reopen" };
+            (serde_json::json!({"t":"Task","i":&ident,"a":action}), false)
+        }
+        (7, Some(ident), _) => (
+            serde_json::json!({"t":"Simple","i":&ident,"a":"Show"}),
+            false,
+        ),
+        _ => {
+            let list = ["Start", "ListReady", "ListOpen", "ListAll"]
+                .choose(rng)
+                .copied()
+                .unwrap_or("ListAll");
+            (serde_json::json!({"t":"","i":"","a":list}), false)
+        }
+    }
+}
+/** do an action, describing it if it fails */
+fn execute(engine: &mut FanlingEngine, action: &serde_json::Value) -> Result<(), String> {
+    match engine.execute(&action.to_string()) {
+        Ok(resp) if resp.is_error() => Err(format!("action {} failed: {:?}", action, resp)),
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("action {} gave an error: {:?}", action, e)),
+    }
+}
+/** the world of the engine */
+fn world(engine: &mut FanlingEngine) -> FLResult<&mut crate::world::World> {
+    engine
+        .world
+        .as_mut()
+        .ok_or_else(|| fanling_error!("the engine has no repository open"))
+}
+/** the idents of the items in the search database */
+fn searched(engine: &mut FanlingEngine) -> FLResult<BTreeSet<String>> {
+    Ok(world(engine)?
+        .search_all()?
+        .entries
+        .into_iter()
+        .map(|e| e.link.ident)
+        .collect())
+}
+/** note the items that have been made since the search database had the `known` items */
+fn note_made(
+    engine: &mut FanlingEngine,
+    known: &mut BTreeSet<String>,
+    made: &mut Made,
+) -> NullResult {
+    let now = searched(engine)?;
+    for ident in now.difference(known) {
+        let (base, _values) = world(engine)?.get_item_parts(ident)?;
+        match base.type_name.as_str() {
+            "Simple" => made.simple.push(ident.clone()),
+            "Task" => made.tasks.push(ident.clone()),
+            _ => {}
+        }
+    }
+    *known = now;
+    Ok(())
+}
+/** check that every item can be parsed and that the search database has the same items as the repository */
+fn check(engine: &mut FanlingEngine) -> FLResult<Option<String>> {
+    let mut stored = BTreeSet::new();
+    for yaml in world(engine)?.all_item_yaml()? {
+        match split_data_parts(yaml.as_bytes()) {
+            Ok((base, _values)) => {
+                stored.insert(base.ident);
+            }
+            Err(e) => return Ok(Some(format!("an item can not be parsed: {:?}", e))),
+        }
+    }
+    let searched = searched(engine)?;
+    if stored != searched {
+        return Ok(Some(format!(
+            "the search database does not match the repository: only stored {:?}, only searched {:?}",
+            stored.difference(&searched).collect::<Vec<_>>(),
+            searched.difference(&stored).collect::<Vec<_>>()
+        )));
+    }
+    Ok(None)
+}
+/** run a soak test on the repository of the options (see the module documentation) */
+pub fn run_soak(options: &crate::EngineOptions, soak: &SoakOptions) -> FLResult<SoakReport> {
+    trace(&format!("starting soak test {:?}", soak));
+    let mut report = SoakReport {
+        seed: soak.seed,
+        ..SoakReport::default()
+    };
+    let mut rng = StdRng::seed_from_u64(soak.seed);
+    let mut engine = FanlingEngine::new(options)?;
+    let mut made = Made::default();
+    let mut known = searched(&mut engine)?;
+    let start = Instant::now();
+    while start.elapsed() < soak.duration
+        && (soak.max_actions == 0 || report.actions < soak.max_actions)
+    {
+        let (action, makes) = random_action(&mut rng, &made);
+        report.actions += 1;
+        if let Err(failure) = execute(&mut engine, &action) {
+            report.failure = Some(failure);
+            break;
+        }
+        if makes {
+            note_made(&mut engine, &mut known, &mut made)?;
+        }
+        if soak.sync_every > 0 && report.actions % soak.sync_every == 0 {
+            report.syncs += 1;
+            let pull = serde_json::json!({"t":"","i":"","a":"Pull"});
+            let push = serde_json::json!({"t":"","i":"","a":{"Push":{"force":false}}});
+            if let Err(failure) =
+                execute(&mut engine, &pull).and_then(|_| execute(&mut engine, &push))
+            {
+                report.failure = Some(failure);
+                break;
+            }
+            note_made(&mut engine, &mut known, &mut made)?;
+        }
+        if report.actions % CHECK_EVERY == 0 {
+            report.checks += 1;
+            report.failure = check(&mut engine)?;
+            if report.failure.is_some() {
+                break;
+            }
+            trace(&format!(
+                "{} actions, {} syncs, all well",
+                report.actions, report.syncs
+            ));
+        }
+    }
+    if report.failure.is_none() {
+        report.checks += 1;
+        report.failure = check(&mut engine)?;
+    }
+    trace(&format!("soak test done: {:?}", &report));
+    Ok(report)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "soak {}",
+        Colour::Fixed(232).on(Colour::Fixed(214)).paint(m)
+    );
+}
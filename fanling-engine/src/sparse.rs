@@ -0,0 +1,59 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! selective sync, for a large repository on a small device (such as a phone).
+
+When the repository is cloned, only the namespaces given in the
+`sparse` option of the repository are checked out (see
+[`crate::namespace`]), and when all the items are loaded (after a clone,
+or when the search is rebuilt), only those in these namespaces and of
+the kinds given in the `sparse_kinds` option are loaded. Each of the
+other items is put in the search as a stub: it is found by search and
+is in the lists of all items (but not of open items), with its name
+marked as remote, and is loaded the first time it is needed, when its
+stub is replaced.
+
+The objects for all the items are still fetched (libgit2 cannot fetch
+only part of a repository), but reading, checking out and indexing them
+are what take the time. */
+use crate::world::World;
+use serde_yaml::Value;
+
+/** the classification of an item in the search that has not been loaded */
+pub const STUB_CLASSIFY: &str = "remote";
+/** marks the names of stubs */
+const STUB_MARK: &str = "☁";
+
+/** whether the item at the path, of the kind, is loaded with the others */
+pub fn is_wanted(world: &World, path: &str, type_name: &str) -> bool {
+    let kinds = world.sparse_kinds();
+    world.in_sparse_set(path) && (kinds.is_empty() || kinds.iter().any(|k| k == type_name))
+}
+/** the name of the stub for an item: its name, if it has one that can be read, or else the end of its ident */
+pub fn stub_name(ident: &str, values: &Value) -> String {
+    let name = values
+        .get("name")
+        .and_then(Value::as_str)
+        .filter(|n| !n.trim().is_empty() && !crate::private::is_sealed(values))
+        .unwrap_or_else(|| {
+            ident
+                .rsplit(crate::namespace::SEPARATOR)
+                .next()
+                .unwrap_or(ident)
+        });
+    format!("{} {}", STUB_MARK, name.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn stub_names() -> crate::shared::NullResult {
+        let values: Value = serde_yaml::from_str("name: ' road map '")?;
+        assert_eq!("☁ road map", stub_name("projects/roadmap-a1", &values));
+        let values: Value = serde_yaml::from_str("text: aaaa")?;
+        assert_eq!("☁ roadmap-a1", stub_name("projects/roadmap-a1", &values));
+        Ok(())
+    }
+}
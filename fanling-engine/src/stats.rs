@@ -0,0 +1,566 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! statistics about the items, such as an activity heatmap derived
+from the git history, counts of the items by kind and tag, and for
+each item the length of its text and when it was last changed.
+
+The charts (the heatmap here, the tasks completed each week from
+[`crate::report`] and the timelines of [`crate::timeline`]) can be
+exported as standalone SVG files, as PNG pictures (with the `png`
+feature) or as items with the SVG attached, for putting them in other
+documents. */
+use crate::format::FormatOptions;
+use crate::item::{Ident, ItemBaseForSerde, ItemLink, ItemRef};
+use crate::shared::FLResult;
+use crate::world::World;
+use askama::Template;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use taipo_git_control::CommitDescr;
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** the maximum number of commits to examine */
+pub const MAX_COMMITS: usize = 20_000;
+/** the number of weeks shown in the heatmap on the stats page */
+pub const HEATMAP_WEEKS: i64 = 53;
+/** size of a heatmap cell (pixels) */
+const CELL: i64 = 11;
+/** distance between the starts of adjacent heatmap cells (pixels) */
+const PITCH: i64 = 13;
+/** space for the weekday labels (pixels) */
+const LEFT_MARGIN: i64 = 28;
+/** space for the month labels (pixels) */
+const TOP_MARGIN: i64 = 15;
+/** colours for the activity levels, from none to most */
+const LEVEL_COLOURS: [&str; 5] = ["#ebedf0", "#9be9a8", "#40c463", "#30a14e", "#216e39"];
+/** the number of months of activity listed on the stats page */
+const ACTIVITY_MONTHS: u32 = 12;
+/** the number of tags listed on the stats page (the most used) */
+const MAX_TAGS: usize = 20;
+/** how many words are read in a minute, for the reading time */
+const WORDS_PER_MINUTE: usize = 200;
+/** how many of the latest changes to an item are shown with it */
+pub const RECENT_EDITS: usize = 3;
+
+/** statistics about the text of one item, shown with it */
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ItemStats {
+    /** the number of words (that have a letter or digit, not counting the boxes of entries) */
+    pub words: usize,
+    /** the number of characters, including spaces */
+    pub characters: usize,
+    /** the estimated time to read the text, in minutes */
+    pub reading_minutes: usize,
+    /** when the latest changes were made, newest first */
+    pub edits: Vec<String>,
+}
+impl ItemStats {
+    /** the statistics for the text (without the changes) */
+    pub fn for_text(text: &str) -> Self {
+        let text = text.trim();
+        let words = text
+            .split_whitespace()
+            .filter(|w| w.chars().any(char::is_alphanumeric) && *w != "[x]")
+            .count();
+        Self {
+            words,
+            characters: text.chars().count(),
+            reading_minutes: (words + WORDS_PER_MINUTE - 1) / WORDS_PER_MINUTE,
+            edits: vec![],
+        }
+    }
+    /** whether there is anything to show */
+    pub fn is_empty(&self) -> bool {
+        self.words == 0 && self.edits.is_empty()
+    }
+}
+/** the statistics for the item with the ident and text, with its latest changes */
+pub fn item_stats(world: &World, ident: &Ident, text: &str) -> FLResult<ItemStats> {
+    let mut stats = ItemStats::for_text(text);
+    if ident.is_empty() {
+        return Ok(stats);
+    }
+    for commit in world.item_history(ident, RECENT_EDITS)? {
+        let local =
+            NaiveDateTime::from_timestamp(commit.when + i64::from(commit.offset_minutes) * 60, 0);
+        stats.edits.push(world.format().format_date_time(local));
+    }
+    Ok(stats)
+}
+
+/** counts of activity on one day */
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DayActivity {
+    /** items created */
+    pub created: u32,
+    /** items modified */
+    pub edited: u32,
+    /** items completed (eg tasks closed) */
+    pub completed: u32,
+}
+impl DayActivity {
+    /** all activity on the day */
+    pub fn total(&self) -> u32 {
+        self.created + self.edited + self.completed
+    }
+}
+/** activity by (local) date */
+pub type Activity = BTreeMap<NaiveDate, DayActivity>;
+
+/** count the activity recorded in commit messages.
+
+By default commit messages are made up of `verb ident` pairs, one for
+each change in the commit (see [`crate::Action::verb`]). Any word
+that is a verb is counted, so this also works for most commit
+message templates that include the verb (see [`crate::settings`]). */
+pub fn activity_from_history(commits: &[CommitDescr]) -> Activity {
+    let mut activity = Activity::new();
+    for commit in commits {
+        let date = NaiveDateTime::from_timestamp(commit.when + commit.offset_minutes as i64 * 60, 0)
+            .date();
+        for word in commit.message.split_whitespace() {
+            let day = activity.entry(date).or_default();
+            match word.trim_matches(|c: char| !c.is_alphabetic()) {
+                "add" => day.created += 1,
+                "modify" | "reopen" | "archive" | "snooze" => day.edited += 1,
+                "close" => day.completed += 1,
+                _ => {}
+            }
+        }
+    }
+    activity
+}
+
+/** the activity in each of the `months` months up to the one containing `last_day`, oldest first */
+pub fn by_month(
+    activity: &Activity,
+    last_day: NaiveDate,
+    months: u32,
+) -> Vec<(NaiveDate, DayActivity)> {
+    let mut month = NaiveDate::from_ymd(last_day.year(), last_day.month(), 1);
+    for _ in 1..months {
+        month = (month - Duration::days(1))
+            .with_day(1)
+            .expect("no first day");
+    }
+    let mut totals = vec![];
+    for _ in 0..months {
+        let next = (month + Duration::days(31))
+            .with_day(1)
+            .expect("no first day");
+        let mut total = DayActivity::default();
+        for (_d, a) in activity.range(month..next) {
+            total.created += a.created;
+            total.edited += a.edited;
+            total.completed += a.completed;
+        }
+        totals.push((month, total));
+        month = next;
+    }
+    totals
+}
+
+/** render activity as a GitHub-style heatmap in SVG, with one
+column for each of `weeks` weeks ending with the week containing
+`last_day` */
+pub fn heatmap_svg(
+    activity: &Activity,
+    last_day: NaiveDate,
+    weeks: i64,
+    format: &FormatOptions,
+) -> String {
+    let last_week_start = last_day - Duration::days(format.day_of_week(last_day) as i64);
+    let first_day = last_week_start - Duration::weeks(weeks - 1);
+    let max = activity
+        .range(first_day..=last_day)
+        .map(|(_d, a)| a.total())
+        .max()
+        .unwrap_or(0);
+    let width = LEFT_MARGIN + weeks * PITCH;
+    let height = TOP_MARGIN + 7 * PITCH;
+    let mut parts = vec![format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" class="heatmap" width="{}" height="{}" viewBox="0 0 {} {}" font-family="sans-serif" font-size="9">"#,
+        width, height, width, height
+    )];
+    for (weekday, label) in &[
+        (Weekday::Mon, "Mon"),
+        (Weekday::Wed, "Wed"),
+        (Weekday::Fri, "Fri"),
+    ] {
+        let row = (weekday.num_days_from_monday() + 7 - format.week_start().num_days_from_monday())
+            % 7;
+        parts.push(format!(
+            r#"<text x="0" y="{}">{}</text>"#,
+            TOP_MARGIN + row as i64 * PITCH + CELL - 2,
+            label
+        ));
+    }
+    let mut day = first_day;
+    while day <= last_day {
+        let offset = (day - first_day).num_days();
+        let (col, row) = (offset / 7, offset % 7);
+        let x = LEFT_MARGIN + col * PITCH;
+        if row == 0 && day.day() <= 7 {
+            parts.push(format!(
+                r#"<text x="{}" y="{}">{}</text>"#,
+                x,
+                TOP_MARGIN - 4,
+                day.format("%b")
+            ));
+        }
+        let a = activity.get(&day).cloned().unwrap_or_default();
+        parts.push(format!(
+            r#"<rect x="{}" y="{}" width="{}" height="{}" rx="2" fill="{}"><title>{}: {} created, {} edited, {} completed</title></rect>"#,
+            x,
+            TOP_MARGIN + row * PITCH,
+            CELL,
+            CELL,
+            LEVEL_COLOURS[level(a.total(), max)],
+            format.format_date(day),
+            a.created,
+            a.edited,
+            a.completed
+        ));
+        day = day.succ();
+    }
+    parts.push("</svg>".to_owned());
+    parts.join("")
+}
+/** the colour level (0 to 4) for a count */
+fn level(count: u32, max: u32) -> usize {
+    if count == 0 || max == 0 {
+        0
+    } else {
+        (((count * 4) + max - 1) / max).max(1).min(4) as usize
+    }
+}
+
+/** the activity heatmap for the last year, suitable for embedding in a page */
+pub fn activity_heatmap(world: &World) -> FLResult<String> {
+    let activity = activity_from_history(&world.history(MAX_COMMITS)?);
+    Ok(heatmap_svg(
+        &activity,
+        chrono::Local::today().naive_local(),
+        HEATMAP_WEEKS,
+        world.format(),
+    ))
+}
+
+/** how many items there are of a kind or with a tag */
+struct Count {
+    name: String,
+    count: usize,
+}
+/** the activity in a month */
+struct MonthActivity {
+    month: String,
+    activity: DayActivity,
+}
+/** the counts, most first (then by name), of at most `max` names */
+fn counts(by_name: BTreeMap<String, usize>, max: usize) -> Vec<Count> {
+    let mut counts: Vec<Count> = by_name
+        .into_iter()
+        .map(|(name, count)| Count { name, count })
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count));
+    counts.truncate(max);
+    counts
+}
+
+/** template data for the stats page */
+#[derive(Template)]
+#[template(path = "stats.html", print = "none")]
+struct StatsTemplate {
+    item_count: usize,
+    kinds: Vec<Count>,
+    tags: Vec<Count>,
+    months: Vec<MonthActivity>,
+    created: u32,
+    edited: u32,
+    completed: u32,
+    per_active_day: String,
+    heatmap: String,
+    message: String,
+    /** the charts that can be exported, by name, with their titles */
+    charts: Vec<(&'static str, &'static str)>,
+    /** whether charts can be exported as PNG pictures */
+    png: bool,
+}
+/** show the stats page, with a message (eg about an export) */
+pub fn show_stats(world: &mut World, message: &str) -> fanling_interface::ResponseResult {
+    let activity = activity_from_history(&world.history(MAX_COMMITS)?);
+    let today = chrono::Local::today().naive_local();
+    let year_ago = today - Duration::weeks(HEATMAP_WEEKS);
+    let mut year = DayActivity::default();
+    let mut active_days = 0;
+    for (_d, a) in activity.range(year_ago..=today) {
+        year.created += a.created;
+        year.edited += a.edited;
+        year.completed += a.completed;
+        if a.total() > 0 {
+            active_days += 1;
+        }
+    }
+    let per_active_day = if active_days == 0 {
+        0.0
+    } else {
+        year.total() as f64 / active_days as f64
+    };
+    let mut kinds = BTreeMap::new();
+    let mut tags = BTreeMap::new();
+    let entries = world.search_all()?.entries;
+    for entry in &entries {
+        let item_ref = ItemLink::from(entry.link.clone()).resolve_link(world)?;
+        let item = item_ref.borrow();
+        *kinds.entry(item.type_name()).or_insert(0) += 1;
+        for tag in item.tags() {
+            *tags.entry(tag).or_insert(0) += 1;
+        }
+    }
+    let months = by_month(&activity, today, ACTIVITY_MONTHS)
+        .into_iter()
+        .rev()
+        .map(|(month, activity)| MonthActivity {
+            month: month.format("%Y-%m").to_string(),
+            activity,
+        })
+        .collect();
+    #[cfg(test)]
+    let kind_count = kinds.len();
+    let t = StatsTemplate {
+        item_count: entries.len(),
+        kinds: counts(kinds, usize::MAX),
+        tags: counts(tags, MAX_TAGS),
+        months,
+        created: year.created,
+        edited: year.edited,
+        completed: year.completed,
+        per_active_day: world.format().format_number(per_active_day, 1),
+        heatmap: heatmap_svg(&activity, today, HEATMAP_WEEKS, world.format()),
+        message: message.to_owned(),
+        charts: CHARTS.to_vec(),
+        png: cfg!(feature = "png"),
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    {
+        resp.set_test_data("created", &format!("{}", year.created));
+        resp.set_test_data("kinds", &format!("{}", kind_count));
+        resp.set_test_data("message", message);
+    }
+    Ok(resp)
+}
+
+/** how a chart is exported */
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ChartFormat {
+    /** a standalone SVG file in the export directory */
+    Svg,
+    /** a PNG file in the export directory (needs the `png` feature) */
+    Png,
+    /** a simple item showing the SVG, which is attached to it */
+    Attachment,
+}
+/** the charts that can be exported, by name, with their titles */
+pub const CHARTS: [(&str, &str); 4] = [
+    ("heatmap", "Activity"),
+    ("completed", "Tasks completed each week"),
+    ("timeline", "Timeline by project"),
+    ("timeline-tags", "Timeline by tag"),
+];
+/** the chart with the name as SVG, as in the page it is shown in */
+fn chart_svg(world: &mut World, name: &str) -> FLResult<String> {
+    match name {
+        "heatmap" => activity_heatmap(world),
+        "completed" => crate::report::completed_chart(world),
+        "timeline" => crate::timeline::timeline_chart(world, false),
+        "timeline-tags" => crate::timeline::timeline_chart(world, true),
+        _ => Err(fanling_error!(&format!("no chart called '{}'", name))),
+    }
+}
+/** the SVG as a PNG picture, made by `rsvg-convert` */
+#[cfg(feature = "png")]
+fn png(svg: &str) -> FLResult<Vec<u8>> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+    let mut child = Command::new("rsvg-convert")
+        .args(&["--format", "png", "--background-color", "white"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    /* the standard input is closed (when dropped) before waiting */
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(svg.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(fanling_error!(&format!(
+            "rsvg-convert exited with {}",
+            output.status
+        )));
+    }
+    Ok(output.stdout)
+}
+#[cfg(not(feature = "png"))]
+fn png(_svg: &str) -> FLResult<Vec<u8>> {
+    Err(fanling_error!("PNG pictures cannot be made here"))
+}
+/** make a simple item showing the chart, with the SVG attached to it */
+fn chart_item(world: &mut World, name: &str, svg: &str, date: &str) -> FLResult<ItemRef> {
+    let title = CHARTS
+        .iter()
+        .find(|(n, _t)| *n == name)
+        .map_or(name, |(_n, t)| *t);
+    let base = ItemBaseForSerde {
+        ident: "".to_owned(),
+        type_name: "Simple".to_owned(),
+        ..ItemBaseForSerde::default()
+    };
+    let mut vals = HashMap::new();
+    vals.insert("name".to_owned(), format!("{}, {}", title, date));
+    vals.insert("text".to_owned(), "".to_owned());
+    let item_ref = world.make_item("Simple", &base, &vals)?;
+    let ident = item_ref.borrow().ident();
+    let path = format!(
+        "{}/{}/{}-{}.svg",
+        crate::bookmark::ATTACHMENTS_DIR,
+        ident,
+        name,
+        date
+    );
+    world.add_attachment(&path, svg)?;
+    vals.insert(
+        "text".to_owned(),
+        format!(
+            "![{}]({})\n",
+            title,
+            crate::assets::url(&format!("repo/{}", path))
+        ),
+    );
+    {
+        let mut item = item_ref.borrow_mut();
+        item.set_data(&vals, world)?;
+        world.persist_change_as(&mut item, "attach to")?;
+    }
+    Ok(item_ref)
+}
+/** export a chart (one of [`CHARTS`]): write it to a standalone file
+in the export directory and show the stats page, or make an item with
+it attached and show that */
+pub fn export_chart(
+    world: &mut World,
+    name: &str,
+    format: ChartFormat,
+) -> fanling_interface::ResponseResult {
+    let svg = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}\n",
+        chart_svg(world, name)?
+    );
+    let date = chrono::Local::today()
+        .naive_local()
+        .format(crate::format::ISO_DATE_FORMAT)
+        .to_string();
+    if format == ChartFormat::Attachment {
+        world.begin_batch();
+        let made = chart_item(world, name, &svg, &date);
+        world.end_batch()?;
+        let item_ref = made?;
+        let ident = item_ref.borrow().ident();
+        trace(&format!("attached {} to {}", name, ident));
+        let mut resp = item_ref.borrow_mut().for_show(world)?;
+        resp.show_toast(&format!("Saved '{}'", item_ref.borrow().description()));
+        #[cfg(test)]
+        resp.set_test_data("ident", &ident);
+        return Ok(resp);
+    }
+    let dir = world.export_dir().to_path_buf();
+    fs::create_dir_all(&dir)?;
+    let path = if format == ChartFormat::Png {
+        let path = dir.join(format!("{}-{}.png", name, date));
+        fs::write(&path, png(&svg)?)?;
+        path
+    } else {
+        let path = dir.join(format!("{}-{}.svg", name, date));
+        fs::write(&path, &svg)?;
+        path
+    };
+    trace(&format!("exported {} to {:?}", name, &path));
+    show_stats(world, &format!("Exported to {}", path.display()))
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "stats {}",
+        ansi_term::Colour::Fixed(10).on(ansi_term::Colour::Fixed(233)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn commit(when: i64, message: &str) -> CommitDescr {
+        CommitDescr {
+            id: "".to_owned(),
+            when,
+            offset_minutes: 0,
+            author: "tester".to_owned(),
+            message: message.to_owned(),
+        }
+    }
+    #[test]
+    fn activity_and_heatmap() {
+        let day = NaiveDate::from_ymd(2020, 6, 3);
+        let when = day.and_hms(12, 0, 0).timestamp();
+        let commits = vec![
+            commit(when, "add a-a1 add b-a2"),
+            commit(when, "modify a-a1"),
+            commit(when + 86400, "close b-a2"),
+            commit(when + 86400, "Task 'shopping': close [phone]"),
+            commit(when, "merge after fetch (merged)"),
+        ];
+        let activity = activity_from_history(&commits);
+        let expected = DayActivity {
+            created: 2,
+            edited: 1,
+            completed: 0,
+        };
+        assert_eq!(Some(&expected), activity.get(&day));
+        assert_eq!(2, activity[&day.succ()].completed);
+        let sunday_first = FormatOptions {
+            week_starts_monday: false,
+            ..FormatOptions::default()
+        };
+        let svg = heatmap_svg(&activity, day.succ(), 2, &sunday_first);
+        /* 7 days in the first week, Sunday to Thursday in the second */
+        assert_eq!(12, svg.matches("<rect").count());
+        assert!(svg.contains("2020-06-03: 2 created, 1 edited, 0 completed"));
+        assert!(svg.contains(LEVEL_COLOURS[4]));
+        /* Monday to Thursday in the second week */
+        let svg = heatmap_svg(&activity, day.succ(), 2, &FormatOptions::default());
+        assert_eq!(11, svg.matches("<rect").count());
+        let months = by_month(&activity, NaiveDate::from_ymd(2020, 7, 15), 3);
+        assert_eq!(NaiveDate::from_ymd(2020, 5, 1), months[0].0);
+        assert_eq!(5, months[1].1.total());
+        assert_eq!(2, months[1].1.completed);
+        assert_eq!(0, months[2].1.total());
+    }
+    #[test]
+    fn text_stats() {
+        let stats = ItemStats::for_text("# Notes\n\n- [x] one - two\n");
+        assert_eq!(3, stats.words);
+        assert_eq!(24, stats.characters);
+        assert_eq!(1, stats.reading_minutes);
+        let long = "word ".repeat(WORDS_PER_MINUTE + 1);
+        assert_eq!(2, ItemStats::for_text(&long).reading_minutes);
+        assert!(ItemStats::for_text("  ").is_empty());
+    }
+}
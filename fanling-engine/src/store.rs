@@ -3,12 +3,16 @@ License, v. 2.0. If a copy of the MPL was not distributed with this
 file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 /*! overall code for mapping idents into items */
+use crate::crypt::Cipher;
 use crate::fanling_trace;
+use crate::idents::IdentGenerator;
 use crate::item::Ident;
 use crate::item::{Item, ItemBaseForSerde, ItemRef};
+use crate::private::Passphrase;
+use crate::settings::CommitOptions;
 use crate::shared::{FLResult, FanlingError, NullResult, Tracer};
 use regex::Regex;
-use taipo_git_control::MergeOutcome;
+use taipo_git_control::{CommitDescr, MergeOutcome, RemoteDescr, Verification};
 use taipo_git_control::{
     Change, ChangeList, ConflictList, EntryDescr, FanlingRepository, ObjectOperation,
     RepoActionRequired, RepoOptions,
@@ -22,6 +26,9 @@ use std::panic;
 use std::panic::AssertUnwindSafe;
 //#[macro_use]
 use crate::fanling_error;
+
+/** how many idents are made before giving up on finding one that is not used */
+const MAX_IDENT_TRIES: usize = 100;
 // use std::any::Any;
 
 /** FUTURE: check that repo "file names" (within the repo) are the same as in fanling9 namely `items/_ident_.page`
@@ -34,10 +41,18 @@ pub struct Store {
     repo: FanlingRepository,
     known: HashMap<Ident, ItemRef>,
     pending_changes: ChangeList,
-    unallowed_chars: Regex,
     next_ident_num: u64,
     item_path_re: Regex,
-    initial_dash: Regex,
+    /** how the commits for changes are described */
+    commit_options: CommitOptions,
+    /** encrypts and decrypts the items (if the repository is encrypted) */
+    cipher: Option<Cipher>,
+    /** how many batches of changes are being collected into a single commit (they can be nested) */
+    batches: usize,
+    /** passphrases of the private items, as given this session */
+    passphrases: HashMap<Ident, Passphrase>,
+    /** whether the items can only be read (see [`crate::read_only`]) */
+    read_only: bool,
 }
 impl Store {
     /** create and open a [Store] */
@@ -50,10 +65,13 @@ impl Store {
                 repo,
                 known: HashMap::new(),
                 pending_changes: vec![],
-                unallowed_chars: Regex::new("[^0-9a-zA-Z]+")?,
                 next_ident_num: 0,
                 item_path_re: Regex::new("^([^.]*)[.](item|page)$")?,
-                initial_dash: Regex::new("^-")?,
+                commit_options: CommitOptions::default(),
+                cipher: None,
+                batches: 0,
+                passphrases: HashMap::new(),
+                read_only: false,
             },
             repo_action_required,
         ))
@@ -76,12 +94,13 @@ impl Store {
             return Err(fanling_error!(&format!("duplicate ident '{}'", &ident)));
         }
         self.known.insert(ident.clone(), item_ref.clone());
-        let blob = item_.to_yaml()?;
+        let yaml = self.protect(&item_)?;
+        let blob = self.seal(&yaml)?;
         //  let oid = self.repo.notify_blob(&blob)?;
         self.pending_changes.push(Change::new(
-            ObjectOperation::Add(String::from_utf8_lossy(&blob).to_string()),
+            ObjectOperation::Add(blob),
             self.path_from_ident(&ident),
-            format!("add {}", &ident),
+            self.commit_options.message("add", &item_),
         ));
         self.apply_changes()?;
         //     .apply_changes(&self.pending_changes, &format!("add item {}", &ident))?;
@@ -89,15 +108,37 @@ impl Store {
         //  self.get_item_parent
         Ok(())
     }
-    /** ensure that all pending changes are actioned */
+    /** ensure that all pending changes are actioned (unless they are
+    being collected into a single commit) */
     pub fn apply_changes(&mut self) -> NullResult {
+        if self.batches > 0 || self.pending_changes.is_empty() {
+            return Ok(());
+        }
+        if self.read_only {
+            self.pending_changes.clear();
+            return Err(fanling_error!("the repository is read-only"));
+        }
         self.repo.apply_changes(&self.pending_changes)?;
         self.pending_changes.clear();
         self.set_needs_push();
         Ok(())
     }
+    /** collect the following changes into a single commit (made by
+    [Store::end_batch], or by the outermost one if batches are nested) */
+    pub fn begin_batch(&mut self) {
+        self.batches += 1;
+    }
+    /** make a single commit for the changes since [Store::begin_batch] */
+    pub fn end_batch(&mut self) -> NullResult {
+        self.batches = self.batches.saturating_sub(1);
+        self.apply_changes()
+    }
     /** mark an [`Item`] as modified */
     pub fn mark_item_modified(&mut self, item_: &mut Item) -> NullResult {
+        self.mark_item_modified_as(item_, "modify")
+    }
+    /** mark an [`Item`] as modified, describing the change with `verb` in the commit message */
+    pub fn mark_item_modified_as(&mut self, item_: &mut Item, verb: &str) -> NullResult {
         //    let item_ = item_ref.borrow();
         let ident = item_.ident();
         fanling_trace!(&format!(
@@ -112,16 +153,210 @@ impl Store {
             ));
             return Err(fanling_error!(&format!("ident '{}' not known", &ident)));
         }
-        let blob = item_.to_yaml()?;
+        let yaml = self.protect(&item_)?;
+        let blob = self.seal(&yaml)?;
         //  let oid = self.repo.notify_blob(&blob)?;
         self.pending_changes.push(Change::new(
-            ObjectOperation::Modify(String::from_utf8_lossy(&blob).to_string()),
+            ObjectOperation::Modify(blob),
             self.path_from_ident(&ident),
-            format!("modify {}", &ident),
+            self.commit_options.message(verb, item_),
         ));
         self.apply_changes()?;
         Ok(())
     }
+    /** the YAML for an item, encrypted using the item's passphrase if it is private */
+    fn protect(&mut self, item_: &Item) -> FLResult<Vec<u8>> {
+        let yaml = item_.to_yaml()?;
+        if !item_.is_private() || item_.is_locked() {
+            return Ok(yaml);
+        }
+        let passphrase = item_
+            .passphrase()
+            .or_else(|| self.passphrases.get(&item_.ident()))
+            .cloned()
+            .ok_or_else(|| fanling_error!("a private item needs a passphrase"))?;
+        self.passphrases.insert(item_.ident(), passphrase.clone());
+        crate::private::seal_item(&yaml, &passphrase)
+    }
+    /** keep the passphrase of a private item for the rest of the session */
+    pub fn remember_passphrase(&mut self, ident: &Ident, passphrase: &Passphrase) {
+        self.passphrases.insert(ident.clone(), passphrase.clone());
+    }
+    /** encrypt the items with a key from the passphrase (setting up
+    encryption for the repository if this is the first time) */
+    pub fn set_passphrase(&mut self, passphrase: &str) -> NullResult {
+        let (salt_key, check_key) = crate::crypt::config_keys();
+        let (cipher, new_values) = Cipher::for_repo(
+            passphrase,
+            self.config_value(salt_key)?,
+            self.config_value(check_key)?,
+        )?;
+        if let Some((salt, check)) = new_values {
+            self.set_config_value(salt_key, &salt)?;
+            self.set_config_value(check_key, &check)?;
+        }
+        self.cipher = Some(cipher);
+        Ok(())
+    }
+    /** whether the items can only be read */
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+    /** make the items read-only, or not */
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+    /** where the repository is */
+    pub fn repo_path(&self) -> &std::path::Path {
+        &self.repo.path
+    }
+    /** whether the items are encrypted */
+    pub fn is_encrypted(&self) -> bool {
+        self.cipher.is_some()
+    }
+    /** write files that are not items (such as a generated README)
+    beside the items, committing the ones that have changed */
+    pub fn write_other_files(&mut self, files: &[(String, String)]) -> NullResult {
+        for (path, contents) in files {
+            let op = if self.repo.repo_has_file(path)? {
+                if self.repo.blob_from_path(path)? == contents.as_bytes() {
+                    continue;
+                }
+                ObjectOperation::Modify(contents.clone())
+            } else {
+                ObjectOperation::Add(contents.clone())
+            };
+            self.pending_changes
+                .push(Change::new(op, path.clone(), format!("generate {}", path)));
+        }
+        self.apply_changes()
+    }
+    /** add a file attached to an item (such as an archived web page),
+    encrypted if the items are */
+    pub fn add_attachment(&mut self, path: &str, contents: &str) -> NullResult {
+        let op = ObjectOperation::Add(self.seal(contents.as_bytes())?);
+        self.pending_changes
+            .push(Change::new(op, path.to_owned(), format!("attach {}", path)));
+        self.apply_changes()
+    }
+    /** the contents of a file attached to an item */
+    pub fn attachment(&self, path: &str) -> FLResult<String> {
+        if !self.repo.repo_has_file(path)? {
+            return Err(fanling_error!(&format!("no attachment {}", path)));
+        }
+        self.unseal(&String::from_utf8_lossy(&self.repo.blob_from_path(path)?))
+    }
+    /** the serialised item as stored (encrypted if required) */
+    fn seal(&self, blob: &[u8]) -> FLResult<String> {
+        let text = String::from_utf8_lossy(blob).to_string();
+        match &self.cipher {
+            Some(cipher) => cipher.seal(&text),
+            None => Ok(text),
+        }
+    }
+    /** the serialised item from the stored form */
+    fn unseal(&self, stored: &str) -> FLResult<String> {
+        match &self.cipher {
+            Some(cipher) => cipher.open(stored),
+            None if crate::crypt::is_encrypted(stored) => {
+                Err(fanling_error!("item is encrypted but no passphrase was given"))
+            }
+            None => Ok(stored.to_owned()),
+        }
+    }
+    /** the change as stored (encrypted if required) */
+    fn seal_change(&self, change: &Change) -> FLResult<Change> {
+        let op = match &change.op {
+            ObjectOperation::Add(text) => ObjectOperation::Add(self.seal(text.as_bytes())?),
+            ObjectOperation::Modify(text) => ObjectOperation::Modify(self.seal(text.as_bytes())?),
+            op => op.clone(),
+        };
+        Ok(Change::new(op, change.path.clone(), change.descr.clone()))
+    }
+    /** how the commits for changes are described */
+    pub fn commit_options(&self) -> &CommitOptions {
+        &self.commit_options
+    }
+    /** change how the commits for changes are described */
+    pub fn set_commit_options(&mut self, commit_options: CommitOptions) {
+        self.commit_options = commit_options;
+    }
+    /** the name and email address used as the author of commits */
+    pub fn identity(&self) -> (String, String) {
+        self.repo.identity()
+    }
+    /** set (and save) the author of commits */
+    pub fn set_identity(&mut self, name: &str, email: &str) -> NullResult {
+        Ok(self.repo.set_identity(name, email)?)
+    }
+    /** a value from the repository's configuration */
+    pub fn config_value(&self, key: &str) -> FLResult<Option<String>> {
+        Ok(self.repo.config_value(key)?)
+    }
+    /** save a value in the repository's configuration */
+    pub fn set_config_value(&mut self, key: &str, value: &str) -> NullResult {
+        Ok(self.repo.set_config_value(key, value)?)
+    }
+    /** the id of the latest commit, if there is one yet */
+    pub fn head_id(&self) -> FLResult<Option<String>> {
+        Ok(self.repo.head_id()?)
+    }
+    /** the most recent commits, newest first */
+    pub fn history(&self, max: usize) -> FLResult<Vec<CommitDescr>> {
+        Ok(self.repo.history(max)?)
+    }
+    /** whether the path is in the directories checked out */
+    pub fn in_sparse_set(&self, path: &str) -> bool {
+        self.repo.in_sparse_set(path)
+    }
+    /** the commits that have not been pushed to the remote, newest first */
+    pub fn unpushed(&self, max: usize) -> FLResult<Vec<CommitDescr>> {
+        Ok(self.repo.unpushed(max)?)
+    }
+    /** how many commits the store is ahead of and behind the remote (`None` if there is no remote) */
+    pub fn ahead_behind(&self) -> FLResult<Option<(usize, usize)>> {
+        Ok(self.repo.ahead_behind()?)
+    }
+    /** the most recent commits that changed an item, newest first */
+    pub fn item_history(&self, ident: &Ident, max: usize) -> FLResult<Vec<CommitDescr>> {
+        Ok(self.repo.path_history(&self.path_from_ident(ident), max)?)
+    }
+    /** when each item was last changed (seconds since the Unix epoch), from no more than `max` commits */
+    pub fn last_changed(&self, max: usize) -> FLResult<HashMap<Ident, i64>> {
+        Ok(self
+            .repo
+            .last_changed(max)?
+            .into_iter()
+            .filter_map(|(path, when)| Some((self.ident_from_path(&path)?, when)))
+            .collect())
+    }
+    /** the serialised item as it was in a commit, if it was there */
+    pub fn item_at_commit(&self, ident: &Ident, id: &str) -> FLResult<Option<String>> {
+        match self.repo.blob_at_commit(id, &self.path_from_ident(ident))? {
+            Some(blob) => Ok(Some(self.unseal(&String::from_utf8_lossy(&blob))?)),
+            None => Ok(None),
+        }
+    }
+    /** the items added or changed since a commit */
+    pub fn changed_items(&self, id: &str) -> FLResult<Vec<Ident>> {
+        Ok(self
+            .repo
+            .changed_paths(id)?
+            .iter()
+            .filter_map(|path| self.ident_from_path(path))
+            .collect())
+    }
+    /** move back to a commit, discarding the ones after it (this
+    invalidates all [`Item`]s) */
+    pub fn reset_to(&mut self, id: &str) -> NullResult {
+        self.repo.reset_to(id)?;
+        self.clear_known();
+        Ok(())
+    }
+    /** whether a commit is signed, and if so whether the signature is good */
+    pub fn verify_commit(&self, id: &str) -> FLResult<Verification> {
+        Ok(self.repo.verify_commit(id)?)
+    }
     /** mark an [`Item`] as deleted */
     pub fn mark_item_deleted(&mut self, item: ItemRef) -> NullResult {
         let item_ = item.borrow();
@@ -137,11 +372,23 @@ impl Store {
         self.pending_changes.push(Change::new(
             ObjectOperation::Delete,
             self.path_from_ident(&ident),
-            format!("delete {}", &ident),
+            self.commit_options.message("delete", &item_),
         ));
         self.apply_changes()?;
         Ok(())
     }
+    /** delete the file of the item with the ident, whether or not the
+    item is known (as when it has been stored with the wrong ident) */
+    pub fn delete_file(&mut self, ident: &Ident) -> NullResult {
+        fanling_trace!(&format!("deleting file of '{}' in store", ident));
+        self.known.remove(ident);
+        self.pending_changes.push(Change::new(
+            ObjectOperation::Delete,
+            self.path_from_ident(ident),
+            format!("delete {}", ident),
+        ));
+        self.apply_changes()
+    }
     /** get an item if it is already known. (This can be used to check whether an item is known). */
     pub fn get_item_if_known(&self, ident: &Ident) -> Option<&ItemRef> {
         // debug code
@@ -164,7 +411,13 @@ impl Store {
         fanling_trace!(&format!("getting parts of '{}'", ident));
         assert!(!ident.is_empty(), "Ident is blank when getting item parts");
         let data = self.get_serialised(ident)?;
-        crate::item::split_data_parts(&data)
+        let (base, values) = crate::item::split_data_parts(&data)?;
+        match self.passphrases.get(ident) {
+            Some(passphrase) if crate::private::is_sealed(&values) => {
+                crate::private::open_item(&values, passphrase)
+            }
+            _ => Ok((base, values)),
+        }
     }
     /** get the serialised data for an item */
     fn get_serialised(&self, ident: &Ident) -> FLResult<Vec<u8>> {
@@ -175,7 +428,15 @@ impl Store {
             trace(&format!("error was {:?}", e));
         }
         trace(&format!("as serialised {:?}", &vec_res));
-        Ok(vec_res?)
+        let stored = vec_res?;
+        if self.cipher.is_none() {
+            return Ok(stored);
+        }
+        Ok(self.unseal(&String::from_utf8_lossy(&stored))?.into_bytes())
+    }
+    /** the items in the known map */
+    pub fn known_items(&self) -> Vec<ItemRef> {
+        self.known.values().cloned().collect()
     }
     /** create an [`Item`] from YAML and add it to the known map. */
     pub fn make_known(&mut self, item_rcrc: ItemRef) -> FLResult<ItemRef> {
@@ -215,46 +476,66 @@ impl Store {
         self.next_ident_num.try_into().expect("bad???")
     }
 
-    /** make a new [`Ident`] */
-    pub fn make_identifier(&mut self, uniq_pfx: &str, name: &str) -> Ident {
-        trace(&format!(
-            "making ident from '{}' prefix '{}'",
-            name, uniq_pfx
-        ));
-        assert!(!uniq_pfx.is_empty(), "prefix is empty");
-        assert!(!name.is_empty(), "name is empty");
-        let tnx = self.unallowed_chars.replace_all(name, "-");
-        let tidy_name = self.initial_dash.replace(&tnx, "");
-        match tidy_name.chars().next() {
-            None => panic!("name should not resolve to empty (name was '{}')", name),
-            Some(ch0) => assert!(ch0 != '-' && ch0 != '?', "bad name '{}'", &tidy_name),
+    /** make a new [`Ident`] with the generator (see [`crate::idents`]), one that no item has already */
+    pub fn make_identifier(
+        &mut self,
+        generator: &mut dyn IdentGenerator,
+        name: &str,
+    ) -> FLResult<Ident> {
+        trace(&format!("making ident from '{}' with {:?}", name, generator));
+        for _ in 0..MAX_IDENT_TRIES {
+            self.next_ident_num += 1;
+            let ident = generator.generate(name, self.next_ident_num);
+            if !self.known.contains_key(&ident) && !self.has_file(&ident)? {
+                return Ok(ident);
+            }
+            trace(&format!("ident '{}' is already used, trying again", &ident));
         }
-        let max_len = 20;
-        let short_tidy_name = if tidy_name.len() < max_len {
-            tidy_name.into_owned()
-        } else {
-            tidy_name[..max_len].to_string()
-        };
-        self.next_ident_num += 1;
-        format!("{}-{}{}", short_tidy_name, uniq_pfx, &self.next_ident_num)
+        Err(fanling_error!(&format!(
+            "could not make an ident for '{}' that is not already used",
+            name
+        )))
     }
     /** returns a list of all items */
     pub fn list_all_items(&self) -> FLResult<Vec<EntryDescr>> {
-        let items: Vec<EntryDescr> = self
+        let mut items: Vec<EntryDescr> = self
             .repo
             .list_all()?
             .into_iter()
             .filter(|i| self.path_has_ident(&i.path))
             .collect();
+        for item in items.iter_mut() {
+            item.blob = self.unseal(&item.blob)?;
+        }
         trace(&format!("{} entries found in repo", items.len()));
         Ok(items)
     }
+    /** the largest number used at the end of an ident in the repository */
+    pub fn max_ident_num(&self) -> FLResult<u64> {
+        let num_re = Regex::new("([0-9]+)$")?;
+        Ok(self
+            .repo
+            .list_all()?
+            .iter()
+            .filter_map(|i| self.ident_from_path(&i.path))
+            .filter_map(|ident| {
+                num_re
+                    .captures(&ident)
+                    .and_then(|cc| cc.get(1))
+                    .and_then(|m| m.as_str().parse().ok())
+            })
+            .max()
+            .unwrap_or(0))
+    }
     // /** pull any new changes from the server */
     // pub fn pull(&mut self) -> NullResult {
     //     unimplemented!()
     // }
-    /** push the store to the server if required */
+    /** push the store to the server if required (never, if it is read-only) */
     pub fn push(&mut self, force: bool) -> NullResult {
+        if self.read_only {
+            return Ok(());
+        }
         let needs_pushing = self.repo.does_need_pushing();
         fanling_trace!(&format!(
             "push: {} from {}",
@@ -295,11 +576,11 @@ impl Store {
     pub fn path_has_ident(&self, path: &str) -> bool {
         self.item_path_re.captures(path).is_some()
     }
-    /** fetch from server */
-    pub fn fetch(&mut self) -> NullResult {
-        trace("fetching (store)");
+    /** fetch from one of the remotes */
+    pub fn fetch_from(&mut self, remote: &str) -> NullResult {
+        trace(&format!("fetching from {} (store)", remote));
         let result = panic::catch_unwind(AssertUnwindSafe(|| {
-            let fr = self.repo.fetch();
+            let fr = self.repo.fetch_from(remote);
             trace(&format!("fetch (store) result {:#?}", fr));
             Ok(fr?)
         }));
@@ -311,6 +592,75 @@ impl Store {
             }
         }
     }
+    /** push to one of the remotes other than the main one (whether or not there are commits to push) */
+    pub fn push_to(&mut self, remote: &str, force: bool) -> NullResult {
+        trace(&format!("pushing to {} (store)", remote));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| Ok(self.repo.push_to(remote, force)?)));
+        match result {
+            Ok(pr) => pr,
+            Err(_e) => Err(fanling_error!(&format!("push to {} failed", remote))),
+        }
+    }
+    /** the remotes, the main one first */
+    pub fn remotes(&self) -> FLResult<Vec<RemoteDescr>> {
+        Ok(self.repo.remotes()?)
+    }
+    /** the name of the main remote (given in the options), if there is one */
+    pub fn main_remote(&self) -> Option<String> {
+        self.repo.main_remote().map(str::to_owned)
+    }
+    /** add or change a remote */
+    pub fn set_remote(&mut self, remote: &RemoteDescr) -> NullResult {
+        Ok(self.repo.set_remote(remote)?)
+    }
+    /** remove a remote other than the main one */
+    pub fn delete_remote(&mut self, remote: &str) -> NullResult {
+        Ok(self.repo.delete_remote(remote)?)
+    }
+    /** the name of the credential provider, if there is one */
+    pub fn credential_provider(&self) -> Option<String> {
+        self.repo.credential_provider()
+    }
+    /** the key for the secret used with the remote (see [`taipo_git_control::token_key`]) */
+    pub fn credential_key(&self, remote: &str) -> FLResult<String> {
+        Ok(self.repo.credential_key(remote)?)
+    }
+    /** whether there is a secret kept for the remote */
+    pub fn has_secret(&self, remote: &str) -> FLResult<bool> {
+        Ok(self.repo.has_secret(remote)?)
+    }
+    /** keep the secret for the remote */
+    pub fn set_secret(&self, remote: &str, secret: &str) -> NullResult {
+        Ok(self.repo.set_secret(remote, secret)?)
+    }
+    /** forget the secret for the remote */
+    pub fn forget_secret(&self, remote: &str) -> NullResult {
+        Ok(self.repo.forget_secret(remote)?)
+    }
+    /** check that the remote can be reached with its credentials */
+    pub fn test_remote(&self, remote: &str) -> NullResult {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| Ok(self.repo.test_remote(remote)?)));
+        match result {
+            Ok(tr) => tr,
+            Err(_e) => Err(fanling_error!(&format!("testing {} failed", remote))),
+        }
+    }
+    /** how big the repository is, with the `max` largest files */
+    pub fn size_report(&self, max: usize) -> FLResult<taipo_git_control::SizeReport> {
+        Ok(self.repo.size_report(max)?)
+    }
+    /** pack the objects of the repository */
+    pub fn repack(&self) -> FLResult<taipo_git_control::RepackReport> {
+        Ok(self.repo.repack()?)
+    }
+    /** collect the garbage in the repository (with the `git` program) */
+    pub fn gc(&self) -> NullResult {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| Ok(self.repo.gc()?)));
+        match result {
+            Ok(gr) => gr,
+            Err(_e) => Err(fanling_error!("git gc failed")),
+        }
+    }
     /** merge the versions and determine the status (no change/fast forward/conflict) */
     pub fn merge(&mut self) -> FLResult<MergeOutcome> {
         Ok(self.repo.merge()?)
@@ -340,7 +690,24 @@ impl Store {
     }
     /**  the conflicts, if any, resulting from the merge */
     pub fn conflicts(&self, mo: &MergeOutcome) -> FLResult<ConflictList> {
-        Ok(self.repo.conflicts(mo)?)
+        let mut conflicts = self.repo.conflicts(mo)?;
+        if self.cipher.is_some() {
+            for conflict in conflicts.iter_mut() {
+                for entry in vec![
+                    &mut conflict.ancestor,
+                    &mut conflict.our,
+                    &mut conflict.their,
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    entry.data = self
+                        .unseal(&String::from_utf8_lossy(&entry.data))?
+                        .into_bytes();
+                }
+            }
+        }
+        Ok(conflicts)
     }
     /** apply a change list to an index (to resolve conflicts) */
     // pub fn apply_changelist_to_index(&self, changes: &ChangeList, index: &mut Index) -> NullResult {
@@ -354,7 +721,11 @@ impl Store {
         mo: &mut MergeOutcome,
     ) -> NullResult {
         self.set_needs_push();
-        Ok(self.repo.apply_changes_to_merge_outcome(c, mo)?)
+        let sealed = c
+            .iter()
+            .map(|change| self.seal_change(change))
+            .collect::<FLResult<ChangeList>>()?;
+        Ok(self.repo.apply_changes_to_merge_outcome(&sealed, mo)?)
     }
     /** whether the repository has a remote */
     pub fn has_remote(&self) -> bool {
@@ -0,0 +1,170 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! suggestions of what to do now.
+
+Given the time available, the energy level and the current context,
+the ready tasks that fit are scored using their priority, context and
+deadline, and the best few are suggested. */
+use crate::item::{ItemListEntry, ItemListEntryList};
+use crate::shared::FLResult;
+use crate::task::Energy;
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/** the most tasks suggested */
+const MAX_SUGGESTIONS: usize = 5;
+
+/** the current situation, as given by the user */
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SuggestionRequest {
+    /** time available in minutes (0 if not limited) */
+    #[serde(default)]
+    pub minutes: u32,
+    /** energy level, such as "low" (blank if not limited) */
+    #[serde(default)]
+    pub energy: String,
+    /** ident of the current context (blank if any) */
+    #[serde(default)]
+    pub context: String,
+}
+/** the parts of a task's data used for suggestions */
+#[derive(Deserialize)]
+struct TaskForSuggestion {
+    #[serde(default)]
+    priority: i64,
+    #[serde(default)]
+    context: String,
+    #[serde(default)]
+    effort: u32,
+    #[serde(default)]
+    energy: Energy,
+    #[serde(default)]
+    deadline: String,
+}
+/** a suggested task */
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    pub entry: ItemListEntry,
+    pub score: i64,
+    /** why the task was suggested */
+    pub reasons: String,
+}
+
+/** the score and reasons for a task, or `None` if it does not fit the request */
+fn score(task: &TaskForSuggestion, req: &SuggestionRequest) -> Option<(i64, Vec<&'static str>)> {
+    if req.minutes > 0 && task.effort > req.minutes {
+        return None;
+    }
+    if !req.energy.trim().is_empty() && task.energy > Energy::parse(&req.energy) {
+        return None;
+    }
+    let mut score = 100 - 5 * task.priority;
+    let mut reasons = vec![];
+    if task.priority < 10 {
+        reasons.push("high priority");
+    }
+    if !req.context.is_empty() && task.context == req.context {
+        score += 30;
+        reasons.push("in this context");
+    }
+    if let Ok(deadline) = task.deadline.parse::<NaiveDateTime>() {
+        if deadline >= NaiveDateTime::from_timestamp(1, 0) {
+            let days = (deadline - chrono::Local::now().naive_local()).num_days();
+            if days < 0 {
+                score += 60;
+                reasons.push("overdue");
+            } else if days < 3 {
+                score += 40;
+                reasons.push("due soon");
+            }
+        }
+    }
+    if req.minutes > 0 && task.effort > 0 {
+        score += 10;
+        reasons.push("fits the time available");
+    }
+    Some((score, reasons))
+}
+
+/** the ready tasks that best fit the request, best first */
+pub fn suggest(world: &mut World, req: &SuggestionRequest) -> FLResult<Vec<Suggestion>> {
+    let ready = world
+        .search_type("Task")?
+        .filter_on_item(|i, w| Ok(i.is_ready(w)?), world)?
+        .entries;
+    let mut suggestions = vec![];
+    for entry in ready {
+        let (_base, values) = world.get_item_parts(&entry.link.ident)?;
+        if crate::private::is_sealed(&values) {
+            continue;
+        }
+        let task: TaskForSuggestion = serde_yaml::from_value(values)?;
+        if let Some((score, reasons)) = score(&task, req) {
+            suggestions.push(Suggestion {
+                entry,
+                score,
+                reasons: reasons.join(", "),
+            });
+        }
+    }
+    suggestions.sort_by(|a, b| {
+        (-a.score, &a.entry.descr, &a.entry.link.ident).cmp(&(
+            -b.score,
+            &b.entry.descr,
+            &b.entry.link.ident,
+        ))
+    });
+    suggestions.truncate(MAX_SUGGESTIONS);
+    trace(&format!("{} suggestions for {:?}", suggestions.len(), req));
+    Ok(suggestions)
+}
+
+/** template data for the suggestions view */
+#[derive(Template)]
+#[template(path = "suggest.html", print = "none")]
+struct SuggestTemplate {
+    minutes: u32,
+    energy: String,
+    contexts: ItemListEntryList,
+    suggestions: Vec<Suggestion>,
+}
+/** show the "what should I do now?" view, with the suggestions for the request */
+pub fn show_suggestions(
+    world: &mut World,
+    req: &SuggestionRequest,
+) -> fanling_interface::ResponseResult {
+    let suggestions = suggest(world, req)?;
+    let mut contexts = world.search_contexts()?;
+    contexts.prepend(ItemListEntry::make_special("any context"));
+    contexts.select(req.context.clone());
+    #[cfg(test)]
+    let idents: Vec<String> = suggestions
+        .iter()
+        .map(|s| s.entry.link.ident.clone())
+        .collect();
+    let t = SuggestTemplate {
+        minutes: req.minutes,
+        energy: if req.energy.trim().is_empty() {
+            "".to_owned()
+        } else {
+            format!("{:?}", Energy::parse(&req.energy))
+        },
+        contexts,
+        suggestions,
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    resp.set_test_data("suggestions", &idents.join(","));
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("suggest {}", Colour::Fixed(11).on(Colour::Fixed(233)).paint(m));
+}
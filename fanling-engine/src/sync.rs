@@ -0,0 +1,122 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! how the repository stands against its remote.
+
+Each response from a world action carries a
+[`fanling_interface::SyncStatus`]: how many local commits have not been
+pushed, how many fetched ones have not been merged, when the repository
+was last pulled or pushed, and how many items had conflicting changes in
+the last merge (and which remotes the last pull or push failed for, see
+[`crate::remotes`]), so that the main program can show a badge. The time of
+the last sync and the conflicts are kept in the configuration of the
+repository (so on this device only). There is also a page listing the
+commits waiting to be pushed. */
+use crate::shared::{FLResult, NullResult};
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use chrono::{NaiveDateTime, TimeZone};
+use fanling_interface::SyncStatus;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/** the key in the repository's configuration for when it was last synced */
+const LAST_SYNC_KEY: &str = "fanling.lastsync";
+/** the key in the repository's configuration for the conflicts in the last merge */
+const CONFLICTS_KEY: &str = "fanling.syncconflicts";
+/** the most commits listed */
+const MAX_COMMITS: usize = 100;
+
+/** how the repository stands against its remote */
+pub fn status(world: &World) -> FLResult<SyncStatus> {
+    let ahead_behind = world.ahead_behind()?;
+    let (ahead, behind) = ahead_behind.unwrap_or((0, 0));
+    Ok(SyncStatus {
+        has_remote: ahead_behind.is_some(),
+        ahead,
+        behind,
+        last_sync: world
+            .config_value(LAST_SYNC_KEY)?
+            .and_then(|s| s.parse().ok()),
+        conflicts: world
+            .config_value(CONFLICTS_KEY)?
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0),
+        failed_remotes: crate::remotes::failed(world),
+    })
+}
+/** record that the repository has been synced just now, with the
+conflicts in the merge if it was pulled */
+pub fn record(world: &mut World, conflicts: Option<usize>) -> NullResult {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    trace(&format!("synced at {}, conflicts {:?}", now, conflicts));
+    world.set_config_value(LAST_SYNC_KEY, &format!("{}", now))?;
+    if let Some(conflicts) = conflicts {
+        world.set_config_value(CONFLICTS_KEY, &format!("{}", conflicts))?;
+    }
+    Ok(())
+}
+
+/** a commit waiting to be pushed, for the template */
+struct Waiting {
+    when: String,
+    author: String,
+    message: String,
+}
+/** template data for the page of the changes waiting to push */
+#[derive(Template)]
+#[template(path = "unpushed.html", print = "none")]
+struct UnpushedTemplate {
+    status: SyncStatus,
+    last_sync: String,
+    commits: Vec<Waiting>,
+    /** whether there are more commits than those listed */
+    more: bool,
+}
+/** show the commits waiting to be pushed, with how the repository stands against its remote */
+pub fn show_unpushed(world: &mut World) -> fanling_interface::ResponseResult {
+    let status = status(world)?;
+    /* with no remote, the commits are not waiting for anything */
+    let unpushed = if status.has_remote {
+        world.unpushed(MAX_COMMITS)?
+    } else {
+        vec![]
+    };
+    let commits: Vec<Waiting> = unpushed
+        .into_iter()
+        .map(|c| Waiting {
+            when: world
+                .format()
+                .format_date_time(NaiveDateTime::from_timestamp(
+                    c.when + i64::from(c.offset_minutes) * 60,
+                    0,
+                )),
+            author: c.author,
+            message: c.message.trim().to_owned(),
+        })
+        .collect();
+    let last_sync = match status.last_sync {
+        Some(secs) => world
+            .format()
+            .format_date_time(chrono::Local.timestamp(secs, 0).naive_local()),
+        None => world.i18n().tr("never"),
+    };
+    trace(&format!("{} commits waiting to push", commits.len()));
+    let t = UnpushedTemplate {
+        more: status.ahead > commits.len(),
+        status,
+        last_sync,
+        commits,
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    resp.set_test_data("waiting", &format!("{}", t.commits.len()));
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("sync {}", Colour::Fixed(0).on(Colour::Fixed(117)).paint(m));
+}
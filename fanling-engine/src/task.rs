@@ -2,20 +2,20 @@
 License, v. 2.0. If a copy of the MPL was not distributed with this
 file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-/*! implements [`Task`] items */
+/*! implements [`Task`] items, including the time spent on them (see [`crate::timing`]) */
 use crate::item::{
     Ident, Item, ItemBase, ItemBaseForSerde, ItemData, ItemLink, ItemListEntry, ItemListEntryList,
     NewBaseTemplate, ShowBaseTemplate,
 };
-use crate::markdown;
 use crate::shared::{merge_strings, FLResult, FanlingError, NullResult};
+use crate::timing::TimeRecord;
+use crate::transclude;
 //#[macro_use]
 use crate::fanling_error;
 use crate::world::{ActionResponse, World};
 use ansi_term::Colour;
 use askama::Template;
-use chrono::offset::TimeZone;
-use chrono::{NaiveDateTime, Utc};
+use chrono::{Duration, NaiveDateTime, Utc};
 use fanling_interface::error_response_result;
 use log::trace;
 use serde::de::Deserializer;
@@ -33,6 +33,7 @@ use std::ops::Deref;
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
 pub enum TaskStatus {
     Open,
+    InProgress,
     Closed,
 }
 impl TaskStatus {
@@ -41,15 +42,19 @@ impl TaskStatus {
     }
     pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TaskStatus, D::Error> {
         let status: String = Deserialize::deserialize(deserializer)?;
-        let ts = match status.to_lowercase().as_str() {
+        Ok(Self::parse(&status))
+    }
+    /** the status with the name (open if it is not known) */
+    pub fn parse(status: &str) -> Self {
+        match status.to_lowercase().as_str() {
             "open" | "" => TaskStatus::Open,
+            "inprogress" | "in progress" | "in_progress" => TaskStatus::InProgress,
             "closed" => TaskStatus::Closed,
             _ => {
                 trace(&format!("unknown status: {}", status));
                 TaskStatus::Open
             }
-        };
-        Ok(ts)
+        }
     }
 }
 impl fmt::Display for TaskStatus {
@@ -63,6 +68,70 @@ impl Default for TaskStatus {
         Self::Open
     }
 }
+/** how much energy a task needs */
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, PartialOrd, Ord)]
+pub enum Energy {
+    Low,
+    Medium,
+    High,
+}
+impl Energy {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+    /** the energy level from a description such as "high" (low if unknown) */
+    pub fn parse(s: &str) -> Self {
+        match s.trim().to_lowercase().as_str() {
+            "medium" => Energy::Medium,
+            "high" => Energy::High,
+            _ => Energy::Low,
+        }
+    }
+}
+impl Default for Energy {
+    fn default() -> Self {
+        Self::Low
+    }
+}
+/** how long to snooze (or defer) a task for */
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Snooze {
+    /** for a number of hours */
+    Hours(i64),
+    /** until the start of the next working day */
+    Tomorrow,
+    /** until the start of the first working day of next week */
+    NextWeek,
+    /** until a date (and optionally a time) entered by the user */
+    Until(String),
+}
+impl Snooze {
+    /** when the snooze ends */
+    fn until(&self, world: &World) -> FLResult<NaiveDateTime> {
+        let today = chrono::Local::today().naive_local();
+        let holidays = world.holidays()?;
+        Ok(match self {
+            Snooze::Hours(hours) => Utc::now().naive_utc() + Duration::hours(*hours),
+            Snooze::Tomorrow => holidays.next_working_day(today).and_hms(0, 0, 0),
+            Snooze::NextWeek => {
+                let next_week =
+                    today + Duration::days(7 - world.format().day_of_week(today) as i64);
+                holidays
+                    .this_or_next_working_day(next_week)
+                    .and_hms(0, 0, 0)
+            }
+            Snooze::Until(text) => world.format().parse_date_time(text)?,
+        })
+    }
+}
+/** a record of a task being snoozed */
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SnoozeRecord {
+    /** when the task was snoozed */
+    when: NaiveDateTime,
+    /** when the snooze ended (the new show-after date) */
+    until: NaiveDateTime,
+}
 /** data for a task item , a task item, like a wiki page */
 #[derive(Debug, Clone)]
 pub struct Task {
@@ -83,9 +152,23 @@ pub struct Task {
     deadline: chrono::NaiveDateTime,
     /** only show the task as 'ready' after this date */
     show_after_date: chrono::NaiveDateTime,
+    /** when to be reminded of the task (not at all if at the Unix epoch) */
+    remind: chrono::NaiveDateTime,
     /** task is blocked by these other tasks, do not show this task as
     ready until all these tasks are closed */
     blockedby: Vec<ItemLink>,
+    /** the times the task has been snoozed, oldest first */
+    snoozes: Vec<SnoozeRecord>,
+    /** the child chosen as the next action when the task is a project (blank if none) */
+    next_action: Ident,
+    /** estimated effort in minutes (0 if not known) */
+    effort: u32,
+    /** how much energy the task needs */
+    energy: Energy,
+    /** ident of the contact the task is assigned to (blank if none) */
+    assignee: Ident,
+    /** the periods when the timer was running, oldest first */
+    timings: Vec<TimeRecord>,
 }
 impl Task {
     /** create a new [Task]  */
@@ -100,7 +183,14 @@ impl Task {
             project: "".to_owned(),
             deadline: NaiveDateTime::from_timestamp(0, 0),
             show_after_date: NaiveDateTime::from_timestamp(0, 0),
+            remind: NaiveDateTime::from_timestamp(0, 0),
             blockedby: vec![],
+            snoozes: vec![],
+            next_action: "".to_owned(),
+            effort: 0,
+            energy: Energy::Low,
+            assignee: "".to_owned(),
+            timings: vec![],
         }
     }
     // pub fn set_context(&mut self, context: ItemLink) {
@@ -120,6 +210,24 @@ impl Task {
         }
         Ok(contexts)
     }
+    /** when to be reminded, for display (blank if not at all) */
+    fn remind_for_display(&self, world: &World) -> String {
+        if is_no_time(&self.remind) {
+            "".to_owned()
+        } else {
+            world.format().format_date_time(self.remind)
+        }
+    }
+    /** a warning if the deadline is not on a working day or is close (blank if none) */
+    fn deadline_warning(&self, world: &World) -> FLResult<String> {
+        if !self.is_open() || self.deadline < NaiveDateTime::from_timestamp(1, 0) {
+            return Ok("".to_owned());
+        }
+        Ok(world
+            .holidays()?
+            .deadline_warning(self.deadline.date(), chrono::Local::today().naive_local())
+            .unwrap_or_default())
+    }
     /** data that can be used to display the context in a template */
     pub fn context_for_display(&mut self, world: &mut World) -> FLResult<ItemListEntry> {
         let context_item = world.resolve_link(
@@ -163,17 +271,50 @@ impl Task {
             project: task.project.clone(),
             deadline: task.deadline,
             show_after_date: task.show_after_date,
+            remind: task.remind,
             blockedby: task
                 .blockedby
                 .iter()
                 .map(|il| ItemLink::from(il.clone()))
                 .collect(),
+            snoozes: task.snoozes.clone(),
+            next_action: task.next_action.clone(),
+            effort: task.effort,
+            energy: task.energy,
+            assignee: task.assignee.clone(),
+            timings: task.timings.clone(),
         })
     }
+    /** the name of the contact the task is assigned to (blank if none) */
+    fn assignee_name(&self, world: &World) -> String {
+        if self.assignee.is_empty() {
+            return "".to_owned();
+        }
+        world
+            .get_item_parts(&self.assignee)
+            .ok()
+            .and_then(|(_base, values)| {
+                values.get("name").and_then(|n| n.as_str()).map(str::to_owned)
+            })
+            .unwrap_or_else(|| self.assignee.clone())
+    }
     /** implement the close action */
     fn close(&mut self, _world: &mut World) -> NullResult {
         self.status = TaskStatus::Closed;
         self.when_closed = Utc::now().naive_utc();
+        self.stop_timer(crate::timing::now());
+        Ok(())
+    }
+    /** start the timer (stopping any other that is running) */
+    fn start_timer(&mut self, base: &ItemBase, world: &mut World) -> NullResult {
+        if crate::timing::running_since(&self.timings).is_some() {
+            return Ok(());
+        }
+        crate::timing::stop_others(world, &base.get_ident())?;
+        self.timings.push(TimeRecord {
+            start: crate::timing::now(),
+            end: None,
+        });
         Ok(())
     }
     /** implement the re-open action */
@@ -182,10 +323,27 @@ impl Task {
         // self.when_closed = Utc::now().naive_utc();
         Ok(())
     }
+    /** implement the snooze action: hide the task until the snooze
+    ends, moving the deadline too if it would otherwise be earlier */
+    fn snooze(&mut self, snooze: &Snooze, world: &World) -> NullResult {
+        let until = snooze.until(world)?;
+        if self.deadline >= NaiveDateTime::from_timestamp(1, 0) && self.deadline < until {
+            self.deadline = until;
+        }
+        self.show_after_date = until;
+        self.snoozes.push(SnoozeRecord {
+            when: Utc::now().naive_utc(),
+            until,
+        });
+        trace(&format!("snoozed until {}", until));
+        Ok(())
+    }
     /** block this task by the task with the `ident` */
     fn block(&mut self, ident: &str) -> NullResult {
-        self.blockedby.push(ItemLink::new(ident.to_string()));
-        // TODO: checks; prevent duplications
+        // TODO: checks
+        if !self.blocked_by().iter().any(|b| b == ident) {
+            self.blockedby.push(ItemLink::new(ident.to_string()));
+        }
         Ok(())
     }
     /** unblock this task by the task with the `ident` */
@@ -210,6 +368,18 @@ impl Task {
             },
         );
         resp.set_test_data("open", if self.is_open() { "true" } else { "false" });
+        resp.set_test_data("snoozes", &format!("{}", self.snoozes.len()));
+        resp.set_test_data("effort", &format!("{}", self.effort));
+        resp.set_test_data("assignee", &self.assignee);
+        resp.set_test_data(
+            "timer",
+            if crate::timing::running_since(&self.timings).is_some() {
+                "running"
+            } else {
+                "stopped"
+            },
+        );
+        resp.set_test_data("timings", &format!("{}", self.timings.len()));
     }
 }
 impl crate::item::ItemData for Task {
@@ -231,15 +401,20 @@ impl crate::item::ItemData for Task {
             priority: self.priority,
             context: contexts,
             when_closed: self.when_closed,
-            deadline: self.deadline,
-            show_after_date: self.show_after_date,
+            deadline: world.format().format_date_time(self.deadline),
+            show_after_date: world.format().format_date_time(self.show_after_date),
+            remind: self.remind_for_display(world),
+            effort: self.effort,
+            energy: format!("{:?}", self.energy),
             blockedby,
         };
         let mut resp = fanling_interface::Response::new();
         resp.clear_errors(vec![
             "name-error".to_owned(),
             "priority-error".to_owned(),
+            "effort-error".to_owned(),
             "show-after-date-error".to_owned(),
+            "remind-error".to_owned(),
             "".to_owned(),
         ]);
         resp.add_tag("content", &(nt.render()?));
@@ -250,6 +425,97 @@ impl crate::item::ItemData for Task {
         trace(&format!("for edit {:?}", &resp));
         Ok(resp)
     }
+    /** closing or reopening, depending on the status, adding a child, and starting or stopping the timer */
+    fn available_actions(&self, base: &ItemBase) -> Vec<crate::item::AvailableAction> {
+        let status = match self.status {
+            TaskStatus::Closed => {
+                crate::item::AvailableAction::new(base, crate::Action::Reopen, "Re-open", "↺")
+            }
+            _ => crate::item::AvailableAction::new(base, crate::Action::Close, "Close", "✔"),
+        };
+        let mut actions = vec![
+            status,
+            crate::item::AvailableAction::new(
+                base,
+                crate::Action::NewChild(base.get_ident()),
+                "Add task child",
+                "➕",
+            ),
+        ];
+        if crate::timing::running_since(&self.timings).is_some() {
+            actions.push(crate::item::AvailableAction::new(
+                base,
+                crate::Action::StopTimer,
+                "Stop timer",
+                "⏹",
+            ));
+        } else if self.is_open() {
+            actions.push(crate::item::AvailableAction::new(
+                base,
+                crate::Action::StartTimer,
+                "Start timer",
+                "⏱",
+            ));
+        }
+        actions
+    }
+    /** the context, which is given as a `#word` in quick capture (none for the default context) */
+    fn tags(&self) -> Vec<String> {
+        self.context
+            .as_ref()
+            .and_then(|c| c.ident().ok())
+            .filter(|c| !c.is_empty() && c != "default_context")
+            .into_iter()
+            .collect()
+    }
+    /** the context becomes the last of the tags (found or made by
+    name, unless it is the context already), or the default context if
+    there are none */
+    fn set_tags(&mut self, tags: &[String], world: &mut World) -> FLResult<bool> {
+        let context = match tags.last() {
+            Some(tag) if self.tags().contains(tag) => return Ok(true),
+            Some(tag) => crate::quick::context_ident(world, tag)?,
+            None => "default_context".to_owned(),
+        };
+        self.context = Some(ItemLink::from(
+            world.get_item(context, "Simple".to_owned())?,
+        ));
+        Ok(true)
+    }
+    fn blocked_by(&self) -> Vec<Ident> {
+        self.blockedby
+            .iter()
+            .filter_map(|b| b.ident().ok())
+            .collect()
+    }
+    /** the deadline, if one has been set */
+    fn due(&self) -> Option<NaiveDateTime> {
+        if self.deadline >= NaiveDateTime::from_timestamp(1, 0) {
+            Some(self.deadline)
+        } else {
+            None
+        }
+    }
+    /** the date it is not shown before, if one has been set */
+    fn starts(&self) -> Option<NaiveDateTime> {
+        if self.show_after_date >= NaiveDateTime::from_timestamp(1, 0) {
+            Some(self.show_after_date)
+        } else {
+            None
+        }
+    }
+    fn time_records(&self) -> Vec<TimeRecord> {
+        self.timings.clone()
+    }
+    fn stop_timer(&mut self, now: NaiveDateTime) -> bool {
+        match self.timings.last_mut() {
+            Some(record) if record.end.is_none() => {
+                record.end = Some(now.max(record.start));
+                true
+            }
+            _ => false,
+        }
+    }
     fn for_show(
         &mut self,
         base: &mut ItemBase,
@@ -257,17 +523,45 @@ impl crate::item::ItemData for Task {
     ) -> fanling_interface::ResponseResult {
         let t = ShowTaskTemplate {
             name: self.name.clone(),
-            rendered_text: markdown::render(&self.text),
-            base: ShowBaseTemplate::from_base(base, world)?,
+            rendered_text: transclude::render(world, &base.get_ident(), &self.text),
+            base: ShowBaseTemplate::from_base(base, world)?
+                .with_actions(base, self.available_actions(base))
+                .with_stats(self.stats(base, world)?),
             status: self.status,
             priority: self.priority,
             can_be_context: false,
             context: self.context_for_display(world)?,
-            when_closed: self.when_closed.format("%Y-%m-%d").to_string(),
-            deadline: self.deadline,
-            show_after_date: self.show_after_date,
+            when_closed: world.format().format_date(self.when_closed.date()),
+            deadline: world.format().format_date_time(self.deadline),
+            show_after_date: world.format().format_date_time(self.show_after_date),
+            remind: self.remind_for_display(world),
+            deadline_warning: self.deadline_warning(world)?,
+            effort: self.effort,
+            energy: format!("{:?}", self.energy),
+            assignee: self.assignee.clone(),
+            assignee_name: self.assignee_name(world),
+            can_snooze: self.is_open(),
+            parent_is_task: self.is_open()
+                && base
+                    .resolve_parent(world)?
+                    .map_or(false, |p| p.deref().borrow().type_name() == "Task"),
+            snoozes: self
+                .snoozes
+                .iter()
+                .map(|s| SnoozeForShow {
+                    when: world.format().format_date_time(s.when),
+                    until: world.format().format_date_time(s.until),
+                })
+                .collect(),
             blockedby: ItemListEntryList::from_links(&mut self.blockedby, world),
+            blocks: crate::blocking::blocked_tasks(world, &base.get_ident())?,
             potential_blockers: world.search_open_hier()?,
+            time_spent: crate::timing::format_duration(crate::timing::total(
+                &self.timings,
+                crate::timing::now(),
+            )),
+            timer_running: crate::timing::running_since(&self.timings).is_some(),
+            timed: !self.timings.is_empty(),
         };
         let mut resp = fanling_interface::Response::new();
         resp.add_tag("content", &(t.render()?));
@@ -290,7 +584,7 @@ impl crate::item::ItemData for Task {
     }
     fn is_open(&self) -> bool {
         match self.status {
-            TaskStatus::Open => true,
+            TaskStatus::Open | TaskStatus::InProgress => true,
             TaskStatus::Closed => false,
         }
     }
@@ -337,7 +631,11 @@ impl crate::item::ItemData for Task {
             _ => parts.push(format!("[{:?}] ", self.status)),
         }
         if self.deadline >= NaiveDateTime::from_timestamp(1, 0) {
-            parts.push(format!("[{}] ", self.deadline.format("%Y-%m-%d")));
+            /* descriptions are stored in the search database, so do not use the user's date format */
+            parts.push(format!(
+                "[{}] ",
+                self.deadline.format(crate::format::ISO_DATE_FORMAT)
+            ));
         }
         parts.push(self.name.clone());
         parts.join("")
@@ -373,13 +671,36 @@ impl crate::item::ItemData for Task {
             _ => None,
         };
         self.deadline = match vals.get("deadline") {
-            Some(dl) => Utc.datetime_from_str(dl, "%F %T")?.naive_utc(),
+            Some(dl) => world.format().parse_date_time(dl)?,
             _ => NaiveDateTime::from_timestamp(0, 0),
         };
         self.show_after_date = match vals.get("show_after_date") {
-            Some(dl) => Utc.datetime_from_str(dl, "%F %T")?.naive_utc(),
+            Some(dl) => world.format().parse_date_time(dl)?,
             _ => NaiveDateTime::from_timestamp(0, 0),
         };
+        self.remind = match vals.get("remind").map(|r| r.trim()) {
+            Some(r) if !r.is_empty() => world.format().parse_date_time(r)?,
+            _ => NaiveDateTime::from_timestamp(0, 0),
+        };
+        self.effort = match vals.get("effort").map(|e| e.trim()) {
+            Some(e) if !e.is_empty() => e.parse::<u32>()?,
+            _ => 0,
+        };
+        self.energy = Energy::parse(vals.get("energy").map_or("", |e| e.as_str()));
+        /* the edit form does not include the assignee, so keep any existing one */
+        if let Some(assignee) = vals.get("assignee") {
+            self.assignee = assignee.trim().to_owned();
+        }
+        /* nor the status, which is only given when importing */
+        if let Some(status) = vals.get("status") {
+            self.status = TaskStatus::parse(status);
+            if self.status == TaskStatus::Closed {
+                self.when_closed = match vals.get("when_closed").map(|w| w.trim()) {
+                    Some(w) if !w.is_empty() => world.format().parse_date_time(w)?,
+                    _ => Utc::now().naive_utc(),
+                };
+            }
+        }
         Ok(())
     }
     fn set_from_yaml(&mut self, yaml: &serde_yaml::Value, world: &mut World) -> NullResult {
@@ -407,6 +728,24 @@ impl crate::item::ItemData for Task {
                 self.reopen(world)?;
                 Ok(self.for_show(base, world)?)
             }
+            crate::Action::Snooze(snooze) => {
+                self.snooze(snooze, world)?;
+                Ok(self.for_show(base, world)?)
+            }
+            crate::Action::NextAction(ident) => {
+                self.next_action = ident.clone();
+                Ok(self.for_show(base, world)?)
+            }
+            /* the focus actions are followed by showing the focus view (see `World::do_action`) */
+            crate::Action::FocusDone => {
+                self.close(world)?;
+                Ok(fanling_interface::Response::new())
+            }
+            crate::Action::FocusSkip => {
+                self.next_action =
+                    crate::focus::skip(world, &base.get_ident(), &self.next_action)?;
+                Ok(fanling_interface::Response::new())
+            }
             crate::Action::BlockBy(ident) => {
                 self.block(&ident)?;
                 Ok(self.for_show(base, world)?)
@@ -415,6 +754,31 @@ impl crate::item::ItemData for Task {
                 self.unblock(&ident)?;
                 Ok(self.for_show(base, world)?)
             }
+            /* the board actions are followed by showing the board (see `World::do_action`) */
+            crate::Action::SetStatus(status) => {
+                match status {
+                    TaskStatus::Closed => self.close(world)?,
+                    _ => self.status = *status,
+                }
+                crate::board::place_card(base, *status, world)?;
+                Ok(fanling_interface::Response::new())
+            }
+            crate::Action::StartTimer => {
+                self.start_timer(base, world)?;
+                Ok(self.for_show(base, world)?)
+            }
+            crate::Action::StopTimer => {
+                self.stop_timer(crate::timing::now());
+                Ok(self.for_show(base, world)?)
+            }
+            crate::Action::MoveCardUp => {
+                crate::board::move_card(base, self.status, true, world)?;
+                Ok(fanling_interface::Response::new())
+            }
+            crate::Action::MoveCardDown => {
+                crate::board::move_card(base, self.status, false, world)?;
+                Ok(fanling_interface::Response::new())
+            }
             _ => error_response_result(&format!("invalid action {:?}", action)),
         }
     }
@@ -430,7 +794,14 @@ impl crate::item::ItemData for Task {
             project: self.project.clone(),
             deadline: self.deadline,
             show_after_date: self.show_after_date,
+            remind: self.remind,
             blockedby: vec![],
+            snoozes: vec![],
+            next_action: "".to_owned(),
+            effort: self.effort,
+            energy: self.energy,
+            assignee: self.assignee.clone(),
+            timings: vec![],
         }))
     }
     /** transitional code to fix some old data */
@@ -518,11 +889,40 @@ struct TaskForSerde {
     #[serde(alias = "showafterdate")]
     #[serde(deserialize_with = "ItemBaseForSerde::deserialize")]
     show_after_date: chrono::NaiveDateTime,
+    /** when to be reminded of the task */
+    #[serde(default = "no_time")]
+    #[serde(skip_serializing_if = "is_no_time")]
+    #[serde(deserialize_with = "ItemBaseForSerde::deserialize")]
+    remind: chrono::NaiveDateTime,
     /** task is blocked by these tasks */
     #[serde(alias = "waitingon")]
     #[serde(default)]
     #[serde(skip_serializing_if = "Vec::is_empty")]
     blockedby: Vec<Ident>,
+    /** the times the task has been snoozed */
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    snoozes: Vec<SnoozeRecord>,
+    /** the next action when the task is a project */
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::string::String::is_empty")]
+    next_action: Ident,
+    /** estimated effort in minutes */
+    #[serde(default)]
+    #[serde(skip_serializing_if = "ItemBaseForSerde::is_zero_u32")]
+    effort: u32,
+    /** how much energy the task needs */
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Energy::is_default")]
+    energy: Energy,
+    /** the contact the task is assigned to */
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::string::String::is_empty")]
+    assignee: Ident,
+    /** the periods when the timer was running */
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    timings: Vec<TimeRecord>,
     /** old field from legacy daata */
     #[serde(default)]
     closed: bool,
@@ -539,7 +939,14 @@ impl Default for TaskForSerde {
             project: "".to_owned(),
             deadline: NaiveDateTime::from_timestamp(0, 0),
             show_after_date: NaiveDateTime::from_timestamp(0, 0),
+            remind: no_time(),
             blockedby: vec![],
+            snoozes: vec![],
+            next_action: "".to_owned(),
+            effort: 0,
+            energy: Energy::Low,
+            assignee: "".to_owned(),
+            timings: vec![],
             closed: false,
         }
     }
@@ -577,11 +984,18 @@ impl TryFrom<&Task> for TaskForSerde {
             project: task.project.clone(),
             deadline: task.deadline,
             show_after_date: task.show_after_date,
+            remind: task.remind,
             blockedby: task
                 .blockedby
                 .iter()
                 .map(|il| il.ident().expect("no ident"))
                 .collect(),
+            snoozes: task.snoozes.clone(),
+            next_action: task.next_action.clone(),
+            effort: task.effort,
+            energy: task.energy,
+            assignee: task.assignee.clone(),
+            timings: task.timings.clone(),
             closed: false,
         })
     }
@@ -597,11 +1011,20 @@ struct NewTaskTemplate<'a> {
     pub priority: i8,
     pub context: ItemListEntryList,
     pub when_closed: NaiveDateTime,
-    pub deadline: chrono::NaiveDateTime,
-    pub show_after_date: chrono::NaiveDateTime,
+    pub deadline: String,
+    pub show_after_date: String,
+    /** when to be reminded (blank if not at all) */
+    pub remind: String,
+    pub effort: u32,
+    pub energy: String,
     pub blockedby: ItemListEntryList,
 }
 
+/** a snooze, formatted for showing */
+struct SnoozeForShow {
+    when: String,
+    until: String,
+}
 /** template data for showing a task item */
 #[derive(Template)]
 #[template(path = "show-task.html")]
@@ -614,10 +1037,29 @@ struct ShowTaskTemplate {
     pub context: ItemListEntry,
     pub can_be_context: bool,
     pub when_closed: String,
-    pub deadline: chrono::NaiveDateTime,
-    pub show_after_date: chrono::NaiveDateTime,
+    pub deadline: String,
+    pub show_after_date: String,
+    /** when to be reminded (blank if not at all) */
+    pub remind: String,
+    pub deadline_warning: String,
+    pub effort: u32,
+    pub energy: String,
+    /** ident of the contact the task is assigned to (blank if none) */
+    pub assignee: Ident,
+    pub assignee_name: String,
+    pub can_snooze: bool,
+    /** the parent is a task, so this can be its next action */
+    pub parent_is_task: bool,
+    pub snoozes: Vec<SnoozeForShow>,
     pub potential_blockers: ItemListEntryList,
     pub blockedby: ItemListEntryList,
+    /** the open tasks that this one blocks */
+    pub blocks: ItemListEntryList,
+    /** the total time spent, as hours and minutes */
+    pub time_spent: String,
+    pub timer_running: bool,
+    /** whether the timer has ever been started */
+    pub timed: bool,
 }
 
 /** policy for the task item type*/
@@ -655,28 +1097,60 @@ impl crate::item::ItemTypePolicy for TaskTypePolicy {
         ot.name = merge_strings(&ot.name, &tt.name);
         ot.text = merge_strings(&ot.text, &tt.text);
         //   ot.context = "";
-        if ot.status == TaskStatus::Open {
+        if ot.status != TaskStatus::Closed {
             if tt.status == TaskStatus::Closed {
                 ot.status = TaskStatus::Closed;
                 ot.when_closed = tt.when_closed;
+            } else if tt.status == TaskStatus::InProgress {
+                ot.status = TaskStatus::InProgress;
             }
         }
         ot.priority = std::cmp::min(ot.priority, tt.priority);
         //  ot.project = "";
         ot.deadline = std::cmp::min(ot.deadline, tt.deadline);
         ot.show_after_date = std::cmp::min(ot.show_after_date, tt.show_after_date);
+        if is_no_time(&ot.remind) {
+            ot.remind = tt.remind;
+        }
         for t in tt.blockedby {
             if !ot.blockedby.contains(&t) {
                 ot.blockedby.push(t);
             }
         }
+        for s in tt.snoozes {
+            if !ot.snoozes.contains(&s) {
+                ot.snoozes.push(s);
+            }
+        }
+        ot.snoozes.sort_by(|a, b| a.when.cmp(&b.when));
+        if ot.next_action.is_empty() {
+            ot.next_action = tt.next_action;
+        }
+        ot.effort = std::cmp::max(ot.effort, tt.effort);
+        ot.energy = std::cmp::max(ot.energy, tt.energy);
+        if ot.assignee.is_empty() {
+            ot.assignee = tt.assignee;
+        }
+        /* the periods timed on either side are all kept, and a period
+        stopped on one side is stopped */
+        for t in tt.timings {
+            match ot.timings.iter_mut().find(|o| o.start == t.start) {
+                Some(o) => {
+                    if o.end.is_none() {
+                        o.end = t.end;
+                    }
+                }
+                None => ot.timings.push(t),
+            }
+        }
+        ot.timings.sort_by(|a, b| a.start.cmp(&b.start));
         Ok(Box::new(ot))
     }
     fn check_valid(
         &mut self,
-        _base: &ItemBaseForSerde,
+        base: &ItemBaseForSerde,
         vals: &HashMap<String, String>,
-        _world: &mut World,
+        world: &mut World,
     ) -> ActionResponse {
         let mut ar = ActionResponse::new();
         ar.assert(
@@ -690,17 +1164,35 @@ impl crate::item::ItemTypePolicy for TaskTypePolicy {
             "Priority must be numeric",
         );
         /* TODO: validate context */
+        match world.format().parse_date_time(&vals["deadline"]) {
+            Ok(deadline) => ar.warn(
+                !is_new_past_deadline(deadline, base, world),
+                "deadline-error",
+                "The deadline is in the past.",
+            ),
+            Err(_) => ar.add_error("deadline-error", "Invalid deadline date"),
+        }
         ar.assert(
-            Utc.datetime_from_str(&vals["deadline"], "%F %T").is_ok(),
-            "deadline-error",
-            "Invalid deadline date",
-        );
-        ar.assert(
-            Utc.datetime_from_str(&vals["show_after_date"], "%F %T")
+            world
+                .format()
+                .parse_date_time(&vals["show_after_date"])
                 .is_ok(),
             "show-after-date-error",
             "Invalid show-after date",
         );
+        ar.assert(
+            vals.get("remind").map_or(true, |r| {
+                r.trim().is_empty() || world.format().parse_date_time(r).is_ok()
+            }),
+            "remind-error",
+            "Invalid reminder date",
+        );
+        ar.assert(
+            vals.get("effort")
+                .map_or(true, |e| e.trim().is_empty() || e.trim().parse::<u32>().is_ok()),
+            "effort-error",
+            "Effort must be a number of minutes",
+        );
         ar
     }
     /** get item data from serde value */
@@ -711,6 +1203,75 @@ impl crate::item::ItemTypePolicy for TaskTypePolicy {
         Ok(Box::new(t))
     }
 }
+/** what is exported of a task (as to org-mode), from its stored values */
+#[derive(Debug, Clone)]
+pub struct Summary {
+    pub name: String,
+    pub text: String,
+    pub status: TaskStatus,
+    /** the ident of the context */
+    pub context: Ident,
+    /** the deadline ([`no_time`] if none) */
+    pub deadline: NaiveDateTime,
+    /** when the task is shown as ready ([`no_time`] if always) */
+    pub show_after_date: NaiveDateTime,
+    /** when the task was closed (if it has been) */
+    pub when_closed: NaiveDateTime,
+}
+/** the summary of a task from its stored values */
+pub fn summary(values: &serde_yaml::Value) -> FLResult<Summary> {
+    let mut tfs = TaskForSerde::default();
+    tfs.set_from_yaml(values)?;
+    Ok(Summary {
+        name: tfs.name,
+        text: tfs.text,
+        status: tfs.status,
+        context: tfs.context,
+        deadline: tfs.deadline,
+        show_after_date: tfs.show_after_date,
+        when_closed: tfs.when_closed,
+    })
+}
+/** whether a deadline is in the past, and is not the one the task
+already had (so that an overdue task can still be edited) */
+/** the time used for no time (the Unix epoch) */
+pub fn no_time() -> NaiveDateTime {
+    NaiveDateTime::from_timestamp(0, 0)
+}
+/** whether the time is the one used for no time (for serialisation) */
+pub fn is_no_time(time: &NaiveDateTime) -> bool {
+    *time < NaiveDateTime::from_timestamp(1, 0)
+}
+/** the name, reminder time and deadline of a task from its stored
+values, if it is still open (either time is [`no_time`] if the task has
+none) */
+pub fn reminder_times(
+    values: &serde_yaml::Value,
+) -> FLResult<Option<(String, NaiveDateTime, NaiveDateTime)>> {
+    let mut tfs = TaskForSerde::default();
+    tfs.set_from_yaml(values)?;
+    Ok(if tfs.status == TaskStatus::Closed {
+        None
+    } else {
+        Some((tfs.name, tfs.remind, tfs.deadline))
+    })
+}
+fn is_new_past_deadline(deadline: NaiveDateTime, base: &ItemBaseForSerde, world: &World) -> bool {
+    if deadline < NaiveDateTime::from_timestamp(1, 0)
+        || deadline.date() >= chrono::Local::today().naive_local()
+    {
+        return false;
+    }
+    if base.ident.is_empty() {
+        return true;
+    }
+    let previous = world
+        .get_item_parts(&base.ident)
+        .ok()
+        .and_then(|(_base, values)| values.get("deadline").cloned())
+        .and_then(|d| serde_yaml::from_value::<NaiveDateTime>(d).ok());
+    previous != Some(deadline)
+}
 /** convenience function for debug traces */
 fn trace(m: &str) {
     trace!("{}", m);
@@ -0,0 +1,610 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! importing tasks from other to-do apps: the exports of Todoist (its
+JSON backups and completed tasks, and its CSV project templates) and of
+Google Tasks (the JSON from Google Takeout).
+
+Each task becomes a task item in the namespace of its project (or its
+list in Google Tasks), such as `shopping` for the project "Shopping".
+The first label of a task is its context (made if there is none with
+that name), as fanling tasks have only one. Due dates are deadlines,
+and completed tasks (including each completion of a recurring task, in
+the completed tasks that Todoist exports) are imported as closed, when
+they were completed. A CSV file is of a single project, which is given
+with it (such as the name of the file).
+
+All the tasks are made in a single commit. The rows that are not
+imported are reported, with why: they are not tasks (such as the
+sections and notes of a Todoist CSV file), have no name, or are already
+present (a task with the same name in the same namespace, closed at the
+same time). A due date that cannot be read (such as the "every day" of
+a recurring task in a CSV file) is reported too, but the task is still
+imported, without a deadline. */
+use crate::item::{split_data_parts, ItemBaseForSerde, ItemListEntry, ItemListEntryList};
+use crate::shared::{FLResult, FanlingError, NullResult};
+use crate::task::TaskStatus;
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+//#[macro_use]
+use crate::fanling_error;
+
+/** how dates and times are given to tasks */
+const DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+/** the most reasons for skipping rows that are shown */
+const MAX_REASONS: usize = 10;
+
+/** a task read from an export, before it is made */
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ImportedTask {
+    pub name: String,
+    pub text: String,
+    /** the project (or list) it is in, if any */
+    pub project: String,
+    pub labels: Vec<String>,
+    pub due: Option<NaiveDateTime>,
+    /** when it was completed, if it has been */
+    pub completed: Option<NaiveDateTime>,
+}
+/** a row (counted from 1) that was not imported, or not all of it, and why */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub row: usize,
+    pub reason: String,
+}
+/** what was read from an export */
+#[derive(Debug, Default)]
+pub struct Parsed {
+    pub tasks: Vec<(usize, ImportedTask)>,
+    /** the rows that were skipped */
+    pub skipped: Vec<Problem>,
+    /** the rows that were imported without some of their data */
+    pub warnings: Vec<Problem>,
+}
+impl Parsed {
+    /** add the task from the row, unless it has no name */
+    fn add(&mut self, row: usize, task: ImportedTask) {
+        if task.name.trim().is_empty() {
+            self.skip(row, "it has no name");
+        } else {
+            self.tasks.push((row, task));
+        }
+    }
+    fn skip(&mut self, row: usize, reason: &str) {
+        self.skipped.push(Problem {
+            row,
+            reason: reason.to_owned(),
+        });
+    }
+    fn warn(&mut self, row: usize, reason: &str) {
+        self.warnings.push(Problem {
+            row,
+            reason: reason.to_owned(),
+        });
+    }
+    /** the time in a due date, noting if it cannot be read */
+    fn due(&mut self, row: usize, due: &str) -> Option<NaiveDateTime> {
+        let due = due.trim();
+        if due.is_empty() {
+            return None;
+        }
+        let time = parse_time(due);
+        if time.is_none() {
+            self.warn(row, &format!("the due date '{}' was not understood", due));
+        }
+        time
+    }
+}
+
+/** the time given, in one of the forms used in exports; a date alone is at the end of the day */
+pub fn parse_time(text: &str) -> Option<NaiveDateTime> {
+    let text = text.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(text, crate::format::ISO_DATE_FORMAT) {
+        return Some(date.and_time(NaiveTime::from_hms(23, 59, 0)));
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        /* Google Tasks gives due dates (which have no time) at midnight UTC */
+        let time = time.naive_utc();
+        return Some(if time.time() == NaiveTime::from_hms(0, 0, 0) {
+            time.date().and_time(NaiveTime::from_hms(23, 59, 0))
+        } else {
+            time
+        });
+    }
+    if let Ok(time) = DateTime::parse_from_rfc2822(text) {
+        return Some(time.naive_utc());
+    }
+    ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(text, f).ok())
+}
+/** a string field of a JSON object (blank if there is none) */
+fn field(value: &Value, key: &str) -> String {
+    match value.get(key) {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Number(n)) => n.to_string(),
+        _ => "".to_owned(),
+    }
+}
+/** a field of a JSON object that is one of several names, whichever is given */
+fn any_field(value: &Value, keys: &[&str]) -> String {
+    keys.iter()
+        .map(|k| field(value, k))
+        .find(|f| !f.is_empty())
+        .unwrap_or_default()
+}
+/** the tasks in an export (of Todoist or Google Tasks, as JSON or CSV) */
+pub fn parse(text: &str, project: &str) -> FLResult<Parsed> {
+    let trimmed = text.trim_start();
+    if !trimmed.starts_with('{') && !trimmed.starts_with('[') {
+        return parse_csv(text, project);
+    }
+    let value: Value = serde_json::from_str(text)?;
+    let items = match value
+        .get("items")
+        .or_else(|| value.as_array().map(|_| &value))
+    {
+        Some(Value::Array(items)) => items,
+        _ => return Err(fanling_error!("there are no tasks in the JSON")),
+    };
+    let is_google = field(&value, "kind").starts_with("tasks#")
+        || items.iter().any(|i| field(i, "kind").starts_with("tasks#"));
+    let mut parsed = Parsed::default();
+    if is_google {
+        parse_google(&mut parsed, items, project);
+    } else {
+        parse_todoist(&mut parsed, &value, items, project);
+    }
+    Ok(parsed)
+}
+/** the tasks in the lists of Google Tasks (or the tasks of a list) */
+fn parse_google(parsed: &mut Parsed, items: &[Value], project: &str) {
+    let mut row = 0;
+    for item in items {
+        let (list, tasks) = match item.get("items") {
+            Some(Value::Array(tasks)) => (field(item, "title"), tasks.clone()),
+            _ if field(item, "kind") == "tasks#taskList" => continue,
+            _ => (project.to_owned(), vec![item.clone()]),
+        };
+        for task in &tasks {
+            row += 1;
+            if task.get("deleted").and_then(Value::as_bool) == Some(true) {
+                parsed.skip(row, "it was deleted");
+                continue;
+            }
+            let due = parsed.due(row, &field(task, "due"));
+            let completed = if field(task, "status") == "completed" {
+                Some(parse_time(&field(task, "completed")).unwrap_or_else(crate::timing::now))
+            } else {
+                None
+            };
+            parsed.add(
+                row,
+                ImportedTask {
+                    name: field(task, "title"),
+                    text: field(task, "notes"),
+                    project: list.clone(),
+                    labels: vec![],
+                    due,
+                    completed,
+                },
+            );
+        }
+    }
+}
+/** the tasks in a Todoist backup (or its completed tasks) */
+fn parse_todoist(parsed: &mut Parsed, value: &Value, items: &[Value], project: &str) {
+    /* the projects are a list in backups, but a map by id in the completed tasks */
+    let mut projects: HashMap<String, String> = HashMap::new();
+    match value.get("projects") {
+        Some(Value::Array(list)) => {
+            for p in list {
+                projects.insert(field(p, "id"), field(p, "name"));
+            }
+        }
+        Some(Value::Object(map)) => {
+            for (id, p) in map {
+                projects.insert(id.clone(), field(p, "name"));
+            }
+        }
+        _ => {}
+    }
+    let labels: HashMap<String, String> = match value.get("labels") {
+        Some(Value::Array(list)) => list
+            .iter()
+            .map(|l| (field(l, "id"), field(l, "name")))
+            .collect(),
+        _ => HashMap::new(),
+    };
+    for (i, item) in items.iter().enumerate() {
+        let row = i + 1;
+        if item.get("is_deleted").map_or(false, is_true) {
+            parsed.skip(row, "it was deleted");
+            continue;
+        }
+        let due = match item.get("due") {
+            Some(due @ Value::Object(_)) => parsed.due(row, &field(due, "date")),
+            Some(Value::String(due)) => parsed.due(row, due),
+            _ => None,
+        };
+        let completed_at = any_field(item, &["completed_at", "completed_date", "date_completed"]);
+        let completed = if !completed_at.is_empty() {
+            parse_time(&completed_at).or_else(|| Some(crate::timing::now()))
+        } else if item.get("checked").map_or(false, is_true) {
+            Some(crate::timing::now())
+        } else {
+            None
+        };
+        let project_id = field(item, "project_id");
+        parsed.add(
+            row,
+            ImportedTask {
+                name: field(item, "content"),
+                text: field(item, "description"),
+                project: projects
+                    .get(&project_id)
+                    .cloned()
+                    .unwrap_or_else(|| project.to_owned()),
+                labels: match item.get("labels") {
+                    Some(Value::Array(l)) => l
+                        .iter()
+                        .map(|l| match l {
+                            Value::String(name) => name.clone(),
+                            id => labels.get(&id.to_string()).cloned().unwrap_or_default(),
+                        })
+                        .filter(|l| !l.is_empty())
+                        .collect(),
+                    _ => vec![],
+                },
+                due,
+                completed,
+            },
+        );
+    }
+}
+/** whether a JSON value is true (as a boolean or a number) */
+fn is_true(value: &Value) -> bool {
+    value.as_bool().unwrap_or(false) || value.as_i64().map_or(false, |n| n != 0)
+}
+/** the labels (as `@label`) in the content of a Todoist task, and the content without them */
+fn split_labels(content: &str) -> (String, Vec<String>) {
+    let mut labels = vec![];
+    let mut words = vec![];
+    for word in content.split_whitespace() {
+        match word.strip_prefix('@') {
+            Some(label) if !label.is_empty() => labels.push(label.to_owned()),
+            _ => words.push(word),
+        }
+    }
+    (words.join(" "), labels)
+}
+/** the tasks in a CSV file (a Todoist template, or with columns such as title, notes and due) */
+fn parse_csv(text: &str, project: &str) -> FLResult<Parsed> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| fanling_error!(&e.to_string()))?
+        .iter()
+        .map(|h| h.trim().to_lowercase())
+        .collect();
+    let column = |names: &[&str]| headers.iter().position(|h| names.contains(&h.as_str()));
+    let type_col = column(&["type"]);
+    let name_col = match column(&["content", "title", "task", "name"]) {
+        Some(col) => col,
+        None => {
+            return Err(fanling_error!(
+                "there is no column for the names of the tasks"
+            ))
+        }
+    };
+    let text_col = column(&["description", "notes"]);
+    let due_col = column(&["date", "due", "due date"]);
+    let project_col = column(&["project", "list"]);
+    let labels_col = column(&["labels", "tags"]);
+    let completed_col = column(&["completed", "completed at", "completed date"]);
+    let mut parsed = Parsed::default();
+    for (i, record) in reader.records().enumerate() {
+        /* the header is the first row */
+        let row = i + 2;
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                parsed.skip(row, &format!("it could not be read ({})", e));
+                continue;
+            }
+        };
+        let get = |col: Option<usize>| col.and_then(|c| record.get(c)).unwrap_or("").trim();
+        if record.iter().all(|f| f.trim().is_empty()) {
+            continue;
+        }
+        match get(type_col).to_lowercase().as_str() {
+            "" | "task" => {}
+            other => {
+                parsed.skip(row, &format!("it is a {}, not a task", other));
+                continue;
+            }
+        }
+        let (name, mut labels) = split_labels(get(Some(name_col)));
+        labels.extend(
+            get(labels_col)
+                .split(|c| c == ',' || c == ' ')
+                .map(|l| l.trim_start_matches('@'))
+                .filter(|l| !l.is_empty())
+                .map(str::to_owned),
+        );
+        let due = parsed.due(row, get(due_col));
+        let completed = match get(completed_col) {
+            "" | "false" | "no" | "0" => None,
+            "true" | "yes" | "1" => Some(crate::timing::now()),
+            when => parse_time(when).or_else(|| Some(crate::timing::now())),
+        };
+        let row_project = get(project_col);
+        parsed.add(
+            row,
+            ImportedTask {
+                name,
+                text: get(text_col).to_owned(),
+                project: if row_project.is_empty() {
+                    project.to_owned()
+                } else {
+                    row_project.to_owned()
+                },
+                labels,
+                due,
+                completed,
+            },
+        );
+    }
+    Ok(parsed)
+}
+
+/** the namespace for a project, such as `shopping` for "Shopping" (none if it is blank) */
+pub fn namespace_for(project: &str) -> String {
+    project
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-")
+}
+/** what makes a task the same as one already present: its namespace, name and when it was closed */
+type TaskKey = (String, String, Option<NaiveDateTime>);
+/** the keys of the tasks already present */
+fn known_tasks(world: &World) -> FLResult<HashSet<TaskKey>> {
+    let mut known = HashSet::new();
+    for (ident, values) in world.stored_values()? {
+        if crate::private::is_sealed(&values) {
+            continue;
+        }
+        let (base, values) = split_data_parts(&serde_yaml::to_vec(&values)?)?;
+        if base.type_name != "Task" {
+            continue;
+        }
+        let task = crate::task::summary(&values)?;
+        known.insert((
+            crate::namespace::namespace_of(&ident).to_owned(),
+            task.name.trim().to_lowercase(),
+            if task.status == TaskStatus::Closed {
+                Some(task.when_closed)
+            } else {
+                None
+            },
+        ));
+    }
+    Ok(known)
+}
+/** the values for making the item for a task */
+fn vals_for(world: &mut World, task: &ImportedTask) -> FLResult<HashMap<String, String>> {
+    let mut vals = HashMap::new();
+    vals.insert("name".to_owned(), task.name.trim().to_owned());
+    vals.insert("text".to_owned(), task.text.clone());
+    let context = match task.labels.first() {
+        Some(label) => crate::quick::context_ident(world, label)?,
+        None => "default_context".to_owned(),
+    };
+    vals.insert("context".to_owned(), context);
+    if let Some(due) = task.due {
+        vals.insert(
+            "deadline".to_owned(),
+            due.format(DATE_TIME_FORMAT).to_string(),
+        );
+    }
+    if let Some(completed) = task.completed {
+        vals.insert("status".to_owned(), format!("{:?}", TaskStatus::Closed));
+        vals.insert(
+            "when_closed".to_owned(),
+            completed.format(DATE_TIME_FORMAT).to_string(),
+        );
+    }
+    Ok(vals)
+}
+
+/** template data for importing tasks */
+#[derive(Template)]
+#[template(path = "taskimport.html", print = "none")]
+struct TaskImportTemplate {
+    message: String,
+}
+/** show the form for importing tasks, with a message */
+pub fn show_task_import(message: &str) -> fanling_interface::ResponseResult {
+    let t = TaskImportTemplate {
+        message: message.to_owned(),
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    resp.set_test_data("message", message);
+    Ok(resp)
+}
+/** the reasons for the problems, as a sentence (with at most [`MAX_REASONS`]) */
+fn describe(problems: &[Problem]) -> String {
+    let mut reasons: Vec<String> = problems
+        .iter()
+        .take(MAX_REASONS)
+        .map(|p| format!("row {}: {}", p.row, p.reason))
+        .collect();
+    if problems.len() > MAX_REASONS {
+        reasons.push(format!("and {} more", problems.len() - MAX_REASONS));
+    }
+    reasons.join("; ")
+}
+/** make a task for each task in the export (from Todoist or Google
+Tasks) that is not already present, in one commit, then list them with
+a summary of the rows that were skipped */
+pub fn import_tasks(
+    world: &mut World,
+    text: &str,
+    project: &str,
+) -> fanling_interface::ResponseResult {
+    if text.trim().is_empty() {
+        return show_task_import("");
+    }
+    let mut parsed = match parse(text, project) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            trace(&format!("cannot read tasks: {:?}", e));
+            return show_task_import(&format!("Cannot import the tasks: {}", e));
+        }
+    };
+    let mut known = known_tasks(world)?;
+    let mut imported = ItemListEntryList::from_vec(vec![]);
+    world.begin_batch();
+    let made = make_tasks(world, &mut parsed, &mut known, &mut imported);
+    world.end_batch()?;
+    made?;
+    trace(&format!(
+        "imported {} tasks ({} rows skipped)",
+        imported.num_entries(),
+        parsed.skipped.len()
+    ));
+    let mut resp = World::show_list(&mut imported, "imported")?;
+    let mut summary = format!("Imported {} tasks.", imported.num_entries());
+    if !parsed.skipped.is_empty() {
+        summary.push_str(&format!(
+            " Skipped {} rows ({}).",
+            parsed.skipped.len(),
+            describe(&parsed.skipped)
+        ));
+    }
+    if !parsed.warnings.is_empty() {
+        summary.push_str(&format!(
+            " Not all imported: {}.",
+            describe(&parsed.warnings)
+        ));
+    }
+    resp.show_toast(&summary);
+    #[cfg(test)]
+    {
+        resp.set_test_data("skipped", &format!("{}", parsed.skipped.len()));
+        resp.set_test_data("warnings", &format!("{}", parsed.warnings.len()));
+    }
+    Ok(resp)
+}
+/** make the tasks, while the changes are collected into a single commit */
+fn make_tasks(
+    world: &mut World,
+    parsed: &mut Parsed,
+    known: &mut HashSet<TaskKey>,
+    imported: &mut ItemListEntryList,
+) -> NullResult {
+    let tasks = std::mem::take(&mut parsed.tasks);
+    for (row, task) in &tasks {
+        let namespace = namespace_for(&task.project);
+        let vals = vals_for(world, task)?;
+        /* the time closed as it is stored, to the second */
+        let closed = vals
+            .get("when_closed")
+            .and_then(|w| NaiveDateTime::parse_from_str(w, DATE_TIME_FORMAT).ok());
+        let key = (namespace.clone(), task.name.trim().to_lowercase(), closed);
+        if !known.insert(key) {
+            parsed.skip(*row, "it is already present");
+            continue;
+        }
+        let base = ItemBaseForSerde {
+            ident: "".to_owned(),
+            type_name: "Task".to_owned(),
+            ..ItemBaseForSerde::default()
+        };
+        match world.make_item_in(&namespace, "Task", &base, &vals) {
+            Ok(item_ref) => imported.add(ItemListEntry::from_item(&item_ref.borrow())?),
+            Err(e) => parsed.skip(*row, &format!("it could not be made ({})", e)),
+        }
+    }
+    parsed.skipped.sort_by_key(|p| p.row);
+    Ok(())
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "taskimport {}",
+        Colour::Fixed(0).on(Colour::Fixed(152)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn todoist_csv() -> FLResult<()> {
+        let csv =
+            "TYPE,CONTENT,DESCRIPTION,PRIORITY,INDENT,AUTHOR,RESPONSIBLE,DATE,DATE_LANG,TIMEZONE
+section,Weekly,,,,,,,,
+task,Buy milk @errands,semi-skimmed,4,1,me,,2020-03-14,en,UTC
+note,remember the bags,,,,,,,,
+task,Water the plants,,4,1,me,,every day,en,UTC
+task,,,4,1,me,,,en,UTC
+";
+        let parsed = parse(csv, "Home")?;
+        assert_eq!(2, parsed.tasks.len());
+        let (row, milk) = &parsed.tasks[0];
+        assert_eq!(3, *row);
+        assert_eq!("Buy milk", milk.name);
+        assert_eq!(vec!["errands"], milk.labels);
+        assert_eq!("Home", milk.project);
+        assert_eq!(
+            Some(NaiveDate::from_ymd(2020, 3, 14).and_hms(23, 59, 0)),
+            milk.due
+        );
+        assert_eq!(
+            vec![2, 4, 6],
+            parsed.skipped.iter().map(|p| p.row).collect::<Vec<usize>>()
+        );
+        assert_eq!(5, parsed.warnings[0].row);
+        Ok(())
+    }
+    #[test]
+    fn google_tasks() -> FLResult<()> {
+        let json = r#"{"kind":"tasks#taskLists","items":[{"kind":"tasks#taskList","title":"My Tasks","items":[
+            {"kind":"tasks#task","title":"Post letter","status":"completed","completed":"2020-03-10T09:30:00.000Z"},
+            {"kind":"tasks#task","title":"Old","status":"needsAction","deleted":true},
+            {"kind":"tasks#task","title":"Call","notes":"about the party","status":"needsAction","due":"2020-03-20T00:00:00.000Z"}]}]}"#;
+        let parsed = parse(json, "")?;
+        assert_eq!(2, parsed.tasks.len());
+        assert_eq!(
+            Some(NaiveDate::from_ymd(2020, 3, 10).and_hms(9, 30, 0)),
+            parsed.tasks[0].1.completed
+        );
+        assert_eq!("My Tasks", parsed.tasks[1].1.project);
+        assert_eq!(
+            Some(NaiveDate::from_ymd(2020, 3, 20).and_hms(23, 59, 0)),
+            parsed.tasks[1].1.due
+        );
+        assert_eq!(1, parsed.skipped.len());
+        Ok(())
+    }
+    #[test]
+    fn namespaces() {
+        assert_eq!("shopping", namespace_for("Shopping"));
+        assert_eq!("work-q1-plans", namespace_for("Work: Q1 plans!"));
+        assert_eq!("", namespace_for(""));
+    }
+}
@@ -8,6 +8,7 @@ use crate::fanling_interface::Engine;
 use std::fs;
 use std::path::PathBuf;
 #[cfg(test)]
+mod recipe;
 mod utils;
 #[test]
 fn simple() -> crate::shared::NullResult {
@@ -72,6 +73,28 @@ fn local() -> crate::shared::NullResult {
             field_key: "text".to_string(),
             test_ident: "aaaa-o2".to_string(),
         },
+        LocalTestCase {
+            narr: "contact".to_string(),
+            create_action: utils::create_contact_action("aaa"),
+            expected_text_after_create: "aaaa".to_string(),
+            update_action2: utils::update_contact_action("aaa-o2", "bbb", "bbbb"),
+            expected_text_after_update: "bbbb".to_string(),
+            update_action3: utils::update_contact_action("aaa-o2", "bbb", "cccc"),
+            expected_text_at_end: "ccccbbbb".to_string(),
+            field_key: "notes".to_string(),
+            test_ident: "aaa-o2".to_string(),
+        },
+        LocalTestCase {
+            narr: "snippet".to_string(),
+            create_action: utils::create_snippet_action("aaa"),
+            expected_text_after_create: "aaaa".to_string(),
+            update_action2: utils::update_snippet_action("aaa-o2", "bbb", "bbbb"),
+            expected_text_after_update: "bbbb".to_string(),
+            update_action3: utils::update_snippet_action("aaa-o2", "bbb", "cccc"),
+            expected_text_at_end: "ccccbbbb".to_string(),
+            field_key: "code".to_string(),
+            test_ident: "aaa-o2".to_string(),
+        },
     ];
     for test_case in local_test_cases {
         local_test(&test_case)?
@@ -248,55 +271,2984 @@ fn no_name() -> crate::shared::NullResult {
     Ok(())
 }
 #[test]
-///  tests for task ready including blocking
-fn ready_task() -> crate::shared::NullResult {
-    trace("ready task test: start");
-    const TEST_DIR1: &str = "testfiles5";
-    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-ready");
+/// tests for importing and exporting contacts as vCards
+fn vcard() -> crate::shared::NullResult {
+    trace("vcard test: start");
+    const TEST_DIR1: &str = "testfiles6";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-vcard");
     let options = utils::simple_options(&test_dir, &database_path);
     let mut engine = super::FanlingEngine::new(&options)?;
-    let create1 = utils::create_task_action("t1", "task 1");
-    let resp = engine.execute(&create1)?;
-    let ident1 = resp.get_test_data("ident");
-    utils::check_test_data(&mut engine, &ident1, "ready", "true")?;
-    let create2 = utils::create_task_action("t2", "task 2");
-    let resp = engine.execute(&create2)?;
-    let ident2 = resp.get_test_data("ident");
-    utils::check_test_data(&mut engine, &ident2, "ready", "true")?;
-    let ready = r#"{"a":"ListReady","i":"","t":""}"#;
-    let resp = engine.execute(&ready)?;
+    let ada = engine
+        .execute(&utils::create_contact_action("Ada Lovelace"))?
+        .get_test_data("ident");
+    let log = serde_json::json!({"t":"Contact","i":ada,"a":{"LogInteraction":["Call","about the engine"]}});
+    let resp = engine.execute(&log.to_string())?;
+    assert_eq!("1", resp.get_test_data("interactions"));
+    let (_tag, html) = resp.get_tag(0);
+    assert!(html.contains(r#"href="mailto:someone@example.com""#));
+    assert!(html.contains("about the engine"));
+    let import = serde_json::json!({"t":"Contact","i":"","a":{"ImportVCard":
+        "BEGIN:VCARD\r\nVERSION:3.0\r\nFN:Grace Hopper\r\nEMAIL:grace@example.com\r\nBDAY:1906-12-09\r\n\
+         PHOTO;ENCODING=b;TYPE=PNG:iVBORw0KGgo\r\nEND:VCARD\r\n\
+         BEGIN:VCARD\r\nVERSION:3.0\r\nN:Turing;Alan;;;\r\nEND:VCARD\r\n"}});
+    let resp = engine.execute(&import.to_string())?;
+    assert_eq!("2", resp.get_test_data("count"));
+    /* the photo is attached, and only its path kept with the contact */
+    let world = engine.world.as_ref().unwrap();
+    let mut photos = vec![];
+    for entry in world.search_type("Contact")?.entries {
+        let (_base, values) = world.get_item_parts(&entry.link.ident)?;
+        if let Some(photo) = values.get("photo").and_then(|p| p.as_str()) {
+            photos.push(photo.to_owned());
+        }
+    }
+    assert_eq!(1, photos.len());
+    assert!(photos[0].starts_with("attachments/photos/"));
+    assert!(photos[0].ends_with(".png.base64"));
+    assert_eq!("iVBORw0KGgo", world.attachment(&photos[0])?);
+    let resp = engine.execute(r#"{"a":"ExportVCard","i":"","t":"Contact"}"#)?;
     assert_eq!("3", resp.get_test_data("count"));
-    let block = format!(
-        r#"{{"t":"Task","i":{:?},"a":{{"BlockBy":{:?}}}}}"#,
-        &ident1, &ident2
-    );
-    let unblock = format!(
-        r#"{{"t":"Task","i":{:?},"a":{{"UnblockBy":{:?}}}}}"#,
-        &ident1, &ident2
+    let message = resp.get_test_data("message");
+    let exported = fs::read_to_string(message.trim_start_matches("Exported to "))?;
+    assert!(exported.contains("BDAY:1906-12-09"));
+    assert!(exported.contains("PHOTO;ENCODING=b;TYPE=PNG:iVBORw0KGgo"));
+    let resp = engine.execute(r#"{"a":"Stats","i":"","t":""}"#)?;
+    let created: u32 = resp.get_test_data("created").parse()?;
+    assert!(created >= 3);
+    let export = |chart: &str, format: &str| {
+        format!(
+            r#"{{"a":{{"ExportChart":{{"chart":"{}","format":"{}"}}}},"i":"","t":""}}"#,
+            chart, format
+        )
+    };
+    for chart in &["heatmap", "completed"] {
+        let resp = engine.execute(&export(chart, "Svg"))?;
+        let message = resp.get_test_data("message");
+        let path = message.trim_start_matches("Exported to ");
+        assert!(path.ends_with(".svg"));
+        assert!(fs::read_to_string(path)?.contains("<svg"));
+    }
+    let resp = engine.execute(&export("timeline", "Attachment"))?;
+    let ident = resp.get_test_data("ident");
+    let url = crate::assets::url(&format!(
+        "repo/{}/{}/timeline-{}.svg",
+        crate::bookmark::ATTACHMENTS_DIR,
+        ident,
+        chrono::Local::today()
+            .naive_local()
+            .format(crate::format::ISO_DATE_FORMAT)
+    ));
+    let asset = fanling_interface::Engine::asset(&engine, &url)?.expect("no attachment");
+    assert_eq!("image/svg+xml", asset.content_type);
+    assert!(resp.region("content").unwrap().contains(&url));
+    Ok(())
+}
+#[test]
+///  tests for checklist entry actions
+fn checklist() -> crate::shared::NullResult {
+    trace("checklist test: start");
+    const TEST_DIR1: &str = "testfiles7";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-checklist");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_checklist_action("shopping", r"milk\n[x] eggs"))?;
+    let ident = resp.get_test_data("ident");
+    let action = |a: &str| format!(r#"{{"t":"Checklist","i":"{}","a":{}}}"#, &ident, a);
+    engine.execute(&action(r#"{"AddEntry":"bread"}"#))?;
+    let resp = engine.execute(&action(r#"{"ToggleEntry":0}"#))?;
+    let (tag, html) = resp.get_tag(0);
+    assert_eq!(
+        (fanling_interface::Operation::Replace, "entry-0"),
+        fanling_interface::Operation::from_tag(&tag)
     );
-    let _resp = engine.execute(&block)?;
-    utils::check_test_data(&mut engine, &ident1, "ready", "false")?;
-    let resp = engine.execute(&ready)?;
+    assert!(html.contains("<s>milk</s>"));
+    assert!(resp.get_tags().all(|(tag, _html)| tag != "content"));
+    engine.execute(&action(r#"{"MoveEntryUp":2}"#))?;
+    let resp = engine.execute(&action(r#"{"DeleteEntry":2}"#))?;
+    assert_eq!("[x] milk\n[ ] bread", resp.get_test_data("entries"));
+    assert!(engine.execute(&action(r#"{"ToggleEntry":5}"#)).is_err());
+    Ok(())
+}
+#[test]
+///  tests for events, the agenda and exporting them as iCalendar
+fn event_agenda() -> crate::shared::NullResult {
+    trace("event test: start");
+    const TEST_DIR1: &str = "testfiles20";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-event");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_event_action(
+        "standup",
+        "2020-03-02 09:30",
+        "weekly",
+    ))?;
+    let ident = resp.get_test_data("ident");
+    utils::check_test_data(&mut engine, &ident, "recurrence", "weekly")?;
+    engine.execute(&utils::create_event_action("party", "2020-03-14 19:00", "none"))?;
+    let agenda = |month: bool| {
+        format!(
+            r#"{{"t":"","i":"","a":{{"Agenda":{{"month":{},"date":"2020-03-10"}}}}}}"#,
+            month
+        )
+    };
+    let resp = engine.execute(&agenda(true))?;
+    assert_eq!("6", resp.get_test_data("count"));
+    let resp = engine.execute(&agenda(false))?;
     assert_eq!("2", resp.get_test_data("count"));
-
-    trace("ready test: repo2 - update");
-    let mut engine2 = utils::test_engine(TEST_DIR1, "test-ready", "test-ready2", "p")?;
-    utils::check_test_data(&mut engine2, &ident1, "ready", "false")?;
-    let _resp = engine.execute(&unblock)?;
-    utils::check_test_data(&mut engine, &ident1, "ready", "true")?;
-    let resp = engine.execute(&ready)?;
-    assert_eq!("3", resp.get_test_data("count"));
-    //  TODO more testing: push to server and pull to second server
-    //  TODO more testing: check in second repo for ready status
-    let _resp = engine.execute(&block)?;
-    let close_action = format!(r#"{{"t":"Task","i":"{}","a":"Close"}}"#, ident2);
-    let _response = engine.execute(&close_action)?;
-    utils::check_test_data(&mut engine, &ident1, "ready", "true")?;
-    let resp = engine.execute(&ready)?;
+    let bad = r#"{"t":"Event","i":"","a":{"Create":[{"ident":"","type":"Event"},{"name":"late","start":"2020-03-02","end":"2020-03-01","location":"","recurrence":"none","description":""}]}}"#;
+    let resp = engine.execute(bad)?;
+    assert!(resp.get_tags().any(|(tag, _m)| tag == "end-error"));
+    let resp = engine.execute(r#"{"a":"ExportICal","i":"","t":""}"#)?;
+    let message = resp.get_test_data("message");
+    let path = message.trim_start_matches("Exported to ");
+    let text = fs::read_to_string(path)?;
+    assert_eq!(2, text.matches("BEGIN:VEVENT").count());
+    assert!(text.contains("RRULE:FREQ=WEEKLY"));
+    Ok(())
+}
+#[test]
+///  tests for moving cards on the board
+fn board() -> crate::shared::NullResult {
+    trace("board test: start");
+    const TEST_DIR1: &str = "testfiles8";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-board");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let mut idents = vec![];
+    for name in &["t1", "t2", "t3"] {
+        let resp = engine.execute(&utils::create_task_action(name, "text"))?;
+        idents.push(resp.get_test_data("ident"));
+    }
+    let action = |ident: &str, a: &str| format!(r#"{{"t":"Task","i":"{}","a":{}}}"#, ident, a);
+    engine.execute(&action(&idents[0], r#""MoveCardDown""#))?;
+    let resp = engine.execute(&action(&idents[2], r#"{"SetStatus":"InProgress"}"#))?;
+    assert_eq!(
+        format!("{},{}|{}|", &idents[1], &idents[0], &idents[2]),
+        resp.get_test_data("columns")
+    );
+    engine.execute(&action(&idents[0], r#"{"SetStatus":"Closed"}"#))?;
+    utils::check_test_data(&mut engine, &idents[0], "open", "false")?;
+    let resp = engine.execute(r#"{"a":"Board","i":"","t":""}"#)?;
+    assert_eq!(
+        format!("{}|{}|{}", &idents[1], &idents[2], &idents[0]),
+        resp.get_test_data("columns")
+    );
+    Ok(())
+}
+#[test]
+/// tests for the settings kept with the repository
+fn settings() -> crate::shared::NullResult {
+    trace("settings test: start");
+    const TEST_DIR1: &str = "testfiles10";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-settings");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(
+        r#"{"t":"","i":"","a":{"SaveSettings":{"name":"other","email":"other@example.com","template":"{verb} {type} {name}","append_device":"false"}}}"#,
+    )?;
+    assert_eq!("Settings saved.", resp.get_test_data("message"));
+    engine.execute(&utils::create_simple_action("note"))?;
+    let history = engine.world.as_ref().unwrap().history(1)?;
+    assert_eq!("add Simple note", history[0].message);
+    assert_eq!("other", history[0].author);
+    engine.execute(
+        r#"{"t":"","i":"","a":{"SaveSettings":{"name":"other","email":"other@example.com","template":"{verb} {ident}","append_device":"false","generate_index":"true"}}}"#,
+    )?;
+    engine.execute(&utils::create_simple_action("indexed"))?;
+    let history = engine.world.as_ref().unwrap().history(2)?;
+    assert_eq!("generate README.md generate index.md", history[0].message);
+    assert!(history[1].message.starts_with("add indexed"));
+    Ok(())
+}
+#[test]
+/// tests for focus mode
+fn focus() -> crate::shared::NullResult {
+    trace("focus test: start");
+    const TEST_DIR1: &str = "testfiles11";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-focus");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_task_action("project", "p"))?;
+    let project = resp.get_test_data("ident");
+    let mut children = vec![];
+    for (name, priority) in &[("c1", 5), ("c2", 1)] {
+        let create = format!(
+            r#"{{"t":"Task","i":"","a":{{"Create":[{{"ident":"","type":"Task","parent":"{}"}},{{"name":"{}","text":"","priority":"{}","context":"default_context","deadline":"1970-01-01 00:00:00","show_after_date":"1970-01-01 00:00:00"}}]}}}}"#,
+            &project, name, priority
+        );
+        children.push(engine.execute(&create)?.get_test_data("ident"));
+    }
+    let resp = engine.execute(r#"{"a":"Focus","i":"","t":""}"#)?;
+    assert_eq!(
+        format!("{}:{}", &project, &children[1]),
+        resp.get_test_data("focus")
+    );
+    let action = |ident: &str, a: &str| format!(r#"{{"t":"Task","i":"{}","a":{}}}"#, ident, a);
+    let resp = engine.execute(&action(&project, r#""FocusSkip""#))?;
+    assert_eq!(
+        format!("{}:{}", &project, &children[0]),
+        resp.get_test_data("focus")
+    );
+    let resp = engine.execute(&action(&children[0], r#""FocusDone""#))?;
+    assert_eq!(
+        format!("{}:{}", &project, &children[1]),
+        resp.get_test_data("focus")
+    );
+    utils::check_test_data(&mut engine, &children[0], "open", "false")?;
+    Ok(())
+}
+#[test]
+/// tests for suggesting what to do now
+fn suggest() -> crate::shared::NullResult {
+    trace("suggest test: start");
+    const TEST_DIR1: &str = "testfiles12";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-suggest");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let mut idents = vec![];
+    for (name, priority, effort, energy) in &[
+        ("quick", 10, "10", "Low"),
+        ("long", 1, "120", "High"),
+        ("unknown", 5, "", "Medium"),
+    ] {
+        let create = format!(
+            r#"{{"t":"Task","i":"","a":{{"Create":[{{"ident":"","type":"Task"}},{{"name":"{}","text":"","priority":"{}","effort":"{}","energy":"{}","context":"default_context","deadline":"1970-01-01 00:00:00","show_after_date":"1970-01-01 00:00:00"}}]}}}}"#,
+            name, priority, effort, energy
+        );
+        idents.push(engine.execute(&create)?.get_test_data("ident"));
+    }
+    let suggest = |req: &str| format!(r#"{{"t":"","i":"","a":{{"Suggest":{}}}}}"#, req);
+    let resp = engine.execute(&suggest("{}"))?;
+    assert_eq!(
+        format!("{},{},{}", &idents[1], &idents[2], &idents[0]),
+        resp.get_test_data("suggestions")
+    );
+    let resp = engine.execute(&suggest(r#"{"minutes":30,"energy":"low"}"#))?;
+    assert_eq!(idents[0], resp.get_test_data("suggestions"));
+    let resp = engine.execute(&suggest(r#"{"minutes":30,"energy":"Medium"}"#))?;
+    assert_eq!(
+        format!("{},{}", &idents[2], &idents[0]),
+        resp.get_test_data("suggestions")
+    );
+    utils::check_test_data(&mut engine, &idents[1], "effort", "120")?;
+    Ok(())
+}
+#[test]
+/// tests for the history of an item
+fn item_history() -> crate::shared::NullResult {
+    trace("item history test: start");
+    const TEST_DIR1: &str = "testfiles13";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-history");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_simple_action("note"))?;
+    let ident = resp.get_test_data("ident");
+    engine.execute(&utils::create_simple_action("other note"))?;
+    engine.execute(&utils::update_simple_action(&ident, "note", "bbbb"))?;
+    let resp = engine.execute(&format!(r#"{{"t":"Simple","i":"{}","a":"History"}}"#, &ident))?;
+    assert_eq!("unsigned,unsigned", resp.get_test_data("history"));
+    Ok(())
+}
+#[test]
+/// tests for using template items with variables
+fn template_items() -> crate::shared::NullResult {
+    trace("template items test: start");
+    const TEST_DIR1: &str = "testfiles14";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-template");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let create = r#"{"t":"Simple","i":"","a":{"Create":[{"ident":"","type":"Simple","is_template":true},{"name":"Meet {{who:person}}","text":"On {{when:date}} with {{who}}"}]}}"#;
+    let template = engine.execute(create)?.get_test_data("ident");
+    let use_template = format!(r#"{{"t":"Simple","i":"{}","a":"Instantiate"}}"#, &template);
+    let resp = engine.execute(&use_template)?;
+    assert_eq!("who,when", resp.get_test_data("variables"));
+    let resp = engine.execute(&format!(
+        r#"{{"t":"Simple","i":"{}","a":{{"InstantiateWith":{{"who":"Jo","when":"Friday"}}}}}}"#,
+        &template
+    ))?;
+    let ident = resp.get_test_data("ident");
+    assert_ne!(template, ident);
+    let (base, values) = engine.world.as_ref().unwrap().get_item_parts(&ident)?;
+    assert!(!base.is_template);
+    assert_eq!(Some("Meet Jo"), values.get("name").and_then(|n| n.as_str()));
+    assert_eq!(
+        Some("On Friday with Jo"),
+        values.get("text").and_then(|n| n.as_str())
+    );
+    let (base, _values) = engine.world.as_ref().unwrap().get_item_parts(&template)?;
+    assert!(base.is_template);
+    let create = r#"{"t":"Simple","i":"","a":{"Create":[{"ident":"","type":"Simple","is_template":true},{"name":"Diary {{date}}","text":"Today: {{cursor}}"}]}}"#;
+    let diary = engine.execute(create)?.get_test_data("ident");
+    let resp = engine.execute(r#"{"t":"","i":"","a":"Templates"}"#)?;
     assert_eq!("2", resp.get_test_data("count"));
-    //  TODO more testing: push to server and pull to second server
-    // TODO more testing: check in second repo for ready status
-    let check_data = r#"{"a":"CheckData","i":"","t":""}"#;
-    let _resp = engine.execute(&check_data)?;
+    let resp = engine.execute(&format!(
+        r#"{{"t":"Simple","i":"{}","a":"Instantiate"}}"#,
+        &diary
+    ))?;
+    assert_eq!("text:7", resp.get_test_data("cursor"));
+    let (_base, values) = engine
+        .world
+        .as_ref()
+        .unwrap()
+        .get_item_parts(&resp.get_test_data("ident"))?;
+    let today = crate::FormatOptions::default().format_date(chrono::Local::today().naive_local());
+    assert_eq!(
+        Some(format!("Diary {}", today).as_str()),
+        values.get("name").and_then(|n| n.as_str())
+    );
+    assert_eq!(Some("Today: "), values.get("text").and_then(|n| n.as_str()));
+    Ok(())
+}
+#[test]
+/// tests for having several repositories open
+fn several_repos() -> crate::shared::NullResult {
+    trace("several repositories test: start");
+    const TEST_DIR1: &str = "testfiles15";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-main");
+    let mut options = utils::simple_options(&test_dir, &database_path);
+    let work_dir = format!("{}/test-work", TEST_DIR1);
+    options.repos.others.push(crate::OtherRepoOptions {
+        name: "work".to_owned(),
+        repo_options: taipo_git_control::RepoOptions {
+            path: PathBuf::from(&work_dir).into_boxed_path(),
+            name: "tester".to_string(),
+            email: "tester@example.com".to_string(),
+            write_to_server: true,
+            ..taipo_git_control::RepoOptions::default()
+        },
+        search_options: crate::search::SearchOptions {
+            database_path: format!("{}.db", &work_dir),
+        },
+        read_only: false,
+    });
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_simple_action("main note"))?;
+    let main_ident = resp.get_test_data("ident");
+    let resp = engine.execute(r#"{"t":"","i":"","a":{"SelectRepo":"work"}}"#)?;
+    assert_eq!("work", resp.get_test_data("repo"));
+    let resp = engine.execute(&utils::create_simple_action("work note"))?;
+    let work_ident = resp.get_test_data("ident");
+    assert_eq!("work", engine.world.as_ref().unwrap().repo_name());
+    let show = format!(
+        r#"{{"t":"Simple","i":"{}","a":"Show"}}"#,
+        crate::repos::qualify("main", &main_ident)
+    );
+    engine.execute(&show)?;
+    assert_eq!("main", engine.world.as_ref().unwrap().repo_name());
+    let resp = engine.execute(r#"{"t":"","i":"","a":"Repos"}"#)?;
+    assert_eq!("main", resp.get_test_data("repo"));
+    /* links qualified with the name of a repository */
+    let check = r#"{"t":"","i":"","a":"Check"}"#;
+    let problems = engine.execute(check)?.get_test_data("problems");
+    let resp = engine.execute(&utils::create_simple_action("main links"))?;
+    let linking = resp.get_test_data("ident");
+    engine.execute(&utils::update_simple_action(
+        &linking,
+        "main links",
+        &format!("see [[work:{}]] and [[main:{}]]", work_ident, main_ident),
+    ))?;
+    let resp = engine.execute(&format!(
+        r#"{{"t":"Simple","i":"{}","a":"Show"}}"#,
+        &linking
+    ))?;
+    let to_work = crate::repos::qualify("work", &work_ident);
+    let shown = |ident: &str, label: &str| {
+        let link = format!(r#"i: "{}", a: "Show"}})'>{}"#, ident, label);
+        resp.get_tags().any(|(_tag, html)| html.contains(&link))
+    };
+    assert!(shown(&to_work, "work:"));
+    assert!(shown(&main_ident, "main:"));
+    let resp = engine.execute(r#"{"t":"","i":"","a":"Orphans"}"#)?;
+    let orphans = resp.get_test_data("orphans");
+    assert!(!orphans.split(',').any(|o| o == main_ident || o == linking));
+    let graph = crate::graph::graph(engine.world.as_mut().unwrap())?;
+    let linked = |to: &str| graph.edges.iter().any(|e| e.from == linking && e.to == to);
+    assert!(linked(&to_work));
+    assert!(linked(&main_ident));
+    assert!(graph.nodes.iter().any(|n| n.ident == to_work));
+    /* nor is the link to another repository a link to a missing item */
+    assert_eq!(problems, engine.execute(check)?.get_test_data("problems"));
+    Ok(())
+}
+#[test]
+/// tests for using checklist templates
+fn checklist_template() -> crate::shared::NullResult {
+    trace("checklist template test: start");
+    const TEST_DIR1: &str = "testfiles16";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-checklist-template");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let create = r#"{"t":"Checklist","i":"","a":{"Create":[{"ident":"","type":"Checklist","is_template":true},{"name":"packing","entries":"[x] passport\ncharger"}]}}"#;
+    let template = engine.execute(create)?.get_test_data("ident");
+    let use_template = format!(r#"{{"t":"Checklist","i":"{}","a":"Instantiate"}}"#, &template);
+    let resp = engine.execute(&use_template)?;
+    let copy = resp.get_test_data("ident");
+    assert_ne!(template, copy);
+    assert_eq!("[ ] passport\n[ ] charger", resp.get_test_data("entries"));
+    assert_eq!(template, resp.get_test_data("template"));
+    engine.execute(&format!(
+        r#"{{"t":"Checklist","i":"{}","a":{{"ToggleEntry":0}}}}"#,
+        &copy
+    ))?;
+    engine.execute(&use_template)?;
+    utils::check_test_data(&mut engine, &template, "uses", "1")?;
+    utils::check_test_data(&mut engine, &template, "skipped", "passport:0,charger:1")?;
+    Ok(())
+}
+#[test]
+/// tests for encrypted repositories
+fn encrypted_repo() -> crate::shared::NullResult {
+    trace("encrypted repository test: start");
+    const TEST_DIR1: &str = "testfiles17";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-encrypted");
+    let mut options = utils::simple_options(&test_dir, &database_path);
+    options.encryption.enabled = true;
+    let ident = {
+        let mut engine = super::FanlingEngine::new(&options)?;
+        let resp = engine.execute(r#"{"t":"","i":"","a":"Start"}"#)?;
+        assert_eq!(Some("passphrase"), resp.get_prompt());
+        engine.execute(r#"{"t":"","i":"","a":{"Unlock":"secret"}}"#)?;
+        let resp = engine.execute(&utils::create_simple_action("private note"))?;
+        resp.get_test_data("ident")
+    };
+    options.encryption.passphrase = Some("wrong".to_owned());
+    assert!(super::FanlingEngine::new(&options).is_err());
+    options.encryption.passphrase = None;
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(r#"{"t":"","i":"","a":{"Unlock":"wrong"}}"#)?;
+    assert!(resp.is_error());
+    engine.execute(r#"{"t":"","i":"","a":{"Unlock":"secret"}}"#)?;
+    let (_base, values) = engine.world.as_ref().unwrap().get_item_parts(&ident)?;
+    assert_eq!(
+        Some("private note"),
+        values.get("name").and_then(|n| n.as_str())
+    );
+    let resp = engine.execute(r#"{"t":"","i":"","a":"ListAll"}"#)?;
+    assert_ne!("0", resp.get_test_data("count"));
+    Ok(())
+}
+#[test]
+/// tests for making tasks from the action items of a meeting
+fn meeting_actions() -> crate::shared::NullResult {
+    trace("meeting actions test: start");
+    const TEST_DIR1: &str = "testfiles18";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-meeting");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_contact_action("Jo Bloggs"))?;
+    let contact = resp.get_test_data("ident");
+    let resp = engine.execute(&utils::create_meeting_action(
+        "planning",
+        &contact,
+        "Went well.\\n- [ ] send report @jo\\n- [ ] book room",
+    ))?;
+    let ident = resp.get_test_data("ident");
+    utils::check_test_data(&mut engine, &ident, "attendees", &contact)?;
+    let extract = format!(r#"{{"t":"Meeting","i":"{}","a":"ExtractActions"}}"#, &ident);
+    let resp = engine.execute(&extract)?;
+    let tasks: Vec<String> = resp
+        .get_test_data("tasks")
+        .split(',')
+        .map(str::to_owned)
+        .collect();
+    assert_eq!(2, tasks.len());
+    utils::check_test_data(&mut engine, &tasks[0], "assignee", &contact)?;
+    utils::check_test_data(&mut engine, &tasks[1], "assignee", "")?;
+    let resp = engine.execute(&extract)?;
+    assert_eq!("", resp.get_test_data("tasks"));
+    Ok(())
+}
+#[test]
+/// tests for private items
+fn private_item() -> crate::shared::NullResult {
+    trace("private item test: start");
+    const TEST_DIR1: &str = "testfiles19";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-private");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let create = r#"{"t":"Simple","i":"","a":{"Create":[{"ident":"","type":"Simple","is_private":true,"passphrase":"pw"},{"name":"diary","text":"dear diary"}]}}"#;
+    let ident = {
+        let mut engine = super::FanlingEngine::new(&options)?;
+        let resp = engine.execute(create)?;
+        let ident = resp.get_test_data("ident");
+        let resp = engine.execute(&format!(r#"{{"t":"","i":"{}","a":"Show"}}"#, &ident))?;
+        assert_eq!(None, resp.get_prompt());
+        ident
+    };
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let (_base, values) = engine.world.as_ref().unwrap().get_item_parts(&ident)?;
+    assert!(crate::private::is_sealed(&values));
+    assert_eq!(Some("diary"), values.get("name").and_then(|n| n.as_str()));
+    assert_eq!(None, values.get("text"));
+    let show = format!(r#"{{"t":"","i":"{}","a":"Show"}}"#, &ident);
+    let resp = engine.execute(&show)?;
+    assert_eq!(Some("item-passphrase"), resp.get_prompt());
+    assert_eq!("true", resp.get_test_data("locked"));
+    let unlock = |p: &str| format!(r#"{{"t":"Simple","i":"{}","a":{{"UnlockItem":"{}"}}}}"#, &ident, p);
+    let resp = engine.execute(&unlock("wrong"))?;
+    assert!(resp.is_error());
+    let resp = engine.execute(&unlock("pw"))?;
+    assert_eq!(None, resp.get_prompt());
+    let resp = engine.execute(&show)?;
+    assert_eq!(None, resp.get_prompt());
+    let resp = engine.execute(r#"{"t":"","i":"","a":"ListAll"}"#)?;
+    assert_ne!("0", resp.get_test_data("count"));
+    Ok(())
+}
+#[test]
+///  tests for reminders of the birthdays of contacts
+fn contact_reminders() -> crate::shared::NullResult {
+    use chrono::Datelike;
+    trace("reminders test: start");
+    const TEST_DIR1: &str = "testfiles21";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-reminders");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let today = chrono::Local::today().naive_local();
+    /* a multiple of 4 years ago, so that it works on 29 February */
+    let born = crate::reminder::in_year(today, today.year() - 32);
+    let create = serde_json::json!({"t":"Contact","i":"","a":{"Create":[{"ident":"","type":"Contact"},
+        {"name":"Ada Lovelace","emails":"","phones":"","address":"","notes":"",
+         "birthday":born.format(crate::format::ISO_DATE_FORMAT).to_string(),"anniversary":""}]}});
+    engine.execute(&create.to_string())?;
+    let resp = engine.execute(r#"{"t":"","i":"","a":"Start"}"#)?;
+    let notifications: Vec<String> = resp.get_notifications().cloned().collect();
+    assert_eq!(
+        vec!["Today: Ada Lovelace's birthday (32)".to_owned()],
+        notifications
+    );
+    let agenda = r#"{"t":"","i":"","a":{"Agenda":{"month":false,"date":""}}}"#;
+    let resp = engine.execute(agenda)?;
+    assert_eq!("1", resp.get_test_data("count"));
+    Ok(())
+}
+#[test]
+///  tests for previewing Markdown while editing
+fn preview() -> crate::shared::NullResult {
+    trace("preview test: start");
+    const TEST_DIR1: &str = "testfiles22";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-preview");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(r##"{"t":"","i":"","a":{"Preview":"# Plan\n\nsee [[list-a1]]"}}"##)?;
+    let (tag, html) = resp.get_tag(0);
+    assert_eq!("preview", tag);
+    assert!(html.contains(r#"<h1 id="heading-plan">Plan</h1>"#));
+    assert!(html.contains(r#"id="list-a1""#));
+    Ok(())
+}
+#[test]
+///  tests for drafts of unsaved edits
+fn drafts() -> crate::shared::NullResult {
+    trace("drafts test: start");
+    const TEST_DIR1: &str = "testfiles23";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-drafts");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let save = r#"{"t":"Contact","i":"","a":{"SaveDraft":[{"ident":"","type":"Contact"},
+        {"name":"Half Typed","emails":"","phones":"","address":"","notes":"so far"}]}}"#;
+    let resp = engine.execute(save)?;
+    let (tag, status) = resp.get_tag(0);
+    assert_eq!("draft", tag);
+    assert!(status.starts_with("Draft saved"));
+    /* the draft is kept locally, as if the app had been killed */
+    let dir = std::path::Path::new(&database_path)
+        .parent()
+        .unwrap_or_else(|| std::path::Path::new("."));
+    let kept = crate::draft::Drafts::open(dir, true);
+    assert_eq!(
+        Some("Half Typed"),
+        kept.get("new Contact").map(|d| d.vals["name"].as_str())
+    );
+    let resp = engine.execute(r#"{"t":"Contact","i":"","a":"New"}"#)?;
+    assert_eq!("restored", resp.get_test_data("draft"));
+    assert!(resp.get_tags().any(|(_tag, html)| html.contains("Half Typed")));
+    engine.execute(&utils::create_contact_action("Half Typed"))?;
+    let resp = engine.execute(r#"{"t":"Contact","i":"","a":"New"}"#)?;
+    assert!(resp.get_tags().all(|(_tag, html)| !html.contains("Half Typed")));
+    assert!(crate::draft::Drafts::open(dir, true).get("new Contact").is_none());
+    Ok(())
+}
+#[test]
+///  tests for warnings that the user can confirm
+fn confirm_warnings() -> crate::shared::NullResult {
+    trace("confirm warnings test: start");
+    const TEST_DIR1: &str = "testfiles24";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-confirm");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let create = |confirmed: bool| {
+        serde_json::json!({"t":"Task","i":"","a":{"Create":[{"ident":"","type":"Task","confirmed":confirmed},
+            {"name":"late","text":"","priority":"10","context":"default_context",
+             "deadline":"2001-02-03 00:00:00","show_after_date":"1970-01-01 00:00:00"}]}})
+        .to_string()
+    };
+    let resp = engine.execute(&create(false))?;
+    assert!(resp.is_confirm_required());
+    assert_eq!("The deadline is in the past.", resp.get_test_data("unconfirmed"));
+    let resp = engine.execute(r#"{"t":"","i":"","a":"ListAll"}"#)?;
+    let before = resp.get_test_data("count");
+    let resp = engine.execute(&create(true))?;
+    assert!(!resp.is_confirm_required());
+    let ident = resp.get_test_data("ident");
+    let resp = engine.execute(r#"{"t":"","i":"","a":"ListAll"}"#)?;
+    assert_ne!(before, resp.get_test_data("count"));
+    /* keeping the same overdue deadline needs no confirmation */
+    let update = serde_json::json!({"t":"Task","i":ident,"a":{"Update":[{"ident":ident,"type":"Task"},
+        {"name":"later","text":"","priority":"10","context":"default_context",
+         "deadline":"2001-02-03 00:00:00","show_after_date":"1970-01-01 00:00:00"}]}});
+    let resp = engine.execute(&update.to_string())?;
+    assert!(!resp.is_confirm_required());
+    Ok(())
+}
+#[test]
+///  tests for exporting and importing encrypted bundles
+fn bundles() -> crate::shared::NullResult {
+    trace("bundles test: start");
+    const TEST_DIR1: &str = "testfiles25";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-bundle");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_task_action("t1", "task 1"))?;
+    let ident = resp.get_test_data("ident");
+    engine.execute(&utils::create_simple_action("s1"))?;
+    let export = serde_json::json!({"t":"","i":"","a":{"ExportBundle":{"types":["Task"],"passphrase":"secret"}}});
+    let resp = engine.execute(&export.to_string())?;
+    let exported = resp.get_test_data("count");
+    assert_ne!("0", exported);
+    let text = std::fs::read_to_string(resp.get_test_data("path"))?;
+    assert!(!text.contains("task 1"));
+    /* import into another repository */
+    let test_dir2 = format!("{}/test-bundle-2", TEST_DIR1);
+    let database_path2 = format!("{}.db", test_dir2);
+    let options2 = utils::simple_options(&test_dir2, &database_path2);
+    let mut engine2 = super::FanlingEngine::new(&options2)?;
+    let import = |passphrase: &str| {
+        serde_json::json!({"t":"","i":"","a":{"ImportBundle":{"text":text,"passphrase":passphrase}}})
+            .to_string()
+    };
+    let resp = engine2.execute(&import("wrong"))?;
+    assert!(resp.get_test_data("message").starts_with("Cannot import"));
+    let resp = engine2.execute(&import("secret"))?;
+    assert_ne!("0", resp.get_test_data("count"));
+    utils::check_test_data(&mut engine2, &ident, "ready", "true")?;
+    /* importing again adds nothing */
+    let resp = engine2.execute(&import("secret"))?;
+    assert_eq!("0", resp.get_test_data("count"));
+    assert_eq!(exported, resp.get_test_data("skipped"));
+    Ok(())
+}
+#[test]
+///  tests for rebuilding a search database that has been corrupted
+fn corrupt_cache() -> crate::shared::NullResult {
+    trace("corrupt cache test: start");
+    const TEST_DIR1: &str = "testfiles26";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-corrupt");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let list_all = r#"{"t":"","i":"","a":"ListAll"}"#;
+    let (ident, count) = {
+        let mut engine = super::FanlingEngine::new(&options)?;
+        let resp = engine.execute(&utils::create_simple_action("kept"))?;
+        let ident = resp.get_test_data("ident");
+        let count = engine.execute(list_all)?.get_test_data("count");
+        engine.execute(r#"{"a":"Shutdown","i":"","t":""}"#)?;
+        (ident, count)
+    };
+    std::fs::write(&database_path, "not a database")?;
+    let mut engine = super::FanlingEngine::new(&options)?;
+    assert_eq!(count, engine.execute(list_all)?.get_test_data("count"));
+    utils::check_test_data(&mut engine, &ident, "ident", &ident)?;
+    Ok(())
+}
+#[test]
+///  tests for showing lists a page at a time
+fn list_pages() -> crate::shared::NullResult {
+    trace("list pages test: start");
+    const TEST_DIR1: &str = "testfiles27";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-pages");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    for name in &["p1", "p2", "p3"] {
+        engine.execute(&utils::create_simple_action(name))?;
+    }
+    let resp = engine.execute(r#"{"t":"","i":"","a":"ListAll"}"#)?;
+    let count: usize = resp.get_test_data("count").parse()?;
+    assert_eq!("", resp.get_test_data("next"));
+    let mut offset = 0;
+    let mut shown = 0;
+    loop {
+        let more = serde_json::json!({"t":"","i":"","a":{"ListMore":{"list":"all","offset":offset,"limit":1}}});
+        let resp = engine.execute(&more.to_string())?;
+        let page: usize = resp.get_test_data("page").parse()?;
+        assert!(page >= 1);
+        shown += page;
+        match resp.get_test_data("next").as_str() {
+            "" => break,
+            next => offset = next.parse()?,
+        }
+    }
+    assert_eq!(count, shown);
+    Ok(())
+}
+#[test]
+///  tests for rebuilding a search database when the repository has been changed without it
+fn stale_index() -> crate::shared::NullResult {
+    trace("stale index test: start");
+    const TEST_DIR1: &str = "testfiles28";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-stale");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let list_all = r#"{"t":"","i":"","a":"ListAll"}"#;
+    let count: usize = {
+        let mut engine = super::FanlingEngine::new(&options)?;
+        engine.execute(&utils::create_simple_action("first"))?;
+        engine.execute(list_all)?.get_test_data("count").parse()?
+    };
+    {
+        /* change the repository using another search database */
+        let mut other_options = utils::simple_options(&test_dir, &format!("{}-other.db", test_dir));
+        other_options.uniq_pfx = "b".to_owned();
+        let mut engine = super::FanlingEngine::new(&other_options)?;
+        engine.execute(&utils::create_simple_action("second"))?;
+    }
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(list_all)?;
+    assert_eq!(format!("{}", count + 1), resp.get_test_data("count"));
+    Ok(())
+}
+#[test]
+/// tests for the warnings given before saving items that seem to contain secrets
+fn secrets() -> crate::shared::NullResult {
+    trace("secrets test: start");
+    const TEST_DIR1: &str = "testfiles29";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-secrets");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let create = |confirmed: bool| {
+        serde_json::json!({"t":"Simple","i":"","a":{"Create":[{"ident":"","type":"Simple","confirmed":confirmed},
+            {"name":"wifi","text":"password: hunter2"}]}})
+        .to_string()
+    };
+    let resp = engine.execute(&create(false))?;
+    assert!(resp.is_confirm_required());
+    assert_eq!(
+        "The text seems to contain a password, which would be saved unencrypted.",
+        resp.get_test_data("unconfirmed")
+    );
+    let resp = engine.execute(&create(true))?;
+    assert!(!resp.is_confirm_required());
+    let resp = engine.execute(
+        r#"{"t":"","i":"","a":{"SaveSettings":{"name":"other","email":"other@example.com","template":"{verb} {ident}","check_secrets":"true","secret_patterns":"("}}}"#,
+    )?;
+    assert!(resp.get_tags().any(|(tag, _m)| tag == "secret-patterns-error"));
+    engine.execute(
+        r#"{"t":"","i":"","a":{"SaveSettings":{"name":"other","email":"other@example.com","template":"{verb} {ident}","check_secrets":"false"}}}"#,
+    )?;
+    let resp = engine.execute(&create(false))?;
+    assert!(!resp.is_confirm_required());
+    Ok(())
+}
+#[test]
+/// tests for the actions that can be done to an item now
+fn available_actions() -> crate::shared::NullResult {
+    trace("available actions test: start");
+    const TEST_DIR1: &str = "testfiles30";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-available-actions");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let ident = engine
+        .execute(&utils::create_task_action("chore", "sweep"))?
+        .get_test_data("ident");
+    let action = |a: &str| format!(r#"{{"t":"Task","i":"{}","a":"{}"}}"#, &ident, a);
+    let pdf = if cfg!(feature = "pdf") {
+        ",Export PDF"
+    } else {
+        ""
+    };
+    let resp = engine.execute(&action("AvailableActions"))?;
+    assert_eq!(
+        format!(
+            "Edit,Close,Add task child,Start timer,Delete,History,Clone,Print,Rename,Copy,Cut,Paste,Copy markdown,Export HTML{},Pin,Favourite",
+            pdf
+        ),
+        resp.get_test_data("actions")
+    );
+    let (tag, json) = resp.get_tag(0);
+    assert_eq!("actions", tag);
+    assert!(json.contains(r#""action":"Close""#));
+    engine.execute(&action("Close"))?;
+    let resp = engine.execute(&action("AvailableActions"))?;
+    assert_eq!(
+        format!(
+            "Edit,Re-open,Add task child,Delete,History,Clone,Print,Rename,Copy,Cut,Paste,Copy markdown,Export HTML{},Pin,Favourite",
+            pdf
+        ),
+        resp.get_test_data("actions")
+    );
+    let (_tag, html) = engine.execute(&action("Show"))?.get_tag(0);
+    assert!(html.contains("&quot;Reopen&quot;"));
+    Ok(())
+}
+#[test]
+/// tests for translating the text shown to the user
+fn translations() -> crate::shared::NullResult {
+    trace("translations test: start");
+    const TEST_DIR1: &str = "testfiles31";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-translations");
+    let mut options = utils::simple_options(&test_dir, &database_path);
+    options.format_options.locale = "fr".to_owned();
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_simple_action(""))?;
+    assert!(resp
+        .get_tags()
+        .any(|(tag, m)| tag == "name-error" && m == "Le nom ne doit pas être vide."));
+    let resp = engine.execute(&utils::create_simple_action("page"))?;
+    let ident = resp.get_test_data("ident");
+    let (_tag, html) = resp.get_tag(0);
+    assert!(html.contains("Mettre à jour"));
+    let show = format!(r#"{{"t":"Simple","i":"{}","a":"Show"}}"#, &ident);
+    let (_tag, html) = engine.execute(&show)?.get_tag(0);
+    assert!(html.contains("Modifier"));
+    assert!(html.contains("Parent :"));
+    Ok(())
+}
+#[test]
+/// tests for the dashboard, which updates several regions of the page at once
+fn dashboard() -> crate::shared::NullResult {
+    trace("dashboard test: start");
+    const TEST_DIR1: &str = "testfiles32";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-dashboard");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let tomorrow = chrono::Local::today().naive_local().succ();
+    engine.execute(&utils::create_event_action(
+        "dentist",
+        &format!("{} 10:00", tomorrow.format("%Y-%m-%d")),
+        "none",
+    ))?;
+    engine.execute(&utils::create_task_action("chore", "sweep"))?;
+    let resp = engine.execute(r#"{"a":"Dashboard","i":"","t":""}"#)?;
+    assert_eq!("1", resp.get_test_data("events"));
+    for region in &[
+        fanling_interface::region::CONTENT,
+        fanling_interface::region::SIDEBAR,
+        fanling_interface::region::STATUS,
+        fanling_interface::region::NOTIFICATIONS,
+        fanling_interface::region::ALWAYS,
+    ] {
+        assert!(resp.region(region).is_some(), "no {}", region);
+    }
+    assert!(resp.region("sidebar").unwrap().contains("dentist"));
+    let ready = resp.get_test_data("ready");
+    assert!(resp
+        .region("status")
+        .unwrap()
+        .contains(&format!("{} ready", ready)));
+    Ok(())
+}
+#[test]
+/// tests for quick capture
+fn quick_add() -> crate::shared::NullResult {
+    trace("quick add test: start");
+    const TEST_DIR1: &str = "testfiles33";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-quick-add");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.quick_add("buy milk #errands !tomorrow")?;
+    assert_eq!("Task", resp.get_test_data("type"));
+    let milk = resp.get_test_data("ident");
+    assert!(resp
+        .region("toast:")
+        .unwrap()
+        .contains("Added task 'buy milk' (errands, due "));
+    utils::check_test_data(&mut engine, &milk, "open", "true")?;
+    let resp = engine.execute(r#"{"a":"ListAll","i":"","t":""}"#)?;
+    let all = resp.get_test_data("count");
+    /* the context made for the first is used for the second */
+    let resp = engine.quick_add("buy eggs #Errands")?;
+    assert_ne!(milk, resp.get_test_data("ident"));
+    let resp = engine.quick_add("note: read more")?;
+    assert_eq!("Simple", resp.get_test_data("type"));
+    let resp = engine.quick_add(" #errands ")?;
+    assert_eq!("Nothing to add.", resp.region("toast:").unwrap());
+    let resp = engine.execute(r#"{"a":"ListAll","i":"","t":""}"#)?;
+    assert_eq!(
+        format!("{}", all.parse::<usize>()? + 2),
+        resp.get_test_data("count")
+    );
+    Ok(())
+}
+#[test]
+/// tests for items made from shared content
+fn capture_shared() -> crate::shared::NullResult {
+    trace("capture shared test: start");
+    const TEST_DIR1: &str = "testfiles34";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-capture-shared");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    /* the page cannot be fetched, but the bookmark is still made */
+    let resp = engine.capture_shared("Rust", "A language.", "https://rust.example.invalid/")?;
+    assert_eq!("Saved 'Rust'", resp.region("toast:").unwrap());
+    assert_eq!("Bookmark", resp.get_test_data("type"));
+    assert!(resp.region("content").unwrap().contains("https://rust.example.invalid/"));
+    let rust = resp.get_test_data("ident");
+    let resp = engine.capture_shared("", "remember this\nand this", "")?;
+    assert_eq!("Saved 'remember this'", resp.region("toast:").unwrap());
+    assert_eq!("Simple", resp.get_test_data("type"));
+    assert_ne!(rust, resp.get_test_data("ident"));
+    let resp = engine.capture_shared(" ", "", "")?;
+    assert_eq!("Nothing was shared.", resp.region("toast:").unwrap());
+    Ok(())
+}
+#[test]
+/// tests for prefetching the items likely to be shown next
+fn prefetch() -> crate::shared::NullResult {
+    trace("prefetch test: start");
+    const TEST_DIR1: &str = "testfiles35";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-prefetch");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_simple_action("target"))?;
+    let target = resp.get_test_data("ident");
+    let resp = engine.execute(&utils::create_simple_action("source"))?;
+    let source = resp.get_test_data("ident");
+    engine.execute(&utils::update_simple_action(
+        &source,
+        "source",
+        &format!("see [[{}]] and [[missing-item]]", target),
+    ))?;
+    let show = |ident: &str| format!(r#"{{"t":"","i":"{}","a":"Show"}}"#, ident);
+    let resp = engine.execute(&show(&source))?;
+    assert_eq!("false", resp.get_test_data("prefetched"));
+    let resp = engine.execute(r#"{"t":"","i":"","a":"Prefetch"}"#)?;
+    assert_eq!("1", resp.get_test_data("count"));
+    let resp = engine.execute(&show(&target))?;
+    assert_eq!("true", resp.get_test_data("prefetched"));
+    assert!(resp.region("content").unwrap().contains("target"));
+    engine.execute(&show(&source))?;
+    let resp = engine.execute(r#"{"t":"","i":"","a":"Prefetch"}"#)?;
+    assert_eq!("0", resp.get_test_data("count"));
+    /* after an edit, what was prefetched is not used */
+    engine.execute(&utils::update_simple_action(&target, "target", "changed"))?;
+    let resp = engine.execute(&show(&target))?;
+    assert_eq!("false", resp.get_test_data("prefetched"));
+    assert!(resp.region("content").unwrap().contains("changed"));
+    Ok(())
+}
+#[test]
+/// tests for bookmarks
+fn bookmarks() -> crate::shared::NullResult {
+    trace("bookmarks test: start");
+    const TEST_DIR1: &str = "testfiles36";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-bookmarks");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_bookmark_action("ftp://example.invalid/", ""))?;
+    assert!(resp.get_tags().any(|(tag, _m)| tag == "url-error"));
+    let resp = engine.execute(&utils::create_bookmark_action(
+        "https://example.invalid/article",
+        " reading, rust ",
+    ))?;
+    let ident = resp.get_test_data("ident");
+    utils::check_test_data(&mut engine, &ident, "tags", "reading,rust")?;
+    /* the page cannot be fetched, so there is still no snapshot */
+    let resp = engine.execute(&format!(
+        r#"{{"t":"Bookmark","i":"{}","a":"Refetch"}}"#,
+        ident
+    ))?;
+    assert_eq!("false", resp.get_test_data("fetched"));
+    assert!(resp.region("content").unwrap().contains("none yet"));
+    /* nor can it be archived */
+    let action = |a: &str| format!(r#"{{"t":"Bookmark","i":"{}","a":{}}}"#, ident, a);
+    let resp = engine.execute(&action(r#""ArchivePage""#))?;
+    assert_eq!("false", resp.get_test_data("fetched"));
+    assert_eq!("0", resp.get_test_data("archives"));
+    let path = format!("attachments/{}/20200102-030405.html", ident);
+    engine
+        .world
+        .as_mut()
+        .unwrap()
+        .add_attachment(&path, "<p>kept</p>")?;
+    let show = serde_json::json!({ "ShowArchivedPage": path }).to_string();
+    let resp = engine.execute(&action(&show))?;
+    assert_eq!("<p>kept</p>", resp.get_test_data("archive"));
+    Ok(())
+}
+#[test]
+///  tests for warm starts from a snapshot of the items in use
+fn warm_start() -> crate::shared::NullResult {
+    trace("warm start test: start");
+    const TEST_DIR1: &str = "testfiles37";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-warm");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let snapshot = crate::warm::path(&database_path);
+    let ident = {
+        let mut engine = super::FanlingEngine::new(&options)?;
+        let resp = engine.execute(&utils::create_simple_action("warm"))?;
+        let ident = resp.get_test_data("ident");
+        engine.execute(r#"{"a":"Shutdown","i":"","t":""}"#)?;
+        ident
+    };
+    assert!(snapshot.exists());
+    {
+        let mut engine = super::FanlingEngine::new(&options)?;
+        assert!(engine.world.as_ref().unwrap().warm_started() > 0);
+        assert!(!snapshot.exists());
+        utils::check_test_data(&mut engine, &ident, "ident", &ident)?;
+    }
+    /* a snapshot from another commit is not used */
+    crate::integrity::write(&snapshot, br#"{"head":"0000","items":{}}"#)?;
+    let mut engine = super::FanlingEngine::new(&options)?;
+    assert_eq!(0, engine.world.as_ref().unwrap().warm_started());
+    assert!(!snapshot.exists());
+    utils::check_test_data(&mut engine, &ident, "ident", &ident)?;
+    Ok(())
+}
+#[test]
+///  tests for the graph of links and the orphan items
+fn graph() -> crate::shared::NullResult {
+    trace("graph test: start");
+    const TEST_DIR1: &str = "testfiles38";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-graph");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let mut idents = vec![];
+    for name in &["linking", "linked", "alone"] {
+        let resp = engine.execute(&utils::create_simple_action(name))?;
+        idents.push(resp.get_test_data("ident"));
+    }
+    engine.execute(&utils::update_simple_action(
+        &idents[0],
+        "linking",
+        &format!("see [[{}]] and [[missing]]", idents[1]),
+    ))?;
+    let resp = engine.execute(r#"{"t":"","i":"","a":"Orphans"}"#)?;
+    let orphans: Vec<String> = resp
+        .get_test_data("orphans")
+        .split(',')
+        .map(|o| o.to_owned())
+        .collect();
+    assert!(!orphans.contains(&idents[0]));
+    assert!(!orphans.contains(&idents[1]));
+    assert!(orphans.contains(&idents[2]));
+    engine.execute(r#"{"t":"","i":"","a":"ExportGraph"}"#)?;
+    let dir = engine.world.as_ref().unwrap().export_dir().to_path_buf();
+    let dot = std::fs::read_dir(&dir)?
+        .filter_map(|e| e.ok())
+        .find(|e| e.file_name().to_string_lossy().ends_with(".dot"))
+        .expect("no DOT file");
+    let dot = std::fs::read_to_string(dot.path())?;
+    assert!(dot.contains(&format!("\"{}\" -> \"{}\";", idents[0], idents[1])));
+    assert!(!dot.contains("missing"));
+    Ok(())
+}
+#[test]
+///  tests for view sessions, each with its own current item
+fn sessions() -> crate::shared::NullResult {
+    trace("sessions test: start");
+    const TEST_DIR1: &str = "testfiles39";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-sessions");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let mut idents = vec![];
+    for name in &["first", "second", "other"] {
+        let resp = engine.execute(&utils::create_simple_action(name))?;
+        idents.push(resp.get_test_data("ident"));
+    }
+    let in_session = |action: &str, ident: &str, session: &str| {
+        format!(
+            r#"{{"t":"","i":"{}","a":"{}","s":"{}"}}"#,
+            ident, action, session
+        )
+    };
+    engine.execute(&in_session("Show", &idents[0], "w1"))?;
+    engine.execute(&in_session("Show", &idents[1], "w1"))?;
+    engine.execute(&in_session("Show", &idents[2], "w2"))?;
+    let resp = engine.execute(&in_session("Back", "", "w1"))?;
+    assert_eq!(idents[0], resp.get_test_data("ident"));
+    let resp = engine.execute(&in_session("Back", "", "w1"))?;
+    assert_eq!(
+        "There is nothing to go back to.",
+        resp.region("toast:").unwrap()
+    );
+    let resp = engine.execute(&in_session("ShowCurrent", "", "w2"))?;
+    assert_eq!(idents[2], resp.get_test_data("ident"));
+    engine.execute(&in_session("CloseSession", "", "w2"))?;
+    /* with no current item, the start page (the ready list) is shown */
+    let resp = engine.execute(&in_session("ShowCurrent", "", "w2"))?;
+    assert!(!resp.get_test_data("count").is_empty());
+    Ok(())
+}
+#[test]
+///  tests for checking the items for problems, and fixing them
+fn check_items() -> crate::shared::NullResult {
+    trace("check items test: start");
+    const TEST_DIR1: &str = "testfiles40";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-check");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let check = r#"{"t":"","i":"","a":"Check"}"#;
+    let problems = |resp: &fanling_interface::Response| -> usize {
+        resp.get_test_data("problems").parse().unwrap_or(0)
+    };
+    let before = problems(&engine.execute(check)?);
+    let resp = engine.execute(&utils::create_simple_action("linking"))?;
+    let ident = resp.get_test_data("ident");
+    engine.execute(&utils::update_simple_action(
+        &ident,
+        "linking",
+        "see [[nowhere1]] and [[nowhere1|again]]",
+    ))?;
+    /* the second link to the same item is the same problem */
+    let resp = engine.execute(check)?;
+    assert_eq!(before + 1, problems(&resp));
+    let resp =
+        engine.execute(r#"{"t":"","i":"","a":{"FixProblem":{"CreateMissing":"nowhere1"}}}"#)?;
+    assert_eq!(before, problems(&resp));
+    utils::check_test_data(&mut engine, "nowhere1", "ident", "nowhere1")?;
+    Ok(())
+}
+#[test]
+///  tests for the registry of the kinds of item
+fn kinds() -> crate::shared::NullResult {
+    trace("kinds test: start");
+    const TEST_DIR1: &str = "testfiles41";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-kinds");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(r#"{"t":"","i":"","a":"Kinds"}"#)?;
+    let kinds: serde_json::Value = serde_json::from_str(resp.region("kinds").unwrap())?;
+    assert_eq!(crate::kinds().len(), kinds.as_array().unwrap().len());
+    let html = engine.initial_html()?;
+    for kind in crate::kinds().iter().filter(|k| k.creatable) {
+        assert!(html.contains(&format!(r#"doAction("New", "{}", "")"#, kind.name)));
+    }
+    Ok(())
+}
+#[test]
+///  tests for items of a type unknown to this version
+fn foreign_items() -> crate::shared::NullResult {
+    trace("foreign items test: start");
+    const TEST_DIR1: &str = "testfiles42";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-foreign");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let check = r#"{"t":"","i":"","a":"Check"}"#;
+    let before: usize = engine.execute(check)?.get_test_data("problems").parse()?;
+    let yaml = "ident: soup1\ntype: Recipe\nname: Soup\nservings: 4\n";
+    let (base, values) = crate::item::split_data_parts(yaml.as_bytes())?;
+    {
+        let world = engine.world.as_mut().unwrap();
+        world.begin_batch();
+        assert!(world.import_item(&base, &values)?.is_some());
+        world.end_batch()?;
+    }
+    utils::check_test_data(&mut engine, "soup1", "type", "Recipe")?;
+    let resp = engine.execute(r#"{"t":"Recipe","i":"soup1","a":"Edit"}"#)?;
+    assert_eq!(
+        "Items of type 'Recipe' cannot be changed by this version.",
+        resp.region("toast:").unwrap()
+    );
+    let after: usize = engine.execute(check)?.get_test_data("problems").parse()?;
+    assert_eq!(before + 1, after);
+    Ok(())
+}
+#[test]
+///  tests for upgrading items written in an old format
+fn migrate_items() -> crate::shared::NullResult {
+    trace("migrate items test: start");
+    const TEST_DIR1: &str = "testfiles43";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-migrate");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_task_action("t1", "task 1"))?;
+    let ident = resp.get_test_data("ident");
+    let resp = engine.execute(r#"{"t":"","i":"","a":"MigrateAll"}"#)?;
+    assert_eq!("0", resp.get_test_data("migrated"));
+    let stored = engine.world.as_ref().unwrap().stored_values()?;
+    let (_, values) = stored.iter().find(|(i, _)| *i == ident).unwrap();
+    assert_eq!(
+        crate::migrate::SCHEMA_VERSION,
+        crate::migrate::schema_version(values)
+    );
+    Ok(())
+}
+#[test]
+///  tests for the differences between two versions of an item
+fn diffs() -> crate::shared::NullResult {
+    trace("diffs test: start");
+    const TEST_DIR1: &str = "testfiles44";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-diffs");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_simple_action("versions"))?;
+    let ident = resp.get_test_data("ident");
+    engine.execute(&utils::update_simple_action(
+        &ident,
+        "versions",
+        "the quick brown fox",
+    ))?;
+    engine.execute(&utils::update_simple_action(
+        &ident,
+        "versions",
+        "the slow brown fox",
+    ))?;
+    let history = engine
+        .world
+        .as_ref()
+        .unwrap()
+        .item_history(&ident, 10)?;
+    assert!(history.len() >= 2);
+    let diff = serde_json::json!({"t":"Simple","i":ident,"a":{"Diff":{"from":history[1].id,"to":history[0].id}}});
+    let resp = engine.execute(&diff.to_string())?;
+    assert_eq!(
+        "the <ins>slow</ins><del>quick</del> brown fox",
+        resp.get_test_data("diff")
+    );
+    Ok(())
+}
+#[test]
+///  tests for articles saved to read later
+fn articles() -> crate::shared::NullResult {
+    trace("articles test: start");
+    const TEST_DIR1: &str = "testfiles45";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-articles");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let save = |url: &str| serde_json::json!({"t":"","i":"","a":{"SaveArticle":url}}).to_string();
+    let resp = engine.execute(&save("ftp://example.invalid/"))?;
+    assert_eq!(
+        "URL must start with http:// or https://.",
+        resp.region("toast:").unwrap()
+    );
+    /* the page cannot be fetched, so no article is saved */
+    let resp = engine.execute(&save("https://example.invalid/article"))?;
+    assert_eq!("false", resp.get_test_data("fetched"));
+    engine.execute(&utils::create_bookmark_action(
+        "https://example.invalid/later",
+        "rust, read-later",
+    ))?;
+    engine.execute(&utils::create_bookmark_action(
+        "https://example.invalid/other",
+        "rust",
+    ))?;
+    let resp = engine.execute(r#"{"t":"","i":"","a":"ListReading"}"#)?;
+    assert_eq!("1", resp.get_test_data("count"));
+    Ok(())
+}
+#[test]
+///  tests for restoring items to earlier versions
+fn restore_version() -> crate::shared::NullResult {
+    trace("restore test: start");
+    const TEST_DIR1: &str = "testfiles46";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-restore");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_simple_action("versions"))?;
+    let ident = resp.get_test_data("ident");
+    engine.execute(&utils::update_simple_action(&ident, "versions", "first"))?;
+    engine.execute(&utils::update_simple_action(&ident, "versions", "second"))?;
+    let history = engine
+        .world
+        .as_ref()
+        .unwrap()
+        .item_history(&ident, 10)?;
+    let restore = |confirmed: bool| {
+        serde_json::json!({"t":"Simple","i":ident,"a":{"Restore":{"id":history[1].id,"confirmed":confirmed}}})
+            .to_string()
+    };
+    let resp = engine.execute(&restore(false))?;
+    assert!(resp.is_confirm_required());
+    let resp = engine.execute(&restore(true))?;
+    assert_eq!(
+        "The earlier version has been restored.",
+        resp.region("toast:").unwrap()
+    );
+    let restored = engine.world.as_ref().unwrap().item_history(&ident, 10)?;
+    assert_eq!(history.len() + 1, restored.len());
+    let diff = serde_json::json!({"t":"Simple","i":ident,"a":{"Diff":{"from":history[1].id,"to":restored[0].id}}});
+    let resp = engine.execute(&diff.to_string())?;
+    assert_eq!("first", resp.get_test_data("diff"));
+    Ok(())
+}
+#[test]
+///  tests for highlights in the snapshots of bookmarks
+fn highlights() -> crate::shared::NullResult {
+    trace("highlights test: start");
+    const TEST_DIR1: &str = "testfiles47";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-highlights");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let create = serde_json::json!({"t":"Bookmark","i":"","a":{"Create":[{"ident":"","type":"Bookmark"},
+        {"url":"https://example.invalid/article","name":"Article","tags":"read-later",
+         "snapshot":"The first paragraph.\n\nThe second paragraph."}]}});
+    let resp = engine.execute(&create.to_string())?;
+    let ident = resp.get_test_data("ident");
+    let highlight = |quote: &str| {
+        serde_json::json!({"t":"Bookmark","i":ident,"a":{"Highlight":{"quote":quote,"note":"worth noting"}}})
+            .to_string()
+    };
+    let resp = engine.execute(&highlight("second paragraph"))?;
+    assert_eq!("1", resp.get_test_data("highlights"));
+    let resp = engine.execute(&highlight("a third paragraph"))?;
+    assert_eq!("1", resp.get_test_data("highlights"));
+    let resp = engine.execute(r#"{"t":"","i":"","a":"ListHighlights"}"#)?;
+    assert_eq!("1", resp.get_test_data("count"));
+    let remove = serde_json::json!({"t":"Bookmark","i":ident,"a":{"RemoveHighlight":0}});
+    let resp = engine.execute(&remove.to_string())?;
+    assert_eq!("0", resp.get_test_data("highlights"));
+    Ok(())
+}
+#[test]
+///  tests for timing tasks
+fn time_tracking() -> crate::shared::NullResult {
+    trace("time tracking test: start");
+    const TEST_DIR1: &str = "testfiles48";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-timing");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_task_action("first", "timed"))?;
+    let first = resp.get_test_data("ident");
+    let resp = engine.execute(&utils::create_task_action("second", "timed"))?;
+    let second = resp.get_test_data("ident");
+    let timer = |ident: &str, action: &str| {
+        serde_json::json!({"t":"Task","i":ident,"a":action}).to_string()
+    };
+    let resp = engine.execute(&timer(&first, "StartTimer"))?;
+    assert_eq!("running", resp.get_test_data("timer"));
+    assert!(resp
+        .get_tags()
+        .any(|(tag, html)| tag == "timer" && html.contains(&first)));
+    /* starting another timer stops the first */
+    let resp = engine.execute(&timer(&second, "StartTimer"))?;
+    assert_eq!("running", resp.get_test_data("timer"));
+    let resp = engine.execute(&timer(&first, "Show"))?;
+    assert_eq!("stopped", resp.get_test_data("timer"));
+    assert_eq!("1", resp.get_test_data("timings"));
+    let resp = engine.execute(&timer(&second, "StopTimer"))?;
+    assert_eq!("stopped", resp.get_test_data("timer"));
+    assert!(resp
+        .get_tags()
+        .any(|(tag, html)| tag == "timer" && !html.contains("StopTimer")));
+    let resp = engine.execute(r#"{"t":"","i":"","a":{"TimeReport":""}}"#)?;
+    assert_eq!("0:00", resp.get_test_data("total"));
+    Ok(())
+}
+#[test]
+///  tests for references and citations
+fn references() -> crate::shared::NullResult {
+    trace("references test: start");
+    const TEST_DIR1: &str = "testfiles49";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-references");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let bibtex = "@article{turing1936,\n  title = {On Computable Numbers},\n  author = {Turing, Alan},\n  year = 1936,\n  journal = {Proceedings of the London Mathematical Society}\n}\n\
+         @book{knuth:taocp, title = \"The Art of Computer Programming\", author = \"Knuth, Donald E.\", year = 1968}";
+    let import = serde_json::json!({"t":"Reference","i":"","a":{"ImportBibTeX": bibtex}});
+    let resp = engine.execute(&import.to_string())?;
+    assert_eq!("2", resp.get_test_data("count"));
+    /* references already there are not imported again */
+    let resp = engine.execute(&import.to_string())?;
+    assert_eq!("0", resp.get_test_data("count"));
+    let resp = engine.execute(r#"{"t":"","i":"","a":{"Cite":"knuth:taocp"}}"#)?;
+    assert_eq!("knuth:taocp", resp.get_test_data("key"));
+    let resp = engine.execute(r#"{"t":"","i":"","a":{"Cite":"nobody2000"}}"#)?;
+    assert!(resp.region("toast:").unwrap().contains("nobody2000"));
+    let resp = engine.execute(r#"{"a":"ExportBibTeX","i":"","t":"Reference"}"#)?;
+    assert_eq!("2", resp.get_test_data("count"));
+    let (_tag, html) = resp.get_tag(0);
+    assert!(html.contains("@article{turing1936,"));
+    Ok(())
+}
+#[test]
+///  tests for habits
+fn habits() -> crate::shared::NullResult {
+    trace("habits test: start");
+    const TEST_DIR1: &str = "testfiles50";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-habits");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    for per_week in &[0, 8] {
+        let resp = engine.execute(&utils::create_habit_action("stretch", *per_week))?;
+        assert!(resp.get_tags().any(|(tag, _m)| tag == "cadence-error"));
+    }
+    let resp = engine.execute(&utils::create_habit_action("stretch", 7))?;
+    let ident = resp.get_test_data("ident");
+    assert_eq!("0", resp.get_test_data("streak"));
+    let done = serde_json::json!({"t":"Habit","i":&ident,"a":"DoneToday"}).to_string();
+    let resp = engine.execute(&done)?;
+    assert_eq!("1", resp.get_test_data("streak"));
+    assert_eq!("100", resp.get_test_data("rate"));
+    /* done once a day at most */
+    let resp = engine.execute(&done)?;
+    assert_eq!("1", resp.get_test_data("done"));
+    let resp = engine.execute(
+        &serde_json::json!({"t":"Habit","i":&ident,"a":"AvailableActions"}).to_string(),
+    )?;
+    assert!(!resp.get_test_data("actions").contains("Done today"));
+    Ok(())
+}
+#[test]
+///  tests for retention policies
+fn retention() -> crate::shared::NullResult {
+    trace("retention test: start");
+    const TEST_DIR1: &str = "testfiles51";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-retention");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let settings = |retention: &str| {
+        serde_json::json!({"t":"","i":"","a":{"SaveSettings":{"name":"other","email":"other@example.com",
+            "template":"{verb} {ident}","retention":retention}}})
+        .to_string()
+    };
+    let resp = engine.execute(&settings("tasks older than a while: archive"))?;
+    assert!(resp.get_tags().any(|(tag, _m)| tag == "retention-error"));
+    engine.execute(&settings("tasks untagged for 0 days: flag"))?;
+    let resp = engine.execute(&utils::create_task_action("old", "an old task"))?;
+    let ident = resp.get_test_data("ident");
+    engine.execute(&utils::create_simple_action("kept"))?;
+    let resp = engine.execute(r#"{"t":"","i":"","a":"Check"}"#)?;
+    assert_eq!("1", resp.get_test_data("retention"));
+    let resp = engine.execute(r#"{"t":"","i":"","a":"Retention"}"#)?;
+    assert_eq!("1", resp.get_test_data("due"));
+    let apply = serde_json::json!({"t":"","i":"","a":{"ApplyRetention":[&ident]}});
+    let resp = engine.execute(&apply.to_string())?;
+    assert_eq!("1 item has been archived or flagged.", resp.get_test_data("message"));
+    assert_eq!("0", resp.get_test_data("due"));
+    let resp = engine.execute(&serde_json::json!({"t":"Task","i":&ident,"a":"Show"}).to_string())?;
+    let (_tag, html) = resp.get_tag(0);
+    assert!(html.contains("Flagged for review by a retention policy."));
+    Ok(())
+}
+#[test]
+///  tests for the notifications scheduled for the main program
+fn scheduled_reminders() -> crate::shared::NullResult {
+    trace("scheduled reminders test: start");
+    const TEST_DIR1: &str = "testfiles52";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-scheduled-reminders");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let tomorrow = chrono::Local::today().naive_local() + chrono::Duration::days(1);
+    let create = |name: &str, deadline: &str, remind: &str| {
+        serde_json::json!({"t":"Task","i":"","a":{"Create":[{"ident":"","type":"Task"},
+            {"name":name,"text":"","priority":"10","context":"default_context",
+            "deadline":deadline,"remind":remind,"show_after_date":"1970-01-01 00:00:00"}]}})
+        .to_string()
+    };
+    let remind = tomorrow.and_hms(10, 30, 0).format("%Y-%m-%d %H:%M:%S");
+    let none = "1970-01-01 00:00:00";
+    let resp = engine.execute(&create("call the bank", none, &remind.to_string()))?;
+    let ident = resp.get_test_data("ident");
+    let deadline = tomorrow.format("%Y-%m-%d 00:00:00");
+    engine.execute(&create("file the report", &deadline.to_string(), ""))?;
+    engine.execute(&create("past", none, "2000-01-01 10:00:00"))?;
+    let reminders = engine.next_reminders(5)?;
+    let texts: Vec<&str> = reminders.iter().map(|r| r.text.as_str()).collect();
+    assert_eq!(vec!["Deadline: file the report", "call the bank"], texts);
+    assert_eq!(ident, reminders[1].ident);
+    assert_eq!(
+        5400,
+        reminders[1].when - reminders[0].when,
+        "the deadline without a time is in the morning"
+    );
+    assert_eq!(1, engine.next_reminders(1)?.len());
+    Ok(())
+}
+#[test]
+///  tests for rolling back an operation that leaves items that are not valid
+fn rollback() -> crate::shared::NullResult {
+    trace("rollback test: start");
+    const TEST_DIR1: &str = "testfiles53";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-rollback");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_simple_action("merged"))?;
+    let ident = resp.get_test_data("ident");
+    let world = engine.world.as_mut().unwrap();
+    let before = world.head_id()?;
+    assert!(crate::rollback::check_since(world, "merge", &before)?.is_none());
+    /* as if a merge had lost a field that is needed */
+    let (_base, mut values) = world.get_item_parts(&ident)?;
+    values
+        .as_mapping_mut()
+        .unwrap()
+        .remove(&serde_yaml::Value::from("name"));
+    let path = world.path_from_ident(&ident);
+    world.add_attachment(&path, &serde_yaml::to_string(&values)?)?;
+    assert_ne!(before, world.head_id()?);
+    let report = crate::rollback::check_since(world, "merge", &before)?.unwrap();
+    assert_eq!(1, report.invalid.len());
+    assert_eq!(ident, report.invalid[0].ident);
+    assert_eq!(before, world.head_id()?);
+    let (_base, values) = world.get_item_parts(&ident)?;
+    assert_eq!(Some("merged"), values.get("name").and_then(|n| n.as_str()));
+    let resp = crate::rollback::show(&report)?;
+    assert_eq!("1", resp.get_test_data("invalid"));
+    assert_eq!(1, resp.get_notifications().count());
+    Ok(())
+}
+#[test]
+///  tests for sorting the list views and moving items in the manual order
+fn list_order() -> crate::shared::NullResult {
+    trace("list order test: start");
+    const TEST_DIR1: &str = "testfiles54";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-order");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let mut idents = vec![];
+    for name in &["b1", "a2", "c3"] {
+        let resp = engine.execute(&utils::create_simple_action(name))?;
+        idents.push(resp.get_test_data("ident"));
+    }
+    let position = |resp: &fanling_interface::Response, ident: &String| {
+        resp.get_test_data("idents")
+            .split(',')
+            .position(|i| i == ident)
+            .unwrap()
+    };
+    let sort = |order: &str| {
+        serde_json::json!({"t":"","i":"","a":{"SortList":{"list":"all","order":order}}}).to_string()
+    };
+    let resp = engine.execute(&sort("name"))?;
+    assert!(position(&resp, &idents[1]) < position(&resp, &idents[0]));
+    assert!(position(&resp, &idents[0]) < position(&resp, &idents[2]));
+    let resp = engine.execute(&sort("manual"))?;
+    /* the last of them has the other two before it */
+    let last = idents
+        .iter()
+        .max_by_key(|i| position(&resp, *i))
+        .unwrap()
+        .clone();
+    let mut before = position(&resp, &last);
+    for _ in 0..2 {
+        let up = serde_json::json!({"t":"","i":"","a":{"MoveInList":{"list":"all","ident":&last,"up":true}}});
+        let resp = engine.execute(&up.to_string())?;
+        let after = position(&resp, &last);
+        assert!(after < before);
+        before = after;
+    }
+    let world = engine.world.as_mut().unwrap();
+    let (base, _values) = world.get_item_parts(&last)?;
+    assert!(!base.sort.is_empty());
+    let resp = engine.execute(r#"{"t":"","i":"","a":"ListAll"}"#)?;
+    assert_eq!(before, position(&resp, &last));
+    Ok(())
+}
+#[test]
+///  tests for pinned and favourite items on the home page
+fn home_page() -> crate::shared::NullResult {
+    trace("home page test: start");
+    const TEST_DIR1: &str = "testfiles55";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-home");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_simple_action("pinned note"))?;
+    let ident = resp.get_test_data("ident");
+    engine.execute(&utils::create_task_action("open task", "to do"))?;
+    let action = |a: &str| serde_json::json!({"t":"Simple","i":&ident,"a":a}).to_string();
+    engine.execute(&action("TogglePin"))?;
+    engine.execute(&action("ToggleFavourite"))?;
+    let resp = engine.execute(&action("AvailableActions"))?;
+    let actions = resp.get_test_data("actions");
+    assert!(actions.contains("Unpin") && actions.contains("Unfavourite"));
+    /* the edit form does not include the flags, so saving it keeps them */
+    engine.execute(&utils::update_simple_action(&ident, "pinned note", "bbbb"))?;
+    let resp = engine.execute(r#"{"t":"","i":"","a":"Start"}"#)?;
+    assert_eq!(ident, resp.get_test_data("pinned"));
+    assert_eq!(ident, resp.get_test_data("favourites"));
+    assert!(resp.get_test_data("recent").split(',').any(|i| i == ident));
+    assert_eq!("1", resp.get_test_data("open-tasks"));
+    assert_eq!("0", resp.get_test_data("overdue"));
+    engine.execute(&action("TogglePin"))?;
+    let resp = engine.execute(r#"{"t":"","i":"","a":"Start"}"#)?;
+    assert_eq!("", resp.get_test_data("pinned"));
+    assert_eq!(ident, resp.get_test_data("favourites"));
+    Ok(())
+}
+#[test]
+///  tests for the soak test mode (briefly)
+fn soak() -> crate::shared::NullResult {
+    trace("soak test: start");
+    const TEST_DIR1: &str = "testfiles56";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-soak");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let soak = crate::SoakOptions {
+        duration: std::time::Duration::from_secs(600),
+        max_actions: 60,
+        sync_every: 0,
+        seed: 7,
+    };
+    let report = crate::run_soak(&options, &soak)?;
+    assert_eq!(None, report.failure);
+    assert_eq!(60, report.actions);
+    assert_eq!(3, report.checks);
+    assert_eq!(7, report.seed);
+    Ok(())
+}
+#[test]
+///  tests for the schemes for idents, and for repairing idents that collide
+fn ident_schemes() -> crate::shared::NullResult {
+    trace("ident schemes test: start");
+    const TEST_DIR1: &str = "testfiles57";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-idents");
+    let mut options = utils::simple_options(&test_dir, &database_path);
+    options.ident_scheme = crate::IdentScheme::Ulid;
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_simple_action("ulid"))?;
+    let ident = resp.get_test_data("ident");
+    assert_eq!(26, ident.len());
+    let resp = engine.execute(&utils::create_simple_action("another"))?;
+    assert_ne!(ident, resp.get_test_data("ident"));
+    /* a file from another source claims the ident of the first item */
+    let yaml = format!("ident: {}\ntype: Simple\nname: Clash\ntext: x\n", &ident);
+    engine
+        .world
+        .as_mut()
+        .unwrap()
+        .add_attachment("clash1.page", &yaml)?;
+    engine.execute(r#"{"t":"","i":"","a":"GetAll"}"#)?;
+    utils::check_test_data(&mut engine, &ident, "ident", &ident)?;
+    let resp = engine.execute(r#"{"t":"","i":"","a":"Start"}"#)?;
+    assert!(resp.get_notifications().any(|n| n.contains("collide")));
+    let check = r#"{"t":"","i":"","a":"Check"}"#;
+    let resp = engine.execute(check)?;
+    let before: usize = resp.get_test_data("problems").parse()?;
+    assert!(resp.get_test_data("fixes").parse::<usize>()? >= 1);
+    let resp = engine.execute(r#"{"t":"","i":"","a":{"FixProblem":{"NewIdent":"clash1"}}}"#)?;
+    assert_eq!(before - 1, resp.get_test_data("problems").parse::<usize>()?);
+    let world = engine.world.as_mut().unwrap();
+    assert!(world.collisions().is_empty());
+    assert!(!world.has_item("clash1")?);
+    let names: Vec<String> = world
+        .search_all()?
+        .entries
+        .into_iter()
+        .map(|e| e.link.ident)
+        .filter(|i| *i != ident && i.len() == 26)
+        .collect();
+    assert_eq!(2, names.len());
+    Ok(())
+}
+#[test]
+///  tests for going back and forward, and for the recent items (kept when shutting down)
+fn navigation_history() -> crate::shared::NullResult {
+    trace("navigation history test: start");
+    const TEST_DIR1: &str = "testfiles58";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-history");
+    let mut options = utils::simple_options(&test_dir, &database_path);
+    options.keep_history = true;
+    let recent = {
+        let mut engine = super::FanlingEngine::new(&options)?;
+        let mut idents = vec![];
+        for name in &["first", "second", "third"] {
+            let resp = engine.execute(&utils::create_simple_action(name))?;
+            idents.push(resp.get_test_data("ident"));
+        }
+        for ident in &idents {
+            engine.execute(&format!(r#"{{"t":"","i":"{}","a":"Show"}}"#, ident))?;
+        }
+        let action = |a: &str| format!(r#"{{"t":"","i":"","a":"{}"}}"#, a);
+        let resp = engine.execute(&action("Back"))?;
+        assert_eq!(idents[1], resp.get_test_data("ident"));
+        let resp = engine.execute(&action("Back"))?;
+        assert_eq!(idents[0], resp.get_test_data("ident"));
+        let resp = engine.execute(&action("Forward"))?;
+        assert_eq!(idents[1], resp.get_test_data("ident"));
+        let resp = engine.execute(&action("Forward"))?;
+        assert_eq!(idents[2], resp.get_test_data("ident"));
+        let resp = engine.execute(&action("Forward"))?;
+        assert_eq!(
+            "There is nothing to go forward to.",
+            resp.region("toast:").unwrap()
+        );
+        /* showing another item after going back forgets the items gone back from */
+        engine.execute(&action("Back"))?;
+        engine.execute(&format!(r#"{{"t":"","i":"{}","a":"Show"}}"#, &idents[0]))?;
+        let resp = engine.execute(&action("Forward"))?;
+        assert!(resp.region("toast:").is_some());
+        let resp = engine.execute(&action("Recent"))?;
+        let expected = format!("{},{},{}", &idents[0], &idents[2], &idents[1]);
+        assert_eq!(expected, resp.get_test_data("recent"));
+        engine.execute(r#"{"a":"Shutdown","i":"","t":""}"#)?;
+        expected
+    };
+    assert!(crate::session::history_path(&database_path).exists());
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(r#"{"t":"","i":"","a":"Recent"}"#)?;
+    assert_eq!(recent, resp.get_test_data("recent"));
+    Ok(())
+}
+#[test]
+///  tests for renaming items, with links to the old idents still working
+fn rename_items() -> crate::shared::NullResult {
+    trace("rename items test: start");
+    const TEST_DIR1: &str = "testfiles59";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-rename");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let target = engine
+        .execute(&utils::create_simple_action("target"))?
+        .get_test_data("ident");
+    let linker = engine
+        .execute(&utils::create_simple_action("linker"))?
+        .get_test_data("ident");
+    let text = format!("see [[{}]]", &target);
+    engine.execute(&utils::update_simple_action(&linker, "linker", &text))?;
+    let rename = |ident: &str, new_ident: &str, rewrite_links: bool| {
+        serde_json::json!({"t":"","i":"","a":{"Rename":{
+            "ident":ident,"new_ident":new_ident,"rewrite_links":rewrite_links}}})
+        .to_string()
+    };
+    let resp = engine.execute(&rename(&target, "renamed-target", false))?;
+    assert_eq!("0", resp.get_test_data("changed"));
+    /* the old ident goes to the item, both when shown and in links */
+    utils::check_test_data(&mut engine, &target, "ident", "renamed-target")?;
+    let show = format!(r#"{{"t":"","i":"{}","a":"Show"}}"#, &linker);
+    let (_tag, html) = engine.execute(&show)?.get_tag(0);
+    assert!(html.contains(r#"i: "renamed-target", a: "Show""#));
+    let resp = engine.execute(&rename("renamed-target", "final-target", true))?;
+    assert_eq!("1", resp.get_test_data("changed"));
+    let (_base, values) = engine
+        .world
+        .as_mut()
+        .unwrap()
+        .get_item_parts(&linker)?;
+    assert_eq!(
+        Some("see [[final-target]]"),
+        values["text"].as_str()
+    );
+    utils::check_test_data(&mut engine, &target, "ident", "final-target")?;
+    let resp = engine.execute(r#"{"t":"","i":"final-target","a":"ShowRename"}"#)?;
+    assert_eq!(
+        format!("{},renamed-target", &target),
+        resp.get_test_data("aliases")
+    );
+    /* an ident that is already used is refused */
+    let resp = engine.execute(&rename("final-target", &linker, true))?;
+    assert!(resp.get_tags().any(|(tag, _m)| tag == "new-ident-error"));
+    let check = r#"{"t":"","i":"","a":"Check"}"#;
+    let before: usize = engine.execute(check)?.get_test_data("problems").parse()?;
+    engine.execute(&utils::update_simple_action(&linker, "linker", &text))?;
+    let after: usize = engine.execute(check)?.get_test_data("problems").parse()?;
+    assert_eq!(before, after);
+    Ok(())
+}
+#[test]
+/// tests for items in namespaces, stored in directories
+fn namespaces() -> crate::shared::NullResult {
+    trace("namespaces test: start");
+    const TEST_DIR1: &str = "testfiles60";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-namespaces");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let roadmap = engine
+        .execute(&utils::create_simple_action("roadmap"))?
+        .get_test_data("ident");
+    let notes = engine
+        .execute(&utils::create_simple_action("notes"))?
+        .get_test_data("ident");
+    let rename = |ident: &str, new_ident: &str| {
+        serde_json::json!({"t":"","i":"","a":{"Rename":{
+            "ident":ident,"new_ident":new_ident,"rewrite_links":false}}})
+        .to_string()
+    };
+    engine.execute(&rename(&roadmap, "projects/fanling/roadmap"))?;
+    engine.execute(&rename(&notes, "projects/notes"))?;
+    let resp = engine.execute(&rename("projects/notes", "attachments/notes"))?;
+    assert!(resp.get_tags().any(|(tag, _m)| tag == "new-ident-error"));
+    let namespace = |namespace: &str| {
+        serde_json::json!({"t":"","i":"","a":{"Namespace":namespace}}).to_string()
+    };
+    let resp = engine.execute(&namespace(""))?;
+    assert!(resp.get_test_data("namespaces").contains("projects:2"));
+    let resp = engine.execute(&namespace("projects"))?;
+    assert_eq!("projects/fanling:1", resp.get_test_data("namespaces"));
+    assert_eq!("projects/notes", resp.get_test_data("items"));
+    let resp = engine.execute(&namespace("projects/fanling"))?;
+    assert_eq!("projects/fanling/roadmap", resp.get_test_data("items"));
+    let list = |list: &str| {
+        serde_json::json!({"t":"","i":"","a":{"ListMore":{"list":list,"offset":0,"limit":10}}})
+            .to_string()
+    };
+    assert_eq!("2", engine.execute(&list("all@projects"))?.get_test_data("count"));
+    assert_eq!(
+        "1",
+        engine
+            .execute(&list("all@projects/fanling"))?
+            .get_test_data("count")
+    );
+    /* the items are read back from their directories */
+    engine.execute(r#"{"t":"","i":"","a":"GetAll"}"#)?;
+    utils::check_test_data(&mut engine, "projects/fanling/roadmap", "ident", "projects/fanling/roadmap")?;
+    assert_eq!("2", engine.execute(&list("all@projects"))?.get_test_data("count"));
+    Ok(())
+}
+#[test]
+/// tests for cutting, copying and pasting between items
+fn clipboard() -> crate::shared::NullResult {
+    trace("clipboard test: start");
+    const TEST_DIR1: &str = "testfiles61";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-clipboard");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let from = engine
+        .execute(&utils::create_checklist_action("from", r"a\nb\n[x] c\nd"))?
+        .get_test_data("ident");
+    let to = engine
+        .execute(&utils::create_checklist_action("to", "x"))?
+        .get_test_data("ident");
+    let note = engine
+        .execute(&utils::create_simple_action("note"))?
+        .get_test_data("ident");
+    let clip = |ident: &str, field: &str, blocks: &[usize], cut: bool| {
+        serde_json::json!({"t":"","i":"","a":{"Clip":{
+            "ident":ident,"field":field,"blocks":blocks,"cut":cut}}})
+        .to_string()
+    };
+    let paste = |ident: &str, field: &str, at: Option<usize>| {
+        serde_json::json!({"t":"","i":"","a":{"Paste":{"ident":ident,"field":field,"at":at}}})
+            .to_string()
+    };
+    let resp = engine.execute(&paste(&to, "entries", None))?;
+    assert_eq!("There is nothing to paste.", resp.region("toast:").unwrap());
+    let resp = engine.execute(&clip(&from, "entries", &[2, 0, 3], true))?;
+    assert_eq!("3", resp.get_test_data("clipped"));
+    /* nothing is removed until the blocks are pasted */
+    utils::check_test_data(&mut engine, &from, "entries", "[ ] a\n[ ] b\n[x] c\n[ ] d")?;
+    let resp = engine.execute(&paste(&to, "entries", Some(0)))?;
+    assert_eq!("3", resp.get_test_data("pasted"));
+    utils::check_test_data(&mut engine, &to, "entries", "[ ] a\n[x] c\n[ ] d\n[ ] x")?;
+    utils::check_test_data(&mut engine, &from, "entries", "[ ] b")?;
+    /* a cut is pasted only once */
+    let resp = engine.execute(&paste(&to, "entries", None))?;
+    assert_eq!("There is nothing to paste.", resp.region("toast:").unwrap());
+    /* entries copied into a text are lines of it */
+    engine.execute(&clip(&to, "entries", &[1], false))?;
+    engine.execute(&paste(&note, "", None))?;
+    let (_base, values) = engine.world.as_ref().unwrap().get_item_parts(&note)?;
+    assert!(values["text"].as_str().unwrap_or("").ends_with("\nc"));
+    /* cutting a whole item removes it when pasted */
+    engine.execute(&clip(&from, "", &[], true))?;
+    engine.execute(&paste(&to, "", None))?;
+    utils::check_test_data(&mut engine, &to, "entries", "[ ] a\n[x] c\n[ ] d\n[ ] x\n[ ] b")?;
+    assert!(!engine.world.as_ref().unwrap().has_item(&from)?);
+    Ok(())
+}
+#[test]
+/// tests for the sync status and the changes waiting to push
+fn sync_status() -> crate::shared::NullResult {
+    trace("sync status test: start");
+    const TEST_DIR1: &str = "testfiles62";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-sync");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_simple_action("note"))?;
+    let status = resp.get_sync_status().expect("no sync status");
+    /* the test repository has no remote, so nothing is waiting */
+    assert!(!status.has_remote);
+    assert_eq!(0, status.ahead);
+    assert_eq!(None, status.last_sync);
+    assert_eq!("", status.badge());
+    let resp = engine.execute(r#"{"t":"","i":"","a":"Unpushed"}"#)?;
+    assert_eq!("0", resp.get_test_data("waiting"));
+    assert!(resp.get_sync_status().is_some());
+    Ok(())
+}
+#[test]
+/// tests for loading only some kinds of item, with stubs for the rest
+fn sparse() -> crate::shared::NullResult {
+    trace("sparse test: start");
+    const TEST_DIR1: &str = "testfiles63";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-sparse");
+    let mut options = utils::simple_options(&test_dir, &database_path);
+    let (task, note) = {
+        let mut engine = super::FanlingEngine::new(&options)?;
+        let task = engine
+            .execute(&utils::create_task_action("t1", "task 1"))?
+            .get_test_data("ident");
+        let note = engine
+            .execute(&utils::create_simple_action("s1"))?
+            .get_test_data("ident");
+        engine.execute(r#"{"a":"Shutdown","i":"","t":""}"#)?;
+        (task, note)
+    };
+    /* loading all the items again, but only the tasks */
+    std::fs::remove_file(&database_path)?;
+    options.sparse_kinds = vec!["Task".to_owned()];
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let descr = |engine: &super::FanlingEngine, ident: &str| -> crate::shared::FLResult<String> {
+        let entries = engine.world.as_ref().unwrap().search_all()?.entries;
+        Ok(entries
+            .into_iter()
+            .find(|e| e.link.ident == ident)
+            .map(|e| e.descr)
+            .unwrap_or_default())
+    };
+    assert!(descr(&engine, &task)?.contains("t1"));
+    assert!(!descr(&engine, &task)?.starts_with("☁"));
+    assert_eq!("☁ s1", descr(&engine, &note)?);
+    /* the stub is replaced when the item is needed */
+    utils::check_test_data(&mut engine, &note, "ident", &note)?;
+    assert_eq!("s1", descr(&engine, &note)?);
+    Ok(())
+}
+#[test]
+/// tests for the remotes, with how the sync with each went
+fn remotes() -> crate::shared::NullResult {
+    trace("remotes test: start");
+    const TEST_DIR1: &str = "testfiles64";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-remotes");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(r#"{"t":"","i":"","a":"Remotes"}"#)?;
+    assert_eq!("", resp.get_test_data("remotes"));
+    let resp = engine.execute(
+        r#"{"t":"","i":"","a":{"SaveRemote":{"name":"bad name","url":"x","on_save":true,"pull":true}}}"#,
+    )?;
+    assert!(resp.region("remote-error").is_some());
+    /* a remote that cannot be reached fails without stopping the others */
+    let resp = engine.execute(&format!(
+        r#"{{"t":"","i":"","a":{{"SaveRemote":{{"name":"broken","url":"{}-missing","on_save":true,"pull":false}}}}}}"#,
+        test_dir
+    ))?;
+    assert_eq!("broken:ok", resp.get_test_data("remotes"));
+    /* the push is left for the page to ask for, so the action does not wait for it */
+    let resp = engine.execute(&utils::create_simple_action("note"))?;
+    let status = resp.get_sync_status().expect("no sync status");
+    assert!(status.failed_remotes.is_empty());
+    let resp = engine.execute(r#"{"t":"","i":"","a":"PushPending"}"#)?;
+    assert_eq!("true", resp.get_test_data("pending"));
+    let status = resp.get_sync_status().expect("no sync status");
+    assert_eq!(vec!["broken".to_owned()], status.failed_remotes);
+    assert_eq!("✗1", status.badge());
+    let resp = engine.execute(r#"{"t":"","i":"","a":{"Push":{"force":false}}}"#)?;
+    assert!(resp.region("toast:").unwrap().contains("broken"));
+    let resp = engine.execute(r#"{"t":"","i":"","a":"Remotes"}"#)?;
+    assert_eq!("broken:failed", resp.get_test_data("remotes"));
+    let resp = engine.execute(r#"{"t":"","i":"","a":{"RemoveRemote":"broken"}}"#)?;
+    assert_eq!("", resp.get_test_data("remotes"));
+    assert_eq!("", resp.get_sync_status().expect("no sync status").badge());
+    Ok(())
+}
+#[test]
+/// tests for keeping the secrets for remotes with a credential provider
+fn credentials() -> crate::shared::NullResult {
+    trace("credentials test: start");
+    const TEST_DIR1: &str = "testfiles65";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-credentials");
+    let mut options = utils::simple_options(&test_dir, &database_path);
+    options.repo_options.credentials = Some(std::sync::Arc::new(
+        taipo_git_control::MemoryProvider::new(),
+    ));
+    let mut engine = super::FanlingEngine::new(&options)?;
+    engine.execute(
+        r#"{"t":"","i":"","a":{"SaveRemote":{"name":"mirror","url":"https://example.invalid/r.git","on_save":false,"pull":false}}}"#,
+    )?;
+    let resp = engine.execute(r#"{"t":"","i":"","a":"Credentials"}"#)?;
+    assert_eq!("mirror:none", resp.get_test_data("credentials"));
+    let resp = engine.execute(
+        r#"{"t":"","i":"","a":{"SaveCredential":{"remote":"mirror","secret":"sekrit"}}}"#,
+    )?;
+    assert_eq!("mirror:kept", resp.get_test_data("credentials"));
+    assert!(!resp.region("content").unwrap().contains("sekrit"));
+    assert_eq!(
+        "(saving a credential)",
+        crate::for_trace(r#"{"a":{"SaveCredential":{"secret":"sekrit"}}}"#)
+    );
+    let resp = engine.execute(r#"{"t":"","i":"","a":{"ForgetCredential":"mirror"}}"#)?;
+    assert_eq!("mirror:none", resp.get_test_data("credentials"));
+    Ok(())
+}
+#[test]
+/// tests for the size report and packing of the repository
+fn maintenance() -> crate::shared::NullResult {
+    trace("maintenance test: start");
+    const TEST_DIR1: &str = "testfiles66";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-maintenance");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    engine.execute(&utils::create_simple_action("a note"))?;
+    engine.execute(&utils::create_task_action("t1", "task 1"))?;
+    let field = |resp: &fanling_interface::Response, name: &str| -> usize {
+        resp.get_test_data("maintenance")
+            .split(',')
+            .find_map(|f| f.strip_prefix(&format!("{}:", name)).map(str::to_owned))
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(0)
+    };
+    let resp = engine.execute(r#"{"t":"","i":"","a":"Maintenance"}"#)?;
+    assert!(field(&resp, "blobs") >= 2);
+    let loose = field(&resp, "loose");
+    assert!(loose > 0);
+    let resp = engine.execute(r#"{"t":"","i":"","a":"Repack"}"#)?;
+    assert!(field(&resp, "packs") >= 1);
+    assert!(field(&resp, "loose") < loose);
+    assert!(field(&resp, "blobs") >= 2);
+    Ok(())
+}
+#[test]
+/// tests for backing up to an archive, and restoring it as a new setup
+fn backup() -> crate::shared::NullResult {
+    trace("backup test: start");
+    const TEST_DIR1: &str = "testfiles67";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-backup");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_task_action("t1", "task 1"))?;
+    let ident = resp.get_test_data("ident");
+    engine.execute(
+        r#"{"t":"Contact","i":"","a":{"SaveDraft":[{"ident":"","type":"Contact"},
+        {"name":"Half Typed","emails":"","phones":"","address":"","notes":""}]}}"#,
+    )?;
+    let resp = engine.execute(r#"{"t":"","i":"","a":"ExportBackup"}"#)?;
+    let archive = std::path::PathBuf::from(resp.get_test_data("path"));
+    assert!(archive.is_file());
+    /* restored on a new device, with no remote */
+    let new_dir = format!("{}/new", TEST_DIR1);
+    let new_repo = format!("{}/test-restored", new_dir);
+    let manifest = crate::restore_backup(
+        &archive,
+        std::path::Path::new(&new_repo),
+        std::path::Path::new(&new_dir),
+    )?;
+    assert_eq!(vec![crate::draft::DRAFTS_FILE.to_owned()], manifest.local_files);
+    assert!(crate::restore_backup(
+        &archive,
+        std::path::Path::new(&new_repo),
+        std::path::Path::new(&new_dir)
+    )
+    .is_err());
+    let new_options = utils::simple_options(&new_repo, &format!("{}.db", new_repo));
+    let mut restored = super::FanlingEngine::new(&new_options)?;
+    utils::check_test_data(&mut restored, &ident, "ready", "true")?;
+    let kept = crate::draft::Drafts::open(std::path::Path::new(&new_dir), true);
+    assert_eq!(
+        Some("Half Typed"),
+        kept.get("new Contact").map(|d| d.vals["name"].as_str())
+    );
+    Ok(())
+}
+#[test]
+/// tests that links in a backup are not restored, so nothing can be written outside the restored setup
+fn backup_links() -> crate::shared::NullResult {
+    trace("backup links test: start");
+    const TEST_DIR1: &str = "testfiles88";
+    let (_test_dir, _database_path) = utils::init_files(TEST_DIR1, "test-backup-links");
+    let outside = std::path::PathBuf::from(format!("{}/outside", TEST_DIR1));
+    std::fs::create_dir_all(&outside)?;
+    let outside = std::fs::canonicalize(&outside)?;
+    /* an archive with a link from the repository to the directory outside, then a file through the link */
+    let archive = std::path::PathBuf::from(format!("{}/links.tar.gz", TEST_DIR1));
+    let encoder = flate2::write::GzEncoder::new(
+        std::fs::File::create(&archive)?,
+        flate2::Compression::default(),
+    );
+    let mut builder = tar::Builder::new(encoder);
+    let manifest = serde_json::to_string(&crate::BackupManifest {
+        format: 1,
+        created: chrono::Local::now().naive_local(),
+        repo_name: "test-backup-links".to_owned(),
+        encrypted: false,
+        local_files: vec![],
+    })?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest.len() as u64);
+    header.set_mode(0o644);
+    builder.append_data(&mut header, "fanling-backup.json", manifest.as_bytes())?;
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Symlink);
+    header.set_size(0);
+    header.set_mode(0o777);
+    header.set_link_name(&outside)?;
+    builder.append_data(&mut header, "repo/x", std::io::empty())?;
+    let text = "planted";
+    let mut header = tar::Header::new_gnu();
+    header.set_size(text.len() as u64);
+    header.set_mode(0o644);
+    builder.append_data(&mut header, "repo/x/file", text.as_bytes())?;
+    builder.into_inner()?.finish()?;
+    let new_dir = format!("{}/new", TEST_DIR1);
+    let new_repo = format!("{}/test-restored", new_dir);
+    crate::restore_backup(
+        &archive,
+        std::path::Path::new(&new_repo),
+        std::path::Path::new(&new_dir),
+    )?;
+    assert!(!outside.join("file").exists());
+    let restored = std::path::Path::new(&new_repo).join("x");
+    let file_type = std::fs::symlink_metadata(&restored)?.file_type();
+    assert!(!file_type.is_symlink());
+    assert!(restored.join("file").is_file());
+    Ok(())
+}
+#[test]
+/// tests for a read-only repository, whose items can be shown but not changed
+fn read_only() -> crate::shared::NullResult {
+    trace("read-only test: start");
+    const TEST_DIR1: &str = "testfiles68";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-read-only");
+    let mut options = utils::simple_options(&test_dir, &database_path);
+    let ident = {
+        let mut engine = super::FanlingEngine::new(&options)?;
+        let resp = engine.execute(&utils::create_task_action("t1", "task 1"))?;
+        resp.get_test_data("ident")
+    };
+    options.read_only = true;
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let commits = engine.world.as_ref().unwrap().history(100)?.len();
+    let show = format!(r#"{{"t":"Task","i":"{}","a":"Show"}}"#, &ident);
+    let resp = engine.execute(&show)?;
+    let content = resp.region("content").unwrap_or("");
+    assert!(content.contains("read-only"));
+    assert!(!content.contains("Delete"));
+    let resp = engine.execute(&utils::update_task_action(&ident, "t1", "changed"))?;
+    assert!(resp.region("error").unwrap_or("").contains("read-only"));
+    let resp = engine.execute(&utils::create_simple_action("a note"))?;
+    assert!(resp.region("error").unwrap_or("").contains("read-only"));
+    engine.execute(&format!(r#"{{"t":"Task","i":"{}","a":"Close"}}"#, &ident))?;
+    utils::check_test_data(&mut engine, &ident, "ready", "true")?;
+    assert_eq!(commits, engine.world.as_ref().unwrap().history(100)?.len());
+    engine.execute(r#"{"t":"","i":"","a":"ListAll"}"#)?;
+    Ok(())
+}
+#[test]
+///  tests for kinds of item added by plugins
+fn plugins() -> crate::shared::NullResult {
+    trace("plugins test: start");
+    const TEST_DIR1: &str = "testfiles69";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-plugins");
+    let plugin: crate::Plugin = std::sync::Arc::new(recipe::RecipePlugin {});
+    let mut options = utils::simple_options(&test_dir, &database_path);
+    options.plugins = vec![plugin.clone()];
+    let mut engine = super::FanlingEngine::new(&options)?;
+    assert!(engine
+        .world
+        .as_ref()
+        .unwrap()
+        .kinds()
+        .iter()
+        .any(|k| k.name == recipe::RECIPE));
+    let resp = engine.execute(
+        r#"{"t":"Recipe","i":"","a":{"Create":[{"ident":"","type":"Recipe"},{"name":"Stew","servings":"2"}]}}"#,
+    )?;
+    let ident = resp.get_test_data("ident");
+    utils::check_test_data(&mut engine, &ident, "servings", "2")?;
+    let stored = engine.world.as_ref().unwrap().stored_values()?;
+    let (_, values) = stored.iter().find(|(i, _)| *i == ident).unwrap();
+    assert_eq!(
+        Some(recipe::RECIPE),
+        values.get("type").and_then(|t| t.as_str())
+    );
+    // where the plugin is not loaded, the item is kept as it is
+    let other_dir = format!("{}/test-without", TEST_DIR1);
+    let other_options = utils::simple_options(&other_dir, &format!("{}.db", &other_dir));
+    let mut without = super::FanlingEngine::new(&other_options)?;
+    let (base, other_values) = crate::item::split_data_parts(&serde_yaml::to_vec(values)?)?;
+    {
+        let world = without.world.as_mut().unwrap();
+        world.begin_batch();
+        assert!(world.import_item(&base, &other_values)?.is_some());
+        world.end_batch()?;
+    }
+    utils::check_test_data(&mut without, &ident, "type", recipe::RECIPE)?;
+    let kept = without.world.as_ref().unwrap().stored_values()?;
+    let (_, kept_values) = kept.iter().find(|(i, _)| *i == ident).unwrap();
+    assert_eq!(values, kept_values);
+    // a kind cannot be added twice
+    let mut registry = crate::item::ItemTypeRegistry::new();
+    let mut kinds = vec![];
+    crate::plugin::register(&mut registry, &mut kinds, plugin.as_ref())?;
+    assert!(crate::plugin::register(&mut registry, &mut kinds, plugin.as_ref()).is_err());
+    Ok(())
+}
+#[test]
+///  tests for scripts run when items are made or closed
+fn automation() -> crate::shared::NullResult {
+    trace("automation test: start");
+    const TEST_DIR1: &str = "testfiles70";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-automation");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let create_script = |name: &str, code: &str| {
+        serde_json::json!({"t":"Snippet","i":"","a":{"Create":[
+            {"ident":"","type":"Snippet"},
+            {"name":name,"language":"rhai","code":code}
+        ]}})
+        .to_string()
+    };
+    let rules = "// on: created, closed\n\
+        if item[\"type\"] == \"Task\" {\n\
+          if event == \"created\" { item.priority = 1; }\n\
+          else { create(\"Simple\", #{name: \"after \" + item.name, text: \"done\"}); }\n\
+        }\n";
+    engine.execute(&create_script("rules", rules))?;
+    let resp = engine.execute(&utils::create_task_action("t1", "task 1"))?;
+    let ident = resp.get_test_data("ident");
+    let stored = engine.world.as_ref().unwrap().stored_values()?;
+    let (_, values) = stored.iter().find(|(i, _)| *i == ident).unwrap();
+    assert_eq!(Some(1), values.get("priority").and_then(|p| p.as_i64()));
+    engine.execute(&format!(r#"{{"t":"Task","i":"{}","a":"Close"}}"#, &ident))?;
+    let stored = engine.world.as_ref().unwrap().stored_values()?;
+    assert!(stored
+        .iter()
+        .any(|(_, v)| v.get("name").and_then(|n| n.as_str()) == Some("after t1")));
+    let resp = engine.execute(&create_script("broken", "// on: created\nlet x = ;"))?;
+    assert!(resp
+        .region("toast:")
+        .unwrap()
+        .starts_with("A script failed: broken: "));
+    Ok(())
+}
+#[test]
+///  tests for the hooks called when items change
+fn hooks() -> crate::shared::NullResult {
+    trace("hooks test: start");
+    const TEST_DIR1: &str = "testfiles71";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-hooks");
+    let out_path = format!("{}/hooked.txt", TEST_DIR1);
+    let mut options = utils::simple_options(&test_dir, &database_path);
+    options.hooks = vec![
+        crate::HookOptions::parse(&format!(
+            "created,closed=!cat >> {}; echo >> {}",
+            &out_path, &out_path
+        ))?,
+        crate::HookOptions::parse("updated=http://127.0.0.1:9/hook")?,
+    ];
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_task_action("t1", "task 1"))?;
+    let ident = resp.get_test_data("ident");
+    engine.execute(&format!(r#"{{"t":"Task","i":"{}","a":"Close"}}"#, &ident))?;
+    let hooked = fs::read_to_string(&out_path)?;
+    let events: Vec<serde_json::Value> = hooked
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    assert_eq!(2, events.len());
+    assert_eq!("created", events[0]["event"]);
+    assert_eq!("closed", events[1]["event"]);
+    assert_eq!(ident, events[1]["ident"]);
+    // the posts are queued, and left there when the url cannot be reached
+    let resp = engine.execute(&utils::create_simple_action("note"))?;
+    let note = resp.get_test_data("ident");
+    engine.execute(&utils::update_simple_action(&note, "note", "changed"))?;
+    assert_eq!(2, engine.world.as_ref().unwrap().hooks().queued());
+    let resp = engine.execute(r#"{"t":"","i":"","a":"DeliverHooks"}"#)?;
+    assert_eq!("2", resp.get_test_data("queued"));
+    assert!(PathBuf::from(TEST_DIR1)
+        .join(crate::hooks::QUEUE_FILE)
+        .exists());
+    Ok(())
+}
+#[test]
+///  tests for exporting and importing items as JSON
+fn json() -> crate::shared::NullResult {
+    trace("json test: start");
+    const TEST_DIR1: &str = "testfiles72";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-json");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_task_action("t1", "task 1"))?;
+    let ident = resp.get_test_data("ident");
+    engine.execute(&utils::create_simple_action("s1"))?;
+    /* a single item */
+    let export = |idents: Vec<&str>, types: Vec<&str>| {
+        serde_json::json!({"t":"","i":"","a":{"ExportJson":{"idents":idents,"types":types}}})
+            .to_string()
+    };
+    let resp = engine.execute(&export(vec![ident.as_str()], vec![]))?;
+    assert_eq!("1", resp.get_test_data("count"));
+    let items: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(resp.get_test_data("path"))?)?;
+    assert_eq!("task 1", items[0]["text"]);
+    assert_eq!(ident, items[0]["ident"]);
+    let resp = engine.execute(&export(vec![], vec!["Task"]))?;
+    assert_eq!("1", resp.get_test_data("count"));
+    /* everything */
+    let resp = engine.execute(&export(vec![], vec![]))?;
+    let exported = resp.get_test_data("count");
+    let text = std::fs::read_to_string(resp.get_test_data("path"))?;
+    let import =
+        |text: &str| serde_json::json!({"t":"","i":"","a":{"ImportJson":text}}).to_string();
+    /* importing into the same repository adds nothing */
+    let resp = engine.execute(&import(&text))?;
+    assert_eq!("0", resp.get_test_data("count"));
+    assert_eq!(exported, resp.get_test_data("present"));
+    assert_eq!("", resp.get_test_data("conflicts"));
+    /* a changed item is a conflict, and one without an ident is invalid */
+    let mut changed = items[0].clone();
+    changed["text"] = serde_json::json!("task 1 changed");
+    let both = serde_json::json!([changed, {"type":"Simple","name":"s2"}]).to_string();
+    let resp = engine.execute(&import(&both))?;
+    assert_eq!(ident, resp.get_test_data("conflicts"));
+    assert_eq!("1", resp.get_test_data("invalid"));
+    let resp = engine.execute(&export(vec![ident.as_str()], vec![]))?;
+    let kept: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(resp.get_test_data("path"))?)?;
+    assert_eq!("task 1", kept[0]["text"]);
+    /* import into another repository */
+    let test_dir2 = format!("{}/test-json-2", TEST_DIR1);
+    let database_path2 = format!("{}.db", test_dir2);
+    let options2 = utils::simple_options(&test_dir2, &database_path2);
+    let mut engine2 = super::FanlingEngine::new(&options2)?;
+    let resp = engine2.execute(&import(&text))?;
+    assert_ne!("0", resp.get_test_data("count"));
+    utils::check_test_data(&mut engine2, &ident, "ready", "true")?;
+    let resp = engine2.execute(&import("not json"))?;
+    assert!(resp.get_test_data("message").starts_with("Cannot import"));
+    Ok(())
+}
+#[test]
+///  tests for importing and exporting org-mode files
+fn org() -> crate::shared::NullResult {
+    trace("org test: start");
+    const TEST_DIR1: &str = "testfiles73";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-org");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    engine.execute(&utils::create_task_action("t1", "task 1"))?;
+    engine.execute(&utils::create_simple_action("note 1"))?;
+    let resp = engine.execute(r#"{"t":"","i":"","a":"ExportOrg"}"#)?;
+    let text = std::fs::read_to_string(resp.get_test_data("path"))?;
+    assert!(text.contains("* TODO t1\n"), "{}", text);
+    assert!(text.contains("* note 1\n"), "{}", text);
+    assert!(!text.contains("default_context"), "{}", text);
+    /* import into another repository */
+    let test_dir2 = format!("{}/test-org-2", TEST_DIR1);
+    let database_path2 = format!("{}.db", test_dir2);
+    let options2 = utils::simple_options(&test_dir2, &database_path2);
+    let mut engine2 = super::FanlingEngine::new(&options2)?;
+    let org = "* TODO Write report :work:
+  DEADLINE: <2020-03-14 Sat 17:00>
+  Some notes
+** DONE Gather figures
+   CLOSED: [2020-03-09 Mon 10:30]
+* Ideas
+";
+    let import = |text: &str| serde_json::json!({"t":"","i":"","a":{"ImportOrg":text}}).to_string();
+    let resp = engine2.execute(&import(org))?;
+    assert_eq!("3", resp.get_test_data("count"));
+    /* importing again adds nothing */
+    let resp = engine2.execute(&import(org))?;
+    assert_eq!("0", resp.get_test_data("count"));
+    assert_eq!("3", resp.get_test_data("skipped"));
+    let resp = engine2.execute(r#"{"t":"","i":"","a":"ExportOrg"}"#)?;
+    let text = std::fs::read_to_string(resp.get_test_data("path"))?;
+    assert!(
+        text.contains(
+            "* TODO Write report :work:\n  DEADLINE: <2020-03-14 Sat 17:00>\n  Some notes\n"
+        ),
+        "{}",
+        text
+    );
+    assert!(
+        text.contains("** DONE Gather figures\n   CLOSED: [2020-03-09 Mon 10:30]\n"),
+        "{}",
+        text
+    );
+    assert!(text.contains("* Ideas\n"), "{}", text);
+    Ok(())
+}
+#[test]
+///  tests for importing tasks from Todoist and Google Tasks
+fn task_import() -> crate::shared::NullResult {
+    trace("task import test: start");
+    const TEST_DIR1: &str = "testfiles74";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-task-import");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let import = |text: &str, project: &str| {
+        serde_json::json!({"t":"","i":"","a":{"ImportTasks":{"text":text,"project":project}}})
+            .to_string()
+    };
+    let resp = engine.execute(&import("", ""))?;
+    assert_eq!("", resp.get_test_data("message"));
+    let backup = r#"{
+        "projects": [{"id": 1, "name": "Shopping"}, {"id": 2, "name": "Work"}],
+        "labels": [{"id": 7, "name": "errands"}],
+        "items": [
+            {"content": "Buy milk", "project_id": 1, "labels": ["errands"], "due": {"date": "2020-03-14"}, "checked": 0},
+            {"content": "Send report", "project_id": 2, "labels": [7], "checked": 1, "completed_at": "2020-03-10T09:30:00Z"},
+            {"content": "", "project_id": 2}
+        ]
+    }"#;
+    let resp = engine.execute(&import(backup, ""))?;
+    assert_eq!("2", resp.get_test_data("count"));
+    assert_eq!("1", resp.get_test_data("skipped"));
+    /* the tasks are in the namespaces of their projects */
+    let resp = engine.execute(r#"{"t":"","i":"","a":{"Namespace":""}}"#)?;
+    let namespaces = resp.get_test_data("namespaces");
+    assert!(namespaces.contains("shopping:1"), "{}", namespaces);
+    assert!(namespaces.contains("work:1"), "{}", namespaces);
+    /* importing again adds nothing */
+    let resp = engine.execute(&import(backup, ""))?;
+    assert_eq!("0", resp.get_test_data("count"));
+    assert_eq!("3", resp.get_test_data("skipped"));
+    let csv = "TYPE,CONTENT,DESCRIPTION,PRIORITY,INDENT,AUTHOR,RESPONSIBLE,DATE,DATE_LANG,TIMEZONE
+task,Water the plants @home,,4,1,me,,every day,en,UTC
+note,use rain water,,,,,,,,
+";
+    let resp = engine.execute(&import(csv, "Garden"))?;
+    assert_eq!("1", resp.get_test_data("count"));
+    assert_eq!("1", resp.get_test_data("skipped"));
+    assert_eq!("1", resp.get_test_data("warnings"));
+    let resp = engine.execute(&import("{ not JSON", ""))?;
+    assert!(resp.get_test_data("message").starts_with("Cannot import"));
+    Ok(())
+}
+#[test]
+/// tests for syncing with a CalDAV and CardDAV server
+fn dav_sync() -> crate::shared::NullResult {
+    trace("dav sync test: start");
+    const TEST_DIR1: &str = "testfiles75";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-dav-sync");
+    let mut options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(r#"{"a":"SyncDav","i":"","t":""}"#)?;
+    assert_eq!(
+        "No CalDAV or CardDAV server has been set up.",
+        resp.region("toast:").unwrap()
+    );
+    /* nothing is listening, so the calendar cannot be synced */
+    options.dav = crate::DavOptions::new("http://127.0.0.1:9/cal/", "", "", "", "local")?;
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(r#"{"a":"SyncDav","i":"","t":""}"#)?;
+    assert_eq!("1", resp.get_test_data("failed"));
+    assert!(resp.region("toast:").unwrap().contains("Could not sync 1"));
+    Ok(())
+}
+#[test]
+/// tests for making items from e-mail messages
+fn mail_capture() -> crate::shared::NullResult {
+    trace("mail capture test: start");
+    const TEST_DIR1: &str = "testfiles76";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-mail-capture");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let raw = "Subject: Dots\nContent-Type: multipart/mixed; boundary=b\n\n--b\nContent-Type: text/plain\n\nSee attached.\n--b\nContent-Type: image/png; name=\"dot.png\"\nContent-Transfer-Encoding: base64\n\niVBORw0KGgo=\n--b--\n";
+    let resp = engine.capture_mail(raw)?;
+    assert_eq!("Saved 'Dots'", resp.region("toast:").unwrap());
+    assert_eq!("1", resp.get_test_data("attachments"));
+    let ident = resp.get_test_data("ident");
+    let url = crate::assets::url(&format!(
+        "repo/{}/{}/dot.png{}",
+        crate::bookmark::ATTACHMENTS_DIR,
+        ident,
+        crate::mail::BASE64_SUFFIX
+    ));
+    let asset = fanling_interface::Engine::asset(&engine, &url)?.expect("no attachment");
+    assert_eq!("image/png", asset.content_type);
+    assert_eq!(b"\x89PNG\r\n\x1a\n".to_vec(), asset.bytes);
+    /* no mailbox, so nothing to poll */
+    let resp = engine.execute(r#"{"a":"PollMail","i":"","t":""}"#)?;
+    assert_eq!(
+        "No mailbox has been set up.",
+        resp.region("toast:").unwrap()
+    );
+    Ok(())
+}
+#[test]
+/// tests for exporting items for sending or sharing
+fn item_export() -> crate::shared::NullResult {
+    trace("item export test: start");
+    const TEST_DIR1: &str = "testfiles77";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-item-export");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let other = engine
+        .execute(&utils::create_simple_action("shopping"))?
+        .get_test_data("ident");
+    let note = engine
+        .execute(&utils::create_simple_action("note"))?
+        .get_test_data("ident");
+    let text = format!(
+        "See [[{}]] and ![style](https://fanling.assets/fanling.css).",
+        other
+    );
+    engine.execute(&utils::update_simple_action(&note, "note", &text))?;
+    let export = |format: &str| {
+        format!(
+            r#"{{"t":"Simple","i":"{}","a":{{"ExportItem":"{}"}}}}"#,
+            &note, format
+        )
+    };
+    let resp = engine.execute(&export("Markdown"))?;
+    assert_eq!("Copied 'note' as markdown", resp.region("toast:").unwrap());
+    assert_eq!(
+        format!("# note\n\n{}\n", text),
+        resp.get_test_data("exported")
+    );
+    assert!(resp.get_files().next().expect("no file").for_clipboard);
+    let resp = engine.execute(&export("Html"))?;
+    assert_eq!(format!("{}.html", note), resp.get_test_data("name"));
+    let html = resp.get_test_data("exported");
+    assert!(html.contains("<title>note</title>"));
+    assert!(html.contains(">shopping</span>"));
+    assert!(html.contains("src=\"data:text/css;base64,"));
+    assert!(!html.contains("onclick"));
+    let file = resp.get_files().next().expect("no file");
+    assert_eq!("text/html", file.content_type);
+    assert!(!file.for_clipboard);
+    let resp = engine.execute(&export("Pdf"))?;
+    assert_eq!(cfg!(feature = "pdf"), resp.get_files().count() == 1);
+    Ok(())
+}
+#[test]
+/// tests for laying out items for printing
+fn print_item() -> crate::shared::NullResult {
+    trace("print item test: start");
+    const TEST_DIR1: &str = "testfiles78";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-print-item");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let other = engine
+        .execute(&utils::create_simple_action("shopping"))?
+        .get_test_data("ident");
+    let note = engine
+        .execute(&utils::create_simple_action("note"))?
+        .get_test_data("ident");
+    let text = format!(
+        "See [[{}]], [the docs](https://docs.example/) and <https://other.example/>.",
+        other
+    );
+    engine.execute(&utils::update_simple_action(&note, "note", &text))?;
+    let resp = engine.execute(&format!(r#"{{"t":"Simple","i":"{}","a":"Print"}}"#, &note))?;
+    assert_eq!("2", resp.get_test_data("footnotes"));
+    let html = resp.region("content").unwrap();
+    assert!(html.contains("<h1>note</h1>"));
+    assert!(html.contains(">shopping</span>"));
+    assert!(html.contains("the docs<sup class=\"footnote-ref\">[1]</sup>"));
+    assert!(html.contains("<li>https://other.example/</li>"));
+    /* the only controls are for printing and going back */
+    assert!(!html.contains("Delete"));
+    assert!(html.contains("window.print()"));
+    /* other types are printed from their fields */
+    let list = engine
+        .execute(&utils::create_checklist_action(
+            "packing",
+            r"[x] passport\ncharger",
+        ))?
+        .get_test_data("ident");
+    let resp = engine.execute(&format!(
+        r#"{{"t":"Checklist","i":"{}","a":"Print"}}"#,
+        &list
+    ))?;
+    let html = resp.region("content").unwrap();
+    assert!(html.contains(r#"<h1 id="heading-packing">packing</h1>"#));
+    assert!(html.contains("charger"));
+    Ok(())
+}
+#[test]
+/// tests for including the text of one item in another
+fn transclusion() -> crate::shared::NullResult {
+    trace("transclusion test: start");
+    const TEST_DIR1: &str = "testfiles79";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-transclusion");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let part = engine
+        .execute(&utils::create_simple_action("part"))?
+        .get_test_data("ident");
+    engine.execute(&utils::update_simple_action(
+        &part,
+        "part",
+        "the *included* text",
+    ))?;
+    let page = engine
+        .execute(&utils::create_simple_action("page"))?
+        .get_test_data("ident");
+    let text = format!(
+        "before\\n\\n{{{{include:{}}}}}\\n\\n{{{{include:{}}}}}\\n\\n{{{{include:nowhere}}}}\\n\\n```\\n{{{{include:{}}}}}\\n```",
+        part, page, part
+    );
+    engine.execute(&utils::update_simple_action(&page, "page", &text))?;
+    let resp = engine.execute(&format!(r#"{{"t":"Simple","i":"{}","a":"Show"}}"#, &page))?;
+    let html = resp.region("content").unwrap();
+    assert!(html.contains(&format!("<div class=\"transclusion\" title=\"{}\">", part)));
+    assert!(html.contains("the <em>included</em> text"));
+    assert!(html.contains(&format!("{} would include itself", page)));
+    assert!(html.contains("there is no item nowhere"));
+    /* inclusions in code are left as they are */
+    assert!(html.contains(&format!("{{{{include:{}}}}}", part)));
+    /* a change to the included item shows where it is included */
+    engine.execute(&utils::update_simple_action(&part, "part", "new words"))?;
+    let resp = engine.execute(&format!(r#"{{"t":"Simple","i":"{}","a":"Show"}}"#, &page))?;
+    let html = resp.region("content").unwrap();
+    assert!(html.contains("new words"));
+    Ok(())
+}
+#[test]
+/// tests for the table of contents of a page and links to its headings
+fn table_of_contents() -> crate::shared::NullResult {
+    trace("table of contents test: start");
+    const TEST_DIR1: &str = "testfiles80";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-contents");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let guide = engine
+        .execute(&utils::create_simple_action("guide"))?
+        .get_test_data("ident");
+    let show = |ident: &str| format!(r#"{{"t":"Simple","i":"{}","a":"Show"}}"#, ident);
+    engine.execute(&utils::update_simple_action(
+        &guide,
+        "guide",
+        r"# Setting up\n\ntext\n\n## Getting started\n\ntext",
+    ))?;
+    /* a short page has no table of contents */
+    let resp = engine.execute(&show(&guide))?;
+    let html = resp.region("content").unwrap();
+    assert!(html.contains(r#"<h2 id="heading-getting-started">"#));
+    assert!(!html.contains("<nav class=\"contents\">"));
+    engine.execute(&utils::update_simple_action(
+        &guide,
+        "guide",
+        r"# Setting up\n\ntext\n\n## Getting started\n\ntext\n\n## Going further\n\ntext",
+    ))?;
+    let resp = engine.execute(&show(&guide))?;
+    let html = resp.region("content").unwrap();
+    assert!(html.contains("<nav class=\"contents\">"));
+    assert!(html.contains(r##"<a href="#heading-going-further">Going further</a>"##));
+    /* a link from another item goes to the heading */
+    let other = engine
+        .execute(&utils::create_simple_action("other"))?
+        .get_test_data("ident");
+    let text = format!("see [[{}#going-further]]", guide);
+    engine.execute(&utils::update_simple_action(&other, "other", &text))?;
+    let resp = engine.execute(&show(&other))?;
+    let html = resp.region("content").unwrap();
+    assert!(html.contains(r#"a: {"ShowHeading": "going-further"}"#));
+    let resp = engine.execute(&format!(
+        r#"{{"t":"Simple","i":"{}","a":{{"ShowHeading":"going-further"}}}}"#,
+        guide
+    ))?;
+    assert!(resp.region("content").unwrap().contains("Going further"));
+    assert!(resp
+        .region("append:content")
+        .unwrap()
+        .contains(r#"data-target="heading-going-further""#));
+    Ok(())
+}
+#[test]
+/// tests for emoji shortcodes and smart punctuation, set for the repository
+fn typography() -> crate::shared::NullResult {
+    trace("typography test: start");
+    const TEST_DIR1: &str = "testfiles81";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-typography");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let preview =
+        r#"{"t":"","i":"","a":{"Preview":"\"Ship it\" :rocket: -- `\"as is\" :rocket:`"}}"#;
+    let (_tag, html) = engine.execute(preview)?.get_tag(0);
+    assert!(html.contains("&quot;Ship it&quot; :rocket: --"));
+    engine.execute(
+        r#"{"t":"","i":"","a":{"SaveSettings":{"name":"other","email":"other@example.com","template":"{verb} {ident}","emoji":"true","smart_punctuation":"true"}}}"#,
+    )?;
+    let resp = engine.execute(preview)?;
+    let (_tag, html) = resp.get_tag(0);
+    assert!(html.contains("“Ship it” 🚀 –"));
+    assert!(html.contains("<code>&quot;as is&quot; :rocket:</code>"));
+    /* the settings are kept with the repository */
+    drop(engine);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let (_tag, html) = engine.execute(preview)?.get_tag(0);
+    assert!(html.contains("🚀"));
+    Ok(())
+}
+#[test]
+/// tests for the statistics about items
+fn item_statistics() -> crate::shared::NullResult {
+    trace("item statistics test: start");
+    const TEST_DIR1: &str = "testfiles82";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-item-stats");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let notes = engine
+        .execute(&utils::create_simple_action("notes"))?
+        .get_test_data("ident");
+    engine.execute(&utils::update_simple_action(
+        &notes,
+        "notes",
+        r"# Plans\n\nthree more words",
+    ))?;
+    let show = format!(r#"{{"t":"Simple","i":"{}","a":"Show"}}"#, &notes);
+    let resp = engine.execute(&show)?;
+    let html = resp.region("content").unwrap();
+    assert!(html.contains("4 words, 25"));
+    assert!(html.contains("min to read"));
+    assert!(html.contains("Changed"));
+    engine.execute(&utils::create_task_action("t1", "task 1"))?;
+    let resp = engine.execute(r#"{"a":"Stats","i":"","t":""}"#)?;
+    let kinds: usize = resp.get_test_data("kinds").parse()?;
+    assert!(kinds >= 2);
+    let (_tag, html) = resp.get_tag(0);
+    assert!(html.contains("<td>Task</td><td>1</td>"));
+    Ok(())
+}
+#[test]
+/// tests for the report for weekly reviews
+fn review_report() -> crate::shared::NullResult {
+    trace("review report test: start");
+    const TEST_DIR1: &str = "testfiles83";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-report");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let old = engine
+        .execute(&utils::create_simple_action("old note"))?
+        .get_test_data("ident");
+    let done = engine
+        .execute(&utils::create_task_action("t1", "task 1"))?
+        .get_test_data("ident");
+    engine.execute(&format!(r#"{{"t":"Task","i":"{}","a":"Close"}}"#, &done))?;
+    let resp = engine.execute(r#"{"a":"Report","i":"","t":""}"#)?;
+    assert_eq!("1", resp.get_test_data("completed"));
+    let untouched = resp.get_test_data("untouched");
+    assert!(!untouched.contains(&done));
+    assert!(untouched.contains(&old));
+    Ok(())
+}
+#[test]
+/// tests for projects, with their progress and closing them
+fn projects() -> crate::shared::NullResult {
+    trace("projects test: start");
+    const TEST_DIR1: &str = "testfiles84";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-projects");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let project = engine
+        .execute(&utils::create_project_action("house move"))?
+        .get_test_data("ident");
+    let mut tasks = vec![];
+    for name in &["pack", "book van", "clean"] {
+        let resp = engine.execute(&utils::create_child_task_action(name, &project))?;
+        tasks.push(resp.get_test_data("ident"));
+    }
+    engine.execute(&format!(
+        r#"{{"t":"Task","i":"{}","a":"Close"}}"#,
+        &tasks[0]
+    ))?;
+    utils::check_test_data(&mut engine, &project, "open", "2")?;
+    utils::check_test_data(&mut engine, &project, "percent", "33")?;
+    let resp = engine.execute(r#"{"a":"ListProjects","i":"","t":""}"#)?;
+    assert_eq!(format!("{}:33", &project), resp.get_test_data("projects"));
+    let close = |children: &str| {
+        format!(
+            r#"{{"t":"Project","i":"{}","a":{{"CloseProject":"{}"}}}}"#,
+            &project, children
+        )
+    };
+    let resp = engine.execute(&close("Ask"))?;
+    assert!(resp.is_confirm_required());
+    utils::check_test_data(&mut engine, &tasks[1], "ready", "true")?;
+    engine.execute(&close("Close"))?;
+    utils::check_test_data(&mut engine, &tasks[1], "ready", "false")?;
+    utils::check_test_data(&mut engine, &project, "closed", "3")?;
+    utils::check_test_data(&mut engine, &project, "percent", "100")?;
+    /* orphaning leaves the open tasks open, but outside the project */
+    let other = engine
+        .execute(&utils::create_project_action("garden"))?
+        .get_test_data("ident");
+    let weed = engine
+        .execute(&utils::create_child_task_action("weed", &other))?
+        .get_test_data("ident");
+    engine.execute(&format!(
+        r#"{{"t":"Project","i":"{}","a":{{"CloseProject":"Orphan"}}}}"#,
+        &other
+    ))?;
+    utils::check_test_data(&mut engine, &weed, "ready", "true")?;
+    utils::check_test_data(&mut engine, &other, "open", "0")?;
+    Ok(())
+}
+#[test]
+/// tests for the timeline of tasks and events
+fn timeline() -> crate::shared::NullResult {
+    trace("timeline test: start");
+    const TEST_DIR1: &str = "testfiles85";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-timeline");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let day = |days| {
+        (chrono::Local::today().naive_local() + chrono::Duration::days(days))
+            .format("%Y-%m-%d")
+            .to_string()
+    };
+    let project = engine
+        .execute(&utils::create_project_action("launch"))?
+        .get_test_data("ident");
+    let write = format!("{} 17:00:00", day(2));
+    engine.execute(&utils::create_due_task_action("write", &project, &write))?;
+    let buy = format!("{} 09:00:00", day(2));
+    engine.execute(&utils::create_due_task_action("buy", "", &buy))?;
+    /* no deadline, so not on the timeline */
+    engine.execute(&utils::create_task_action("someday", "maybe"))?;
+    let demo = format!("{} 10:00", day(4));
+    engine.execute(&utils::create_event_action("demo", &demo, "none"))?;
+    let resp = engine.execute(r#"{"a":{"Timeline":{"by_tag":false,"date":""}},"i":"","t":""}"#)?;
+    assert_eq!("3", resp.get_test_data("spans"));
+    assert_eq!("launch,No project", resp.get_test_data("groups"));
+    /* the two deadlines on the same day */
+    assert_eq!("1", resp.get_test_data("collisions"));
+    let html = resp.region("content").unwrap();
+    assert!(html.contains("<svg"));
+    let resp = engine.execute(r#"{"a":{"Timeline":{"by_tag":true,"date":""}},"i":"","t":""}"#)?;
+    assert_eq!("No tag", resp.get_test_data("groups"));
+    Ok(())
+}
+#[test]
+/// tests for batch operations on the items chosen in a list
+fn batch() -> crate::shared::NullResult {
+    trace("batch test: start");
+    const TEST_DIR1: &str = "testfiles86";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-batch");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let t1 = engine
+        .execute(&utils::create_task_action("t1", "task 1"))?
+        .get_test_data("ident");
+    let t2 = engine
+        .execute(&utils::create_task_action("t2", "task 2"))?
+        .get_test_data("ident");
+    let bookmark = engine
+        .execute(&utils::create_bookmark_action(
+            "https://example.invalid/batch",
+            "news",
+        ))?
+        .get_test_data("ident");
+    let project = engine
+        .execute(&utils::create_project_action("tidy"))?
+        .get_test_data("ident");
+    let batch = |idents: &[&str], operation: &str| {
+        format!(
+            r#"{{"t":"","i":"","a":{{"Batch":{{"idents":{:?},"operation":{}}}}}}}"#,
+            idents, operation
+        )
+    };
+    let resp = engine.execute(&batch(&[&bookmark, &t1], r#"{"AddTag":"reading"}"#))?;
+    assert_eq!("2", resp.get_test_data("changed"));
+    utils::check_test_data(&mut engine, &bookmark, "tags", "news,reading")?;
+    let resp = engine.execute(&batch(&[&bookmark], r#"{"RemoveTag":"news"}"#))?;
+    assert_eq!("1", resp.get_test_data("changed"));
+    utils::check_test_data(&mut engine, &bookmark, "tags", "reading")?;
+    /* a project cannot be its own parent */
+    let resp = engine.execute(&batch(
+        &[&t1, &t2, &project],
+        &format!("{{\"SetParent\":{:?}}}", &project),
+    ))?;
+    assert_eq!("2", resp.get_test_data("changed"));
+    assert_eq!("1", resp.get_test_data("skipped"));
+    let (base, _values) = engine.world.as_ref().unwrap().get_item_parts(&t2)?;
+    assert_eq!(Some(project.clone()), base.parent);
+    /* the bookmark cannot be closed, and both tasks are closed in one commit */
+    let resp = engine.execute(&batch(&[&t1, &t2, &bookmark], r#""Close""#))?;
+    assert_eq!("2", resp.get_test_data("changed"));
+    assert_eq!("1", resp.get_test_data("skipped"));
+    let history = engine.world.as_ref().unwrap().history(1)?;
+    assert_eq!(2, history[0].message.matches("close").count());
+    engine.execute(&batch(&[&bookmark], r#""SoftDelete""#))?;
+    let (base, _values) = engine.world.as_ref().unwrap().get_item_parts(&bookmark)?;
+    assert_eq!("archived", base.classify);
+    let resp = engine.execute(&batch(&[&t1], r#"{"ChangeNamespace":"done"}"#))?;
+    assert_eq!("1", resp.get_test_data("changed"));
+    assert!(engine
+        .world
+        .as_ref()
+        .unwrap()
+        .has_item(&format!("done/{}", &t1))?);
+    Ok(())
+}
+#[test]
+/// tests for snoozing tasks
+fn snooze() -> crate::shared::NullResult {
+    trace("snooze test: start");
+    const TEST_DIR1: &str = "testfiles9";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-snooze");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let resp = engine.execute(&utils::create_task_action("t1", "task 1"))?;
+    let ident = resp.get_test_data("ident");
+    utils::check_test_data(&mut engine, &ident, "ready", "true")?;
+    let action = |a: &str| format!(r#"{{"t":"Task","i":"{}","a":{{"Snooze":{}}}}}"#, &ident, a);
+    let resp = engine.execute(&action(r#"{"Hours":2}"#))?;
+    assert_eq!("false", resp.get_test_data("ready"));
+    assert_eq!("1", resp.get_test_data("snoozes"));
+    engine.execute(&action(r#""NextWeek""#))?;
+    engine.execute(&action(r#"{"Until":"2000-01-01"}"#))?;
+    utils::check_test_data(&mut engine, &ident, "ready", "true")?;
+    utils::check_test_data(&mut engine, &ident, "snoozes", "3")?;
+    Ok(())
+}
+#[test]
+///  tests for task ready including blocking
+fn ready_task() -> crate::shared::NullResult {
+    trace("ready task test: start");
+    const TEST_DIR1: &str = "testfiles5";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-ready");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    let create1 = utils::create_task_action("t1", "task 1");
+    let resp = engine.execute(&create1)?;
+    let ident1 = resp.get_test_data("ident");
+    utils::check_test_data(&mut engine, &ident1, "ready", "true")?;
+    let create2 = utils::create_task_action("t2", "task 2");
+    let resp = engine.execute(&create2)?;
+    let ident2 = resp.get_test_data("ident");
+    utils::check_test_data(&mut engine, &ident2, "ready", "true")?;
+    let ready = r#"{"a":"ListReady","i":"","t":""}"#;
+    let resp = engine.execute(&ready)?;
+    assert_eq!("3", resp.get_test_data("count"));
+    let block = format!(
+        r#"{{"t":"Task","i":{:?},"a":{{"BlockBy":{:?}}}}}"#,
+        &ident1, &ident2
+    );
+    let unblock = format!(
+        r#"{{"t":"Task","i":{:?},"a":{{"UnblockBy":{:?}}}}}"#,
+        &ident1, &ident2
+    );
+    let _resp = engine.execute(&block)?;
+    utils::check_test_data(&mut engine, &ident1, "ready", "false")?;
+    let resp = engine.execute(&ready)?;
+    assert_eq!("2", resp.get_test_data("count"));
+
+    trace("ready test: repo2 - update");
+    let mut engine2 = utils::test_engine(TEST_DIR1, "test-ready", "test-ready2", "p")?;
+    utils::check_test_data(&mut engine2, &ident1, "ready", "false")?;
+    let _resp = engine.execute(&unblock)?;
+    utils::check_test_data(&mut engine, &ident1, "ready", "true")?;
+    let resp = engine.execute(&ready)?;
+    assert_eq!("3", resp.get_test_data("count"));
+    //  TODO more testing: push to server and pull to second server
+    //  TODO more testing: check in second repo for ready status
+    let _resp = engine.execute(&block)?;
+    let resp = engine.execute(&format!(r#"{{"t":"Task","i":"{}","a":"Show"}}"#, ident2))?;
+    let (_tag, html) = resp.get_tag(0);
+    assert!(html.contains(&format!(r#"id="{}""#, ident1)));
+    assert!(html.contains("Blocks:"));
+    let close_action = format!(r#"{{"t":"Task","i":"{}","a":"Close"}}"#, ident2);
+    let response = engine.execute(&close_action)?;
+    assert_eq!("t1", response.get_test_data("unblocked"));
+    utils::check_test_data(&mut engine, &ident1, "ready", "true")?;
+    let resp = engine.execute(&ready)?;
+    assert_eq!("2", resp.get_test_data("count"));
+    //  TODO more testing: push to server and pull to second server
+    // TODO more testing: check in second repo for ready status
+    let check_data = r#"{"a":"CheckData","i":"","t":""}"#;
+    let _resp = engine.execute(&check_data)?;
+    Ok(())
+}
+#[test]
+/// tests for items whose names give no slug for their idents
+fn names_without_slugs() -> crate::shared::NullResult {
+    trace("names without slugs test: start");
+    const TEST_DIR1: &str = "testfiles87";
+    let (test_dir, database_path) = utils::init_files(TEST_DIR1, "test-no-slug");
+    let options = utils::simple_options(&test_dir, &database_path);
+    let mut engine = super::FanlingEngine::new(&options)?;
+    for name in &["会议", "!!!", "🎉"] {
+        let resp = engine.execute(&utils::create_simple_action(name))?;
+        let ident = resp.get_test_data("ident");
+        assert!(ident.starts_with("item-"), "ident {} for {}", ident, name);
+        let item_ref = engine
+            .world
+            .as_mut()
+            .unwrap()
+            .get_item(ident.clone(), "Simple".to_owned())?;
+        assert_eq!(*name, item_ref.borrow().description());
+    }
     Ok(())
 }
@@ -0,0 +1,168 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! a plugin adding recipes, for testing plugins. It only uses what is
+exported from the crate, as a plugin in another crate would. */
+use crate::{
+    ActionResponse, FLResult, FanlingError, Item, ItemBase, ItemBaseForSerde, ItemData, ItemKind,
+    ItemTypePlugin, ItemTypePolicy, ItemTypeRef, KindInfo, NullResult, World,
+};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::HashMap;
+
+/** the type name of recipes */
+pub(crate) const RECIPE: &str = "Recipe";
+
+/** data for a recipe item */
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Recipe {
+    name: String,
+    #[serde(default)]
+    servings: u32,
+}
+#[derive(Serialize, Deserialize)]
+struct RecipeForSerde {
+    #[serde(flatten)]
+    base: ItemBaseForSerde,
+    #[serde(flatten)]
+    data: Recipe,
+}
+impl ItemData for Recipe {
+    fn for_edit(
+        &mut self,
+        base: &mut ItemBase,
+        _is_for_update: bool,
+        _world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let mut resp = fanling_interface::Response::new();
+        resp.add_tag("content", &format!("editing {}", self.name));
+        resp.set_test_data("ident", &base.get_ident());
+        Ok(resp)
+    }
+    fn for_show(
+        &mut self,
+        base: &mut ItemBase,
+        _world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        let mut resp = fanling_interface::Response::new();
+        resp.add_tag(
+            "content",
+            &format!("{} (serves {})", self.name, self.servings),
+        );
+        resp.set_test_data("ident", &base.get_ident());
+        resp.set_test_data("servings", &self.servings.to_string());
+        Ok(resp)
+    }
+    fn to_yaml(&self, base: &ItemBase) -> Result<Vec<u8>, FanlingError> {
+        let for_serde = RecipeForSerde {
+            base: ItemBaseForSerde::from_base(base)?,
+            data: self.clone(),
+        };
+        Ok(serde_yaml::to_vec(&for_serde)?)
+    }
+    fn is_open(&self) -> bool {
+        true
+    }
+    fn is_ready(&mut self, _world: &mut World) -> FLResult<bool> {
+        Ok(false)
+    }
+    fn description(&self) -> String {
+        self.name.clone()
+    }
+    fn fanling_clone(&self) -> FLResult<Box<dyn ItemData>> {
+        Ok(Box::new(self.clone()))
+    }
+    fn descr_for_ident(&self) -> String {
+        self.name.clone()
+    }
+    fn description_for_list(&self) -> String {
+        self.name.clone()
+    }
+    fn set_data(&mut self, vals: &HashMap<String, String>, _world: &mut World) -> NullResult {
+        self.name = vals.get("name").cloned().unwrap_or_default();
+        self.servings = vals
+            .get("servings")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0);
+        Ok(())
+    }
+    fn set_from_yaml(&mut self, yaml: &Value, _world: &mut World) -> NullResult {
+        *self = serde_yaml::from_value(yaml.clone())?;
+        Ok(())
+    }
+    fn fix_data(&self, _yaml: &Value, _base: &mut ItemBase, _world: &mut World) -> NullResult {
+        Ok(())
+    }
+    fn do_action(
+        &mut self,
+        _base: &mut ItemBase,
+        _action: crate::Action,
+        _world: &mut World,
+    ) -> fanling_interface::ResponseResult {
+        Ok(fanling_interface::Response::new())
+    }
+}
+
+/** policy for recipes */
+#[derive(Debug)]
+struct RecipeTypePolicy {}
+impl ItemTypePolicy for RecipeTypePolicy {
+    fn kind(&self) -> ItemKind {
+        ItemKind::Plugin(RECIPE)
+    }
+    fn make_raw(&self, item_type: ItemTypeRef) -> Item {
+        Item::new_with_data(item_type, Box::new(Recipe::default()))
+    }
+    /** keep our version */
+    fn resolve_conflict_both(
+        &self,
+        _world: &mut World,
+        _ancestor: &Value,
+        ours: &Value,
+        _theirs: &Value,
+    ) -> FLResult<Box<dyn ItemData>> {
+        Ok(Box::new(serde_yaml::from_value::<Recipe>(ours.clone())?))
+    }
+    fn check_valid(
+        &mut self,
+        _base: &ItemBaseForSerde,
+        vals: &HashMap<String, String>,
+        _world: &mut World,
+    ) -> ActionResponse {
+        let mut ar = ActionResponse::new();
+        ar.assert(
+            vals.get("name").map_or(false, |n| !n.is_empty()),
+            "name-error",
+            "Name must be non-blank.",
+        );
+        ar
+    }
+    fn from_yaml(&self, values: &Value, world: &mut World) -> FLResult<Box<dyn ItemData>> {
+        let mut recipe = Recipe::default();
+        recipe.set_from_yaml(values, world)?;
+        Ok(Box::new(recipe))
+    }
+}
+
+/** the plugin */
+#[derive(Debug)]
+pub(crate) struct RecipePlugin {}
+impl ItemTypePlugin for RecipePlugin {
+    fn info(&self) -> KindInfo {
+        KindInfo {
+            kind: ItemKind::Plugin(RECIPE),
+            name: RECIPE,
+            aliases: &["recipe"],
+            label: "recipe",
+            icon: "🍲",
+            description: "a dish, with how many it serves",
+            creatable: true,
+            required_fields: &["name"],
+        }
+    }
+    fn policy(&self) -> Box<dyn ItemTypePolicy> {
+        Box::new(RecipeTypePolicy {})
+    }
+}
@@ -12,6 +12,75 @@ pub(crate) fn create_task_action(name: &str, text: &str) -> String {
         &name, &text,
     )
 }
+pub(crate) fn create_contact_action(name: &str) -> String {
+    format!(
+        r#"{{"t":"Contact","i":"","a":{{"Create":[{{"ident":"","type":"Contact"}},{{"name":"{}","emails":"someone@example.com","phones":"","address":"","notes":"aaaa"}}]}}}}"#,
+        name
+    )
+}
+pub(crate) fn create_checklist_action(name: &str, entries: &str) -> String {
+    format!(
+        r#"{{"t":"Checklist","i":"","a":{{"Create":[{{"ident":"","type":"Checklist"}},{{"name":"{}","entries":"{}"}}]}}}}"#,
+        name, entries
+    )
+}
+pub(crate) fn create_event_action(name: &str, start: &str, recurrence: &str) -> String {
+    format!(
+        r#"{{"t":"Event","i":"","a":{{"Create":[{{"ident":"","type":"Event"}},{{"name":"{}","start":"{}","end":"","location":"","recurrence":"{}","description":""}}]}}}}"#,
+        name, start, recurrence
+    )
+}
+pub(crate) fn update_contact_action(ident: &str, name: &str, notes: &str) -> String {
+    format!(
+        r#"{{"t":"Contact","i":"{}","a":{{"Update":[{{"ident":"{}","type":"Contact"}},{{"name":"{}","emails":"someone@example.com","phones":"","address":"","notes":"{}"}}]}}}}"#,
+        &ident, &ident, &name, &notes,
+    )
+}
+pub(crate) fn create_snippet_action(name: &str) -> String {
+    format!(
+        r#"{{"t":"Snippet","i":"","a":{{"Create":[{{"ident":"","type":"Snippet"}},{{"name":"{}","language":"rs","code":"aaaa"}}]}}}}"#,
+        name
+    )
+}
+pub(crate) fn create_meeting_action(name: &str, attendees: &str, notes: &str) -> String {
+    format!(
+        r#"{{"t":"Meeting","i":"","a":{{"Create":[{{"ident":"","type":"Meeting"}},{{"name":"{}","date":"2020-11-05","attendees":"{}","notes":"{}"}}]}}}}"#,
+        name, attendees, notes
+    )
+}
+pub(crate) fn create_bookmark_action(url: &str, tags: &str) -> String {
+    format!(
+        r#"{{"t":"Bookmark","i":"","a":{{"Create":[{{"ident":"","type":"Bookmark"}},{{"url":"{}","name":"","tags":"{}","notes":"aaaa"}}]}}}}"#,
+        url, tags
+    )
+}
+pub(crate) fn create_habit_action(name: &str, per_week: u32) -> String {
+    format!(
+        r#"{{"t":"Habit","i":"","a":{{"Create":[{{"ident":"","type":"Habit"}},{{"name":"{}","per_week":"{}"}}]}}}}"#,
+        name, per_week
+    )
+}
+pub(crate) fn create_project_action(name: &str) -> String {
+    format!(
+        r#"{{"t":"Project","i":"","a":{{"Create":[{{"ident":"","type":"Project","can_be_parent":true}},{{"name":"{}","text":"aaaa"}}]}}}}"#,
+        name
+    )
+}
+pub(crate) fn create_child_task_action(name: &str, parent: &str) -> String {
+    create_due_task_action(name, parent, "1970-01-01 00:00:00")
+}
+pub(crate) fn create_due_task_action(name: &str, parent: &str, deadline: &str) -> String {
+    format!(
+        r#"{{"t":"Task","i":"","a":{{"Create":[{{"ident":"","type":"Task","parent":"{}"}},{{"name":"{}","text":"","priority":"10","context":"default_context","deadline":"{}","show_after_date":"1970-01-01 00:00:00"}}]}}}}"#,
+        parent, name, deadline,
+    )
+}
+pub(crate) fn update_snippet_action(ident: &str, name: &str, code: &str) -> String {
+    format!(
+        r#"{{"t":"Snippet","i":"{}","a":{{"Update":[{{"ident":"{}","type":"Snippet"}},{{"name":"{}","language":"rs","code":"{}"}}]}}}}"#,
+        &ident, &ident, &name, &code,
+    )
+}
 pub(crate) fn update_simple_action(ident: &str, name: &str, text: &str) -> String {
     format!(
         r#"{{"t":"Simple","i":"{}","a":{{"Update":[{{"ident":"{}","type":"Simple"}},{{"name":"{}","text":"{}"}}]}}}}"#,
@@ -20,7 +89,7 @@ pub(crate) fn update_simple_action(ident: &str, name: &str, text: &str) -> Strin
 }
 pub(crate) fn update_task_action(ident: &str, name: &str, text: &str) -> String {
     format!(
-        r#"{{"t":"Task","i":"{}","a":{{"Update":[{{"ident":"{}","type":"Task"}},{{"name":"{}","text":"{}","priority":"10","context":"default_context","deadline":"2021-01-01 00:00:00","show_after_date":"2020-01-01 00:00:00"}}]}}}}"#,
+        r#"{{"t":"Task","i":"{}","a":{{"Update":[{{"ident":"{}","type":"Task","confirmed":true}},{{"name":"{}","text":"{}","priority":"10","context":"default_context","deadline":"2021-01-01 00:00:00","show_after_date":"2020-01-01 00:00:00"}}]}}}}"#,
         &ident, &ident, &name, &text,
     )
 }
@@ -64,7 +133,21 @@ pub(crate) fn simple_options(test_dir: &str, database_path: &str) -> super::Engi
             database_path: database_path.to_string(),
         },
         uniq_pfx: "a".to_string(),
+        ident_scheme: crate::IdentScheme::default(),
         auto_link: false,
+        format_options: crate::FormatOptions::default(),
+        commit_options: crate::CommitOptions::default(),
+        repos: crate::ReposOptions::default(),
+        encryption: crate::EncryptionOptions::default(),
+        keep_history: false,
+        serve_assets: false,
+        sparse_kinds: vec![],
+        read_only: false,
+        plugins: vec![],
+        hooks: vec![],
+        dav: None,
+        mail: None,
+        math: crate::MathRendering::default(),
     }
 }
 pub(crate) fn init_files(dir: &str, subdir: &str) -> (String, String) {
@@ -109,7 +192,21 @@ pub(crate) fn test_engine(
             database_path: database2_path,
         },
         uniq_pfx: uniq_pfx.to_string(),
+        ident_scheme: crate::IdentScheme::default(),
         auto_link: false,
+        format_options: crate::FormatOptions::default(),
+        commit_options: crate::CommitOptions::default(),
+        repos: crate::ReposOptions::default(),
+        encryption: crate::EncryptionOptions::default(),
+        keep_history: false,
+        serve_assets: false,
+        sparse_kinds: vec![],
+        read_only: false,
+        plugins: vec![],
+        hooks: vec![],
+        dav: None,
+        mail: None,
+        math: crate::MathRendering::default(),
     };
 
     let engine = super::FanlingEngine::new(&options)?;
@@ -0,0 +1,443 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! a timeline of the open tasks with deadlines and the events, over a
+few weeks, grouped by project (see [`crate::project`]) or by tag.
+
+A task is shown from the date it is not shown before (if it has one)
+to its deadline, which is marked; an event from its start to its end,
+with each occurrence of a recurring event shown. An item with several
+tags is shown in the group for each of them. The days when more than
+one thing is due or happening (deadlines and events, but not the time
+leading up to a deadline) are shaded, so that collisions stand out.
+
+The picture is SVG, made here (as for the heatmap of
+[`crate::stats`]) so that it needs nothing in the page. */
+use crate::format::FormatOptions;
+use crate::item::ItemLink;
+use crate::shared::FLResult;
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use std::collections::{BTreeMap, BTreeSet};
+
+/** the number of weeks shown */
+const TIMELINE_WEEKS: i64 = 8;
+/** how many weeks of it are before the date asked for */
+const WEEKS_BEFORE: i64 = 1;
+/** the width of a day (pixels) */
+const DAY_WIDTH: i64 = 14;
+/** the height of a row */
+const ROW_HEIGHT: i64 = 18;
+/** the width of the names on the left */
+const LABEL_WIDTH: i64 = 180;
+/** the height of the dates above the rows */
+const AXIS_HEIGHT: i64 = 20;
+/** the number of characters of a name shown */
+const MAX_LABEL: usize = 28;
+/** how far up from an item a project is looked for */
+const MAX_DEPTH: usize = 16;
+
+/** a task or an occurrence of an event on the timeline */
+#[derive(Debug, Clone, PartialEq)]
+struct Span {
+    ident: String,
+    name: String,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+    /** whether it ends with a deadline (a task) rather than being an event */
+    deadline: bool,
+}
+impl Span {
+    /** the days when it is due (for a task) or happening (for an event) */
+    fn busy_days(&self) -> Vec<NaiveDate> {
+        if self.deadline {
+            return vec![self.to.date()];
+        }
+        let mut days = vec![];
+        let mut day = self.from.date();
+        while day <= self.to.date() {
+            days.push(day);
+            day = day.succ();
+        }
+        days
+    }
+}
+
+/** the days when more than one thing is due or happening */
+fn collisions(spans: &[Span]) -> BTreeSet<NaiveDate> {
+    let mut busy: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+    for span in spans {
+        for day in span.busy_days() {
+            *busy.entry(day).or_insert(0) += 1;
+        }
+    }
+    busy.into_iter()
+        .filter(|(_day, n)| *n > 1)
+        .map(|(day, _n)| day)
+        .collect()
+}
+
+/** the open tasks with deadlines and the events on any of the days from `first` to before `last`, in order */
+fn spans(world: &mut World, first: NaiveDate, last: NaiveDate) -> FLResult<Vec<Span>> {
+    let mut spans = vec![];
+    for entry in world.search_type("Task")?.entries {
+        let item_ref = ItemLink::from(entry.link.clone()).resolve_link(world)?;
+        let item = item_ref.borrow();
+        if let (true, Some(due)) = (item.is_open(), item.due()) {
+            let from = item.starts().filter(|s| *s < due).unwrap_or(due);
+            if due.date() >= first && from.date() < last {
+                spans.push(Span {
+                    ident: item.ident(),
+                    name: item.description(),
+                    from,
+                    to: due,
+                    deadline: true,
+                });
+            }
+        }
+    }
+    for (from, to, ident, name) in crate::event::spans(world, first, last)? {
+        spans.push(Span {
+            ident,
+            name,
+            from,
+            to,
+            deadline: false,
+        });
+    }
+    spans.sort_by(|a, b| a.from.cmp(&b.from).then_with(|| a.name.cmp(&b.name)));
+    Ok(spans)
+}
+
+/** the name of the project that the item is in (its nearest ancestor that is a project), if any */
+fn project(world: &mut World, ident: &str) -> FLResult<Option<String>> {
+    let mut ident = ident.to_owned();
+    for _ in 0..MAX_DEPTH {
+        let parent = match ItemLink::new(ident)
+            .resolve_link(world)?
+            .borrow_mut()
+            .parent_ident()
+        {
+            Some(parent) => parent,
+            None => return Ok(None),
+        };
+        let parent_ref = ItemLink::new(parent.clone()).resolve_link(world)?;
+        let item = parent_ref.borrow();
+        if item.type_name() == "Project" {
+            return Ok(Some(item.description()));
+        }
+        ident = parent;
+    }
+    Ok(None)
+}
+/** the groups by name, with those in none last, and the spans (by index) in each */
+fn groups(world: &mut World, spans: &[Span], by_tag: bool) -> FLResult<Vec<(String, Vec<usize>)>> {
+    let mut groups: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    let mut ungrouped = vec![];
+    for (i, span) in spans.iter().enumerate() {
+        let names: Vec<String> = if by_tag {
+            ItemLink::new(span.ident.clone())
+                .resolve_link(world)?
+                .borrow()
+                .tags()
+        } else {
+            project(world, &span.ident)?.into_iter().collect()
+        };
+        if names.is_empty() {
+            ungrouped.push(i);
+        }
+        for name in names {
+            groups.entry(name).or_insert_with(Vec::new).push(i);
+        }
+    }
+    let mut groups: Vec<(String, Vec<usize>)> = groups.into_iter().collect();
+    if !ungrouped.is_empty() {
+        let none = if by_tag { "No tag" } else { "No project" };
+        groups.push((none.to_owned(), ungrouped));
+    }
+    Ok(groups)
+}
+
+/** the name, shortened if it is long */
+fn label(name: &str) -> String {
+    if name.chars().count() > MAX_LABEL {
+        format!("{}…", name.chars().take(MAX_LABEL - 1).collect::<String>())
+    } else {
+        name.to_owned()
+    }
+}
+/** the timeline as SVG, for the days from `first`: a row for each
+group and then one for each of its spans */
+fn timeline_svg(
+    spans: &[Span],
+    groups: &[(String, Vec<usize>)],
+    first: NaiveDate,
+    days: i64,
+    today: NaiveDate,
+    format: &FormatOptions,
+) -> String {
+    let rows: i64 = groups.iter().map(|(_name, s)| 1 + s.len() as i64).sum();
+    let width = LABEL_WIDTH + days * DAY_WIDTH;
+    let height = AXIS_HEIGHT + rows.max(1) * ROW_HEIGHT;
+    let start = first.and_hms(0, 0, 0);
+    let x_of = |dt: NaiveDateTime| {
+        (LABEL_WIDTH + (dt - start).num_minutes() * DAY_WIDTH / (24 * 60))
+            .max(LABEL_WIDTH)
+            .min(width)
+    };
+    let mut parts = vec![format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" class="timeline" width="{}" height="{}" viewBox="0 0 {} {}" font-family="sans-serif" font-size="10">"#,
+        width, height, width, height
+    )];
+    for day in collisions(spans) {
+        if day >= first && day < first + Duration::days(days) {
+            parts.push(format!(
+                r##"<rect x="{}" y="{}" width="{}" height="{}" fill="#f8d0d0"><title>{}: more than one thing</title></rect>"##,
+                x_of(day.and_hms(0, 0, 0)),
+                AXIS_HEIGHT,
+                DAY_WIDTH,
+                height - AXIS_HEIGHT,
+                format.format_date(day)
+            ));
+        }
+    }
+    for week in 0..=days / 7 {
+        let day = first + Duration::weeks(week);
+        let x = x_of(day.and_hms(0, 0, 0));
+        parts.push(format!(
+            r##"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="#ccc"/><text x="{}" y="{}">{}</text>"##,
+            x,
+            AXIS_HEIGHT - 4,
+            x,
+            height,
+            x + 2,
+            AXIS_HEIGHT - 8,
+            day.format("%d %b")
+        ));
+    }
+    if today >= first && today < first + Duration::days(days) {
+        let x = x_of(today.and_hms(12, 0, 0));
+        parts.push(format!(
+            r##"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="#c00"/>"##,
+            x, AXIS_HEIGHT, x, height
+        ));
+    }
+    let mut y = AXIS_HEIGHT;
+    for (name, indices) in groups {
+        parts.push(format!(
+            r#"<text x="0" y="{}" font-weight="bold">{}</text>"#,
+            y + ROW_HEIGHT - 5,
+            crate::markdown::escape(&label(name))
+        ));
+        y += ROW_HEIGHT;
+        for span in indices.iter().filter_map(|i| spans.get(*i)) {
+            let title = format!(
+                "{}: {} – {}",
+                span.name,
+                format.format_date_time(span.from),
+                format.format_date_time(span.to)
+            );
+            let (x1, x2) = (x_of(span.from), x_of(span.to));
+            let mut shapes = vec![];
+            if x2 > x1 || !span.deadline {
+                shapes.push(format!(
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" rx="3" fill="{}"/>"#,
+                    x1,
+                    y + 4,
+                    (x2 - x1).max(4),
+                    ROW_HEIGHT - 8,
+                    if span.deadline { "#7aa6da" } else { "#9ccc65" }
+                ));
+            }
+            if span.deadline {
+                let (cx, cy) = (x2, y + ROW_HEIGHT / 2);
+                shapes.push(format!(
+                    r##"<polygon points="{},{} {},{} {},{} {},{}" fill="#e06c75"/>"##,
+                    cx,
+                    cy - 5,
+                    cx + 5,
+                    cy,
+                    cx,
+                    cy + 5,
+                    cx - 5,
+                    cy
+                ));
+            }
+            parts.push(format!(
+                r#"<g class="itemlink" onclick='invoke({{ t:"",  i: "{}", a: "Show"}})'><title>{}</title><text x="8" y="{}">{}</text>{}</g>"#,
+                crate::markdown::escape(&span.ident),
+                crate::markdown::escape(&title),
+                y + ROW_HEIGHT - 5,
+                crate::markdown::escape(&label(&span.name)),
+                shapes.join("")
+            ));
+            y += ROW_HEIGHT;
+        }
+    }
+    parts.push("</svg>".to_owned());
+    parts.join("")
+}
+
+/** template data for the timeline */
+#[derive(Template)]
+#[template(path = "timeline.html", print = "none")]
+struct TimelineTemplate {
+    by_tag: bool,
+    date: String,
+    earlier: String,
+    later: String,
+    svg: String,
+    /** the days of collisions, formatted */
+    collisions: Vec<String>,
+}
+/** show the timeline of the weeks around a date (today if it is blank), grouped by project or by tag */
+pub fn show_timeline(
+    world: &mut World,
+    by_tag: bool,
+    date: &str,
+) -> fanling_interface::ResponseResult {
+    let today = chrono::Local::today().naive_local();
+    let date = if date.trim().is_empty() {
+        today
+    } else {
+        world.format().parse_date_time(date)?.date()
+    };
+    let first = date
+        - Duration::days(world.format().day_of_week(date).into())
+        - Duration::weeks(WEEKS_BEFORE);
+    let days = TIMELINE_WEEKS * 7;
+    let last = first + Duration::days(days);
+    let spans = spans(world, first, last)?;
+    let groups = groups(world, &spans, by_tag)?;
+    let collisions: Vec<NaiveDate> = collisions(&spans)
+        .into_iter()
+        .filter(|day| *day >= first && *day < last)
+        .collect();
+    trace(&format!(
+        "{} spans in {} groups, {} collisions",
+        spans.len(),
+        groups.len(),
+        collisions.len()
+    ));
+    let t = TimelineTemplate {
+        by_tag,
+        date: world.format().format_date(date),
+        earlier: world
+            .format()
+            .format_date(date - Duration::weeks(TIMELINE_WEEKS / 2)),
+        later: world
+            .format()
+            .format_date(date + Duration::weeks(TIMELINE_WEEKS / 2)),
+        svg: timeline_svg(&spans, &groups, first, days, today, world.format()),
+        collisions: collisions
+            .iter()
+            .map(|day| world.format().format_date(*day))
+            .collect(),
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    {
+        resp.set_test_data("spans", &format!("{}", spans.len()));
+        resp.set_test_data(
+            "groups",
+            &groups
+                .iter()
+                .map(|(name, _s)| name.clone())
+                .collect::<Vec<String>>()
+                .join(","),
+        );
+        resp.set_test_data("collisions", &format!("{}", collisions.len()));
+    }
+    Ok(resp)
+}
+/** the timeline of the weeks around today as SVG, for exporting (see [`crate::stats::export_chart`]) */
+pub fn timeline_chart(world: &mut World, by_tag: bool) -> FLResult<String> {
+    let today = chrono::Local::today().naive_local();
+    let first = today
+        - Duration::days(world.format().day_of_week(today).into())
+        - Duration::weeks(WEEKS_BEFORE);
+    let days = TIMELINE_WEEKS * 7;
+    let spans = spans(world, first, first + Duration::days(days))?;
+    let groups = groups(world, &spans, by_tag)?;
+    Ok(timeline_svg(
+        &spans,
+        &groups,
+        first,
+        days,
+        today,
+        world.format(),
+    ))
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "timeline {}",
+        Colour::Fixed(0).on(Colour::Fixed(152)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn span(name: &str, from: NaiveDateTime, to: NaiveDateTime, deadline: bool) -> Span {
+        Span {
+            ident: name.to_owned(),
+            name: name.to_owned(),
+            from,
+            to,
+            deadline,
+        }
+    }
+    #[test]
+    fn colliding() {
+        let day = |d| NaiveDate::from_ymd(2020, 6, d);
+        let spans = vec![
+            /* the time up to a deadline does not count, only its day */
+            span(
+                "report",
+                day(1).and_hms(9, 0, 0),
+                day(10).and_hms(17, 0, 0),
+                true,
+            ),
+            span(
+                "trip",
+                day(9).and_hms(8, 0, 0),
+                day(11).and_hms(18, 0, 0),
+                false,
+            ),
+            span(
+                "call",
+                day(3).and_hms(10, 0, 0),
+                day(3).and_hms(11, 0, 0),
+                false,
+            ),
+            span(
+                "review",
+                day(3).and_hms(17, 0, 0),
+                day(3).and_hms(17, 0, 0),
+                true,
+            ),
+        ];
+        let found: Vec<NaiveDate> = collisions(&spans).into_iter().collect();
+        assert_eq!(vec![day(3), day(10)], found);
+        let svg = timeline_svg(
+            &spans,
+            &[
+                ("work".to_owned(), vec![0, 2, 3]),
+                ("No tag".to_owned(), vec![1]),
+            ],
+            day(1),
+            14,
+            day(2),
+            &FormatOptions::default(),
+        );
+        assert_eq!(4, svg.matches("<g class=\"itemlink\"").count());
+        assert_eq!(2, svg.matches("<polygon").count());
+        assert!(svg.contains("more than one thing"));
+    }
+}
@@ -0,0 +1,274 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! time tracking on tasks. Each task keeps the periods when its timer
+was running (see [`TimeRecord`]); only one timer runs at once, so
+starting one stops any other. The running timer is shown at the top of
+the page, and the weekly report sums the time spent on each task and
+on each tag (the context of a task, as given by `#word` in quick
+capture) in a week.
+
+Times are local times, as the report is by local days. */
+use crate::item::{Ident, ItemLink};
+use crate::shared::{FLResult, NullResult};
+use crate::world::World;
+use ansi_term::Colour;
+use askama::Template;
+use chrono::{Duration, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::DerefMut;
+
+/** a period when the timer of a task was running */
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TimeRecord {
+    /** when the timer was started */
+    pub start: NaiveDateTime,
+    /** when it was stopped (`None` while it is running) */
+    #[serde(default)]
+    pub end: Option<NaiveDateTime>,
+}
+impl TimeRecord {
+    /** how much of the period is between two times (up to `now` if it is still running) */
+    fn within(&self, from: NaiveDateTime, to: NaiveDateTime, now: NaiveDateTime) -> Duration {
+        let start = self.start.max(from);
+        let end = self.end.unwrap_or(now).min(to);
+        if end > start {
+            end - start
+        } else {
+            Duration::zero()
+        }
+    }
+}
+
+/** the current local time */
+pub fn now() -> NaiveDateTime {
+    chrono::Local::now().naive_local()
+}
+/** when the running timer was started, if one of the records is running */
+pub fn running_since(records: &[TimeRecord]) -> Option<NaiveDateTime> {
+    records.last().filter(|r| r.end.is_none()).map(|r| r.start)
+}
+/** the total time in the records (up to `now` for a running timer) */
+pub fn total(records: &[TimeRecord], now: NaiveDateTime) -> Duration {
+    records
+        .iter()
+        .map(|r| r.within(r.start, now, now))
+        .fold(Duration::zero(), |a, b| a + b)
+}
+/** a duration as hours and minutes, eg "1:05" */
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes().max(0);
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+/** the timer that is running, with its task */
+#[derive(Debug, Clone)]
+pub struct Running {
+    pub ident: Ident,
+    pub descr: String,
+    pub since: NaiveDateTime,
+}
+/** the timer that is running, if there is one */
+pub fn find_running(world: &mut World) -> FLResult<Option<Running>> {
+    find_running_except(world, "")
+}
+/** the timer that is running for a task other than the one with the
+ident (which may be in use), if there is one */
+fn find_running_except(world: &mut World, ident: &str) -> FLResult<Option<Running>> {
+    for entry in &world.search_type("Task")?.entries {
+        if entry.link.ident == ident {
+            continue;
+        }
+        let item_ref = ItemLink::from(entry.link.clone()).resolve_link(world)?;
+        let item = item_ref.borrow();
+        if let Some(since) = running_since(&item.time_records()) {
+            return Ok(Some(Running {
+                ident: item.ident(),
+                descr: item.description_for_list(),
+                since,
+            }));
+        }
+    }
+    Ok(None)
+}
+/** stop the timer that is running, unless it is for the item with the
+ident (as its timer is about to be started) */
+pub fn stop_others(world: &mut World, ident: &str) -> NullResult {
+    if let Some(running) = find_running_except(world, ident)? {
+        trace(&format!("stopping timer for {}", &running.ident));
+        let item_ref = world.get_item(running.ident, "Task".to_owned())?;
+        let mut item = item_ref.borrow_mut();
+        item.stop_timer(now());
+        world.persist_change(item.deref_mut())?;
+    }
+    Ok(())
+}
+
+/** template data for the running timer, shown at the top of the page */
+#[derive(Template)]
+#[template(path = "timer.html", print = "none")]
+struct TimerTemplate {
+    /** the task whose timer is running (blank if none is) */
+    ident: Ident,
+    descr: String,
+    /** how long it has been running */
+    elapsed: String,
+}
+/** the running timer as HTML (blank if there is none) */
+pub fn render_running(running: &Option<Running>) -> FLResult<String> {
+    let t = match running {
+        Some(running) => TimerTemplate {
+            ident: running.ident.clone(),
+            descr: running.descr.clone(),
+            elapsed: format_duration(now() - running.since),
+        },
+        None => TimerTemplate {
+            ident: "".to_owned(),
+            descr: "".to_owned(),
+            elapsed: "".to_owned(),
+        },
+    };
+    Ok(t.render()?)
+}
+
+/** a line in the weekly report */
+struct ReportLine {
+    /** the ident of the task (blank for a tag) */
+    ident: Ident,
+    descr: String,
+    duration: String,
+}
+/** template data for the weekly time report */
+#[derive(Template)]
+#[template(path = "time-report.html", print = "none")]
+struct ReportTemplate {
+    title: String,
+    previous: String,
+    next: String,
+    tasks: Vec<ReportLine>,
+    tags: Vec<ReportLine>,
+    total: String,
+}
+
+/** the time spent on each task and on each tag in the week containing
+a date (this week if the date is blank) */
+pub fn weekly_report(world: &mut World, date: &str) -> fanling_interface::ResponseResult {
+    let date = if date.trim().is_empty() {
+        chrono::Local::today().naive_local()
+    } else {
+        world.format().parse_date_time(date)?.date()
+    };
+    let first = date - Duration::days(world.format().day_of_week(date).into());
+    let (from, to) = (
+        first.and_hms(0, 0, 0),
+        (first + Duration::days(7)).and_hms(0, 0, 0),
+    );
+    let now = now();
+    let mut tasks = vec![];
+    let mut tags: BTreeMap<String, Duration> = BTreeMap::new();
+    let mut all = Duration::zero();
+    for entry in &world.search_type("Task")?.entries {
+        let item_ref = ItemLink::from(entry.link.clone()).resolve_link(world)?;
+        let item = item_ref.borrow();
+        let spent = item
+            .time_records()
+            .iter()
+            .map(|r| r.within(from, to, now))
+            .fold(Duration::zero(), |a, b| a + b);
+        if spent <= Duration::zero() {
+            continue;
+        }
+        let mut item_tags = item.tags();
+        if item_tags.is_empty() {
+            item_tags.push("(none)".to_owned());
+        }
+        for tag in item_tags {
+            let sum = tags.entry(tag).or_insert_with(Duration::zero);
+            *sum = *sum + spent;
+        }
+        all = all + spent;
+        tasks.push((spent, item.ident(), item.description_for_list()));
+    }
+    tasks.sort_by(|a, b| b.0.cmp(&a.0));
+    trace(&format!(
+        "{} tasks timed in the week of {}",
+        tasks.len(),
+        first
+    ));
+    #[cfg(test)]
+    let test_tags = tags
+        .iter()
+        .map(|(tag, d)| format!("{}={}", tag, format_duration(*d)))
+        .collect::<Vec<String>>()
+        .join(",");
+    let t = ReportTemplate {
+        title: format!("Week of {}", world.format().format_date(first)),
+        previous: world.format().format_date(first - Duration::days(7)),
+        next: world.format().format_date(first + Duration::days(7)),
+        tasks: tasks
+            .into_iter()
+            .map(|(spent, ident, descr)| ReportLine {
+                ident,
+                descr,
+                duration: format_duration(spent),
+            })
+            .collect(),
+        tags: tags
+            .into_iter()
+            .map(|(tag, spent)| ReportLine {
+                ident: "".to_owned(),
+                descr: tag,
+                duration: format_duration(spent),
+            })
+            .collect(),
+        total: format_duration(all),
+    };
+    let mut resp = fanling_interface::Response::new();
+    resp.add_tag("content", &(t.render()?));
+    #[cfg(test)]
+    {
+        resp.set_test_data("total", &t.total);
+        resp.set_test_data("tags", &test_tags);
+    }
+    Ok(resp)
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "timing {}",
+        Colour::Fixed(5).on(Colour::Fixed(230)).paint(m)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    /** a time on 2 January 2020 */
+    fn at(hour: u32, minute: u32) -> NaiveDateTime {
+        chrono::NaiveDate::from_ymd(2020, 1, 2).and_hms(hour, minute, 0)
+    }
+    #[test]
+    fn durations() {
+        let records = vec![
+            TimeRecord {
+                start: at(9, 0),
+                end: Some(at(10, 30)),
+            },
+            TimeRecord {
+                start: at(11, 0),
+                end: None,
+            },
+        ];
+        assert_eq!(Some(at(11, 0)), running_since(&records));
+        assert_eq!("2:05", format_duration(total(&records, at(11, 35))));
+        /* only the part within the times counts */
+        assert_eq!(
+            "0:30",
+            format_duration(records[0].within(at(10, 0), at(12, 0), at(12, 0)))
+        );
+        assert_eq!(None, running_since(&records[..1]));
+    }
+}
@@ -0,0 +1,110 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! transclusion: the text of one item included in another, written
+`{{include:some-ident}}`, so that a page can be made from smaller
+notes without the text being copied.
+
+The included text is the body of the item (its text fields and any
+entries, see [`crate::export`]), which may itself include other
+items. An item that is already being included (so would include
+itself), a private item, one that does not exist and one nested more
+than [`MAX_DEPTH`] deep are not included, but a note saying so is.
+Inclusions in fenced code blocks are left as they are. An old ident
+of an item that has been renamed includes the item. */
+use crate::shared::FLResult;
+use crate::world::World;
+use ansi_term::Colour;
+use regex::Regex;
+
+/** how deeply inclusions can be nested */
+pub const MAX_DEPTH: usize = 5;
+/** an inclusion (the ident only has word characters, dots, dashes and the slashes of namespaces) */
+const INCLUDE_PATTERN: &str = r"\{\{\s*include:\s*([\w./-]+)\s*\}\}";
+
+thread_local! {
+    static INCLUDE: Regex = Regex::new(INCLUDE_PATTERN).expect("bad include regex");
+}
+
+/** a note shown instead of an item that is not included */
+fn note(text: &str) -> String {
+    format!("*({})*", text)
+}
+/** the markdown for an inclusion of the item, given the items already being included */
+fn included(world: &World, ident: &str, stack: &mut Vec<String>) -> FLResult<String> {
+    let ident = world.resolve_alias(&ident.to_owned())?;
+    if stack.contains(&ident) {
+        return Ok(note(&format!("{} would include itself", ident)));
+    }
+    if stack.len() > MAX_DEPTH {
+        return Ok(note(&format!("{} is nested too deeply", ident)));
+    }
+    if !world.has_item(&ident)? {
+        return Ok(note(&format!("there is no item {}", ident)));
+    }
+    let (base, values) = world.get_item_parts(&ident)?;
+    if base.is_private || crate::private::is_sealed(&values) {
+        return Ok(note(&format!("{} is private", ident)));
+    }
+    stack.push(ident.clone());
+    let body = expand(world, &crate::export::body_markdown(&values), stack);
+    stack.pop();
+    Ok(format!(
+        "\n\n<div class=\"transclusion\" title=\"{}\">\n\n{}\n\n</div>\n\n",
+        ident, body?
+    ))
+}
+/** the markdown with each inclusion (outside fenced code) replaced by the item's body */
+fn expand(world: &World, text: &str, stack: &mut Vec<String>) -> FLResult<String> {
+    INCLUDE.with(|include| {
+        let mut lines = vec![];
+        let mut in_fence = false;
+        for line in text.lines() {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_fence = !in_fence;
+            }
+            if in_fence || !include.is_match(line) {
+                lines.push(line.to_owned());
+                continue;
+            }
+            let mut expanded = String::new();
+            let mut last = 0;
+            for caps in include.captures_iter(line) {
+                let whole = caps.get(0).expect("no match");
+                expanded.push_str(&line[last..whole.start()]);
+                expanded.push_str(&included(world, &caps[1], stack)?);
+                last = whole.end();
+            }
+            expanded.push_str(&line[last..]);
+            lines.push(expanded);
+        }
+        Ok(lines.join("\n"))
+    })
+}
+
+/** render the markdown of the item with the ident (blank if it is not
+an item's) to HTML, with the items it includes */
+pub fn render(world: &World, ident: &str, text: &str) -> String {
+    let mut stack = if ident.is_empty() {
+        vec![]
+    } else {
+        vec![ident.to_owned()]
+    };
+    match expand(world, text, &mut stack) {
+        Ok(expanded) => crate::markdown::render(&expanded, &world.render_context()),
+        Err(e) => {
+            trace(&format!("could not include items in {}: {:?}", ident, e));
+            crate::markdown::render(text, &world.render_context())
+        }
+    }
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!(
+        "transclude {}",
+        Colour::Fixed(0).on(Colour::Fixed(180)).paint(m)
+    );
+}
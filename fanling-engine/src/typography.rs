@@ -0,0 +1,163 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! typography for rendered markdown, each part turned on in the
+settings of the repository (see [`crate::settings`]): emoji shortcodes
+(`:tada:` is 🎉) and smart punctuation (curly quotes and apostrophes,
+`--` and `---` as en and em dashes and `...` as an ellipsis).
+
+Both apply to the text of items wherever it is rendered (when shown,
+in previews and when exported), but not to code or wiki links.
+Shortcodes that are not known are left as they are. */
+use regex::{Captures, Regex};
+
+thread_local! {
+    /** a shortcode, with its name (without the colons) as the first group */
+    static SHORTCODE: Regex = Regex::new(r":([a-z0-9_+-]+):").expect("bad shortcode regex");
+}
+
+/** the emoji for the shortcodes that are known */
+const EMOJI: &[(&str, &str)] = &[
+    ("+1", "👍"),
+    ("-1", "👎"),
+    ("bell", "🔔"),
+    ("book", "📖"),
+    ("bookmark", "🔖"),
+    ("bug", "🐛"),
+    ("bulb", "💡"),
+    ("calendar", "📆"),
+    ("check", "✔️"),
+    ("clap", "👏"),
+    ("clock", "🕐"),
+    ("coffee", "☕"),
+    ("construction", "🚧"),
+    ("cry", "😢"),
+    ("eyes", "👀"),
+    ("fire", "🔥"),
+    ("grin", "😁"),
+    ("heart", "❤️"),
+    ("hourglass", "⌛"),
+    ("house", "🏠"),
+    ("joy", "😂"),
+    ("key", "🔑"),
+    ("laughing", "😆"),
+    ("link", "🔗"),
+    ("lock", "🔒"),
+    ("memo", "📝"),
+    ("no_entry", "⛔"),
+    ("ok", "🆗"),
+    ("ok_hand", "👌"),
+    ("pencil", "✏️"),
+    ("pushpin", "📌"),
+    ("question", "❓"),
+    ("recycle", "♻️"),
+    ("rocket", "🚀"),
+    ("smile", "😄"),
+    ("smiley", "😃"),
+    ("sparkles", "✨"),
+    ("star", "⭐"),
+    ("sunny", "☀️"),
+    ("tada", "🎉"),
+    ("thinking", "🤔"),
+    ("thumbsdown", "👎"),
+    ("thumbsup", "👍"),
+    ("umbrella", "☔"),
+    ("warning", "⚠️"),
+    ("wave", "👋"),
+    ("white_check_mark", "✅"),
+    ("wink", "😉"),
+    ("x", "❌"),
+    ("zap", "⚡"),
+];
+
+/** which parts of the typography are used */
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Typography {
+    /** emoji shortcodes are expanded */
+    pub emoji: bool,
+    /** quotes, dashes and ellipses are made typographic */
+    pub smart_punctuation: bool,
+}
+
+/** the emoji for a shortcode (without its colons), if it is known */
+fn emoji(shortcode: &str) -> Option<&'static str> {
+    EMOJI
+        .iter()
+        .find(|(code, _emoji)| *code == shortcode)
+        .map(|(_code, emoji)| *emoji)
+}
+/** the text with the shortcodes that are known replaced by their emoji */
+fn with_emoji(text: &str) -> String {
+    SHORTCODE.with(|shortcode| {
+        shortcode
+            .replace_all(text, |caps: &Captures| {
+                emoji(&caps[1]).map_or_else(|| caps[0].to_owned(), |e| e.to_owned())
+            })
+            .into_owned()
+    })
+}
+
+/** whether a quote after the character (none at the start of the text) opens */
+fn opens(before: Option<char>) -> bool {
+    match before {
+        None => true,
+        Some(c) => c.is_whitespace() || "([{-–—/".contains(c),
+    }
+}
+/** the text with smart punctuation, `before` being the character before it (if any) */
+fn with_smart_punctuation(text: &str, before: Option<char>) -> String {
+    let text = text
+        .replace("---", "—")
+        .replace("--", "–")
+        .replace("...", "…");
+    let mut smart = String::new();
+    let mut previous = before;
+    for c in text.chars() {
+        smart.push(match c {
+            '"' if opens(previous) => '“',
+            '"' => '”',
+            '\'' if opens(previous) => '‘',
+            '\'' => '’',
+            c => c,
+        });
+        previous = Some(c);
+    }
+    smart
+}
+
+/** the text (not code) with the typography, `before` being the
+character before it (if any) */
+pub(crate) fn apply(text: &str, before: Option<char>, typography: Typography) -> String {
+    let text = if typography.emoji {
+        with_emoji(text)
+    } else {
+        text.to_owned()
+    };
+    if typography.smart_punctuation {
+        with_smart_punctuation(&text, before)
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn emoji_shortcodes() {
+        assert_eq!(
+            "done 🎉 at 12:30:45 :nonesuch:",
+            with_emoji("done :tada: at 12:30:45 :nonesuch:")
+        );
+        assert_eq!(Some("👍"), emoji("+1"));
+    }
+    #[test]
+    fn smart_punctuation() {
+        assert_eq!(
+            "“It’s”—she said… ‘pages 1–2’",
+            with_smart_punctuation("\"It's\"---she said... 'pages 1--2'", None)
+        );
+        assert_eq!("” then", with_smart_punctuation("\" then", Some('d')));
+    }
+}
@@ -0,0 +1,336 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! reads and writes vCard 3.0 data (RFC 2426) */
+use crate::format::ISO_DATE_FORMAT;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/** the maximum length of a line before it is folded */
+const MAX_LINE_OCTETS: usize = 75;
+
+/** a single vCard, restricted to the properties that Fanling uses */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VCard {
+    /** formatted name (`FN`) */
+    pub name: String,
+    /** e-mail addresses (`EMAIL`) */
+    pub emails: Vec<String>,
+    /** telephone numbers (`TEL`) */
+    pub phones: Vec<String>,
+    /** postal address (`ADR`), as free text */
+    pub address: String,
+    /** notes (`NOTE`) */
+    pub notes: String,
+    /** date of birth (`BDAY`) */
+    pub birthday: Option<NaiveDate>,
+    /** anniversary (`X-ANNIVERSARY`, or `ANNIVERSARY` in vCard 4.0) */
+    pub anniversary: Option<NaiveDate>,
+    /** an embedded photo (`PHOTO`) */
+    pub photo: Option<Photo>,
+    /** unique identifier (`UID`), if there is one */
+    pub uid: String,
+}
+/** an image embedded in a card (also attached to a contact item) */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Photo {
+    /** such as `image/jpeg` */
+    pub media_type: String,
+    /** the image, base64-encoded */
+    pub data: String,
+}
+impl Photo {
+    /** the photo as a `data:` URI, which can be used as the `src` of an `img` */
+    pub fn to_data_uri(&self) -> String {
+        format!("data:{};base64,{}", self.media_type, self.data)
+    }
+    /** the photo from a `data:` URI (`None` if it is not a base64 image) */
+    pub fn from_data_uri(uri: &str) -> Option<Self> {
+        let rest = uri.trim().strip_prefix("data:")?;
+        let (media_type, data) = rest.split_at(rest.find(";base64,")?);
+        if !media_type.starts_with("image/") {
+            return None;
+        }
+        Some(Photo {
+            media_type: media_type.to_owned(),
+            data: data[";base64,".len()..].to_owned(),
+        })
+    }
+}
+impl VCard {
+    /** serialise as vCard 3.0 text (lines end with CRLF) */
+    pub fn to_vcard_string(&self) -> String {
+        let mut lines = vec!["BEGIN:VCARD".to_owned(), "VERSION:3.0".to_owned()];
+        lines.push(format!("FN:{}", escape(&self.name)));
+        lines.push(format!("N:{}", structured_name(&self.name)));
+        if !self.uid.is_empty() {
+            lines.push(format!("UID:{}", escape(&self.uid)));
+        }
+        for email in &self.emails {
+            lines.push(format!("EMAIL;TYPE=INTERNET:{}", escape(email)));
+        }
+        for phone in &self.phones {
+            lines.push(format!("TEL:{}", escape(phone)));
+        }
+        if !self.address.is_empty() {
+            lines.push(format!("ADR:;;{};;;;", escape(&self.address)));
+        }
+        if !self.notes.is_empty() {
+            lines.push(format!("NOTE:{}", escape(&self.notes)));
+        }
+        if let Some(birthday) = self.birthday {
+            lines.push(format!("BDAY:{}", birthday.format(ISO_DATE_FORMAT)));
+        }
+        if let Some(anniversary) = self.anniversary {
+            lines.push(format!("X-ANNIVERSARY:{}", anniversary.format(ISO_DATE_FORMAT)));
+        }
+        if let Some(photo) = &self.photo {
+            let subtype = photo.media_type.trim_start_matches("image/").to_uppercase();
+            lines.push(format!("PHOTO;ENCODING=b;TYPE={}:{}", subtype, photo.data));
+        }
+        lines.push("END:VCARD".to_owned());
+        let folded: Vec<String> = lines.iter().map(|l| fold(l)).collect();
+        folded.join("\r\n") + "\r\n"
+    }
+}
+
+/** parse vCard text, which may contain several cards */
+pub fn parse(text: &str) -> Vec<VCard> {
+    let mut cards = vec![];
+    let mut current: Option<VCard> = None;
+    let mut structured: String = "".to_owned();
+    for line in unfold(text).lines() {
+        let line = line.trim_end_matches('\r');
+        let colon = match line.find(':') {
+            Some(c) => c,
+            None => continue,
+        };
+        let (params, value) = (&line[..colon], &line[colon + 1..]);
+        let prop = params.split(';').next().unwrap_or("");
+        /* strip any group prefix such as `item1.` */
+        let prop = prop.rsplit('.').next().unwrap_or("").to_uppercase();
+        match prop.as_str() {
+            "BEGIN" if value.eq_ignore_ascii_case("VCARD") => {
+                current = Some(VCard::default());
+                structured = "".to_owned();
+            }
+            "END" if value.eq_ignore_ascii_case("VCARD") => {
+                if let Some(mut card) = current.take() {
+                    if card.name.is_empty() {
+                        card.name = structured.clone();
+                    }
+                    cards.push(card);
+                }
+            }
+            _ => {
+                if let Some(card) = current.as_mut() {
+                    match prop.as_str() {
+                        "FN" => card.name = unescape(value),
+                        "N" => structured = name_from_structured(value),
+                        "EMAIL" => card.emails.push(unescape(value)),
+                        "TEL" => card.phones.push(unescape(value)),
+                        "ADR" => {
+                            let parts: Vec<String> = split_unescaped(value, ';')
+                                .iter()
+                                .map(|p| unescape(p))
+                                .filter(|p| !p.is_empty())
+                                .collect();
+                            card.address = parts.join("\n");
+                        }
+                        "NOTE" => card.notes = unescape(value),
+                        "BDAY" => card.birthday = parse_date(value),
+                        "ANNIVERSARY" | "X-ANNIVERSARY" => card.anniversary = parse_date(value),
+                        "PHOTO" => card.photo = parse_photo(params, value),
+                        "UID" => card.uid = unescape(value),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    cards
+}
+
+/** serialise several cards into one vCard file */
+pub fn to_vcard_text(cards: &[VCard]) -> String {
+    cards
+        .iter()
+        .map(|c| c.to_vcard_string())
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+/** a date such as `1815-12-10` or `18151210` (any time is ignored) */
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    let date = value.split('T').next().unwrap_or("");
+    NaiveDate::parse_from_str(date, ISO_DATE_FORMAT)
+        .or_else(|_| NaiveDate::parse_from_str(date, "%Y%m%d"))
+        .ok()
+}
+/** an embedded photo, either base64 with a `TYPE` parameter (vCard
+3.0) or a `data:` URI (vCard 4.0); photos given only as links are
+ignored */
+fn parse_photo(params: &str, value: &str) -> Option<Photo> {
+    if value.starts_with("data:") {
+        return Photo::from_data_uri(value);
+    }
+    let mut encoded = false;
+    let mut media_type = "image/jpeg".to_owned();
+    for param in params.split(';').skip(1) {
+        let mut kv = param.splitn(2, '=');
+        let key = kv.next().unwrap_or("").to_uppercase();
+        let val = kv.next().unwrap_or("").trim_matches('"');
+        match key.as_str() {
+            "ENCODING" => {
+                encoded = val.eq_ignore_ascii_case("b") || val.eq_ignore_ascii_case("base64")
+            }
+            "TYPE" if val.contains('/') => media_type = val.to_lowercase(),
+            "TYPE" if !val.is_empty() => media_type = format!("image/{}", val.to_lowercase()),
+            _ => {}
+        }
+    }
+    if encoded {
+        Some(Photo {
+            media_type,
+            data: value.split_whitespace().collect(),
+        })
+    } else {
+        None
+    }
+}
+/** make an `N` value (family;given;additional;prefix;suffix) from a formatted name */
+fn structured_name(name: &str) -> String {
+    let words: Vec<&str> = name.split_whitespace().collect();
+    match words.split_last() {
+        None => ";;;;".to_owned(),
+        Some((family, [])) => format!("{};;;;", escape(family)),
+        Some((family, given)) => format!("{};{};;;", escape(family), escape(&given.join(" "))),
+    }
+}
+/** make a formatted name from an `N` value */
+fn name_from_structured(value: &str) -> String {
+    let parts: Vec<String> = split_unescaped(value, ';')
+        .iter()
+        .map(|p| unescape(p))
+        .collect();
+    let mut words: Vec<String> = vec![];
+    for i in &[3, 1, 2, 0, 4] {
+        if let Some(p) = parts.get(*i) {
+            if !p.is_empty() {
+                words.push(p.clone());
+            }
+        }
+    }
+    words.join(" ")
+}
+/** escape a text value (also used for iCalendar) */
+pub(crate) fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+/** reverse [escape] */
+pub(crate) fn unescape(s: &str) -> String {
+    let mut out = String::new();
+    let mut chars = s.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            match chars.next() {
+                Some('n') | Some('N') => out.push('\n'),
+                Some(c) => out.push(c),
+                None => out.push('\\'),
+            }
+        } else {
+            out.push(ch);
+        }
+    }
+    out
+}
+/** split on a separator that is not escaped (the parts are still escaped) */
+fn split_unescaped(s: &str, sep: char) -> Vec<String> {
+    let mut parts = vec![];
+    let mut part = String::new();
+    let mut chars = s.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            part.push(ch);
+            if let Some(c) = chars.next() {
+                part.push(c);
+            }
+        } else if ch == sep {
+            parts.push(part);
+            part = String::new();
+        } else {
+            part.push(ch);
+        }
+    }
+    parts.push(part);
+    parts
+}
+/** fold a long line (continuation lines start with a space) */
+pub(crate) fn fold(line: &str) -> String {
+    let mut out = String::new();
+    let mut len = 0;
+    for ch in line.chars() {
+        if len + ch.len_utf8() > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            len = 1;
+        }
+        out.push(ch);
+        len += ch.len_utf8();
+    }
+    out
+}
+/** reverse [fold] */
+pub(crate) fn unfold(text: &str) -> String {
+    text.replace("\r\n ", "")
+        .replace("\r\n\t", "")
+        .replace("\n ", "")
+        .replace("\n\t", "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn round_trip() {
+        let card = VCard {
+            name: "Ada Lovelace".to_owned(),
+            emails: vec!["ada@example.com".to_owned(), "a.l@example.org".to_owned()],
+            phones: vec!["+44 20 7946 0000".to_owned()],
+            address: "12 St James's Square\nLondon".to_owned(),
+            notes: "Analyst; metaphysician, and founder of scientific computing. ".repeat(3),
+            birthday: Some(NaiveDate::from_ymd(1815, 12, 10)),
+            anniversary: None,
+            photo: Some(Photo {
+                media_type: "image/png".to_owned(),
+                data: "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNk".repeat(2),
+            }),
+            uid: "ada-1@fanling".to_owned(),
+        };
+        let text = card.to_vcard_string();
+        assert!(text.lines().all(|l| l.len() <= MAX_LINE_OCTETS + 1));
+        assert!(text.contains("N:Lovelace;Ada;;;"));
+        assert_eq!(vec![card], parse(&text));
+    }
+    #[test]
+    fn name_from_n_only() {
+        let text = "BEGIN:VCARD\nVERSION:3.0\nN:Hopper;Grace;Brewster;Rear Admiral;\nitem1.EMAIL;TYPE=INTERNET:grace@example.com\nEND:VCARD\n";
+        let cards = parse(text);
+        assert_eq!(1, cards.len());
+        assert_eq!("Rear Admiral Grace Brewster Hopper", cards[0].name);
+        assert_eq!(vec!["grace@example.com".to_owned()], cards[0].emails);
+    }
+    #[test]
+    fn photo_as_data_uri() {
+        let text = "BEGIN:VCARD\nVERSION:4.0\nFN:Alan Turing\nBDAY:19120623\nPHOTO:data:image/jpeg;base64,/9j/4AAQ\nEND:VCARD\n";
+        let cards = parse(text);
+        assert_eq!(Some(NaiveDate::from_ymd(1912, 6, 23)), cards[0].birthday);
+        let photo = cards[0].photo.as_ref().expect("no photo");
+        assert_eq!("image/jpeg", photo.media_type);
+        assert_eq!("data:image/jpeg;base64,/9j/4AAQ", photo.to_data_uri());
+    }
+}
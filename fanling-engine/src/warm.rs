@@ -0,0 +1,110 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! warm starts: the items that were loaded when the engine shut down
+are kept in a snapshot file beside the search database, so that when
+it starts again they can be loaded from there rather than one at a
+time from the repository (which is slow, especially on Android).
+
+The snapshot records the commit at the head of the repository, and is
+only used if the head is the same when the engine starts (otherwise
+the items are loaded from the repository as they are needed, as
+usual). Like the hash of the search database, the snapshot is
+forgotten as soon as it is read, so a snapshot is only used after a
+clean shutdown. There is no snapshot for encrypted repositories or
+private items, as it is not encrypted. */
+use crate::item::{split_data_parts, Ident};
+use crate::shared::{FLResult, NullResult};
+use crate::world::World;
+use ansi_term::Colour;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/** the name of the snapshot file, beside the search database */
+const SNAPSHOT_FILE: &str = "warm-start.json";
+/** the most items kept in a snapshot */
+const MAX_ITEMS: usize = 2_000;
+
+/** the items loaded when the engine shut down */
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Snapshot {
+    /** the commit at the head of the repository */
+    head: String,
+    /** the serialised data of each item */
+    items: BTreeMap<Ident, String>,
+}
+
+/** the path of the snapshot for the search database at `database_path` */
+pub fn path(database_path: &str) -> PathBuf {
+    Path::new(database_path)
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(SNAPSHOT_FILE)
+}
+
+/** write a snapshot of the items that are loaded */
+pub fn save(world: &World, path: &Path) -> NullResult {
+    let head = match world.head_id()? {
+        Some(head) => head,
+        None => return Ok(()),
+    };
+    let mut snapshot = Snapshot {
+        head,
+        items: BTreeMap::new(),
+    };
+    for item_ref in world.known_items().into_iter().take(MAX_ITEMS) {
+        let item = item_ref.borrow();
+        if item.is_private() {
+            continue;
+        }
+        snapshot.items.insert(
+            item.ident(),
+            String::from_utf8_lossy(&item.to_yaml()?).into_owned(),
+        );
+    }
+    crate::integrity::write(path, &serde_json::to_vec(&snapshot)?)?;
+    trace(&format!("saved {} items", snapshot.items.len()));
+    Ok(())
+}
+
+/** load the items in the snapshot (if there is one that is up to date),
+returning how many were loaded */
+pub fn load(world: &mut World, path: &Path) -> FLResult<usize> {
+    let check = crate::integrity::check(path)?;
+    let data = std::fs::read(path);
+    forget(path)?;
+    if check != crate::integrity::Check::Matches {
+        return Ok(0);
+    }
+    let snapshot: Snapshot = serde_json::from_slice(&data?)?;
+    if world.head_id()?.as_ref() != Some(&snapshot.head) {
+        trace("snapshot is stale, not using it");
+        return Ok(0);
+    }
+    let mut count = 0;
+    for (ident, data) in &snapshot.items {
+        let (base, values) = split_data_parts(data.as_bytes())?;
+        if base.ident != *ident {
+            continue;
+        }
+        world.make_known(&values, &base)?;
+        count += 1;
+    }
+    trace(&format!("loaded {} items", count));
+    Ok(count)
+}
+/** remove the snapshot (and its hash) */
+fn forget(path: &Path) -> NullResult {
+    crate::integrity::forget(path)?;
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/** convenience function for debug traces */
+fn trace(m: &str) {
+    println!("warm {}", Colour::Fixed(11).on(Colour::Fixed(236)).paint(m));
+}
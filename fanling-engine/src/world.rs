@@ -5,10 +5,12 @@ file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 /*! implement the top level of the model */
 use crate::fanling_error;
 use crate::fanling_trace;
+use crate::holiday::{HolidayCalendar, HOLIDAYS_IDENT};
 use crate::item::{
-    split_data_parts, Ident, Item, ItemBaseForSerde, ItemKind, ItemLink, ItemListEntryList,
-    ItemRef, ItemType, SpecialKind,
+    split_data_parts, Ident, Item, ItemBaseForSerde, ItemKind, ItemLink, ItemListEntry,
+    ItemListEntryList, ItemRef, ItemType, SpecialKind,
 };
+use crate::repos::OpenRepo;
 use crate::search::Search;
 use crate::shared::{FLResult, FanlingError, NullResult, Tracer};
 use crate::store::Store;
@@ -16,9 +18,10 @@ use askama::Template;
 use fanling_interface::error_response_result;
 use log::trace;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryInto;
 use std::ops::Deref;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use taipo_git_control::{MergeOutcome, RepoActionRequired};
 
@@ -30,14 +33,70 @@ pub struct World {
     search: Search,
     /** the item types */
     item_type_registry: crate::item::ItemTypeRegistry,
+    /** the kinds of item added by plugins (see [`crate::plugin`]) */
+    plugin_kinds: Vec<crate::kinds::KindInfo>,
     /** type of interface that is connected to the engine*/
     interface_type: crate::InterfaceType,
-    /** unique prefix for items created on this instance to ensure that idents aer unique */
-    uniq_pfx: String,
+    /** whether the interface serves the assets of the page (see [`crate::assets`]) */
+    serve_assets: bool,
+    /** the kinds of item loaded when all the items are loaded (see [`crate::sparse`]) */
+    sparse_kinds: Vec<String>,
+    /** how mathematics in markdown is shown (see [`crate::math`]) */
+    math: crate::math::MathRendering,
+    /** the emoji and smart punctuation of the repository (see [`crate::typography`]) */
+    typography: crate::typography::Typography,
+    /** how the last pull from and push to each remote went (see [`crate::remotes`]) */
+    remote_results: BTreeMap<String, crate::remotes::Results>,
+    /** whether commits have been made since the remotes pushed to on save were pushed to (see [`crate::remotes`]) */
+    push_pending: bool,
+    /** makes the idents of new items (see [`crate::idents`]) */
+    ident_generator: Box<dyn crate::idents::IdentGenerator>,
+    /** items whose idents collide with those of others, found when the items were loaded */
+    collisions: Vec<crate::idents::Collision>,
+    /** the current ident of each item that has been renamed, by its old ident */
+    aliases: BTreeMap<Ident, Ident>,
     /** context to use when creating a new task */
     default_context: Option<ItemRef>,
     /** automatically generate items for missing items in links */
     auto_link: bool,
+    /** directory for exported files such as charts */
+    export_dir: PathBuf,
+    /** directory for the files kept locally beside the search database (such as the drafts) */
+    local_dir: PathBuf,
+    /** how dates, times and numbers are formatted */
+    format: crate::FormatOptions,
+    /** translates the text shown to the user */
+    i18n: Rc<crate::i18n::Translator>,
+    /** name of the current repository */
+    repo_name: String,
+    /** the other open repositories */
+    other_repos: Vec<OpenRepo>,
+    /** whether lists include items from the other repositories */
+    cross_repo_search: bool,
+    /** unsaved edits, kept locally */
+    drafts: crate::draft::Drafts,
+    /** the items rendered in advance, as they are likely to be shown next */
+    prefetched: crate::prefetch::Prefetched,
+    /** where the snapshot for warm starts is kept (none if the repository is encrypted) */
+    warm_start: Option<PathBuf>,
+    /** how many items were loaded from the snapshot when starting */
+    warm_started: usize,
+    /** the view sessions, each with the items shown in it */
+    sessions: crate::session::Sessions,
+    /** where the items shown recently are kept (none if not asked for, or if the repository is encrypted) */
+    history: Option<PathBuf>,
+    /** the timer that is running, if known (found again after any action that may change it) */
+    running_timer: Option<Option<crate::timing::Running>>,
+    /** the report of the latest operation that was rolled back, until it is shown */
+    recovery: Option<crate::rollback::Report>,
+    /** the scripts that have failed since the last action (see [`crate::automation`]) */
+    script_failures: Vec<String>,
+    /** the outbound hooks, with the posts waiting to be delivered (see [`crate::hooks`]) */
+    hooks: crate::hooks::Hooks,
+    /** the CalDAV and CardDAV server that events and contacts are synced with (see [`crate::dav`]) */
+    dav: Option<crate::dav::Dav>,
+    /** the mailbox that messages are captured from (see [`crate::mail`]) */
+    mail: Option<crate::mail::MailOptions>,
 }
 impl<'a> World {
     /** create a new [World]  */
@@ -48,31 +107,434 @@ impl<'a> World {
         item_type_registry.register(simple_itr);
         let task_itr = crate::item::ItemType::new(crate::task::TaskTypePolicy::new_boxed());
         item_type_registry.register(task_itr);
-        let (search, _new_db) = Search::new_and_open(&opts.search_options)?;
-        let (last_ident, _ident_prefix) = search.read_global()?;
-        let (mut store, repo_action_required) = Store::new_and_open(&opts.repo_options)?;
-        store.set_next_ident_num(last_ident.into());
+        let contact_itr =
+            crate::item::ItemType::new(crate::contact::ContactTypePolicy::new_boxed());
+        item_type_registry.register(contact_itr);
+        let checklist_itr =
+            crate::item::ItemType::new(crate::checklist::ChecklistTypePolicy::new_boxed());
+        item_type_registry.register(checklist_itr);
+        let event_itr = crate::item::ItemType::new(crate::event::EventTypePolicy::new_boxed());
+        item_type_registry.register(event_itr);
+        let snippet_itr =
+            crate::item::ItemType::new(crate::snippet::SnippetTypePolicy::new_boxed());
+        item_type_registry.register(snippet_itr);
+        let meeting_itr =
+            crate::item::ItemType::new(crate::meeting::MeetingTypePolicy::new_boxed());
+        item_type_registry.register(meeting_itr);
+        let bookmark_itr =
+            crate::item::ItemType::new(crate::bookmark::BookmarkTypePolicy::new_boxed());
+        item_type_registry.register(bookmark_itr);
+        let reference_itr =
+            crate::item::ItemType::new(crate::reference::ReferenceTypePolicy::new_boxed());
+        item_type_registry.register(reference_itr);
+        let habit_itr = crate::item::ItemType::new(crate::habit::HabitTypePolicy::new_boxed());
+        item_type_registry.register(habit_itr);
+        let project_itr =
+            crate::item::ItemType::new(crate::project::ProjectTypePolicy::new_boxed());
+        item_type_registry.register(project_itr);
+        let foreign_itr =
+            crate::item::ItemType::new(crate::foreign::ForeignTypePolicy::new_boxed());
+        item_type_registry.register(foreign_itr);
+        let mut plugin_kinds = vec![];
+        for plugin in &opts.plugins {
+            crate::plugin::register(&mut item_type_registry, &mut plugin_kinds, plugin.as_ref())?;
+        }
+        let (mut store, search, repo_action_required, load_all) = Self::open_repo(
+            &opts.repo_options,
+            &opts.search_options,
+            &opts.commit_options,
+            &opts.encryption,
+        )?;
+        store.set_read_only(opts.read_only);
+        let mut other_repos = vec![];
+        for other in &opts.repos.others {
+            let (mut store, search, action_required, other_load_all) = Self::open_repo(
+                &other.repo_options,
+                &other.search_options,
+                &opts.commit_options,
+                &opts.encryption,
+            )?;
+            store.set_read_only(other.read_only);
+            other_repos.push(OpenRepo {
+                name: other.name.clone(),
+                store,
+                search,
+                action_required: if other_load_all {
+                    // the search for an encrypted repository (or a rebuilt one) starts empty
+                    Some(RepoActionRequired::LoadAll)
+                } else if other.repo_options.url.is_some() {
+                    Some(action_required)
+                } else {
+                    None
+                },
+            });
+        }
         let mut world = Self {
             store,
             search,
             item_type_registry,
+            plugin_kinds,
             interface_type: opts.interface_type,
-            uniq_pfx: opts.uniq_pfx.clone(),
+            serve_assets: opts.serve_assets,
+            sparse_kinds: opts.sparse_kinds.clone(),
+            math: opts.math,
+            typography: crate::typography::Typography::default(),
+            remote_results: BTreeMap::new(),
+            push_pending: false,
+            ident_generator: crate::idents::generator(opts.ident_scheme, &opts.uniq_pfx)?,
+            collisions: vec![],
+            aliases: BTreeMap::new(),
             default_context: None,
             auto_link: opts.auto_link,
+            export_dir: Path::new(&opts.search_options.database_path)
+                .parent()
+                .unwrap_or_else(|| Path::new("."))
+                .join("exports"),
+            local_dir: Path::new(&opts.search_options.database_path)
+                .parent()
+                .unwrap_or_else(|| Path::new("."))
+                .to_path_buf(),
+            format: opts.format_options.clone(),
+            i18n: Rc::new(crate::i18n::Translator::new(&opts.format_options.locale)?),
+            repo_name: opts.repos.name.clone(),
+            other_repos,
+            cross_repo_search: opts.repos.cross_repo_search,
+            drafts: crate::draft::Drafts::open(
+                Path::new(&opts.search_options.database_path)
+                    .parent()
+                    .unwrap_or_else(|| Path::new(".")),
+                !opts.encryption.enabled,
+            ),
+            prefetched: crate::prefetch::Prefetched::default(),
+            warm_start: if opts.encryption.enabled {
+                None
+            } else {
+                Some(crate::warm::path(&opts.search_options.database_path))
+            },
+            warm_started: 0,
+            sessions: crate::session::Sessions::default(),
+            history: if opts.keep_history && !opts.encryption.enabled {
+                Some(crate::session::history_path(
+                    &opts.search_options.database_path,
+                ))
+            } else {
+                None
+            },
+            running_timer: None,
+            recovery: None,
+            script_failures: vec![],
+            hooks: crate::hooks::Hooks::open(
+                &opts.hooks,
+                Path::new(&opts.search_options.database_path)
+                    .parent()
+                    .unwrap_or_else(|| Path::new(".")),
+                !opts.encryption.enabled,
+                !matches!(opts.interface_type, crate::InterfaceType::Android),
+            ),
+            dav: crate::dav::Dav::open(
+                &opts.dav,
+                Path::new(&opts.search_options.database_path)
+                    .parent()
+                    .unwrap_or_else(|| Path::new(".")),
+            ),
+            mail: opts.mail.clone(),
         };
         // if new_db {
         //     world.get_all()?;
         // }
-        if opts.repo_options.url.is_some() {
+        let loaded = load_all;
+        if loaded {
+            trace("loading all items into search...");
+            world.process_fetch_changes(RepoActionRequired::LoadAll)?;
+        }
+        if opts.repo_options.url.is_some()
+            && !(loaded && matches!(repo_action_required, RepoActionRequired::LoadAll))
+        {
             trace("fetching changes...");
             world.process_fetch_changes(repo_action_required)?;
         }
+        if let Some(path) = world.warm_start.clone() {
+            if !loaded {
+                world.warm_started = crate::warm::load(&mut world, &path)?;
+                trace(&format!("warm start with {} items", world.warm_started));
+            }
+        }
+        if let Some(path) = world.history.clone() {
+            world.sessions = crate::session::load_history(&path)?;
+        }
+        world.load_aliases()?;
+        world.load_typography()?;
         trace("ensuring some items...");
         world.ensure_some_items()?;
+        world.note_head()?;
         trace("created world.");
         Ok(world)
     }
+    /** open the store and search for a repository. Also returns
+    whether all the items have to be loaded into the search. */
+    fn open_repo(
+        repo_options: &taipo_git_control::RepoOptions,
+        search_options: &crate::SearchOptions,
+        commit_options: &crate::CommitOptions,
+        encryption: &crate::EncryptionOptions,
+    ) -> FLResult<(Store, Search, RepoActionRequired, bool)> {
+        let (mut store, repo_action_required) = Store::new_and_open(repo_options)?;
+        let (search, load_all) = if encryption.enabled {
+            store.set_passphrase(encryption.passphrase.as_deref().unwrap_or(""))?;
+            let (search, _new_db) = Search::new_and_open(&crate::SearchOptions {
+                database_path: crate::crypt::IN_MEMORY_DATABASE.to_owned(),
+            })?;
+            store.set_next_ident_num(store.max_ident_num()?.try_into()?);
+            (search, true)
+        } else {
+            let (mut search, mut rebuild) = Self::open_search(search_options)?;
+            if !rebuild && Self::is_stale(&store, &search)? {
+                trace("search database is behind the repository, rebuilding");
+                search.clear()?;
+                rebuild = true;
+            }
+            if rebuild {
+                store.set_next_ident_num(store.max_ident_num()?.try_into()?);
+            } else {
+                let (last_ident, _ident_prefix) = search.read_global()?;
+                store.set_next_ident_num(last_ident.into());
+            }
+            (search, rebuild)
+        };
+        let mut commit_options = commit_options.clone();
+        if let Some(template) = store.config_value(crate::settings::TEMPLATE_KEY)? {
+            commit_options.template = template;
+        }
+        if let Some(append) = store.config_value(crate::settings::APPEND_DEVICE_KEY)? {
+            commit_options.append_device = append == "true";
+        }
+        if let Some(generate) = store.config_value(crate::settings::GENERATE_INDEX_KEY)? {
+            commit_options.generate_index = generate == "true";
+        }
+        store.set_commit_options(commit_options);
+        Ok((store, search, repo_action_required, load_all))
+    }
+    /** open the search database, starting again with an empty one if
+    it has been corrupted. Also returns whether it has to be rebuilt. */
+    fn open_search(search_options: &crate::SearchOptions) -> FLResult<(Search, bool)> {
+        let path = Path::new(&search_options.database_path);
+        let corrupt = crate::integrity::check(path)? == crate::integrity::Check::Mismatch;
+        crate::integrity::forget(path)?;
+        if corrupt {
+            trace("search database does not match its hash, rebuilding");
+            std::fs::remove_file(path)?;
+        }
+        match Search::new_and_open(search_options) {
+            Ok((search, _new_db)) => Ok((search, corrupt)),
+            Err(e) if !corrupt => {
+                trace(&format!("cannot open search database ({:?}), rebuilding", e));
+                let _ = std::fs::remove_file(path);
+                let (search, _new_db) = Search::new_and_open(search_options)?;
+                Ok((search, true))
+            }
+            Err(e) => Err(e),
+        }
+    }
+    /** whether the search database was last brought up to date with a
+    different commit from the latest one, as when the repository has
+    been changed by something other than the app. (If it has not noted
+    a commit, as for a new database, it is trusted.) */
+    fn is_stale(store: &Store, search: &Search) -> FLResult<bool> {
+        let head = search.head()?;
+        Ok(!head.is_empty() && store.head_id()?.map_or(false, |id| id != head))
+    }
+    /** whether there has been a commit since the head was last noted */
+    fn head_moved(&self) -> FLResult<bool> {
+        Ok(match self.store.head_id()? {
+            Some(head) => self.search.head()? != head,
+            None => false,
+        })
+    }
+    /** note the commit that the search database is now up to date
+    with, first updating the generated files if there has been a commit */
+    fn note_head(&mut self) -> NullResult {
+        if let Some(head) = self.store.head_id()? {
+            if self.search.head()? != head {
+                crate::companion::update_files(self)?;
+                let head = self.store.head_id()?.unwrap_or(head);
+                self.search.set_head(&head)?;
+            }
+        }
+        Ok(())
+    }
+    /** record the hashes of the search databases, so that they can be
+    checked when they are next opened */
+    pub fn record_hashes(&self) -> NullResult {
+        self.search.record_hash()?;
+        for other in &self.other_repos {
+            other.search.record_hash()?;
+        }
+        Ok(())
+    }
+    /** keep a snapshot of the items in use, for a warm start next time */
+    pub fn save_warm_start(&self) -> NullResult {
+        match &self.warm_start {
+            Some(path) => crate::warm::save(self, path),
+            None => Ok(()),
+        }
+    }
+    /** keep the items shown recently, if asked for */
+    pub fn save_history(&self) -> NullResult {
+        match &self.history {
+            Some(path) => crate::session::save_history(&self.sessions, path),
+            None => Ok(()),
+        }
+    }
+    /** how many items were loaded from the snapshot when starting */
+    #[cfg(test)]
+    pub fn warm_started(&self) -> usize {
+        self.warm_started
+    }
+    /** the items that have been loaded */
+    pub fn known_items(&self) -> Vec<ItemRef> {
+        self.store.known_items()
+    }
+    /** take the raw data for an item (as from a snapshot) and make it ready to use */
+    pub fn make_known(
+        &mut self,
+        serde_value: &serde_yaml::Value,
+        base: &ItemBaseForSerde,
+    ) -> FLResult<ItemRef> {
+        self.get_and_make_known(serde_value, base)
+    }
+    /** the name of the current repository */
+    pub fn repo_name(&self) -> String {
+        self.repo_name.clone()
+    }
+    /** the commit at the head of the current repository, if any */
+    pub fn head_id(&self) -> FLResult<Option<String>> {
+        self.store.head_id()
+    }
+    /** keep the report of an operation that was rolled back, to be shown */
+    pub fn set_recovery(&mut self, report: crate::rollback::Report) {
+        self.recovery = Some(report);
+    }
+    /** the report of the latest operation that was rolled back, if it has not been shown yet */
+    pub fn take_recovery(&mut self) -> Option<crate::rollback::Report> {
+        self.recovery.take()
+    }
+    /** the items added or changed since a commit */
+    pub fn changed_items(&self, id: &str) -> FLResult<Vec<Ident>> {
+        self.store.changed_items(id)
+    }
+    /** move the current repository back to a commit, discarding the
+    ones after it, and load the items again */
+    pub fn roll_back_to(&mut self, id: &str) -> NullResult {
+        trace(&format!("rolling back to {}", id));
+        self.store.reset_to(id)?;
+        self.default_context = None;
+        self.get_all()?;
+        self.ensure_some_items()
+    }
+    /** whether the item is in the current repository */
+    pub fn has_item(&self, ident: &str) -> FLResult<bool> {
+        self.store.has_file(ident)
+    }
+    /** the items rendered in advance */
+    pub fn prefetched(&mut self) -> &mut crate::prefetch::Prefetched {
+        &mut self.prefetched
+    }
+    /** the view sessions */
+    pub fn sessions(&mut self) -> &mut crate::session::Sessions {
+        &mut self.sessions
+    }
+    /** whether there are commits that have not been pushed yet */
+    pub fn needs_push(&self) -> bool {
+        self.store.does_need_pushing()
+    }
+    /** the names of all the open repositories */
+    pub fn repo_names(&self) -> Vec<String> {
+        let mut names = vec![self.repo_name.clone()];
+        names.extend(self.other_repos.iter().map(|r| r.name.clone()));
+        names
+    }
+    /** make the named repository the current one */
+    pub fn select_repo(&mut self, name: &str) -> NullResult {
+        if name == self.repo_name {
+            return Ok(());
+        }
+        let other = self
+            .other_repos
+            .iter_mut()
+            .find(|r| r.name == name)
+            .ok_or_else(|| fanling_error!(&format!("no repository '{}'", name)))?;
+        std::mem::swap(&mut self.store, &mut other.store);
+        std::mem::swap(&mut self.search, &mut other.search);
+        std::mem::swap(&mut self.repo_name, &mut other.name);
+        let action_required = other.action_required.take();
+        trace(&format!("current repository is now {}", name));
+        self.default_context = None;
+        if let Some(action_required) = action_required {
+            self.process_fetch_changes(action_required)?;
+        }
+        self.load_aliases()?;
+        self.load_typography()?;
+        self.ensure_some_items()
+    }
+    /** if the request is for an item in another repository (with a
+    qualified ident), make that repository current and return the
+    request with the ident unqualified */
+    fn unqualified_request(
+        &mut self,
+        basic_request: &crate::BasicRequest,
+    ) -> FLResult<Option<crate::BasicRequest>> {
+        let (repo, ident) = match basic_request
+            .ident
+            .as_ref()
+            .and_then(|i| crate::repos::split_qualified(i))
+        {
+            Some((repo, ident)) if self.repo_names().iter().any(|n| n == repo) => {
+                (repo.to_owned(), ident.to_owned())
+            }
+            _ => return Ok(None),
+        };
+        self.select_repo(&repo)?;
+        Ok(Some(crate::BasicRequest {
+            ident: Some(ident),
+            ..basic_request.clone()
+        }))
+    }
+    /** the entries for the items in the other repositories (if
+    cross-repository search is enabled), with qualified idents */
+    fn other_repo_entries(&self, open_only: bool) -> FLResult<Vec<ItemListEntry>> {
+        let mut entries = vec![];
+        if !self.cross_repo_search {
+            return Ok(entries);
+        }
+        for other in &self.other_repos {
+            let list = if open_only {
+                other.search.search_open_hier()?
+            } else {
+                other.search.search_all_hier()?
+            };
+            for mut entry in list.entries {
+                entry.link.ident = crate::repos::qualify(&other.name, &entry.link.ident);
+                entry.descr = format!("[{}] {}", &other.name, &entry.descr);
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+    /** the type of the item with the qualified ident in another open
+    repository (`None` if the repository is not open or has no such item) */
+    pub fn other_repo_item_type(&self, qualified: &str) -> FLResult<Option<String>> {
+        let (repo, ident) = match crate::repos::split_qualified(qualified) {
+            Some(parts) => parts,
+            None => return Ok(None),
+        };
+        match self.other_repos.iter().find(|r| r.name == repo) {
+            Some(other) if other.store.has_file(ident)? => {
+                let (base, _values) = other.store.get_item_parts(&ident.to_owned())?;
+                Ok(Some(base.type_name))
+            }
+            _ => Ok(None),
+        }
+    }
     /** handle any changes to the data that come from the new state of
     the repository after a fetch */
     fn process_fetch_changes(&mut self, repo_action_required: RepoActionRequired) -> NullResult {
@@ -84,9 +546,24 @@ impl<'a> World {
             RepoActionRequired::NoAction => Ok(()),
             RepoActionRequired::LoadAll => {
                 fanling_trace!("loading all items");
+                self.collisions = self.find_collisions()?;
+                for collision in &self.collisions {
+                    trace(&format!(
+                        "ident collision: {} {}",
+                        collision.ident, collision.descr
+                    ));
+                }
                 for entry in self.store.list_all_items()? {
                     // trace(&format!("should load {:?}", entry));
                     let (base, values) = split_data_parts(entry.blob.as_bytes())?;
+                    if self.claims_other_ident(&entry.path, &base.ident) {
+                        continue;
+                    }
+                    if !crate::sparse::is_wanted(self, &entry.path, &base.type_name) {
+                        let name = crate::sparse::stub_name(&base.ident, &values);
+                        self.search.add_stub(&base.ident, &base.type_name, &name)?;
+                        continue;
+                    }
                     let ident = self.make_known(&values, base)?;
                     let path_from_ident = self.store.path_from_ident(&ident);
                     if path_from_ident != entry.path {
@@ -118,27 +595,50 @@ impl<'a> World {
             }
         }
     }
+    /** pull from each of the remotes that are pulled from (see [`crate::remotes`]) */
     fn pull(&mut self) -> NullResult {
         fanling_trace!("pulling");
-        if self.store.has_remote() {
-            fanling_trace!("fetching");
-            let fr: NullResult = self.store.fetch();
-            if fr.is_err() {
-                trace(&format!("fetch was error ({:#?})", &fr));
-            }
-            fanling_trace!(&format!("fetch result {:#?}", &fr));
-            fr?;
-            let mut merge_outcome = self.store.merge()?;
-            trace(&format!("fetch result was {:?}", merge_outcome,));
-            match merge_outcome {
-                MergeOutcome::AlreadyUpToDate => {}
-                MergeOutcome::Merged(_) | MergeOutcome::Conflict(_) => {
-                    self.store.set_needs_push();
-                    self.handle_merge_outcome(&mut merge_outcome)?;
-                    self.store.commit_merge(&mut merge_outcome)?;
+        crate::automation::run(self, crate::automation::Event::BeforeSync, None);
+        self.hooks.retry()?;
+        if let Err(e) = crate::dav::sync(self) {
+            trace(&format!(
+                "could not sync with the CalDAV or CardDAV server: {:?}",
+                e
+            ));
+        }
+        if !self.is_read_only() {
+            if let Err(e) = crate::mail::poll(self) {
+                trace(&format!("could not fetch mail: {:?}", e));
+            }
+        }
+        crate::remotes::pull_all(self)
+    }
+    /** fetch from the remote and merge what was fetched */
+    pub fn pull_from(&mut self, remote: &str) -> NullResult {
+        fanling_trace!("fetching");
+        let fr: NullResult = self.store.fetch_from(remote);
+        if fr.is_err() {
+            trace(&format!("fetch was error ({:#?})", &fr));
+        }
+        fanling_trace!(&format!("fetch result {:#?}", &fr));
+        fr?;
+        let before = self.store.head_id()?;
+        let mut merge_outcome = self.store.merge()?;
+        trace(&format!("fetch result was {:?}", merge_outcome,));
+        let conflicts = merge_outcome.num_conflicts()?;
+        match merge_outcome {
+            MergeOutcome::AlreadyUpToDate => {}
+            MergeOutcome::Merged(_) | MergeOutcome::Conflict(_) => {
+                self.store.set_needs_push();
+                self.handle_merge_outcome(&mut merge_outcome)?;
+                self.store.commit_merge(&mut merge_outcome)?;
+                if let Some(report) = crate::rollback::check_since(self, "merge", &before)? {
+                    self.recovery = Some(report);
                 }
+                self.load_aliases()?;
             }
         }
+        crate::sync::record(self, Some(conflicts))?;
         Ok(())
     }
 
@@ -148,6 +648,10 @@ impl<'a> World {
         let mut changes = vec![];
         for conflict in &self.store.conflicts(mo)? {
             trace(&format!("conflict: {:?}", &conflict));
+            if let Some(change) = crate::companion::resolve_conflict(&conflict) {
+                changes.push(change);
+                continue;
+            }
             let mut type_name: Option<String> = None;
             let anc = match &conflict.ancestor {
                 Some(ie) => {
@@ -222,7 +726,7 @@ impl<'a> World {
         let contexts = self.search_contexts()?.entries;
         trace(&format!("contexts are {:?}", &contexts));
         /* FUTURE seemed to be generating default context even when one already exists. This sems to be fixed now, but check. */
-        if contexts.is_empty() {
+        if contexts.is_empty() && !self.is_read_only() {
             self.ensure_some_context()?;
         }
         Ok(())
@@ -246,7 +750,7 @@ impl<'a> World {
         Ok(())
     }
     /** create an item */
-    fn ensure_item(&mut self, ident: Ident, type_name: Ident) -> FLResult<ItemRef> {
+    pub fn ensure_item(&mut self, ident: Ident, type_name: Ident) -> FLResult<ItemRef> {
         //  let type_name = "Simple".to_owned();
         let base = ItemBaseForSerde {
             ident: ident.to_owned(),
@@ -258,10 +762,21 @@ impl<'a> World {
         let mut vals = HashMap::new();
         vals.insert("name".to_owned(), ident);
         match type_name.as_str() {
-            "Simple" => {}
+            "Simple" | "Contact" | "Checklist" | "Snippet" | "Meeting" | "Reference" | "Habit"
+            | "Project" => {}
             "Task" => {
                 vals.insert("context".to_string(), "default_context".to_string());
             }
+            "Bookmark" => {
+                vals.insert("url".to_string(), "".to_string());
+            }
+            "Event" => {
+                let today = chrono::Local::today().naive_local();
+                vals.insert(
+                    "start".to_string(),
+                    today.format(crate::format::ISO_DATE_FORMAT).to_string(),
+                );
+            }
             _ => return Err(fanling_error!(&format!("invalid type '{}'", &type_name))),
         }
         let res = self.make_item(&type_name, &base, &vals)?;
@@ -278,13 +793,25 @@ impl<'a> World {
             Some(c) => Ok(ItemLink::from(c)),
         }
     }
-    /** interpret an ident as an item kind */
-    pub fn item_kind(type_ident: &Ident) -> ItemKind {
-        match type_ident.as_str() {
-            "simple" | "Simple" => ItemKind::Simple,
-            "task" | "Task" | "todo" => ItemKind::Task,
-            _ => panic!(format!("bad type ident: {}", &type_ident)),
-        }
+    /** map an item type name to an [ItemKind]. Types that are not
+    known (as from a newer version, or a plugin that is not loaded) are
+    foreign (see [`crate::foreign`]). */
+    pub fn item_kind(&self, type_ident: &Ident) -> ItemKind {
+        self.kind_of(type_ident).unwrap_or(ItemKind::Foreign)
+    }
+    /** the kind of item for a type name, if it is a known one (including those of the plugins) */
+    pub fn kind_of(&self, type_ident: &str) -> Option<ItemKind> {
+        crate::kinds::find(type_ident)
+            .or_else(|| crate::plugin::find(&self.plugin_kinds, type_ident))
+            .map(|k| k.kind)
+    }
+    /** the kinds of item, with those added by the plugins after the built-in ones */
+    pub fn kinds(&self) -> Vec<crate::kinds::KindInfo> {
+        crate::kinds::kinds()
+            .iter()
+            .chain(self.plugin_kinds.iter())
+            .cloned()
+            .collect()
     }
     /** make an [`Item`] and add it to the store and search */
     pub fn make_item(
@@ -292,6 +819,17 @@ impl<'a> World {
         type_name: &str,
         base: &ItemBaseForSerde,
         vals: &HashMap<String, String>,
+    ) -> crate::shared::FLResult<crate::item::ItemRef> {
+        self.make_item_in("", type_name, base, vals)
+    }
+    /** make an item as [`World::make_item`] does, but in the namespace
+    (none if blank, see [`crate::namespace`]) */
+    pub fn make_item_in(
+        &mut self,
+        namespace: &str,
+        type_name: &str,
+        base: &ItemBaseForSerde,
+        vals: &HashMap<String, String>,
     ) -> crate::shared::FLResult<crate::item::ItemRef> {
         // let item_type_rcrc: Rc<RefCell<ItemType>> = self
         //     .item_type_registry
@@ -307,7 +845,21 @@ impl<'a> World {
         if descr.is_empty() {
             return Err(fanling_error!("description must not be blank"));
         }
-        item.set_ident(self.store.make_identifier(&self.uniq_pfx, &descr));
+        let ident = self
+            .store
+            .make_identifier(self.ident_generator.as_mut(), &descr)?;
+        if namespace.is_empty() {
+            item.set_ident(ident);
+        } else {
+            let ident = format!("{}{}{}", namespace, crate::namespace::SEPARATOR, ident);
+            if let Some(problem) = crate::namespace::check_ident(&ident) {
+                return Err(fanling_error!(problem));
+            }
+            if self.store.has_file(&ident)? {
+                return Err(fanling_error!(&format!("'{}' is already used", ident)));
+            }
+            item.set_ident(ident);
+        }
         assert!(item.ident() != "", "ident is null");
         self.search
             .update_last_ident(self.store.get_next_ident_num().try_into()?)?;
@@ -321,9 +873,33 @@ impl<'a> World {
         trace("made item.");
         Ok(item_rcrc)
     }
+    /** the outbound hooks (see [`crate::hooks`]) */
+    pub fn hooks(&self) -> &crate::hooks::Hooks {
+        &self.hooks
+    }
+    /** the outbound hooks, to call them */
+    pub fn hooks_mut(&mut self) -> &mut crate::hooks::Hooks {
+        &mut self.hooks
+    }
+    /** the CalDAV and CardDAV server, if there is one (see [`crate::dav`]) */
+    pub fn dav(&self) -> Option<&crate::dav::Dav> {
+        self.dav.as_ref()
+    }
+    /** the mailbox, if there is one (see [`crate::mail`]) */
+    pub fn mail(&self) -> Option<&crate::mail::MailOptions> {
+        self.mail.as_ref()
+    }
+    /** keep the failure of a script, to be shown after the action (see [`crate::automation`]) */
+    pub fn note_script_failure(&mut self, failure: &str) {
+        self.script_failures.push(failure.to_owned());
+    }
+    /** the scripts that have failed since this was last called */
+    pub fn take_script_failures(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.script_failures)
+    }
     /** get the item type with a given name */
     pub fn get_item_type(&mut self, type_name: Ident) -> FLResult<Rc<RefCell<ItemType>>> {
-        Ok(self.item_type_registry.get(Self::item_kind(&type_name))?)
+        Ok(self.item_type_registry.get(self.item_kind(&type_name))?)
         //  Ok(item_type_rf.deref().borrow())
     }
     /** check that an item would be valid */
@@ -336,8 +912,31 @@ impl<'a> World {
     ) -> FLResult<ActionResponse> {
         let mut item_type = item_type_rf.deref().borrow_mut();
         //    let item = self.get_item(ident)?;
-        let ar = item_type.check_valid(base, vals, self);
-        Ok(ar)
+        let mut ar = item_type.check_valid(base, vals, self);
+        if !base.is_private && !self.is_encrypted() {
+            self.secret_policy()?.check(vals, &mut ar);
+        }
+        let ar = ar.translated(&self.i18n);
+        Ok(if base.confirmed { ar.confirmed() } else { ar })
+    }
+    /** what is checked for before an item is saved, from the settings of the current repository */
+    pub fn secret_policy(&self) -> FLResult<crate::secrets::SecretPolicy> {
+        let enabled = self
+            .store
+            .config_value(crate::settings::CHECK_SECRETS_KEY)?
+            .map_or(true, |v| v == "true");
+        let patterns = self
+            .store
+            .config_value(crate::settings::SECRET_PATTERNS_KEY)?
+            .unwrap_or_default();
+        crate::secrets::SecretPolicy::new(enabled, &patterns)
+    }
+    /** the retention policies given in the settings */
+    pub fn retention_policies(&self) -> FLResult<Vec<crate::retention::RetentionPolicy>> {
+        let text = self
+            .config_value(crate::settings::RETENTION_KEY)?
+            .unwrap_or_default();
+        crate::retention::parse_policies(&text)
     }
     // /** check that an item would be valid */
     // fn check_valid(
@@ -354,6 +953,15 @@ impl<'a> World {
         basic_request: &crate::BasicRequest,
         _json_value: serde_json::value::Value,
     ) -> fanling_interface::ResponseResult {
+        let unqualified = self.unqualified_request(basic_request)?;
+        let basic_request = unqualified.as_ref().unwrap_or(basic_request);
+        if self.is_read_only() && !crate::read_only::allows(&basic_request.action) {
+            return crate::read_only::refuse(self, &basic_request.action);
+        }
+        if !basic_request.action.keeps_prefetched() {
+            self.prefetched.clear();
+            self.running_timer = None;
+        }
         let mut res = match basic_request.action.kind() {
             crate::ActionKind::Engine => error_response_result("should not come here"),
             crate::ActionKind::World => self.do_world_action(basic_request),
@@ -363,14 +971,59 @@ impl<'a> World {
                     .as_ref()
                     .ok_or_else(|| fanling_error!("need ident here"))?
                     .to_string();
-                let item_rf = self.get_item(ident, "Simple".to_owned())?;
-                let item: &mut Item = &mut item_rf.deref().borrow_mut();
-                let res = item.do_action(basic_request.action.clone(), self)?;
-                trace("item action done");
-                Ok(res)
+                let ident = self.resolve_alias(&ident)?;
+                let item_rf = self.get_item(ident.clone(), "Simple".to_owned())?;
+                if matches!(
+                    basic_request.action,
+                    crate::Action::Show | crate::Action::ShowHeading(_)
+                ) {
+                    let mut resp = crate::prefetch::show(self, &item_rf)?;
+                    self.sessions.shown(basic_request.session(), &ident);
+                    if let crate::Action::ShowHeading(heading) = &basic_request.action {
+                        resp.append_to("content", &crate::markdown::scroll_to(heading));
+                    }
+                    Ok(resp)
+                } else {
+                    let (res, changed, closed) = {
+                        let item: &mut Item = &mut item_rf.deref().borrow_mut();
+                        let was_open = item.is_open();
+                        let before = item.to_yaml().ok();
+                        let res = item.do_action(basic_request.action.clone(), self)?;
+                        let changed = item.to_yaml().ok() != before;
+                        (res, changed, was_open && !item.is_open())
+                    };
+                    trace("item action done");
+                    if changed {
+                        crate::hooks::fire(self, crate::hooks::HookEvent::Updated, &item_rf);
+                    }
+                    if closed {
+                        crate::automation::run(
+                            self,
+                            crate::automation::Event::Closed,
+                            Some(&item_rf),
+                        );
+                        crate::hooks::fire(self, crate::hooks::HookEvent::Closed, &item_rf);
+                    }
+                    let mut resp = if basic_request.action.shows_board() {
+                        crate::board::show_board(self)?
+                    } else if basic_request.action.shows_focus() {
+                        crate::focus::show_focus(self)?
+                    } else {
+                        res
+                    };
+                    if closed {
+                        crate::blocking::notify_unblocked(self, &ident, &mut resp)?;
+                    }
+                    Ok(resp)
+                }
             }
         }?;
+        if self.head_moved()? {
+            self.push_pending = true;
+        }
         self.add_always(&mut res)?;
+        crate::automation::report(self, &mut res);
+        self.note_head()?;
         trace("action done");
         Ok(res)
     }
@@ -391,6 +1044,10 @@ impl<'a> World {
                 }
                 let (base, serde_value) = self.store.get_item_parts(&ident)?;
                 let item_ref = self.get_and_make_known(&serde_value, &base)?;
+                if self.search.is_stub(&ident)? {
+                    trace(&format!("'{}' was a stub, now loaded", &ident));
+                    self.search.update_item(&mut item_ref.borrow_mut())?;
+                }
                 Ok(item_ref)
             }
         }
@@ -420,7 +1077,11 @@ impl<'a> World {
         fanling_trace!(&format!("making and populating item {}", &base.ident));
         let mut item = item_type.make_raw();
         trace("setting base...");
-        item.set_from_yaml(serde_value, self)?;
+        if crate::private::is_sealed(serde_value) {
+            item.lock(serde_value)?;
+        } else {
+            item.set_from_yaml(serde_value, self)?;
+        }
         trace("setting data...");
         item.set_from_serde(base)?;
         assert!(
@@ -440,12 +1101,23 @@ impl<'a> World {
         item_link.resolve_link(self)
     }
 
-    /** add content to push to id=always */
-    pub fn add_always(&self, res: &mut fanling_interface::Response) -> NullResult {
+    /** add content to push to id=always, and the running timer to id=timer */
+    pub fn add_always(&mut self, res: &mut fanling_interface::Response) -> NullResult {
+        let status = crate::sync::status(self)?;
         let at = AlwaysTemplate {
             needs_push: self.store.does_need_pushing(),
+            repo_name: self.repo_name.clone(),
+            has_other_repos: !self.other_repos.is_empty(),
+            badge: status.badge(),
         };
         res.add_tag("always", &(at.render()?));
+        res.set_sync_status(status);
+        if self.running_timer.is_none() {
+            self.running_timer = Some(crate::timing::find_running(self)?);
+        }
+        if let Some(running) = &self.running_timer {
+            res.add_tag("timer", &crate::timing::render_running(running)?);
+        }
         Ok(())
     }
     /** carry out an action and return a [`Response`]. Does not delegate. */
@@ -455,23 +1127,19 @@ impl<'a> World {
         //    _json_value: serde_json::value::Value,
     ) -> fanling_interface::ResponseResult {
         match &basic_request.action {
-            crate::Action::Start | crate::Action::ListReady => {
-                let mut open = self.search.search_open_hier()?;
-                let mut ready = open.filter_on_item(|i, world| i.is_ready(world), self)?;
-                Self::show_list(&mut ready, "ready")
-            }
+            crate::Action::Start => crate::dashboard::show_home(self),
+            crate::Action::ListReady => self.show_sorted_list("ready", 0, PAGE_SIZE),
             crate::Action::Create(base, vals) => {
                 let type_name = basic_request.ensure_type_name()?;
                 let item_type_rf = self.get_item_type(type_name.clone())?;
                 let action_result = self.check_item_valid(item_type_rf, base, vals)?;
-                if let ActionResponse::Failure {
-                    messages: _,
-                    specifics: _,
-                } = action_result
-                {
+                if action_result.is_blocking() {
                     return action_result.to_response();
                 }
                 let item_ref = self.make_item(&type_name, &base, &vals)?;
+                self.drafts.discard(&crate::draft::key(&"".to_owned(), &type_name))?;
+                crate::automation::run(self, crate::automation::Event::Created, Some(&item_ref));
+                crate::hooks::fire(self, crate::hooks::HookEvent::Created, &item_ref);
                 let res = item_ref.deref().borrow_mut().for_edit(true, self);
                 fanling_trace!("action done");
                 res
@@ -484,29 +1152,63 @@ impl<'a> World {
             crate::Action::Delete => self.delete_item_action(basic_request),
             crate::Action::GetAll => self.get_all(),
             crate::Action::CheckData => self.check_data(),
-            crate::Action::ListOpen => {
-                let mut open = self.search.search_open_hier()?;
-                Self::show_list(&mut open, "open")
+            crate::Action::Check => crate::check::show_check(self, ""),
+            crate::Action::FixProblem(fix) => crate::check::fix_and_show(self, fix),
+            crate::Action::Retention => crate::retention::show_preview(self, ""),
+            crate::Action::ApplyRetention(idents) => {
+                crate::retention::apply_and_show(self, idents)
             }
-            crate::Action::ListAll => {
-                let mut all = self.search.search_all_hier()?;
-                Self::show_list(&mut all, "all")
+            crate::Action::Batch { idents, operation } => {
+                crate::batch::do_batch(self, idents, operation)
+            }
+            crate::Action::MigrateAll => crate::migrate::migrate_and_show(self),
+            crate::Action::ListOpen => self.show_sorted_list("open", 0, PAGE_SIZE),
+            crate::Action::ListAll => self.show_sorted_list("all", 0, PAGE_SIZE),
+            crate::Action::ListReading => self.show_sorted_list("reading", 0, PAGE_SIZE),
+            crate::Action::ListHighlights => crate::highlight::show_highlights(self),
+            crate::Action::TimeReport(date) => crate::timing::weekly_report(self, date),
+            crate::Action::ListMore {
+                list,
+                offset,
+                limit,
+            } => self.show_sorted_list(list, *offset, *limit),
+            crate::Action::SortList { list, order } => {
+                crate::ordering::set_list_order(self, crate::ordering::SortOrder::parse(order))?;
+                self.show_sorted_list(list, 0, PAGE_SIZE)
+            }
+            crate::Action::MoveInList { list, ident, up } => {
+                crate::ordering::move_in_list(self, list, ident, *up)?;
+                self.show_sorted_list(list, 0, PAGE_SIZE)
+            }
+            crate::Action::Repos => crate::repos::show_repos(self),
+            crate::Action::SelectRepo(name) => {
+                self.select_repo(name)?;
+                crate::repos::show_repos(self)
             }
             crate::Action::Pull => {
                 trace("doing pull action");
                 self.pull()?;
                 fanling_trace!("action done");
-                Ok(fanling_interface::Response::new())
+                match self.recovery.take() {
+                    Some(report) => crate::rollback::show(&report),
+                    None => {
+                        let mut resp = fanling_interface::Response::new();
+                        crate::remotes::warn_failed(self, &mut resp);
+                        Ok(resp)
+                    }
+                }
             }
             crate::Action::Push { force } => {
                 trace("doing push action");
-                self.store.push(*force)?;
+                self.push(*force)?;
                 trace(&format!(
                     "after push, needs push: {:?}",
                     self.store.get_needs_push()
                 ));
                 fanling_trace!("action done");
-                Ok(fanling_interface::Response::new())
+                let mut resp = fanling_interface::Response::new();
+                crate::remotes::warn_failed(self, &mut resp);
+                Ok(resp)
             }
             crate::Action::New => {
                 let item_type_name: Ident = basic_request.ensure_type_name()?;
@@ -529,11 +1231,179 @@ impl<'a> World {
                 fanling_trace!("action done");
                 item.for_edit(false, self)
             }
+            crate::Action::Templates => crate::instantiate::show_templates(self),
+            crate::Action::Instantiate => {
+                crate::instantiate::start(self, &basic_request.ensure_ident()?)
+            }
+            crate::Action::InstantiateWith(vals) => {
+                crate::instantiate::instantiate(self, &basic_request.ensure_ident()?, vals)
+            }
             crate::Action::Clone => {
                 let res = self.clone_item(basic_request);
                 fanling_trace!("action done");
                 res
             }
+            crate::Action::ExportVCard => crate::contact::export_vcards(self),
+            crate::Action::ImportVCard(text) => crate::contact::import_vcards(self, text),
+            crate::Action::ExportBibTeX => crate::reference::export_bibtex(self),
+            crate::Action::ImportBibTeX(text) => crate::reference::import_bibtex(self, text),
+            crate::Action::Cite(key) => crate::reference::cite(self, key),
+            crate::Action::Backup => crate::bundle::show_bundle("", &[]),
+            crate::Action::ExportBundle { types, passphrase } => {
+                crate::bundle::export_bundle(self, types, passphrase)
+            }
+            crate::Action::ImportBundle { text, passphrase } => {
+                crate::bundle::import_bundle(self, text, passphrase)
+            }
+            crate::Action::ExportJson { idents, types } => {
+                crate::json::export_json(self, idents, types)
+            }
+            crate::Action::ImportJson(text) => crate::json::import_json(self, text),
+            crate::Action::ExportOrg => crate::org::export_org(self),
+            crate::Action::ImportOrg(text) => crate::org::import_org(self, text),
+            crate::Action::ImportTasks { text, project } => {
+                crate::taskimport::import_tasks(self, text, project)
+            }
+            crate::Action::SyncDav => crate::dav::sync_dav(self),
+            crate::Action::Holidays => self.edit_holidays(),
+            crate::Action::Board => crate::board::show_board(self),
+            crate::Action::Focus => crate::focus::show_focus(self),
+            crate::Action::Dashboard => crate::dashboard::show_dashboard(self),
+            crate::Action::Prefetch => crate::prefetch::prefetch(self),
+            crate::Action::PushPending => crate::remotes::push_pending(self),
+            crate::Action::DeliverHooks => crate::hooks::deliver(self),
+            crate::Action::QuickAdd(text) => crate::quick::quick_add(self, text),
+            crate::Action::SaveArticle(url) => crate::article::save_article(self, url),
+            crate::Action::CaptureShared { title, text, url } => {
+                crate::share::capture_shared(self, title, text, url)
+            }
+            crate::Action::CaptureMail(message) => crate::mail::capture_mail(self, message),
+            crate::Action::PollMail => crate::mail::poll_mail(self),
+            crate::Action::Suggest(req) => crate::suggest::show_suggestions(self, req),
+            crate::Action::Stats => crate::stats::show_stats(self, ""),
+            crate::Action::Report => crate::report::show_report(self),
+            crate::Action::ListProjects => crate::project::list_projects(self),
+            crate::Action::ExportChart { chart, format } => {
+                crate::stats::export_chart(self, chart, *format)
+            }
+            crate::Action::Back => crate::session::back(self, basic_request.session()),
+            crate::Action::Forward => crate::session::forward(self, basic_request.session()),
+            crate::Action::Rename {
+                ident,
+                new_ident,
+                rewrite_links,
+            } => crate::alias::rename_and_show(self, ident, new_ident, *rewrite_links),
+            crate::Action::Recent => crate::session::show_recent(self),
+            crate::Action::Namespace(namespace) => {
+                crate::namespace::show_namespace(self, namespace)
+            }
+            crate::Action::Unpushed => crate::sync::show_unpushed(self),
+            crate::Action::Remotes => crate::remotes::show_remotes(self, ""),
+            crate::Action::SaveRemote {
+                name,
+                url,
+                ssh_key,
+                on_save,
+                pull,
+            } => crate::remotes::save_remote(
+                self,
+                taipo_git_control::RemoteDescr {
+                    name: name.clone(),
+                    url: url.clone(),
+                    ssh_key: ssh_key.clone(),
+                    push: if *on_save {
+                        taipo_git_control::PushPolicy::OnSave
+                    } else {
+                        taipo_git_control::PushPolicy::Manual
+                    },
+                    pull: *pull,
+                },
+            ),
+            crate::Action::RemoveRemote(name) => crate::remotes::remove_remote(self, name),
+            crate::Action::PullFrom(name) => {
+                crate::remotes::pull_from(self, name)?;
+                match self.recovery.take() {
+                    Some(report) => crate::rollback::show(&report),
+                    None => crate::remotes::show_remotes(self, ""),
+                }
+            }
+            crate::Action::PushTo { name, force } => {
+                crate::remotes::push_to_and_show(self, name, *force)
+            }
+            crate::Action::Credentials => crate::credentials::show_credentials(self, ""),
+            crate::Action::SaveCredential { remote, secret } => {
+                crate::credentials::save_credential(self, remote, secret)
+            }
+            crate::Action::ForgetCredential(remote) => {
+                crate::credentials::forget_credential(self, remote)
+            }
+            crate::Action::TestRemote(remote) => crate::credentials::test_and_show(self, remote),
+            crate::Action::Maintenance => crate::maintenance::show_maintenance(self, ""),
+            crate::Action::Repack => crate::maintenance::repack_and_show(self),
+            crate::Action::Gc => crate::maintenance::gc_and_show(self),
+            crate::Action::ExportBackup => crate::backup::backup_and_show(self),
+            crate::Action::Clip {
+                ident,
+                field,
+                blocks,
+                cut,
+            } => crate::clipboard::clip(
+                self,
+                basic_request.session(),
+                ident,
+                field,
+                blocks,
+                *cut,
+            ),
+            crate::Action::Paste { ident, field, at } => {
+                crate::clipboard::paste(self, basic_request.session(), ident, field, *at)
+            }
+            crate::Action::ShowCurrent => {
+                crate::session::show_current(self, basic_request.session())
+            }
+            crate::Action::CloseSession => {
+                self.sessions.close(basic_request.session());
+                Ok(fanling_interface::Response::new())
+            }
+            crate::Action::Orphans => crate::graph::show_orphans(self, ""),
+            crate::Action::ExportGraph => crate::graph::export_graph(self),
+            crate::Action::Agenda { month, date } => crate::event::agenda(self, *month, date, ""),
+            crate::Action::Timeline { by_tag, date } => {
+                crate::timeline::show_timeline(self, *by_tag, date)
+            }
+            crate::Action::ExportICal => crate::event::export_ical(self),
+            crate::Action::Preview(text) => Ok(fanling_interface::Response::new_with_tags(&[(
+                "preview",
+                &crate::transclude::render(self, "", text),
+            )])),
+            crate::Action::SaveDraft(base, vals) => {
+                let saved = self.drafts.save(base, vals)?;
+                let status = if saved {
+                    format!(
+                        "Draft saved at {}",
+                        chrono::Local::now().naive_local().format("%H:%M")
+                    )
+                } else {
+                    "".to_owned()
+                };
+                Ok(fanling_interface::Response::new_with_tags(&[("draft", &status)]))
+            }
+            crate::Action::DiscardDraft => {
+                let ident = basic_request.ident.clone().unwrap_or_default();
+                let type_name = basic_request.ensure_type_name()?;
+                self.drafts.discard(&crate::draft::key(&ident, &type_name))?;
+                if ident.is_empty() {
+                    let item_type = self.get_item_type(type_name)?;
+                    let mut item = item_type.deref().borrow().make_raw();
+                    item.for_edit(false, self)
+                } else {
+                    let item_ref = self.get_item(ident, type_name)?;
+                    let res = item_ref.deref().borrow_mut().for_edit(true, self);
+                    res
+                }
+            }
+            crate::Action::Settings => crate::settings::show_settings(self, ""),
+            crate::Action::SaveSettings(vals) => crate::settings::save_settings(self, vals),
             crate::Action::TestError2 => {
                 trace("making world test error 2");
                 Err(Box::new(fanling_error!("test error 2")))
@@ -541,8 +1411,104 @@ impl<'a> World {
             _ => error_response_result(&format!("invalid action {:?}", basic_request.action)),
         }
     }
+    /** the entries for one of the lists ("ready", "open" or "all"),
+    perhaps scoped to a namespace (see [`crate::namespace`]) */
+    pub fn list_entries(&mut self, list: &str) -> FLResult<ItemListEntryList> {
+        if let (list, Some(namespace)) = crate::namespace::scoped_list(list) {
+            let mut entries = self.list_entries(list)?;
+            entries
+                .entries
+                .retain(|e| crate::namespace::is_in(&e.link.ident, namespace));
+            return Ok(entries);
+        }
+        match list {
+            "ready" => {
+                let mut open = self.search.search_open_hier()?;
+                open.filter_on_item(|i, world| i.is_ready(world), self)
+            }
+            "open" => {
+                let mut open = self.search.search_open_hier()?;
+                open.entries.extend(self.other_repo_entries(true)?);
+                Ok(open)
+            }
+            "all" => {
+                let mut all = self.search.search_all_hier()?;
+                all.entries.extend(self.other_repo_entries(false)?);
+                Ok(all)
+            }
+            "reading" => crate::article::reading_list(self),
+            _ => Err(fanling_error!(&format!("unknown list '{}'", list))),
+        }
+    }
+    /** show a page of one of the lists, in the order chosen for them */
+    fn show_sorted_list(
+        &mut self,
+        list: &str,
+        offset: usize,
+        limit: usize,
+    ) -> fanling_interface::ResponseResult {
+        let order = crate::ordering::list_order(self)?;
+        let entries = self.list_entries(list)?;
+        let entries = crate::ordering::sort_list(self, entries, order);
+        Self::show_list_page(&entries, list, offset, limit, order)
+    }
+    /** show a page of one of the lists, starting at `offset`. The
+    first page replaces the content; later ones replace the "load
+    more" button of the page before, so that the rows are added to
+    those already shown. */
+    fn show_list_page(
+        list: &ItemListEntryList,
+        narr: &str,
+        offset: usize,
+        limit: usize,
+        order: crate::ordering::SortOrder,
+    ) -> fanling_interface::ResponseResult {
+        let (mut page, next) = list.page(offset, limit);
+        page.set_level_changes();
+        trace(&format!(
+            "{}: {} of {} entries from {}",
+            narr,
+            page.num_entries(),
+            list.num_entries(),
+            offset
+        ));
+        #[cfg(test)]
+        let page_count = page.num_entries();
+        #[cfg(test)]
+        let page_idents: Vec<String> = page.entries.iter().map(|e| e.link.ident.clone()).collect();
+        let lt = ListTemplate {
+            items: page,
+            more: next.map(|offset| ListMore {
+                list: narr.to_owned(),
+                offset,
+                limit,
+            }),
+            list: narr.to_owned(),
+            orders: if offset == 0 {
+                OrderChoice::all(order)
+            } else {
+                vec![]
+            },
+            manual: order == crate::ordering::SortOrder::Manual,
+            batch: offset == 0,
+        };
+        let mut resp = fanling_interface::Response::new();
+        if offset == 0 {
+            resp.add_tag("content", &(lt.render()?));
+        } else {
+            resp.add_tag(&format!("list-more-{}", offset), &(lt.render()?));
+        }
+        #[cfg(test)]
+        {
+            resp.set_test_data("count", &format!("{}", list.num_entries()));
+            resp.set_test_data("page", &format!("{}", page_count));
+            resp.set_test_data("next", &next.map_or("".to_owned(), |n| format!("{}", n)));
+            resp.set_test_data("idents", &page_idents.join(","));
+        }
+        Ok(resp)
+    }
     /** show a list of items */
-    fn show_list(list: &mut ItemListEntryList, narr: &str) -> fanling_interface::ResponseResult {
+    pub fn show_list(list: &mut ItemListEntryList, narr: &str) -> fanling_interface::ResponseResult {
         list.set_level_changes();
         trace(&format!(
             "{}: {} entries {:?}",
@@ -554,6 +1520,11 @@ impl<'a> World {
         let entries_count = list.num_entries();
         let lt = ListTemplate {
             items: list.clone(),
+            more: None,
+            list: "".to_owned(),
+            orders: vec![],
+            manual: false,
+            batch: true,
         };
         let mut resp = fanling_interface::Response::new();
         resp.add_tag("content", &(lt.render()?));
@@ -577,15 +1548,25 @@ impl<'a> World {
         trace(&format!("action result is {:#?}", action_result));
         if action_result.ok() {
             let item_rf = self.get_item(ident, "Simple".to_owned())?;
-            let mut item = item_rf.deref().borrow_mut();
-            trace(&format!("values for base update: {:#?}", base));
-            item.set_from_serde(base)?;
-            trace(&format!("values for data update: {:#?}", vals));
-            item.set_data(vals, self)?;
-            // self.search.check_item_valid(&mut item)?;
-            // self.store.mark_item_modified(&mut item)?;
-            trace("persisting change for ok update action");
-            self.persist_change(&mut item)?;
+            let closed = {
+                let mut item = item_rf.deref().borrow_mut();
+                let was_open = item.is_open();
+                trace(&format!("values for base update: {:#?}", base));
+                item.set_from_serde(base)?;
+                trace(&format!("values for data update: {:#?}", vals));
+                item.set_data(vals, self)?;
+                // self.search.check_item_valid(&mut item)?;
+                // self.store.mark_item_modified(&mut item)?;
+                trace("persisting change for ok update action");
+                self.persist_change(&mut item)?;
+                self.drafts.discard(&item.ident())?;
+                was_open && !item.is_open()
+            };
+            crate::hooks::fire(self, crate::hooks::HookEvent::Updated, &item_rf);
+            if closed {
+                crate::automation::run(self, crate::automation::Event::Closed, Some(&item_rf));
+                crate::hooks::fire(self, crate::hooks::HookEvent::Closed, &item_rf);
+            }
         }
         Ok(action_result.to_response()?)
     }
@@ -603,7 +1584,7 @@ impl<'a> World {
         item.clone_from(&existing_item)?;
         item.set_ident(
             self.store
-                .make_identifier(&self.uniq_pfx, &item.descr_for_ident()),
+                .make_identifier(self.ident_generator.as_mut(), &item.descr_for_ident())?,
         );
         assert!(item.ident() != "", "ident is null");
         self.search
@@ -614,13 +1595,225 @@ impl<'a> World {
         let mut item_ref = item_rcrc.deref().borrow_mut();
         Ok(item_ref.for_edit(true, self)?)
     }
+    /** create a new item from a template item, with the data given
+    by `values` (which are the template's own values with any variables
+    replaced), and show it (or edit it if `edit`) */
+    pub fn instantiate_item(
+        &mut self,
+        template_ident: &Ident,
+        values: &serde_yaml::Value,
+        edit: bool,
+    ) -> fanling_interface::ResponseResult {
+        let template_rf = self.get_item(template_ident.clone(), "Simple".to_owned())?;
+        let mut item = {
+            let template = template_rf.deref().borrow();
+            let item_type_rf = template.item_type();
+            let item_type = item_type_rf.deref().borrow();
+            let mut item = item_type.make_raw();
+            item.clone_from(&template)?;
+            item
+        };
+        item.set_from_yaml(values, self)?;
+        item.set_template(false);
+        item.instantiated(template_ident, self)?;
+        item.set_ident(
+            self.store
+                .make_identifier(self.ident_generator.as_mut(), &item.descr_for_ident())?,
+        );
+        self.search
+            .update_last_ident(self.store.get_next_ident_num().try_into()?)?;
+        let item_rcrc = Rc::new(RefCell::new(item));
+        self.store.add_item(&item_rcrc)?;
+        self.search.add_item(&item_rcrc)?;
+        let mut item_ref = item_rcrc.deref().borrow_mut();
+        trace(&format!("instantiated {} as {}", template_ident, item_ref.ident()));
+        let resp = if edit {
+            item_ref.for_edit(true, self)?
+        } else {
+            item_ref.for_show(self)?
+        };
+        #[cfg(test)]
+        let resp = {
+            let mut resp = resp;
+            resp.set_test_data("ident", &item_ref.ident());
+            resp
+        };
+        Ok(resp)
+    }
+    /** the YAML for every item in the store (private items are still sealed) */
+    pub fn all_item_yaml(&self) -> FLResult<Vec<String>> {
+        Ok(self
+            .store
+            .list_all_items()?
+            .into_iter()
+            .map(|entry| entry.blob)
+            .collect())
+    }
+    /** the serialised data of the items as stored, with the idents from their paths */
+    pub fn stored_items(&self) -> FLResult<Vec<(Ident, String)>> {
+        Ok(self
+            .store
+            .list_all_items()?
+            .into_iter()
+            .filter_map(|entry| {
+                self.store
+                    .ident_from_path(&entry.path)
+                    .map(|ident| (ident, entry.blob))
+            })
+            .collect())
+    }
+    /** add an item from elsewhere (such as a backup), keeping its
+    ident. Returns `None` if there is already an item with the ident. */
+    pub fn import_item(
+        &mut self,
+        base: &ItemBaseForSerde,
+        values: &serde_yaml::Value,
+    ) -> FLResult<Option<ItemRef>> {
+        if base.ident.is_empty() || self.store.has_file(&base.ident)? {
+            return Ok(None);
+        }
+        let item_type_rcrc = self.get_item_type(base.type_name.to_owned())?;
+        let item_type = item_type_rcrc.deref().borrow();
+        let item_ref = self.make_and_populate_item(&item_type, base, values)?;
+        self.store.add_item(&item_ref)?;
+        self.search.add_item(&item_ref)?;
+        Ok(Some(item_ref))
+    }
+    /** collect the following changes into a single commit */
+    pub fn begin_batch(&mut self) {
+        self.store.begin_batch()
+    }
+    /** make a single commit for the changes since [World::begin_batch] */
+    pub fn end_batch(&mut self) -> NullResult {
+        self.store.end_batch()
+    }
+    /** keep the passphrase of a private item for the rest of the session */
+    pub fn remember_passphrase(&mut self, ident: &Ident, passphrase: &crate::private::Passphrase) {
+        self.store.remember_passphrase(ident, passphrase)
+    }
     /** write out any changes to the search database and the store */
     pub fn persist_change(&mut self, item: &mut Item) -> NullResult {
-        trace(&format!("persisting change for '{}'", item.ident()));
+        self.persist_change_as(item, "modify")
+    }
+    /** write out any changes, describing the change with `verb` in the commit message */
+    pub fn persist_change_as(&mut self, item: &mut Item, verb: &str) -> NullResult {
+        trace(&format!("persisting change ({}) for '{}'", verb, item.ident()));
         self.search.update_item(item)?;
-        self.store.mark_item_modified(item)?;
+        self.store.mark_item_modified_as(item, verb)?;
         Ok(())
     }
+    /** the path of an item within the repository's directory of items */
+    pub fn path_from_ident(&self, ident: &Ident) -> String {
+        self.store.path_from_ident(ident)
+    }
+    /** whether the items are encrypted */
+    pub fn is_encrypted(&self) -> bool {
+        self.store.is_encrypted()
+    }
+    /** whether the items in the current repository can only be read (see [`crate::read_only`]) */
+    pub fn is_read_only(&self) -> bool {
+        self.store.is_read_only()
+    }
+    /** write files that are not items beside the items, committing the ones that have changed */
+    pub fn write_other_files(&mut self, files: &[(String, String)]) -> NullResult {
+        self.store.write_other_files(files)
+    }
+    /** add a file attached to an item (see [`Store::add_attachment`]) */
+    pub fn add_attachment(&mut self, path: &str, contents: &str) -> NullResult {
+        self.store.add_attachment(path, contents)
+    }
+    /** the contents of a file attached to an item */
+    pub fn attachment(&self, path: &str) -> FLResult<String> {
+        self.store.attachment(path)
+    }
+    /** the kind of user interface */
+    pub fn interface_type(&self) -> crate::InterfaceType {
+        self.interface_type
+    }
+    /** the holidays, as listed in the holidays item (if there is one) */
+    pub fn holidays(&self) -> FLResult<HolidayCalendar> {
+        if !self.store.has_file(HOLIDAYS_IDENT)? {
+            return Ok(HolidayCalendar::new());
+        }
+        let (_base, values) = self.store.get_item_parts(&HOLIDAYS_IDENT.to_owned())?;
+        Ok(HolidayCalendar::parse(
+            values.get("text").and_then(|t| t.as_str()).unwrap_or(""),
+        ))
+    }
+    /** edit the holidays item, creating it if necessary */
+    fn edit_holidays(&mut self) -> fanling_interface::ResponseResult {
+        let item_ref = if self.store.has_file(HOLIDAYS_IDENT)? {
+            self.get_item(HOLIDAYS_IDENT.to_owned(), "Simple".to_owned())?
+        } else {
+            self.ensure_item(HOLIDAYS_IDENT.to_owned(), "Simple".to_owned())?
+        };
+        let res = item_ref.deref().borrow_mut().for_edit(true, self);
+        res
+    }
+    /** how dates, times and numbers are formatted */
+    pub fn format(&self) -> &crate::FormatOptions {
+        &self.format
+    }
+    /** translates the text shown to the user */
+    pub fn i18n(&self) -> Rc<crate::i18n::Translator> {
+        self.i18n.clone()
+    }
+    /** unsaved edits */
+    pub fn drafts(&self) -> &crate::draft::Drafts {
+        &self.drafts
+    }
+    /** directory for exported files such as charts */
+    pub fn export_dir(&self) -> &Path {
+        &self.export_dir
+    }
+    /** how the commits for changes are described */
+    pub fn commit_options(&self) -> &crate::settings::CommitOptions {
+        self.store.commit_options()
+    }
+    /** change how the commits for changes are described */
+    pub fn set_commit_options(&mut self, commit_options: crate::settings::CommitOptions) {
+        self.store.set_commit_options(commit_options);
+    }
+    /** the name and email address used as the author of commits */
+    pub fn identity(&self) -> (String, String) {
+        self.store.identity()
+    }
+    /** set (and save) the author of commits */
+    pub fn set_identity(&mut self, name: &str, email: &str) -> NullResult {
+        self.store.set_identity(name, email)
+    }
+    /** a value in the repository's configuration, if it has been set */
+    pub fn config_value(&self, key: &str) -> FLResult<Option<String>> {
+        self.store.config_value(key)
+    }
+    /** save a value in the repository's configuration */
+    pub fn set_config_value(&mut self, key: &str, value: &str) -> NullResult {
+        self.store.set_config_value(key, value)
+    }
+    /** the most recent changes to the store, newest first */
+    pub fn history(&self, max: usize) -> FLResult<Vec<taipo_git_control::CommitDescr>> {
+        self.store.history(max)
+    }
+    /** the most recent changes to an item, newest first */
+    pub fn item_history(
+        &self,
+        ident: &Ident,
+        max: usize,
+    ) -> FLResult<Vec<taipo_git_control::CommitDescr>> {
+        self.store.item_history(ident, max)
+    }
+    /** when each item was last changed (seconds since the Unix epoch), from no more than `max` commits */
+    pub fn last_changed(&self, max: usize) -> FLResult<HashMap<Ident, i64>> {
+        self.store.last_changed(max)
+    }
+    /** the serialised item as it was in a commit (given as hex), if it was there */
+    pub fn item_at_commit(&self, ident: &Ident, id: &str) -> FLResult<Option<String>> {
+        self.store.item_at_commit(ident, id)
+    }
+    /** whether a commit is signed, and if so whether the signature is good */
+    pub fn verify_commit(&self, id: &str) -> FLResult<taipo_git_control::Verification> {
+        self.store.verify_commit(id)
+    }
     /** delete an item and ensure that the store and the search are updated accordingly */
     fn delete_item_action(
         &mut self,
@@ -631,13 +1824,19 @@ impl<'a> World {
         let _item_type_rf = self.get_item_type(type_name)?;
         // TODO check whether item can be deleted
         let item_rf = self.get_item(ident, "Simple".to_owned())?;
+        self.delete_item(item_rf)?;
+        Ok(fanling_interface::Response::new())
+    }
+    /** delete an item from the store and the search */
+    pub fn delete_item(&mut self, item_rf: ItemRef) -> NullResult {
         self.search.delete_item(item_rf.clone())?;
         self.store.mark_item_deleted(item_rf)?;
-        Ok(fanling_interface::Response::new())
+        Ok(())
     }
     /** process all items in the store */
     fn get_all(&mut self) -> fanling_interface::ResponseResult {
         fanling_trace!("getting items into store...");
+        self.collisions = self.find_collisions()?;
         self.search.clear()?;
         self.store.clear_known();
         for ed in self.store.list_all_items()?.iter() {
@@ -677,6 +1876,9 @@ impl<'a> World {
                             // let item_ref = self.get_and_make_known(serde_value, &base)?;
                             // self.search.add_item(&item_ref)?;
                             // assert_eq!(ident_from_path, item_ref.deref().borrow().ident());
+                            if self.claims_other_ident(&ed.path, &base.ident) {
+                                continue;
+                            }
                             let ident = self.make_known(&serde_value, base)?;
                             assert_eq!(ident_from_path, ident);
                         }
@@ -684,6 +1886,7 @@ impl<'a> World {
                 }
             }
         }
+        self.load_aliases()?;
         trace("got items into store");
         Ok(fanling_interface::Response::new())
     }
@@ -702,10 +1905,22 @@ impl<'a> World {
     pub fn search_all(&self) -> FLResult<ItemListEntryList> {
         Ok(self.search.search_all()?)
     }
+    /** search everything with hierarchy */
+    pub fn search_all_hier(&self) -> FLResult<ItemListEntryList> {
+        self.search.search_all_hier()
+    }
+    /** search for all items of a type */
+    pub fn search_type(&self, type_name: &str) -> FLResult<ItemListEntryList> {
+        self.search.search_type(type_name)
+    }
     /** search parents  */
     pub fn search_parents(&self) -> FLResult<ItemListEntryList> {
         Ok(self.search.search_special(SpecialKind::Parent)?)
     }
+    /** search templates  */
+    pub fn search_templates(&self) -> FLResult<ItemListEntryList> {
+        Ok(self.search.search_special(SpecialKind::Template)?)
+    }
     /** search contexts  */
     pub fn search_contexts(&self) -> FLResult<ItemListEntryList> {
         Ok(self.search.search_special(SpecialKind::Context)?)
@@ -775,17 +1990,284 @@ impl<'a> World {
         Ok(fanling_interface::Response::new())
     }
 
+    /** check all the items in the repository for problems such as
+    links to missing items (see [`crate::check`]), also returning the
+    number of items checked */
+    pub fn check(&mut self) -> FLResult<(Vec<crate::check::Problem>, usize)> {
+        let mut stored = vec![];
+        for entry in self.store.list_all_items()? {
+            if let Some(ident) = self.store.ident_from_path(&entry.path) {
+                stored.push((entry.path, ident, entry.blob));
+            }
+        }
+        let problems = crate::check::check(self, &stored)?;
+        Ok((problems, stored.len()))
+    }
+    /** the items whose idents collide with those of others (see [`crate::idents`]) */
+    pub fn find_collisions(&self) -> FLResult<Vec<crate::idents::Collision>> {
+        let mut stored = vec![];
+        for entry in self.store.list_all_items()? {
+            if let Some(ident) = self.store.ident_from_path(&entry.path) {
+                if let Ok((base, _values)) = split_data_parts(entry.blob.as_bytes()) {
+                    stored.push((ident, base.ident));
+                }
+            }
+        }
+        Ok(crate::idents::collisions(&stored))
+    }
+    /** find the aliases of the items stored, so that links to their old idents still work */
+    pub fn load_aliases(&mut self) -> NullResult {
+        let mut aliases = BTreeMap::new();
+        for entry in self.store.list_all_items()? {
+            if let Ok((base, _values)) = split_data_parts(entry.blob.as_bytes()) {
+                /* an old ident that has since been given to another item is its ident */
+                for alias in base.aliases {
+                    if !self.store.has_file(&alias)? {
+                        aliases.insert(alias, base.ident.clone());
+                    }
+                }
+            }
+        }
+        self.set_aliases(aliases);
+        Ok(())
+    }
+    /** use the typography of the repository for rendered text (see [`crate::typography`]) */
+    pub fn load_typography(&mut self) -> NullResult {
+        let setting = |key: &str| -> FLResult<bool> {
+            Ok(self.config_value(key)?.map_or(false, |v| v == "true"))
+        };
+        self.typography = crate::typography::Typography {
+            emoji: setting(crate::settings::EMOJI_KEY)?,
+            smart_punctuation: setting(crate::settings::SMART_PUNCTUATION_KEY)?,
+        };
+        Ok(())
+    }
+    /** the typography used for rendered text */
+    pub fn typography(&self) -> crate::typography::Typography {
+        self.typography
+    }
+    /** use the typography for rendered text */
+    pub fn set_typography(&mut self, typography: crate::typography::Typography) {
+        self.typography = typography;
+    }
+    /** the current ident of each item that has been renamed, by its old ident */
+    pub fn aliases(&self) -> &BTreeMap<Ident, Ident> {
+        &self.aliases
+    }
+    /** set the aliases, for the links in rendered text too */
+    pub fn set_aliases(&mut self, aliases: BTreeMap<Ident, Ident>) {
+        trace(&format!("{} aliases", aliases.len()));
+        self.aliases = aliases;
+    }
+    /** what text is rendered with in this repository (see [`crate::markdown::render`]) */
+    pub fn render_context(&self) -> crate::markdown::RenderContext {
+        crate::markdown::RenderContext {
+            aliases: &self.aliases,
+            repo: &self.repo_name,
+            math: self.math,
+            typography: self.typography,
+        }
+    }
+    /** the ident of the item now, if it has been renamed from `ident` (and
+    no item has since been given that ident), otherwise `ident` */
+    pub fn resolve_alias(&self, ident: &Ident) -> FLResult<Ident> {
+        match self.aliases.get(ident) {
+            Some(current) if !self.store.has_file(ident)? => {
+                trace(&format!("redirecting {} to {}", ident, current));
+                Ok(current.clone())
+            }
+            _ => Ok(ident.clone()),
+        }
+    }
+    /** forget an item whose file is to be deleted (as it has been
+    moved to another path), in both the store and the search */
+    pub fn forget_item_file(&mut self, ident: &Ident) -> NullResult {
+        let item_rf = self.get_item(ident.clone(), "Simple".to_owned())?;
+        self.search.delete_item(item_rf)?;
+        self.store.delete_file(ident)
+    }
+    /** the collisions found when the items were last loaded */
+    pub fn collisions(&self) -> &[crate::idents::Collision] {
+        &self.collisions
+    }
+    /** whether the item stored at the path claims the ident of
+    another, found when the items were loaded (so it is not loaded, rather
+    than replacing the other) */
+    fn claims_other_ident(&self, path: &str, data_ident: &Ident) -> bool {
+        match self.store.ident_from_path(path) {
+            Some(ident) if ident != *data_ident => {
+                self.collisions.iter().any(|c| c.ident == ident)
+            }
+            _ => false,
+        }
+    }
+    /** give the item stored with the ident (from its path) a new ident,
+    moving it to a new path, and load the items again, returning the new ident */
+    pub fn give_new_ident(&mut self, ident: &Ident) -> FLResult<Ident> {
+        let (mut base, values) = self.store.get_item_parts(ident)?;
+        let new_ident = self
+            .store
+            .make_identifier(self.ident_generator.as_mut(), ident)?;
+        trace(&format!("giving {} the new ident {}", ident, &new_ident));
+        base.ident = new_ident.clone();
+        self.begin_batch();
+        let moved = self
+            .import_item(&base, &values)
+            .and_then(|_item| self.store.delete_file(ident));
+        /* the batch is ended before any error is returned, so that later changes are committed */
+        self.end_batch()?;
+        moved?;
+        self.search
+            .update_last_ident(self.store.get_next_ident_num().try_into()?)?;
+        self.get_all()?;
+        Ok(new_ident)
+    }
+    /** the YAML of the items as stored (before any upgrade), passing over any that is malformed */
+    pub fn stored_values(&self) -> FLResult<Vec<(Ident, serde_yaml::Value)>> {
+        let mut stored = vec![];
+        for entry in self.store.list_all_items()? {
+            if let Some(ident) = self.store.ident_from_path(&entry.path) {
+                if let Ok(values) = serde_yaml::from_str(&entry.blob) {
+                    stored.push((ident, values));
+                }
+            }
+        }
+        Ok(stored)
+    }
     /** generate the initial HTML */
     pub fn initial_html(&self) -> crate::shared::FLResult<String> {
-        let mt = MainTemplate {
-            interface_type: self.interface_type,
-            interface_type_string: format!("{:?}", self.interface_type),
-        };
-        Ok(mt.render()?)
+        main_html(
+            self.interface_type,
+            self.serve_assets,
+            self.math,
+            self.kinds(),
+        )
     }
-    /** push the store to the server */
+    /** push to each of the remotes (see [`crate::remotes`]) */
     pub fn push(&mut self, force: bool) -> NullResult {
-        self.store.push(force)
+        crate::remotes::push_all(self, force)
+    }
+    /** push the store to the main remote (if there are commits to push) */
+    pub fn push_main(&mut self, force: bool) -> NullResult {
+        /* commits made before the app was last closed still need pushing */
+        if self.ahead_behind()?.map_or(false, |(ahead, _behind)| ahead > 0) {
+            self.store.set_needs_push();
+        }
+        let pushing = self.store.get_needs_push();
+        self.store.push(force)?;
+        if pushing {
+            crate::sync::record(self, None)?;
+        }
+        Ok(())
+    }
+    /** how many commits the store is ahead of and behind the remote (`None` if there is no remote) */
+    pub fn ahead_behind(&self) -> FLResult<Option<(usize, usize)>> {
+        self.store.ahead_behind()
+    }
+    /** push to one of the remotes other than the main one */
+    pub fn push_to(&mut self, remote: &str, force: bool) -> NullResult {
+        self.store.push_to(remote, force)?;
+        crate::sync::record(self, None)
+    }
+    /** the remotes, the main one first */
+    pub fn remotes(&self) -> FLResult<Vec<taipo_git_control::RemoteDescr>> {
+        self.store.remotes()
+    }
+    /** the name of the main remote (given in the options), if there is one */
+    pub fn main_remote(&self) -> Option<String> {
+        self.store.main_remote()
+    }
+    /** add or change a remote */
+    pub fn set_remote(&mut self, remote: &taipo_git_control::RemoteDescr) -> NullResult {
+        self.store.set_remote(remote)
+    }
+    /** remove a remote other than the main one */
+    pub fn delete_remote(&mut self, remote: &str) -> NullResult {
+        self.remote_results.remove(remote);
+        self.store.delete_remote(remote)
+    }
+    /** the name of the credential provider, if there is one */
+    pub fn credential_provider(&self) -> Option<String> {
+        self.store.credential_provider()
+    }
+    /** the key for the secret used with the remote */
+    pub fn credential_key(&self, remote: &str) -> FLResult<String> {
+        self.store.credential_key(remote)
+    }
+    /** whether there is a secret kept for the remote */
+    pub fn has_secret(&self, remote: &str) -> FLResult<bool> {
+        self.store.has_secret(remote)
+    }
+    /** keep the secret for the remote */
+    pub fn set_secret(&self, remote: &str, secret: &str) -> NullResult {
+        self.store.set_secret(remote, secret)
+    }
+    /** forget the secret for the remote */
+    pub fn forget_secret(&self, remote: &str) -> NullResult {
+        self.store.forget_secret(remote)
+    }
+    /** check that the remote can be reached with its credentials */
+    pub fn test_remote(&self, remote: &str) -> NullResult {
+        self.store.test_remote(remote)
+    }
+    /** write a backup of the repository and the local files to `path` (see [`crate::backup`]) */
+    pub fn export_backup(&self, path: &Path) -> FLResult<crate::backup::Manifest> {
+        self.save_history()?;
+        crate::backup::write_backup(
+            self.store.repo_path(),
+            &self.local_dir,
+            &self.repo_name,
+            self.is_encrypted(),
+            path,
+        )
+    }
+    /** how big the repository is, with the `max` largest files */
+    pub fn size_report(&self, max: usize) -> FLResult<taipo_git_control::SizeReport> {
+        self.store.size_report(max)
+    }
+    /** pack the objects of the repository */
+    pub fn repack(&self) -> FLResult<taipo_git_control::RepackReport> {
+        self.store.repack()
+    }
+    /** collect the garbage in the repository */
+    pub fn gc(&self) -> NullResult {
+        self.store.gc()
+    }
+    /** how the last pull from and push to each remote went */
+    pub fn remote_results(&self) -> &BTreeMap<String, crate::remotes::Results> {
+        &self.remote_results
+    }
+    /** how the last pull from and push to each remote went, to be changed */
+    pub fn remote_results_mut(&mut self) -> &mut BTreeMap<String, crate::remotes::Results> {
+        &mut self.remote_results
+    }
+    /** whether commits have been made since the remotes pushed to on save were pushed to */
+    pub fn push_pending(&self) -> bool {
+        self.push_pending
+    }
+    /** note whether the remotes pushed to on save still need pushing to */
+    pub fn set_push_pending(&mut self, pending: bool) {
+        self.push_pending = pending;
+    }
+    /** the commits that have not been pushed to the remote, newest first */
+    pub fn unpushed(&self, max: usize) -> FLResult<Vec<taipo_git_control::CommitDescr>> {
+        self.store.unpushed(max)
+    }
+    /** whether the path is in the directories checked out */
+    pub fn in_sparse_set(&self, path: &str) -> bool {
+        self.store.in_sparse_set(path)
+    }
+    /** the kinds of item loaded when all the items are loaded (all of them if empty) */
+    pub fn sparse_kinds(&self) -> &[String] {
+        &self.sparse_kinds
+    }
+    /** whether the store has commits that should be pushed */
+    pub fn needs_push(&self) -> bool {
+        self.store.get_needs_push()
+    }
+    /** find all the children of this item, open or not */
+    pub fn search_children(&self, ident: &str) -> FLResult<ItemListEntryList> {
+        self.search.search_children(ident)
     }
     /** find all the children of this item that have open status */
     pub fn search_open_children(&self, ident: &str) -> FLResult<ItemListEntryList> {
@@ -813,17 +2295,65 @@ impl<'a> World {
         Err(fanling_error!("test error"))
     }
 }
+/** number of entries in a page of a list */
+pub(crate) const PAGE_SIZE: usize = 100;
+/** for the button that loads the next page of a list */
+struct ListMore {
+    list: String,
+    offset: usize,
+    limit: usize,
+}
 /** template data for a list of items */
 #[derive(Template)]
 #[template(path = "list.html")]
 struct ListTemplate {
     items: ItemListEntryList,
+    /** the next page, if the list has more entries */
+    more: Option<ListMore>,
+    /** the name of the list, if it can be sorted and its items moved */
+    list: String,
+    /** the orders to choose from (on the first page only) */
+    orders: Vec<OrderChoice>,
+    /** whether the list is in the manual order, so that items can be moved */
+    manual: bool,
+    /** whether the choice of batch operation for the items selected is shown (see [`crate::batch`], on the first page only) */
+    batch: bool,
+}
+/** an order the list views can be sorted into, for the template */
+struct OrderChoice {
+    name: &'static str,
+    label: &'static str,
+    selected: bool,
+}
+impl OrderChoice {
+    fn all(current: crate::ordering::SortOrder) -> Vec<Self> {
+        crate::ordering::SortOrder::ALL
+            .iter()
+            .map(|o| Self {
+                name: o.name(),
+                label: o.label(),
+                selected: *o == current,
+            })
+            .collect()
+    }
 }
 /** template data that should always be refreshed */
 #[derive(Template)]
 #[template(path = "always.html")]
 struct AlwaysTemplate {
     needs_push: bool,
+    repo_name: String,
+    has_other_repos: bool,
+    /** how the repository stands against its remote, in short (see [`fanling_interface::SyncStatus::badge`]) */
+    badge: String,
+}
+/** template data for asking the user to confirm warnings */
+#[derive(Template)]
+#[template(path = "confirm.html", print = "none")]
+struct ConfirmTemplate {
+    warnings: Vec<String>,
+    /** what the user can choose to do instead of saving anyway (if anything) */
+    choices: Vec<crate::item::AvailableAction>,
 }
 /** ActionResponse is the result of an update (or new item) request. */
 #[derive(Eq, PartialEq, Debug)]
@@ -835,6 +2365,13 @@ pub enum ActionResponse {
         /** specific messages */
         specifics: Vec<(String, String)>,
     },
+    /** there are warnings, so the update needs to be confirmed by the user */
+    Unconfirmed {
+        /** the warnings */
+        messages: Vec<String>,
+        /** specific warnings */
+        specifics: Vec<(String, String)>,
+    },
     Success {
         #[cfg(test)]
         /** identifier if created by the action*/
@@ -849,12 +2386,17 @@ impl ActionResponse {
             test_data: HashMap::new(),
         }
     }
-    /** record that a user error has been found */
+    /** record that a user error has been found (any warnings are
+    dropped, as they will be given again once the errors are fixed) */
     pub fn add_error(&mut self, area: &str, m: &str) {
         match self {
             Self::Success {
                 #[cfg(test)]
                     test_data: _,
+            }
+            | Self::Unconfirmed {
+                messages: _,
+                specifics: _,
             } => {
                 let mut ss = Vec::new();
                 ss.push((area.to_owned(), m.to_owned()));
@@ -878,7 +2420,85 @@ impl ActionResponse {
             self.add_error(area, m);
         }
     }
-    /** whether there has been no errors */
+    /** record something that the user should confirm before the update goes ahead */
+    pub fn add_warning(&mut self, area: &str, m: &str) {
+        match self {
+            Self::Success {
+                #[cfg(test)]
+                    test_data: _,
+            } => {
+                *self = Self::Unconfirmed {
+                    messages: vec![m.to_owned()],
+                    specifics: vec![(area.to_owned(), m.to_owned())],
+                };
+            }
+            Self::Unconfirmed {
+                messages,
+                specifics,
+            } => {
+                messages.push(m.to_owned());
+                specifics.push((area.to_owned(), m.to_owned()));
+            }
+            Self::Failure {
+                messages: _,
+                specifics: _,
+            } => {}
+        }
+    }
+    /** check the condition, otherwise give a warning that the user can confirm */
+    pub fn warn(&mut self, cond: bool, area: &str, m: &str) {
+        if !cond {
+            self.add_warning(area, m);
+        }
+    }
+    /** the result once the user has confirmed any warnings */
+    pub fn confirmed(self) -> Self {
+        match self {
+            Self::Unconfirmed {
+                messages: _,
+                specifics: _,
+            } => Self::new(),
+            other => other,
+        }
+    }
+    /** the same, with the messages translated for the user */
+    pub fn translated(self, i18n: &crate::i18n::Translator) -> Self {
+        let tr_all = |messages: Vec<String>,
+                      specifics: Vec<(String, String)>|
+         -> (Vec<String>, Vec<(String, String)>) {
+            (
+                messages.iter().map(|m| i18n.tr(m)).collect(),
+                specifics
+                    .into_iter()
+                    .map(|(area, m)| (area, i18n.tr(&m)))
+                    .collect(),
+            )
+        };
+        match self {
+            Self::Failure {
+                messages,
+                specifics,
+            } => {
+                let (messages, specifics) = tr_all(messages, specifics);
+                Self::Failure {
+                    messages,
+                    specifics,
+                }
+            }
+            Self::Unconfirmed {
+                messages,
+                specifics,
+            } => {
+                let (messages, specifics) = tr_all(messages, specifics);
+                Self::Unconfirmed {
+                    messages,
+                    specifics,
+                }
+            }
+            other => other,
+        }
+    }
+    /** whether there has been no errors (or unconfirmed warnings) */
     pub fn ok(&self) -> bool {
         *self
             == Self::Success {
@@ -886,7 +2506,11 @@ impl ActionResponse {
                 test_data: HashMap::new(),
             }
     }
-    /** user errors */
+    /** whether the update should not go ahead */
+    pub fn is_blocking(&self) -> bool {
+        !matches!(self, Self::Success { .. })
+    }
+    /** user errors (or warnings) */
     pub fn errors(&self) -> Vec<(String, String)> {
         match self {
             Self::Success {
@@ -896,6 +2520,10 @@ impl ActionResponse {
             Self::Failure {
                 messages: _,
                 specifics,
+            }
+            | Self::Unconfirmed {
+                messages: _,
+                specifics,
             } => specifics.clone(),
         }
     }
@@ -909,15 +2537,39 @@ impl ActionResponse {
             Self::Failure {
                 messages,
                 specifics: _,
+            }
+            | Self::Unconfirmed {
+                messages,
+                specifics: _,
             } => messages.join(" "),
         }
     }
-    fn to_response(&self) -> fanling_interface::ResponseResult {
-        let mut response =
-            fanling_interface::Response::new_with_tags(&[("message", &self.overall_message())]);
+    /** convert to a response showing any user errors, or asking for
+    any warnings to be confirmed */
+    pub fn to_response(&self) -> fanling_interface::ResponseResult {
+        self.to_response_with_choices(vec![])
+    }
+    /** the same, but with the warnings answered by choosing one of
+    the actions (whose labels should already be translated) */
+    pub fn to_response_with_choices(
+        &self,
+        choices: Vec<crate::item::AvailableAction>,
+    ) -> fanling_interface::ResponseResult {
+        let message = match self {
+            Self::Unconfirmed { messages, .. } => ConfirmTemplate {
+                warnings: messages.clone(),
+                choices,
+            }
+            .render()?,
+            _ => self.overall_message(),
+        };
+        let mut response = fanling_interface::Response::new_with_tags(&[("message", &message)]);
         for (t, v) in self.errors() {
             response.add_tag(&t, &v);
         }
+        if let Self::Unconfirmed { .. } = self {
+            response.set_confirm_required();
+        }
         // if let Some(td) = self. {
         #[cfg(test)]
         response.set_all_test_data(self.get_test_data());
@@ -931,10 +2583,7 @@ impl ActionResponse {
             Self::Success { test_data: td } => {
                 *td = test_data;
             }
-            Self::Failure {
-                messages: _,
-                specifics: _,
-            } => {}
+            Self::Failure { .. } | Self::Unconfirmed { .. } => {}
         }
     }
     #[cfg(test)]
@@ -942,10 +2591,7 @@ impl ActionResponse {
     pub fn ident(&self) -> Option<String> {
         match self {
             Self::Success { test_data: td } => Some(td.get("ident").unwrap().clone()),
-            Self::Failure {
-                messages: _,
-                specifics: _,
-            } => None,
+            Self::Failure { .. } | Self::Unconfirmed { .. } => None,
         }
     }
     #[cfg(test)]
@@ -953,20 +2599,50 @@ impl ActionResponse {
     pub fn get_test_data(&self) -> HashMap<String, String> {
         match self {
             Self::Success { test_data: td } => td.clone(),
-            Self::Failure {
-                messages: _,
-                specifics: _,
-            } => HashMap::new(),
+            Self::Failure { .. } => HashMap::new(),
+            Self::Unconfirmed { .. } => {
+                let mut td = HashMap::new();
+                td.insert("unconfirmed".to_owned(), self.overall_message());
+                td
+            }
         }
     }
 }
 
+/** the initial HTML page */
+pub fn main_html(
+    interface_type: crate::InterfaceType,
+    serve_assets: bool,
+    math: crate::math::MathRendering,
+    /** the emoji and smart punctuation of the repository (see [`crate::typography`]) */
+    typography: crate::typography::Typography,
+    kinds: Vec<crate::kinds::KindInfo>,
+) -> FLResult<String> {
+    let mt = MainTemplate {
+        interface_type,
+        interface_type_string: format!("{:?}", interface_type),
+        kinds,
+        serve_assets,
+        style_url: crate::assets::url("fanling.css"),
+        script_url: crate::assets::url("code.js"),
+        katex: math == crate::math::MathRendering::Client,
+    };
+    Ok(mt.render()?)
+}
 /* template for initial HTML */
 #[derive(Template)]
 #[template(path = "main.html")]
 struct MainTemplate {
     interface_type: crate::InterfaceType,
     interface_type_string: String,
+    /** the kinds of item, for the "New" buttons */
+    kinds: Vec<crate::kinds::KindInfo>,
+    /** link to the style sheet and script (see [`crate::assets`]) rather than inlining them */
+    serve_assets: bool,
+    style_url: String,
+    script_url: String,
+    /** load KaTeX, which shows the mathematics in the items (see [`crate::math`]) */
+    katex: bool,
 }
 impl Drop for World {
     fn drop(&mut self) {
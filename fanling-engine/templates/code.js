@@ -2,11 +2,62 @@
 License, v. 2.0. If a copy of the MPL was not distributed with this
 file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 console.log("starting scripts");
-window.addEventListener("load", function() {doAction("Start", "", "");});
+// the view session of this window, kept when the page is reloaded (so that it shows the same item)
+var session = (function() {
+    let s = Math.random().toString(36).substring(2);
+    try {
+        if (sessionStorage.getItem("session")) return sessionStorage.getItem("session");
+        sessionStorage.setItem("session", s);
+    } catch (e) {
+        console.log("no session storage: " + e);
+    }
+    return s;
+})();
+window.addEventListener("load", function() {doAction("ShowCurrent", "", "");});
+// carry out one part of a response: the tag may start with an operation such as "append:"
 var setTag = function(tag, text) {
+    let colon = tag.indexOf(":");
+    let op = colon < 0 ? "" : tag.substring(0, colon);
+    if (op == "toast") {
+        show_toast(text);
+        return;
+    }
+    if (op != "") tag = tag.substring(colon + 1);
     var s = document.getElementById(tag);
     if (!s) console.error("no tag called '" + tag + "'");
-    else s.innerHTML = text;
+    else if (op == "value") {
+        if (s.type == "checkbox") s.checked = (text == "true"); else s.value = text;
+    } else if (op == "replace") {
+        s.outerHTML = text;
+    } else {
+        if (op == "append") s.insertAdjacentHTML("beforeend", text); else s.innerHTML = text;
+        let cursor = s.querySelector(".cursor");
+        if (cursor) place_cursor(cursor.dataset.field, parseInt(cursor.dataset.offset));
+        let scroll = s.querySelector(".scrollto");
+        if (scroll) scroll_to(scroll.dataset.target);
+        if (window.renderMathInElement) renderMathInElement(s);
+    }
+};
+// show some text for a few seconds
+var toast_timer = null;
+var show_toast = function(text) {
+    let toast = document.getElementById("toast");
+    toast.textContent = text;
+    toast.hidden = false;
+    if (toast_timer) clearTimeout(toast_timer);
+    toast_timer = setTimeout(function() { toast.hidden = true; }, 3000);
+};
+// show the element with the id (a heading of the item), if it is there
+var scroll_to = function(id) {
+    let elt = document.getElementById(id);
+    if (elt) elt.scrollIntoView();
+};
+// put the cursor at a position in a field (such as where a template had its cursor placeholder)
+var place_cursor = function(field, offset) {
+    let elt = document.getElementById(field);
+    if (!elt || !elt.setSelectionRange) return;
+    elt.focus();
+    elt.setSelectionRange(offset, offset);
 };
 var doAction = function(aVal, tVal, iVal) {
     var c = {
@@ -14,6 +65,30 @@ var doAction = function(aVal, tVal, iVal) {
     };
     invoke(c);
 };
+// add an item from the quick-capture box (when Enter is pressed)
+var quick_add = function(event) {
+    if (event.key != "Enter") return;
+    let box = document.getElementById("quickadd");
+    if (box.value.trim() == "") return;
+    doActionWithIdent("QuickAdd", "", "", box.value);
+    box.value = "";
+};
+// save the page at the URL in the box as an article to read later (when Enter is pressed)
+var save_article = function(event) {
+    if (event.key != "Enter") return;
+    let box = document.getElementById("savearticle");
+    if (box.value.trim() == "") return;
+    doActionWithIdent("SaveArticle", "", "", box.value.trim());
+    box.value = "";
+};
+// highlight the text selected in a bookmark's snapshot, asking for a note (which may be blank)
+var highlight_selection = function(ident) {
+    let quote = window.getSelection().toString();
+    if (quote.trim() == "") return;
+    let note = window.prompt("Note (or leave blank)", "");
+    if (note === null) return;
+    invoke({ t: "Bookmark", i: ident, a: { Highlight: { quote: quote, note: note } } });
+};
 var doActionWithIdent = function(aVal, tVal, iVal, iVal2) {
     var a = {};
     a[aVal] = iVal2;
@@ -23,12 +98,15 @@ var doActionWithIdent = function(aVal, tVal, iVal, iVal2) {
 {% when crate::InterfaceType::PC %}
 // code for PC platform
 var invoke = function(arg) {
+  arg.s = session;
   console.log("\ninvoking from PC with "+JSON.stringify(arg));
   window.external.invoke(JSON.stringify(arg));
+  after_invoke(arg);
 };
 {% when crate::InterfaceType::Android %}
 // android specific code
 var invoke = function(arg) {
+    arg.s = session;
     console.log("\ninvoking from Android with " + JSON.stringify(arg));
     taipo.execute(JSON.stringify(arg));
     console.log("execute done");
@@ -47,8 +125,16 @@ var invoke = function(arg) {
         alert( taipo.response_error());
         console.log("error: " + taipo.response_error());
     }
+    after_invoke(arg);
 };
 {% endmatch %}
+// after an item is shown, render the items likely to be shown next while it is read
+var prefetch_timer = null;
+var after_invoke = function(arg) {
+    if (arg.a != "Show" && arg.a != "Back") return;
+    if (prefetch_timer) clearTimeout(prefetch_timer);
+    prefetch_timer = setTimeout(function() { invoke({ t: "", i: "", a: "Prefetch" }); }, 500);
+};
 var onclick_simple = function(ident, nextop) {
     let type_name = "Simple";
     // let base = {ident: ident, type: type_name };
@@ -76,12 +162,279 @@ var onclick_task = function(ident, nextop) {
         name: document.getElementById("name").value,
         text: document.getElementById("text").value,
         priority: document.getElementById("priority").value,
+        effort: document.getElementById("effort").value,
+        energy: document.getElementById("energy").value,
         context: document.getElementById("context").value,
         deadline: document.getElementById("deadline").value,
+        remind: document.getElementById("remind").value,
         show_after_date: document.getElementById("showafterdate").value
     };
     invoke_action(nextop, base, data, ident,  type_name );
 };
+var onclick_contact = function(ident, nextop) {
+    let type_name = "Contact";
+    let base =  make_base(ident,  type_name);
+    let data = {
+        name: document.getElementById("name").value,
+        emails: document.getElementById("emails").value,
+        phones: document.getElementById("phones").value,
+        address: document.getElementById("address").value,
+        birthday: document.getElementById("birthday").value,
+        anniversary: document.getElementById("anniversary").value,
+        photo: document.getElementById("photo").value,
+        notes: document.getElementById("notes").value
+    };
+    invoke_action(nextop, base, data, ident,  type_name );
+};
+var onclick_log_interaction = function(ident) {
+    let kind = document.getElementById("interaction-kind").value;
+    let note = document.getElementById("interaction-note").value;
+    invoke({ t: "Contact", i: ident, a: {"LogInteraction": [kind, note]}});
+};
+// attach the chosen photo to the contact being edited (or remove it if input is null)
+var onchange_photo = function(input) {
+    let set_photo = function(uri) {
+        document.getElementById("photo").value = uri;
+        let preview = document.getElementById("photo-preview");
+        preview.src = uri;
+        preview.hidden = (uri == "");
+    };
+    if (input == null || input.files.length == 0) {
+        set_photo("");
+        return;
+    }
+    let reader = new FileReader();
+    reader.onload = function() { set_photo(reader.result); };
+    reader.readAsDataURL(input.files[0]);
+};
+// put the contents of the chosen vCard file in the import box
+var onchange_vcard_file = function(input) {
+    if (input.files.length == 0) {
+        return;
+    }
+    let reader = new FileReader();
+    reader.onload = function() { document.getElementById("vcardimport").value = reader.result; };
+    reader.readAsText(input.files[0]);
+};
+// put the contents of the chosen BibTeX file in the import box
+var onchange_bibtex_file = function(input) {
+    if (input.files.length == 0) {
+        return;
+    }
+    let reader = new FileReader();
+    reader.onload = function() { document.getElementById("bibteximport").value = reader.result; };
+    reader.readAsText(input.files[0]);
+};
+// put the contents of the chosen org-mode file in the import box
+var onchange_org_file = function(input) {
+    if (input.files.length == 0) {
+        return;
+    }
+    let reader = new FileReader();
+    reader.onload = function() { document.getElementById("orgimport").value = reader.result; };
+    reader.readAsText(input.files[0]);
+};
+// put the contents of the chosen export of tasks in the import box, and its name as the project
+var onchange_tasks_file = function(input) {
+    if (input.files.length == 0) {
+        return;
+    }
+    let name = input.files[0].name.replace(/\.[^.]*$/, "");
+    let reader = new FileReader();
+    reader.onload = function() {
+        document.getElementById("tasksimport").value = reader.result;
+        let project = document.getElementById("tasksproject");
+        if (project.value == "") {
+            project.value = name;
+        }
+    };
+    reader.readAsText(input.files[0]);
+};
+var onclick_import_tasks = function() {
+    let text = document.getElementById("tasksimport").value;
+    let project = document.getElementById("tasksproject").value;
+    invoke({ t: "", i: "", a: {"ImportTasks": {text: text, project: project}}});
+};
+var onclick_export_bundle = function() {
+    let types = Array.from(document.getElementsByClassName("bundle-type"))
+        .filter(function(c) { return c.checked; })
+        .map(function(c) { return c.value; });
+    let passphrase = document.getElementById("bundle-passphrase").value;
+    invoke({ t: "", i: "", a: {"ExportBundle": {types: types, passphrase: passphrase}}});
+};
+var onclick_import_bundle = function() {
+    let text = document.getElementById("bundleimport").value;
+    let passphrase = document.getElementById("bundle-passphrase").value;
+    invoke({ t: "", i: "", a: {"ImportBundle": {text: text, passphrase: passphrase}}});
+};
+// keep the contents of the chosen bundle file for importing
+var onchange_bundle_file = function(input) {
+    if (input.files.length == 0) {
+        return;
+    }
+    let reader = new FileReader();
+    reader.onload = function() { document.getElementById("bundleimport").value = reader.result; };
+    reader.readAsText(input.files[0]);
+};
+var onclick_export_json = function() {
+    let types = Array.from(document.getElementsByClassName("json-type"))
+        .filter(function(c) { return c.checked; })
+        .map(function(c) { return c.value; });
+    invoke({ t: "", i: "", a: {"ExportJson": {idents: [], types: types}}});
+};
+var onclick_import_json = function() {
+    let text = document.getElementById("jsonimport").value;
+    invoke({ t: "", i: "", a: {"ImportJson": text}});
+};
+// put the contents of the chosen JSON file in the import box
+var onchange_json_file = function(input) {
+    if (input.files.length == 0) {
+        return;
+    }
+    let reader = new FileReader();
+    reader.onload = function() { document.getElementById("jsonimport").value = reader.result; };
+    reader.readAsText(input.files[0]);
+};
+var onclick_checklist = function(ident, nextop) {
+    let type_name = "Checklist";
+    let base =  make_base(ident,  type_name);
+    let data = {
+        name: document.getElementById("name").value,
+        entries: document.getElementById("entries").value
+    };
+    invoke_action(nextop, base, data, ident,  type_name );
+};
+var onclick_event = function(ident, nextop) {
+    let type_name = "Event";
+    let base =  make_base(ident,  type_name);
+    let data = {
+        name: document.getElementById("name").value,
+        start: document.getElementById("start").value,
+        end: document.getElementById("end").value,
+        location: document.getElementById("location").value,
+        recurrence: document.getElementById("recurrence").value,
+        description: document.getElementById("description").value
+    };
+    invoke_action(nextop, base, data, ident,  type_name );
+};
+var onclick_snippet = function(ident, nextop) {
+    let type_name = "Snippet";
+    let base =  make_base(ident,  type_name);
+    let data = {
+        name: document.getElementById("name").value,
+        language: document.getElementById("language").value,
+        code: document.getElementById("code").value
+    };
+    invoke_action(nextop, base, data, ident,  type_name );
+};
+var onclick_bookmark = function(ident, nextop) {
+    let type_name = "Bookmark";
+    let base =  make_base(ident,  type_name);
+    let data = {
+        url: document.getElementById("url").value,
+        name: document.getElementById("name").value,
+        tags: document.getElementById("tags").value,
+        notes: document.getElementById("notes").value
+    };
+    invoke_action(nextop, base, data, ident,  type_name );
+};
+var onclick_reference = function(ident, nextop) {
+    let type_name = "Reference";
+    let base =  make_base(ident,  type_name);
+    let data = {
+        name: document.getElementById("name").value,
+        authors: document.getElementById("authors").value,
+        year: document.getElementById("year").value,
+        published_in: document.getElementById("published_in").value,
+        entry_type: document.getElementById("entry_type").value,
+        doi: document.getElementById("doi").value,
+        url: document.getElementById("url").value,
+        key: document.getElementById("key").value,
+        notes: document.getElementById("notes").value
+    };
+    invoke_action(nextop, base, data, ident,  type_name );
+};
+var onclick_habit = function(ident, nextop) {
+    let type_name = "Habit";
+    let base =  make_base(ident,  type_name);
+    let data = {
+        name: document.getElementById("name").value,
+        per_week: document.getElementById("per_week").value
+    };
+    invoke_action(nextop, base, data, ident,  type_name );
+};
+var onclick_project = function(ident, nextop) {
+    let type_name = "Project";
+    let base =  make_base(ident,  type_name);
+    let data = {
+        name: document.getElementById("name").value,
+        text: document.getElementById("text").value
+    };
+    invoke_action(nextop, base, data, ident,  type_name );
+};
+var onclick_meeting = function(ident, nextop) {
+    let type_name = "Meeting";
+    let base =  make_base(ident,  type_name);
+    let attendees = Array.from(document.getElementById("attendees").selectedOptions)
+        .map(function(o) { return o.value; });
+    let data = {
+        name: document.getElementById("name").value,
+        date: document.getElementById("date").value,
+        attendees: attendees.join(","),
+        notes: document.getElementById("notes").value
+    };
+    invoke_action(nextop, base, data, ident,  type_name );
+};
+var onclick_settings = function() {
+    let vals = {
+        name: document.getElementById("name").value,
+        email: document.getElementById("email").value,
+        template: document.getElementById("template").value,
+        append_device: document.getElementById("append_device").checked ? "true" : "false",
+        generate_index: document.getElementById("generate_index").checked ? "true" : "false",
+        emoji: document.getElementById("emoji").checked ? "true" : "false",
+        smart_punctuation: document.getElementById("smart_punctuation").checked ? "true" : "false",
+        check_secrets: document.getElementById("check_secrets").checked ? "true" : "false",
+        secret_patterns: document.getElementById("secret_patterns").value,
+        retention: document.getElementById("retention").value
+    };
+    invoke({ t: "", i: "", a: {"SaveSettings": vals}});
+};
+var onclick_unlock = function() {
+    invoke({ t: "", i: "", a: {"Unlock": document.getElementById("passphrase").value}});
+};
+var onclick_unlock_item = function(ident, type_name) {
+    invoke({ t: type_name, i: ident, a: {"UnlockItem": document.getElementById("item-passphrase").value}});
+};
+var onclick_suggest = function() {
+    let req = {
+        minutes: parseInt(document.getElementById("minutes").value) || 0,
+        energy: document.getElementById("energy").value,
+        context: document.getElementById("context").value
+    };
+    invoke({ t: "", i: "", a: {"Suggest": req}});
+};
+var onclick_instantiate = function(ident, type_name) {
+    let vals = {};
+    let fields = document.getElementsByClassName("template-var");
+    for (let i = 0; i < fields.length; i++)
+        vals[fields[i].getAttribute("data-name")] = fields[i].value;
+    invoke({ t: type_name, i: ident, a: {"InstantiateWith": vals}});
+};
+var preview_timer = null;
+var onchange_preview = function(text) {
+    if (preview_timer) clearTimeout(preview_timer);
+    preview_timer = setTimeout(function() {
+        invoke({ t: "", i: "", a: {"Preview": text}});
+    }, 300);
+};
+// save the form being edited again, confirming any warnings
+var save_confirmed = false;
+var confirm_save = function() {
+    let marker = document.getElementById("draft");
+    save_confirmed = true;
+    window["onclick_" + marker.dataset.draft](marker.dataset.ident, marker.dataset.nextOp);
+};
 var make_base = function(ident,  type_name) {
     let base = {ident: ident, type: type_name };
     if  (document.getElementById("parent").value != "")
@@ -92,6 +445,16 @@ var make_base = function(ident,  type_name) {
         base.can_be_parent = true;
     if (document.getElementById("canbecontext").checked)
         base.can_be_context = true;
+    if (document.getElementById("istemplate").checked)
+        base.is_template = true;
+    if (document.getElementById("isprivate").checked)
+        base.is_private = true;
+    if  (document.getElementById("passphrase").value != "")
+        base.passphrase = document.getElementById("passphrase").value;
+    if (save_confirmed) {
+        base.confirmed = true;
+        save_confirmed = false;
+    }
     return base;
 }
 var invoke_action = function(nextop, base, data, ident,  type_name ) {
@@ -100,6 +463,32 @@ var invoke_action = function(nextop, base, data, ident,  type_name ) {
     var cmnd = { t: type_name, i: ident, a: action};
     invoke(cmnd);
 }
+// every so often, keep a draft of the form being edited (the "draft" element says which form it is)
+var draft_state = { form: "", saved: "" };
+var save_draft = function() {
+    let marker = document.getElementById("draft");
+    if (!marker) {
+        draft_state.form = "";
+        return;
+    }
+    let form = marker.dataset.draft + " " + marker.dataset.ident;
+    let values = JSON.stringify(Array.from(document.querySelectorAll("input, textarea, select"),
+        function(e) { return e.type == "checkbox" ? e.checked : e.value; }));
+    if (form != draft_state.form) {
+        // just opened, so nothing has been typed yet
+        draft_state = { form: form, saved: values };
+        return;
+    }
+    if (values == draft_state.saved) return;
+    draft_state.saved = values;
+    window["onclick_" + marker.dataset.draft](marker.dataset.ident, "SaveDraft");
+};
+window.setInterval(save_draft, 15000);
+// push the commits made since the last time to the remotes pushed to on save, away from the actions that made them
+window.setInterval(function() { invoke({ t: "", i: "", a: "PushPending" }); }, 30000);
+// post what the hooks have queued, away from the actions that changed the items
+window.setInterval(function() { invoke({ t: "", i: "", a: "DeliverHooks" }); }, 30000);
+document.addEventListener("visibilitychange", function() { if (document.hidden) save_draft(); });
 var onclick_caret = function(elt) {
     console.log("click on caret " + elt);
     if (!elt) console.error("no element"); else console.log("elt is " + elt);
@@ -111,3 +500,17 @@ var onclick_caret = function(elt) {
     elt.classList.toggle("caret-down");
 };
 
+// do the operation chosen to the items selected in a list, in a single commit
+var batch_selected = function() {
+    let idents = Array.from(document.querySelectorAll("input.batch-select:checked"),
+        function(e) { return e.value; });
+    if (idents.length == 0) return;
+    let kind = document.getElementById("batch-operation").value;
+    let value = document.getElementById("batch-value").value.trim();
+    let operation = kind;
+    if (kind != "Close" && kind != "SoftDelete") {
+        operation = {};
+        operation[kind] = value;
+    }
+    invoke({ t: "", i: "", a: { "Batch": { "idents": idents, "operation": operation } } });
+};
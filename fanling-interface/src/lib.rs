@@ -35,6 +35,12 @@ pub trait Engine {
     fn handle_event(&mut self, event: &CycleEvent) -> TPResult<Response>;
     /** the initial HTML web page for when the app is opened */
     fn initial_html(&self) -> TPResult<String>;
+    /** the next `n` (or fewer) notifications to be given, in time
+    order, so that the main program can schedule them with the
+    operating system. They may change after any command, so they
+    should be asked for again (and the ones scheduled before
+    replaced). */
+    fn next_reminders(&mut self, n: usize) -> TPResult<Vec<ScheduledReminder>>;
     // /** get a string value from the engine */
     // fn get_value(&self, key: &str) -> String;
     // /** set a callback */
@@ -42,6 +48,40 @@ pub trait Engine {
     /** a description identifying the engine for use in diagnostic
     traces */
     fn trace_descr(&self) -> String;
+    /** the asset (such as a style sheet, a script or a picture) for a
+    URL that starts with [ASSET_BASE], or `None` if there is no such
+    asset. A main program that can answer requests from the page
+    (as the Android `WebView` can) uses it so that the pages need not
+    have everything inlined. */
+    fn asset(&self, url: &str) -> TPResult<Option<Asset>> {
+        let _ = url;
+        Ok(None)
+    }
+}
+/** the start of the URLs of assets served by the engine (see
+[Engine::asset]). It is an `https` URL, as some web views will not
+load other schemes from the page, but the host does not exist, so the
+requests are never sent over the network. */
+pub const ASSET_BASE: &str = "https://fanling.assets/";
+/** a file served to the page by the engine */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Asset {
+    /** the MIME type, such as `text/css` */
+    pub content_type: String,
+    pub bytes: Vec<u8>,
+}
+/** a file made by the engine (such as an item exported as a PDF),
+given with a [Response] for the main program to share or save, or to
+put on the clipboard */
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputFile {
+    /** a name for the file, such as `shopping.pdf` */
+    pub name: String,
+    /** the MIME type, such as `application/pdf` */
+    pub content_type: String,
+    pub bytes: Vec<u8>,
+    /** whether the file (which is text) is to be put on the clipboard rather than shared or saved */
+    pub for_clipboard: bool,
 }
 /// [Result] type for this package
 pub type TPResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
@@ -57,10 +97,121 @@ pub fn error_response_result(msg: &str) -> ResponseResult {
     trace("getting error response result");
     Ok(Response::new_error_with_tags(&vec![("error", msg)]))
 }
+/** a notification to be given at a time, even if the app is not
+running then (such as for the deadline of a task) */
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScheduledReminder {
+    /** when it is to be given (seconds since the Unix epoch) */
+    pub when: i64,
+    /** the ident of the item it is for, so that the item can be shown
+    when the notification is chosen */
+    pub ident: String,
+    /** the text of the notification */
+    pub text: String,
+}
+/** how the repository stands against its remote, given with each
+[Response] so that the user interface can show a badge for it */
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct SyncStatus {
+    /** whether there is a remote to synchronise with */
+    pub has_remote: bool,
+    /** how many local commits have not been pushed */
+    pub ahead: usize,
+    /** how many commits on the remote have not been merged */
+    pub behind: usize,
+    /** when the repository was last pulled or pushed successfully (seconds since the Unix epoch) */
+    pub last_sync: Option<i64>,
+    /** how many items had conflicting changes in the last merge */
+    pub conflicts: usize,
+    /** the remotes that the last pull or push failed for */
+    pub failed_remotes: Vec<String>,
+}
+impl SyncStatus {
+    /** a short text for the badge (empty if there is nothing to show) */
+    pub fn badge(&self) -> String {
+        let mut parts = vec![];
+        if self.ahead > 0 {
+            parts.push(format!("↑{}", self.ahead));
+        }
+        if self.behind > 0 {
+            parts.push(format!("↓{}", self.behind));
+        }
+        if self.conflicts > 0 {
+            parts.push(format!("⚠{}", self.conflicts));
+        }
+        if !self.failed_remotes.is_empty() {
+            parts.push(format!("✗{}", self.failed_remotes.len()));
+        }
+        parts.join(" ")
+    }
+}
+/** the regions of the main page that a [Response] can update. A
+single response can update several of them (as for the dashboard),
+so that a composite screen needs only one request. */
+pub mod region {
+    /** the main area, showing the item or list being looked at */
+    pub const CONTENT: &str = "content";
+    /** beside the content, for things such as upcoming events */
+    pub const SIDEBAR: &str = "sidebar";
+    /** a line summarising the state of the repository */
+    pub const STATUS: &str = "status";
+    /** notifications such as reminders of birthdays */
+    pub const NOTIFICATIONS: &str = "notifications";
+    /** buttons that are always shown (such as Push or Quit) */
+    pub const ALWAYS: &str = "always";
+    /** for errors */
+    pub const ERROR: &str = "error";
+}
+/** what the user interface is to do with a tag/value pair of a
+[Response]. The operation is given by a prefix of the tag (such as
+`append:list`), so that a user interface that passes the pairs on to
+the `setTag` function of the page needs no changes for it. */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operation {
+    /** replace the contents of the element with the HTML (no prefix) */
+    Content,
+    /** replace the whole element with the HTML */
+    Replace,
+    /** add the HTML to the end of the contents of the element */
+    Append,
+    /** set the value of a single field (or check or uncheck a checkbox for "true" or "false") */
+    Value,
+    /** show the text briefly to the user (the tag is empty) */
+    Toast,
+}
+impl Operation {
+    /** the prefix of a tag for the operation */
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Operation::Content => "",
+            Operation::Replace => "replace:",
+            Operation::Append => "append:",
+            Operation::Value => "value:",
+            Operation::Toast => "toast:",
+        }
+    }
+    /** the operation for a tag of a [Response], and the rest of the tag */
+    pub fn from_tag(tag: &str) -> (Self, &str) {
+        for op in &[
+            Operation::Replace,
+            Operation::Append,
+            Operation::Value,
+            Operation::Toast,
+        ] {
+            if tag.starts_with(op.prefix()) {
+                return (*op, &tag[op.prefix().len()..]);
+            }
+        }
+        (Operation::Content, tag)
+    }
+}
 /** the response from the Engine to the user interface resulting from a command or event */
 #[derive(Default, Clone, Debug)]
 pub struct Response {
-    /** An instruction to, for each pair `(tag, html)`, replace the element identified by `tag` with `html`. The order of the pairs will be significant.  */
+    /** An instruction to, for each pair `(tag, html)`, replace the
+    contents of the element identified by `tag` with `html`, or do
+    some other [Operation] given by a prefix of the tag. The order of
+    the pairs will be significant.  */
     tags: Vec<(String, String)>,
     /** set to true to tell the user interface to shut down. The
     engine should have already saved state or whatever it needs to
@@ -68,6 +219,23 @@ pub struct Response {
     shutdown_required: bool,
     /** whether the response includes an error */
     error: bool,
+    /** the user is being asked to confirm some warnings: if they do,
+    the request should be made again as confirmed */
+    confirm_required: bool,
+    /** what the user interface is being asked to prompt the user for
+    (such as "passphrase" for an encrypted repository, or
+    "item-passphrase" for a private item), if anything. The content of the response
+    is a form for it, but the user interface can prompt in its own way
+    instead. */
+    prompt: Option<String>,
+    /** notifications for the user (such as reminders of birthdays),
+    which the user interface can show in its own way as well as in the
+    content of the response */
+    notifications: Vec<String>,
+    /** how the repository stands against its remote, if the engine has a repository open */
+    sync_status: Option<SyncStatus>,
+    /** files for the user interface to share, save or copy */
+    files: Vec<OutputFile>,
     /** assocated test data if any */
     //   #[cfg(test)]
     test_data: HashMap<String, String>,
@@ -80,6 +248,11 @@ impl Response {
             //  to_clear: vec![],
             shutdown_required: false,
             error: false,
+            confirm_required: false,
+            prompt: None,
+            notifications: vec![],
+            sync_status: None,
+            files: vec![],
             //  #[cfg(test)]
             test_data: HashMap::new(),
         }
@@ -96,6 +269,51 @@ impl Response {
     pub fn add_tag(&mut self, tag: &str, val: &str) {
         self.tags.push((tag.to_owned(), val.to_owned()));
     }
+    /** add a tag value pair for an [Operation] to the response */
+    pub fn add_operation(&mut self, op: Operation, tag: &str, val: &str) {
+        self.add_tag(&format!("{}{}", op.prefix(), tag), val);
+    }
+    /** replace the whole element identified by `tag` with `html` */
+    pub fn replace_element(&mut self, tag: &str, html: &str) {
+        self.add_operation(Operation::Replace, tag, html);
+    }
+    /** add `html` to the end of the element (such as a list) identified by `tag` */
+    pub fn append_to(&mut self, tag: &str, html: &str) {
+        self.add_operation(Operation::Append, tag, html);
+    }
+    /** set the value of the field identified by `id` */
+    pub fn set_field(&mut self, id: &str, value: &str) {
+        self.add_operation(Operation::Value, id, value);
+    }
+    /** show some text briefly to the user, without changing the page */
+    pub fn show_toast(&mut self, text: &str) {
+        self.add_operation(Operation::Toast, "", text);
+    }
+    /** add the response for another region (or regions) to this one,
+    so that several regions are updated by the same response */
+    pub fn merge(&mut self, other: Response) {
+        self.tags.extend(other.tags);
+        self.shutdown_required |= other.shutdown_required;
+        self.error |= other.error;
+        self.confirm_required |= other.confirm_required;
+        if other.prompt.is_some() {
+            self.prompt = other.prompt;
+        }
+        self.notifications.extend(other.notifications);
+        if other.sync_status.is_some() {
+            self.sync_status = other.sync_status;
+        }
+        self.files.extend(other.files);
+        self.test_data.extend(other.test_data);
+    }
+    /** the HTML for a region (the last given for it), if the response updates it */
+    pub fn region(&self, region: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .rev()
+            .find(|(tag, _html)| tag == region)
+            .map(|(_tag, html)| html.as_str())
+    }
     /** convenience method */
     pub fn add_error_tag(&mut self, tag: &str, val: &str) {
         self.add_tag(tag, val);
@@ -144,6 +362,46 @@ impl Response {
     pub fn set_shutdown_required(&mut self) {
         self.shutdown_required = true;
     }
+    /** does the user need to confirm warnings for the request to go ahead? */
+    pub fn is_confirm_required(&self) -> bool {
+        self.confirm_required
+    }
+    /** the user needs to confirm warnings for the request to go ahead */
+    pub fn set_confirm_required(&mut self) {
+        self.confirm_required = true;
+    }
+    /** what the user should be prompted for, if anything */
+    pub fn get_prompt(&self) -> Option<&str> {
+        self.prompt.as_deref()
+    }
+    /** ask the user interface to prompt the user for something (such as "passphrase") */
+    pub fn set_prompt(&mut self, what: &str) {
+        self.prompt = Some(what.to_owned());
+    }
+    /** the notifications for the user */
+    pub fn get_notifications(&self) -> impl Iterator<Item = &String> {
+        self.notifications.iter()
+    }
+    /** add a notification for the user */
+    pub fn add_notification(&mut self, text: &str) {
+        self.notifications.push(text.to_owned());
+    }
+    /** how the repository stands against its remote, if given */
+    pub fn get_sync_status(&self) -> Option<&SyncStatus> {
+        self.sync_status.as_ref()
+    }
+    /** give how the repository stands against its remote */
+    pub fn set_sync_status(&mut self, status: SyncStatus) {
+        self.sync_status = Some(status);
+    }
+    /** the files for the user interface to share, save or copy */
+    pub fn get_files(&self) -> impl Iterator<Item = &OutputFile> {
+        self.files.iter()
+    }
+    /** give a file for the user interface to share, save or copy */
+    pub fn add_file(&mut self, file: OutputFile) {
+        self.files.push(file);
+    }
     /**  whether the response includes an error */
     pub fn is_error(&self) -> bool {
         self.error
@@ -0,0 +1,38 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! keeps the secrets for remotes (HTTPS tokens and ssh key
+passphrases) in the keychain of the operating system (see
+[`taipo_git_control::CredentialProvider`]). */
+use taipo_git_control::{CredentialProvider, NullResult, RepoResult};
+
+/** the service that the secrets are kept for in the keychain */
+const SERVICE: &str = "fanling";
+
+/** the keychain of the operating system */
+#[derive(Debug)]
+pub struct Keychain;
+impl CredentialProvider for Keychain {
+    fn name(&self) -> String {
+        "the keychain".to_owned()
+    }
+    fn get(&self, key: &str) -> RepoResult<Option<String>> {
+        match keyring::Keyring::new(SERVICE, key).get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::KeyringError::NoPasswordFound) => Ok(None),
+            Err(e) => Err(format!("keychain: {}", e).into()),
+        }
+    }
+    fn set(&self, key: &str, secret: &str) -> NullResult {
+        keyring::Keyring::new(SERVICE, key)
+            .set_password(secret)
+            .map_err(|e| format!("keychain: {}", e).into())
+    }
+    fn delete(&self, key: &str) -> NullResult {
+        match keyring::Keyring::new(SERVICE, key).delete_password() {
+            Ok(()) | Err(keyring::KeyringError::NoPasswordFound) => Ok(()),
+            Err(e) => Err(format!("keychain: {}", e).into()),
+        }
+    }
+}
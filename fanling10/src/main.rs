@@ -27,6 +27,8 @@ The main functionality areas of this program are to:
 
 * read configuration parameters from a configuration file (or the command line);
 * create a web view;
+* keep the secrets for remotes in the keychain (see [`keychain`]);
+* restore a backup, or write one (see [`fanling_engine::restore_backup`]);
 * create an [`fanling_interface::Engine`] which does most of the functionality;
 * connect the [`fanling_interface::Engine`] to the web view.
 
@@ -45,12 +47,15 @@ use ansi_term::Style;
 //use log::trace;
 
 use fanling_interface::Engine;
-use std::path::PathBuf;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::thread;
 use std::time::SystemTime;
 use structopt::StructOpt;
 #[cfg(not(target_os = "android"))]
 use web_view::*;
+#[cfg(not(target_os = "android"))]
+mod keychain;
 #[macro_use]
 extern crate quick_error;
 quick_error! {
@@ -112,6 +117,9 @@ pub struct Opt {
     /// prefix for identifiers
     #[structopt(parse(from_str), short = "p", long = "prefix", default_value = "?")]
     uniq_pfx: String,
+    /// how the idents of new items are made (slug, sequential, uuid7 or ulid)
+    #[structopt(long = "ident-scheme", default_value = "slug")]
+    ident_scheme: String,
     /// configuration file (will override all values)
     #[structopt(parse(from_os_str), short = "c", long = "config", default_value = "")]
     config: PathBuf,
@@ -130,10 +138,159 @@ pub struct Opt {
     /// whether to slurp ssh files
     #[structopt(long = "slurp-ssh")]
     slurp_ssh: bool,
+    /// keep the secrets for remotes in memory only (until the program ends), rather than in the keychain
+    #[structopt(long = "no-keychain")]
+    no_keychain: bool,
+    /// weeks start on Sunday (otherwise Monday)
+    #[structopt(long = "sunday-first")]
+    week_starts_sunday: bool,
+    /// format for dates (strftime codes)
+    #[structopt(parse(from_str), long = "date-format", default_value = "%Y-%m-%d")]
+    date_format: String,
+    /// use a 12 hour clock
+    #[structopt(long = "twelve-hour")]
+    twelve_hour: bool,
+    /// decimal separator for numbers
+    #[structopt(long = "decimal-separator", default_value = ".")]
+    decimal_separator: char,
+    /// locale for the text shown (eg "fr"; English if there are no translations for it)
+    #[structopt(parse(from_str), long = "locale", default_value = "en")]
+    locale: String,
+    /// template for commit messages
+    #[structopt(parse(from_str), long = "commit-template", default_value = "{verb} {ident}")]
+    commit_template: String,
+    /// name of this device
+    #[structopt(parse(from_str), long = "device", default_value = "")]
+    device_name: String,
+    /// append the device name to commit messages
+    #[structopt(long = "append-device")]
+    append_device: bool,
+    /// keep a generated README and index with the items, for browsing the repository with other Git tools
+    #[structopt(long = "generate-index")]
+    generate_index: bool,
+    /// key for signing commits: a GPG key id or the path of an SSH private key (blank for no signing)
+    #[structopt(parse(from_str), long = "signing-key", default_value = "")]
+    signing_key: String,
+    /// kind of signing key (gpg or ssh)
+    #[structopt(parse(from_str), long = "signing-format", default_value = "gpg")]
+    signing_format: String,
+    /// allowed signers file that SSH signatures are checked against (blank if they are not to be checked)
+    #[structopt(parse(from_os_str), long = "allowed-signers", default_value = "")]
+    allowed_signers: PathBuf,
+    /// name of the main repository
+    #[structopt(parse(from_str), long = "repo-name", default_value = "main")]
+    repo_name: String,
+    /// another repository to open, as name=path (its database is kept beside it)
+    #[structopt(parse(from_str), long = "other-repo")]
+    other_repos: Vec<String>,
+    /// the items can only be read, and syncing only pulls (as for a shared reference wiki)
+    #[structopt(long = "read-only")]
+    read_only: bool,
+    /// another repository to open read-only, as name=path
+    #[structopt(parse(from_str), long = "read-only-repo")]
+    read_only_repos: Vec<String>,
+    /// include the items in all the repositories in lists
+    #[structopt(long = "cross-repo-search")]
+    cross_repo_search: bool,
+    /// a namespace to check out when cloning (all of them if none are given)
+    #[structopt(parse(from_str), long = "sparse")]
+    sparse: Vec<String>,
+    /// a kind of item to load when cloning (all of them if none are given)
+    #[structopt(parse(from_str), long = "sparse-kind")]
+    sparse_kinds: Vec<String>,
+    /// a url to post items to (or a command, after `!`) when they change, as events=target (eg created,closed=https://example.com/hook)
+    #[structopt(parse(from_str), long = "hook")]
+    hooks: Vec<String>,
+    /// the url of a CalDAV calendar to sync the events with
+    #[structopt(parse(from_str), long = "caldav", default_value = "")]
+    caldav: String,
+    /// the url of a CardDAV address book to sync the contacts with
+    #[structopt(parse(from_str), long = "carddav", default_value = "")]
+    carddav: String,
+    /// the user name for the CalDAV and CardDAV server
+    #[structopt(parse(from_str), long = "dav-user", default_value = "")]
+    dav_user: String,
+    /// the password for the CalDAV and CardDAV server
+    #[structopt(parse(from_str), long = "dav-password", default_value = "")]
+    dav_password: String,
+    /// which is kept when an item has changed both here and on the server: local, remote or newer
+    #[structopt(parse(from_str), long = "dav-prefer", default_value = "newer")]
+    dav_prefer: String,
+    /// the IMAP server (host or host:port) whose unread messages are made into items
+    #[structopt(parse(from_str), long = "imap-server", default_value = "")]
+    imap_server: String,
+    /// the user name for the IMAP server
+    #[structopt(parse(from_str), long = "imap-user", default_value = "")]
+    imap_user: String,
+    /// the password for the IMAP server
+    #[structopt(parse(from_str), long = "imap-password", default_value = "")]
+    imap_password: String,
+    /// the folder (or label) whose unread messages are made into items
+    #[structopt(parse(from_str), long = "imap-folder", default_value = "INBOX")]
+    imap_folder: String,
+    /// how mathematics in items is shown (mathml, or client for KaTeX in the page)
+    #[structopt(long = "math", default_value = "mathml")]
+    math: String,
+    /// encrypt the items (the passphrase is asked for when starting)
+    #[structopt(long = "encrypt")]
+    encrypt: bool,
+    /// keep the items shown recently, for the recent page next time
+    #[structopt(long = "keep-history")]
+    keep_history: bool,
+    /// instead of showing the items, run a soak test for this many hours (only on a test repository)
+    #[structopt(long = "soak", hidden = true)]
+    soak_hours: Option<f64>,
+    /// in a soak test, sync with the remote after this many actions (0 for never)
+    #[structopt(long = "soak-sync", hidden = true, default_value = "0")]
+    soak_sync: usize,
+    /// instead of showing the items, write a backup of the repository and the local files to this file
+    #[structopt(parse(from_os_str), long = "backup")]
+    backup: Option<PathBuf>,
+    /// restore a backup made with --backup into the (new) repository before starting
+    #[structopt(parse(from_os_str), long = "restore")]
+    restore: Option<PathBuf>,
+    /// instead of showing the items, make an item from the e-mail message in this file (- for the standard input)
+    #[structopt(parse(from_os_str), long = "capture-mail")]
+    capture_mail: Option<PathBuf>,
+}
+/** options for another repository, given as `name=path` (the other
+options are the same as for the main repository), which may be read-only */
+fn other_repo(spec: &str, read_only: bool, opt: &Opt) -> Option<fanling_engine::OtherRepoOptions> {
+    let mut parts = spec.splitn(2, '=');
+    let name = parts.next()?.trim().to_owned();
+    let path = PathBuf::from(parts.next()?.trim());
+    Some(fanling_engine::OtherRepoOptions {
+        name,
+        search_options: fanling_engine::SearchOptions {
+            database_path: path.with_extension("db").to_string_lossy().to_string(),
+        },
+        repo_options: taipo_git_control::RepoOptions {
+            path: path.into_boxed_path(),
+            name: opt.name.clone(),
+            email: opt.email.clone(),
+            item_dir: opt.item_dir.clone(),
+            write_to_server: !opt.no_write_to_server,
+            ssh_path: opt.ssh_path.clone().into_boxed_path(),
+            slurp_ssh: opt.slurp_ssh,
+            credentials: Some(credentials(opt)),
+            ..taipo_git_control::RepoOptions::default()
+        },
+        read_only,
+    })
+}
+/** where the secrets for remotes are kept */
+fn credentials(opt: &Opt) -> taipo_git_control::Credentials {
+    if opt.no_keychain {
+        std::sync::Arc::new(taipo_git_control::MemoryProvider::new())
+    } else {
+        std::sync::Arc::new(keychain::Keychain)
+    }
 }
 /** used by [web_view::WebView] */
 struct UserData {
     engine: fanling_engine::FanlingEngine,
+    /** where files given by the engine (such as exported items) are saved */
+    export_dir: PathBuf,
 }
 impl Drop for UserData {
     fn drop(&mut self) {
@@ -155,6 +312,10 @@ fn actual_main() -> NullResult {
     trace(Blue.on(White), "starting main");
 
     let mut opt = Opt::from_args();
+    /* the soak test, backups, restores and captured mail are only asked for on the command line, never in a configuration file */
+    let (soak_hours, soak_sync) = (opt.soak_hours, opt.soak_sync);
+    let (backup, restore) = (opt.backup.clone(), opt.restore.clone());
+    let capture_mail = opt.capture_mail.clone();
     let config_filename = opt
         .config
         .to_str()
@@ -168,8 +329,43 @@ fn actual_main() -> NullResult {
         config.set_default("repo_branch", None as Option<String>)?;
         config.set_default("repo_remote", None as Option<String>)?;
         config.set_default("item_dir", "items")?;
+        config.set_default("ident_scheme", "slug")?;
         config.set_default("no_write_to_server", "false")?;
+        config.set_default("no_keychain", "false")?;
         config.set_default("autolink", "false")?;
+        config.set_default("week_starts_sunday", "false")?;
+        config.set_default("date_format", "%Y-%m-%d")?;
+        config.set_default("twelve_hour", "false")?;
+        config.set_default("decimal_separator", ".")?;
+        config.set_default("locale", "en")?;
+        config.set_default("commit_template", "{verb} {ident}")?;
+        config.set_default("device_name", "")?;
+        config.set_default("append_device", "false")?;
+        config.set_default("generate_index", "false")?;
+        config.set_default("signing_key", "")?;
+        config.set_default("signing_format", "gpg")?;
+        config.set_default("allowed_signers", "")?;
+        config.set_default("repo_name", "main")?;
+        config.set_default("read_only", "false")?;
+        config.set_default("other_repos", Vec::<String>::new())?;
+        config.set_default("read_only_repos", Vec::<String>::new())?;
+        config.set_default("cross_repo_search", "false")?;
+        config.set_default("sparse", Vec::<String>::new())?;
+        config.set_default("sparse_kinds", Vec::<String>::new())?;
+        config.set_default("hooks", Vec::<String>::new())?;
+        config.set_default("caldav", "")?;
+        config.set_default("carddav", "")?;
+        config.set_default("dav_user", "")?;
+        config.set_default("dav_password", "")?;
+        config.set_default("dav_prefer", "newer")?;
+        config.set_default("imap_server", "")?;
+        config.set_default("imap_user", "")?;
+        config.set_default("imap_password", "")?;
+        config.set_default("imap_folder", "INBOX")?;
+        config.set_default("math", "mathml")?;
+        config.set_default("encrypt", "false")?;
+        config.set_default("keep_history", "false")?;
+        config.set_default("soak_sync", "0")?;
         config.merge(config::File::with_name(config_filename))?;
         opt = config.try_into()?;
     }
@@ -191,13 +387,94 @@ fn actual_main() -> NullResult {
             write_to_server: !opt.no_write_to_server,
             ssh_path: opt.ssh_path.clone().into_boxed_path(),
             slurp_ssh: opt.slurp_ssh,
+            signing: if opt.signing_key.is_empty() {
+                None
+            } else {
+                Some(taipo_git_control::SigningKey {
+                    format: taipo_git_control::SigningFormat::parse(&opt.signing_format),
+                    key: opt.signing_key.clone(),
+                })
+            },
+            allowed_signers: if opt.allowed_signers.as_os_str().is_empty() {
+                None
+            } else {
+                Some(opt.allowed_signers.clone().into_boxed_path())
+            },
+            sparse: opt.sparse.clone(),
+            credentials: Some(credentials(&opt)),
         },
         interface_type: fanling_engine::InterfaceType::PC,
         search_options: fanling_engine::SearchOptions {
             database_path: opt.database_path.to_string_lossy().to_string(),
         },
         uniq_pfx: opt.uniq_pfx.clone(),
+        ident_scheme: fanling_engine::IdentScheme::parse(&opt.ident_scheme),
         auto_link: opt.auto_link,
+        format_options: fanling_engine::FormatOptions {
+            week_starts_monday: !opt.week_starts_sunday,
+            date_format: opt.date_format.clone(),
+            use_24_hour: !opt.twelve_hour,
+            decimal_separator: opt.decimal_separator,
+            locale: opt.locale.clone(),
+        },
+        commit_options: fanling_engine::CommitOptions {
+            template: opt.commit_template.clone(),
+            device_name: opt.device_name.clone(),
+            append_device: opt.append_device,
+            generate_index: opt.generate_index,
+        },
+        repos: fanling_engine::ReposOptions {
+            name: opt.repo_name.clone(),
+            others: opt
+                .other_repos
+                .iter()
+                .map(|spec| (spec, false))
+                .chain(opt.read_only_repos.iter().map(|spec| (spec, true)))
+                .filter_map(|(spec, read_only)| other_repo(spec, read_only, &opt))
+                .collect(),
+            cross_repo_search: opt.cross_repo_search,
+        },
+        encryption: fanling_engine::EncryptionOptions {
+            enabled: opt.encrypt,
+            passphrase: None,
+        },
+        keep_history: opt.keep_history,
+        // the web view can not answer requests from the page, so the assets are inlined
+        serve_assets: false,
+        sparse_kinds: opt.sparse_kinds.clone(),
+        read_only: opt.read_only,
+        plugins: vec![],
+        hooks: opt
+            .hooks
+            .iter()
+            .filter_map(|spec| match fanling_engine::HookOptions::parse(spec) {
+                Ok(hook) => Some(hook),
+                Err(e) => {
+                    trace(Red.on(White), &format!("ignoring hook {}: {}", spec, e));
+                    None
+                }
+            })
+            .collect(),
+        dav: match fanling_engine::DavOptions::new(
+            &opt.caldav,
+            &opt.carddav,
+            &opt.dav_user,
+            &opt.dav_password,
+            &opt.dav_prefer,
+        ) {
+            Ok(dav) => dav,
+            Err(e) => {
+                trace(Red.on(White), &format!("not syncing with CalDAV: {}", e));
+                None
+            }
+        },
+        mail: fanling_engine::MailOptions::new(
+            &opt.imap_server,
+            &opt.imap_user,
+            &opt.imap_password,
+            &opt.imap_folder,
+        ),
+        math: fanling_engine::MathRendering::parse(&opt.math),
     };
     //  let mut engine = fanling_engine::FanlingEngine::new(&options)?;
     trace(
@@ -208,12 +485,87 @@ fn actual_main() -> NullResult {
             &options
         ),
     );
+    if let Some(hours) = soak_hours {
+        return run_soak(&options, hours, soak_sync);
+    }
+    if let Some(archive) = restore {
+        restore_backup(&options, &archive)?;
+    }
+    if let Some(path) = backup {
+        let engine = fanling_engine::FanlingEngine::new(&options)?;
+        let manifest = engine.export_backup(&path)?;
+        trace(
+            Blue.on(White),
+            &format!("backed up to {:?}: {:?}", &path, &manifest),
+        );
+        return Ok(());
+    }
+    if let Some(path) = capture_mail {
+        return capture_mail_from(&options, &path);
+    }
     trace(Blue.on(White), "running engine with webview...");
     run_engine_with_webview(options, &opt)?;
     trace(Blue.on(White), "finished running engine with webview");
     //  engine.touch();
     Ok(())
 }
+/** run a soak test (see [`fanling_engine::run_soak`]) instead of the user interface */
+fn run_soak(options: &fanling_engine::EngineOptions, hours: f64, sync_every: usize) -> NullResult {
+    let soak = fanling_engine::SoakOptions {
+        duration: std::time::Duration::from_secs_f64(hours * 3600.0),
+        max_actions: 0,
+        sync_every,
+        seed: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs(),
+    };
+    trace(Blue.on(White), &format!("running soak test {:?}", &soak));
+    let report = fanling_engine::run_soak(options, &soak)?;
+    trace(Blue.on(White), &format!("soak test done: {:?}", &report));
+    match report.failure {
+        Some(failure) => Err(Fanling10Error::new(&format!(
+            "soak test (seed {}) failed after {} actions: {}",
+            report.seed, report.actions, failure
+        ))),
+        None => Ok(()),
+    }
+}
+/** make an item from the e-mail message in the file (or the standard input, if `-`) */
+fn capture_mail_from(options: &fanling_engine::EngineOptions, path: &Path) -> NullResult {
+    let read = if path == Path::new("-") {
+        let mut raw = vec![];
+        std::io::stdin().read_to_end(&mut raw).map(|_| raw)
+    } else {
+        std::fs::read(path)
+    };
+    let raw = read.map_err(|e| Fanling10Error::new(&format!("cannot read {:?}: {}", path, e)))?;
+    let mut engine = fanling_engine::FanlingEngine::new(options)?;
+    engine.capture_mail(&String::from_utf8_lossy(&raw))?;
+    trace(Blue.on(White), &format!("captured mail from {:?}", path));
+    Ok(())
+}
+/** restore a backup into the repository given in the options, with
+the local files beside the database (see [`fanling_engine::restore_backup`]) */
+fn restore_backup(
+    options: &fanling_engine::EngineOptions,
+    archive: &std::path::Path,
+) -> NullResult {
+    let local_dir = std::path::Path::new(&options.search_options.database_path)
+        .parent()
+        .unwrap_or_else(|| std::path::Path::new("."))
+        .to_path_buf();
+    let manifest = fanling_engine::restore_backup(archive, &options.repo_options.path, &local_dir)?;
+    trace(
+        Blue.on(White),
+        &format!("restored {:?}: {:?}", archive, &manifest),
+    );
+    if manifest.encrypted && !options.encryption.enabled {
+        return Err(Fanling10Error::new(
+            "the backup is of an encrypted repository: start again with --encrypt (without --restore)",
+        ));
+    }
+    Ok(())
+}
 fn run_engine_with_webview(
     //  engine: &mut fanling_engine::FanlingEngine,
     options: fanling_engine::EngineOptions,
@@ -223,6 +575,10 @@ fn run_engine_with_webview(
     let verbose = opt.verbose;
     let p = UserData {
         engine: fanling_engine::FanlingEngine::new(&options)?,
+        export_dir: Path::new(&options.search_options.database_path)
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join("exports"),
     };
     {
         let now = SystemTime::now();
@@ -288,6 +644,18 @@ fn handle_response(
                 trace(Red.on(White), "exiting");
                 webview.exit();
             }
+            /* the sync badge goes in the title */
+            if let Some(status) = r.get_sync_status() {
+                let badge = status.badge();
+                let title = if badge.is_empty() {
+                    "Fanling 10".to_owned()
+                } else {
+                    format!("Fanling 10 ({})", badge)
+                };
+                if let Err(e) = webview.set_title(&title) {
+                    trace(Black.on(White), &format!("set title error {:?}", e));
+                }
+            }
             for (t, v) in r.get_tags() {
                 //FIXME: does not like new lines
                 trace(Blue.on(Yellow), &format!("{} to be set to: {}", t, v));
@@ -298,6 +666,28 @@ fn handle_response(
                     trace(Black.on(White), &format!("eval error {:?} for {}", e, js));
                 }
             }
+            /* files are copied to the clipboard or saved, after the engine's own toast */
+            for file in r.get_files() {
+                let js = if file.for_clipboard {
+                    let text = String::from_utf8_lossy(&file.bytes);
+                    format!(
+                        "navigator.clipboard.writeText({});",
+                        serde_json::to_string(&text).unwrap_or_default()
+                    )
+                } else {
+                    let message = match save_file(&webview.user_data().export_dir, file) {
+                        Ok(path) => format!("Saved {}", path.display()),
+                        Err(e) => format!("Could not save {}: {}", file.name, e),
+                    };
+                    format!("setTag({}, {});", js_quote("toast:"), js_quote(&message))
+                };
+                if let Err(e) = webview.eval(&js) {
+                    trace(
+                        Black.on(White),
+                        &format!("eval error {:?} for {}", e, file.name),
+                    );
+                }
+            }
         }
         Err(e) => {
             trace(Red.on(White), "system error during execution");
@@ -316,6 +706,14 @@ fn handle_response(
     }
     trace(Blue.on(Yellow), "handled response.");
 }
+/** save a file given by the engine in the directory, returning where */
+fn save_file(dir: &Path, file: &fanling_interface::OutputFile) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(&file.name);
+    std::fs::write(&path, &file.bytes)?;
+    trace(Blue.on(White), &format!("saved {:?}", path));
+    Ok(path)
+}
 fn trace(style: Style, s: &str) {
     println!("main {}", style.paint(s));
 }
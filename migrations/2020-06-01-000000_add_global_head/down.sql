@@ -0,0 +1,8 @@
+CREATE TABLE global_without_head (
+       id INTEGER NOT NULL DEFAULT(0) PRIMARY KEY,
+       ident_prefix VARCHAR NOT NULL,
+       last_ident INTEGER NOT NULL DEFAULT(0)
+);
+INSERT INTO global_without_head SELECT id, ident_prefix, last_ident FROM global;
+DROP TABLE global;
+ALTER TABLE global_without_head RENAME TO global;
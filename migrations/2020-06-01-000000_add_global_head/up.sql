@@ -0,0 +1 @@
+ALTER TABLE global ADD COLUMN head VARCHAR NOT NULL DEFAULT('');
@@ -29,16 +29,25 @@ extern crate quick_error;
 
 #[macro_use]
 mod error;
+mod credentials;
+mod maintenance;
 mod repo;
 #[macro_use]
 mod shared;
+mod sign;
 #[cfg(test)]
 mod test;
 
+pub use crate::credentials::{
+    passphrase_key, split_token, token_key, CredentialProvider, Credentials, MemoryProvider,
+};
 pub use crate::error::{NullResult, RepoError, RepoResult};
+pub use crate::maintenance::{RepackReport, SizeReport};
 pub use crate::repo::{
     Conflict, ConflictList, FanlingRepository, MergeOutcome, RepoActionRequired,
 };
 pub use crate::shared::{
-    Change, ChangeList, EntryDescr, ObjectOperation, RepoOid, RepoOptions, Tracer,
+    Change, ChangeList, CommitDescr, EntryDescr, ObjectOperation, PushPolicy, RemoteDescr, RepoOid,
+    RepoOptions, Tracer,
 };
+pub use crate::sign::{SigningFormat, SigningKey, Verification};
@@ -0,0 +1,101 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! maintenance of a repository that has been syncing for a long time:
+how big it is, and packing its objects.
+
+libgit2 cannot collect garbage, so [`crate::FanlingRepository::gc`]
+runs the `git` program (which must be installed, so not on a phone).
+[`crate::FanlingRepository::repack`] is done with libgit2 itself: the
+objects that can be reached are written into a new pack, and the loose
+copies of them removed. */
+use crate::error::{NullResult, RepoError, RepoResult};
+use crate::shared::trace;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/** how big a repository is */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SizeReport {
+    /** the size of everything in the `.git` directory (bytes) */
+    pub total_bytes: u64,
+    /** the size of the packs (bytes) */
+    pub pack_bytes: u64,
+    pub packs: usize,
+    /** the size of the loose objects (bytes) */
+    pub loose_bytes: u64,
+    pub loose_objects: usize,
+    pub commits: usize,
+    pub trees: usize,
+    pub blobs: usize,
+    pub tags: usize,
+    /** the largest files in the current version, with their sizes (bytes), largest first */
+    pub largest: Vec<(String, u64)>,
+}
+/** what a repack did */
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RepackReport {
+    /** how many objects were written to the new pack */
+    pub packed: usize,
+    /** how many loose objects were removed, as they are now in the pack */
+    pub removed: usize,
+}
+
+/** the total size of the files in the directory (and those within it), and how many there are */
+pub(crate) fn dir_size(dir: &Path) -> RepoResult<(u64, usize)> {
+    let mut size = 0;
+    let mut count = 0;
+    if !dir.is_dir() {
+        return Ok((0, 0));
+    }
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        if meta.is_dir() {
+            let (dir_bytes, dir_count) = dir_size(&entry.path())?;
+            size += dir_bytes;
+            count += dir_count;
+        } else {
+            size += meta.len();
+            count += 1;
+        }
+    }
+    Ok((size, count))
+}
+/** the loose objects (in `objects/xx/...`), with their ids as hex */
+pub(crate) fn loose_objects(objects_dir: &Path) -> RepoResult<Vec<(String, std::path::PathBuf)>> {
+    let mut loose = vec![];
+    for entry in fs::read_dir(objects_dir)? {
+        let entry = entry?;
+        let prefix = entry.file_name().to_string_lossy().to_string();
+        if prefix.len() != 2 || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+            continue;
+        }
+        for object in fs::read_dir(entry.path())? {
+            let object = object?;
+            let rest = object.file_name().to_string_lossy().to_string();
+            loose.push((format!("{}{}", prefix, rest), object.path()));
+        }
+    }
+    Ok(loose)
+}
+/** run `git gc` in the repository */
+pub(crate) fn run_gc(git_dir: &Path) -> NullResult {
+    trace(&format!("running git gc in {:?}", git_dir));
+    let output = Command::new("git")
+        .arg("--git-dir")
+        .arg(git_dir)
+        .arg("gc")
+        .arg("--quiet")
+        .output()
+        .map_err(|e| repo_error!(&format!("could not run git (is it installed?): {}", e)))?;
+    if !output.status.success() {
+        return Err(repo_error!(&format!(
+            "git gc failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    Ok(())
+}
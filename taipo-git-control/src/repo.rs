@@ -3,18 +3,28 @@ License, v. 2.0. If a copy of the MPL was not distributed with this
 file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 /*! repository support */
+use crate::credentials::{passphrase_key, split_token, token_key, Credentials};
 use crate::error::{NullResult, RepoError, RepoResult};
+use crate::maintenance::{self, RepackReport, SizeReport};
 //#[macro_use]
 use crate::shared::{
-    trace, ChangeList, ChangeWithOid, ChangeWithOidList, EntryDescr, ObjectOperation, RepoOid,
-    RepoOptions, StructureStatus, Timer, Tracer,
+    trace, ChangeList, ChangeWithOid, ChangeWithOidList, CommitDescr, EntryDescr, ObjectOperation,
+    PushPolicy, RemoteDescr, RepoOid, RepoOptions, StructureStatus, Timer, Tracer,
 };
+use crate::sign::{self, SigningKey, Verification};
 use crate::{repo_timer, repo_trace};
 use git2::{build::RepoBuilder, *};
 use git2_credentials::CredentialHandler;
+use std::collections::{BTreeMap, HashSet};
 use std::path::{Path, PathBuf};
 /** name of the SSL key file */
 pub const SSL_KEY_FILE: &str = "id_rsa";
+/** the key in the configuration of a remote for its own ssh key */
+const REMOTE_SSH_KEY: &str = "fanlingsshkey";
+/** the key in the configuration of a remote for when it is pushed to */
+const REMOTE_PUSH: &str = "fanlingpush";
+/** the key in the configuration of a remote for whether it is pulled from */
+const REMOTE_PULL: &str = "fanlingpull";
 use std::convert::TryInto;
 use std::fmt;
 use std::str;
@@ -37,8 +47,10 @@ pub struct FanlingRepository {
     pub path: Box<Path>,
     /** the Git repository */
     repo: Repository,
-    /** the user for signing commits */
-    signature: Signature<'static>,
+    /** the name of the author of commits */
+    name: String,
+    /** the email address of the author of commits */
+    email: String,
     /** does the repo have unpushed changes */
     needs_push: bool,
     /** URL of remote repository (if required) */
@@ -55,6 +67,14 @@ pub struct FanlingRepository {
     ssh_path: Box<Path>,
     /** whether to slurp ssh files */
     slurp_ssh: bool,
+    /** key for signing commits (if they are to be signed) */
+    signing: Option<SigningKey>,
+    /** the allowed signers file for checking SSH signatures (they cannot be checked without one) */
+    allowed_signers: Option<Box<Path>>,
+    /** the directories checked out (all of them if empty) */
+    sparse: Vec<String>,
+    /** where the secrets for remotes are kept */
+    credentials: Option<Credentials>,
 }
 impl FanlingRepository {
     /*  Creating repository */
@@ -63,6 +83,7 @@ impl FanlingRepository {
         repo_trace!("new open");
         trace(&format!("options {:?}", opts));
         let (mut repo, repo_action_required) = Self::new_from_options(opts)?;
+        repo.secure_urls()?;
         let struct_status = repo.check_structure()?;
         if struct_status == StructureStatus::BadHead {
             repo.create_initial()?;
@@ -114,12 +135,21 @@ impl FanlingRepository {
                 .ok_or_else(|| repo_error!("bad path"))?
                 .to_owned(),
             opts.slurp_ssh,
+            opts.credentials.clone(),
             &mut cb,
         )?;
         fetch_options.remote_callbacks(cb);
         let mut builder = RepoBuilder::new();
         builder.bare(false);
         builder.fetch_options(fetch_options);
+        if !opts.sparse.is_empty() {
+            /* only these directories are written out: the rest are read from the objects when needed */
+            let mut checkout = build::CheckoutBuilder::new();
+            for dir in &opts.sparse {
+                checkout.path(dir.trim_matches('/'));
+            }
+            builder.with_checkout(checkout);
+        }
         let url = opts
             .url
             .clone()
@@ -135,9 +165,19 @@ impl FanlingRepository {
     }
     /** create a new repo object */
     fn new(repo: Repository, opts: &RepoOptions) -> RepoResult<FanlingRepository> {
+        /* an identity set for this repository overrides the one in the options */
+        let local_value = |key: &str| -> Option<String> {
+            repo.config()
+                .and_then(|c| c.open_level(ConfigLevel::Local))
+                .and_then(|c| c.get_string(key))
+                .ok()
+        };
+        let name = local_value("user.name").unwrap_or_else(|| opts.name.clone());
+        let email = local_value("user.email").unwrap_or_else(|| opts.email.clone());
         Ok(Self {
             repo,
-            signature: Signature::now(&opts.name, &opts.email)?,
+            name,
+            email,
             needs_push: false,
             url: opts.url.clone(),
             required_remote: opts
@@ -155,8 +195,52 @@ impl FanlingRepository {
             write_to_server: opts.write_to_server,
             ssh_path: opts.ssh_path.clone(),
             slurp_ssh: opts.slurp_ssh,
+            signing: opts.signing.clone(),
+            allowed_signers: opts.allowed_signers.clone(),
+            sparse: opts
+                .sparse
+                .iter()
+                .map(|d| d.trim_matches('/').to_owned())
+                .filter(|d| !d.is_empty())
+                .collect(),
+            credentials: opts.credentials.clone(),
         })
     }
+    /** move any tokens written in the urls of the remotes into the credential provider */
+    fn secure_urls(&mut self) -> NullResult {
+        let credentials = match &self.credentials {
+            Some(credentials) => credentials.clone(),
+            None => return Ok(()),
+        };
+        if let Some(url) = &self.url {
+            let (bare, token) = split_token(url);
+            if let Some(token) = token {
+                credentials.set(&token_key(&bare), &token)?;
+                self.url = Some(bare);
+            }
+        }
+        let names: Vec<String> = dump_error!(self.repo.remotes())
+            .iter()
+            .flatten()
+            .map(str::to_owned)
+            .collect();
+        for name in names {
+            let url = dump_error!(self.repo.find_remote(&name))
+                .url()
+                .unwrap_or("")
+                .to_owned();
+            if let (bare, Some(token)) = split_token(&url) {
+                trace(&format!(
+                    "moving the token for {} to {}",
+                    name,
+                    credentials.name()
+                ));
+                credentials.set(&token_key(&bare), &token)?;
+                dump_error!(self.repo.remote_set_url(&name, &bare));
+            }
+        }
+        Ok(())
+    }
     /* ### Branches and commits */
     // /** check if branch exists/get branch */
     // fn try_get_branch(&self) -> Option<Branch> {
@@ -273,14 +357,36 @@ impl FanlingRepository {
         } else {
             None
         };
-        let commit_oid = dump_error!(self.repo.commit(
-            update_ref,      //  point HEAD to our new commit
-            &self.signature, // author
-            &self.signature, // committer
-            message,         // commit message
-            &new_tree,       // tree
-            parent_commits,  // parents
+        let signature = dump_error!(Signature::now(&self.name, &self.email));
+        let key = match &self.signing {
+            Some(key) => key,
+            None => {
+                return Ok(dump_error!(self.repo.commit(
+                    update_ref,     //  point HEAD to our new commit
+                    &signature,     // author
+                    &signature,     // committer
+                    message,        // commit message
+                    &new_tree,      // tree
+                    parent_commits, // parents
+                )))
+            }
+        };
+        let content = dump_error!(self.repo.commit_create_buffer(
+            &signature,
+            &signature,
+            message,
+            &new_tree,
+            parent_commits,
         ));
+        let content = content
+            .as_str()
+            .ok_or_else(|| repo_error!("commit is not UTF-8"))?;
+        let commit_oid = dump_error!(self.repo.commit_signed(content, &key.sign(content)?, None));
+        if update_ref.is_some() {
+            /* unlike a plain commit, a signed commit does not move the branch */
+            let mut head = dump_error!(self.repo.head());
+            dump_error!(head.set_target(commit_oid, message));
+        }
         Ok(commit_oid)
     }
     /** latest local commit for fetch */
@@ -289,6 +395,13 @@ impl FanlingRepository {
             .find_last_commit()?
             .ok_or_else(|| (repo_error!("no commit")))?)
     }
+    /** the id of the latest local commit, if there is one yet */
+    pub fn head_id(&self) -> RepoResult<Option<String>> {
+        match self.repo.head() {
+            Ok(head) => Ok(head.target().map(|oid| oid.to_string())),
+            Err(_) => Ok(None),
+        }
+    }
     /** latest commit on other branch after fetch */
     pub fn their_commit(&self) -> RepoResult<Commit> {
         let their_reference = self.repo.find_reference("FETCH_HEAD")?;
@@ -470,9 +583,9 @@ impl FanlingRepository {
 
     /** check if tree contains file by path */
     fn has_file(&self, tree: Tree, path: &str) -> bool {
-        match tree.get_name(path) {
-            None => false,
-            Some(n) => n.kind() == Some(ObjectType::Blob),
+        match tree.get_path(Path::new(path)) {
+            Err(_) => false,
+            Ok(n) => n.kind() == Some(ObjectType::Blob),
         }
     }
     /** get the latest top-level tree in the repo (on the required branch) */
@@ -488,10 +601,22 @@ impl FanlingRepository {
     /* ### Other in repo */
     /** changes since specified commit */
     /* ### Remotes and remote (server) repositories */
+    /** whether the path is in the directories checked out (any path is, if they all are) */
+    pub fn in_sparse_set(&self, path: &str) -> bool {
+        self.sparse.is_empty()
+            || self
+                .sparse
+                .iter()
+                .any(|dir| path.starts_with(dir.as_str()) && path[dir.len()..].starts_with('/'))
+    }
     /** does this repo have a remote? */
     pub fn has_remote(&self) -> bool {
         self.url.is_some()
     }
+    /** the name of the remote given in the options, if it has a url */
+    pub fn main_remote(&self) -> Option<&str> {
+        self.url.as_ref().map(|_| self.required_remote.as_str())
+    }
     /** check if have/get remote */
     fn try_get_remote(&self) -> RepoResult<Remote> {
         let url = match self.url.clone() {
@@ -523,29 +648,337 @@ impl FanlingRepository {
     pub fn does_need_pushing(&self) -> bool {
         self.needs_push
     }
+    /** the latest commit known to be on the remote branch (as last fetched or pushed), if any */
+    fn upstream_oid(&self) -> Option<Oid> {
+        let tracking = format!(
+            "refs/remotes/{}/{}",
+            self.required_remote, self.required_branch
+        );
+        self.repo
+            .find_reference(&tracking)
+            .or_else(|_| self.repo.find_reference("FETCH_HEAD"))
+            .ok()
+            .and_then(|r| r.peel_to_commit().ok())
+            .map(|c| c.id())
+    }
+    /** how many local commits are not on the remote branch, and how
+    many commits on it have not been merged (`None` if there is no remote) */
+    pub fn ahead_behind(&self) -> RepoResult<Option<(usize, usize)>> {
+        if self.url.is_none() {
+            return Ok(None);
+        }
+        let head = match self.repo.head().ok().and_then(|h| h.target()) {
+            Some(head) => head,
+            None => return Ok(Some((0, 0))),
+        };
+        Ok(Some(match self.upstream_oid() {
+            Some(upstream) => dump_error!(self.repo.graph_ahead_behind(head, upstream)),
+            None => {
+                let mut walk = dump_error!(self.repo.revwalk());
+                walk.push(head)?;
+                (walk.count(), 0)
+            }
+        }))
+    }
+    /** how big the repository is, with the `max` largest files in the current version */
+    pub fn size_report(&self, max: usize) -> RepoResult<SizeReport> {
+        repo_timer!("size report");
+        let git_dir = self.repo.path();
+        let objects_dir = git_dir.join("objects");
+        let pack_dir = objects_dir.join("pack");
+        let mut packs = 0;
+        if pack_dir.is_dir() {
+            for entry in fs::read_dir(&pack_dir)? {
+                if entry?.path().extension().map_or(false, |e| e == "pack") {
+                    packs += 1;
+                }
+            }
+        }
+        let loose = maintenance::loose_objects(&objects_dir)?;
+        let mut loose_bytes = 0;
+        for (_id, path) in &loose {
+            loose_bytes += fs::metadata(path)?.len();
+        }
+        let mut report = SizeReport {
+            total_bytes: maintenance::dir_size(git_dir)?.0,
+            pack_bytes: maintenance::dir_size(&pack_dir)?.0,
+            packs,
+            loose_bytes,
+            loose_objects: loose.len(),
+            ..SizeReport::default()
+        };
+        let odb = dump_error!(self.repo.odb());
+        let mut seen = HashSet::new();
+        odb.foreach(|oid| {
+            seen.insert(*oid);
+            true
+        })?;
+        for oid in &seen {
+            match odb.read_header(*oid)?.1 {
+                ObjectType::Commit => report.commits += 1,
+                ObjectType::Tree => report.trees += 1,
+                ObjectType::Blob => report.blobs += 1,
+                ObjectType::Tag => report.tags += 1,
+                _ => {}
+            }
+        }
+        if let Ok(head) = self.repo.head() {
+            let tree = head.peel_to_tree()?;
+            let mut files = vec![];
+            tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+                if entry.kind() == Some(ObjectType::Blob) {
+                    files.push((
+                        format!("{}{}", dir, entry.name().unwrap_or("?")),
+                        entry.id(),
+                    ));
+                }
+                TreeWalkResult::Ok
+            })?;
+            let mut sizes = vec![];
+            for (path, oid) in files {
+                sizes.push((path, odb.read_header(oid)?.0 as u64));
+            }
+            sizes.sort_by(|a, b| b.1.cmp(&a.1));
+            sizes.truncate(max);
+            report.largest = sizes;
+        }
+        Ok(report)
+    }
+    /** write the objects that can be reached from the branches into a
+    new pack, and remove the loose copies of them */
+    pub fn repack(&self) -> RepoResult<RepackReport> {
+        repo_timer!("repack");
+        let mut walk = dump_error!(self.repo.revwalk());
+        walk.push_glob("refs/heads/*")?;
+        walk.push_glob("refs/remotes/*")?;
+        let mut reachable = HashSet::new();
+        let mut commits = vec![];
+        for oid in walk {
+            commits.push(oid?);
+        }
+        for oid in &commits {
+            reachable.insert(*oid);
+            let tree = self.repo.find_commit(*oid)?.tree()?;
+            self.mark_tree(&tree, &mut reachable)?;
+        }
+        let mut builder = dump_error!(self.repo.packbuilder());
+        let mut walk = dump_error!(self.repo.revwalk());
+        for oid in commits {
+            walk.push(oid)?;
+        }
+        builder.insert_walk(&mut walk)?;
+        let objects_dir = self.repo.path().join("objects");
+        builder.write(&objects_dir.join("pack"), 0)?;
+        let mut report = RepackReport {
+            packed: builder.written(),
+            removed: 0,
+        };
+        trace(&format!("packed {} objects", report.packed));
+        for (id, path) in maintenance::loose_objects(&objects_dir)? {
+            if Oid::from_str(&id).map_or(false, |oid| reachable.contains(&oid)) {
+                fs::remove_file(path)?;
+                report.removed += 1;
+            }
+        }
+        trace(&format!("removed {} loose objects", report.removed));
+        Ok(report)
+    }
+    /** add the tree, and everything in it, to the objects seen (passing over trees already seen) */
+    fn mark_tree(&self, tree: &Tree, seen: &mut HashSet<Oid>) -> NullResult {
+        if !seen.insert(tree.id()) {
+            return Ok(());
+        }
+        for entry in tree.iter() {
+            if entry.kind() == Some(ObjectType::Tree) {
+                self.mark_tree(&self.repo.find_tree(entry.id())?, seen)?;
+            } else {
+                seen.insert(entry.id());
+            }
+        }
+        Ok(())
+    }
+    /** collect the garbage in the repository, with the `git` program */
+    pub fn gc(&self) -> NullResult {
+        repo_timer!("gc");
+        maintenance::run_gc(self.repo.path())
+    }
+    /** the local commits that are not on the remote branch, newest first (at most `max` of them) */
+    pub fn unpushed(&self, max: usize) -> RepoResult<Vec<CommitDescr>> {
+        repo_trace!("listing unpushed commits");
+        if self.head_id()?.is_none() {
+            return Ok(vec![]);
+        }
+        let mut walk = dump_error!(self.repo.revwalk());
+        walk.push_head()?;
+        if let Some(upstream) = self.upstream_oid() {
+            walk.hide(upstream)?;
+        }
+        walk.set_sorting(Sort::TIME)?;
+        let mut commits = vec![];
+        for oid in walk.take(max) {
+            let commit = self.repo.find_commit(oid?)?;
+            commits.push(Self::describe_commit(&commit));
+        }
+        Ok(commits)
+    }
     /** fetch from server */
     pub fn fetch(&mut self) -> NullResult {
-        repo_timer!("fetch repo");
         assert!(self.url.is_some(), "fetching but no remote repo");
+        let name = self.required_remote.clone();
+        self.fetch_from(&name)
+    }
+    /** fetch from one of the remotes (to be merged from `FETCH_HEAD`) */
+    pub fn fetch_from(&mut self, name: &str) -> NullResult {
+        repo_timer!("fetch repo");
         let mut fetch_options = FetchOptions::new();
         let mut cb = git2::RemoteCallbacks::new();
         Self::set_remote_callbacks(
-            &self
-                .ssh_path
-                .to_str()
-                .ok_or_else(|| repo_error!("bad path"))?
-                .to_owned(),
+            &self.ssh_key_for(name)?,
             self.slurp_ssh,
+            self.credentials.clone(),
             &mut cb,
         )?;
         fetch_options.remote_callbacks(cb);
-        trace(&format!("finding remote: {}...", &self.required_remote));
-        let mut remote = dump_error!(self.repo.find_remote(&self.required_remote));
+        trace(&format!("finding remote: {}...", name));
+        let mut remote = dump_error!(self.repo.find_remote(name));
         trace(&format!("fetching (branch: {})...", &self.required_branch));
         dump_error!(remote.fetch(&[&self.required_branch], Some(&mut fetch_options), None));
         trace("fetched.");
         Ok(())
     }
+    /** the ssh key to use for the remote: its own, or else the one in the options */
+    fn ssh_key_for(&self, name: &str) -> RepoResult<String> {
+        match self.remote_config(name, REMOTE_SSH_KEY)? {
+            Some(key) if !key.trim().is_empty() => Ok(key.trim().to_owned()),
+            _ => Ok(self
+                .ssh_path
+                .to_str()
+                .ok_or_else(|| repo_error!("bad path"))?
+                .to_owned()),
+        }
+    }
+    /** a value in the configuration of a remote */
+    fn remote_config(&self, name: &str, key: &str) -> RepoResult<Option<String>> {
+        self.config_value(&format!("remote.{}.{}", name, key))
+    }
+    /** the remotes, the one in the options first */
+    pub fn remotes(&self) -> RepoResult<Vec<RemoteDescr>> {
+        let mut remotes = vec![];
+        if let Some(url) = &self.url {
+            remotes.push(self.describe_remote(&self.required_remote, url)?);
+        }
+        for name in dump_error!(self.repo.remotes()).iter().flatten() {
+            if name == self.required_remote {
+                continue;
+            }
+            let url = dump_error!(self.repo.find_remote(name))
+                .url()
+                .unwrap_or("")
+                .to_owned();
+            remotes.push(self.describe_remote(name, &url)?);
+        }
+        Ok(remotes)
+    }
+    /** describe a remote from its configuration */
+    fn describe_remote(&self, name: &str, url: &str) -> RepoResult<RemoteDescr> {
+        Ok(RemoteDescr {
+            name: name.to_owned(),
+            url: url.to_owned(),
+            ssh_key: self.remote_config(name, REMOTE_SSH_KEY)?.unwrap_or_default(),
+            push: PushPolicy::parse(&self.remote_config(name, REMOTE_PUSH)?.unwrap_or_default()),
+            pull: self
+                .remote_config(name, REMOTE_PULL)?
+                .map_or(true, |p| p.trim() != "false"),
+        })
+    }
+    /** add a remote, or change one (the url of the remote in the options is not changed) */
+    pub fn set_remote(&mut self, remote: &RemoteDescr) -> NullResult {
+        repo_trace!("setting remote");
+        if !Remote::is_valid_name(&remote.name) {
+            return Err(repo_error!("bad remote name"));
+        }
+        if remote.name != self.required_remote {
+            if self.repo.find_remote(&remote.name).is_ok() {
+                dump_error!(self.repo.remote_set_url(&remote.name, &remote.url));
+            } else {
+                dump_error!(self.repo.remote(&remote.name, &remote.url));
+            }
+        }
+        let key = |k: &str| format!("remote.{}.{}", remote.name, k);
+        self.set_config_value(&key(REMOTE_SSH_KEY), remote.ssh_key.trim())?;
+        self.set_config_value(&key(REMOTE_PUSH), remote.push.name())?;
+        self.set_config_value(&key(REMOTE_PULL), if remote.pull { "true" } else { "false" })?;
+        Ok(())
+    }
+    /** remove a remote (but not the one in the options) */
+    pub fn delete_remote(&mut self, name: &str) -> NullResult {
+        repo_trace!("deleting remote");
+        if name == self.required_remote {
+            return Err(repo_error!("cannot delete the remote in the options"));
+        }
+        dump_error!(self.repo.remote_delete(name));
+        Ok(())
+    }
+    /** the name of the credential provider, if there is one */
+    pub fn credential_provider(&self) -> Option<String> {
+        self.credentials.as_ref().map(|c| c.name())
+    }
+    /** the key for the secret used with the remote: its HTTPS token,
+    or else the passphrase of its ssh key */
+    pub fn credential_key(&self, name: &str) -> RepoResult<String> {
+        let url = match &self.url {
+            Some(url) if name == self.required_remote => url.clone(),
+            _ => dump_error!(self.repo.find_remote(name))
+                .url()
+                .unwrap_or("")
+                .to_owned(),
+        };
+        Ok(if url.starts_with("https:") || url.starts_with("http:") {
+            token_key(&url)
+        } else {
+            passphrase_key(&self.ssh_key_for(name)?)
+        })
+    }
+    /** the credential provider, or an error if there is none */
+    fn provider(&self) -> RepoResult<&Credentials> {
+        self.credentials
+            .as_ref()
+            .ok_or_else(|| repo_error!("there is nowhere to keep secrets"))
+    }
+    /** whether there is a secret kept for the remote */
+    pub fn has_secret(&self, name: &str) -> RepoResult<bool> {
+        match &self.credentials {
+            Some(credentials) => Ok(credentials.get(&self.credential_key(name)?)?.is_some()),
+            None => Ok(false),
+        }
+    }
+    /** keep the secret (token or passphrase) for the remote */
+    pub fn set_secret(&self, name: &str, secret: &str) -> NullResult {
+        self.provider()?.set(&self.credential_key(name)?, secret)
+    }
+    /** forget the secret for the remote */
+    pub fn forget_secret(&self, name: &str) -> NullResult {
+        self.provider()?.delete(&self.credential_key(name)?)
+    }
+    /** check that the remote can be reached with its credentials */
+    pub fn test_remote(&self, name: &str) -> NullResult {
+        repo_trace!("testing remote");
+        let mut remote = if name == self.required_remote {
+            self.try_get_remote()?
+        } else {
+            dump_error!(self.repo.find_remote(name))
+        };
+        let mut cb = git2::RemoteCallbacks::new();
+        Self::set_remote_callbacks(
+            &self.ssh_key_for(name)?,
+            self.slurp_ssh,
+            self.credentials.clone(),
+            &mut cb,
+        )?;
+        remote.connect_auth(Direction::Fetch, Some(cb), None)?;
+        remote.disconnect()?;
+        Ok(())
+    }
     /** merge the versions and determine the status (no change/fast forward/conflict) */
     pub fn merge(&mut self) -> RepoResult<MergeOutcome> {
         repo_trace!("merge");
@@ -728,59 +1161,59 @@ impl FanlingRepository {
         force: bool,
     ) -> NullResult {
         trace("preparing to push...");
-        repo_timer!("push repo");
         {
-            repo_trace!("pushing to remote");
-            let mut remote: Remote = match self.try_get_remote() {
+            let remote: Remote = match self.try_get_remote() {
                 Ok(r) => r,
                 Err(_) => return Err(repo_error!("no remote")),
             };
-            trace("authenticating......");
-            let mut cb = git2::RemoteCallbacks::new();
-            Self::set_remote_callbacks(
-                &self
-                    .ssh_path
-                    .to_str()
-                    .ok_or_else(|| repo_error!("bad path"))?
-                    .to_owned(),
-                self.slurp_ssh,
-                &mut cb,
-            )?;
-            trace("connecting...");
-            remote.connect_auth(Direction::Push, Some(cb), None)?;
-            trace("connected, preparing...");
-            trace("authenticating......");
-            let mut cb = git2::RemoteCallbacks::new();
-            Self::set_remote_callbacks(
-                &self
-                    .ssh_path
-                    .to_str()
-                    .ok_or_else(|| repo_error!("bad path"))?
-                    .to_owned(),
-                self.slurp_ssh,
-                &mut cb,
-            )?;
-            let mut push_options = PushOptions::new();
-            push_options.remote_callbacks(cb);
-            trace("pushing...");
-            let base_refspec = format!(
-                "refs/heads/{}:refs/heads/{}",
-                self.required_branch, self.required_branch
-            );
-            let refspec = (if force { "+" } else { "" }).to_owned() + &base_refspec;
-            trace(&format!("actually pushing {})...", refspec.as_str(),));
-            remote.push(&[refspec.as_str()], Some(&mut push_options))?;
-            trace("actually pushed.");
+            self.push_remote(remote, &self.required_remote, force)?;
             //     self.needs_push = false;
         }
         trace("after push, clearing needs push...");
         self.needs_push = false;
         Ok(())
     }
+    /** push changes to one of the remotes */
+    pub fn push_to(&mut self, name: &str, force: bool) -> NullResult {
+        if name == self.required_remote {
+            return self.push(force);
+        }
+        trace(&format!("preparing to push to {}...", name));
+        let remote = dump_error!(self.repo.find_remote(name));
+        self.push_remote(remote, name, force)
+    }
+    /** push the branch to the remote */
+    fn push_remote(&self, mut remote: Remote, name: &str, force: bool) -> NullResult {
+        repo_timer!("push repo");
+        repo_trace!("pushing to remote");
+        let ssh_key = self.ssh_key_for(name)?;
+        trace("authenticating......");
+        let mut cb = git2::RemoteCallbacks::new();
+        Self::set_remote_callbacks(&ssh_key, self.slurp_ssh, self.credentials.clone(), &mut cb)?;
+        trace("connecting...");
+        remote.connect_auth(Direction::Push, Some(cb), None)?;
+        trace("connected, preparing...");
+        trace("authenticating......");
+        let mut cb = git2::RemoteCallbacks::new();
+        Self::set_remote_callbacks(&ssh_key, self.slurp_ssh, self.credentials.clone(), &mut cb)?;
+        let mut push_options = PushOptions::new();
+        push_options.remote_callbacks(cb);
+        trace("pushing...");
+        let base_refspec = format!(
+            "refs/heads/{}:refs/heads/{}",
+            self.required_branch, self.required_branch
+        );
+        let refspec = (if force { "+" } else { "" }).to_owned() + &base_refspec;
+        trace(&format!("actually pushing {})...", refspec.as_str(),));
+        remote.push(&[refspec.as_str()], Some(&mut push_options))?;
+        trace("actually pushed.");
+        Ok(())
+    }
     /** set the remote callbacks for a repo access. In particular, set up the SSL credentials to access the repo. */
     fn set_remote_callbacks(
         ssh_path: &String,
         slurp_ssh: bool,
+        credentials: Option<Credentials>,
         cb: &mut RemoteCallbacks,
     ) -> NullResult {
         trace(&format!(
@@ -793,6 +1226,11 @@ impl FanlingRepository {
         let mut try_count: i8 = 0;
         const MAX_TRIES: i8 = 5;
         let ssh_path_copy = ssh_path.clone();
+        let passphrase = match &credentials {
+            Some(credentials) => credentials.get(&passphrase_key(ssh_path))?,
+            None => None,
+        };
+        let mut tried_token = false;
         cb.credentials(move |url, username, allowed| {
             trace(&format!(
                 "{}: looking for credential (url {}, user {:?}, type {:?}) {}",
@@ -813,7 +1251,12 @@ impl FanlingRepository {
                     "no slurp: username is {}, public is {:?}, private is {:?}",
                     &username, &publickey, &privatekey
                 ));
-                let cred = Cred::ssh_key(username, Some(&publickey), &privatekey, None);
+                let cred = Cred::ssh_key(
+                    username,
+                    Some(&publickey),
+                    &privatekey,
+                    passphrase.as_deref(),
+                );
                 return cred;
             }
             if allowed.contains(CredentialType::SSH_MEMORY) && slurp_ssh {
@@ -822,9 +1265,25 @@ impl FanlingRepository {
                 let publickey = slurp::read_all_to_string(publickey_fn).expect("bad key file");
                 let privatekey_fn = ssh_path_copy.clone();
                 let privatekey = slurp::read_all_to_string(privatekey_fn).expect("bad key file");
-                let cred = Cred::ssh_key_from_memory(username, Some(&publickey), &privatekey, None);
+                let cred = Cred::ssh_key_from_memory(
+                    username,
+                    Some(&publickey),
+                    &privatekey,
+                    passphrase.as_deref(),
+                );
                 return cred;
             }
+            if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) && !tried_token {
+                let token = credentials
+                    .as_ref()
+                    .and_then(|c| c.get(&token_key(url)).ok())
+                    .flatten();
+                if let Some(token) = token {
+                    trace("using the token from the credential provider");
+                    tried_token = true;
+                    return Cred::userpass_plaintext(username.unwrap_or("git"), &token);
+                }
+            }
             trace(&format!("look for credential {:?}", allowed));
             try_count += 1;
             if try_count > MAX_TRIES {
@@ -911,12 +1370,29 @@ impl FanlingRepository {
             .ok_or_else(|| repo_error!("no items dir"))?;
         Self::describe_tree(&old_subtree, "actually_do_changes:old subtree");
         trace("actually_do_changes: building new subtree...");
-        let mut new_subtree_builder = dump_error!(self.repo.treebuilder(Some(&old_subtree)));
-        let messages = dump_error!(Self::apply_changes_to_item_treebuilder(
-            &mut new_subtree_builder,
-            changes
+        let messages: Vec<String> = changes
+            .iter()
+            .map(|c: &ChangeWithOid| c.change.descr.clone())
+            .collect();
+        let split_paths: Vec<Vec<&str>> = changes
+            .iter()
+            .map(|c| {
+                c.change
+                    .path
+                    .split('/')
+                    .filter(|part| !part.is_empty())
+                    .collect()
+            })
+            .collect();
+        let changes_in_tree: Vec<(&[&str], &ChangeWithOid)> = split_paths
+            .iter()
+            .map(|parts| parts.as_slice())
+            .zip(changes.iter())
+            .collect();
+        let new_subtree_oid = dump_error!(self.apply_changes_to_tree(
+            Some(&old_subtree),
+            &changes_in_tree
         ));
-        let new_subtree_oid = new_subtree_builder.write()?;
         trace(&format!(
             "actually_do_changes: new subtree {}",
             new_subtree_oid
@@ -940,47 +1416,82 @@ impl FanlingRepository {
         trace("actually done changes.");
         Ok(())
     }
-    /** apply the changes to a tree builder for items */
-    fn apply_changes_to_item_treebuilder(
-        tree_builder: &mut TreeBuilder,
-        changes_with_oids: &ChangeWithOidList,
-    ) -> Result<Vec<String>, RepoError> {
-        repo_trace!("applying changes to tree builder");
-        let messages: Vec<String> = changes_with_oids
-            .iter()
-            .map(|c: &ChangeWithOid| c.change.descr.clone())
-            .collect();
-        trace("applying changes...");
-        for c in changes_with_oids.iter() {
-            trace(&format!("applying change {:?} to tree...", &c.change));
-            let path = Path::new(&c.change.path);
-            match &c.change.op {
-                ObjectOperation::Add(_data)
-                | ObjectOperation::Modify(_data)
-                | ObjectOperation::Fix(_data) => {
-                    // let repoid = self.notify_blob(data.as_bytes())?;
-
-                    let oid = c.oid.to_oid()?;
-                    //   trace("actually_do_changes: j");
-                    let entry = tree_builder.insert(path, oid, 0o100644 /* regular */);
-                    match entry {
-                        Err(e) => {
-                            trace(&format!("insert error is {:?}", e));
-                            return Err(repo_error!(&format!("git error: {:?}", e)));
-                        }
-                        Ok(e) => trace(&format!("entry is {:?}", e.id())),
-                    }
-                }
-                ObjectOperation::Delete => tree_builder.remove(path)?,
-                _ => {
+    /** the tree made by applying the changes to a tree (or to an
+    empty tree), each with its path split into the directories and the
+    file name. The changes in a directory are applied to its subtree
+    (so that items can be kept in directories, as for namespaces), and
+    a directory that is left empty is removed. */
+    fn apply_changes_to_tree(
+        &self,
+        tree: Option<&Tree>,
+        changes: &[(&[&str], &ChangeWithOid)],
+    ) -> RepoResult<Oid> {
+        let mut tree_builder = dump_error!(self.repo.treebuilder(tree));
+        let mut in_dirs: BTreeMap<&str, Vec<(&[&str], &ChangeWithOid)>> = BTreeMap::new();
+        for (parts, c) in changes {
+            match *parts {
+                [name] => Self::apply_change_to_treebuilder(&mut tree_builder, name, c)?,
+                [dir, rest @ ..] => in_dirs.entry(*dir).or_default().push((rest, *c)),
+                [] => {
                     return Err(repo_error!(&format!(
-                        "change type {:?} not implemented",
-                        c.change.op
+                        "no file name in path for change {:?}",
+                        &c.change
                     )))
                 }
+            }
+        }
+        for (dir, dir_changes) in in_dirs {
+            let subtree = match tree.and_then(|t| t.get_name(dir)) {
+                Some(entry) if entry.kind() == Some(ObjectType::Tree) => {
+                    Some(dump_error!(self.repo.find_tree(entry.id())))
+                }
+                _ => None,
             };
+            let subtree_oid = self.apply_changes_to_tree(subtree.as_ref(), &dir_changes)?;
+            if dump_error!(self.repo.find_tree(subtree_oid)).is_empty() {
+                trace(&format!("directory {} is now empty", dir));
+                if tree_builder.get(dir)?.is_some() {
+                    tree_builder.remove(dir)?;
+                }
+            } else {
+                Self::insert_directory(&mut tree_builder, dir, subtree_oid)?;
+            }
         }
-        Ok(messages)
+        Ok(dump_error!(tree_builder.write()))
+    }
+    /** apply a change to the file with the name in a tree builder */
+    fn apply_change_to_treebuilder(
+        tree_builder: &mut TreeBuilder,
+        name: &str,
+        c: &ChangeWithOid,
+    ) -> NullResult {
+        trace(&format!("applying change {:?} to tree...", &c.change));
+        match &c.change.op {
+            ObjectOperation::Add(_data)
+            | ObjectOperation::Modify(_data)
+            | ObjectOperation::Fix(_data) => {
+                // let repoid = self.notify_blob(data.as_bytes())?;
+
+                let oid = c.oid.to_oid()?;
+                //   trace("actually_do_changes: j");
+                let entry = tree_builder.insert(name, oid, 0o100644 /* regular */);
+                match entry {
+                    Err(e) => {
+                        trace(&format!("insert error is {:?}", e));
+                        return Err(repo_error!(&format!("git error: {:?}", e)));
+                    }
+                    Ok(e) => trace(&format!("entry is {:?}", e.id())),
+                }
+            }
+            ObjectOperation::Delete => tree_builder.remove(name)?,
+            _ => {
+                return Err(repo_error!(&format!(
+                    "change type {:?} not implemented",
+                    c.change.op
+                )))
+            }
+        };
+        Ok(())
     }
     /** */
     pub fn add_oids_to_changelist(&mut self, changelist: ChangeList) -> ChangeWithOidList {
@@ -1009,29 +1520,205 @@ impl FanlingRepository {
             .ok_or_else(|| repo_error!("no subtree"))?;
         trace("listing all - iterating...");
         trace(&format!("subtree has {} entries", subtree.len()));
-        let all: Vec<EntryDescr> = subtree
-            .iter()
-            .map(|te| {
-                trace(&format!("grabbing {:?} ({:?})", te.name(), te.kind()));
-                EntryDescr {
-                    oid: RepoOid::from_oid(&te.id()),
-                    path: te.name().unwrap_or("??").to_string(),
-                    kind: format!("{:?}", te.kind()),
-                    blob: str::from_utf8(
-                        te.to_object(&self.repo)
-                            .expect("could not convert to object")
-                            .peel_to_blob()
-                            .expect("could not peel to blob")
-                            .content(),
-                    )
-                    .expect("bad entry descr")
-                    .to_owned(),
-                }
-            })
-            .collect();
+        /* the files in directories are listed too, with their paths from the items directory */
+        let mut all: Vec<EntryDescr> = vec![];
+        dump_error!(subtree.walk(TreeWalkMode::PreOrder, |dir, te| {
+            if te.kind() != Some(ObjectType::Blob) {
+                return TreeWalkResult::Ok;
+            }
+            trace(&format!("grabbing {}{:?} ({:?})", dir, te.name(), te.kind()));
+            all.push(EntryDescr {
+                oid: RepoOid::from_oid(&te.id()),
+                path: format!("{}{}", dir, te.name().unwrap_or("??")),
+                kind: format!("{:?}", te.kind()),
+                blob: String::from_utf8_lossy(
+                    te.to_object(&self.repo)
+                        .expect("could not convert to object")
+                        .peel_to_blob()
+                        .expect("could not peel to blob")
+                        .content(),
+                )
+                .into_owned(),
+            });
+            TreeWalkResult::Ok
+        }));
         trace(&format!("listed all, {} found.", all.len()));
         Ok(all)
     }
+    /** the commits reachable from HEAD, most recent first (at most `max` of them) */
+    pub fn history(&self, max: usize) -> RepoResult<Vec<CommitDescr>> {
+        repo_trace!("listing history");
+        let mut walk = dump_error!(self.repo.revwalk());
+        walk.push_head()?;
+        walk.set_sorting(Sort::TIME)?;
+        let mut commits = vec![];
+        for oid in walk.take(max) {
+            let commit = self.repo.find_commit(oid?)?;
+            commits.push(Self::describe_commit(&commit));
+        }
+        trace(&format!("{} commits in history", commits.len()));
+        Ok(commits)
+    }
+    /** the commits that changed the item at the path, most recent first (at most `max` of them) */
+    pub fn path_history(&self, path: &str, max: usize) -> RepoResult<Vec<CommitDescr>> {
+        repo_trace!(&format!("listing history of {}", path));
+        let path = Path::new(&self.item_dir).join(path);
+        let entry_id = |commit: &Commit| -> Option<Oid> {
+            commit
+                .tree()
+                .ok()
+                .and_then(|t| t.get_path(&path).ok())
+                .map(|e| e.id())
+        };
+        let mut walk = dump_error!(self.repo.revwalk());
+        walk.push_head()?;
+        walk.set_sorting(Sort::TIME)?;
+        let mut commits = vec![];
+        for oid in walk {
+            if commits.len() >= max {
+                break;
+            }
+            let commit = self.repo.find_commit(oid?)?;
+            let id = entry_id(&commit);
+            let parent_id = commit.parent(0).ok().and_then(|p| entry_id(&p));
+            if id.is_some() && id != parent_id {
+                commits.push(Self::describe_commit(&commit));
+            }
+        }
+        trace(&format!("{} commits in history of path", commits.len()));
+        Ok(commits)
+    }
+    /** the blob at the path (within the items directory) in a commit (given as hex), if it was there */
+    pub fn blob_at_commit(&self, id: &str, path: &str) -> RepoResult<Option<Vec<u8>>> {
+        repo_trace!(&format!("getting blob (path {:?}) at {}", path, id));
+        let commit = self.repo.find_commit(Oid::from_str(id)?)?;
+        let path = Path::new(&self.item_dir).join(path);
+        let entry = match commit.tree()?.get_path(&path) {
+            Ok(entry) => entry,
+            Err(e) if e.code() == ErrorCode::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Some(self.repo.find_blob(entry.id())?.content().to_vec()))
+    }
+    /** the paths (within the items directory) of the items added or
+    changed between a commit (given as hex) and HEAD */
+    pub fn changed_paths(&self, id: &str) -> RepoResult<Vec<String>> {
+        repo_trace!(&format!("listing paths changed since {}", id));
+        let old_tree = self.repo.find_commit(Oid::from_str(id)?)?.tree()?;
+        let new_tree = self.our_commit()?.tree()?;
+        let diff = self
+            .repo
+            .diff_tree_to_tree(Some(&old_tree), Some(&new_tree), None)?;
+        let item_dir = Path::new(&self.item_dir);
+        let paths: Vec<String> = diff
+            .deltas()
+            .filter(|d| d.status() != Delta::Deleted)
+            .filter_map(|d| d.new_file().path().map(Path::to_path_buf))
+            .filter_map(|p| {
+                p.strip_prefix(item_dir)
+                    .ok()
+                    .map(|p| p.to_string_lossy().into_owned())
+            })
+            .collect();
+        trace(&format!("{} paths changed", paths.len()));
+        Ok(paths)
+    }
+    /** when each path (within the items directory) was last added or
+    changed (seconds since the Unix epoch), looking at no more than
+    `max` commits back from HEAD */
+    pub fn last_changed(&self, max: usize) -> RepoResult<BTreeMap<String, i64>> {
+        repo_trace!("listing when paths were last changed");
+        let mut walk = dump_error!(self.repo.revwalk());
+        walk.push_head()?;
+        walk.set_sorting(Sort::TIME)?;
+        let item_dir = Path::new(&self.item_dir);
+        let mut changed = BTreeMap::new();
+        for oid in walk.take(max) {
+            let commit = self.repo.find_commit(oid?)?;
+            let parent_tree = match commit.parent(0) {
+                Ok(parent) => Some(parent.tree()?),
+                Err(_) => None,
+            };
+            let diff =
+                self.repo
+                    .diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+            for delta in diff.deltas().filter(|d| d.status() != Delta::Deleted) {
+                if let Some(path) = delta.new_file().path() {
+                    if let Ok(path) = path.strip_prefix(item_dir) {
+                        /* the walk is newest first, so the first time seen is the latest */
+                        changed
+                            .entry(path.to_string_lossy().into_owned())
+                            .or_insert_with(|| commit.time().seconds());
+                    }
+                }
+            }
+        }
+        trace(&format!("{} paths with changes", changed.len()));
+        Ok(changed)
+    }
+    /** move HEAD back to a commit (given as hex), discarding the
+    commits after it (as when the result of a merge is not valid) */
+    pub fn reset_to(&mut self, id: &str) -> NullResult {
+        repo_trace!(&format!("resetting to {}", id));
+        let commit = self.repo.find_commit(Oid::from_str(id)?)?;
+        self.repo.reset(commit.as_object(), ResetType::Soft, None)?;
+        Ok(())
+    }
+    /** describe a commit for a history */
+    fn describe_commit(commit: &Commit) -> CommitDescr {
+        CommitDescr {
+            id: commit.id().to_string(),
+            when: commit.time().seconds(),
+            offset_minutes: commit.time().offset_minutes(),
+            author: commit.author().name().unwrap_or("").to_owned(),
+            message: commit.message().unwrap_or("").to_owned(),
+        }
+    }
+    /** whether the commit (given as hex) is signed, and if so whether the signature is good */
+    pub fn verify_commit(&self, id: &str) -> RepoResult<Verification> {
+        let oid = Oid::from_str(id)?;
+        match self.repo.extract_signature(&oid, None) {
+            Ok((signature, data)) => {
+                let commit = self.repo.find_commit(oid)?;
+                let identity = commit.committer().email().unwrap_or("").to_owned();
+                Ok(sign::verify(
+                    &signature,
+                    &data,
+                    &identity,
+                    self.allowed_signers.as_deref(),
+                ))
+            }
+            Err(e) if e.code() == ErrorCode::NotFound => Ok(Verification::Unsigned),
+            Err(e) => Err(e.into()),
+        }
+    }
+    /** the name and email address used as the author of commits */
+    pub fn identity(&self) -> (String, String) {
+        (self.name.clone(), self.email.clone())
+    }
+    /** set the author of commits, saving it in the repository's configuration */
+    pub fn set_identity(&mut self, name: &str, email: &str) -> NullResult {
+        self.set_config_value("user.name", name)?;
+        self.set_config_value("user.email", email)?;
+        self.name = name.to_owned();
+        self.email = email.to_owned();
+        Ok(())
+    }
+    /** a value from the repository's own configuration (ignoring the user's global configuration) */
+    pub fn config_value(&self, key: &str) -> RepoResult<Option<String>> {
+        let config = dump_error!(self.repo.config()?.open_level(ConfigLevel::Local));
+        match config.get_string(key) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+    /** set a value in the repository's own configuration */
+    pub fn set_config_value(&mut self, key: &str, value: &str) -> NullResult {
+        let mut config = dump_error!(self.repo.config()?.open_level(ConfigLevel::Local));
+        dump_error!(config.set_str(key, value));
+        Ok(())
+    }
     /** print out some debug info about a tree */
     pub(crate) fn describe_tree(tree: &Tree, descr: &str) {
         let mut descrs: Vec<String> = vec![];
@@ -1205,4 +1892,11 @@ impl MergeOutcome {
             Self::Merged(ix) | Self::Conflict(ix) => Some(ix),
         }
     }
+    /** how many paths have conflicting changes */
+    pub fn num_conflicts(&self) -> RepoResult<usize> {
+        match self {
+            Self::Conflict(ix) => Ok(ix.conflicts()?.count()),
+            _ => Ok(0),
+        }
+    }
 }
@@ -8,13 +8,14 @@ file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 use crate::error::*;
 
 use crate::repo::SSL_KEY_FILE;
+use crate::sign::SigningKey;
 use git2::Oid;
 use log::trace;
 use std::fmt;
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 /** data for creating a new [`FanlingRepository`] */
 pub struct RepoOptions {
     /** path for repository location */
@@ -37,6 +38,14 @@ pub struct RepoOptions {
     pub ssh_path: Box<Path>,
     /** whether to slurp ssh files */
     pub slurp_ssh: bool,
+    /** key for signing commits (if they are to be signed) */
+    pub signing: Option<SigningKey>,
+    /** the allowed signers file (as for `gpg.ssh.allowedSignersFile`) that SSH signatures are checked against */
+    pub allowed_signers: Option<Box<Path>>,
+    /** the directories to check out when cloning (all of them if empty) */
+    pub sparse: Vec<String>,
+    /** where the secrets for remotes are kept (see [`crate::CredentialProvider`]) */
+    pub credentials: Option<crate::Credentials>,
 }
 impl RepoOptions {
     pub fn new() -> Self {
@@ -56,6 +65,10 @@ impl Default for RepoOptions {
             write_to_server: false,
             ssh_path: PathBuf::from(SSL_KEY_FILE).into_boxed_path(), /* ?? */
             slurp_ssh: false,
+            signing: None,
+            allowed_signers: None,
+            sparse: vec![],
+            credentials: None,
         }
     }
 }
@@ -171,6 +184,56 @@ pub struct EntryDescr {
     pub kind: String,
     pub blob: String,
 }
+/** data about a commit as retrieved from git */
+#[derive(Debug, Clone)]
+pub struct CommitDescr {
+    /** the commit id (as hex) */
+    pub id: String,
+    /** when the commit was made (seconds since the Unix epoch) */
+    pub when: i64,
+    /** the committer's offset from UTC, in minutes */
+    pub offset_minutes: i32,
+    /** the author's name */
+    pub author: String,
+    /** the commit message */
+    pub message: String,
+}
+/** when a remote is pushed to */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PushPolicy {
+    /** after each change is committed */
+    OnSave,
+    /** only when a push is asked for (including when quitting) */
+    Manual,
+}
+impl PushPolicy {
+    /** the policy as kept in the configuration */
+    pub fn name(self) -> &'static str {
+        match self {
+            PushPolicy::OnSave => "onsave",
+            PushPolicy::Manual => "manual",
+        }
+    }
+    /** the policy from the configuration (manual if not known) */
+    pub fn parse(name: &str) -> Self {
+        match name.trim() {
+            "onsave" => PushPolicy::OnSave,
+            _ => PushPolicy::Manual,
+        }
+    }
+}
+/** a remote repository, and how it is synced */
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteDescr {
+    /** the name of the remote in git */
+    pub name: String,
+    pub url: String,
+    /** the ssh key for this remote (empty for the one in the options) */
+    pub ssh_key: String,
+    pub push: PushPolicy,
+    /** whether it is pulled from when syncing */
+    pub pull: bool,
+}
 /** time an operation and output start and end messages */
 pub struct Timer {
     start: SystemTime,
@@ -0,0 +1,181 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*! signing commits and checking their signatures.
+
+As with git itself, the work is done by the `gpg` or `ssh-keygen`
+programs, which must be installed. An SSH signature is only good if
+its key is given for the committer in the allowed signers file; with
+no such file it cannot be checked. */
+use crate::error::{RepoError, RepoResult};
+use crate::shared::trace;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+use std::time::SystemTime;
+
+/** the kind of key used to sign commits */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SigningFormat {
+    Gpg,
+    Ssh,
+}
+impl SigningFormat {
+    /** the format from a description such as `ssh` (GPG if not known) */
+    pub fn parse(s: &str) -> Self {
+        if s.trim().eq_ignore_ascii_case("ssh") {
+            SigningFormat::Ssh
+        } else {
+            SigningFormat::Gpg
+        }
+    }
+}
+
+/** a key used to sign commits */
+#[derive(Debug, Clone, PartialEq)]
+pub struct SigningKey {
+    pub format: SigningFormat,
+    /** a GPG key id, or the path of an SSH private key file */
+    pub key: String,
+}
+impl SigningKey {
+    /** sign the content of a commit, returning the (armoured) signature */
+    pub(crate) fn sign(&self, content: &str) -> RepoResult<String> {
+        trace(&format!("signing commit with {:?}", self));
+        let output = match self.format {
+            SigningFormat::Gpg => run("gpg", &["--batch", "-bsau", &self.key], content)?,
+            SigningFormat::Ssh => run(
+                "ssh-keygen",
+                &["-Y", "sign", "-n", "git", "-f", &self.key],
+                content,
+            )?,
+        };
+        if !output.status.success() {
+            return Err(repo_error!(&format!(
+                "could not sign commit: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        Ok(String::from_utf8(output.stdout)?)
+    }
+}
+
+/** whether a commit is signed, and if so whether the signature is good */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Verification {
+    Unsigned,
+    Good,
+    Bad,
+    /** signed, but the signature could not be checked (eg the key is not known) */
+    Unknown,
+}
+impl fmt::Display for Verification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Verification::Unsigned => "unsigned",
+            Verification::Good => "good signature",
+            Verification::Bad => "bad signature",
+            Verification::Unknown => "cannot check signature",
+        })
+    }
+}
+
+/** check the signature of some signed data, by the signer with the
+identity (an email address) if it is an SSH signature */
+pub(crate) fn verify(
+    signature: &[u8],
+    data: &[u8],
+    identity: &str,
+    allowed_signers: Option<&Path>,
+) -> Verification {
+    match try_verify(signature, data, identity, allowed_signers) {
+        Ok(v) => v,
+        Err(e) => {
+            trace(&format!("could not verify signature: {:?}", e));
+            Verification::Unknown
+        }
+    }
+}
+fn try_verify(
+    signature: &[u8],
+    data: &[u8],
+    identity: &str,
+    allowed_signers: Option<&Path>,
+) -> RepoResult<Verification> {
+    let is_ssh = signature.starts_with(b"-----BEGIN SSH SIGNATURE");
+    let allowed_signers = match allowed_signers {
+        Some(path) => path.to_string_lossy().to_string(),
+        None if is_ssh => {
+            trace("no allowed signers file to check the SSH signature against");
+            return Ok(Verification::Unknown);
+        }
+        None => "".to_owned(),
+    };
+    let sig_path = std::env::temp_dir().join(format!(
+        "taipo-{}-{}.sig",
+        std::process::id(),
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_nanos()
+    ));
+    fs::write(&sig_path, signature)?;
+    let path = sig_path.to_string_lossy().to_string();
+    let data = String::from_utf8_lossy(data);
+    let output = if is_ssh {
+        run(
+            "ssh-keygen",
+            &[
+                "-Y",
+                "verify",
+                "-f",
+                &allowed_signers,
+                "-I",
+                identity,
+                "-n",
+                "git",
+                "-s",
+                &path,
+            ],
+            &data,
+        )
+    } else {
+        run("gpg", &["--batch", "--status-fd=1", "--verify", &path, "-"], &data)
+    };
+    let _ = fs::remove_file(&sig_path);
+    let output = output?;
+    let status = String::from_utf8_lossy(&output.stdout);
+    Ok(if is_ssh {
+        if output.status.success() {
+            Verification::Good
+        } else {
+            Verification::Bad
+        }
+    } else if status.contains("[GNUPG:] GOODSIG") {
+        Verification::Good
+    } else if status.contains("[GNUPG:] NO_PUBKEY") {
+        Verification::Unknown
+    } else {
+        Verification::Bad
+    })
+}
+
+/** run a program, giving it some input */
+fn run(program: &str, args: &[&str], input: &str) -> RepoResult<Output> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    {
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| repo_error!("no input for signing program"))?;
+        stdin.write_all(input.as_bytes())?;
+    }
+    Ok(child.wait_with_output()?)
+}